@@ -0,0 +1,82 @@
+//! Information about the build of zung, collected at compile time by `build.rs`. Printed with
+//! `zung about` and useful to attach when reporting issues.
+
+use std::collections::BTreeMap;
+use std::fmt::{self, Display};
+
+use serde::Serialize;
+
+/// Version of the zung binary.
+pub const VERSION: &str = env!("CARGO_PKG_VERSION");
+
+/// Short hash of the git commit the binary was built from, or `unknown` outside a git checkout.
+pub const GIT_HASH: &str = env!("ZUNG_GIT_HASH");
+
+/// The cargo profile used for the build.
+pub const PROFILE: &str = env!("ZUNG_PROFILE");
+
+/// The target triple the binary was built for.
+pub const TARGET: &str = env!("ZUNG_TARGET");
+
+/// Comma separated list of the enabled cargo features.
+const FEATURES: &str = env!("ZUNG_FEATURES");
+
+#[derive(Debug, Serialize)]
+pub struct BuildInfo {
+    pub version: &'static str,
+    pub git_hash: &'static str,
+    pub profile: &'static str,
+    pub target: &'static str,
+    pub features: Vec<&'static str>,
+    /// Versions of the zung crates compiled into the binary.
+    pub crates: BTreeMap<&'static str, &'static str>,
+}
+
+impl BuildInfo {
+    /// Returns the information about the running binary.
+    pub fn current() -> Self {
+        BuildInfo {
+            version: VERSION,
+            git_hash: GIT_HASH,
+            profile: PROFILE,
+            target: TARGET,
+            features: FEATURES.split(',').filter(|f| !f.is_empty()).collect(),
+            crates: BTreeMap::from([
+                ("zung_mini", zung_mini::VERSION),
+                ("zung_parsers", zung_parsers::VERSION),
+                ("zung_torrent", zung_torrent::VERSION),
+            ]),
+        }
+    }
+}
+
+impl Display for BuildInfo {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        writeln!(f, "zung {} ({})", self.version, self.git_hash)?;
+        writeln!(f, "Profile:  {}", self.profile)?;
+        writeln!(f, "Target:   {}", self.target)?;
+        if self.features.is_empty() {
+            writeln!(f, "Features: none")?;
+        } else {
+            writeln!(f, "Features: {}", self.features.join(", "))?;
+        }
+        write!(f, "Crates:")?;
+        for (name, version) in &self.crates {
+            write!(f, "\n  {name} {version}")?;
+        }
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn json_contains_versions() {
+        let json = serde_json::to_value(BuildInfo::current()).unwrap();
+        assert_eq!(json["version"], VERSION);
+        assert_eq!(json["crates"]["zung_torrent"], zung_torrent::VERSION);
+        assert!(json["features"].is_array());
+    }
+}
@@ -0,0 +1,237 @@
+//! Maps the errors returned by the commands to exit codes and renders them for the terminal.
+//!
+//! The commands return [`anyhow::Error`]s. The typed errors found in the chain of causes decide
+//! the [`Failure`] reported to the shell, and some of them come with a hint on how to fix the
+//! problem.
+
+use std::io::{self, ErrorKind};
+use std::process::ExitCode;
+
+use anstyle::{AnsiColor, Color, Style};
+use zung_parsers::bencode;
+use zung_torrent::net::connect::ResolveError;
+use zung_torrent::net::http::StatusError;
+use zung_torrent::sources::TrackerError;
+use zung_torrent::storage::InsufficientSpace;
+
+/// The kind of failure of a command, reported to the shell as the exit code.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Failure {
+    /// Any failure without a more specific kind.
+    Other = 1,
+
+    /// The input file is missing, unreadable or is not what the command expects.
+    InputFile = 2,
+
+    /// A network request failed.
+    Network = 3,
+
+    /// The data could not be parsed or encoded in the requested format.
+    Parse = 4,
+}
+
+impl From<Failure> for ExitCode {
+    fn from(failure: Failure) -> Self {
+        ExitCode::from(failure as u8)
+    }
+}
+
+impl Failure {
+    /// Finds the kind of failure from the first typed error in the chain of causes.
+    pub fn of(error: &anyhow::Error) -> Self {
+        error
+            .chain()
+            .find_map(|cause| {
+                if let Some(e) = cause.downcast_ref::<io::Error>() {
+                    Some(Self::of_io(e))
+                } else if let Some(e) = cause.downcast_ref::<bencode::Error>() {
+                    match e {
+                        bencode::Error::IoErr(e) => Some(Self::of_io(e)),
+                        _ => Some(Failure::Parse),
+                    }
+                } else if let Some(e) = cause.downcast_ref::<reqwest::Error>() {
+                    Some(Self::of_reqwest(e))
+                } else if cause.is::<TrackerError>()
+                    || cause.is::<StatusError>()
+                    || cause.is::<ResolveError>()
+                    || cause.is::<tokio::time::error::Elapsed>()
+                {
+                    Some(Failure::Network)
+                } else if cause.is::<serde_json::Error>()
+                    || cause.is::<serde_yaml::Error>()
+                    || cause.is::<toml::ser::Error>()
+                    || cause.is::<toml::de::Error>()
+                {
+                    Some(Failure::Parse)
+                } else {
+                    None
+                }
+            })
+            .unwrap_or(Failure::Other)
+    }
+
+    fn of_io(error: &io::Error) -> Self {
+        match error.kind() {
+            ErrorKind::ConnectionRefused
+            | ErrorKind::ConnectionReset
+            | ErrorKind::ConnectionAborted
+            | ErrorKind::NotConnected
+            | ErrorKind::HostUnreachable
+            | ErrorKind::NetworkUnreachable
+            | ErrorKind::NetworkDown
+            | ErrorKind::AddrInUse
+            | ErrorKind::AddrNotAvailable
+            | ErrorKind::BrokenPipe
+            | ErrorKind::TimedOut => Failure::Network,
+            // The resolver reports its failures without a more specific kind.
+            ErrorKind::Other
+                if error
+                    .get_ref()
+                    .is_some_and(|inner| inner.is::<ResolveError>()) =>
+            {
+                Failure::Network
+            }
+            ErrorKind::NotFound
+            | ErrorKind::PermissionDenied
+            | ErrorKind::AlreadyExists
+            | ErrorKind::IsADirectory
+            | ErrorKind::NotADirectory
+            | ErrorKind::DirectoryNotEmpty
+            | ErrorKind::ReadOnlyFilesystem
+            | ErrorKind::StorageFull
+            | ErrorKind::FileTooLarge
+            | ErrorKind::InvalidFilename => Failure::InputFile,
+            ErrorKind::InvalidData | ErrorKind::UnexpectedEof => Failure::Parse,
+            _ => Failure::Other,
+        }
+    }
+
+    fn of_reqwest(error: &reqwest::Error) -> Self {
+        if error.is_timeout()
+            || error.is_connect()
+            || error.is_status()
+            || error.is_request()
+            || error.is_body()
+        {
+            Failure::Network
+        } else if error.is_decode() {
+            Failure::Parse
+        } else {
+            Failure::Other
+        }
+    }
+}
+
+/// Returns the hints on how to fix the error, if any are known.
+pub fn hints(error: &anyhow::Error) -> Vec<&'static str> {
+    let mut hints = Vec::new();
+    for cause in error.chain() {
+        if cause.is::<toml::ser::Error>() {
+            hints.push("TOML cannot represent every bencode value, try `--format json` instead");
+        } else if cause
+            .downcast_ref::<io::Error>()
+            .is_some_and(|e| e.kind() == ErrorKind::NotFound)
+        {
+            hints.push("check that the path exists and is spelled correctly");
+        } else if cause.is::<tokio::time::error::Elapsed>() {
+            hints.push("the server did not answer in time, check your connection and retry");
+        } else if cause.is::<InsufficientSpace>() {
+            hints.push("free some space, pick another directory with `--output` or pass `--force`");
+        }
+    }
+    hints.dedup();
+    hints
+}
+
+/// Prints the error, its causes and the [`hints`] to stderr.
+pub fn render(error: &anyhow::Error) {
+    let red = Style::new()
+        .bold()
+        .fg_color(Some(Color::Ansi(AnsiColor::Red)));
+    let cyan = Style::new()
+        .bold()
+        .fg_color(Some(Color::Ansi(AnsiColor::Cyan)));
+
+    eprintln!("{red}error:{red:#} {error}");
+    for cause in error.chain().skip(1) {
+        eprintln!("  caused by: {cause}");
+    }
+    for hint in hints(error) {
+        eprintln!("{cyan}hint:{cyan:#} {hint}");
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use anyhow::Context;
+
+    #[test]
+    fn failure_from_cause() {
+        let missing: anyhow::Error = io::Error::from(ErrorKind::NotFound).into();
+        assert_eq!(Failure::of(&missing), Failure::InputFile);
+
+        let refused = Err::<(), _>(io::Error::from(ErrorKind::ConnectionRefused))
+            .context("Unable to announce")
+            .unwrap_err();
+        assert_eq!(Failure::of(&refused), Failure::Network);
+
+        let parse: anyhow::Error = zung_parsers::bencode::parse(b"i12").unwrap_err().into();
+        assert_eq!(Failure::of(&parse), Failure::Parse);
+
+        assert_eq!(Failure::of(&anyhow::anyhow!("no type")), Failure::Other);
+    }
+
+    #[test]
+    fn network_failures() {
+        for kind in [ErrorKind::HostUnreachable, ErrorKind::NetworkUnreachable] {
+            let error: anyhow::Error = io::Error::from(kind).into();
+            assert_eq!(Failure::of(&error), Failure::Network);
+        }
+
+        let status = zung_torrent::net::http::HttpResponse {
+            status: 404,
+            body: Default::default(),
+        }
+        .error_for_status("http://tracker.example/announce")
+        .context("Unable to scrape")
+        .unwrap_err();
+        assert_eq!(Failure::of(&status), Failure::Network);
+
+        let interrupted: anyhow::Error = io::Error::from(ErrorKind::Interrupted).into();
+        assert_eq!(Failure::of(&interrupted), Failure::Other);
+    }
+
+    #[tokio::test]
+    async fn resolve_failure() {
+        // Without a port the address is rejected by the resolver itself.
+        let error: anyhow::Error = zung_torrent::net::connect::resolve("tracker.example")
+            .await
+            .unwrap_err()
+            .into();
+        assert_eq!(Failure::of(&error), Failure::Network);
+    }
+
+    #[test]
+    fn toml_hint() {
+        let value = zung_parsers::bencode::Value::List(vec![
+            zung_parsers::bencode::Value::Integer(1),
+            zung_parsers::bencode::Value::Integer(2),
+        ]);
+        let error: anyhow::Error = toml::to_string_pretty(&value).unwrap_err().into();
+        assert_eq!(Failure::of(&error), Failure::Parse);
+        assert_eq!(hints(&error).len(), 1);
+    }
+
+    #[test]
+    fn space_hint() {
+        let error: anyhow::Error = InsufficientSpace {
+            dir: "downloads".into(),
+            required: 2048,
+            available: 1024,
+        }
+        .into();
+        assert_eq!(Failure::of(&error), Failure::Other);
+        assert!(hints(&error)[0].contains("--force"));
+    }
+}
@@ -0,0 +1,262 @@
+//! The usage examples of the subcommands, shown by their `--help` and in the man
+//! pages generated by `zung gen-man`.
+//!
+//! The examples are kept here rather than in the doc comments of each crate so that the whole CLI
+//! surface can be reviewed in one place, and so that a test can check that every example still
+//! parses.
+
+use clap::Command;
+
+/// A usage example: what it does and the command line doing it.
+pub struct Example {
+    pub description: &'static str,
+    pub command: &'static str,
+}
+
+const fn example(description: &'static str, command: &'static str) -> Example {
+    Example {
+        description,
+        command,
+    }
+}
+
+/// The examples of each subcommand, by its path without the leading `zung`.
+const EXAMPLES: &[(&str, &[Example])] = &[
+    (
+        "torrent info",
+        &[
+            example(
+                "Print the general information and the files, sorted by name",
+                "zung torrent info -f ubuntu.torrent --with-files --sort name",
+            ),
+            example(
+                "Summarize a large torrent, down to 2 levels of directories",
+                "zung torrent info -f dataset.torrent --with-files --depth 2 --top 10",
+            ),
+            example(
+                "Print the general information as JSON",
+                "zung torrent info -f ubuntu.torrent --json",
+            ),
+            example(
+                "Fail instead of printing warnings when the torrent file has oddities",
+                "zung torrent info -f ubuntu.torrent --strict",
+            ),
+        ],
+    ),
+    (
+        "torrent peers",
+        &[example(
+            "List the peers and the client software they run",
+            "zung torrent peers -f ubuntu.torrent --probe",
+        )],
+    ),
+    (
+        "torrent announce",
+        &[example(
+            "Announce only to the HTTP trackers, giving them 5 seconds each",
+            "zung torrent announce -f ubuntu.torrent --scheme-filter http --timeout 5",
+        )],
+    ),
+    (
+        "torrent create",
+        &[
+            example(
+                "Create a torrent of a directory, without the temporary files",
+                "zung torrent create ./photos -a udp://tracker.example.com:6969/announce --exclude '*.tmp'",
+            ),
+            example(
+                "List the files and the piece length of the torrent without hashing them",
+                "zung torrent create ./photos --dry-run",
+            ),
+        ],
+    ),
+    (
+        "torrent rehash",
+        &[example(
+            "Rename a directory of the torrent to cross-seed renamed data",
+            "zung torrent rehash -f show.torrent -m 'Season 1=S01' -o show-renamed.torrent",
+        )],
+    ),
+    (
+        "torrent diff",
+        &[example(
+            "Compare two versions of a torrent file",
+            "zung torrent diff old.torrent new.torrent",
+        )],
+    ),
+    (
+        "torrent export-files",
+        &[example(
+            "Write the files, their pieces and their hashes as CSV",
+            "zung torrent export-files -f ubuntu.torrent --format csv -o files.csv",
+        )],
+    ),
+    (
+        "torrent add",
+        &[example(
+            "Add two files of the same torrent, merging their trackers",
+            "zung torrent add ubuntu.torrent ubuntu-mirror.torrent --merge-trackers",
+        )],
+    ),
+    (
+        "torrent resume-all",
+        &[example(
+            "Restore the torrents of the previous session",
+            "zung torrent resume-all",
+        )],
+    ),
+    (
+        "torrent trackers stats",
+        &[example(
+            "Print the reliability of the trackers announced to so far",
+            "zung torrent trackers stats",
+        )],
+    ),
+    (
+        "parsers bencode decode",
+        &[example(
+            "Convert a torrent file to JSON",
+            "zung parsers bencode decode --format json -f ubuntu.torrent -o ubuntu.json",
+        )],
+    ),
+    (
+        "parsers bencode encode",
+        &[example(
+            "Convert JSON back to bencode",
+            "zung parsers bencode encode -f ubuntu.json -o ubuntu.torrent",
+        )],
+    ),
+    (
+        "parsers bencode query",
+        &[example(
+            "Print the piece length of a torrent",
+            "zung parsers bencode query -f ubuntu.torrent -p 'info/piece length'",
+        )],
+    ),
+    (
+        "parsers bencode sizes",
+        &[example(
+            "Find what makes a torrent file large",
+            "zung parsers bencode sizes -f dataset.torrent --top 5",
+        )],
+    ),
+    (
+        "parsers bencode stats",
+        &[example(
+            "Profile the parsing of a large torrent file",
+            "zung parsers bencode stats -f dataset.torrent --runs 10",
+        )],
+    ),
+    (
+        "mini calc",
+        &[example(
+            "Evaluate an expression with a variable",
+            "zung mini calc '2 * (3 + x)' --var x=4",
+        )],
+    ),
+    (
+        "mini grep",
+        &[example(
+            "Print the lines of a file matching a pattern",
+            "zung mini grep --pattern TODO --file src/main.rs",
+        )],
+    ),
+    (
+        "mini strsplit split",
+        &[example(
+            "Split a string on whitespace",
+            "zung mini strsplit split -s 'a  b c' --whitespace",
+        )],
+    ),
+    (
+        "about",
+        &[example(
+            "Print the build information to attach to a bug report",
+            "zung about --json",
+        )],
+    ),
+    (
+        "gen-man",
+        &[example(
+            "Install the man pages for the current user",
+            "zung gen-man ~/.local/share/man/man1",
+        )],
+    ),
+];
+
+/// Returns the examples of the subcommand at the path, e.g. `torrent info`.
+pub fn for_command(path: &str) -> &'static [Example] {
+    EXAMPLES
+        .iter()
+        .find(|(command, _)| *command == path)
+        .map_or(&[], |(_, examples)| examples)
+}
+
+/// Appends the examples of every subcommand to its long about, shown by `--help`.
+pub fn add_to(command: Command) -> Command {
+    add_to_subcommands(command, "")
+}
+
+fn add_to_subcommands(mut command: Command, path: &str) -> Command {
+    let names: Vec<_> = command
+        .get_subcommands()
+        .map(|subcommand| subcommand.get_name().to_string())
+        .collect();
+    for name in names {
+        let path = format!("{path}{name}");
+        command = command.mut_subcommand(&name, |subcommand| {
+            let subcommand = add_to_subcommands(subcommand, &format!("{path} "));
+            let examples = for_command(&path);
+            if examples.is_empty() {
+                return subcommand;
+            }
+
+            let mut about = subcommand
+                .get_long_about()
+                .or(subcommand.get_about())
+                .map(ToString::to_string)
+                .unwrap_or_default();
+            about.push_str("\n\nExamples:");
+            for example in examples {
+                about.push_str(&format!(
+                    "\n  # {}\n  $ {}",
+                    example.description, example.command
+                ));
+            }
+            subcommand.long_about(about)
+        });
+    }
+    command
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::Cli;
+    use clap::{CommandFactory, Parser};
+
+    #[test]
+    fn examples_parse() {
+        for (path, examples) in EXAMPLES {
+            for example in *examples {
+                let args = shlex::split(example.command).unwrap();
+                assert_eq!(args[1..=path.split(' ').count()].join(" "), *path);
+                if let Err(e) = Cli::try_parse_from(args) {
+                    panic!("{}: {e}", example.command);
+                }
+            }
+        }
+    }
+
+    #[test]
+    fn examples_in_long_help() {
+        let mut command = add_to(Cli::command());
+        let info = command
+            .find_subcommand_mut("torrent")
+            .and_then(|torrent| torrent.find_subcommand_mut("info"))
+            .unwrap();
+        let help = info.render_long_help().to_string();
+        assert!(help.contains("Prints the information contained in the torrent file"));
+        assert!(help.contains("$ zung torrent info -f ubuntu.torrent --json"));
+    }
+}
@@ -1,8 +1,17 @@
-use clap::{Parser, Subcommand};
+mod build_info;
+mod error;
+mod examples;
+mod man;
+mod repl;
+
+use std::path::PathBuf;
+use std::process::ExitCode;
+
+use clap::{CommandFactory, FromArgMatches, Parser, Subcommand};
 
 use zung_mini::MiniArgs;
 use zung_parsers::ParserArgs;
-use zung_torrent::TorrentArgs;
+use zung_torrent::{TorrentArgs, TorrentSession};
 
 #[derive(Parser)]
 #[command(author, version, about, long_about = None, styles=get_styles())] // Read from `Cargo.toml`
@@ -21,16 +30,70 @@ enum Commands {
 
     /// Torrent Client
     Torrent(TorrentArgs),
+
+    /// Run the commands interactively, keeping the loaded torrents between commands
+    Repl,
+
+    /// Print the version and build information, useful when reporting issues
+    About {
+        /// Print the information as JSON.
+        #[arg(long)]
+        json: bool,
+    },
+
+    /// Write the man pages of zung and of each of its commands to a directory
+    GenMan {
+        /// The directory to write the pages to, e.g. `~/.local/share/man/man1`
+        dir: PathBuf,
+    },
+}
+
+/// The command line definition with the usage examples appended to the help of the subcommands.
+fn command() -> clap::Command {
+    examples::add_to(Cli::command())
 }
 
 #[tokio::main]
-async fn main() -> anyhow::Result<()> {
-    let cli = Cli::parse();
+async fn main() -> ExitCode {
+    let cli = Cli::from_arg_matches(&command().get_matches()).unwrap_or_else(|e| e.exit());
+
+    let result = match cli.commands {
+        Commands::Repl => repl::run().await,
+        commands => {
+            let mut session = TorrentSession::default();
+            let result = run(commands, &mut session).await;
+            result.and(session.save_if_modified())
+        }
+    };
+
+    match result {
+        Ok(()) => ExitCode::SUCCESS,
+        Err(e) => {
+            error::render(&e);
+            error::Failure::of(&e).into()
+        }
+    }
+}
 
-    match cli.commands {
-        Commands::Mini(mini_args) => mini_args.run(),
+/// Runs a single command. Shared by the one-shot CLI and the REPL.
+async fn run(commands: Commands, session: &mut TorrentSession) -> anyhow::Result<()> {
+    match commands {
+        Commands::Mini(mini_args) => mini_args.run()?,
         Commands::Parsers(bencode_args) => bencode_args.run()?,
-        Commands::Torrent(torrent_args) => torrent_args.run().await?,
+        Commands::Torrent(torrent_args) => torrent_args.run_in(session).await?,
+        Commands::About { json } => {
+            let info = build_info::BuildInfo::current();
+            if json {
+                println!("{}", serde_json::to_string_pretty(&info)?);
+            } else {
+                println!("{info}");
+            }
+        }
+        Commands::GenMan { dir } => {
+            let pages = man::generate_to(Cli::command(), &dir)?;
+            println!("Wrote {} man pages to {}", pages.len(), dir.display());
+        }
+        Commands::Repl => unreachable!("The REPL is started by main"),
     }
 
     Ok(())
@@ -0,0 +1,256 @@
+//! The man pages of zung, written by `zung gen-man`.
+//!
+//! Every command gets its own page, named after its path like `zung-torrent-info.1`, with the
+//! layout of the pages of clap_mangen: the options and the subcommands come from the clap
+//! definitions and the examples from the [`examples`](crate::examples) registry.
+
+use std::fmt::Write;
+use std::path::{Path, PathBuf};
+
+use anyhow::Context;
+use clap::{Arg, Command};
+
+use crate::examples;
+
+/// Writes the page of the command and of each of its subcommands to the directory, creating it if
+/// required. Returns the paths of the written pages.
+pub fn generate_to(command: Command, dir: &Path) -> anyhow::Result<Vec<PathBuf>> {
+    std::fs::create_dir_all(dir)
+        .with_context(|| format!("Unable to create directory: {}", dir.display()))?;
+
+    let mut command = command;
+    command.build();
+
+    let mut written = Vec::new();
+    let mut pending = vec![(command, Vec::new())];
+    while let Some((mut command, path)) = pending.pop() {
+        let page = dir.join(format!("{}.1", page_name(&command, &path)));
+        std::fs::write(&page, render(&mut command, &path))
+            .with_context(|| format!("Unable to write file: {}", page.display()))?;
+        written.push(page);
+
+        for subcommand in command.get_subcommands().filter(|s| !is_hidden(s)) {
+            let mut path = path.clone();
+            path.push(subcommand.get_name().to_string());
+            pending.push((subcommand.clone(), path));
+        }
+    }
+    written.sort();
+    Ok(written)
+}
+
+/// Renders the roff source of the page of the command, at the path of subcommand names under the
+/// root command.
+fn render(command: &mut Command, path: &[String]) -> String {
+    let name = page_name(command, path);
+    let mut page = String::new();
+    let _ = writeln!(
+        page,
+        ".TH {} 1 \"\" \"zung {}\"",
+        escape(&name.to_uppercase()),
+        env!("CARGO_PKG_VERSION")
+    );
+
+    page.push_str(".SH NAME\n");
+    match command.get_about() {
+        Some(about) => {
+            let _ = writeln!(page, "{} \\- {}", escape(&name), escape(&about.to_string()));
+        }
+        None => {
+            let _ = writeln!(page, "{}", escape(&name));
+        }
+    }
+
+    page.push_str(".SH SYNOPSIS\n");
+    let usage = command.render_usage().to_string();
+    for line in usage.trim_start_matches("Usage:").lines() {
+        let _ = writeln!(page, "{}\n.br", escape(line.trim()));
+    }
+
+    // The about is already in the name, only the longer descriptions are repeated.
+    if let Some(description) = command.get_long_about() {
+        page.push_str(".SH DESCRIPTION\n");
+        push_paragraphs(&mut page, &description.to_string());
+    }
+
+    let (positionals, options): (Vec<_>, Vec<_>) = command
+        .get_arguments()
+        .filter(|arg| !arg.is_hide_set())
+        .partition(|arg| arg.is_positional());
+    if !positionals.is_empty() {
+        page.push_str(".SH ARGUMENTS\n");
+        positionals.iter().for_each(|arg| push_arg(&mut page, arg));
+    }
+    if !options.is_empty() {
+        page.push_str(".SH OPTIONS\n");
+        options.iter().for_each(|arg| push_arg(&mut page, arg));
+    }
+
+    let subcommands: Vec<_> = command
+        .get_subcommands()
+        .filter(|subcommand| !is_hidden(subcommand))
+        .collect();
+    if !subcommands.is_empty() {
+        page.push_str(".SH SUBCOMMANDS\n");
+        for subcommand in subcommands {
+            let mut path = path.to_vec();
+            path.push(subcommand.get_name().to_string());
+            let _ = writeln!(
+                page,
+                ".TP\n\\fB{}\\fR(1)\n{}",
+                escape(&page_name(command, &path)),
+                escape(
+                    &subcommand
+                        .get_about()
+                        .map(ToString::to_string)
+                        .unwrap_or_default()
+                )
+            );
+        }
+    }
+
+    let examples = examples::for_command(&path.join(" "));
+    if !examples.is_empty() {
+        page.push_str(".SH EXAMPLES\n");
+        for example in examples {
+            let _ = writeln!(
+                page,
+                ".PP\n{}\n.RS\n.nf\n$ {}\n.fi\n.RE",
+                escape(example.description),
+                escape(example.command)
+            );
+        }
+    }
+    page
+}
+
+fn push_arg(page: &mut String, arg: &Arg) {
+    let mut flags = Vec::new();
+    if let Some(short) = arg.get_short() {
+        flags.push(format!("\\fB\\-{}\\fR", escape(&short.to_string())));
+    }
+    if let Some(long) = arg.get_long() {
+        flags.push(format!("\\fB\\-\\-{}\\fR", escape(long)));
+    }
+    let mut header = flags.join(", ");
+    if arg.get_action().takes_values() {
+        let values: Vec<_> = arg
+            .get_value_names()
+            .unwrap_or_default()
+            .iter()
+            .map(|value| format!("\\fI<{}>\\fR", escape(value)))
+            .collect();
+        let values = if values.is_empty() {
+            format!(
+                "\\fI<{}>\\fR",
+                escape(&arg.get_id().to_string().to_uppercase())
+            )
+        } else {
+            values.join(" ")
+        };
+        header = if header.is_empty() {
+            values
+        } else {
+            format!("{header} {values}")
+        };
+    }
+
+    let mut help = arg
+        .get_long_help()
+        .or(arg.get_help())
+        .map(ToString::to_string)
+        .unwrap_or_default();
+    let defaults: Vec<_> = arg
+        .get_default_values()
+        .iter()
+        .map(|value| value.to_string_lossy())
+        .collect();
+    if !defaults.is_empty() && arg.get_action().takes_values() {
+        let _ = write!(help, " [default: {}]", defaults.join(", "));
+    }
+    let possible: Vec<_> = arg
+        .get_possible_values()
+        .into_iter()
+        .filter(|value| !value.is_hide_set())
+        .map(|value| value.get_name().to_string())
+        .collect();
+    if !possible.is_empty() {
+        let _ = write!(help, " [possible values: {}]", possible.join(", "));
+    }
+
+    let _ = writeln!(page, ".TP\n{header}");
+    push_paragraphs(page, help.trim());
+}
+
+fn push_paragraphs(page: &mut String, text: &str) {
+    for (i, paragraph) in text.split("\n\n").enumerate() {
+        if i > 0 {
+            page.push_str(".PP\n");
+        }
+        let _ = writeln!(page, "{}", escape(paragraph.trim()));
+    }
+}
+
+// The pages of the subcommands are named after their path, e.g. `zung-torrent-info`.
+fn page_name(command: &Command, path: &[String]) -> String {
+    let root = command
+        .get_bin_name()
+        .and_then(|bin| bin.split(' ').next())
+        .unwrap_or(command.get_name());
+    std::iter::once(root)
+        .chain(path.iter().map(String::as_str))
+        .collect::<Vec<_>>()
+        .join("-")
+}
+
+fn is_hidden(command: &Command) -> bool {
+    command.is_hide_set() || command.get_name() == "help"
+}
+
+// Escapes the text for roff: the backslashes and the dashes, and the lines which would otherwise
+// be read as requests.
+fn escape(text: &str) -> String {
+    text.replace('\\', "\\e")
+        .replace('-', "\\-")
+        .lines()
+        .map(|line| {
+            if line.starts_with(['.', '\'']) {
+                format!("\\&{line}")
+            } else {
+                line.to_string()
+            }
+        })
+        .collect::<Vec<_>>()
+        .join("\n")
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::Cli;
+    use clap::CommandFactory;
+
+    #[test]
+    fn escapes_roff() {
+        assert_eq!(escape("--file a\\b"), "\\-\\-file a\\eb");
+        assert_eq!(escape("one\n.two"), "one\n\\&.two");
+    }
+
+    #[test]
+    fn generates_a_page_per_command() {
+        let dir = std::env::temp_dir().join(format!("zung-man-{}", std::process::id()));
+        let pages = generate_to(Cli::command(), &dir).unwrap();
+        assert!(pages.contains(&dir.join("zung.1")));
+        assert!(!pages.iter().any(|page| page.ends_with("zung-help.1")));
+
+        let info = std::fs::read_to_string(dir.join("zung-torrent-info.1")).unwrap();
+        assert!(info.starts_with(".TH ZUNG\\-TORRENT\\-INFO 1"));
+        assert!(info.contains("\\fB\\-f\\fR, \\fB\\-\\-file\\fR \\fI<FILE>\\fR"));
+        assert!(info.contains("[possible values: name, size]"));
+        assert!(info.contains(".SH EXAMPLES"));
+
+        // The global options of `zung torrent` are documented on its subcommands too.
+        assert!(info.contains("\\-\\-blocklist"));
+        std::fs::remove_dir_all(dir).unwrap();
+    }
+}
@@ -0,0 +1,108 @@
+//! The interactive mode of zung, started with `zung repl`.
+//!
+//! Each line is parsed as a regular zung command (without the leading `zung`) and dispatched to
+//! the same handlers as the one-shot CLI. Unlike the one-shot CLI, the state of the commands, such
+//! as a loaded torrent, is kept until the REPL exits.
+
+use std::path::PathBuf;
+
+use clap::{CommandFactory, FromArgMatches, Parser, Subcommand};
+use rustyline::{error::ReadlineError, DefaultEditor};
+use zung_torrent::TorrentSession;
+
+use crate::Commands;
+
+const HISTORY_FILE_NAME: &str = "repl_history";
+
+#[derive(Parser)]
+#[command(no_binary_name = true, disable_version_flag = true, styles=crate::get_styles())]
+struct ReplLine {
+    #[command(subcommand)]
+    command: ReplCommands,
+}
+
+#[derive(Subcommand)]
+enum ReplCommands {
+    // Boxed, the other variants are tiny.
+    #[command(flatten)]
+    Zung(Box<Commands>),
+
+    /// Load a torrent file and keep it in memory for the following torrent commands.
+    Load {
+        /// Torrent File to load
+        file: PathBuf,
+    },
+
+    /// Exit the REPL.
+    #[command(alias = "quit")]
+    Exit,
+}
+
+/// Runs the REPL until the user exits with `exit`, Ctrl-C or Ctrl-D.
+pub async fn run() -> anyhow::Result<()> {
+    let mut editor = DefaultEditor::new()?;
+    let history = dirs::data_dir().map(|dir| dir.join("zung").join(HISTORY_FILE_NAME));
+    if let Some(history) = &history {
+        // A missing history file is expected on the first run.
+        let _ = editor.load_history(history);
+    }
+
+    let mut session = TorrentSession::default();
+    println!(
+        "zung {} - type `help` for the commands, `exit` to quit.",
+        env!("CARGO_PKG_VERSION")
+    );
+
+    loop {
+        let line = match editor.readline("zung> ") {
+            Ok(line) => line,
+            Err(ReadlineError::Interrupted | ReadlineError::Eof) => break,
+            Err(e) => return Err(e.into()),
+        };
+        if line.trim().is_empty() {
+            continue;
+        }
+        editor.add_history_entry(&line)?;
+
+        let Some(args) = shlex::split(&line) else {
+            eprintln!("Error: unbalanced quotes");
+            continue;
+        };
+
+        let matches = crate::examples::add_to(ReplLine::command()).try_get_matches_from(args);
+        let command = match matches.and_then(|matches| ReplLine::from_arg_matches(&matches)) {
+            Ok(parsed) => parsed.command,
+            Err(e) => {
+                e.print()?;
+                continue;
+            }
+        };
+
+        let result = match command {
+            ReplCommands::Zung(commands) if matches!(*commands, Commands::Repl) => {
+                println!("Already running the REPL");
+                Ok(())
+            }
+            ReplCommands::Zung(commands) => crate::run(*commands, &mut session).await,
+            ReplCommands::Load { file } => session
+                .client(file)
+                .map(|client| println!("Loaded {}", client.file_name())),
+            ReplCommands::Exit => break,
+        };
+
+        if let Err(e) = result {
+            crate::error::render(&e);
+        }
+    }
+
+    session.save_if_modified()?;
+
+    if let Some(history) = &history {
+        if let Some(parent) = history.parent() {
+            std::fs::create_dir_all(parent)?;
+        }
+        editor.save_history(history)?;
+    }
+
+    Ok(())
+}
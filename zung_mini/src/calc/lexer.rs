@@ -0,0 +1,152 @@
+use std::fmt::{self, Display};
+use std::iter::Peekable;
+use std::str::CharIndices;
+
+use super::CalcError;
+
+/// The smallest meaningful units of an expression.
+#[derive(Debug, Clone, PartialEq)]
+pub enum Token {
+    Number(f64),
+    Ident(String),
+    Plus,
+    Minus,
+    Star,
+    Slash,
+    Percent,
+    Caret,
+    LParen,
+    RParen,
+}
+
+impl Display for Token {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Token::Number(n) => write!(f, "{n}"),
+            Token::Ident(name) => write!(f, "{name}"),
+            Token::Plus => write!(f, "+"),
+            Token::Minus => write!(f, "-"),
+            Token::Star => write!(f, "*"),
+            Token::Slash => write!(f, "/"),
+            Token::Percent => write!(f, "%"),
+            Token::Caret => write!(f, "^"),
+            Token::LParen => write!(f, "("),
+            Token::RParen => write!(f, ")"),
+        }
+    }
+}
+
+/// Splits the input into [`Token`]s, skipping the whitespace.
+///
+/// # Example
+///
+/// ```
+/// use zung_mini::calc::{tokenize, Token};
+///
+/// assert_eq!(
+///     tokenize("x*2"),
+///     Ok(vec![Token::Ident("x".into()), Token::Star, Token::Number(2.0)])
+/// );
+/// ```
+pub fn tokenize(input: &str) -> Result<Vec<Token>, CalcError> {
+    let mut tokens = Vec::new();
+    let mut chars = input.char_indices().peekable();
+
+    while let Some(&(index, c)) = chars.peek() {
+        let token = match c {
+            c if c.is_whitespace() => {
+                chars.next();
+                continue;
+            }
+            '0'..='9' | '.' => number(input, &mut chars)?,
+            c if c.is_alphabetic() || c == '_' => {
+                let end = take_while(&mut chars, |c| c.is_alphanumeric() || c == '_');
+                Token::Ident(input[index..end].to_string())
+            }
+            _ => {
+                chars.next();
+                match c {
+                    '+' => Token::Plus,
+                    '-' => Token::Minus,
+                    '*' => Token::Star,
+                    '/' => Token::Slash,
+                    '%' => Token::Percent,
+                    '^' => Token::Caret,
+                    '(' => Token::LParen,
+                    ')' => Token::RParen,
+                    _ => return Err(CalcError::UnexpectedChar(c, index)),
+                }
+            }
+        };
+        tokens.push(token);
+    }
+
+    Ok(tokens)
+}
+
+// Consumes the characters matching the predicate and returns the index after the last one.
+fn take_while(chars: &mut Peekable<CharIndices>, predicate: impl Fn(char) -> bool) -> usize {
+    let mut end = 0;
+    while let Some(&(index, c)) = chars.peek() {
+        if !predicate(c) {
+            return index;
+        }
+        end = index + c.len_utf8();
+        chars.next();
+    }
+    end
+}
+
+// Numbers are digits with an optional fraction and an optional exponent, like `1.5e-3`.
+fn number(input: &str, chars: &mut Peekable<CharIndices>) -> Result<Token, CalcError> {
+    let (start, _) = *chars.peek().expect("Called on a digit");
+    let mut end = take_while(chars, |c| c.is_ascii_digit() || c == '.');
+
+    if let Some(&(_, 'e' | 'E')) = chars.peek() {
+        chars.next();
+        if let Some(&(_, '+' | '-')) = chars.peek() {
+            chars.next();
+        }
+        end = take_while(chars, |c| c.is_ascii_digit());
+    }
+
+    let literal = &input[start..end];
+    literal
+        .parse()
+        .map(Token::Number)
+        .map_err(|_| CalcError::UnexpectedChar(literal.chars().last().unwrap_or('.'), end - 1))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn tokens() {
+        assert_eq!(
+            tokenize(" 1.5 + foo_2*(3e2)^-x % 4 /y "),
+            Ok(vec![
+                Token::Number(1.5),
+                Token::Plus,
+                Token::Ident("foo_2".into()),
+                Token::Star,
+                Token::LParen,
+                Token::Number(300.0),
+                Token::RParen,
+                Token::Caret,
+                Token::Minus,
+                Token::Ident("x".into()),
+                Token::Percent,
+                Token::Number(4.0),
+                Token::Slash,
+                Token::Ident("y".into()),
+            ])
+        );
+    }
+
+    #[test]
+    fn invalid() {
+        assert_eq!(tokenize("1 & 2"), Err(CalcError::UnexpectedChar('&', 2)));
+        assert!(tokenize("1..2").is_err());
+    }
+}
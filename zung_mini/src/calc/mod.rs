@@ -0,0 +1,189 @@
+//! An arithmetic expression calculator built with a tokenizer, a [Pratt
+//! parser](https://matklad.github.io/2020/04/13/simple-but-powerful-pratt-parsing.html) and a tree
+//! walking evaluator.
+//!
+//! ## Overview
+//!
+//! An expression goes through three stages:
+//! 1. The [`tokenize`] function turns the input into a list of [`Token`]s.
+//! 2. The [`parse`] function builds an [`Expr`] tree out of the tokens, taking care of the
+//!    precedence and the associativity of the operators.
+//! 3. The [`Expr::eval`] method walks the tree and computes the result.
+//!
+//! The [`evaluate`] function runs all three at once.
+//!
+//! ### Syntax
+//!
+//! - Numbers such as `2`, `0.5` and `1e3`.
+//! - Variables such as `x` or `rate_2`, whose values are provided while evaluating.
+//! - The binary operators `+`, `-`, `*`, `/`, `%` and `^` (power, right associative).
+//! - Unary `-` and parentheses.
+//!
+//! ### Example
+//!
+//! ```rust
+//! use std::collections::HashMap;
+//! use zung_mini::calc::evaluate;
+//!
+//! assert_eq!(evaluate("2 * (3 + 4)", &HashMap::new()), Ok(14.0));
+//!
+//! let vars = HashMap::from([(String::from("x"), 3.0)]);
+//! assert_eq!(evaluate("-x ^ 2 + 1", &vars), Ok(-8.0));
+//! ```
+
+mod lexer;
+mod parser;
+
+use std::collections::HashMap;
+use std::fmt::{self, Display};
+
+pub use lexer::{tokenize, Token};
+pub use parser::parse;
+
+/// Represents all the errors which can occur while parsing or evaluating an expression.
+#[derive(Debug, Clone, PartialEq)]
+pub enum CalcError {
+    /// The input contains a character which is not part of the syntax. Contains the character and
+    /// its byte index.
+    UnexpectedChar(char, usize),
+
+    /// A token appeared where it is not allowed.
+    UnexpectedToken(Token),
+
+    /// The input ended in the middle of an expression.
+    UnexpectedEnd,
+
+    /// A variable was used without providing its value.
+    UnknownVariable(String),
+
+    /// Division (or remainder) by zero.
+    DivisionByZero,
+}
+
+impl Display for CalcError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            CalcError::UnexpectedChar(c, index) => {
+                write!(f, "Unexpected character '{c}' at index {index}")
+            }
+            CalcError::UnexpectedToken(token) => write!(f, "Unexpected token '{token}'"),
+            CalcError::UnexpectedEnd => write!(f, "Unexpected end of the expression"),
+            CalcError::UnknownVariable(name) => write!(f, "Unknown variable '{name}'"),
+            CalcError::DivisionByZero => write!(f, "Division by zero"),
+        }
+    }
+}
+
+impl std::error::Error for CalcError {}
+
+/// Binary operators, in increasing order of precedence.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum BinaryOp {
+    Add,
+    Sub,
+    Mul,
+    Div,
+    Rem,
+    Pow,
+}
+
+impl Display for BinaryOp {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let op = match self {
+            BinaryOp::Add => "+",
+            BinaryOp::Sub => "-",
+            BinaryOp::Mul => "*",
+            BinaryOp::Div => "/",
+            BinaryOp::Rem => "%",
+            BinaryOp::Pow => "^",
+        };
+        write!(f, "{op}")
+    }
+}
+
+/// The syntax tree of an expression, constructed with [`parse`].
+#[derive(Debug, Clone, PartialEq)]
+pub enum Expr {
+    Number(f64),
+    Variable(String),
+    Neg(Box<Expr>),
+    Binary(BinaryOp, Box<Expr>, Box<Expr>),
+}
+
+impl Expr {
+    /// Evaluates the expression, looking up the values of the variables in `vars`.
+    pub fn eval(&self, vars: &HashMap<String, f64>) -> Result<f64, CalcError> {
+        match self {
+            Expr::Number(n) => Ok(*n),
+            Expr::Variable(name) => vars
+                .get(name)
+                .copied()
+                .ok_or_else(|| CalcError::UnknownVariable(name.clone())),
+            Expr::Neg(expr) => Ok(-expr.eval(vars)?),
+            Expr::Binary(op, lhs, rhs) => {
+                let (lhs, rhs) = (lhs.eval(vars)?, rhs.eval(vars)?);
+                match op {
+                    BinaryOp::Add => Ok(lhs + rhs),
+                    BinaryOp::Sub => Ok(lhs - rhs),
+                    BinaryOp::Mul => Ok(lhs * rhs),
+                    BinaryOp::Div | BinaryOp::Rem if rhs == 0.0 => Err(CalcError::DivisionByZero),
+                    BinaryOp::Div => Ok(lhs / rhs),
+                    BinaryOp::Rem => Ok(lhs % rhs),
+                    BinaryOp::Pow => Ok(lhs.powf(rhs)),
+                }
+            }
+        }
+    }
+}
+
+/// Prints the expression fully parenthesized, which makes the precedence visible.
+impl Display for Expr {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Expr::Number(n) => write!(f, "{n}"),
+            Expr::Variable(name) => write!(f, "{name}"),
+            Expr::Neg(expr) => write!(f, "(-{expr})"),
+            Expr::Binary(op, lhs, rhs) => write!(f, "({lhs} {op} {rhs})"),
+        }
+    }
+}
+
+/// Tokenizes, parses and evaluates the expression in one go.
+pub fn evaluate(input: &str, vars: &HashMap<String, f64>) -> Result<f64, CalcError> {
+    parse(&tokenize(input)?)?.eval(vars)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn eval(input: &str) -> Result<f64, CalcError> {
+        evaluate(input, &HashMap::new())
+    }
+
+    #[test]
+    fn arithmetic() {
+        assert_eq!(eval("2*(3+4)"), Ok(14.0));
+        assert_eq!(eval("1 + 2 * 3 - 4 / 2"), Ok(5.0));
+        assert_eq!(eval("10 % 4"), Ok(2.0));
+        assert_eq!(eval("2 ^ 3 ^ 2"), Ok(512.0));
+        assert_eq!(eval("--2"), Ok(2.0));
+        assert_eq!(eval("1.5e1 / 3"), Ok(5.0));
+    }
+
+    #[test]
+    fn variables() {
+        let vars = HashMap::from([(String::from("x"), 2.0), (String::from("y_1"), 0.5)]);
+        assert_eq!(evaluate("x * y_1 + x", &vars), Ok(3.0));
+        assert_eq!(
+            evaluate("x + z", &vars),
+            Err(CalcError::UnknownVariable(String::from("z")))
+        );
+    }
+
+    #[test]
+    fn division_by_zero() {
+        assert_eq!(eval("1 / (2 - 2)"), Err(CalcError::DivisionByZero));
+        assert_eq!(eval("1 % 0"), Err(CalcError::DivisionByZero));
+    }
+}
@@ -0,0 +1,115 @@
+use std::iter::Peekable;
+use std::slice::Iter;
+
+use super::{BinaryOp, CalcError, Expr, Token};
+
+/// Binding power of the unary minus. Lower than `^` so that `-2^2` is `-(2^2)`.
+const PREFIX_BP: u8 = 5;
+
+/// Builds the [`Expr`] tree out of the tokens.
+///
+/// # Example
+///
+/// ```
+/// use zung_mini::calc::{parse, tokenize};
+///
+/// let expr = parse(&tokenize("1 + 2 * 3").unwrap()).unwrap();
+/// assert_eq!(expr.to_string(), "(1 + (2 * 3))");
+/// ```
+pub fn parse(tokens: &[Token]) -> Result<Expr, CalcError> {
+    let mut tokens = tokens.iter().peekable();
+    let expr = expr_bp(&mut tokens, 0)?;
+    match tokens.next() {
+        Some(token) => Err(CalcError::UnexpectedToken(token.clone())),
+        None => Ok(expr),
+    }
+}
+
+// The left and the right binding powers of the binary operators. Left associative operators bind
+// tighter on the right, and the right associative `^` binds tighter on the left.
+fn infix_bp(token: &Token) -> Option<(BinaryOp, u8, u8)> {
+    let op = match token {
+        Token::Plus => (BinaryOp::Add, 1, 2),
+        Token::Minus => (BinaryOp::Sub, 1, 2),
+        Token::Star => (BinaryOp::Mul, 3, 4),
+        Token::Slash => (BinaryOp::Div, 3, 4),
+        Token::Percent => (BinaryOp::Rem, 3, 4),
+        Token::Caret => (BinaryOp::Pow, 8, 7),
+        _ => return None,
+    };
+    Some(op)
+}
+
+fn expr_bp(tokens: &mut Peekable<Iter<Token>>, min_bp: u8) -> Result<Expr, CalcError> {
+    let mut lhs = match tokens.next().ok_or(CalcError::UnexpectedEnd)? {
+        Token::Number(n) => Expr::Number(*n),
+        Token::Ident(name) => Expr::Variable(name.clone()),
+        Token::Minus => Expr::Neg(Box::new(expr_bp(tokens, PREFIX_BP)?)),
+        Token::LParen => {
+            let expr = expr_bp(tokens, 0)?;
+            match tokens.next() {
+                Some(Token::RParen) => expr,
+                Some(token) => return Err(CalcError::UnexpectedToken(token.clone())),
+                None => return Err(CalcError::UnexpectedEnd),
+            }
+        }
+        token => return Err(CalcError::UnexpectedToken(token.clone())),
+    };
+
+    while let Some(token) = tokens.peek() {
+        let Some((op, left_bp, right_bp)) = infix_bp(token) else {
+            match token {
+                Token::RParen => break,
+                token => return Err(CalcError::UnexpectedToken((*token).clone())),
+            }
+        };
+        if left_bp < min_bp {
+            break;
+        }
+
+        tokens.next();
+        let rhs = expr_bp(tokens, right_bp)?;
+        lhs = Expr::Binary(op, Box::new(lhs), Box::new(rhs));
+    }
+
+    Ok(lhs)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::calc::tokenize;
+
+    fn parsed(input: &str) -> String {
+        parse(&tokenize(input).unwrap()).unwrap().to_string()
+    }
+
+    #[test]
+    fn precedence_and_associativity() {
+        assert_eq!(parsed("1 + 2 * 3"), "(1 + (2 * 3))");
+        assert_eq!(parsed("1 - 2 - 3"), "((1 - 2) - 3)");
+        assert_eq!(parsed("2 ^ 3 ^ 2"), "(2 ^ (3 ^ 2))");
+        assert_eq!(parsed("-2 ^ 2"), "(-(2 ^ 2))");
+        assert_eq!(parsed("-a * b"), "((-a) * b)");
+        assert_eq!(parsed("(1 + 2) * 3"), "((1 + 2) * 3)");
+    }
+
+    #[test]
+    fn errors() {
+        let parse_str = |input: &str| parse(&tokenize(input).unwrap());
+        assert_eq!(parse_str("1 +"), Err(CalcError::UnexpectedEnd));
+        assert_eq!(parse_str("(1 + 2"), Err(CalcError::UnexpectedEnd));
+        assert_eq!(
+            parse_str("1 2"),
+            Err(CalcError::UnexpectedToken(Token::Number(2.0)))
+        );
+        assert_eq!(
+            parse_str("1 + 2)"),
+            Err(CalcError::UnexpectedToken(Token::RParen))
+        );
+        assert_eq!(
+            parse_str("* 2"),
+            Err(CalcError::UnexpectedToken(Token::Star))
+        );
+    }
+}
@@ -0,0 +1,303 @@
+//! A tiny `grep` over the lines of any text, following the classic "build your own grep" learning
+//! project.
+//!
+//! ## Overview
+//!
+//! This module provides the [`GreprExt`] trait, which adds a [`grepr()`](GreprExt::grepr()) method
+//! to any iterator over lines of text. The returned [`Grepr`] iterator lazily yields a
+//! [`LineMatch`] for every line containing the [`Pattern`], along with the line number and the
+//! location of every match within the line.
+//!
+//! ### Patterns
+//!
+//! A [`Pattern`] is either a literal string or a simple wildcard pattern where:
+//! - `?` matches any single character.
+//! - `*` matches any sequence of characters (including none). The shortest possible match is
+//!   used.
+//!
+//! The wildcard pattern is tokenized with [`Strsplit`](crate::strsplit::Strsplit).
+//!
+//! ### Example
+//!
+//! ```rust
+//! use zung_mini::grepr::{GreprExt, Pattern};
+//!
+//! let text = "fn main() {\n    println!(\"hello\");\n}\n";
+//! let matches: Vec<_> = text.lines().grepr(Pattern::new("pr*ln")).collect();
+//!
+//! assert_eq!(matches.len(), 1);
+//! assert_eq!(matches[0].line_number, 2);
+//! assert_eq!(matches[0].matched().collect::<Vec<_>>(), vec!["println"]);
+//! ```
+
+use std::ops::Range;
+
+use colored::Colorize;
+
+use crate::strsplit::StrsplitExt;
+
+/// The pattern to search for in the lines. See the [module](self) documentation for the syntax.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum Pattern {
+    /// Matches the string as is.
+    Literal(String),
+
+    /// Matches the segments in order, with anything in between them. `?` in a segment matches
+    /// any single character.
+    Wildcard(Vec<String>),
+}
+
+impl Pattern {
+    /// Parses the pattern. Patterns without any `*` or `?` are [`Pattern::Literal`].
+    ///
+    /// # Panics
+    ///
+    /// Panics if the pattern is empty.
+    pub fn new(pattern: &str) -> Self {
+        assert!(!pattern.is_empty(), "Empty pattern is not allowed");
+
+        if !pattern.contains(['*', '?']) {
+            return Pattern::Literal(pattern.to_string());
+        }
+
+        // Leading and trailing stars do not change what a line matches, since the shortest match
+        // is used.
+        let segments = pattern
+            .strsplit('*')
+            .filter(|segment| !segment.is_empty())
+            .map(String::from)
+            .collect();
+        Pattern::Wildcard(segments)
+    }
+
+    /// Returns the byte range of the first match of the pattern in the line, if any.
+    pub fn find(&self, line: &str) -> Option<Range<usize>> {
+        match self {
+            Pattern::Literal(literal) => line
+                .find(literal.as_str())
+                .map(|start| start..start + literal.len()),
+            Pattern::Wildcard(segments) => find_wildcard(segments, line),
+        }
+    }
+
+    /// Returns the byte ranges of all the non overlapping matches of the pattern in the line.
+    pub fn find_all(&self, line: &str) -> Vec<Range<usize>> {
+        let mut matches = Vec::new();
+        let mut offset = 0;
+        while let Some(found) = self.find(&line[offset..]) {
+            let found = offset + found.start..offset + found.end;
+            // Step over empty matches so that the search always moves forward.
+            offset = if found.is_empty() {
+                match line[found.end..].chars().next() {
+                    Some(c) => found.end + c.len_utf8(),
+                    None => line.len() + 1,
+                }
+            } else {
+                found.end
+            };
+            matches.push(found);
+            if offset > line.len() {
+                break;
+            }
+        }
+        matches
+    }
+}
+
+// Returns the end of the segment if it matches the text right at the start.
+fn match_segment(segment: &str, text: &str) -> Option<usize> {
+    let mut chars = text.char_indices();
+    for expected in segment.chars() {
+        let (_, c) = chars.next()?;
+        if expected != '?' && expected != c {
+            return None;
+        }
+    }
+    Some(chars.next().map_or(text.len(), |(end, _)| end))
+}
+
+// Returns the range of the first occurrence of the segment in the text.
+fn find_segment(segment: &str, text: &str) -> Option<Range<usize>> {
+    text.char_indices()
+        .map(|(start, _)| start)
+        .chain(std::iter::once(text.len()))
+        .find_map(|start| match_segment(segment, &text[start..]).map(|end| start..start + end))
+}
+
+// A later occurrence of the first segment can not help the rest to match, so the first one is
+// always used.
+fn find_wildcard(segments: &[String], line: &str) -> Option<Range<usize>> {
+    let Some((first, rest)) = segments.split_first() else {
+        return Some(0..0);
+    };
+
+    let Range { start, mut end } = find_segment(first, line)?;
+    for segment in rest {
+        end += find_segment(segment, &line[end..])?.end;
+    }
+    Some(start..end)
+}
+
+/// A line containing at least one match of the [`Pattern`].
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct LineMatch<L> {
+    /// Number of the line, starting from 1.
+    pub line_number: usize,
+
+    /// The line itself.
+    pub line: L,
+
+    /// Byte ranges of all the matches within the line.
+    pub matches: Vec<Range<usize>>,
+}
+
+impl<L: AsRef<str>> LineMatch<L> {
+    /// Returns the matched parts of the line.
+    pub fn matched(&self) -> impl Iterator<Item = &str> {
+        self.matches
+            .iter()
+            .map(|range| &self.line.as_ref()[range.clone()])
+    }
+
+    /// Returns the line with every match highlighted for the terminal.
+    pub fn highlighted(&self) -> String {
+        let line = self.line.as_ref();
+        let mut out = String::with_capacity(line.len());
+        let mut last = 0;
+        for range in &self.matches {
+            out.push_str(&line[last..range.start]);
+            out.push_str(&line[range.clone()].red().bold().to_string());
+            last = range.end;
+        }
+        out.push_str(&line[last..]);
+        out
+    }
+}
+
+/// An iterator over the lines matching a [`Pattern`].
+///
+/// This type is constructed by the [`grepr()`](GreprExt::grepr()) method.
+#[derive(Debug, Clone)]
+pub struct Grepr<I> {
+    lines: I,
+    pattern: Pattern,
+    line_number: usize,
+}
+
+impl<I> Iterator for Grepr<I>
+where
+    I: Iterator,
+    I::Item: AsRef<str>,
+{
+    type Item = LineMatch<I::Item>;
+
+    fn next(&mut self) -> Option<Self::Item> {
+        for line in self.lines.by_ref() {
+            self.line_number += 1;
+            let matches = self.pattern.find_all(line.as_ref());
+            if !matches.is_empty() {
+                return Some(LineMatch {
+                    line_number: self.line_number,
+                    line,
+                    matches,
+                });
+            }
+        }
+        None
+    }
+}
+
+/// A trait to extend iterators over lines of text with the `grepr` method.
+///
+/// # Example
+///
+/// ```
+/// use zung_mini::grepr::{GreprExt, Pattern};
+///
+/// let lines = ["apple", "banana", "cherry"];
+/// let found: Vec<_> = lines.iter().grepr(Pattern::new("a?a")).map(|m| *m.line).collect();
+/// assert_eq!(found, vec!["banana"]);
+/// ```
+pub trait GreprExt: Iterator + Sized
+where
+    Self::Item: AsRef<str>,
+{
+    /// Returns a [`Grepr`] iterator over the lines matching the `pattern`.
+    fn grepr(self, pattern: Pattern) -> Grepr<Self> {
+        Grepr {
+            lines: self,
+            pattern,
+            line_number: 0,
+        }
+    }
+}
+
+impl<I> GreprExt for I
+where
+    I: Iterator,
+    I::Item: AsRef<str>,
+{
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn pattern_parsing() {
+        assert_eq!(Pattern::new("abc"), Pattern::Literal("abc".into()));
+        assert_eq!(
+            Pattern::new("*a*b?c*"),
+            Pattern::Wildcard(vec!["a".into(), "b?c".into()])
+        );
+    }
+
+    #[test]
+    #[should_panic(expected = "Empty pattern is not allowed")]
+    fn empty_pattern() {
+        Pattern::new("");
+    }
+
+    #[test]
+    fn literal_find() {
+        let pattern = Pattern::new("ab");
+        assert_eq!(pattern.find("xxabab"), Some(2..4));
+        assert_eq!(pattern.find_all("xxabab"), vec![2..4, 4..6]);
+        assert_eq!(pattern.find("ba"), None);
+    }
+
+    #[test]
+    fn wildcard_find() {
+        assert_eq!(Pattern::new("a*c").find("xxabbcc"), Some(2..6));
+        assert_eq!(Pattern::new("a?c").find("abxaxc"), Some(3..6));
+        assert_eq!(Pattern::new("h?llo").find("say hällo"), Some(4..10));
+        assert_eq!(Pattern::new("a*b*c").find("c b a"), None);
+        assert_eq!(Pattern::new("a?*z").find("ab a-z"), Some(0..6));
+        assert_eq!(Pattern::new("a*c").find_all("ac abc"), vec![0..2, 3..6]);
+    }
+
+    #[test]
+    fn star_matches_every_line() {
+        let pattern = Pattern::new("*");
+        assert_eq!(pattern.find("anything"), Some(0..0));
+        assert_eq!(pattern.find_all("ab").len(), 3);
+        assert_eq!(pattern.find_all("").len(), 1);
+    }
+
+    #[test]
+    fn grepr_lines() {
+        let text = "one\ntwo\nthree\nfour";
+        let matches: Vec<_> = text.lines().grepr(Pattern::new("o")).collect();
+        let numbers: Vec<_> = matches.iter().map(|m| m.line_number).collect();
+        assert_eq!(numbers, vec![1, 2, 4]);
+        assert_eq!(matches[0].matched().collect::<Vec<_>>(), vec!["o"]);
+    }
+
+    #[test]
+    fn highlighting() {
+        colored::control::set_override(false);
+        let line_match = "a-b-a".lines().grepr(Pattern::new("a")).next().unwrap();
+        assert_eq!(line_match.highlighted(), "a-b-a");
+        assert_eq!(line_match.matches, vec![0..1, 4..5]);
+    }
+}
@@ -0,0 +1,135 @@
+//! A tour of the features of [`ProgBar`](super::ProgBar). Run with `zung mini progbar demo`.
+
+use std::thread::{self, sleep};
+use std::time::Duration;
+
+use rand::Rng;
+
+use super::{BarPreset, MultiProgBar, ProgBarExt};
+
+const ITEMS: usize = 40;
+const WORKERS: usize = 4;
+
+// Size of the chunks of the simulated transfer.
+const CHUNK: usize = 4096;
+
+// Simulates the work done on a single item.
+fn work(speed: u64, jitter: u64) {
+    let jitter = if jitter == 0 {
+        0
+    } else {
+        rand::thread_rng().gen_range(0..=jitter)
+    };
+    sleep(Duration::from_millis(speed + jitter));
+}
+
+fn header(title: &str) {
+    eprintln!("\n{title}");
+}
+
+/// Runs the demo. `speed` is the number of milliseconds spent on each simulated item, plus a
+/// random delay of up to `jitter` milliseconds.
+pub(crate) fn run_demo(speed: u64, jitter: u64) {
+    header("Spinner with an item counter (iterator of unknown length):");
+    for _ in (0..ITEMS)
+        .progbar()
+        .with_message("Fetching...")
+        .with_spinner(&["◐", "◓", "◑", "◒"])
+    {
+        work(speed, jitter);
+    }
+
+    for (name, preset) in [
+        ("Ascii", BarPreset::Ascii),
+        ("Blocks", BarPreset::Blocks),
+        ("Dots", BarPreset::Dots),
+    ] {
+        header(&format!("Bounded bar with the {name} preset:"));
+        for _ in (0..ITEMS)
+            .progbar()
+            .with_bounds("|", "|")
+            .bar_preset(preset)
+        {
+            work(speed, jitter);
+        }
+    }
+
+    header("Nested workload (a bar per stage):");
+    for stage in 1..=3 {
+        eprintln!("Stage {stage}/3");
+        for _ in (0..ITEMS / 2).progbar().with_bounds('[', ']') {
+            work(speed, jitter);
+        }
+    }
+
+    header(&format!(
+        "Parallel workload ({WORKERS} threads sharing a bar):"
+    ));
+    let progbar = (0..ITEMS * WORKERS)
+        .progbar()
+        .with_bounds('[', ']')
+        .bar_preset(BarPreset::Blocks);
+    let workers: Vec<_> = (0..WORKERS)
+        .map(|_| {
+            let handle = progbar.handle();
+            thread::spawn(move || {
+                for _ in 0..ITEMS {
+                    work(speed, jitter);
+                    handle.inc(1);
+                }
+            })
+        })
+        .collect();
+
+    while workers.iter().any(|worker| !worker.is_finished()) {
+        progbar.refresh();
+        sleep(Duration::from_millis(50));
+    }
+    progbar.refresh();
+    eprintln!();
+
+    header(&format!(
+        "Multiple bars ({WORKERS} threads with a bar each):"
+    ));
+    let mut bars = MultiProgBar::new().bar_preset(BarPreset::Blocks);
+    let workers: Vec<_> = (1..=WORKERS)
+        .map(|worker| {
+            // The workers get different amounts of work, so they finish one after the other.
+            let items = ITEMS * worker / WORKERS;
+            let handle = bars.add(format!("Worker {worker}"), items);
+            thread::spawn(move || {
+                for _ in 0..items {
+                    work(speed, jitter);
+                    handle.inc(1);
+                }
+            })
+        })
+        .collect();
+
+    while workers.iter().any(|worker| !worker.is_finished()) {
+        bars.refresh();
+        sleep(Duration::from_millis(50));
+    }
+    bars.refresh();
+
+    header("Bounded bar with an ETA:");
+    for _ in (0..ITEMS).progbar().with_bounds('[', ']').with_eta() {
+        work(speed, jitter);
+    }
+
+    header(&format!(
+        "Byte mode ({} in chunks of {} KiB):",
+        super::format_bytes(ITEMS * CHUNK),
+        CHUNK / 1024
+    ));
+    for byte in (0..ITEMS * CHUNK)
+        .progbar()
+        .with_bounds('[', ']')
+        .with_bytes()
+        .with_eta()
+    {
+        if byte % CHUNK == 0 {
+            work(speed, jitter);
+        }
+    }
+}
@@ -19,7 +19,10 @@
 //!   iterators. Bounded bars can display percentages, while unbounded bars display the progress
 //!   incrementally.
 //! - **Customizable Styles**: Modify the progress bar's appearance with custom delimiters and bar
-//!   styles.
+//!   styles, or pick one of the built-in [`BarPreset`]s.
+//! - **ETA & Bytes**: Bounded bars can show the estimated time left and count bytes, with their
+//!   throughput, instead of items.
+//! - **Multiple Bars**: A [`MultiProgBar`] draws a bar per task, one under the other.
 //! - **Terminal Display**: The library displays a live progress bar in the terminal, updating in
 //!   real-time during iteration.
 //!
@@ -41,7 +44,7 @@
 //!
 //! The output will look something like this in the terminal as the progress bar updates:
 //! ```text
-//!  ⠼ Loading...
+//!  ⠼ Loading... (42 items)
 //! ```
 //!
 //! ## Bounded Progress Bar with Custom Delimiters
@@ -81,33 +84,160 @@
 //! ```text
 //! [ 30%] <===         >
 //! ```
+//!
+//! ## Output
+//!
+//! The progress bar is drawn on `stderr` by default so that it does not get mixed with the actual
+//! output of a program when it is piped. Use [`with_target()`](`ProgBar::with_target()`) to draw
+//! it on `stdout` instead. Bounded progress bars are only redrawn when their content changes.
 
-use std::cell::Cell;
-use std::fmt::{Debug, Display};
+use std::cell::{Cell, RefCell};
+use std::fmt::{Debug, Display, Write as _};
 use std::io::{self, Write};
-use std::thread;
-use std::time::Duration;
+use std::sync::atomic::{AtomicUsize, Ordering};
+use std::sync::Arc;
+use std::time::{Duration, Instant};
+
+use unicode_width::UnicodeWidthStr;
 
-// `BarStyle` is used to define the appearance of the progress bar. It contains
-// a single string field that holds the character(s) used to visually represent the progress.
+pub(crate) mod demo;
+
+// `BarStyle` is used to define the appearance of the progress bar.
 #[derive(Debug)]
-struct BarStyle(String);
+enum BarStyle {
+    // A string repeated to fill the bar. `width` is the number of terminal cells the string
+    // occupies.
+    Repeat {
+        bar: String,
+        width: usize,
+    },
+
+    // Full cells followed by a partially filled cell, for smooth sub-cell progress. `partials` are
+    // in increasing order of fill.
+    Gradient {
+        full: char,
+        partials: &'static [char],
+    },
+}
 
 impl BarStyle {
     fn new(bar_style: String) -> Self {
-        Self(bar_style)
+        let width = bar_style.width();
+        Self::Repeat {
+            bar: bar_style,
+            width,
+        }
+    }
+
+    // Renders the cells of the bar after `step` of `len` items. The result always occupies exactly
+    // `width` terminal cells.
+    fn render(&self, step: usize, len: usize, width: usize) -> String {
+        let step = step.min(len);
+        match self {
+            BarStyle::Repeat { bar, width: cell } => {
+                let filled = (step * width).checked_div(len).unwrap_or(width);
+                let repeats = filled / (*cell).max(1);
+                let drawn = repeats * (*cell).max(1);
+                let mut out = bar.repeat(repeats);
+                out.push_str(&" ".repeat(width.saturating_sub(drawn)));
+                out
+            }
+            BarStyle::Gradient { full, partials } => {
+                let levels = partials.len() + 1;
+                let units = (step * width * levels)
+                    .checked_div(len)
+                    .unwrap_or(width * levels);
+                let (full_cells, partial) = (units / levels, units % levels);
+
+                let mut out: String = std::iter::repeat_n(*full, full_cells).collect();
+                let mut drawn = full_cells;
+                if partial > 0 {
+                    out.push(partials[partial - 1]);
+                    drawn += 1;
+                }
+                out.push_str(&" ".repeat(width - drawn));
+                out
+            }
+        }
     }
 }
 
 impl Display for BarStyle {
     fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
-        write!(f, "{}", self.0)
+        match self {
+            BarStyle::Repeat { bar, .. } => write!(f, "{bar}"),
+            BarStyle::Gradient { full, .. } => write!(f, "{full}"),
+        }
     }
 }
 
 impl Default for BarStyle {
     fn default() -> Self {
-        Self::new(String::from("#"))
+        BarPreset::Ascii.into()
+    }
+}
+
+/// Built-in styles for a [`Bounded`] progress bar. See [`ProgBar::bar_preset`].
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub enum BarPreset {
+    /// `[#####     ]`. The default style.
+    #[default]
+    Ascii,
+
+    /// `[█████▌    ]`. Uses partial blocks for smooth progress within a cell.
+    Blocks,
+
+    /// `[⣿⣿⣿⣿⣿⡆    ]`. Uses braille dots for smooth progress within a cell.
+    Dots,
+}
+
+impl From<BarPreset> for BarStyle {
+    fn from(preset: BarPreset) -> Self {
+        match preset {
+            BarPreset::Ascii => BarStyle::new(String::from("#")),
+            BarPreset::Blocks => BarStyle::Gradient {
+                full: '█',
+                partials: &['▏', '▎', '▍', '▌', '▋', '▊', '▉'],
+            },
+            BarPreset::Dots => BarStyle::Gradient {
+                full: '⣿',
+                partials: &['⡀', '⡄', '⡆', '⡇', '⣇', '⣧', '⣷'],
+            },
+        }
+    }
+}
+
+/// Maximum number of cells of a [`Bounded`] progress bar. Iterators longer than this advance the
+/// bar by one cell every `len / width` items.
+const DEFAULT_WIDTH: usize = 50;
+
+/// The stream on which a [`ProgBar`] is drawn. See [`ProgBar::with_target`].
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub enum Target {
+    /// Draw on the standard error. This is the default so that the progress bar does not end up in
+    /// the output of a program when it is piped.
+    #[default]
+    Stderr,
+
+    /// Draw on the standard output.
+    Stdout,
+}
+
+impl Target {
+    // Errors (such as a closed pipe) are ignored since a progress bar is not worth crashing for.
+    fn write(self, frame: &str) {
+        match self {
+            Target::Stderr => {
+                let mut stderr = io::stderr().lock();
+                let _ = stderr.write_all(frame.as_bytes());
+                let _ = stderr.flush();
+            }
+            Target::Stdout => {
+                let mut stdout = io::stdout().lock();
+                let _ = stdout.write_all(frame.as_bytes());
+                let _ = stdout.flush();
+            }
+        }
     }
 }
 
@@ -115,17 +245,50 @@ impl Default for BarStyle {
 /// state of the [`ProgBar`]. See [`ProgBar::with_bounds`] method if you want to use a [`Bounded`]
 /// ProgBar.
 pub struct UnBounded {
-    spinner: &'static [char; 10],
+    spinner: Vec<String>,
     spinner_step: Cell<usize>,
+    interval: Duration,
+    last_draw: Cell<Option<Instant>>,
+}
+
+/// Default frames of the spinner of an [`UnBounded`] progress bar.
+const DEFAULT_SPINNER: [&str; 10] = ["⠋", "⠙", "⠹", "⠸", "⠼", "⠴", "⠦", "⠧", "⠇", "⠏"];
+
+/// Default minimum time between two frames of the spinner.
+const DEFAULT_INTERVAL: Duration = Duration::from_millis(80);
+
+impl UnBounded {
+    // Returns the current frame of the spinner and moves to the next one.
+    fn next_frame(&self) -> &str {
+        let step = self.spinner_step.get();
+        self.spinner_step.set((step + 1) % self.spinner.len());
+        &self.spinner[step]
+    }
+
+    fn draw<T>(&self, progress: &ProgBar<T, Self>) {
+        self.last_draw.set(Some(Instant::now()));
+        progress.target.write(&format!(
+            "  {} {} ({} items)\r",
+            self.next_frame(),
+            progress.message,
+            progress.position()
+        ));
+    }
 }
 
 /// Internal state of `ProgBar`. Bounded means the size of the Iterator is known. This is
 /// constructed with [`ProgBar::with_bounds`] method.
 pub struct Bounded<D: Display> {
     len: usize,
+    width: usize,
     percentage: Cell<u8>,
     delims: (D, D),
     bar: BarStyle,
+    last_frame: RefCell<String>,
+    started: Instant,
+    last_draw: Cell<Option<Instant>>,
+    eta: bool,
+    bytes: bool,
 }
 
 /// Created through the [`progbar()`](ProgBarExt::progbar()) method called over any iterator.
@@ -133,8 +296,58 @@ pub struct Bounded<D: Display> {
 pub struct ProgBar<T, Bound> {
     iterator: T,
     step: usize,
+    shared: Arc<AtomicUsize>, // Progress made through the `ProgBarHandle`s.
     bound: Bound,
     message: String,
+    target: Target,
+}
+
+/// A cloneable handle to advance a [`ProgBar`] from other threads or async tasks.
+///
+/// Created with [`ProgBar::handle`]. The progress made through the handles is added to the items
+/// yielded by the iterator of the [`ProgBar`], and is drawn whenever the owner of the [`ProgBar`]
+/// iterates or calls `refresh()`.
+///
+/// # Example
+///
+/// ```rust
+/// use zung_mini::progbar::ProgBarExt;
+/// use std::thread;
+///
+/// let progbar = (0..100).progbar().with_bounds('[', ']');
+/// let workers: Vec<_> = (0..4)
+///     .map(|_| {
+///         let handle = progbar.handle();
+///         thread::spawn(move || {
+///             for _ in 0..25 {
+///                 // Perform work
+///                 handle.inc(1);
+///             }
+///         })
+///     })
+///     .collect();
+///
+/// for worker in workers {
+///     worker.join().unwrap();
+///     progbar.refresh();
+/// }
+/// assert_eq!(progbar.position(), 100);
+/// ```
+#[derive(Debug, Clone)]
+pub struct ProgBarHandle {
+    progress: Arc<AtomicUsize>,
+}
+
+impl ProgBarHandle {
+    /// Advances the progress bar by `n` items.
+    pub fn inc(&self, n: usize) {
+        self.progress.fetch_add(n, Ordering::Relaxed);
+    }
+
+    /// Returns the number of items advanced through all the handles of the progress bar.
+    pub fn get(&self) -> usize {
+        self.progress.load(Ordering::Relaxed)
+    }
 }
 
 impl<T> ProgBar<T, UnBounded> {
@@ -143,10 +356,14 @@ impl<T> ProgBar<T, UnBounded> {
         Self {
             iterator,
             step: 0,
+            shared: Arc::new(AtomicUsize::new(0)),
             message: String::from("Loading..."),
+            target: Target::default(),
             bound: UnBounded {
-                spinner: &['⠋', '⠙', '⠹', '⠸', '⠼', '⠴', '⠦', '⠧', '⠇', '⠏'],
+                spinner: DEFAULT_SPINNER.map(String::from).to_vec(),
                 spinner_step: Cell::new(0),
+                interval: DEFAULT_INTERVAL,
+                last_draw: Cell::new(None),
             },
         }
     }
@@ -175,29 +392,97 @@ impl<T> ProgBar<T, UnBounded> {
         self.message = msg.to_string();
         self
     }
+
+    /// Sets the frames of the spinner. The frames are cycled through in order. An empty set of
+    /// frames is ignored.
+    ///
+    /// # Example
+    ///
+    /// ```rust
+    /// use zung_mini::progbar::ProgBarExt;
+    ///
+    /// for _ in (0..).progbar().with_spinner(&["|", "/", "-", "\\"]) {
+    ///         // Perform work here
+    ///         # break;
+    /// };
+    /// ```
+    pub fn with_spinner(mut self, frames: &[&str]) -> Self {
+        if !frames.is_empty() {
+            self.bound.spinner = frames.iter().map(|frame| frame.to_string()).collect();
+            self.bound.spinner_step.set(0);
+        }
+        self
+    }
+
+    /// Sets the minimum time between two frames of the spinner. Items yielded in between do not
+    /// redraw the spinner. Defaults to 80 milliseconds.
+    pub fn with_interval(mut self, interval: Duration) -> Self {
+        self.bound.interval = interval;
+        self
+    }
+
+    /// Redraws the progress bar with the current progress, without advancing the iterator. Useful
+    /// when the progress is made through a [`ProgBarHandle`].
+    pub fn refresh(&self) {
+        self.bound.display(self);
+    }
 }
 
-impl<T, Bound> ProgBar<T, Bound> {}
+impl<T, Bound> ProgBar<T, Bound> {
+    /// Sets the stream on which the progress bar is drawn. Defaults to [`Target::Stderr`].
+    ///
+    /// # Example
+    ///
+    /// ```rust
+    /// use zung_mini::progbar::{ProgBarExt, Target};
+    ///
+    /// for _ in (0..10).progbar().with_target(Target::Stdout) {
+    ///     // Perform work here
+    /// }
+    /// ```
+    pub fn with_target(mut self, target: Target) -> Self {
+        self.target = target;
+        self
+    }
+
+    /// Returns a [`ProgBarHandle`] through which the progress bar can be advanced from other
+    /// threads.
+    pub fn handle(&self) -> ProgBarHandle {
+        ProgBarHandle {
+            progress: Arc::clone(&self.shared),
+        }
+    }
+
+    /// Returns the current progress: the number of items yielded by the iterator plus the progress
+    /// made through the [`ProgBarHandle`]s.
+    pub fn position(&self) -> usize {
+        self.step + self.shared.load(Ordering::Relaxed)
+    }
+}
 
 trait ProgBarDisplay: Sized {
     fn display<T>(&self, progress: &ProgBar<T, Self>);
+
+    // Called once the iterator is exhausted, right before the final newline.
+    fn finish<T>(&self, progress: &ProgBar<T, Self>) {
+        self.display(progress);
+    }
 }
 
 impl ProgBarDisplay for UnBounded {
     fn display<T>(&self, progress: &ProgBar<T, Self>) {
-        let spinner_step = self.spinner_step.get();
-        self.spinner_step.set(spinner_step + 1);
-        if spinner_step > self.spinner.len() - 2 {
-            self.spinner_step.set(0);
+        let throttled = self
+            .last_draw
+            .get()
+            .is_some_and(|last| last.elapsed() < self.interval);
+        if !throttled {
+            self.draw(progress);
         }
+    }
 
-        print!(
-            "  {} {}\r",
-            progress.bound.spinner[spinner_step], progress.message
-        );
-        io::stdout().flush().unwrap();
-
-        thread::sleep(Duration::from_millis(50));
+    // Always draw the final count.
+    fn finish<T>(&self, progress: &ProgBar<T, Self>) {
+        self.draw(progress);
     }
 }
 
@@ -206,20 +491,65 @@ where
     D: Display,
 {
     fn display<T>(&self, progbar: &ProgBar<T, Self>) {
+        // The ETA and the throughput change all the time, so they are throttled like a spinner,
+        // except for the final frame.
+        let throttled = (self.eta || self.bytes)
+            && progbar.position() < self.len
+            && self
+                .last_draw
+                .get()
+                .is_some_and(|last| last.elapsed() < DEFAULT_INTERVAL);
+        if throttled {
+            return;
+        }
+        self.last_draw.set(Some(Instant::now()));
+
         progbar.calculate_percentage();
-        if progbar.step <= 1 {
-            print!("[{:>3}%] \r", 0);
+        let frame = self.frame(progbar.position(), self.started.elapsed());
+
+        // Nothing to redraw if the bar did not move.
+        if *self.last_frame.borrow() != frame {
+            progbar.target.write(&frame);
+            self.last_frame.replace(frame);
         }
+    }
+}
 
-        print!(
-            "[{:>3}%] {}{}{}{}\r",
+impl<D> Bounded<D>
+where
+    D: Display,
+{
+    // Renders the whole line of the progress bar at the provided step, `elapsed` after the bar was
+    // created.
+    fn frame(&self, step: usize, elapsed: Duration) -> String {
+        let mut frame = String::new();
+        write!(
+            frame,
+            "[{:>3}%] {}{}{}",
             self.percentage.get(),
             self.delims.0,
-            self.bar.to_string().repeat(progbar.step),
-            " ".repeat(self.len - progbar.step),
+            self.bar.render(step, self.len, self.width),
             self.delims.1
-        );
-        io::stdout().flush().unwrap();
+        )
+        .expect("Writing to a String does not fail");
+
+        if self.bytes {
+            let rate = (step as f64 / elapsed.as_secs_f64().max(1e-3)) as usize;
+            write!(
+                frame,
+                " {:>10} / {:<10} {:>12}",
+                format_bytes(step),
+                format_bytes(self.len),
+                format!("{}/s", format_bytes(rate))
+            )
+            .expect("Writing to a String does not fail");
+        }
+        if self.eta {
+            let eta = eta(step, self.len, elapsed).map_or(String::from("--:--"), format_duration);
+            write!(frame, " ETA {eta:>8}").expect("Writing to a String does not fail");
+        }
+        frame.push('\r');
+        frame
     }
 }
 
@@ -270,18 +600,27 @@ where
     where
         D: Display,
     {
+        let len = self.iterator.len();
         let bound = Bounded {
-            len: self.iterator.len(),
+            len,
+            width: len.min(DEFAULT_WIDTH),
             percentage: Cell::new(0),
             delims: (bound_start, bound_end),
             bar: BarStyle::default(),
+            last_frame: RefCell::new(String::new()),
+            started: Instant::now(),
+            last_draw: Cell::new(None),
+            eta: false,
+            bytes: false,
         };
 
         ProgBar {
             iterator: self.iterator,
             step: self.step,
+            shared: self.shared,
             bound,
             message: String::new(),
+            target: self.target,
         }
     }
 }
@@ -293,9 +632,9 @@ where
     /// Sets the style of the progress bar.
     ///
     /// This method allows customizing the appearance of the progress bar by specifying
-    /// a type that implements the `BarStyle` trait. The provided `bar` parameter
-    /// should be a reference to an instance of a type that implements both `Display`
-    /// and `Debug` traits.
+    /// a type that implements the `Display` trait. The displayed string is repeated to fill the bar.
+    /// Its width is measured in terminal cells, so multi-character and wide (e.g. CJK or emoji)
+    /// styles keep the bar aligned.
     ///
     /// # Examples
     ///
@@ -323,10 +662,264 @@ where
         self
     }
 
+    /// Redraws the progress bar with the current progress, without advancing the iterator. Useful
+    /// when the progress is made through a [`ProgBarHandle`].
+    pub fn refresh(&self) {
+        self.bound.display(self);
+    }
+
+    /// Sets one of the built-in [`BarPreset`] styles of the progress bar.
+    ///
+    /// # Example
+    ///
+    /// ```rust
+    /// use zung_mini::progbar::{BarPreset, ProgBarExt};
+    ///
+    /// for _ in (0..100).progbar().with_bounds("|", "|").bar_preset(BarPreset::Blocks) {
+    ///     // Do some calculation
+    /// }
+    /// ```
+    pub fn bar_preset(mut self, preset: BarPreset) -> ProgBar<T, Bounded<D>> {
+        self.bound.bar = preset.into();
+        self
+    }
+
+    /// Shows the estimated time left after the bar, from the time taken by the items so far.
+    ///
+    /// # Example
+    ///
+    /// ```rust
+    /// use zung_mini::progbar::ProgBarExt;
+    ///
+    /// for _ in (0..100).progbar().with_bounds('[', ']').with_eta() {
+    ///     // Do some calculation
+    /// }
+    /// ```
+    ///
+    /// The output will look like this:
+    ///
+    /// ```text
+    /// [ 30%] [###       ] ETA    00:42
+    /// ```
+    pub fn with_eta(mut self) -> ProgBar<T, Bounded<D>> {
+        self.bound.eta = true;
+        self
+    }
+
+    /// Counts the progress in bytes: the bar is followed by the bytes done, the total and the
+    /// throughput instead of a plain percentage. Each item of the iterator, or each unit added
+    /// through a [`ProgBarHandle`], is a byte.
+    ///
+    /// # Example
+    ///
+    /// ```rust
+    /// use zung_mini::progbar::ProgBarExt;
+    ///
+    /// let data = vec![0u8; 4096];
+    /// for _byte in data.iter().progbar().with_bounds('[', ']').with_bytes() {
+    ///     // Copy the byte
+    /// }
+    /// ```
+    ///
+    /// The output will look like this:
+    ///
+    /// ```text
+    /// [ 50%] [#####     ]    2.0 KiB / 4.0 KiB       1.0 MiB/s
+    /// ```
+    pub fn with_bytes(mut self) -> ProgBar<T, Bounded<D>> {
+        self.bound.bytes = true;
+        self
+    }
+
     fn calculate_percentage(&self) {
         self.bound
             .percentage
-            .set(((self.step as f64 / self.bound.len as f64) * 100.0) as u8);
+            .set(percentage(self.position(), self.bound.len));
+    }
+}
+
+// Percentage of `len` done after `step` items. An empty bar is complete.
+fn percentage(step: usize, len: usize) -> u8 {
+    if len == 0 {
+        100
+    } else {
+        (step.min(len) as f64 / len as f64 * 100.0) as u8
+    }
+}
+
+// Estimates the time left at the pace of the items done so far. Unknown until an item is done.
+fn eta(step: usize, len: usize, elapsed: Duration) -> Option<Duration> {
+    if step == 0 {
+        return None;
+    }
+    let left = len.saturating_sub(step);
+    Some(elapsed.mul_f64(left as f64 / step as f64))
+}
+
+// Formats a duration as `mm:ss`, or `h:mm:ss` past an hour.
+fn format_duration(duration: Duration) -> String {
+    let secs = duration.as_secs();
+    let (hours, minutes, secs) = (secs / 3600, secs / 60 % 60, secs % 60);
+    if hours > 0 {
+        format!("{hours}:{minutes:02}:{secs:02}")
+    } else {
+        format!("{minutes:02}:{secs:02}")
+    }
+}
+
+// Formats a number of bytes with binary units, e.g. `1.5 KiB`.
+fn format_bytes(bytes: usize) -> String {
+    const UNITS: [&str; 5] = ["KiB", "MiB", "GiB", "TiB", "PiB"];
+    if bytes < 1024 {
+        return format!("{bytes} B");
+    }
+    let mut size = bytes as f64 / 1024.0;
+    let mut unit = 0;
+    while size >= 1024.0 && unit < UNITS.len() - 1 {
+        size /= 1024.0;
+        unit += 1;
+    }
+    format!("{size:.1} {}", UNITS[unit])
+}
+
+/// Several progress bars drawn one under the other, e.g. one per download or per worker.
+///
+/// The bars are advanced through the [`ProgBarHandle`]s returned by [`add()`](MultiProgBar::add),
+/// from any thread, and are drawn together by [`refresh()`](MultiProgBar::refresh). The
+/// `MultiProgBar` owns the lines under the cursor: each refresh moves the cursor back up to redraw
+/// them in place, so nothing else should be written to the same stream while it is in use.
+///
+/// # Example
+///
+/// ```rust
+/// use zung_mini::progbar::MultiProgBar;
+/// use std::thread;
+///
+/// let mut bars = MultiProgBar::new();
+/// let workers: Vec<_> = ["first", "second"]
+///     .into_iter()
+///     .map(|name| {
+///         let handle = bars.add(name, 10);
+///         thread::spawn(move || {
+///             for _ in 0..10 {
+///                 // Perform work
+///                 handle.inc(1);
+///             }
+///         })
+///     })
+///     .collect();
+///
+/// for worker in workers {
+///     worker.join().unwrap();
+///     bars.refresh();
+/// }
+/// assert!(bars.is_finished());
+/// ```
+///
+/// The output will look like this:
+///
+/// ```text
+/// first  [100%] [##########]
+/// second [ 40%] [####      ]
+/// ```
+#[derive(Debug)]
+pub struct MultiProgBar {
+    bars: Vec<Line>,
+    bar: BarStyle,
+    target: Target,
+    drawn: Cell<usize>, // Lines drawn by the last refresh, which the next one goes back up over.
+}
+
+// A bar of a `MultiProgBar`.
+#[derive(Debug)]
+struct Line {
+    label: String,
+    len: usize,
+    progress: Arc<AtomicUsize>,
+}
+
+impl Default for MultiProgBar {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl MultiProgBar {
+    /// Creates a `MultiProgBar` without any bar. Bars are added with [`add()`](Self::add).
+    pub fn new() -> Self {
+        Self {
+            bars: Vec::new(),
+            bar: BarStyle::default(),
+            target: Target::default(),
+            drawn: Cell::new(0),
+        }
+    }
+
+    /// Sets the stream on which the bars are drawn. Defaults to [`Target::Stderr`].
+    pub fn with_target(mut self, target: Target) -> Self {
+        self.target = target;
+        self
+    }
+
+    /// Sets one of the built-in [`BarPreset`] styles of the bars.
+    pub fn bar_preset(mut self, preset: BarPreset) -> Self {
+        self.bar = preset.into();
+        self
+    }
+
+    /// Adds a bar of `len` items under the others, labelled with `label`, and returns the handle
+    /// through which it is advanced.
+    pub fn add(&mut self, label: impl Display, len: usize) -> ProgBarHandle {
+        let progress = Arc::new(AtomicUsize::new(0));
+        self.bars.push(Line {
+            label: label.to_string(),
+            len,
+            progress: Arc::clone(&progress),
+        });
+        ProgBarHandle { progress }
+    }
+
+    /// Redraws all the bars with their current progress.
+    pub fn refresh(&self) {
+        self.target.write(&self.frame());
+        self.drawn.set(self.bars.len());
+    }
+
+    /// Returns whether every bar reached its length.
+    pub fn is_finished(&self) -> bool {
+        self.bars
+            .iter()
+            .all(|line| line.progress.load(Ordering::Relaxed) >= line.len)
+    }
+
+    // Renders the bars, preceded by the moves of the cursor back to the first line of the previous
+    // refresh. Each line is cleared to its end, as a shorter line would leave the previous one
+    // showing.
+    fn frame(&self) -> String {
+        let label_width = self
+            .bars
+            .iter()
+            .map(|line| line.label.width())
+            .max()
+            .unwrap_or(0);
+
+        let mut frame = String::new();
+        if self.drawn.get() > 0 {
+            write!(frame, "\x1b[{}A", self.drawn.get()).expect("Writing to a String does not fail");
+        }
+        for line in &self.bars {
+            let step = line.progress.load(Ordering::Relaxed);
+            let padding = " ".repeat(label_width - line.label.width());
+            write!(
+                frame,
+                "\r{}{padding} [{:>3}%] [{}]\x1b[K\n",
+                line.label,
+                percentage(step, line.len),
+                self.bar.render(step, line.len, line.len.min(DEFAULT_WIDTH))
+            )
+            .expect("Writing to a String does not fail");
+        }
+        frame
     }
 }
 
@@ -430,9 +1023,11 @@ where
     fn next(&mut self) -> Option<Self::Item> {
         let next = self.iterator.next();
 
-        self.bound.display(self);
         if next.is_none() {
-            println!();
+            self.bound.finish(self);
+            self.target.write("\n");
+        } else {
+            self.bound.display(self);
         }
         self.step += 1;
         next
@@ -498,16 +1093,54 @@ mod tests {
     #[test]
     fn test_unbounded_progbar() {
         let mut progbar = (0..).progbar();
-        assert_eq!(
-            progbar.bound.spinner,
-            &['⠋', '⠙', '⠹', '⠸', '⠼', '⠴', '⠦', '⠧', '⠇', '⠏']
-        );
+        assert_eq!(progbar.bound.spinner, DEFAULT_SPINNER);
         for _ in 0..5 {
             progbar.next();
         }
         assert_eq!(progbar.step, 5);
     }
 
+    #[test]
+    fn test_spinner_frames_cycle() {
+        let progbar = (0..).progbar().with_spinner(&["a", "b", "c"]);
+        let frames: Vec<_> = (0..7)
+            .map(|_| progbar.bound.next_frame().to_string())
+            .collect();
+        assert_eq!(frames, ["a", "b", "c", "a", "b", "c", "a"]);
+
+        // Empty frames are ignored.
+        let progbar = progbar.with_spinner(&[]);
+        assert_eq!(progbar.bound.spinner, ["a", "b", "c"]);
+    }
+
+    #[test]
+    fn test_spinner_interval() {
+        let mut progbar = (0..)
+            .progbar()
+            .with_spinner(&["a", "b"])
+            .with_interval(Duration::from_secs(60));
+
+        // Only the first item draws a frame within the interval.
+        for _ in 0..10 {
+            progbar.next();
+        }
+        assert_eq!(progbar.bound.spinner_step.get(), 1);
+
+        let mut progbar = progbar.with_interval(Duration::ZERO);
+        progbar.next();
+        assert_eq!(progbar.bound.spinner_step.get(), 0);
+    }
+
+    #[test]
+    fn test_spinner_finish() {
+        let mut progbar = (0..3).progbar().with_interval(Duration::from_secs(60));
+        while progbar.next().is_some() {}
+
+        // The first item and the final count are drawn.
+        assert_eq!(progbar.bound.spinner_step.get(), 2);
+        assert_eq!(progbar.step, 4);
+    }
+
     #[test]
     fn test_progress_display() {
         let mut progbar = (0..10).progbar().with_bounds('[', ']');
@@ -518,6 +1151,113 @@ mod tests {
         }
     }
 
+    #[test]
+    fn test_wide_bar_style() {
+        let progbar = (0..4).progbar().with_bounds('[', ']').bar_style("世");
+        assert_eq!(progbar.bound.bar.render(1, 4, 4), "    ");
+        assert_eq!(progbar.bound.bar.render(2, 4, 4), "世  ");
+        assert_eq!(progbar.bound.bar.render(4, 4, 4), "世世");
+
+        let progbar = (0..4).progbar().with_bounds('[', ']').bar_style("=>");
+        assert_eq!(progbar.bound.bar.render(3, 4, 4), "=>  ");
+    }
+
+    #[test]
+    fn test_bar_presets() {
+        let progbar = (0..16).progbar().with_bounds('[', ']');
+        assert_eq!(progbar.bound.bar.render(8, 16, 4), "##  ");
+
+        let progbar = progbar.bar_preset(BarPreset::Blocks);
+        assert_eq!(progbar.bound.bar.to_string(), "█");
+        assert_eq!(progbar.bound.bar.render(0, 16, 2), "  ");
+        assert_eq!(progbar.bound.bar.render(1, 16, 2), "▏ ");
+        assert_eq!(progbar.bound.bar.render(9, 16, 2), "█▏");
+        assert_eq!(progbar.bound.bar.render(16, 16, 2), "██");
+
+        let progbar = progbar.bar_preset(BarPreset::Dots);
+        assert_eq!(progbar.bound.bar.render(4, 16, 2), "⡇ ");
+        for step in 0..=16 {
+            assert_eq!(progbar.bound.bar.render(step, 16, 2).width(), 2);
+        }
+    }
+
+    #[test]
+    fn test_bounded_frame() {
+        let progbar = (0..4).progbar().with_bounds('[', ']');
+        assert_eq!(progbar.bound.frame(0, Duration::ZERO), "[  0%] [    ]\r");
+        assert_eq!(progbar.bound.frame(2, Duration::ZERO), "[  0%] [##  ]\r");
+        assert_eq!(progbar.bound.frame(9, Duration::ZERO), "[  0%] [####]\r");
+
+        // Long iterators are scaled down to the default width.
+        let progbar = (0..1000).progbar().with_bounds('[', ']');
+        assert_eq!(progbar.bound.width, DEFAULT_WIDTH);
+        assert_eq!(
+            progbar.bound.frame(19, Duration::ZERO),
+            progbar.bound.frame(0, Duration::ZERO)
+        );
+        assert_ne!(
+            progbar.bound.frame(20, Duration::ZERO),
+            progbar.bound.frame(0, Duration::ZERO)
+        );
+    }
+
+    #[test]
+    fn test_redraw_only_on_change() {
+        let mut progbar = (0..1000).progbar().with_bounds('[', ']');
+        progbar.next();
+        let first = progbar.bound.last_frame.borrow().clone();
+        assert!(first.starts_with("[  0%]"));
+
+        // Neither the percentage nor the bar moved.
+        progbar.next();
+        assert_eq!(*progbar.bound.last_frame.borrow(), first);
+
+        for _ in 0..20 {
+            progbar.next();
+        }
+        assert_ne!(*progbar.bound.last_frame.borrow(), first);
+    }
+
+    #[test]
+    fn test_target() {
+        let progbar = (0..10).progbar();
+        assert_eq!(progbar.target, Target::Stderr);
+
+        let progbar = progbar.with_target(Target::Stdout).with_bounds('[', ']');
+        assert_eq!(progbar.target, Target::Stdout);
+    }
+
+    #[test]
+    fn test_handle_from_threads() {
+        let progbar = (0..100).progbar();
+        let handle = progbar.handle();
+        let mut progbar = progbar.with_bounds('[', ']');
+
+        let workers: Vec<_> = (0..4)
+            .map(|_| {
+                let handle = handle.clone();
+                std::thread::spawn(move || {
+                    for _ in 0..10 {
+                        handle.inc(1);
+                    }
+                })
+            })
+            .collect();
+        for worker in workers {
+            worker.join().unwrap();
+        }
+
+        assert_eq!(handle.get(), 40);
+        progbar.next();
+        assert_eq!(progbar.position(), 41);
+        assert_eq!(progbar.bound.percentage.get(), 40);
+
+        progbar.handle().inc(9);
+        progbar.refresh();
+        assert_eq!(progbar.bound.percentage.get(), 50);
+        assert!(progbar.bound.last_frame.borrow().starts_with("[ 50%]"));
+    }
+
     #[test]
     fn test_with_message() {
         let mut progbar = (0..).progbar().with_message("Loading...");
@@ -533,4 +1273,75 @@ mod tests {
         assert_eq!(progbar.next(), Some(0)); // First item
         assert_eq!(progbar.step, 1); // Progress updated by 1 step
     }
+
+    #[test]
+    fn test_eta_and_bytes_frame() {
+        let progbar = (0..4096).progbar().with_bounds('[', ']').with_eta();
+        assert_eq!(
+            progbar.bound.frame(0, Duration::ZERO),
+            format!("[  0%] [{}] ETA    --:--\r", " ".repeat(DEFAULT_WIDTH))
+        );
+        assert!(progbar
+            .bound
+            .frame(1024, Duration::from_secs(10))
+            .ends_with(" ETA    00:30\r"));
+
+        let progbar = progbar.with_bytes();
+        let frame = progbar.bound.frame(2048, Duration::from_secs(2));
+        assert!(
+            frame.ends_with("    2.0 KiB / 4.0 KiB       1.0 KiB/s ETA    00:02\r"),
+            "{frame:?}"
+        );
+    }
+
+    #[test]
+    fn test_eta() {
+        assert_eq!(eta(0, 10, Duration::from_secs(5)), None);
+        assert_eq!(
+            eta(1, 10, Duration::from_secs(5)),
+            Some(Duration::from_secs(45))
+        );
+        assert_eq!(eta(10, 10, Duration::from_secs(5)), Some(Duration::ZERO));
+        assert_eq!(eta(12, 10, Duration::from_secs(5)), Some(Duration::ZERO));
+    }
+
+    #[test]
+    fn test_format_duration() {
+        assert_eq!(format_duration(Duration::from_millis(999)), "00:00");
+        assert_eq!(format_duration(Duration::from_secs(61)), "01:01");
+        assert_eq!(format_duration(Duration::from_secs(3600 + 62)), "1:01:02");
+    }
+
+    #[test]
+    fn test_format_bytes() {
+        assert_eq!(format_bytes(0), "0 B");
+        assert_eq!(format_bytes(1023), "1023 B");
+        assert_eq!(format_bytes(1536), "1.5 KiB");
+        assert_eq!(format_bytes(5 * 1024 * 1024), "5.0 MiB");
+        assert_eq!(format_bytes(usize::MAX), "16384.0 PiB");
+    }
+
+    #[test]
+    fn test_multi_progbar() {
+        let mut bars = MultiProgBar::new();
+        let first = bars.add("first", 4);
+        let second = bars.add("second", 2);
+        assert!(!bars.is_finished());
+
+        first.inc(2);
+        assert_eq!(
+            bars.frame(),
+            "\rfirst  [ 50%] [##  ]\x1b[K\n\rsecond [  0%] [  ]\x1b[K\n"
+        );
+
+        // The next frames go back up over the bars drawn.
+        bars.drawn.set(2);
+        first.inc(2);
+        second.inc(2);
+        assert_eq!(
+            bars.frame(),
+            "\x1b[2A\rfirst  [100%] [####]\x1b[K\n\rsecond [100%] [##]\x1b[K\n"
+        );
+        assert!(bars.is_finished());
+    }
 }
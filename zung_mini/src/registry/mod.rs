@@ -0,0 +1,96 @@
+//! A registry of the mini projects, built on trait objects.
+//!
+//! ## Overview
+//!
+//! Every mini project that can be run from the commandline implements the [`MiniProject`] trait.
+//! The projects are collected as `&dyn MiniProject` in a single static list, and the `zung mini`
+//! command is generated by iterating over this list. Adding a new project only requires
+//! implementing the trait and registering it in [`PROJECTS`].
+//!
+//! ### Example
+//!
+//! ```rust
+//! use zung_mini::registry;
+//!
+//! let calc = registry::find("calc").unwrap();
+//! assert_eq!(calc.name(), "calc");
+//!
+//! for project in registry::projects() {
+//!     println!("{}: {}", project.name(), project.about());
+//! }
+//! ```
+
+mod projects;
+
+use std::io;
+
+use clap::{ArgMatches, Command};
+
+/// A mini project which can be run from the commandline.
+///
+/// The trait is object safe so that the projects of different types can live in the same list.
+pub trait MiniProject: Sync {
+    /// Name of the subcommand running the project.
+    fn name(&self) -> &'static str;
+
+    /// One line description of the project, printed in the help.
+    fn about(&self) -> &'static str;
+
+    /// Adds the arguments of the project to its subcommand. Defaults to no arguments.
+    fn args(&self, cmd: Command) -> Command {
+        cmd
+    }
+
+    /// Runs the project with the arguments parsed by the subcommand built with
+    /// [`command`](MiniProject::command).
+    fn run(&self, matches: &ArgMatches) -> io::Result<()>;
+
+    /// Builds the subcommand of the project.
+    fn command(&self) -> Command {
+        self.args(Command::new(self.name()).about(self.about()))
+    }
+}
+
+/// Every registered mini project, in the order they are shown in the help.
+pub static PROJECTS: &[&dyn MiniProject] = &[
+    &projects::ProgBar,
+    &projects::Strsplit,
+    &projects::Orst,
+    &projects::Calc,
+    &projects::Grep,
+];
+
+/// Iterates over the registered mini projects.
+pub fn projects() -> impl Iterator<Item = &'static dyn MiniProject> {
+    PROJECTS.iter().copied()
+}
+
+/// Finds the registered mini project with the provided name.
+pub fn find(name: &str) -> Option<&'static dyn MiniProject> {
+    projects().find(|project| project.name() == name)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::collections::HashSet;
+
+    #[test]
+    fn names_are_unique() {
+        let names: HashSet<_> = projects().map(|p| p.name()).collect();
+        assert_eq!(names.len(), PROJECTS.len());
+    }
+
+    #[test]
+    fn find_project() {
+        assert_eq!(find("grep").map(|p| p.name()), Some("grep"));
+        assert!(find("nope").is_none());
+    }
+
+    #[test]
+    fn commands_are_valid() {
+        for project in projects() {
+            project.command().debug_assert();
+        }
+    }
+}
@@ -0,0 +1,294 @@
+use std::io::{self, BufRead, BufReader};
+use std::path::PathBuf;
+
+use clap::{ArgMatches, Args, Command, FromArgMatches, Subcommand};
+
+use super::MiniProject;
+use crate::grepr::{GreprExt, Pattern};
+use crate::progbar::ProgBarExt;
+use crate::strsplit::StrsplitExt;
+use crate::{calc, orst, progbar};
+
+// Parses the arguments of a project declared with the clap derive API.
+fn parse<A: FromArgMatches>(matches: &ArgMatches) -> io::Result<A> {
+    A::from_arg_matches(matches).map_err(|e| io::Error::new(io::ErrorKind::InvalidInput, e))
+}
+
+pub(super) struct ProgBar;
+
+#[derive(Args)]
+struct ProgBarArgs {
+    #[command(subcommand)]
+    command: ProgBarCommands,
+}
+
+#[derive(Clone, Subcommand, Debug)]
+#[command(arg_required_else_help = true)]
+enum ProgBarCommands {
+    /// Runs the progbar on a simulated infinite loop.
+    UnBounded {
+        /// Custom message to display along with the spinner.
+        #[arg(short, long, default_value_t = String::from("Simulating Loading..."))]
+        message: String,
+    },
+
+    /// Runs the progbar on a simulated loop having len of iter_count.
+    Bounded {
+        /// Custom starting delimiter for the loading bar.
+        #[arg(long, default_value_t = String::from("["))]
+        delim_start: String,
+
+        /// Custom ending delimiter for the loading bar.
+        #[arg( long, default_value_t = String::from("]"))]
+        delim_close: String,
+
+        /// Custom bar style. Each value specified will be repeated on each iteration.
+        #[arg(long, default_value_t = String::from("#"))]
+        bar_style: String,
+
+        /// Set custom length of the loop.
+        #[arg(short, long, default_value_t = 50)]
+        iter_count: u8,
+    },
+
+    /// Shows off the features of the progbar on simulated workloads.
+    Demo {
+        /// Milliseconds spent on each simulated item.
+        #[arg(long, default_value_t = 20)]
+        speed: u64,
+
+        /// Maximum random milliseconds added to each simulated item.
+        #[arg(long, default_value_t = 20)]
+        jitter: u64,
+    },
+}
+
+impl MiniProject for ProgBar {
+    fn name(&self) -> &'static str {
+        "progbar"
+    }
+
+    fn about(&self) -> &'static str {
+        "Print a progress bar to an iterator."
+    }
+
+    fn args(&self, cmd: Command) -> Command {
+        ProgBarArgs::augment_args(cmd).subcommand_required(true)
+    }
+
+    fn run(&self, matches: &ArgMatches) -> io::Result<()> {
+        use std::thread::sleep;
+        use std::time::Duration;
+
+        match parse::<ProgBarArgs>(matches)?.command {
+            ProgBarCommands::UnBounded { message } => {
+                // test run UnBounded
+                for _ in (0..).progbar().with_message(&message) {
+                    sleep(Duration::from_millis(50))
+                }
+            }
+            ProgBarCommands::Bounded {
+                delim_start,
+                delim_close,
+                bar_style,
+                iter_count,
+            } => {
+                // test run Bounded
+                for _ in (0..iter_count)
+                    .progbar()
+                    .with_bounds(delim_start, delim_close)
+                    .bar_style(bar_style)
+                {
+                    sleep(Duration::from_millis(50))
+                }
+            }
+            ProgBarCommands::Demo { speed, jitter } => progbar::demo::run_demo(speed, jitter),
+        }
+
+        Ok(())
+    }
+}
+
+pub(super) struct Strsplit;
+
+#[derive(Args)]
+struct StrsplitArgs {
+    #[command(subcommand)]
+    command: StrsplitCommands,
+}
+
+#[derive(Clone, Subcommand, Debug)]
+#[command(arg_required_else_help = true)]
+enum StrsplitCommands {
+    /// Split the provided string on the provided needle.
+    Split {
+        /// The needle to be fond in the haystack.
+        #[arg(short, long, required_unless_present = "whitespace")]
+        needle: Option<String>,
+
+        /// The haystack to find the needle in.
+        #[arg(short, long)]
+        string: String,
+
+        /// Ignore the case while searching for the needle.
+        #[arg(long, conflicts_with = "whitespace")]
+        ignore_case: bool,
+
+        /// Split on runs of whitespace instead of a needle.
+        #[arg(long, conflicts_with = "needle")]
+        whitespace: bool,
+    },
+
+    /// Split the provided string until the needle occurs in the String.
+    Until {
+        /// The needle to be fond in the haystack.
+        #[arg(short, long)]
+        needle: String,
+
+        /// The haystack to find the needle in.
+        #[arg(short, long)]
+        string: String,
+    },
+}
+
+impl MiniProject for Strsplit {
+    fn name(&self) -> &'static str {
+        "strsplit"
+    }
+
+    fn about(&self) -> &'static str {
+        "Perform splitting functions over a string."
+    }
+
+    fn args(&self, cmd: Command) -> Command {
+        StrsplitArgs::augment_args(cmd).subcommand_required(true)
+    }
+
+    fn run(&self, matches: &ArgMatches) -> io::Result<()> {
+        match parse::<StrsplitArgs>(matches)?.command {
+            StrsplitCommands::Split {
+                needle,
+                string,
+                ignore_case,
+                whitespace,
+            } => {
+                let result = match &needle {
+                    _ if whitespace => string.strsplit_whitespace().into_vec(),
+                    Some(needle) if ignore_case => {
+                        string.strsplit(needle).case_insensitive().into_vec()
+                    }
+                    Some(needle) => string.strsplit(needle).into_vec(),
+                    None => unreachable!("clap requires the needle without --whitespace"),
+                };
+                println!("{:?}", result);
+            }
+            StrsplitCommands::Until { needle, string } => {
+                let result = string.strsplit(needle).till_needle();
+                println!("{:?}", result);
+            }
+        }
+
+        Ok(())
+    }
+}
+
+pub(super) struct Orst;
+
+impl MiniProject for Orst {
+    fn name(&self) -> &'static str {
+        "orst"
+    }
+
+    fn about(&self) -> &'static str {
+        "Run custom sorting algorithms."
+    }
+
+    fn run(&self, _matches: &ArgMatches) -> io::Result<()> {
+        orst::benchmark::run_orst();
+        Ok(())
+    }
+}
+
+pub(super) struct Calc;
+
+#[derive(Args)]
+struct CalcArgs {
+    /// The expression to evaluate.
+    expression: String,
+
+    /// Value of a variable used in the expression, as `name=value`. Can be repeated.
+    #[arg(long = "var", value_parser = parse_var)]
+    vars: Vec<(String, f64)>,
+}
+
+// Parses the `name=value` arguments of the calc command.
+fn parse_var(arg: &str) -> Result<(String, f64), String> {
+    let (name, value) = arg
+        .split_once('=')
+        .ok_or_else(|| format!("expected `name=value`, found `{arg}`"))?;
+    let value = value
+        .trim()
+        .parse()
+        .map_err(|e| format!("invalid value of `{name}`: {e}"))?;
+    Ok((name.trim().to_string(), value))
+}
+
+impl MiniProject for Calc {
+    fn name(&self) -> &'static str {
+        "calc"
+    }
+
+    fn about(&self) -> &'static str {
+        "Evaluate an arithmetic expression such as \"2 * (3 + x)\"."
+    }
+
+    fn args(&self, cmd: Command) -> Command {
+        CalcArgs::augment_args(cmd)
+    }
+
+    fn run(&self, matches: &ArgMatches) -> io::Result<()> {
+        let CalcArgs { expression, vars } = parse(matches)?;
+        let vars = vars.into_iter().collect();
+        let result = calc::evaluate(&expression, &vars)
+            .map_err(|e| io::Error::new(io::ErrorKind::InvalidInput, e))?;
+        println!("{result}");
+        Ok(())
+    }
+}
+
+pub(super) struct Grep;
+
+#[derive(Args)]
+struct GrepArgs {
+    /// The pattern to search for. `?` matches any single character and `*` matches any sequence
+    /// of characters.
+    #[arg(short, long)]
+    pattern: String,
+
+    /// The file to search in.
+    #[arg(short, long)]
+    file: PathBuf,
+}
+
+impl MiniProject for Grep {
+    fn name(&self) -> &'static str {
+        "grep"
+    }
+
+    fn about(&self) -> &'static str {
+        "Print the lines of a file matching a pattern."
+    }
+
+    fn args(&self, cmd: Command) -> Command {
+        GrepArgs::augment_args(cmd)
+    }
+
+    fn run(&self, matches: &ArgMatches) -> io::Result<()> {
+        let GrepArgs { pattern, file } = parse(matches)?;
+        let lines = BufReader::new(std::fs::File::open(file)?).lines();
+        for line_match in lines.map_while(Result::ok).grepr(Pattern::new(&pattern)) {
+            println!("{}: {}", line_match.line_number, line_match.highlighted());
+        }
+        Ok(())
+    }
+}
@@ -15,6 +15,7 @@
 //!
 //! ### Features
 //! - Supports splitting both owned `String` and borrowed `&str`.
+//! - Splits on any [`Needle`]: a string, a `char` or a set of strings.
 //! - Returns an iterator that can be used lazily, or fully collected.
 //!
 //! ### Example
@@ -27,6 +28,158 @@
 //! assert_eq!(split, vec!["a", "b", "c", "d", "e"]);
 //! ```
 
+/// A delimiter which can be searched for in a haystack by [`Strsplit`].
+///
+/// Implemented for string types, `char` and sets of strings (`&[&str]` and `[&str; N]`). A set of
+/// strings matches at the leftmost occurrence of any of them, preferring the longest one when
+/// several match at the same position.
+///
+/// # Example
+///
+/// ```
+/// use zung_mini::strsplit::StrsplitExt;
+///
+/// assert_eq!("a,b;c".strsplit([",", ";"]).into_vec(), vec!["a", "b", "c"]);
+/// assert_eq!("a-b".strsplit('-').into_vec(), vec!["a", "b"]);
+/// ```
+pub trait Needle {
+    /// Returns the start and the end byte indices of the first occurrence of the needle in the
+    /// haystack, if any.
+    fn find_in(&self, haystack: &str) -> Option<(usize, usize)>;
+
+    /// Same as [`Needle::find_in`] but ignores the case of the characters. Defaults to the case
+    /// sensitive [`Needle::find_in`].
+    fn find_in_ignore_case(&self, haystack: &str) -> Option<(usize, usize)> {
+        self.find_in(haystack)
+    }
+
+    /// Returns `true` if the needle would match the empty string.
+    fn is_empty(&self) -> bool;
+}
+
+/// A [`Needle`] matching a run of one or more whitespace characters. See
+/// [`strsplit_whitespace()`](StrsplitExt::strsplit_whitespace()).
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct Whitespace;
+
+impl Needle for Whitespace {
+    fn find_in(&self, haystack: &str) -> Option<(usize, usize)> {
+        let start = haystack.find(char::is_whitespace)?;
+        let end = haystack[start..]
+            .find(|c: char| !c.is_whitespace())
+            .map_or(haystack.len(), |len| start + len);
+        Some((start, end))
+    }
+
+    fn is_empty(&self) -> bool {
+        false
+    }
+}
+
+impl Needle for &str {
+    fn find_in(&self, haystack: &str) -> Option<(usize, usize)> {
+        find_needle(self, haystack)
+    }
+
+    fn find_in_ignore_case(&self, haystack: &str) -> Option<(usize, usize)> {
+        find_needle_ignore_case(self, haystack)
+    }
+
+    fn is_empty(&self) -> bool {
+        str::is_empty(self)
+    }
+}
+
+impl Needle for String {
+    fn find_in(&self, haystack: &str) -> Option<(usize, usize)> {
+        find_needle(self, haystack)
+    }
+
+    fn find_in_ignore_case(&self, haystack: &str) -> Option<(usize, usize)> {
+        find_needle_ignore_case(self, haystack)
+    }
+
+    fn is_empty(&self) -> bool {
+        String::is_empty(self)
+    }
+}
+
+impl Needle for &String {
+    fn find_in(&self, haystack: &str) -> Option<(usize, usize)> {
+        find_needle(self, haystack)
+    }
+
+    fn find_in_ignore_case(&self, haystack: &str) -> Option<(usize, usize)> {
+        find_needle_ignore_case(self, haystack)
+    }
+
+    fn is_empty(&self) -> bool {
+        String::is_empty(self)
+    }
+}
+
+impl Needle for char {
+    fn find_in(&self, haystack: &str) -> Option<(usize, usize)> {
+        haystack
+            .find(*self)
+            .map(|index| (index, index + self.len_utf8()))
+    }
+
+    fn find_in_ignore_case(&self, haystack: &str) -> Option<(usize, usize)> {
+        find_needle_ignore_case(self.encode_utf8(&mut [0; 4]), haystack)
+    }
+
+    fn is_empty(&self) -> bool {
+        false
+    }
+}
+
+impl Needle for &[&str] {
+    fn find_in(&self, haystack: &str) -> Option<(usize, usize)> {
+        self.iter()
+            .filter_map(|needle| find_needle(needle, haystack))
+            .min_by_key(|&(start, end)| (start, std::cmp::Reverse(end)))
+    }
+
+    fn find_in_ignore_case(&self, haystack: &str) -> Option<(usize, usize)> {
+        self.iter()
+            .filter_map(|needle| find_needle_ignore_case(needle, haystack))
+            .min_by_key(|&(start, end)| (start, std::cmp::Reverse(end)))
+    }
+
+    fn is_empty(&self) -> bool {
+        <[&str]>::is_empty(self) || self.iter().any(|needle| needle.is_empty())
+    }
+}
+
+impl<const N: usize> Needle for [&str; N] {
+    fn find_in(&self, haystack: &str) -> Option<(usize, usize)> {
+        self.as_slice().find_in(haystack)
+    }
+
+    fn find_in_ignore_case(&self, haystack: &str) -> Option<(usize, usize)> {
+        self.as_slice().find_in_ignore_case(haystack)
+    }
+
+    fn is_empty(&self) -> bool {
+        Needle::is_empty(&self.as_slice())
+    }
+}
+
+impl<const N: usize> Needle for &[&str; N] {
+    fn find_in(&self, haystack: &str) -> Option<(usize, usize)> {
+        self.as_slice().find_in(haystack)
+    }
+
+    fn find_in_ignore_case(&self, haystack: &str) -> Option<(usize, usize)> {
+        self.as_slice().find_in_ignore_case(haystack)
+    }
+
+    fn is_empty(&self) -> bool {
+        Needle::is_empty(&self.as_slice())
+    }
+}
+
 /// A trait to extend string types with the `strsplit` method.
 /// and returns a `Strsplit` iterator over the resulting substrings.
 /// This method allows for splitting a string by a specified delimiter (needle)
@@ -48,7 +201,7 @@ where
     ///
     /// # Arguments
     ///
-    /// * `needle` - The substring used as the delimiter for splitting.
+    /// * `needle` - The [`Needle`] used as the delimiter for splitting.
     ///
     /// # Panics
     ///
@@ -65,7 +218,21 @@ where
     /// ```
     fn strsplit<P>(&'a self, needle: P) -> Strsplit<'a, P>
     where
-        P: 'b + AsRef<str>;
+        P: 'b + Needle;
+
+    /// Splits the string on runs of whitespace, like [`str::split_whitespace`]. Leading and
+    /// trailing whitespace is ignored, so no empty substrings are yielded.
+    ///
+    /// # Example
+    ///
+    /// ```
+    /// use zung_mini::strsplit::StrsplitExt;
+    ///
+    /// let haystack = "  this \t is\n an   example ";
+    /// let split = haystack.strsplit_whitespace().into_vec();
+    /// assert_eq!(split, vec!["this", "is", "an", "example"]);
+    /// ```
+    fn strsplit_whitespace(&'a self) -> Strsplit<'a, Whitespace>;
 }
 
 impl<'a, 'b> StrsplitExt<'a, 'b> for String
@@ -74,10 +241,14 @@ where
 {
     fn strsplit<P>(&'a self, needle: P) -> Strsplit<'a, P>
     where
-        P: 'b + AsRef<str>,
+        P: 'b + Needle,
     {
         Strsplit::new(self, needle)
     }
+
+    fn strsplit_whitespace(&'a self) -> Strsplit<'a, Whitespace> {
+        Strsplit::whitespace(self)
+    }
 }
 
 impl<'a, 'b> StrsplitExt<'a, 'b> for &str
@@ -86,10 +257,14 @@ where
 {
     fn strsplit<P>(&'a self, needle: P) -> Strsplit<'a, P>
     where
-        P: 'b + AsRef<str>,
+        P: 'b + Needle,
     {
         Strsplit::new(self, needle)
     }
+
+    fn strsplit_whitespace(&'a self) -> Strsplit<'a, Whitespace> {
+        Strsplit::whitespace(self)
+    }
 }
 
 /// An iterator over substrings separated by a specified delimiter (`needle`).
@@ -101,20 +276,49 @@ where
 pub struct Strsplit<'a, N> {
     remainder: Option<&'a str>,
     needle: N,
+    ignore_case: bool,
+}
+
+impl<'a> Strsplit<'a, Whitespace> {
+    fn whitespace(haystack: &'a str) -> Self {
+        let haystack = haystack.trim();
+        Self {
+            remainder: (!haystack.is_empty()).then_some(haystack),
+            needle: Whitespace,
+            ignore_case: false,
+        }
+    }
 }
 
 impl<'a, N> Strsplit<'a, N>
 where
-    N: 'a + AsRef<str>,
+    N: 'a + Needle,
 {
     fn new(haystack: &'a str, needle: N) -> Self {
-        assert!(!needle.as_ref().is_empty(), "Empty needle is not allowed");
+        assert!(!needle.is_empty(), "Empty needle is not allowed");
         Self {
             remainder: Some(haystack),
             needle,
+            ignore_case: false,
         }
     }
 
+    /// Makes the [`Strsplit`] ignore the case of the characters while searching for the needle.
+    ///
+    /// # Example
+    ///
+    /// ```
+    /// use zung_mini::strsplit::StrsplitExt;
+    ///
+    /// let haystack = "oneANDtwoandthree";
+    /// let split = haystack.strsplit("and").case_insensitive().into_vec();
+    /// assert_eq!(split, vec!["one", "two", "three"]);
+    /// ```
+    pub fn case_insensitive(mut self) -> Self {
+        self.ignore_case = true;
+        self
+    }
+
     /// Consumes the [`Strsplit`] and constructs and returns a vector.
     ///
     /// # Examples
@@ -155,20 +359,26 @@ where
     /// assert_eq!(result, "hello");
     /// ```
     pub fn till_needle(&mut self) -> &'a str {
-        self.next().unwrap()
+        self.next().unwrap_or_default()
     }
 }
 
 impl<'a, N> Iterator for Strsplit<'a, N>
 where
-    N: 'a + AsRef<str>,
+    N: 'a + Needle,
 {
     type Item = &'a str;
 
     fn next(&mut self) -> Option<Self::Item> {
         let remainder = self.remainder.as_mut()?;
 
-        if let Some((start, end)) = find_needle(self.needle.as_ref(), remainder) {
+        let found = if self.ignore_case {
+            self.needle.find_in_ignore_case(remainder)
+        } else {
+            self.needle.find_in(remainder)
+        };
+
+        if let Some((start, end)) = found {
             let before_needle = &remainder[..start];
             *remainder = &remainder[end..];
             Some(before_needle)
@@ -180,7 +390,7 @@ where
 
 impl<'a, N> From<Strsplit<'a, N>> for Vec<&'a str>
 where
-    N: 'a + AsRef<str>,
+    N: 'a + Needle,
 {
     fn from(value: Strsplit<'a, N>) -> Self {
         value.into_vec()
@@ -193,6 +403,30 @@ fn find_needle(needle: &str, haystack: &str) -> Option<(usize, usize)> {
         .map(|index| (index, index + needle.len()))
 }
 
+// Compares the characters after lowercasing them, so the match in the haystack may have a
+// different length in bytes than the needle.
+fn find_needle_ignore_case(needle: &str, haystack: &str) -> Option<(usize, usize)> {
+    let needle: Vec<char> = needle.chars().flat_map(char::to_lowercase).collect();
+
+    haystack.char_indices().find_map(|(start, _)| {
+        let mut pending = needle.iter();
+        let mut lowered = Vec::new();
+        for (offset, c) in haystack[start..].char_indices() {
+            lowered.clear();
+            lowered.extend(c.to_lowercase());
+            for l in &lowered {
+                if pending.next() != Some(l) {
+                    return None;
+                }
+            }
+            if pending.len() == 0 {
+                return Some((start, start + offset + c.len_utf8()));
+            }
+        }
+        None
+    })
+}
+
 #[cfg(test)]
 mod tests {
     use super::*;
@@ -203,6 +437,7 @@ mod tests {
         let b = Strsplit {
             remainder: Some("a b c d e f"),
             needle: " ",
+            ignore_case: false,
         };
         assert_eq!(a.remainder, b.remainder);
         assert_eq!(a.needle, b.needle);
@@ -226,6 +461,74 @@ mod tests {
         );
     }
 
+    #[test]
+    fn strsplit_works_with_borrowed_string() {
+        let needle = String::from(", ");
+        let split = "a, b, c".strsplit(&needle).into_vec();
+        assert_eq!(split, vec!["a", "b", "c"]);
+
+        // The needle is only borrowed.
+        assert_eq!("x, y".strsplit(&needle).till_needle(), "x");
+    }
+
+    #[test]
+    fn strsplit_works_with_char() {
+        assert_eq!("a→b→c".strsplit('→').into_vec(), vec!["a", "b", "c"]);
+    }
+
+    #[test]
+    fn strsplit_works_with_needle_set() {
+        let needles: &[&str] = &[",", ";", ";;"];
+        assert_eq!(
+            "a;;b,c;d".strsplit(needles).into_vec(),
+            vec!["a", "b", "c", "d"]
+        );
+        assert_eq!(
+            "a b-c".strsplit(&[" ", "-"]).into_vec(),
+            vec!["a", "b", "c"]
+        );
+    }
+
+    #[test]
+    fn strsplit_case_insensitive() {
+        let haystack = "oneANDtwoAndthree";
+        assert_eq!(haystack.strsplit("and").into_vec(), vec![haystack]);
+        assert_eq!(
+            haystack.strsplit("and").case_insensitive().into_vec(),
+            vec!["one", "two", "three"]
+        );
+        assert_eq!(
+            "xÄy".strsplit('ä').case_insensitive().into_vec(),
+            vec!["x", "y"]
+        );
+        assert_eq!(
+            "a;B|c".strsplit([";", "b|"]).case_insensitive().into_vec(),
+            vec!["a", "", "c"]
+        );
+        assert_eq!("ab".strsplit("B").case_insensitive().till_needle(), "a");
+    }
+
+    #[test]
+    fn strsplit_whitespace_works() {
+        let haystack = " \t a  b\n\nc \r\n";
+        assert_eq!(
+            haystack.strsplit_whitespace().into_vec(),
+            haystack.split_whitespace().collect::<Vec<_>>()
+        );
+        assert!("   ".strsplit_whitespace().into_vec().is_empty());
+        assert!("".strsplit_whitespace().into_vec().is_empty());
+        assert_eq!(
+            String::from("one").strsplit_whitespace().till_needle(),
+            "one"
+        );
+    }
+
+    #[test]
+    #[should_panic(expected = "Empty needle is not allowed")]
+    fn empty_needle_in_set() {
+        let _ = "a b".strsplit([" ", ""]);
+    }
+
     #[test]
     #[should_panic(expected = "Empty needle is not allowed")]
     fn empty_needle() {
@@ -0,0 +1,159 @@
+use criterion::{black_box, criterion_group, criterion_main, Criterion};
+use std::alloc::{GlobalAlloc, Layout, System};
+use std::path::PathBuf;
+use std::sync::atomic::{AtomicUsize, Ordering};
+use zung_parsers::bencode;
+
+// Counts the allocations and the peak of the allocated memory, to compare the allocator pressure
+// of the ways of parsing on top of their times.
+struct CountingAllocator;
+
+static ALLOCATIONS: AtomicUsize = AtomicUsize::new(0);
+static ALLOCATED: AtomicUsize = AtomicUsize::new(0);
+static PEAK: AtomicUsize = AtomicUsize::new(0);
+
+fn allocated(size: usize) {
+    let allocated = ALLOCATED.fetch_add(size, Ordering::Relaxed) + size;
+    PEAK.fetch_max(allocated, Ordering::Relaxed);
+}
+
+unsafe impl GlobalAlloc for CountingAllocator {
+    unsafe fn alloc(&self, layout: Layout) -> *mut u8 {
+        ALLOCATIONS.fetch_add(1, Ordering::Relaxed);
+        allocated(layout.size());
+        System.alloc(layout)
+    }
+
+    unsafe fn dealloc(&self, ptr: *mut u8, layout: Layout) {
+        ALLOCATED.fetch_sub(layout.size(), Ordering::Relaxed);
+        System.dealloc(ptr, layout)
+    }
+
+    unsafe fn realloc(&self, ptr: *mut u8, layout: Layout, new_size: usize) -> *mut u8 {
+        ALLOCATIONS.fetch_add(1, Ordering::Relaxed);
+        ALLOCATED.fetch_sub(layout.size(), Ordering::Relaxed);
+        allocated(new_size);
+        System.realloc(ptr, layout, new_size)
+    }
+}
+
+#[global_allocator]
+static GLOBAL: CountingAllocator = CountingAllocator;
+
+fn read(name: &str) -> Vec<u8> {
+    let mut path = PathBuf::from(env!("CARGO_MANIFEST_DIR"));
+    path.push("../utilities/sample_torrents");
+    path.push(name);
+    std::fs::read(path).expect("Unable to read the sample torrent")
+}
+
+// Prints the allocations made by a run of the function, and the peak of the memory it allocated.
+fn report_allocations(name: &str, mut f: impl FnMut()) {
+    let before = ALLOCATIONS.load(Ordering::Relaxed);
+    let allocated = ALLOCATED.load(Ordering::Relaxed);
+    PEAK.store(allocated, Ordering::Relaxed);
+    f();
+    let allocations = ALLOCATIONS.load(Ordering::Relaxed) - before;
+    let peak = (PEAK.load(Ordering::Relaxed) - allocated) / 1024;
+    eprintln!("{name}: {allocations} allocations, peak of {peak} KiB");
+}
+
+// A document of 100k dictionaries with keys too long to be stored inline, as written by tools
+// adding their own metadata to every file.
+fn long_keys() -> Vec<u8> {
+    let (name, timestamp) = (
+        "x-generator-original-file-name",
+        "x-generator-source-timestamp",
+    );
+    let mut document = b"l".to_vec();
+    for i in 0..100_000 {
+        let dictionary = format!(
+            "d{}:{name}i{i}e{}:{timestamp}i{i}ee",
+            name.len(),
+            timestamp.len()
+        );
+        document.extend_from_slice(dictionary.as_bytes());
+    }
+    document.push(b'e');
+    document
+}
+
+// The mc torrent lists 131k files, each a dictionary with a list of path strings: the parse is
+// dominated by building the values.
+fn parse(c: &mut Criterion) {
+    let mit = read("MIT6.00SCS11_archive.torrent");
+    let mc = read("MC_GRID-7f06f8280a3b496f2af0f78131ced619df14a0c3.torrent");
+
+    let mut group = c.benchmark_group("bencode::parse");
+    group.bench_function("mit (308 files)", |b| {
+        b.iter(|| bencode::parse(black_box(&mit)).unwrap())
+    });
+    group.bench_function("mc (131k files)", |b| {
+        b.iter(|| bencode::parse(black_box(&mc)).unwrap())
+    });
+    group.finish();
+}
+
+// Interning the keys only pays off when they are long, the keys of the mc torrent are stored
+// inline either way.
+fn parse_with_interned_keys(c: &mut Criterion) {
+    let mc = read("MC_GRID-7f06f8280a3b496f2af0f78131ced619df14a0c3.torrent");
+    let long_keys = long_keys();
+    let interned = bencode::ParseOptions::new().intern_keys(true);
+
+    for (name, input) in [
+        ("mc (131k files)", &mc),
+        ("long keys (100k dicts)", &long_keys),
+    ] {
+        report_allocations(&format!("parse/{name}"), || {
+            bencode::parse(black_box(input)).unwrap();
+        });
+        report_allocations(&format!("parse_with interned keys/{name}"), || {
+            bencode::parse_with(black_box(input), &interned).unwrap();
+        });
+    }
+
+    let mut group = c.benchmark_group("bencode::parse_with interned keys");
+    group.bench_function("mc (131k files)", |b| {
+        b.iter(|| bencode::parse_with(black_box(&mc), &interned).unwrap())
+    });
+    group.bench_function("long keys (100k dicts)", |b| {
+        b.iter(|| bencode::parse_with(black_box(&long_keys), &interned).unwrap())
+    });
+    group.finish();
+}
+
+// The arena is reset between the runs, as in a batch conversion, so that its memory is reused.
+#[cfg(feature = "arena")]
+fn parse_in(c: &mut Criterion) {
+    let mit = read("MIT6.00SCS11_archive.torrent");
+    let mc = read("MC_GRID-7f06f8280a3b496f2af0f78131ced619df14a0c3.torrent");
+
+    let mut arena = bencode::Bump::new();
+    bencode::parse_in(&mc, &arena).unwrap();
+    arena.reset();
+    report_allocations("parse_in/mc (131k files), warm arena", || {
+        bencode::parse_in(black_box(&mc), &arena).unwrap();
+    });
+
+    let mut group = c.benchmark_group("bencode::parse_in");
+    group.bench_function("mit (308 files)", |b| {
+        b.iter(|| {
+            bencode::parse_in(black_box(&mit), &arena).unwrap();
+            arena.reset();
+        })
+    });
+    group.bench_function("mc (131k files)", |b| {
+        b.iter(|| {
+            bencode::parse_in(black_box(&mc), &arena).unwrap();
+            arena.reset();
+        })
+    });
+    group.finish();
+}
+
+#[cfg(feature = "arena")]
+criterion_group!(benches, parse, parse_with_interned_keys, parse_in);
+#[cfg(not(feature = "arena"))]
+criterion_group!(benches, parse, parse_with_interned_keys);
+criterion_main!(benches);
@@ -0,0 +1,75 @@
+use criterion::{black_box, criterion_group, criterion_main, Criterion};
+use serde::Serialize;
+use std::path::PathBuf;
+use zung_parsers::bencode;
+
+#[derive(Serialize)]
+struct Torrent {
+    announce: String,
+    info: Info,
+}
+
+#[derive(Serialize)]
+struct Info {
+    files: Vec<File>,
+    name: String,
+    #[serde(rename = "piece length")]
+    piece_length: u64,
+    #[serde(with = "serde_bytes_like")]
+    pieces: Vec<u8>,
+}
+
+#[derive(Serialize)]
+struct File {
+    length: u64,
+    path: Vec<String>,
+}
+
+// Serializes the pieces as a byte string rather than a list of integers.
+mod serde_bytes_like {
+    pub fn serialize<S: serde::Serializer>(bytes: &[u8], serializer: S) -> Result<S::Ok, S::Error> {
+        serializer.serialize_bytes(bytes)
+    }
+}
+
+// A torrent of 100k files in 1000 directories, as the large file lists of the mc torrent.
+fn large_file_list() -> Torrent {
+    let files = (0..100_000_u64)
+        .map(|i| File {
+            length: i * 7919 % 1_000_000,
+            path: vec![format!("dir-{}", i / 100), format!("file-{i}.dat")],
+        })
+        .collect();
+
+    Torrent {
+        announce: "udp://tracker.example.com:6969/announce".to_string(),
+        info: Info {
+            files,
+            name: "dataset".to_string(),
+            piece_length: 1 << 20,
+            pieces: vec![0xab; 20 * 5000],
+        },
+    }
+}
+
+fn to_bytes(c: &mut Criterion) {
+    let torrent = large_file_list();
+    let mut path = PathBuf::from(env!("CARGO_MANIFEST_DIR"));
+    path.push(
+        "../utilities/sample_torrents/MC_GRID-7f06f8280a3b496f2af0f78131ced619df14a0c3.torrent",
+    );
+    let mc =
+        bencode::parse(&std::fs::read(path).expect("Unable to read the sample torrent")).unwrap();
+
+    let mut group = c.benchmark_group("bencode::to_bytes");
+    group.bench_function("struct (100k files)", |b| {
+        b.iter(|| bencode::to_bytes(black_box(&torrent)).unwrap())
+    });
+    group.bench_function("Value of mc (131k files)", |b| {
+        b.iter(|| bencode::to_bytes(black_box(&mc)).unwrap())
+    });
+    group.finish();
+}
+
+criterion_group!(benches, to_bytes);
+criterion_main!(benches);
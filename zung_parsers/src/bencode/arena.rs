@@ -0,0 +1,163 @@
+//! Values allocated in an arena, for the workloads building and dropping many values.
+
+use std::collections::HashMap;
+
+use bumpalo::collections::Vec as BumpVec;
+use bumpalo::Bump;
+
+use super::error::{Error, Result};
+use super::{Bencode, Key, Value};
+
+/// A bencode value whose strings, lists and dictionaries live in a [`Bump`] arena, built by
+/// [`parse_in`].
+///
+/// Building a [`Value`] allocates every string, list and dictionary on its own, and dropping it
+/// frees them one by one. An `ArenaValue` is instead bump allocated in the arena and is freed all
+/// at once by [`Bump::reset`], which keeps the memory of the arena for the next document. This
+/// suits batch conversions, where millions of values are built and dropped in a row.
+///
+/// The dictionaries keep their keys in the order of the input, and are searched linearly.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum ArenaValue<'a> {
+    Integer(i64),
+
+    /// A string which is not ASCII, like [`Value::Bytes`].
+    Bytes(&'a [u8]),
+    String(&'a str),
+    List(&'a [ArenaValue<'a>]),
+    Dictionary(&'a [(&'a str, ArenaValue<'a>)]),
+}
+
+impl<'a> ArenaValue<'a> {
+    /// Returns the value of the key if this is a dictionary holding it. When a key is repeated
+    /// the last value is returned, as in the [`Value::Dictionary`] of the same input.
+    pub fn get(&self, key: &str) -> Option<&ArenaValue<'a>> {
+        match self {
+            ArenaValue::Dictionary(entries) => entries
+                .iter()
+                .rev()
+                .find(|(k, _)| *k == key)
+                .map(|(_, value)| value),
+            _ => None,
+        }
+    }
+
+    /// Copies the value out of the arena.
+    pub fn to_value(&self) -> Value {
+        match *self {
+            ArenaValue::Integer(i) => Value::Integer(i),
+            ArenaValue::Bytes(bytes) => Value::Bytes(bytes.to_vec()),
+            ArenaValue::String(string) => Value::String(string.to_string()),
+            ArenaValue::List(list) => Value::List(list.iter().map(Self::to_value).collect()),
+            ArenaValue::Dictionary(entries) => Value::Dictionary(
+                entries
+                    .iter()
+                    .map(|(key, value)| (Key::new(key), value.to_value()))
+                    .collect::<HashMap<_, _>>(),
+            ),
+        }
+    }
+}
+
+/// Parses the input like [`parse`](super::parse), allocating the value in the arena.
+///
+/// The value does not borrow the input, which can be dropped once parsed.
+///
+/// # Example
+///
+/// ```
+/// use zung_parsers::bencode::{self, ArenaValue, Bump};
+///
+/// let mut arena = Bump::new();
+/// for document in [&b"d4:name4:zunge"[..], b"d4:name5:bytese"] {
+///     let value = bencode::parse_in(document, &arena).unwrap();
+///     assert!(matches!(value.get("name"), Some(ArenaValue::String(_))));
+///
+///     // Frees the values of the document at once, keeping the memory for the next one.
+///     arena.reset();
+/// }
+/// ```
+pub fn parse_in<'a>(input: &[u8], arena: &'a Bump) -> Result<ArenaValue<'a>> {
+    parse_value(&mut Bencode::from_bytes(input), arena)
+}
+
+fn parse_value<'a>(bencode: &mut Bencode<'_>, arena: &'a Bump) -> Result<ArenaValue<'a>> {
+    match bencode.input.first() {
+        None => Err(Error::EndOfStream),
+        Some(b'0'..=b'9') => {
+            let bytes = bencode.parse_byte_slice()?;
+            if bytes.is_ascii() {
+                let string = std::str::from_utf8(bytes).expect("ASCII is valid UTF-8");
+                Ok(ArenaValue::String(arena.alloc_str(string)))
+            } else {
+                Ok(ArenaValue::Bytes(arena.alloc_slice_copy(bytes)))
+            }
+        }
+        Some(b'i') => Ok(ArenaValue::Integer(bencode.parse_integer()?)),
+        Some(b'l') => {
+            bencode.input = &bencode.input[1..];
+            let mut list = BumpVec::new_in(arena);
+            while bencode.input.first().is_some_and(|&b| b != b'e') {
+                list.push(parse_value(bencode, arena)?);
+            }
+            bencode.parse_end("Invalid list format: missing 'e'")?;
+            Ok(ArenaValue::List(list.into_bump_slice()))
+        }
+        Some(b'd') => {
+            bencode.input = &bencode.input[1..];
+            let mut entries = BumpVec::new_in(arena);
+            while bencode.input.first().is_some_and(|&b| b != b'e') {
+                if !bencode.input[0].is_ascii_digit() {
+                    return Err(Error::InvalidType(
+                        "Only string values are allowed as dictionary keys".to_string(),
+                    ));
+                }
+                let key = std::str::from_utf8(bencode.parse_byte_slice()?)
+                    .map_err(|e| Error::Custom(e.to_string()))?;
+                let key: &str = arena.alloc_str(key);
+                entries.push((key, parse_value(bencode, arena)?));
+            }
+            bencode.parse_end("Invalid dictionary format: missing 'e'")?;
+            Ok(ArenaValue::Dictionary(entries.into_bump_slice()))
+        }
+        _ => Err(Error::InvalidType("Invalid bencode format".to_string())),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::bencode::parse;
+
+    #[test]
+    fn test_same_as_parse() {
+        let arena = Bump::new();
+        for input in [
+            &b"i-42e"[..],
+            b"4:spam",
+            b"3:\xff\x00\x01",
+            b"l4:spami7ee",
+            b"d3:cow3:moo4:listli1ed1:ai2eee4:spaml1:a1:bee",
+            b"d1:ai1e1:ai2ee",
+            b"le",
+        ] {
+            let value = parse_in(input, &arena).unwrap();
+            assert_eq!(value.to_value(), parse(input).unwrap(), "{input:?}");
+        }
+
+        let value = parse_in(b"d1:ai1e1:ai2ee", &arena).unwrap();
+        assert_eq!(value.get("a"), Some(&ArenaValue::Integer(2)));
+    }
+
+    #[test]
+    fn test_errors() {
+        let arena = Bump::new();
+        for input in [&b""[..], b"l", b"d1:a", b"di1ei2ee", b"x", b"i1x2e"] {
+            assert_eq!(
+                parse_in(input, &arena).unwrap_err().to_string(),
+                parse(input).unwrap_err().to_string(),
+                "{input:?}"
+            );
+        }
+    }
+}
@@ -0,0 +1,294 @@
+//! Conversions between [`Value`] and the values of [`serde_json`] and [`serde_yaml`].
+//!
+//! The rules are documented in the [module](super) documentation.
+
+use std::collections::HashMap;
+
+use super::{Error, Key, Value};
+
+impl TryFrom<serde_json::Value> for Value {
+    type Error = Error;
+
+    fn try_from(value: serde_json::Value) -> Result<Self, Self::Error> {
+        from_json(value, &mut Vec::new())
+    }
+}
+
+impl TryFrom<serde_yaml::Value> for Value {
+    type Error = Error;
+
+    fn try_from(value: serde_yaml::Value) -> Result<Self, Self::Error> {
+        from_yaml(value, &mut Vec::new())
+    }
+}
+
+impl From<Value> for serde_json::Value {
+    fn from(value: Value) -> Self {
+        match value {
+            Value::Integer(i) => i.into(),
+            Value::String(s) => s.into(),
+            Value::Bytes(bytes) => match String::from_utf8(bytes) {
+                Ok(s) => s.into(),
+                Err(e) => e.into_bytes().into(),
+            },
+            Value::List(list) => list.into_iter().map(Self::from).collect(),
+            Value::Dictionary(dictionary) => sorted(dictionary)
+                .map(|(k, v)| (k, Self::from(v)))
+                .collect(),
+        }
+    }
+}
+
+impl From<Value> for serde_yaml::Value {
+    fn from(value: Value) -> Self {
+        match value {
+            Value::Integer(i) => i.into(),
+            Value::String(s) => s.into(),
+            Value::Bytes(bytes) => match String::from_utf8(bytes) {
+                Ok(s) => s.into(),
+                Err(e) => e.into_bytes().into(),
+            },
+            Value::List(list) => list.into_iter().map(Self::from).collect(),
+            Value::Dictionary(dictionary) => serde_yaml::Value::Mapping(
+                sorted(dictionary)
+                    .map(|(k, v)| (k.into(), Self::from(v)))
+                    .collect(),
+            ),
+        }
+    }
+}
+
+fn sorted(dictionary: HashMap<Key, Value>) -> impl Iterator<Item = (String, Value)> {
+    let mut entries: Vec<_> = dictionary.into_iter().collect();
+    entries.sort_by(|(a, _), (b, _)| a.cmp(b));
+    entries.into_iter().map(|(k, v)| (k.into(), v))
+}
+
+fn from_json(value: serde_json::Value, path: &mut Vec<String>) -> Result<Value, Error> {
+    use serde_json::Value as Json;
+
+    match value {
+        Json::Null => Err(invalid("null", path, "bencode has no null")),
+        Json::Bool(b) => Ok(Value::Integer(b.into())),
+        Json::Number(n) => match (n.as_i64(), n.as_f64()) {
+            (Some(i), _) => Ok(Value::Integer(i)),
+            (None, _) if n.is_u64() => Err(invalid(
+                &format!("integer {n}"),
+                path,
+                "larger than the largest bencode integer",
+            )),
+            (None, Some(f)) => from_float(f, path),
+            (None, None) => Err(invalid(&format!("number {n}"), path, "not a number")),
+        },
+        Json::String(s) => Ok(Value::String(s)),
+        Json::Array(array) => from_list(array, path, from_json),
+        Json::Object(object) => {
+            let mut dictionary = HashMap::with_capacity(object.len());
+            for (key, value) in object {
+                if value.is_null() {
+                    continue;
+                }
+                path.push(key);
+                let value = from_json(value, path)?;
+                dictionary.insert(path.pop().expect("Pushed above").into(), value);
+            }
+            Ok(Value::Dictionary(dictionary))
+        }
+    }
+}
+
+fn from_yaml(value: serde_yaml::Value, path: &mut Vec<String>) -> Result<Value, Error> {
+    use serde_yaml::Value as Yaml;
+
+    match value {
+        Yaml::Null => Err(invalid("null", path, "bencode has no null")),
+        Yaml::Bool(b) => Ok(Value::Integer(b.into())),
+        Yaml::Number(n) => match (n.as_i64(), n.as_f64()) {
+            (Some(i), _) => Ok(Value::Integer(i)),
+            (None, _) if n.is_u64() => Err(invalid(
+                &format!("integer {n}"),
+                path,
+                "larger than the largest bencode integer",
+            )),
+            (None, Some(f)) => from_float(f, path),
+            (None, None) => Err(invalid(&format!("number {n}"), path, "not a number")),
+        },
+        Yaml::String(s) => Ok(Value::String(s)),
+        Yaml::Sequence(sequence) => from_list(sequence, path, from_yaml),
+        Yaml::Mapping(mapping) => {
+            let mut dictionary = HashMap::with_capacity(mapping.len());
+            for (key, value) in mapping {
+                let Yaml::String(key) = key else {
+                    return Err(Error::InvalidType(format!(
+                        "Invalid Type: mapping key {} at {} (bencode keys are strings)",
+                        serde_yaml::to_string(&key).unwrap_or_default().trim(),
+                        display(path)
+                    )));
+                };
+                if value.is_null() {
+                    continue;
+                }
+                path.push(key);
+                let value = from_yaml(value, path)?;
+                dictionary.insert(path.pop().expect("Pushed above").into(), value);
+            }
+            Ok(Value::Dictionary(dictionary))
+        }
+        Yaml::Tagged(tagged) => from_yaml(tagged.value, path),
+    }
+}
+
+fn from_list<T>(
+    list: Vec<T>,
+    path: &mut Vec<String>,
+    convert: fn(T, &mut Vec<String>) -> Result<Value, Error>,
+) -> Result<Value, Error> {
+    let mut values = Vec::with_capacity(list.len());
+    for (i, value) in list.into_iter().enumerate() {
+        path.push(i.to_string());
+        values.push(convert(value, path)?);
+        path.pop();
+    }
+    Ok(Value::List(values))
+}
+
+fn from_float(f: f64, path: &[String]) -> Result<Value, Error> {
+    // The bounds are exact powers of two, so the cast below can not saturate.
+    if f.fract() == 0.0 && f >= i64::MIN as f64 && f < i64::MAX as f64 {
+        Ok(Value::Integer(f as i64))
+    } else {
+        Err(invalid(
+            &format!("float {f}"),
+            path,
+            "bencode only has integers",
+        ))
+    }
+}
+
+fn invalid(what: &str, path: &[String], why: &str) -> Error {
+    Error::InvalidValue(format!(
+        "Invalid Value: {what} at {} ({why})",
+        display(path)
+    ))
+}
+
+fn display(path: &[String]) -> String {
+    if path.is_empty() {
+        String::from("the top level")
+    } else {
+        path.join("/")
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use serde_json::json;
+
+    fn dictionary(entries: &[(&str, Value)]) -> Value {
+        Value::Dictionary(
+            entries
+                .iter()
+                .map(|(k, v)| (Key::new(k), v.clone()))
+                .collect(),
+        )
+    }
+
+    #[test]
+    fn test_from_json() {
+        let value = Value::try_from(json!({
+            "name": "test",
+            "private": true,
+            "length": 1e3,
+            "comment": null,
+            "list": [1, false, "two"],
+        }))
+        .unwrap();
+
+        assert_eq!(
+            value,
+            dictionary(&[
+                ("name", Value::String("test".into())),
+                ("private", Value::Integer(1)),
+                ("length", Value::Integer(1000)),
+                (
+                    "list",
+                    Value::List(vec![
+                        Value::Integer(1),
+                        Value::Integer(0),
+                        Value::String("two".into())
+                    ])
+                ),
+            ])
+        );
+    }
+
+    #[test]
+    fn test_from_json_errors() {
+        let error = |value: serde_json::Value| Value::try_from(value).unwrap_err().to_string();
+
+        assert_eq!(
+            error(json!({"info": {"length": 1.5}})),
+            "Invalid Value: float 1.5 at info/length (bencode only has integers)"
+        );
+        assert_eq!(
+            error(json!({"files": [1, null]})),
+            "Invalid Value: null at files/1 (bencode has no null)"
+        );
+        assert_eq!(
+            error(json!(u64::MAX)),
+            "Invalid Value: integer 18446744073709551615 at the top level (larger than the \
+             largest bencode integer)"
+        );
+    }
+
+    #[test]
+    fn test_from_yaml() {
+        let yaml: serde_yaml::Value =
+            serde_yaml::from_str("name: test\nprivate: false\nempty: ~\nlist: [1, 2.0]").unwrap();
+        assert_eq!(
+            Value::try_from(yaml).unwrap(),
+            dictionary(&[
+                ("name", Value::String("test".into())),
+                ("private", Value::Integer(0)),
+                (
+                    "list",
+                    Value::List(vec![Value::Integer(1), Value::Integer(2)])
+                ),
+            ])
+        );
+
+        let yaml: serde_yaml::Value = serde_yaml::from_str("1: one").unwrap();
+        assert_eq!(
+            Value::try_from(yaml).unwrap_err().to_string(),
+            "Invalid Type: mapping key 1 at the top level (bencode keys are strings)"
+        );
+    }
+
+    #[test]
+    fn test_to_json_and_yaml() {
+        let value = dictionary(&[
+            ("b", Value::Bytes(vec![0xff, 0x00])),
+            ("a", Value::Bytes("é".as_bytes().to_vec())),
+            ("c", Value::List(vec![Value::Integer(-1)])),
+        ]);
+
+        let json = serde_json::Value::from(value.clone());
+        assert_eq!(json, json!({"a": "é", "b": [255, 0], "c": [-1]}));
+        assert_eq!(json.to_string(), r#"{"a":"é","b":[255,0],"c":[-1]}"#);
+
+        let yaml = serde_yaml::Value::from(value);
+        assert_eq!(
+            serde_yaml::to_string(&yaml).unwrap(),
+            "a: é\nb:\n- 255\n- 0\nc:\n- -1\n"
+        );
+    }
+
+    #[test]
+    fn test_roundtrip() {
+        let input = "d4:infod6:lengthi42e4:name4:teste4:listli1e2:abee";
+        let value = crate::bencode::parse(input).unwrap();
+        let json = serde_json::Value::from(value.clone());
+        assert_eq!(Value::try_from(json).unwrap(), value);
+    }
+}
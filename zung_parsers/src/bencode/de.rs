@@ -132,7 +132,7 @@ impl<'de> Deserializer<'de> {
     }
 }
 
-impl<'de, 'a> de::Deserializer<'de> for &'a mut Deserializer<'de> {
+impl<'de> de::Deserializer<'de> for &mut Deserializer<'de> {
     type Error = Error;
 
     // Look at the input data to decide what Serde data model type to
@@ -271,7 +271,7 @@ impl<'de, 'a> de::Deserializer<'de> for &'a mut Deserializer<'de> {
     where
         V: Visitor<'de>,
     {
-        visitor.visit_bytes(&self.bencode.parse_bytes()?)
+        visitor.visit_borrowed_bytes(self.bencode.parse_byte_slice()?)
     }
 
     fn deserialize_byte_buf<V>(self, visitor: V) -> Result<V::Value>
@@ -0,0 +1,168 @@
+use std::collections::BTreeSet;
+use std::fmt::{self, Display};
+
+use super::Value;
+
+/// Values printed by [`DiffEntry`] are cut after this many characters.
+const MAX_DISPLAY_LENGTH: usize = 64;
+
+/// A difference between two bencode values. See [`diff`].
+#[derive(Debug, Clone, PartialEq)]
+pub struct DiffEntry<'a> {
+    /// The `/` separated path of the value, as accepted by [`Value::pointer`]. Empty for the top
+    /// level value.
+    pub path: String,
+
+    pub change: Change<'a>,
+}
+
+/// How a value differs between the old and the new value.
+#[derive(Debug, Clone, PartialEq)]
+pub enum Change<'a> {
+    /// The value is only in the new value.
+    Added(&'a Value),
+
+    /// The value is only in the old value.
+    Removed(&'a Value),
+
+    /// The value is in both but differs. Dictionaries and lists are never changed as a whole:
+    /// their items are compared instead.
+    Changed { old: &'a Value, new: &'a Value },
+}
+
+/// Prints as `+ path: value`, `- path: value` or `~ path: old -> new`. Long values are cut.
+impl Display for DiffEntry<'_> {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let path = if self.path.is_empty() {
+            "/"
+        } else {
+            &self.path
+        };
+        match &self.change {
+            Change::Added(value) => write!(f, "+ {path}: {}", short(value)),
+            Change::Removed(value) => write!(f, "- {path}: {}", short(value)),
+            Change::Changed { old, new } => {
+                write!(f, "~ {path}: {} -> {}", short(old), short(new))
+            }
+        }
+    }
+}
+
+/// Compares two bencode values and returns the paths which were added, removed or changed from
+/// `old` to `new`.
+///
+/// Dictionaries are compared key by key, in key order, and lists index by index, so an item
+/// inserted at the start of a list changes every item after it. Values of different types are
+/// always changed. Returns an empty vector if the values are equal.
+///
+/// # Example
+///
+/// ```rust
+/// use zung_parsers::bencode::{self, Change};
+///
+/// let old = bencode::parse("d4:name3:old4:sizei1ee").unwrap();
+/// let new = bencode::parse("d4:name3:new7:privatei1ee").unwrap();
+///
+/// let diff = bencode::diff(&old, &new);
+/// let paths: Vec<_> = diff.iter().map(|entry| entry.path.as_str()).collect();
+/// assert_eq!(paths, ["name", "private", "size"]);
+/// assert!(matches!(diff[2].change, Change::Removed(_)));
+/// ```
+pub fn diff<'a>(old: &'a Value, new: &'a Value) -> Vec<DiffEntry<'a>> {
+    let mut entries = Vec::new();
+    diff_into(old, new, &mut Vec::new(), &mut entries);
+    entries
+}
+
+fn diff_into<'a>(
+    old: &'a Value,
+    new: &'a Value,
+    path: &mut Vec<String>,
+    entries: &mut Vec<DiffEntry<'a>>,
+) {
+    match (old, new) {
+        (Value::Dictionary(old), Value::Dictionary(new)) => {
+            let keys: BTreeSet<_> = old.keys().chain(new.keys()).collect();
+            for key in keys {
+                path.push(key.to_string());
+                match (old.get(key), new.get(key)) {
+                    (Some(old), Some(new)) => diff_into(old, new, path, entries),
+                    (Some(old), None) => push(entries, path, Change::Removed(old)),
+                    (None, Some(new)) => push(entries, path, Change::Added(new)),
+                    (None, None) => unreachable!("The key is from one of the dictionaries"),
+                }
+                path.pop();
+            }
+        }
+        (Value::List(old), Value::List(new)) => {
+            for i in 0..old.len().max(new.len()) {
+                path.push(i.to_string());
+                match (old.get(i), new.get(i)) {
+                    (Some(old), Some(new)) => diff_into(old, new, path, entries),
+                    (Some(old), None) => push(entries, path, Change::Removed(old)),
+                    (None, Some(new)) => push(entries, path, Change::Added(new)),
+                    (None, None) => unreachable!("The index is in one of the lists"),
+                }
+                path.pop();
+            }
+        }
+        (old, new) if old != new => push(entries, path, Change::Changed { old, new }),
+        _ => {}
+    }
+}
+
+fn push<'a>(entries: &mut Vec<DiffEntry<'a>>, path: &[String], change: Change<'a>) {
+    entries.push(DiffEntry {
+        path: path.join("/"),
+        change,
+    });
+}
+
+fn short(value: &Value) -> String {
+    let text = value.to_string();
+    match text.char_indices().nth(MAX_DISPLAY_LENGTH) {
+        Some((end, _)) => format!("{}... ({} characters)", &text[..end], text.chars().count()),
+        None => text,
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::bencode::parse;
+
+    #[test]
+    fn test_equal_values() {
+        let value = parse("d4:infod4:name1:aee").unwrap();
+        assert!(diff(&value, &value.clone()).is_empty());
+    }
+
+    #[test]
+    fn test_nested_changes() {
+        let old = parse("d4:infod5:filesli1ei2ee4:name1:aee").unwrap();
+        let new = parse("d4:infod5:filesli1ei3ei4ee4:namei1eee").unwrap();
+
+        let entries: Vec<_> = diff(&old, &new).iter().map(ToString::to_string).collect();
+        assert_eq!(
+            entries,
+            [
+                "~ info/files/1: 2 -> 3",
+                "+ info/files/2: 4",
+                "~ info/name: a -> 1"
+            ]
+        );
+    }
+
+    #[test]
+    fn test_top_level_and_long_values() {
+        let old = Value::Bytes(vec![0xff; 100]);
+        let new = Value::Integer(1);
+        let entries = diff(&old, &new);
+        assert_eq!(entries.len(), 1);
+        assert_eq!(entries[0].path, "");
+        assert_eq!(
+            entries[0].to_string(),
+            format!("~ /: {}... (200 characters) -> 1", "f".repeat(64))
+        );
+    }
+}
@@ -0,0 +1,290 @@
+use std::{
+    borrow::Borrow,
+    cmp::Ordering,
+    collections::HashSet,
+    fmt,
+    hash::{Hash, Hasher},
+    ops::Deref,
+    sync::Arc,
+};
+
+use compact_str::CompactString;
+use serde::{de::Visitor, Deserialize, Deserializer, Serialize, Serializer};
+
+use super::error::{Error, Result};
+
+/// A key of a [`Value::Dictionary`](super::Value::Dictionary).
+///
+/// The keys of up to 24 bytes, which are nearly all the keys of the torrent files, are stored
+/// inline instead of in their own allocation. A key reads as a `&str` and converts from and into
+/// [`String`], and the dictionaries are indexed with a `&str`:
+///
+/// ```
+/// use zung_parsers::bencode::{self, Value};
+///
+/// let Value::Dictionary(dictionary) = bencode::parse("d4:name4:zunge").unwrap() else {
+///     panic!("Expected a dictionary");
+/// };
+/// let (key, value) = dictionary.iter().next().unwrap();
+/// assert_eq!(key, "name");
+/// assert_eq!(dictionary.get("name"), Some(value));
+/// ```
+///
+/// The longer keys can be shared by all the dictionaries of a document with
+/// [`ParseOptions::intern_keys`](super::ParseOptions::intern_keys).
+#[derive(Clone)]
+pub struct Key(Repr);
+
+#[derive(Clone)]
+enum Repr {
+    Owned(CompactString),
+    Shared(Arc<str>),
+}
+
+/// Length of the longest key stored inline.
+const INLINE_LENGTH: usize = std::mem::size_of::<CompactString>();
+
+impl Key {
+    /// Creates a key from a `&str`, stored inline if it is at most 24 bytes long.
+    pub fn new(key: &str) -> Self {
+        Key(Repr::Owned(CompactString::new(key)))
+    }
+
+    /// Returns the key as a `&str`, whichever way it is stored.
+    pub fn as_str(&self) -> &str {
+        match &self.0 {
+            Repr::Owned(key) => key,
+            Repr::Shared(key) => key,
+        }
+    }
+
+    /// Returns `true` if the key is shared with other dictionaries, see
+    /// [`ParseOptions::intern_keys`](super::ParseOptions::intern_keys).
+    pub fn is_shared(&self) -> bool {
+        matches!(self.0, Repr::Shared(_))
+    }
+
+    pub(crate) fn from_utf8(bytes: &[u8]) -> Result<Self> {
+        std::str::from_utf8(bytes)
+            .map(Key::new)
+            .map_err(|e| Error::Custom(e.to_string()))
+    }
+}
+
+/// Shares the keys longer than [`INLINE_LENGTH`] between the dictionaries of a document. The
+/// shorter keys are already stored without allocating.
+#[derive(Debug, Default)]
+pub(crate) struct KeyInterner {
+    keys: HashSet<Arc<str>>,
+}
+
+impl KeyInterner {
+    pub(crate) fn key_from_utf8(&mut self, bytes: &[u8]) -> Result<Key> {
+        let key = std::str::from_utf8(bytes).map_err(|e| Error::Custom(e.to_string()))?;
+        if key.len() <= INLINE_LENGTH {
+            return Ok(Key::new(key));
+        }
+
+        let shared = match self.keys.get(key) {
+            Some(shared) => Arc::clone(shared),
+            None => {
+                let shared: Arc<str> = Arc::from(key);
+                self.keys.insert(Arc::clone(&shared));
+                shared
+            }
+        };
+        Ok(Key(Repr::Shared(shared)))
+    }
+}
+
+impl Default for Key {
+    fn default() -> Self {
+        Key::new("")
+    }
+}
+
+impl Deref for Key {
+    type Target = str;
+
+    fn deref(&self) -> &str {
+        self.as_str()
+    }
+}
+
+impl AsRef<str> for Key {
+    fn as_ref(&self) -> &str {
+        self.as_str()
+    }
+}
+
+impl Borrow<str> for Key {
+    fn borrow(&self) -> &str {
+        self.as_str()
+    }
+}
+
+// Compared and hashed as a `str` whichever way they are stored, as required by `Borrow<str>`.
+
+impl PartialEq for Key {
+    fn eq(&self, other: &Self) -> bool {
+        self.as_str() == other.as_str()
+    }
+}
+
+impl Eq for Key {}
+
+impl PartialOrd for Key {
+    fn partial_cmp(&self, other: &Self) -> Option<Ordering> {
+        Some(self.cmp(other))
+    }
+}
+
+impl Ord for Key {
+    fn cmp(&self, other: &Self) -> Ordering {
+        self.as_str().cmp(other.as_str())
+    }
+}
+
+impl Hash for Key {
+    fn hash<H: Hasher>(&self, state: &mut H) {
+        self.as_str().hash(state)
+    }
+}
+
+impl fmt::Debug for Key {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        fmt::Debug::fmt(self.as_str(), f)
+    }
+}
+
+impl fmt::Display for Key {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.write_str(self.as_str())
+    }
+}
+
+impl From<&str> for Key {
+    fn from(key: &str) -> Self {
+        Key::new(key)
+    }
+}
+
+impl From<&String> for Key {
+    fn from(key: &String) -> Self {
+        Key::new(key)
+    }
+}
+
+// Short keys are moved inline, and the longer keys keep the allocation of the string.
+impl From<String> for Key {
+    fn from(key: String) -> Self {
+        Key(Repr::Owned(CompactString::from(key)))
+    }
+}
+
+impl From<Key> for String {
+    fn from(key: Key) -> Self {
+        match key.0 {
+            Repr::Owned(key) => key.into_string(),
+            Repr::Shared(key) => key.to_string(),
+        }
+    }
+}
+
+impl PartialEq<str> for Key {
+    fn eq(&self, other: &str) -> bool {
+        self.as_str() == other
+    }
+}
+
+impl PartialEq<&str> for Key {
+    fn eq(&self, other: &&str) -> bool {
+        self.as_str() == *other
+    }
+}
+
+impl PartialEq<String> for Key {
+    fn eq(&self, other: &String) -> bool {
+        self.as_str() == other
+    }
+}
+
+impl Serialize for Key {
+    fn serialize<S>(&self, serializer: S) -> std::result::Result<S::Ok, S::Error>
+    where
+        S: Serializer,
+    {
+        serializer.serialize_str(self.as_str())
+    }
+}
+
+impl<'de> Deserialize<'de> for Key {
+    fn deserialize<D>(deserializer: D) -> std::result::Result<Self, D::Error>
+    where
+        D: Deserializer<'de>,
+    {
+        deserializer.deserialize_str(KeyVisitor)
+    }
+}
+
+struct KeyVisitor;
+
+impl Visitor<'_> for KeyVisitor {
+    type Value = Key;
+
+    fn expecting(&self, formatter: &mut fmt::Formatter) -> fmt::Result {
+        write!(formatter, "a string key")
+    }
+
+    fn visit_str<E>(self, v: &str) -> std::result::Result<Key, E> {
+        Ok(Key::new(v))
+    }
+
+    fn visit_bytes<E>(self, v: &[u8]) -> std::result::Result<Key, E>
+    where
+        E: serde::de::Error,
+    {
+        Key::from_utf8(v).map_err(E::custom)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::collections::HashMap;
+
+    #[test]
+    fn test_key() {
+        let key = Key::from("piece length".to_string());
+        assert_eq!(key, "piece length");
+        assert_eq!(key.len(), 12);
+        assert_eq!(String::from(key.clone()), "piece length");
+
+        // The longer keys keep the allocation of the string.
+        let long = "x-a-key-longer-than-24-bytes".to_string();
+        let pointer = long.as_ptr();
+        assert_eq!(Key::from(long).as_ptr(), pointer);
+
+        let dictionary = HashMap::from([(key, 1)]);
+        assert_eq!(dictionary.get("piece length"), Some(&1));
+
+        assert!(Key::from_utf8(b"\xff").is_err());
+    }
+
+    #[test]
+    fn test_key_interner() {
+        let mut interner = KeyInterner::default();
+        let long = b"x-a-key-longer-than-24-bytes";
+        let (a, b) = (
+            interner.key_from_utf8(long).unwrap(),
+            interner.key_from_utf8(long).unwrap(),
+        );
+        assert!(a.is_shared());
+        assert_eq!(a, b);
+        assert_eq!(a, Key::from_utf8(long).unwrap());
+        assert!(!interner.key_from_utf8(b"length").unwrap().is_shared());
+
+        // The shared keys do not make the keys larger.
+        assert_eq!(std::mem::size_of::<Key>(), std::mem::size_of::<String>());
+    }
+}
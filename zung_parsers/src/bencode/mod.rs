@@ -5,18 +5,65 @@
 //! and decode Bencode strings into Rust data structures or json or yaml. See the implemented
 //! methods for more information,
 //!
+//! ## JSON and YAML
+//!
+//! [`Value`] converts from [`serde_json::Value`] and [`serde_yaml::Value`] with `TryFrom`, and to
+//! them with `From`. Bencode only has integers, byte strings, lists and dictionaries, so the
+//! other types are converted with these rules:
+//!
+//! | JSON / YAML                       | Bencode                                          |
+//! |-----------------------------------|--------------------------------------------------|
+//! | `true` / `false`                  | `i1e` / `i0e`                                    |
+//! | integer                           | integer, if it fits in an `i64`                  |
+//! | float                             | integer if it has no fractional part, else error |
+//! | `null` in a dictionary            | the key is left out                              |
+//! | `null` anywhere else              | error                                            |
+//! | YAML mapping with non-string keys | error                                            |
+//! | YAML tagged value                 | the value, without the tag                       |
+//!
+//! In the other direction byte strings which are valid UTF-8 become strings and the others become
+//! a list of the byte values, which is also how [`Value`] serializes them. Dictionaries are
+//! converted with their keys sorted, as they are encoded.
+//!
+//! The errors name the path of the offending value, such as `Invalid Value: float 1.5 at
+//! info/length (bencode only has integers)`.
+//!
+//! ## Arena
+//!
+//! With the `arena` feature, [`parse_in`] builds an [`ArenaValue`] in a [`Bump`] arena instead of
+//! a [`Value`], which is cheaper when many documents are parsed and dropped in a row. See the
+//! `parse` bench for the difference.
+//!
 //! ## TODO:
 //!
 //! - `to_writer` implementation
 
+#[cfg(feature = "arena")]
+mod arena;
+mod convert;
 mod de;
+mod diff;
 mod error;
+mod key;
 mod ser;
+mod span;
+mod stats;
+mod stream;
 mod value;
 
+#[cfg(feature = "arena")]
+pub use arena::{parse_in, ArenaValue};
+#[cfg(feature = "arena")]
+pub use bumpalo::Bump;
 pub use de::{from_bytes, from_str};
+pub use diff::{diff, Change, DiffEntry};
 pub use error::{Error, Result};
+pub use key::Key;
+use key::KeyInterner;
 pub use ser::{to_bytes, to_string, to_value};
+pub use span::{parse_spanned, Spanned, SpannedValue};
+pub use stats::{token_stats, TokenStats};
+pub use stream::{Status, StreamParser};
 pub use value::Value;
 
 use std::collections::HashMap;
@@ -34,24 +81,80 @@ where
         ValueInput::Bytes(b) => b,
     };
 
-    let mut bencode = Bencode { input: bytes };
+    let mut bencode = Bencode::from_bytes(bytes);
+
+    bencode.parse()
+}
+
+/// Options of [`parse_with`].
+#[derive(Debug, Default, Clone)]
+pub struct ParseOptions {
+    intern_keys: bool,
+}
+
+impl ParseOptions {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Shares the keys longer than 24 bytes between all the dictionaries of the document, instead
+    /// of allocating them for every dictionary. This saves memory on documents repeating long
+    /// keys in many dictionaries, at the cost of looking up every long key. The shorter keys are
+    /// stored inline in any case, see [`Key`].
+    pub fn intern_keys(mut self, intern: bool) -> Self {
+        self.intern_keys = intern;
+        self
+    }
+}
+
+/// Same as [`parse`] with the given options.
+///
+/// # Example
+///
+/// ```
+/// use zung_parsers::bencode::{self, ParseOptions, Value};
+///
+/// let input = "ld28:x-a-key-longer-than-24-bytesi1eed28:x-a-key-longer-than-24-bytesi2eee";
+/// let options = ParseOptions::new().intern_keys(true);
+/// let Value::List(list) = bencode::parse_with(input, &options).unwrap() else {
+///     panic!("Expected a list");
+/// };
+/// let Value::Dictionary(dictionary) = &list[0] else {
+///     panic!("Expected a dictionary");
+/// };
+/// assert!(dictionary.keys().all(|key| key.is_shared()));
+/// ```
+pub fn parse_with<'a, T>(input: T, options: &ParseOptions) -> Result<Value>
+where
+    T: Into<ValueInput<'a>>,
+{
+    let bytes = match input.into() {
+        ValueInput::Str(s) => s.as_bytes(),
+        ValueInput::Bytes(b) => b,
+    };
+
+    let mut bencode = Bencode::from_bytes(bytes);
+    if options.intern_keys {
+        bencode.keys = Some(KeyInterner::default());
+    }
 
     bencode.parse()
 }
 
 struct Bencode<'a> {
     input: &'a [u8],
+
+    // Shares the keys of the dictionaries when set.
+    keys: Option<KeyInterner>,
 }
 
 impl<'a> Bencode<'a> {
     pub(crate) fn from_str(input: &'a str) -> Self {
-        Self {
-            input: input.as_bytes(),
-        }
+        Self::from_bytes(input.as_bytes())
     }
 
     pub(crate) fn from_bytes(input: &'a [u8]) -> Self {
-        Self { input }
+        Self { input, keys: None }
     }
 
     pub(crate) fn parse(&mut self) -> Result<Value> {
@@ -152,7 +255,40 @@ impl<'a> Bencode<'a> {
         Ok(value)
     }
 
+    // Parses a key of a dictionary. The key is built straight from the input so that the short
+    // keys are never allocated.
+    pub(crate) fn parse_key(&mut self) -> Result<Key> {
+        if self.input.first().is_some_and(u8::is_ascii_digit) {
+            let key = self.parse_byte_slice()?;
+            match &mut self.keys {
+                Some(keys) => keys.key_from_utf8(key),
+                None => Key::from_utf8(key),
+            }
+        } else {
+            // Parsed anyway for the error of an invalid value.
+            self.parse()?;
+            Err(Error::InvalidType(
+                "Only string values are allowed as dictionary keys".to_string(),
+            ))
+        }
+    }
+
+    // Eats the 'e' ending a list or a dictionary, or fails with the error message.
+    pub(crate) fn parse_end(&mut self, missing: &str) -> Result<()> {
+        if self.input.first() == Some(&b'e') {
+            self.input = &self.input[1..];
+            Ok(())
+        } else {
+            Err(Error::InvalidType(missing.to_string()))
+        }
+    }
+
     pub(crate) fn parse_bytes(&mut self) -> Result<Vec<u8>> {
+        self.parse_byte_slice().map(<[u8]>::to_vec)
+    }
+
+    // Parses a byte string without copying it out of the input.
+    pub(crate) fn parse_byte_slice(&mut self) -> Result<&'a [u8]> {
         let colon_pos = self.input.iter().position(|p| *p == b':').ok_or_else(|| {
             Error::InvalidValue("Invalid string bencode format: missing ':'".to_string())
         })?;
@@ -197,7 +333,7 @@ impl<'a> Bencode<'a> {
 
         self.input = remainder;
 
-        Ok(string.to_vec())
+        Ok(string)
     }
 
     pub(crate) fn parse_list(&mut self) -> Result<Vec<Value>> {
@@ -211,48 +347,25 @@ impl<'a> Bencode<'a> {
         }
 
         // eat the 'e' tag
-        if self.input.first() == Some(&b'e') {
-            self.input = &self.input[1..];
-        } else {
-            return Err(Error::InvalidType(
-                "Invalid list format: missing 'e'".to_string(),
-            ));
-        }
+        self.parse_end("Invalid list format: missing 'e'")?;
 
         Ok(list)
     }
 
-    pub(crate) fn parse_dictionary(&mut self) -> Result<HashMap<String, Value>> {
+    pub(crate) fn parse_dictionary(&mut self) -> Result<HashMap<Key, Value>> {
         let mut dictionary = HashMap::new();
 
         // eat the 'd' tag
         self.input = &self.input[1..];
 
         while !self.input.is_empty() && self.input[0] != b'e' {
-            let k = match self.parse()? {
-                Value::String(key) => key, // If it's a valid string
-                Value::Bytes(bytes) => {
-                    String::from_utf8(bytes).map_err(|e| Error::Custom(e.to_string()))?
-                } // Convert bytes to String
-                _ => {
-                    return Err(Error::InvalidType(
-                        "Only string values are allowed as dictionary keys".to_string(),
-                    ));
-                }
-            };
-
+            let k = self.parse_key()?;
             let v = self.parse()?;
             dictionary.insert(k, v);
         }
 
         // eat the 'e' tag
-        if self.input.first() == Some(&b'e') {
-            self.input = &self.input[1..];
-        } else {
-            return Err(Error::InvalidType(
-                "Invalid dictionary format: missing 'e'".to_string(),
-            ));
-        }
+        self.parse_end("Invalid dictionary format: missing 'e'")?;
 
         Ok(dictionary)
     }
@@ -339,13 +452,13 @@ mod tests {
         let mut dictionary = HashMap::new();
         dictionary.insert("cow".to_string(), Value::String("moo".to_string()));
         dictionary.insert("spam".to_string(), Value::String("eggs".to_string()));
-        assert_eq!(bencode, Value::Dictionary(dictionary));
+        assert_eq!(bencode, Value::from(dictionary));
 
         let bencode = parse("d3:cow3:moo4:spam4:eggse").unwrap();
         let mut dictionary = HashMap::new();
         dictionary.insert("cow".to_string(), Value::String("moo".to_string()));
         dictionary.insert("spam".to_string(), Value::String("eggs".to_string()));
-        assert_eq!(bencode, Value::Dictionary(dictionary));
+        assert_eq!(bencode, Value::from(dictionary));
 
         let bencode_err = parse("di2e3:moo4:spam4:eggse");
         assert!(bencode_err.is_err());
@@ -355,6 +468,28 @@ mod tests {
         );
     }
 
+    #[test]
+    fn test_parse_with_interned_keys() {
+        let input = "d28:x-a-key-longer-than-24-bytesd28:x-a-key-longer-than-24-bytesi1e3:keyi2eee";
+        let options = ParseOptions::new().intern_keys(true);
+        let interned = parse_with(input, &options).unwrap();
+        assert_eq!(interned, parse(input).unwrap());
+
+        let inner = interned.pointer("x-a-key-longer-than-24-bytes").unwrap();
+        let Value::Dictionary(inner) = inner else {
+            panic!("Expected a dictionary");
+        };
+        let mut keys: Vec<_> = inner
+            .keys()
+            .map(|key| (key.as_str(), key.is_shared()))
+            .collect();
+        keys.sort();
+        assert_eq!(
+            keys,
+            [("key", false), ("x-a-key-longer-than-24-bytes", true)]
+        );
+    }
+
     #[test]
     fn invalid_becode() {
         let bencode_err = parse("werd");
@@ -33,6 +33,13 @@ use super::{
 #[derive(Default)]
 pub struct Serializer {
     buffer: Vec<u8>,
+
+    // Offsets of the entries of the dictionaries being written, see `SerializeMap`. Shared by
+    // the nested dictionaries so that they do not each allocate their own.
+    entries: Vec<usize>,
+
+    // Set by a `None` or a unit, which have no bencode representation. See `to_bytes`.
+    absent: bool,
 }
 
 impl Serializer {
@@ -50,6 +57,35 @@ impl Serializer {
     {
         self.buffer.extend_from_slice(value.as_ref())
     }
+
+    // The integers and the length prefixes are formatted on the stack rather than in a `String`,
+    // as they are written for every value.
+    fn push_integer(&mut self, integer: impl itoa::Integer) {
+        self.buffer.push(b'i');
+        self.push(itoa::Buffer::new().format(integer));
+        self.buffer.push(b'e');
+    }
+
+    fn push_byte_string(&mut self, bytes: &[u8]) {
+        self.push(itoa::Buffer::new().format(bytes.len()));
+        self.buffer.push(b':');
+        self.push(bytes);
+    }
+
+    // Returns `true` if the value just serialized was a `None` or a unit.
+    fn take_absent(&mut self) -> bool {
+        std::mem::take(&mut self.absent)
+    }
+
+    // Fails if the value just serialized was a `None` or a unit, where it can not be skipped.
+    fn expect_present(&mut self) -> Result<()> {
+        if self.take_absent() {
+            return Err(Error::InvalidValue(
+                "`None` can only be serialized as the value of a dictionary".to_string(),
+            ));
+        }
+        Ok(())
+    }
 }
 
 impl AsRef<[u8]> for Serializer {
@@ -85,12 +121,35 @@ impl AsRef<[u8]> for Serializer {
 /// let my_struct = MyStruct { field: 42 };
 /// let bytes = bencode::to_bytes(&my_struct).unwrap(); // outputs b"i42e"
 /// ```
+///
+/// # Optional values
+///
+/// Bencode has no null value: a field or map value set to `None` is left out of its dictionary,
+/// and a `None` anywhere else, such as in a list, is an error. Empty strings, lists and
+/// dictionaries are values like any other and are always written.
+///
+/// ```rust
+/// use serde::Serialize;
+/// use zung_parsers::bencode;
+///
+/// #[derive(Serialize)]
+/// struct Torrent {
+///     comment: Option<String>,
+///     #[serde(rename = "url-list")]
+///     url_list: Option<Vec<String>>,
+/// }
+///
+/// let torrent = Torrent { comment: None, url_list: Some(vec![]) };
+/// assert_eq!(bencode::to_bytes(&torrent).unwrap(), b"d8:url-listlee");
+/// assert!(bencode::to_bytes(&vec![Some(1), None]).is_err());
+/// ```
 pub fn to_bytes<T>(value: &T) -> Result<Vec<u8>>
 where
     T: Serialize,
 {
-    let mut serializer = Serializer { buffer: Vec::new() };
+    let mut serializer = Serializer::new();
     value.serialize(&mut serializer)?;
+    serializer.expect_present()?;
     Ok(serializer.buffer)
 }
 
@@ -120,6 +179,7 @@ where
 pub fn to_string<T: ser::Serialize>(b: &T) -> Result<String> {
     let mut ser = Serializer::new();
     b.serialize(&mut ser)?;
+    ser.expect_present()?;
     std::str::from_utf8(ser.as_ref())
         .map(std::string::ToString::to_string)
         .map_err(|_| Error::InvalidValue("Not an UTF-8".to_string()))
@@ -195,10 +255,8 @@ impl<'a> ser::Serializer for &'a mut Serializer {
         self.serialize_i64(i64::from(v))
     }
 
-    // Not particularly efficient but this is example code anyway. A more
-    // performant approach would be to use the `itoa` crate.
     fn serialize_i64(self, v: i64) -> Result<()> {
-        self.push(format!("i{v}e"));
+        self.push_integer(v);
         Ok(())
     }
 
@@ -215,7 +273,7 @@ impl<'a> ser::Serializer for &'a mut Serializer {
     }
 
     fn serialize_u64(self, v: u64) -> Result<()> {
-        self.push(format!("i{v}e"));
+        self.push_integer(v);
         Ok(())
     }
 
@@ -230,32 +288,28 @@ impl<'a> ser::Serializer for &'a mut Serializer {
     // Serialize a char as a single-character string. Other formats may
     // represent this differently.
     fn serialize_char(self, v: char) -> Result<()> {
-        self.push(format!("1:{v}"));
-        Ok(())
+        self.serialize_str(v.encode_utf8(&mut [0; 4]))
     }
 
     fn serialize_str(self, v: &str) -> Result<()> {
-        self.push(format!("{}:{v}", v.len()));
+        self.push_byte_string(v.as_bytes());
         Ok(())
     }
 
     fn serialize_bytes(self, v: &[u8]) -> Result<()> {
-        self.push(v.len().to_string());
-        self.push(":");
-        self.push(v);
+        self.push_byte_string(v);
         Ok(())
     }
 
-    // An absent optional is represented as the JSON `null`.
+    // An absent optional writes nothing and is skipped by the dictionary holding it. It is an
+    // error anywhere else.
     fn serialize_none(self) -> Result<()> {
         self.serialize_unit()
     }
 
     // A present optional is represented as just the contained value. Note that
     // this is a lossy representation. For example the values `Some(())` and
-    // `None` both serialize as just `null`. Unfortunately this is typically
-    // what people expect when working with JSON. Other formats are encouraged
-    // to behave more intelligently if possible.
+    // `None` are both skipped.
     fn serialize_some<T>(self, value: &T) -> Result<()>
     where
         T: ?Sized + Serialize,
@@ -263,15 +317,16 @@ impl<'a> ser::Serializer for &'a mut Serializer {
         value.serialize(self)
     }
 
-    // In Serde, unit means an anonymous value containing no data. Map this to
-    // JSON as `null`.
+    // In Serde, unit means an anonymous value containing no data. Like `None`, it
+    // has no bencode representation.
     fn serialize_unit(self) -> Result<()> {
+        self.absent = true;
         Ok(())
     }
 
     // Unit struct means a named value containing no data. Again, since there is
-    // no data, map this to JSON as `null`. There is no need to serialize the
-    // name in most formats.
+    // no data, it is handled as a unit. There is no need to serialize the name
+    // in most formats.
     fn serialize_unit_struct(self, _name: &'static str) -> Result<()> {
         self.serialize_unit()
     }
@@ -397,7 +452,7 @@ impl<'a> ser::Serializer for &'a mut Serializer {
 //
 // This impl is SerializeSeq so these methods are called after `serialize_seq`
 // is called on the Serializer.
-impl<'a> ser::SerializeSeq for &'a mut Serializer {
+impl ser::SerializeSeq for &mut Serializer {
     // Must match the `Ok` type of the serializer.
     type Ok = ();
     // Must match the `Error` type of the serializer.
@@ -408,7 +463,8 @@ impl<'a> ser::SerializeSeq for &'a mut Serializer {
     where
         T: ?Sized + Serialize,
     {
-        value.serialize(&mut **self)
+        value.serialize(&mut **self)?;
+        self.expect_present()
     }
 
     // Close the sequence.
@@ -418,11 +474,12 @@ impl<'a> ser::SerializeSeq for &'a mut Serializer {
     }
 }
 
-impl<'a> ser::SerializeTuple for &'a mut Serializer {
+impl ser::SerializeTuple for &mut Serializer {
     type Ok = ();
     type Error = Error;
     fn serialize_element<T: ?Sized + ser::Serialize>(&mut self, value: &T) -> Result<()> {
-        value.serialize(&mut **self)
+        value.serialize(&mut **self)?;
+        self.expect_present()
     }
     fn end(self) -> Result<()> {
         ser::SerializeSeq::end(self)
@@ -430,7 +487,7 @@ impl<'a> ser::SerializeTuple for &'a mut Serializer {
 }
 
 // Same thing but for tuple structs.
-impl<'a> ser::SerializeTupleStruct for &'a mut Serializer {
+impl ser::SerializeTupleStruct for &mut Serializer {
     type Ok = ();
     type Error = Error;
 
@@ -438,7 +495,8 @@ impl<'a> ser::SerializeTupleStruct for &'a mut Serializer {
     where
         T: ?Sized + Serialize,
     {
-        value.serialize(&mut **self)
+        value.serialize(&mut **self)?;
+        self.expect_present()
     }
 
     fn end(self) -> Result<()> {
@@ -446,7 +504,7 @@ impl<'a> ser::SerializeTupleStruct for &'a mut Serializer {
     }
 }
 
-impl<'a> ser::SerializeTupleVariant for &'a mut Serializer {
+impl ser::SerializeTupleVariant for &mut Serializer {
     type Ok = ();
     type Error = Error;
 
@@ -454,7 +512,8 @@ impl<'a> ser::SerializeTupleVariant for &'a mut Serializer {
     where
         T: ?Sized + Serialize,
     {
-        value.serialize(&mut **self)
+        value.serialize(&mut **self)?;
+        self.expect_present()
     }
 
     fn end(self) -> Result<()> {
@@ -470,7 +529,7 @@ impl<'a> ser::SerializeTupleVariant for &'a mut Serializer {
 // `serialize_entry` method allows serializers to optimize for the case where
 // key and value are both available simultaneously. In JSON it doesn't make a
 // difference so the default behavior for `serialize_entry` is fine.
-impl<'a> ser::SerializeMap for &'a mut Serializer {
+impl ser::SerializeMap for &mut Serializer {
     type Ok = ();
     type Error = Error;
 
@@ -505,89 +564,175 @@ impl<'a> ser::SerializeMap for &'a mut Serializer {
     }
 }
 
+/// Serializes the maps and structs as dictionaries, whose keys must be sorted.
+///
+/// The entries are written straight to the buffer as long as the keys arrive sorted, as the
+/// fields of most structs do. Once a key arrives out of order, the entries are buffered and
+/// sorted at the end of the map instead.
 pub struct SerializeMap<'a> {
     ser: &'a mut Serializer,
+
+    // Index in `ser.entries` of the first entry written straight to the buffer, `None` once the
+    // entries are buffered.
+    streamed: Option<usize>,
+
+    // The buffered entries, each an encoded key and its value.
     entries: Vec<(Vec<u8>, Vec<u8>)>,
-    cur_key: Option<Vec<u8>>,
+    cur_key: Option<PendingKey>,
+}
+
+// A key waiting for its value.
+enum PendingKey {
+    // Written to the buffer, at the offset of the last entry of `ser.entries`.
+    Streamed,
+    Buffered(Vec<u8>),
 }
 
 impl<'a> SerializeMap<'a> {
-    pub fn new(ser: &'a mut Serializer, len: usize) -> SerializeMap<'_> {
+    pub fn new(ser: &'a mut Serializer, len: usize) -> SerializeMap<'a> {
+        ser.push("d");
+        let streamed = Some(ser.entries.len());
         SerializeMap {
             ser,
+            streamed,
             entries: Vec::with_capacity(len),
             cur_key: None,
         }
     }
 
-    fn end_map(&mut self) -> Result<()> {
+    fn key<T: ?Sized + ser::Serialize>(&mut self, key: &T) -> Result<()> {
         if self.cur_key.is_some() {
             return Err(Error::InvalidValue(
-                "`serialize_key` called without calling  `serialize_value`".to_string(),
+                "`serialize_key` called multiple times without calling  `serialize_value`"
+                    .to_string(),
             ));
         }
-        let mut entries = std::mem::take(&mut self.entries);
-        entries.sort_by(|(a, _), (b, _)| a.cmp(b));
-        self.ser.push("d");
-        for (k, v) in entries {
-            ser::Serializer::serialize_bytes(&mut *self.ser, k.as_ref())?;
-            self.ser.push(v);
+
+        let Some(first) = self.streamed else {
+            let mut encoded = Vec::new();
+            key.serialize(string::Serializer(&mut encoded))?;
+            self.cur_key = Some(PendingKey::Buffered(encoded));
+            return Ok(());
+        };
+
+        let start = self.ser.buffer.len();
+        key.serialize(string::Serializer(&mut self.ser.buffer))?;
+        let sorted = match self.ser.entries[first..].last() {
+            Some(&last) => {
+                decode_key(&self.ser.buffer[last..]).0 < decode_key(&self.ser.buffer[start..]).0
+            }
+            None => true,
+        };
+
+        if sorted {
+            self.ser.entries.push(start);
+            self.cur_key = Some(PendingKey::Streamed);
+        } else {
+            let encoded = self.ser.buffer.split_off(start);
+            self.buffer_streamed(first);
+            self.cur_key = Some(PendingKey::Buffered(encoded));
         }
-        self.ser.push("e");
         Ok(())
     }
-}
 
-impl<'a> ser::SerializeMap for SerializeMap<'a> {
-    type Ok = ();
-    type Error = Error;
-
-    fn serialize_key<T: ?Sized + ser::Serialize>(&mut self, key: &T) -> Result<()> {
-        if self.cur_key.is_some() {
-            return Err(Error::InvalidValue(
-                "`serialize_key` called multiple times without calling  `serialize_value`"
-                    .to_string(),
-            ));
+    fn value<T: ?Sized + ser::Serialize>(&mut self, value: &T) -> Result<()> {
+        match self.cur_key.take() {
+            Some(PendingKey::Streamed) => {
+                value.serialize(&mut *self.ser)?;
+                if self.ser.take_absent() {
+                    let start = self
+                        .ser
+                        .entries
+                        .pop()
+                        .expect("The key of the entry is written");
+                    self.ser.buffer.truncate(start);
+                }
+            }
+            Some(PendingKey::Buffered(key)) => {
+                let mut ser = Serializer::new();
+                value.serialize(&mut ser)?;
+                if !ser.take_absent() {
+                    self.entries.push((key, ser.into_vec()));
+                }
+            }
+            None => {
+                return Err(Error::InvalidValue(
+                    "`serialize_value` called without calling `serialize_key`".to_string(),
+                ))
+            }
         }
-        self.cur_key = Some(key.serialize(&mut string::Serializer)?);
         Ok(())
     }
 
-    fn serialize_value<T: ?Sized + ser::Serialize>(&mut self, value: &T) -> Result<()> {
-        let key = self.cur_key.take().ok_or_else(|| {
-            Error::InvalidValue(
-                "`serialize_value` called without calling `serialize_key`".to_string(),
-            )
-        })?;
-        let mut ser = Serializer::new();
-        value.serialize(&mut ser)?;
-        let value = ser.into_vec();
-        if !value.is_empty() {
-            self.entries.push((key, value));
+    // Moves the entries written to the buffer since `first` to the buffered entries.
+    fn buffer_streamed(&mut self, first: usize) {
+        let offsets = self.ser.entries.split_off(first);
+        if let Some(&start) = offsets.first() {
+            let streamed = self.ser.buffer.split_off(start);
+            let ends = offsets
+                .iter()
+                .skip(1)
+                .copied()
+                .chain([start + streamed.len()]);
+            for (&entry_start, entry_end) in offsets.iter().zip(ends) {
+                let entry = &streamed[entry_start - start..entry_end - start];
+                let (_, key_length) = decode_key(entry);
+                self.entries
+                    .push((entry[..key_length].to_vec(), entry[key_length..].to_vec()));
+            }
         }
-        Ok(())
+        self.streamed = None;
     }
 
-    fn serialize_entry<K, V>(&mut self, key: &K, value: &V) -> Result<()>
-    where
-        K: ?Sized + ser::Serialize,
-        V: ?Sized + ser::Serialize,
-    {
+    fn end_map(&mut self) -> Result<()> {
         if self.cur_key.is_some() {
             return Err(Error::InvalidValue(
-                "`serialize_key` called multiple times without calling  `serialize_value`"
-                    .to_string(),
+                "`serialize_key` called without calling  `serialize_value`".to_string(),
             ));
         }
-        let key = key.serialize(&mut string::Serializer)?;
-        let mut ser = Serializer::new();
-        value.serialize(&mut ser)?;
-        let value = ser.into_vec();
-        if !value.is_empty() {
-            self.entries.push((key, value));
+        match self.streamed {
+            Some(first) => self.ser.entries.truncate(first),
+            None => {
+                let mut entries = std::mem::take(&mut self.entries);
+                entries.sort_by(|(a, _), (b, _)| decode_key(a).0.cmp(decode_key(b).0));
+                for (k, v) in entries {
+                    self.ser.push(k);
+                    self.ser.push(v);
+                }
+            }
         }
+        self.ser.push("e");
         Ok(())
     }
+}
+
+// Splits the key encoded at the start of the input into its bytes and the length of its
+// encoding.
+fn decode_key(encoded: &[u8]) -> (&[u8], usize) {
+    let colon = encoded
+        .iter()
+        .position(|&b| b == b':')
+        .expect("The key is encoded as a byte string");
+    let length: usize = std::str::from_utf8(&encoded[..colon])
+        .ok()
+        .and_then(|length| length.parse().ok())
+        .expect("The key is encoded as a byte string");
+    let end = colon + 1 + length;
+    (&encoded[colon + 1..end], end)
+}
+
+impl<'a> ser::SerializeMap for SerializeMap<'a> {
+    type Ok = ();
+    type Error = Error;
+
+    fn serialize_key<T: ?Sized + ser::Serialize>(&mut self, key: &T) -> Result<()> {
+        self.key(key)
+    }
+
+    fn serialize_value<T: ?Sized + ser::Serialize>(&mut self, value: &T) -> Result<()> {
+        self.value(value)
+    }
+
     fn end(mut self) -> Result<()> {
         self.end_map()
     }
@@ -626,7 +771,8 @@ impl<'a> ser::SerializeStructVariant for SerializeMap<'a> {
 }
 
 mod string {
-    //! Serializer for serializing *just* strings.
+    //! Serializer for serializing *just* strings, written as a bencode byte string to the end of
+    //! a buffer.
 
     use super::super::error::{Error, Result};
     use serde::de;
@@ -645,65 +791,69 @@ mod string {
         Err(de::Error::invalid_type(unexp, &Expected))
     }
 
-    pub(crate) struct Serializer;
+    pub(crate) struct Serializer<'a>(pub(crate) &'a mut Vec<u8>);
 
-    impl<'a> ser::Serializer for &'a mut Serializer {
-        type Ok = Vec<u8>;
+    impl ser::Serializer for Serializer<'_> {
+        type Ok = ();
         type Error = Error;
-        type SerializeSeq = ser::Impossible<Vec<u8>, Error>;
-        type SerializeTuple = ser::Impossible<Vec<u8>, Error>;
-        type SerializeTupleStruct = ser::Impossible<Vec<u8>, Error>;
-        type SerializeTupleVariant = ser::Impossible<Vec<u8>, Error>;
-        type SerializeMap = ser::Impossible<Vec<u8>, Error>;
-        type SerializeStruct = ser::Impossible<Vec<u8>, Error>;
-        type SerializeStructVariant = ser::Impossible<Vec<u8>, Error>;
-
-        fn serialize_bool(self, value: bool) -> Result<Vec<u8>> {
+        type SerializeSeq = ser::Impossible<(), Error>;
+        type SerializeTuple = ser::Impossible<(), Error>;
+        type SerializeTupleStruct = ser::Impossible<(), Error>;
+        type SerializeTupleVariant = ser::Impossible<(), Error>;
+        type SerializeMap = ser::Impossible<(), Error>;
+        type SerializeStruct = ser::Impossible<(), Error>;
+        type SerializeStructVariant = ser::Impossible<(), Error>;
+
+        fn serialize_bool(self, value: bool) -> Result<()> {
             unexpected(de::Unexpected::Bool(value))
         }
-        fn serialize_i8(self, value: i8) -> Result<Vec<u8>> {
+        fn serialize_i8(self, value: i8) -> Result<()> {
             self.serialize_i64(i64::from(value))
         }
-        fn serialize_i16(self, value: i16) -> Result<Vec<u8>> {
+        fn serialize_i16(self, value: i16) -> Result<()> {
             self.serialize_i64(i64::from(value))
         }
-        fn serialize_i32(self, value: i32) -> Result<Vec<u8>> {
+        fn serialize_i32(self, value: i32) -> Result<()> {
             self.serialize_i64(i64::from(value))
         }
-        fn serialize_i64(self, value: i64) -> Result<Vec<u8>> {
+        fn serialize_i64(self, value: i64) -> Result<()> {
             unexpected(de::Unexpected::Signed(value))
         }
-        fn serialize_u8(self, value: u8) -> Result<Vec<u8>> {
+        fn serialize_u8(self, value: u8) -> Result<()> {
             self.serialize_u64(u64::from(value))
         }
-        fn serialize_u16(self, value: u16) -> Result<Vec<u8>> {
+        fn serialize_u16(self, value: u16) -> Result<()> {
             self.serialize_u64(u64::from(value))
         }
-        fn serialize_u32(self, value: u32) -> Result<Vec<u8>> {
+        fn serialize_u32(self, value: u32) -> Result<()> {
             self.serialize_u64(u64::from(value))
         }
-        fn serialize_u64(self, value: u64) -> Result<Vec<u8>> {
+        fn serialize_u64(self, value: u64) -> Result<()> {
             unexpected(de::Unexpected::Unsigned(value))
         }
-        fn serialize_f32(self, value: f32) -> Result<Vec<u8>> {
+        fn serialize_f32(self, value: f32) -> Result<()> {
             self.serialize_f64(f64::from(value))
         }
-        fn serialize_f64(self, value: f64) -> Result<Vec<u8>> {
+        fn serialize_f64(self, value: f64) -> Result<()> {
             unexpected(de::Unexpected::Float(value))
         }
-        fn serialize_char(self, value: char) -> Result<Vec<u8>> {
-            self.serialize_bytes(&[value as u8])
+        fn serialize_char(self, value: char) -> Result<()> {
+            self.serialize_str(value.encode_utf8(&mut [0; 4]))
         }
-        fn serialize_str(self, value: &str) -> Result<Vec<u8>> {
+        fn serialize_str(self, value: &str) -> Result<()> {
             self.serialize_bytes(value.as_bytes())
         }
-        fn serialize_bytes(self, value: &[u8]) -> Result<Vec<u8>> {
-            Ok(value.into())
+        fn serialize_bytes(self, value: &[u8]) -> Result<()> {
+            self.0
+                .extend_from_slice(itoa::Buffer::new().format(value.len()).as_bytes());
+            self.0.push(b':');
+            self.0.extend_from_slice(value);
+            Ok(())
         }
-        fn serialize_unit(self) -> Result<Vec<u8>> {
+        fn serialize_unit(self) -> Result<()> {
             unexpected(de::Unexpected::Unit)
         }
-        fn serialize_unit_struct(self, _name: &'static str) -> Result<Vec<u8>> {
+        fn serialize_unit_struct(self, _name: &'static str) -> Result<()> {
             self.serialize_unit()
         }
         fn serialize_unit_variant(
@@ -711,14 +861,14 @@ mod string {
             _name: &'static str,
             _variant_index: u32,
             _variant: &'static str,
-        ) -> Result<Vec<u8>> {
+        ) -> Result<()> {
             unexpected(de::Unexpected::UnitVariant)
         }
         fn serialize_newtype_struct<T: ?Sized + ser::Serialize>(
             self,
             _name: &'static str,
             _value: &T,
-        ) -> Result<Vec<u8>> {
+        ) -> Result<()> {
             unexpected(de::Unexpected::NewtypeStruct)
         }
         fn serialize_newtype_variant<T: ?Sized + ser::Serialize>(
@@ -727,26 +877,26 @@ mod string {
             _variant_index: u32,
             _variant: &'static str,
             _value: &T,
-        ) -> Result<Vec<u8>> {
+        ) -> Result<()> {
             unexpected(de::Unexpected::NewtypeVariant)
         }
-        fn serialize_none(self) -> Result<Vec<u8>> {
+        fn serialize_none(self) -> Result<()> {
             unexpected(de::Unexpected::Option)
         }
-        fn serialize_some<T: ?Sized + ser::Serialize>(self, _value: &T) -> Result<Vec<u8>> {
+        fn serialize_some<T: ?Sized + ser::Serialize>(self, _value: &T) -> Result<()> {
             unexpected(de::Unexpected::Option)
         }
-        fn serialize_seq(self, _len: Option<usize>) -> Result<ser::Impossible<Vec<u8>, Error>> {
+        fn serialize_seq(self, _len: Option<usize>) -> Result<ser::Impossible<(), Error>> {
             unexpected(de::Unexpected::Seq)
         }
-        fn serialize_tuple(self, _size: usize) -> Result<ser::Impossible<Vec<u8>, Error>> {
+        fn serialize_tuple(self, _size: usize) -> Result<ser::Impossible<(), Error>> {
             unexpected(de::Unexpected::Seq)
         }
         fn serialize_tuple_struct(
             self,
             _name: &'static str,
             _len: usize,
-        ) -> Result<ser::Impossible<Vec<u8>, Error>> {
+        ) -> Result<ser::Impossible<(), Error>> {
             unexpected(de::Unexpected::NewtypeStruct)
         }
         fn serialize_tuple_variant(
@@ -755,17 +905,17 @@ mod string {
             _variant_index: u32,
             _variant: &'static str,
             _len: usize,
-        ) -> Result<ser::Impossible<Vec<u8>, Error>> {
+        ) -> Result<ser::Impossible<(), Error>> {
             unexpected(de::Unexpected::TupleVariant)
         }
-        fn serialize_map(self, _len: Option<usize>) -> Result<ser::Impossible<Vec<u8>, Error>> {
+        fn serialize_map(self, _len: Option<usize>) -> Result<ser::Impossible<(), Error>> {
             unexpected(de::Unexpected::Map)
         }
         fn serialize_struct(
             self,
             _name: &'static str,
             _len: usize,
-        ) -> Result<ser::Impossible<Vec<u8>, Error>> {
+        ) -> Result<ser::Impossible<(), Error>> {
             unexpected(de::Unexpected::NewtypeStruct)
         }
         fn serialize_struct_variant(
@@ -774,7 +924,7 @@ mod string {
             _variant_index: u32,
             _variant: &'static str,
             _len: usize,
-        ) -> Result<ser::Impossible<Vec<u8>, Error>> {
+        ) -> Result<ser::Impossible<(), Error>> {
             unexpected(de::Unexpected::StructVariant)
         }
     }
@@ -817,6 +967,125 @@ mod tests {
         );
     }
 
+    #[test]
+    fn test_to_bytes_integers_and_lengths() {
+        assert_eq!(to_bytes(&i64::MIN).unwrap(), b"i-9223372036854775808e");
+        assert_eq!(to_bytes(&u64::MAX).unwrap(), b"i18446744073709551615e");
+        assert_eq!(to_bytes(&0_u8).unwrap(), b"i0e");
+
+        // The lengths are in bytes, a `char` is as long as its UTF-8 encoding.
+        assert_eq!(to_bytes(&'é').unwrap(), "2:é".as_bytes());
+        assert_eq!(to_bytes(&"").unwrap(), b"0:");
+        let long = "x".repeat(12345);
+        assert_eq!(to_bytes(&long).unwrap(), format!("12345:{long}").as_bytes());
+
+        let map = std::collections::BTreeMap::from([('é', 1)]);
+        assert_eq!(to_bytes(&map).unwrap(), "d2:éi1ee".as_bytes());
+    }
+
+    #[test]
+    fn test_to_bytes_sorts_the_keys() {
+        #[derive(Serialize)]
+        struct Unsorted {
+            b: i32,
+            c: Option<i32>,
+            inner: Sorted,
+            a: Vec<Sorted>,
+            d: Option<i32>,
+        }
+
+        #[derive(Serialize)]
+        struct Sorted {
+            x: i32,
+            y: Option<i32>,
+            z: String,
+        }
+
+        let sorted = || Sorted {
+            x: 1,
+            y: None,
+            z: "z".to_string(),
+        };
+        assert_eq!(to_bytes(&sorted()).unwrap(), b"d1:xi1e1:z1:ze");
+
+        // The entries written before the key out of order are sorted with the others.
+        let unsorted = Unsorted {
+            b: 2,
+            c: Some(3),
+            inner: sorted(),
+            a: vec![sorted(), sorted()],
+            d: None,
+        };
+        assert_eq!(
+            to_bytes(&unsorted).unwrap(),
+            b"d1:ald1:xi1e1:z1:zed1:xi1e1:z1:zee1:bi2e1:ci3e5:innerd1:xi1e1:z1:zee"
+        );
+
+        let map = std::collections::HashMap::from([("b", 1), ("a", 2), ("", 3), ("ab", 4)]);
+        assert_eq!(to_bytes(&map).unwrap(), b"d0:i3e1:ai2e2:abi4e1:bi1ee");
+    }
+
+    #[test]
+    fn test_none_and_empty_values() {
+        #[derive(Serialize, serde::Deserialize, Debug, PartialEq)]
+        struct MetaInfo {
+            announce: Option<String>,
+            comment: Option<String>,
+            #[serde(rename = "url-list")]
+            url_list: Option<Vec<String>>,
+            #[serde(rename = "announce-list")]
+            announce_list: Option<Vec<Vec<String>>>,
+            extra: std::collections::BTreeMap<String, Value>,
+        }
+
+        for (meta_info, bytes) in [
+            (
+                MetaInfo {
+                    announce: None,
+                    comment: Some(String::new()),
+                    url_list: Some(vec![]),
+                    announce_list: Some(vec![vec![]]),
+                    extra: Default::default(),
+                },
+                &b"d13:announce-listllee7:comment0:5:extrade8:url-listlee"[..],
+            ),
+            (
+                MetaInfo {
+                    announce: Some("http://tracker".to_string()),
+                    comment: None,
+                    url_list: Some(vec![String::new()]),
+                    announce_list: None,
+                    extra: [("x".to_string(), Value::List(vec![]))].into(),
+                },
+                b"d8:announce14:http://tracker5:extrad1:xlee8:url-listl0:ee",
+            ),
+        ] {
+            assert_eq!(to_bytes(&meta_info).unwrap(), bytes);
+            assert_eq!(
+                crate::bencode::from_bytes::<MetaInfo>(bytes).unwrap(),
+                meta_info
+            );
+        }
+
+        // `None` has no representation outside of a dictionary.
+        for result in [
+            to_bytes(&None::<i32>),
+            to_bytes(&vec![Some(1), None]),
+            to_bytes(&(1, ())),
+            to_bytes(
+                &[("key", vec![None::<String>])]
+                    .into_iter()
+                    .collect::<Vec<_>>(),
+            ),
+        ] {
+            assert_eq!(
+                result.unwrap_err().to_string(),
+                "`None` can only be serialized as the value of a dictionary"
+            );
+        }
+        assert_eq!(to_bytes(&Some(Some(1))).unwrap(), b"i1e");
+    }
+
     #[test]
     fn test_to_string() {
         // Test with a simple integer
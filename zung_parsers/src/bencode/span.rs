@@ -0,0 +1,187 @@
+use std::ops::Range;
+
+use super::error::Result;
+use super::value::ValueInput;
+use super::{Bencode, Key, Value};
+
+/// A value parsed by [`parse_spanned`] with the range of bytes it takes in the input.
+///
+/// The children of the lists and dictionaries are spanned values too, so any part of the
+/// document can be mapped back to its bytes without parsing it again, e.g. to hash the `info`
+/// dictionary of a torrent as it is in the file.
+#[derive(Debug, Clone, PartialEq)]
+pub struct SpannedValue {
+    pub value: Spanned,
+
+    /// Offset of the first byte of the value in the input.
+    pub start: usize,
+
+    /// Offset of the byte after the value in the input.
+    pub end: usize,
+}
+
+/// The value of a [`SpannedValue`], as in [`Value`].
+///
+/// The dictionaries keep their entries in the order of the input.
+#[derive(Debug, Clone, PartialEq)]
+pub enum Spanned {
+    Integer(i64),
+    Bytes(Vec<u8>),
+    String(String),
+    List(Vec<SpannedValue>),
+    Dictionary(Vec<(Key, SpannedValue)>),
+}
+
+impl SpannedValue {
+    pub fn span(&self) -> Range<usize> {
+        self.start..self.end
+    }
+
+    /// Returns the bytes of the value in the input it was parsed from.
+    pub fn bytes<'a>(&self, input: &'a [u8]) -> &'a [u8] {
+        &input[self.span()]
+    }
+
+    /// Returns the value of the key if this is a dictionary holding it. When a key is repeated
+    /// the last value is returned, as in the [`Value::Dictionary`] of the same input.
+    pub fn get(&self, key: &str) -> Option<&SpannedValue> {
+        match &self.value {
+            Spanned::Dictionary(entries) => entries
+                .iter()
+                .rev()
+                .find(|(k, _)| k == key)
+                .map(|(_, value)| value),
+            _ => None,
+        }
+    }
+
+    /// Looks up a value by a `/` separated path, as [`Value::pointer`].
+    pub fn pointer(&self, path: &str) -> Option<&SpannedValue> {
+        path.split('/')
+            .filter(|segment| !segment.is_empty())
+            .try_fold(self, |value, segment| match &value.value {
+                Spanned::List(list) => segment.parse::<usize>().ok().and_then(|i| list.get(i)),
+                _ => value.get(segment),
+            })
+    }
+
+    /// Drops the spans.
+    pub fn to_value(&self) -> Value {
+        match &self.value {
+            Spanned::Integer(i) => Value::Integer(*i),
+            Spanned::Bytes(bytes) => Value::Bytes(bytes.clone()),
+            Spanned::String(string) => Value::String(string.clone()),
+            Spanned::List(list) => Value::List(list.iter().map(Self::to_value).collect()),
+            Spanned::Dictionary(entries) => Value::Dictionary(
+                entries
+                    .iter()
+                    .map(|(key, value)| (key.clone(), value.to_value()))
+                    .collect(),
+            ),
+        }
+    }
+}
+
+/// Parses the input like [`parse`](super::parse), recording the range of bytes of every value.
+///
+/// # Example
+///
+/// ```
+/// use zung_parsers::bencode;
+///
+/// let input = b"d8:announce3:url4:infod6:lengthi42eee";
+/// let torrent = bencode::parse_spanned(input).unwrap();
+/// let info = torrent.get("info").unwrap();
+/// assert_eq!(info.span(), 22..36);
+/// assert_eq!(info.bytes(input), b"d6:lengthi42ee");
+/// ```
+pub fn parse_spanned<'a, T>(input: T) -> Result<SpannedValue>
+where
+    T: Into<ValueInput<'a>>,
+{
+    let bytes = match input.into() {
+        ValueInput::Str(s) => s.as_bytes(),
+        ValueInput::Bytes(b) => b,
+    };
+
+    parse_value(&mut Bencode::from_bytes(bytes), bytes.len())
+}
+
+fn parse_value(bencode: &mut Bencode<'_>, length: usize) -> Result<SpannedValue> {
+    let start = length - bencode.input.len();
+    let value = match bencode.input.first() {
+        Some(b'l') => {
+            bencode.input = &bencode.input[1..];
+            let mut list = Vec::new();
+            while bencode.input.first().is_some_and(|&b| b != b'e') {
+                list.push(parse_value(bencode, length)?);
+            }
+            bencode.parse_end("Invalid list format: missing 'e'")?;
+            Spanned::List(list)
+        }
+        Some(b'd') => {
+            bencode.input = &bencode.input[1..];
+            let mut entries = Vec::new();
+            while bencode.input.first().is_some_and(|&b| b != b'e') {
+                let key = bencode.parse_key()?;
+                entries.push((key, parse_value(bencode, length)?));
+            }
+            bencode.parse_end("Invalid dictionary format: missing 'e'")?;
+            Spanned::Dictionary(entries)
+        }
+        _ => match bencode.parse()? {
+            Value::Integer(i) => Spanned::Integer(i),
+            Value::Bytes(bytes) => Spanned::Bytes(bytes),
+            Value::String(string) => Spanned::String(string),
+            Value::List(_) | Value::Dictionary(_) => {
+                unreachable!("Lists and dictionaries are parsed above")
+            }
+        },
+    };
+
+    Ok(SpannedValue {
+        value,
+        start,
+        end: length - bencode.input.len(),
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::bencode::parse;
+
+    #[test]
+    fn test_parse_spanned() {
+        let input = b"d4:infod5:filesl3:onei-7eee3:keyi1e3:keyi2ee";
+        let spanned = parse_spanned(input).unwrap();
+        assert_eq!(spanned.span(), 0..input.len());
+        assert_eq!(spanned.to_value(), parse(input).unwrap());
+
+        let files = spanned.pointer("info/files").unwrap();
+        assert_eq!(files.bytes(input), b"l3:onei-7ee");
+        assert_eq!(spanned.pointer("info/files/1").unwrap().span(), 21..25);
+        assert!(spanned.pointer("info/files/2").is_none());
+        assert_eq!(spanned.get("key").unwrap().bytes(input), b"i2e");
+
+        // The spans are the bytes of the input, even when it is not canonical.
+        let input = b"d1:bi1e1:ai2ee";
+        let spanned = parse_spanned(input).unwrap();
+        assert_eq!(spanned.bytes(input), input);
+        assert_ne!(
+            crate::bencode::to_bytes(&spanned.to_value()).unwrap(),
+            input
+        );
+    }
+
+    #[test]
+    fn test_parse_spanned_errors() {
+        for input in [&b""[..], b"l", b"d1:a", b"di1ei2ee", b"x", b"li1e"] {
+            assert_eq!(
+                parse_spanned(input).unwrap_err().to_string(),
+                parse(input).unwrap_err().to_string(),
+                "{input:?}"
+            );
+        }
+    }
+}
@@ -0,0 +1,162 @@
+use serde::Serialize;
+
+use super::error::{Error, Result};
+use super::Bencode;
+
+/// The tokens of a bencode document, counted by [`token_stats`].
+#[derive(Debug, Default, Clone, Copy, PartialEq, Eq, Serialize)]
+pub struct TokenStats {
+    pub integers: usize,
+
+    /// Byte strings, except the dictionary keys.
+    pub strings: usize,
+
+    /// Bytes of the contents of the strings.
+    pub string_bytes: usize,
+
+    /// Keys of the dictionaries.
+    pub keys: usize,
+    pub lists: usize,
+    pub dictionaries: usize,
+
+    /// Deepest nesting of lists and dictionaries, `0` for a document holding a single integer or
+    /// string.
+    pub max_depth: usize,
+
+    /// Bytes after the end of the first value, which the parser ignores.
+    pub trailing_bytes: usize,
+}
+
+/// Counts the tokens of the first value of the input and validates it, without building the
+/// [`Value`](super::Value).
+///
+/// This is the cost of tokenizing the input on its own: comparing it with the time of
+/// [`parse`](super::parse) tells how much of the parse goes into building the values.
+///
+/// # Example
+///
+/// ```
+/// use zung_parsers::bencode;
+///
+/// let stats = bencode::token_stats(b"d4:listli1ei2ee4:name4:zunge").unwrap();
+/// assert_eq!((stats.keys, stats.integers, stats.strings), (2, 2, 1));
+/// assert_eq!(stats.max_depth, 2);
+/// ```
+pub fn token_stats(input: &[u8]) -> Result<TokenStats> {
+    let mut bencode = Bencode::from_bytes(input);
+    let mut stats = TokenStats::default();
+
+    // The open lists (`None`) and dictionaries (`Some(true)` when a key comes next). Kept on the
+    // heap so that deeply nested documents do not overflow the stack.
+    let mut open: Vec<Option<bool>> = Vec::new();
+    loop {
+        let expects_key = open.last() == Some(&Some(true));
+        match bencode.input.first() {
+            None if open.is_empty() => return Err(Error::EndOfStream),
+            None => {
+                return Err(Error::InvalidType(
+                    "Invalid bencode format: missing 'e'".to_string(),
+                ))
+            }
+            Some(b'e') if open.last() == Some(&Some(false)) => {
+                return Err(Error::InvalidType(
+                    "Invalid dictionary format: missing value".to_string(),
+                ))
+            }
+            Some(b'e') if !open.is_empty() => {
+                bencode.input = &bencode.input[1..];
+                open.pop();
+            }
+            Some(b'0'..=b'9') => {
+                let string = bencode.parse_byte_slice()?;
+                if expects_key {
+                    stats.keys += 1;
+                } else {
+                    stats.strings += 1;
+                    stats.string_bytes += string.len();
+                }
+            }
+            Some(_) if expects_key => {
+                return Err(Error::InvalidType(
+                    "Only string values are allowed as dictionary keys".to_string(),
+                ))
+            }
+            Some(b'i') => {
+                bencode.parse_integer()?;
+                stats.integers += 1;
+            }
+            Some(b'l') => {
+                bencode.input = &bencode.input[1..];
+                stats.lists += 1;
+                open.push(None);
+                stats.max_depth = stats.max_depth.max(open.len());
+                continue;
+            }
+            Some(b'd') => {
+                bencode.input = &bencode.input[1..];
+                stats.dictionaries += 1;
+                open.push(Some(true));
+                stats.max_depth = stats.max_depth.max(open.len());
+                continue;
+            }
+            Some(_) => return Err(Error::InvalidType("Invalid bencode format".to_string())),
+        }
+
+        // A value is complete: the dictionary holding it expects a key next, or a value after a
+        // key.
+        match open.last_mut() {
+            Some(Some(expects_key)) => *expects_key = !*expects_key,
+            Some(None) => {}
+            None => break,
+        }
+    }
+
+    stats.trailing_bytes = bencode.input.len();
+    Ok(stats)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_token_stats() {
+        let stats = token_stats(b"d1:ai1e1:bl3:abcd1:cdeee1:d0:e").unwrap();
+        assert_eq!(
+            stats,
+            TokenStats {
+                integers: 1,
+                strings: 2,
+                string_bytes: 3,
+                keys: 4,
+                lists: 1,
+                dictionaries: 3,
+                max_depth: 4,
+                trailing_bytes: 0,
+            }
+        );
+
+        let stats = token_stats(b"i42eextra").unwrap();
+        assert_eq!((stats.integers, stats.max_depth), (1, 0));
+        assert_eq!(stats.trailing_bytes, 5);
+    }
+
+    #[test]
+    fn test_token_stats_errors() {
+        for invalid in [
+            &b""[..],
+            b"l",
+            b"li1e",
+            b"d1:ae",
+            b"di1ei2ee",
+            b"x",
+            b"5:abc",
+        ] {
+            assert!(token_stats(invalid).is_err(), "{invalid:?}");
+        }
+
+        // Deep nesting does not overflow the stack.
+        let deep = [vec![b'l'; 100_000], vec![b'e'; 100_000]].concat();
+        assert_eq!(token_stats(&deep).unwrap().max_depth, 100_000);
+    }
+}
@@ -0,0 +1,237 @@
+use super::error::{Error, Result};
+use super::{Bencode, Value};
+
+/// Longest integer token, `i-9223372036854775808e` without its `e`. An unfinished integer longer
+/// than this is invalid whatever comes next.
+const MAX_INTEGER_LENGTH: usize = 21;
+
+/// Longest length prefix of a string, the digits of [`usize::MAX`].
+const MAX_LENGTH_DIGITS: usize = 20;
+
+/// What a [`StreamParser`] got out of the bytes fed so far.
+#[derive(Debug, Clone, PartialEq)]
+pub enum Status {
+    /// The value is not complete yet.
+    NeedMore,
+
+    /// A complete value.
+    Done(Value),
+}
+
+/// A parser fed with the bytes of a value as they arrive, such as the chunks of a response read
+/// from the network, instead of requiring the whole value up front.
+///
+/// Every chunk is checked as it is fed so that invalid input fails early, and the value is built
+/// once its last byte arrives. The bytes fed after the end of a value are kept in
+/// [`StreamParser::remainder`] and are the start of the next value, so a single parser reads a
+/// stream of values one after the other.
+///
+/// The parser can not be used anymore once it returned an error.
+///
+/// # Example
+///
+/// ```
+/// use zung_parsers::bencode::{self, Status, StreamParser};
+///
+/// let mut parser = StreamParser::new();
+/// assert_eq!(parser.feed(b"d8:intervali18").unwrap(), Status::NeedMore);
+/// assert_eq!(parser.feed(b"00e5:pe").unwrap(), Status::NeedMore);
+///
+/// let Status::Done(value) = parser.feed(b"ers0:ei42e").unwrap() else {
+///     panic!("Expected a complete value");
+/// };
+/// assert_eq!(value, bencode::parse("d8:intervali1800e5:peers0:e").unwrap());
+/// assert_eq!(parser.remainder(), b"i42e");
+/// ```
+#[derive(Debug, Default)]
+pub struct StreamParser {
+    buffer: Vec<u8>,
+
+    // Bytes of the buffer already checked.
+    checked: usize,
+
+    // The open lists (`None`) and dictionaries (`Some(true)` when a key comes next), as in
+    // `token_stats`.
+    open: Vec<Option<bool>>,
+}
+
+impl StreamParser {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Feeds the next bytes of the input, returning the value once it is complete.
+    ///
+    /// Feeding an empty chunk parses the next value out of the [remainder](Self::remainder).
+    pub fn feed(&mut self, chunk: &[u8]) -> Result<Status> {
+        self.buffer.extend_from_slice(chunk);
+
+        if !self.check()? {
+            return Ok(Status::NeedMore);
+        }
+
+        let value = super::parse(&self.buffer[..self.checked])?;
+        self.buffer.drain(..self.checked);
+        self.checked = 0;
+        Ok(Status::Done(value))
+    }
+
+    /// The bytes fed after the end of the last complete value, which are the start of the next
+    /// value.
+    pub fn remainder(&self) -> &[u8] {
+        &self.buffer
+    }
+
+    /// Returns `true` if a value is partially fed.
+    pub fn is_partial(&self) -> bool {
+        !self.buffer.is_empty()
+    }
+
+    // Checks the tokens of the buffer not checked yet. Returns `true` once the first value of the
+    // buffer is complete, ending at `self.checked`.
+    fn check(&mut self) -> Result<bool> {
+        loop {
+            let rest = &self.buffer[self.checked..];
+            let expects_key = self.open.last() == Some(&Some(true));
+            match rest.first() {
+                None => return Ok(false),
+                Some(b'e') if self.open.last() == Some(&Some(false)) => {
+                    return Err(Error::InvalidType(
+                        "Invalid dictionary format: missing value".to_string(),
+                    ))
+                }
+                Some(b'e') if !self.open.is_empty() => {
+                    self.checked += 1;
+                    self.open.pop();
+                }
+                Some(b'0'..=b'9') => match string_length(rest)? {
+                    Some(length) => self.checked += length,
+                    None => return Ok(false),
+                },
+                Some(_) if expects_key => {
+                    return Err(Error::InvalidType(
+                        "Only string values are allowed as dictionary keys".to_string(),
+                    ))
+                }
+                Some(b'i') => match integer_length(rest)? {
+                    Some(length) => self.checked += length,
+                    None => return Ok(false),
+                },
+                Some(b'l') => {
+                    self.checked += 1;
+                    self.open.push(None);
+                    continue;
+                }
+                Some(b'd') => {
+                    self.checked += 1;
+                    self.open.push(Some(true));
+                    continue;
+                }
+                Some(_) => return Err(Error::InvalidType("Invalid bencode format".to_string())),
+            }
+
+            // A value is complete: the dictionary holding it expects a key next, or a value after
+            // a key.
+            match self.open.last_mut() {
+                Some(Some(expects_key)) => *expects_key = !*expects_key,
+                Some(None) => {}
+                None => return Ok(true),
+            }
+        }
+    }
+}
+
+// Returns the length of the string token at the start of the input, or `None` if it is not
+// complete yet.
+fn string_length(input: &[u8]) -> Result<Option<usize>> {
+    let digits = input.iter().take_while(|b| b.is_ascii_digit()).count();
+    let complete = match input.get(digits) {
+        None => false,
+        Some(b':') => {
+            let length: Option<usize> = std::str::from_utf8(&input[..digits])
+                .ok()
+                .and_then(|digits| digits.parse().ok());
+            length.is_some_and(|length| input.len() - digits > length)
+        }
+        Some(_) => true,
+    };
+
+    if complete || digits > MAX_LENGTH_DIGITS {
+        let mut bencode = Bencode::from_bytes(input);
+        bencode.parse_byte_slice()?;
+        Ok(Some(input.len() - bencode.input.len()))
+    } else {
+        Ok(None)
+    }
+}
+
+// Returns the length of the integer token at the start of the input, or `None` if it is not
+// complete yet.
+fn integer_length(input: &[u8]) -> Result<Option<usize>> {
+    if !input.contains(&b'e') && input.len() <= MAX_INTEGER_LENGTH {
+        return Ok(None);
+    }
+
+    let mut bencode = Bencode::from_bytes(input);
+    bencode.parse_integer()?;
+    Ok(Some(input.len() - bencode.input.len()))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::bencode::parse;
+
+    #[test]
+    fn test_stream_parser_byte_by_byte() {
+        let input = b"d4:infod5:filesli1ei-20ee4:name5:zung!e3:keyl0:lee1:xi0ee";
+        let mut parser = StreamParser::new();
+        for (i, byte) in input.iter().enumerate() {
+            let status = parser.feed(&[*byte]).unwrap();
+            if i + 1 < input.len() {
+                assert_eq!(status, Status::NeedMore, "at {i}");
+                assert!(parser.is_partial());
+            } else {
+                assert_eq!(status, Status::Done(parse(input).unwrap()));
+            }
+        }
+        assert!(!parser.is_partial());
+    }
+
+    #[test]
+    fn test_stream_parser_values_in_a_row() {
+        let mut parser = StreamParser::new();
+        assert_eq!(
+            parser.feed(b"i1e3:abci").unwrap(),
+            Status::Done(Value::Integer(1))
+        );
+        assert_eq!(parser.remainder(), b"3:abci");
+        assert_eq!(
+            parser.feed(&[]).unwrap(),
+            Status::Done(Value::String("abc".to_string()))
+        );
+        assert_eq!(parser.feed(&[]).unwrap(), Status::NeedMore);
+        assert_eq!(parser.remainder(), b"i");
+        assert_eq!(parser.feed(b"2e").unwrap(), Status::Done(Value::Integer(2)));
+    }
+
+    #[test]
+    fn test_stream_parser_fails_early() {
+        for (chunk, error) in [
+            (&b"l4:spamx"[..], "Invalid bencode format"),
+            (b"di1e", "Only string values are allowed as dictionary keys"),
+            (b"d1:ae", "Invalid dictionary format: missing value"),
+            (b"i12x", "Invalid integer bencode format: missing 'e'"),
+            (b"i1x2e", "Invalid character in bencode integer"),
+            (b"4x", "Invalid string bencode format: missing ':'"),
+        ] {
+            let mut parser = StreamParser::new();
+            let err = match parser.feed(chunk) {
+                Err(err) => err,
+                // The integer can only be known to be invalid once it is too long.
+                Ok(_) => parser.feed(&[b'0'; MAX_INTEGER_LENGTH]).unwrap_err(),
+            };
+            assert_eq!(err.to_string(), error, "{chunk:?}");
+        }
+    }
+}
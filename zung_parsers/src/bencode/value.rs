@@ -1,6 +1,7 @@
 use serde::{
+    de::{MapAccess, SeqAccess, Visitor},
     ser::{SerializeMap, SerializeSeq},
-    Serialize, Serializer,
+    Deserialize, Deserializer, Serialize, Serializer,
 };
 
 use std::{
@@ -8,6 +9,8 @@ use std::{
     fmt::{self},
 };
 
+use super::Key;
+
 /// Representation of Bencode values in Rust.
 #[derive(Debug, Clone, PartialEq)]
 pub enum Value {
@@ -31,10 +34,59 @@ pub enum Value {
     ///  keys are strings, and values are other Bencode values. Dictionaries are prefixed and
     ///  suffixed with `d` and `e`, respectively (e.g., `d3:cow3:mooe` for a dictionary with one
     ///  key-value pair).
-    Dictionary(HashMap<String, Value>),
+    ///
+    ///  The keys were [`String`]s before zung_parsers 0.2. A dictionary with [`String`] keys
+    ///  converts into this variant with [`Value::from`] and back with
+    ///  [`Value::into_dictionary`], and a [`Key`] reads as a `&str`.
+    Dictionary(HashMap<Key, Value>),
+}
+
+impl From<HashMap<String, Value>> for Value {
+    fn from(dictionary: HashMap<String, Value>) -> Self {
+        Value::Dictionary(dictionary.into_iter().map(|(k, v)| (k.into(), v)).collect())
+    }
 }
 
 impl Value {
+    /// Returns the entries of the dictionary, or `None` if the value is not a dictionary.
+    pub fn as_dictionary(&self) -> Option<&HashMap<Key, Value>> {
+        match self {
+            Value::Dictionary(dictionary) => Some(dictionary),
+            _ => None,
+        }
+    }
+
+    /// Same as [`Value::as_dictionary`] but the entries can be modified. A [`String`] or a `&str`
+    /// converts into a [`Key`] to insert an entry.
+    pub fn as_dictionary_mut(&mut self) -> Option<&mut HashMap<Key, Value>> {
+        match self {
+            Value::Dictionary(dictionary) => Some(dictionary),
+            _ => None,
+        }
+    }
+
+    /// Returns the dictionary with [`String`] keys, as it was before zung_parsers 0.2, or `None`
+    /// if the value is not a dictionary.
+    ///
+    /// # Example
+    ///
+    /// ```rust
+    /// use std::collections::HashMap;
+    /// use zung_parsers::bencode::{self, Value};
+    ///
+    /// let value = bencode::parse("d4:name4:zunge").unwrap();
+    /// let dictionary: HashMap<String, Value> = value.into_dictionary().unwrap();
+    /// assert_eq!(dictionary["name"].to_string(), "zung");
+    /// ```
+    pub fn into_dictionary(self) -> Option<HashMap<String, Value>> {
+        match self {
+            Value::Dictionary(dictionary) => {
+                Some(dictionary.into_iter().map(|(k, v)| (k.into(), v)).collect())
+            }
+            _ => None,
+        }
+    }
+
     pub fn get_from_dictionary(&self, index: &str) -> Option<&Value> {
         if let Value::Dictionary(map) = self {
             map.get(index)
@@ -42,6 +94,80 @@ impl Value {
             None
         }
     }
+
+    /// Looks up a value by a `/` separated path.
+    ///
+    /// Each segment of the path is either a key of a dictionary or an index into a list. Empty
+    /// segments are ignored, so `"info/name"` and `"/info/name"` are the same path. Returns `None`
+    /// if any segment of the path does not exist.
+    ///
+    /// # Example
+    ///
+    /// ```rust
+    /// use zung_parsers::bencode;
+    ///
+    /// let value = bencode::parse("d4:infod5:filesl3:one3:twoeee").unwrap();
+    /// let file = value.pointer("info/files/1").unwrap();
+    /// assert_eq!(file.to_string(), "two");
+    /// ```
+    pub fn pointer(&self, path: &str) -> Option<&Value> {
+        path.split('/')
+            .filter(|segment| !segment.is_empty())
+            .try_fold(self, |value, segment| value.child(segment))
+    }
+
+    /// Same as [`Value::pointer`] but a segment can also be a `*` wildcard which matches every
+    /// item of a list (or every value of a dictionary, in key order).
+    ///
+    /// Returns all the values matched by the path. The returned vector is empty if nothing
+    /// matches.
+    ///
+    /// # Example
+    ///
+    /// ```rust
+    /// use zung_parsers::bencode;
+    ///
+    /// let value = bencode::parse("d5:filesld4:pathl1:aeed4:pathl1:beeee").unwrap();
+    /// let paths = value.query("files/*/path/0");
+    /// assert_eq!(paths.len(), 2);
+    /// assert_eq!(paths[0].to_string(), "a");
+    /// assert_eq!(paths[1].to_string(), "b");
+    /// ```
+    pub fn query(&self, path: &str) -> Vec<&Value> {
+        let mut matches = vec![self];
+
+        for segment in path.split('/').filter(|segment| !segment.is_empty()) {
+            matches = matches
+                .into_iter()
+                .flat_map(|value| -> Vec<&Value> {
+                    if segment == "*" {
+                        match value {
+                            Value::List(list) => list.iter().collect(),
+                            Value::Dictionary(dictionary) => {
+                                let mut keys: Vec<_> = dictionary.keys().collect();
+                                keys.sort();
+                                keys.into_iter().map(|k| &dictionary[k]).collect()
+                            }
+                            _ => Vec::new(),
+                        }
+                    } else {
+                        value.child(segment).into_iter().collect()
+                    }
+                })
+                .collect();
+        }
+
+        matches
+    }
+
+    // Returns the direct child of a list or a dictionary pointed to by the segment.
+    fn child(&self, segment: &str) -> Option<&Value> {
+        match self {
+            Value::Dictionary(dictionary) => dictionary.get(segment),
+            Value::List(list) => segment.parse::<usize>().ok().and_then(|i| list.get(i)),
+            _ => None,
+        }
+    }
 }
 
 impl Serialize for Value {
@@ -71,6 +197,84 @@ impl Serialize for Value {
     }
 }
 
+impl<'de> Deserialize<'de> for Value {
+    fn deserialize<D>(deserializer: D) -> Result<Self, D::Error>
+    where
+        D: Deserializer<'de>,
+    {
+        deserializer.deserialize_any(ValueVisitor)
+    }
+}
+
+struct ValueVisitor;
+
+impl<'de> Visitor<'de> for ValueVisitor {
+    type Value = Value;
+
+    fn expecting(&self, formatter: &mut fmt::Formatter) -> fmt::Result {
+        write!(formatter, "a bencode value")
+    }
+
+    fn visit_i64<E>(self, v: i64) -> Result<Value, E> {
+        Ok(Value::Integer(v))
+    }
+
+    fn visit_u64<E>(self, v: u64) -> Result<Value, E>
+    where
+        E: serde::de::Error,
+    {
+        i64::try_from(v)
+            .map(Value::Integer)
+            .map_err(|_| E::custom(format!("Integer out of range: {v}")))
+    }
+
+    fn visit_str<E>(self, v: &str) -> Result<Value, E> {
+        Ok(Value::String(v.to_string()))
+    }
+
+    fn visit_string<E>(self, v: String) -> Result<Value, E> {
+        Ok(Value::String(v))
+    }
+
+    fn visit_bytes<E>(self, v: &[u8]) -> Result<Value, E>
+    where
+        E: serde::de::Error,
+    {
+        self.visit_byte_buf(v.to_vec())
+    }
+
+    // Same rule as the parser: ascii byte strings are strings, everything else is bytes.
+    fn visit_byte_buf<E>(self, v: Vec<u8>) -> Result<Value, E> {
+        match String::from_utf8(v) {
+            Ok(s) if s.is_ascii() => Ok(Value::String(s)),
+            Ok(s) => Ok(Value::Bytes(s.into_bytes())),
+            Err(e) => Ok(Value::Bytes(e.into_bytes())),
+        }
+    }
+
+    fn visit_seq<A>(self, mut seq: A) -> Result<Value, A::Error>
+    where
+        A: SeqAccess<'de>,
+    {
+        let mut list = Vec::with_capacity(seq.size_hint().unwrap_or_default());
+        while let Some(value) = seq.next_element()? {
+            list.push(value);
+        }
+        Ok(Value::List(list))
+    }
+
+    fn visit_map<A>(self, mut map: A) -> Result<Value, A::Error>
+    where
+        A: MapAccess<'de>,
+    {
+        let mut dict = HashMap::with_capacity(map.size_hint().unwrap_or_default());
+        while let Some((key, value)) = map.next_entry::<Key, Value>()? {
+            dict.insert(key, value);
+        }
+        Ok(Value::Dictionary(dict))
+    }
+}
+
 pub enum ValueInput<'a> {
     Str(&'a str),
     Bytes(&'a [u8]),
@@ -198,11 +402,52 @@ mod tests {
         let mut dict = HashMap::new();
         dict.insert("key1".to_string(), Value::Integer(10));
         dict.insert("key2".to_string(), Value::String("value".to_string()));
-        let value = Value::Dictionary(dict);
+        let value = Value::from(dict);
 
         let result = value.to_string();
         assert!(result.contains("key1: 10"));
         assert!(result.contains("key2: value"));
+
+        let mut value = value;
+        value
+            .as_dictionary_mut()
+            .unwrap()
+            .insert("key3".to_string().into(), Value::Integer(30));
+        assert_eq!(value.as_dictionary().unwrap().len(), 3);
+
+        let dict = value.into_dictionary().unwrap();
+        assert_eq!(dict.get("key1"), Some(&Value::Integer(10)));
+        assert_eq!(dict.get("key3"), Some(&Value::Integer(30)));
+        assert_eq!(Value::Integer(1).into_dictionary(), None);
+    }
+
+    #[test]
+    fn test_value_pointer() {
+        let value = crate::bencode::parse("d4:infod4:name4:test5:filesl3:one3:twoeee").unwrap();
+
+        assert_eq!(
+            value.pointer("info/name"),
+            Some(&Value::String("test".to_string()))
+        );
+        assert_eq!(
+            value.pointer("/info/files/0"),
+            Some(&Value::String("one".to_string()))
+        );
+        assert_eq!(value.pointer(""), Some(&value));
+        assert!(value.pointer("info/files/2").is_none());
+        assert!(value.pointer("info/name/0").is_none());
+    }
+
+    #[test]
+    fn test_value_query_wildcard() {
+        let value = crate::bencode::parse("d5:filesld6:lengthi1eed6:lengthi2eeee").unwrap();
+
+        assert_eq!(
+            value.query("files/*/length"),
+            vec![&Value::Integer(1), &Value::Integer(2)]
+        );
+        assert_eq!(value.query("files/1/length"), vec![&Value::Integer(2)]);
+        assert!(value.query("files/*/path").is_empty());
     }
 
     #[test]
@@ -224,4 +469,11 @@ mod tests {
             panic!("Expected ValueInput::Bytes");
         }
     }
+
+    #[test]
+    fn test_value_deserialize_matches_parse() {
+        let input = b"d4:infod6:lengthi42e4:name4:test6:pieces2:\xff\x00e4:listli1e2:abee";
+        let deserialized: Value = crate::bencode::from_bytes(input).unwrap();
+        assert_eq!(deserialized, crate::bencode::parse(input).unwrap());
+    }
 }
@@ -0,0 +1,117 @@
+use std::{
+    fmt::{self, Display},
+    path::{Path, PathBuf},
+};
+
+use crate::Format;
+
+/// Returned when the format of a file can not be detected from its extension nor from its
+/// content.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct FormatDetectionError {
+    /// The file whose format was detected.
+    pub file: PathBuf,
+
+    /// The formats the content was parsed as, with the reason each of them was ruled out.
+    pub attempts: Vec<(&'static str, String)>,
+}
+
+impl Display for FormatDetectionError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(
+            f,
+            "Could not detect the format of {}: the extension is not one of json, yaml, yml or \
+             toml, and the content is not",
+            self.file.display()
+        )?;
+        for (format, reason) in &self.attempts {
+            write!(f, "\n  - {format}: {reason}")?;
+        }
+        write!(f, "\nPass the format with --format instead")
+    }
+}
+
+impl std::error::Error for FormatDetectionError {}
+
+/// Detects the format of a file from its extension or, when the extension is unknown, by parsing
+/// its content as JSON, TOML and then YAML. YAML accepts nearly any text as a plain string, so the
+/// content is only YAML if it is a mapping or a sequence.
+pub(crate) fn detect_format(file: &Path, content: &[u8]) -> Result<Format, FormatDetectionError> {
+    let extension = file
+        .extension()
+        .and_then(|e| e.to_str())
+        .map(str::to_ascii_lowercase);
+    match extension.as_deref() {
+        Some("json") => return Ok(Format::Json),
+        Some("yaml" | "yml") => return Ok(Format::Yaml),
+        Some("toml") => return Ok(Format::Toml),
+        _ => {}
+    }
+
+    let mut attempts = Vec::new();
+
+    match serde_json::from_slice::<serde_json::Value>(content) {
+        Ok(_) => return Ok(Format::Json),
+        Err(e) => attempts.push(("json", e.to_string())),
+    }
+
+    match std::str::from_utf8(content) {
+        Ok(text) => match toml::from_str::<toml::Table>(text) {
+            Ok(_) => return Ok(Format::Toml),
+            Err(e) => attempts.push(("toml", e.message().to_string())),
+        },
+        Err(e) => attempts.push(("toml", e.to_string())),
+    }
+
+    match serde_yaml::from_slice::<serde_yaml::Value>(content) {
+        Ok(serde_yaml::Value::Mapping(_) | serde_yaml::Value::Sequence(_)) => {
+            return Ok(Format::Yaml)
+        }
+        Ok(_) => attempts.push(("yaml", String::from("not a mapping or a sequence"))),
+        Err(e) => attempts.push(("yaml", e.to_string())),
+    }
+
+    Err(FormatDetectionError {
+        file: file.to_path_buf(),
+        attempts,
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn detect(file: &str, content: &str) -> Result<Format, FormatDetectionError> {
+        detect_format(Path::new(file), content.as_bytes())
+    }
+
+    #[test]
+    fn test_detect_by_extension() {
+        assert_eq!(detect("a.json", "").unwrap(), Format::Json);
+        assert_eq!(detect("a.YML", "").unwrap(), Format::Yaml);
+        assert_eq!(detect("dir/a.yaml", "").unwrap(), Format::Yaml);
+        assert_eq!(detect("a.toml", "").unwrap(), Format::Toml);
+    }
+
+    #[test]
+    fn test_detect_by_content() {
+        assert_eq!(detect("a", r#"{"name": "a"}"#).unwrap(), Format::Json);
+        assert_eq!(detect("a.txt", "name = \"a\"\n").unwrap(), Format::Toml);
+        assert_eq!(
+            detect("a", "name: a\nlist: [1, 2]\n").unwrap(),
+            Format::Yaml
+        );
+        assert_eq!(detect("a", "- 1\n- 2\n").unwrap(), Format::Yaml);
+    }
+
+    #[test]
+    fn test_detection_failure() {
+        let error = detect("notes.txt", "just some text").unwrap_err();
+        let tried: Vec<_> = error.attempts.iter().map(|(format, _)| *format).collect();
+        assert_eq!(tried, ["json", "toml", "yaml"]);
+        assert_eq!(error.attempts[2].1, "not a mapping or a sequence");
+        assert!(error.to_string().starts_with(
+            "Could not detect the format of notes.txt: the extension is not one of json"
+        ));
+    }
+}
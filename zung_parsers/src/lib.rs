@@ -1,14 +1,24 @@
 #![doc = include_str!("../README.md")]
 
 pub mod bencode;
+mod detect;
+
+pub use detect::FormatDetectionError;
 
 use clap::{Args, Subcommand, ValueEnum};
+use colored::Colorize;
+use serde::Serialize;
+use sha2::Digest;
 use std::{
     fs::File,
     io::{BufWriter, Write},
-    path::PathBuf,
+    path::{Path, PathBuf},
+    time::{Duration, Instant},
 };
 
+/// Version of the crate, as published on crates.io.
+pub const VERSION: &str = env!("CARGO_PKG_VERSION");
+
 #[derive(Debug, Args)]
 #[command(flatten_help = true, subcommand_required = true)]
 pub struct ParserArgs {
@@ -45,9 +55,10 @@ enum BencodeCommands {
 
     /// Encode to bencode from given format
     Encode {
-        /// Decode in the provided format.       
-        #[arg(long, value_enum, required = true)]
-        format: Format,
+        /// Format of the file to encode. `auto` detects it from the extension of the file or,
+        /// failing that, from its content.
+        #[arg(long, value_enum, default_value_t = InputFormat::Auto)]
+        format: InputFormat,
 
         /// File containing the format data
         #[arg(short, long, required = true)]
@@ -58,6 +69,81 @@ enum BencodeCommands {
         output: PathBuf,
     },
 
+    /// Print the value found at a path inside a bencode file.
+    ///
+    /// The path is a `/` separated list of dictionary keys and list indices, for example
+    /// `info/files/0/path`. A `*` segment matches every item of a list.
+    Query {
+        /// The Bencode file to query
+        #[arg(short, long, required = true)]
+        file: PathBuf,
+
+        /// Path of the value to print.
+        #[arg(short, long, required = true)]
+        path: String,
+
+        /// Print the value in the provided format instead of the plain bencode value.
+        #[arg(long, value_enum)]
+        format: Option<Format>,
+    },
+
+    /// Identify a torrent file without fully deserializing it.
+    ///
+    /// Prints the top level keys, whether the file looks like a valid metainfo file, the size of
+    /// the info dictionary and its SHA1 (v1) and SHA256 (v2) hashes.
+    Id {
+        /// The Bencode file to identify
+        #[arg(short, long, required = true)]
+        file: PathBuf,
+    },
+
+    /// Report which values make a bencode file large.
+    ///
+    /// Prints the number of bytes the values down to the given depth take in the file, the largest
+    /// first, along with their share of the file and, for strings, their entropy in bits per byte.
+    /// Random data such as piece hashes is close to 8 bits per byte.
+    Sizes {
+        /// The Bencode file to report on
+        #[arg(short, long, required = true)]
+        file: PathBuf,
+
+        /// Report the lists and dictionaries nested deeper than this as a whole.
+        #[arg(long, default_value_t = 2)]
+        depth: usize,
+
+        /// Only print this many values.
+        #[arg(long, default_value_t = 10)]
+        top: usize,
+    },
+
+    /// Profile the parsing of a bencode file, e.g. to report a performance issue.
+    ///
+    /// Prints the time spent reading the file, tokenizing it and building the values, the best of
+    /// the runs, along with the tokens by type and the deepest nesting.
+    Stats {
+        /// The Bencode file to profile
+        #[arg(short, long, required = true)]
+        file: PathBuf,
+
+        /// Number of times the file is parsed. The fastest run is reported, which is the least
+        /// disturbed by the rest of the system.
+        #[arg(long, default_value_t = 5)]
+        runs: usize,
+
+        /// Print the report as JSON. The times are in microseconds.
+        #[arg(long)]
+        json: bool,
+    },
+
+    /// Print the values which were added, removed or changed between two bencode files.
+    Diff {
+        /// The old bencode file
+        a: PathBuf,
+
+        /// The new bencode file
+        b: PathBuf,
+    },
+
     /// Try encoding or decoding a String of bencode for testing purposes. This simply prints out
     /// the output.
     Try {
@@ -78,6 +164,21 @@ enum Format {
     Toml,
 }
 
+#[derive(Copy, Clone, Debug, PartialEq, Eq, PartialOrd, Ord, ValueEnum)]
+enum InputFormat {
+    /// Detect the format from the extension or the content of the file
+    Auto,
+
+    /// Read json
+    Json,
+
+    /// Read yaml
+    Yaml,
+
+    /// Read toml
+    Toml,
+}
+
 #[derive(Clone, Subcommand, Debug)]
 enum TryCommands {
     /// Try encoding
@@ -103,8 +204,13 @@ impl ParserArgs {
                     let file = File::create(output)?;
                     let mut buf_writer = BufWriter::new(file);
                     match format {
-                        Format::Json => serde_json::to_writer_pretty(buf_writer, &bencode)?,
-                        Format::Yaml => serde_yaml::to_writer(buf_writer, &bencode)?,
+                        Format::Json => serde_json::to_writer_pretty(
+                            buf_writer,
+                            &serde_json::Value::from(bencode),
+                        )?,
+                        Format::Yaml => {
+                            serde_yaml::to_writer(buf_writer, &serde_yaml::Value::from(bencode))?
+                        }
                         Format::Toml => {
                             let b = toml::to_string_pretty(&bencode)?;
                             buf_writer.write_all(b.as_bytes())?;
@@ -117,24 +223,99 @@ impl ParserArgs {
                     file,
                     output,
                 } => {
-                    let file_read = std::fs::read(file)?;
+                    let file_read = std::fs::read(&file)?;
+                    let format = match format {
+                        InputFormat::Auto => detect::detect_format(&file, &file_read)?,
+                        InputFormat::Json => Format::Json,
+                        InputFormat::Yaml => Format::Yaml,
+                        InputFormat::Toml => Format::Toml,
+                    };
 
                     let file_write = File::create(output)?;
                     let mut buf_writer = BufWriter::new(file_write);
 
-                    match format {
+                    let value = match format {
                         Format::Json => {
                             let value: serde_json::Value = serde_json::from_slice(&file_read)?;
-                            let bencode = bencode::to_string(&value)?;
-                            write!(buf_writer, "{bencode}")?
+                            bencode::Value::try_from(value)?
                         }
                         Format::Yaml => {
                             let value: serde_yaml::Value = serde_yaml::from_slice(&file_read)?;
-                            let bencode = bencode::to_string(&value)?;
-                            write!(buf_writer, "{bencode}")?
+                            bencode::Value::try_from(value)?
+                        }
+                        Format::Toml => {
+                            let value: toml::Table =
+                                toml::from_str(std::str::from_utf8(&file_read)?)?;
+                            bencode::Value::try_from(toml_to_json(toml::Value::Table(value)))?
                         }
-                        Format::Toml => unimplemented!(),
                     };
+                    buf_writer.write_all(&bencode::to_bytes(&value)?)?;
+                }
+
+                BencodeCommands::Query { file, path, format } => {
+                    let file = std::fs::read(file)?;
+                    let bencode = bencode::parse(&file)?;
+
+                    let mut matches = bencode.query(&path);
+                    let value = if path.split('/').any(|segment| segment == "*") {
+                        bencode::Value::List(matches.into_iter().cloned().collect())
+                    } else if let Some(value) = matches.pop() {
+                        value.clone()
+                    } else {
+                        anyhow::bail!("Nothing found at path: {path}")
+                    };
+
+                    match format {
+                        Some(Format::Json) => println!(
+                            "{}",
+                            serde_json::to_string_pretty(&serde_json::Value::from(value))?
+                        ),
+                        Some(Format::Yaml) => {
+                            print!(
+                                "{}",
+                                serde_yaml::to_string(&serde_yaml::Value::from(value))?
+                            )
+                        }
+                        Some(Format::Toml) => println!("{}", toml::to_string_pretty(&value)?),
+                        None => println!("{value}"),
+                    }
+                }
+
+                BencodeCommands::Id { file } => {
+                    let file = std::fs::read(file)?;
+                    print!("{}", identify(&file)?);
+                }
+
+                BencodeCommands::Sizes { file, depth, top } => {
+                    let file = std::fs::read(file)?;
+                    let bencode = bencode::parse_spanned(&file)?;
+
+                    let mut sizes = Vec::new();
+                    collect_sizes(&bencode, &mut Vec::new(), depth, &mut sizes);
+                    sizes.sort_by(|a, b| b.1.cmp(&a.1).then_with(|| a.0.cmp(&b.0)));
+
+                    println!("Total: {} bytes", file.len());
+                    for (path, size, entropy) in sizes.into_iter().take(top) {
+                        let share = size as f64 * 100.0 / file.len() as f64;
+                        let entropy =
+                            entropy.map_or(String::new(), |e| format!("{e:.2} bits/byte"));
+                        println!("{share:>6.2}% {size:>12} bytes {entropy:>14}  {path}");
+                    }
+                }
+
+                BencodeCommands::Stats { file, runs, json } => {
+                    let report = ParseReport::profile(&file, runs.max(1))?;
+                    if json {
+                        println!("{}", serde_json::to_string_pretty(&report)?);
+                    } else {
+                        println!("{report}");
+                    }
+                }
+
+                BencodeCommands::Diff { a, b } => {
+                    let a = bencode::parse(&std::fs::read(a)?)?;
+                    let b = bencode::parse(&std::fs::read(b)?)?;
+                    print_diff(&bencode::diff(&a, &b));
                 }
 
                 BencodeCommands::Try { commands } => match commands {
@@ -152,3 +333,308 @@ impl ParserArgs {
         Ok(())
     }
 }
+
+/// The report of `zung parsers bencode stats`.
+#[derive(Debug, Serialize)]
+struct ParseReport {
+    file: PathBuf,
+    bytes: usize,
+    runs: usize,
+    version: &'static str,
+
+    /// The fastest times of the runs.
+    #[serde(serialize_with = "as_micros")]
+    read: Duration,
+
+    /// Tokenizing and validating the input, without building the values.
+    #[serde(serialize_with = "as_micros")]
+    tokenize: Duration,
+
+    /// The whole parse into a [`bencode::Value`].
+    #[serde(serialize_with = "as_micros")]
+    parse: Duration,
+
+    /// Building the values, i.e. the parse without the tokenizing.
+    #[serde(serialize_with = "as_micros")]
+    values: Duration,
+    tokens: bencode::TokenStats,
+}
+
+impl ParseReport {
+    fn profile(file: &Path, runs: usize) -> anyhow::Result<Self> {
+        let (mut read, mut tokenize, mut parse) = (Duration::MAX, Duration::MAX, Duration::MAX);
+        let mut last = None;
+        for _ in 0..runs {
+            let start = Instant::now();
+            let bytes = std::fs::read(file)?;
+            read = read.min(start.elapsed());
+
+            let start = Instant::now();
+            let tokens = bencode::token_stats(&bytes)?;
+            tokenize = tokenize.min(start.elapsed());
+
+            let start = Instant::now();
+            let value = bencode::parse(&bytes)?;
+            parse = parse.min(start.elapsed());
+            // Dropped outside of the timing, as it is not part of the parse.
+            drop(value);
+
+            last = Some((bytes.len(), tokens));
+        }
+        let (bytes, tokens) = last.expect("At least one run");
+
+        Ok(Self {
+            file: file.to_path_buf(),
+            bytes,
+            runs,
+            version: VERSION,
+            read,
+            tokenize,
+            parse,
+            values: parse.saturating_sub(tokenize),
+            tokens,
+        })
+    }
+}
+
+impl std::fmt::Display for ParseReport {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        let throughput = self.bytes as f64 / self.parse.as_secs_f64().max(1e-9) / 1024.0 / 1024.0;
+        let t = &self.tokens;
+        writeln!(
+            f,
+            "File: {} ({} bytes), best of {} runs, zung_parsers {}",
+            self.file.display(),
+            self.bytes,
+            self.runs,
+            self.version
+        )?;
+        writeln!(f, "Read:     {:>10.3?}", self.read)?;
+        writeln!(f, "Tokenize: {:>10.3?}", self.tokenize)?;
+        writeln!(f, "Values:   {:>10.3?}", self.values)?;
+        writeln!(f, "Parse:    {:>10.3?} ({throughput:.1} MiB/s)", self.parse)?;
+        writeln!(
+            f,
+            "Tokens:   {} integers, {} strings ({} bytes), {} keys, {} lists, {} dictionaries",
+            t.integers, t.strings, t.string_bytes, t.keys, t.lists, t.dictionaries
+        )?;
+        write!(f, "Depth:    {}", t.max_depth)?;
+        if t.trailing_bytes > 0 {
+            write!(f, "\nTrailing: {} bytes ignored", t.trailing_bytes)?;
+        }
+        Ok(())
+    }
+}
+
+fn as_micros<S: serde::Serializer>(duration: &Duration, serializer: S) -> Result<S::Ok, S::Error> {
+    serializer.serialize_u128(duration.as_micros())
+}
+
+/// The report of `zung parsers bencode id`.
+///
+/// The info hashes are computed from the bytes of the info dictionary as they are in the file, as
+/// the clients do, so a dictionary whose keys are not sorted keeps its hashes.
+fn identify(file: &[u8]) -> anyhow::Result<String> {
+    use std::fmt::Write;
+
+    let torrent = bencode::parse_spanned(file)?;
+    let bencode::Spanned::Dictionary(entries) = &torrent.value else {
+        anyhow::bail!("Not a torrent file: the top level value is not a dictionary")
+    };
+
+    let mut report = String::new();
+    let mut keys: Vec<_> = entries.iter().map(|(key, _)| key.as_str()).collect();
+    keys.sort();
+    keys.dedup();
+    writeln!(report, "Top level keys: {}", keys.join(", "))?;
+
+    let Some(info) = torrent.get("info") else {
+        writeln!(report, "Valid metainfo: no (missing info dictionary)")?;
+        return Ok(report);
+    };
+
+    writeln!(
+        report,
+        "Valid metainfo: {}",
+        if looks_like_info(&info.to_value()) {
+            "yes"
+        } else {
+            "no"
+        }
+    )?;
+
+    let info = info.bytes(file);
+    writeln!(report, "Info dictionary size: {} bytes", info.len())?;
+    writeln!(
+        report,
+        "Info hash (SHA1): {}",
+        sha1_smol::Sha1::from(info).digest()
+    )?;
+    writeln!(
+        report,
+        "Info hash (SHA256): {}",
+        hex::encode(sha2::Sha256::digest(info))
+    )?;
+    Ok(report)
+}
+
+/// Collects the path, the number of bytes in the file and the entropy of the values down to
+/// `depth`. The entropy is only computed for strings.
+fn collect_sizes(
+    value: &bencode::SpannedValue,
+    path: &mut Vec<String>,
+    depth: usize,
+    sizes: &mut Vec<(String, usize, Option<f64>)>,
+) {
+    let children: Vec<(String, &bencode::SpannedValue)> = match &value.value {
+        _ if path.len() == depth => Vec::new(),
+        bencode::Spanned::Dictionary(entries) => {
+            entries.iter().map(|(k, v)| (k.to_string(), v)).collect()
+        }
+        bencode::Spanned::List(list) => list
+            .iter()
+            .enumerate()
+            .map(|(i, v)| (i.to_string(), v))
+            .collect(),
+        _ => Vec::new(),
+    };
+
+    if children.is_empty() {
+        let entropy = match &value.value {
+            bencode::Spanned::Bytes(bytes) => Some(entropy(bytes)),
+            bencode::Spanned::String(string) => Some(entropy(string.as_bytes())),
+            _ => None,
+        };
+        let path = if path.is_empty() {
+            String::from("/")
+        } else {
+            path.join("/")
+        };
+        sizes.push((path, value.span().len(), entropy));
+        return;
+    }
+
+    for (segment, child) in children {
+        path.push(segment);
+        collect_sizes(child, path, depth, sizes);
+        path.pop();
+    }
+}
+
+/// Shannon entropy of the bytes, in bits per byte.
+fn entropy(bytes: &[u8]) -> f64 {
+    let mut counts = [0usize; 256];
+    for &byte in bytes {
+        counts[byte as usize] += 1;
+    }
+    counts
+        .iter()
+        .filter(|&&count| count > 0)
+        .map(|&count| {
+            let p = count as f64 / bytes.len() as f64;
+            -p * p.log2()
+        })
+        .sum()
+}
+
+/// Prints each entry on its own line, colored by the kind of change.
+fn print_diff(entries: &[bencode::DiffEntry]) {
+    if entries.is_empty() {
+        println!("No differences");
+        return;
+    }
+    for entry in entries {
+        let line = entry.to_string();
+        match entry.change {
+            bencode::Change::Added(_) => println!("{}", line.green()),
+            bencode::Change::Removed(_) => println!("{}", line.red()),
+            bencode::Change::Changed { .. } => println!("{}", line.yellow()),
+        }
+    }
+}
+
+// TOML maps onto JSON except for its dates, which become strings.
+fn toml_to_json(value: toml::Value) -> serde_json::Value {
+    match value {
+        toml::Value::String(s) => s.into(),
+        toml::Value::Integer(i) => i.into(),
+        toml::Value::Float(f) => f.into(),
+        toml::Value::Boolean(b) => b.into(),
+        toml::Value::Datetime(d) => d.to_string().into(),
+        toml::Value::Array(array) => array.into_iter().map(toml_to_json).collect(),
+        toml::Value::Table(table) => table
+            .into_iter()
+            .map(|(k, v)| (k, toml_to_json(v)))
+            .collect(),
+    }
+}
+
+// Checks for the keys that are mandatory in the info dictionary of a v1 or v2 metainfo file.
+fn looks_like_info(info: &bencode::Value) -> bool {
+    let has = |key| info.get_from_dictionary(key).is_some();
+    has("name")
+        && has("piece length")
+        && (has("pieces") || has("file tree"))
+        && (has("length") || has("files") || has("file tree"))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_identify_hashes_info_as_in_file() {
+        // The keys of the info dictionary are not sorted, so encoding it again would sort them
+        // and change the hashes.
+        let info = b"d4:name1:a6:lengthi42e12:piece lengthi16384e6:pieces0:e";
+        let file = [&b"d8:announce3:url4:info"[..], info, b"e"].concat();
+        assert_ne!(
+            bencode::to_bytes(&bencode::parse(info).unwrap()).unwrap(),
+            info
+        );
+
+        let report = identify(&file).unwrap();
+        assert!(report.contains("Top level keys: announce, info\n"));
+        assert!(report.contains("Valid metainfo: yes\n"));
+        assert!(report.contains(&format!("Info dictionary size: {} bytes\n", info.len())));
+        assert!(report.contains(&format!(
+            "Info hash (SHA1): {}\n",
+            sha1_smol::Sha1::from(info).digest()
+        )));
+        assert!(report.contains(&format!(
+            "Info hash (SHA256): {}\n",
+            hex::encode(sha2::Sha256::digest(info))
+        )));
+    }
+
+    #[test]
+    fn test_identify_not_a_torrent() {
+        assert!(identify(b"li1ee").is_err());
+        assert_eq!(
+            identify(b"d8:announce3:urle").unwrap(),
+            "Top level keys: announce\nValid metainfo: no (missing info dictionary)\n"
+        );
+    }
+
+    #[test]
+    fn test_collect_sizes() {
+        // The repeated key is dropped when parsed into a Value, but still takes its bytes.
+        let input = b"d4:infod6:pieces4:abcd6:pieces2:xxe3:urli1ee";
+        let spanned = bencode::parse_spanned(input).unwrap();
+
+        let mut sizes = Vec::new();
+        collect_sizes(&spanned, &mut Vec::new(), 0, &mut sizes);
+        assert_eq!(sizes, vec![(String::from("/"), input.len(), None)]);
+
+        let mut sizes = Vec::new();
+        collect_sizes(&spanned, &mut Vec::new(), 2, &mut sizes);
+        assert_eq!(
+            sizes,
+            vec![
+                (String::from("info/pieces"), 6, Some(2.0)),
+                (String::from("info/pieces"), 4, Some(0.0)),
+                (String::from("url"), 3, None),
+            ]
+        );
+    }
+}
@@ -0,0 +1,56 @@
+use criterion::{black_box, criterion_group, criterion_main, Criterion};
+use std::path::PathBuf;
+use zung_torrent::meta_info::MetaInfo;
+use zung_torrent::Client;
+
+fn path(name: &str) -> PathBuf {
+    let mut path = PathBuf::from(env!("CARGO_MANIFEST_DIR"));
+    path.push("../utilities/sample_torrents");
+    path.push(name);
+    path
+}
+
+fn read(name: &str) -> Vec<u8> {
+    std::fs::read(path(name)).expect("Unable to read the sample torrent")
+}
+
+fn from_bytes(c: &mut Criterion) {
+    let kali = read("kali-linux-2024.1-installer-amd64.iso.torrent");
+    let mit = read("MIT6.00SCS11_archive.torrent");
+
+    let mut group = c.benchmark_group("MetaInfo::from_bytes");
+    group.bench_function("kali (15650 pieces)", |b| {
+        b.iter(|| MetaInfo::from_bytes(black_box(&kali)).unwrap())
+    });
+    group.bench_function("mit (3259 pieces, 308 files)", |b| {
+        b.iter(|| MetaInfo::from_bytes(black_box(&mit)).unwrap())
+    });
+    group.finish();
+}
+
+// Client::new parses the torrent twice: once for the MetaInfo and once for the info hash.
+fn client_new(c: &mut Criterion) {
+    let kali = path("kali-linux-2024.1-installer-amd64.iso.torrent");
+    let mc = path("MC_GRID-7f06f8280a3b496f2af0f78131ced619df14a0c3.torrent");
+
+    let mut group = c.benchmark_group("Client::new");
+    group.bench_function("kali (15650 pieces)", |b| {
+        b.iter(|| Client::new(black_box(&kali)).unwrap())
+    });
+    group.bench_function("mc (131k files)", |b| {
+        b.iter(|| Client::new(black_box(&mc)).unwrap())
+    });
+    group.finish();
+}
+
+// The summary builds the file tree, which dominates for torrents with many files.
+fn summary(c: &mut Criterion) {
+    let mc = path("MC_GRID-7f06f8280a3b496f2af0f78131ced619df14a0c3.torrent");
+
+    c.bench_function("Client::summary/mc (131k files)", |b| {
+        b.iter(|| Client::new(black_box(&mc)).unwrap().summary())
+    });
+}
+
+criterion_group!(benches, from_bytes, client_new, summary);
+criterion_main!(benches);
@@ -0,0 +1,88 @@
+use std::fmt::Write;
+
+use serde::Serialize;
+
+use crate::meta_info::InfoHash;
+
+/// Every file of a torrent with what an external tool needs to verify it: its location in the
+/// pieces and its expected hashes. See [`Client::manifest`](crate::Client::manifest).
+///
+/// It can be serialized (e.g. to JSON) or written as CSV with [`FileManifest::to_csv`].
+#[derive(Debug, Clone, Serialize)]
+pub struct FileManifest {
+    pub name: String,
+    pub info_hash: InfoHash,
+
+    /// Length of each piece in bytes.
+    pub piece_length: usize,
+    pub files: Vec<ManifestFile>,
+}
+
+/// A file of a [`FileManifest`]. The padding files are left out.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize)]
+pub struct ManifestFile {
+    /// Path of the file relative to the torrent root, joined with `/`.
+    pub path: String,
+
+    /// Length of the file in bytes.
+    pub length: usize,
+
+    /// Offset of the first byte of the file in the torrent's byte stream.
+    pub offset: usize,
+
+    /// Index of the first piece containing bytes of the file.
+    pub first_piece: usize,
+
+    /// Index of the last piece containing bytes of the file, `None` for zero-length files which
+    /// are in no piece.
+    pub last_piece: Option<usize>,
+
+    /// The hex encoded BEP 47 SHA1 hash of the file, if the torrent provides one.
+    pub sha1: Option<String>,
+
+    /// The hex encoded hashes of the pieces covering the file, when the torrent does not provide
+    /// a `sha1` of the file. The first and the last pieces may also cover the neighbouring files.
+    pub piece_hashes: Vec<String>,
+}
+
+impl FileManifest {
+    /// Writes the files as CSV, with a header row. The piece hashes are separated by spaces.
+    pub fn to_csv(&self) -> String {
+        let mut csv = String::from("path,length,offset,first_piece,last_piece,sha1,piece_hashes\n");
+        for file in &self.files {
+            writeln!(
+                csv,
+                "{},{},{},{},{},{},{}",
+                csv_field(&file.path),
+                file.length,
+                file.offset,
+                file.first_piece,
+                file.last_piece.map(|p| p.to_string()).unwrap_or_default(),
+                file.sha1.as_deref().unwrap_or_default(),
+                file.piece_hashes.join(" ")
+            )
+            .expect("Writing to a String does not fail");
+        }
+        csv
+    }
+}
+
+// Quotes the field if it contains a separator, a quote or a line break (RFC 4180).
+fn csv_field(field: &str) -> String {
+    if field.contains([',', '"', '\n', '\r']) {
+        format!("\"{}\"", field.replace('"', "\"\""))
+    } else {
+        field.to_string()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_csv_field() {
+        assert_eq!(csv_field("a/b.txt"), "a/b.txt");
+        assert_eq!(csv_field("a, \"b\".txt"), "\"a, \"\"b\"\".txt\"");
+    }
+}
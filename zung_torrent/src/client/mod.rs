@@ -1,32 +1,132 @@
+mod manifest;
 mod peer_id;
+mod stats;
+mod summary;
+pub use manifest::{FileManifest, ManifestFile};
 pub use peer_id::PeerID;
+pub use stats::{Progress, SessionStats};
+pub use summary::ClientSummary;
 
-use anyhow::{bail, Result};
+use anyhow::{bail, Context, Result};
+use chrono::Local;
 use colored::Colorize;
-use human_bytes::human_bytes;
 use zung_parsers::bencode;
 
 use std::{
     fmt::Display,
-    path::Path,
+    hash::Hash,
+    net::SocketAddr,
+    ops::Range,
+    path::{Path, PathBuf},
     sync::{Arc, OnceLock},
-    thread,
 };
 
 use crate::{
-    meta_info::{FileTree, InfoHash, SortOrd},
-    sources::{DownloadSources, HttpSeederList, TrackerList},
+    hooks::CompletionHook,
+    ipfilter::IpFilter,
+    meta_info::{FileSpan, FileTree, Files, InfoHash, SizeFormat, SortOrd},
+    net::rate::RateSchedule,
+    peers::{Bitfield, BlockScheduler, DEFAULT_ENDGAME_THRESHOLD},
+    sources::{
+        AnnounceKey, AnnounceOptions, DownloadSources, SourceList, TrackerList, TrackerPeer,
+        DEFAULT_NUMWANT,
+    },
+    storage::{FileRule, Storage},
     MetaInfo,
 };
 
+/// Options for [`Client::print_torrent_info_with`].
+#[derive(Debug, Default, Clone, Copy)]
+pub struct InfoOptions {
+    /// Print the dates in UTC instead of the local timezone.
+    pub utc: bool,
+
+    /// How the sizes are printed.
+    pub size_format: SizeFormat,
+}
+
+/// Options for downloading a torrent.
+#[derive(Debug, Clone)]
+pub struct DownloadOptions {
+    /// Maximum number of peers connected at the same time.
+    pub max_peers: usize,
+
+    /// Maximum number of peers asked from a tracker in a single announce.
+    pub numwant: usize,
+
+    /// Directory in which the content is stored. Defaults to the current directory.
+    pub download_dir: PathBuf,
+
+    /// Store the files of a multi file torrent directly in the download directory, instead of
+    /// in a directory named after the torrent.
+    pub flatten_root: bool,
+
+    /// Rules renaming or skipping files, the first rule matching the path of a file applying.
+    pub file_rules: Vec<FileRule>,
+
+    /// Start the download even if the disk does not have enough free space for the files. See
+    /// [`Client::check_space`].
+    pub force: bool,
+
+    /// Limits of the download rate, applied to a [`RateLimiter`](crate::net::rate::RateLimiter)
+    /// by [`RateSchedule::spawn`]. Unlimited by default.
+    pub download_rate: RateSchedule,
+
+    /// Limits of the upload rate. Unlimited by default.
+    pub upload_rate: RateSchedule,
+
+    /// Commands and webhooks run when a torrent completes. See
+    /// [`TorrentSession::torrent_completed`](crate::TorrentSession::torrent_completed).
+    pub on_complete: Vec<CompletionHook>,
+
+    /// Number of blocks left under which they are requested from several peers at once, so
+    /// that the download does not stall on its last blocks. `0` disables the endgame. See
+    /// [`BlockScheduler::endgame_threshold`].
+    pub endgame_threshold: usize,
+}
+
+impl Default for DownloadOptions {
+    fn default() -> Self {
+        Self {
+            max_peers: 100,
+            numwant: DEFAULT_NUMWANT,
+            download_dir: PathBuf::from("."),
+            flatten_root: false,
+            file_rules: Vec::new(),
+            force: false,
+            download_rate: RateSchedule::default(),
+            upload_rate: RateSchedule::default(),
+            on_complete: Vec::new(),
+            endgame_threshold: DEFAULT_ENDGAME_THRESHOLD,
+        }
+    }
+}
+
+impl DownloadOptions {
+    /// Number of peers to ask the trackers for when `connected` peers are already connected.
+    ///
+    /// Only the free peer slots are asked for (up to [`DownloadOptions::numwant`]), so the
+    /// re-announces of a well connected torrent do not make the trackers send peers which can not
+    /// be used.
+    pub fn peers_wanted(&self, connected: usize) -> usize {
+        self.max_peers.saturating_sub(connected).min(self.numwant)
+    }
+}
+
 /// A torrent client providing the methods to interact with a torrent file.
 #[derive(Debug)]
 pub struct Client {
     meta_info: Arc<MetaInfo>,
     file_name: String,
+    file_checksum: String,
     info_hash: InfoHash,
     peer_id: PeerID,
-    num_files: OnceLock<usize>, // Cache no. of files.
+    announce_key: AnnounceKey,
+    stats: SessionStats,
+    ip_filter: Option<Arc<IpFilter>>,
+    file_tree: OnceLock<Arc<FileTree<'static>>>, // Cache the built file tree.
+    file_spans: OnceLock<Vec<FileSpan>>,         // Cache the piece <-> file mapping.
+    extra_trackers: Vec<String>,
 }
 
 /// Main functions
@@ -61,37 +161,41 @@ impl Client {
         if let Some(file_name) = file.as_ref().file_name() {
             let file_name = file_name.to_string_lossy().to_string();
 
-            let file = std::fs::read(file).expect("Unable to read the provided file");
-
-            let value = bencode::parse(&file)?;
-
-            let meta_info = thread::spawn(move || {
-                MetaInfo::from_bytes(&file).expect("Invalid torrent file provided")
-            });
+            let file = std::fs::read(&file)
+                .with_context(|| format!("Unable to read {}", file.as_ref().display()))?;
 
-            let info = thread::spawn(move || {
-                let info = value
-                    .get_from_dictionary("info")
-                    .expect("Invalid Torrent File - No info dictionary provided");
+            // Deserializing and hashing both parse the whole file and take about as long as each
+            // other (~300ms each for the 131k files of the MC_GRID sample), so they run in
+            // parallel. See the `Client::new` benchmark.
+            let (meta_info, info_hash) = rayon::join(
+                || MetaInfo::from_bytes(&file).context("Invalid torrent file provided"),
+                || {
+                    // The info hash is the hash of the info dictionary as it is in the file,
+                    // which differs from its encoding when the file is not canonical.
+                    let value = bencode::parse_spanned(&file)?;
+                    let info = value
+                        .get("info")
+                        .context("Invalid Torrent File - No info dictionary provided")?;
 
-                let info = bencode::to_bytes(info).expect("Failed to calculate the info hash");
-
-                InfoHash::new(&info)
-            });
-
-            let meta_info = Arc::new(
-                meta_info
-                    .join()
-                    .expect("Unable to deserialize the torrent file"),
+                    anyhow::Ok(InfoHash::new(info.bytes(&file)))
+                },
             );
-            let info_hash = info.join().expect("Unable to calculate infohash");
+            let meta_info = Arc::new(meta_info?);
+            let info_hash = info_hash?;
+            let file_checksum = sha1_smol::Sha1::from(&file).digest().to_string();
 
             Ok(Client {
                 meta_info,
                 file_name,
+                file_checksum,
                 info_hash,
                 peer_id: PeerID::new(),
-                num_files: OnceLock::new(),
+                announce_key: AnnounceKey::random(),
+                stats: SessionStats::default(),
+                ip_filter: None,
+                file_tree: OnceLock::new(),
+                file_spans: OnceLock::new(),
+                extra_trackers: Vec::new(),
             })
         } else {
             bail!("File not found")
@@ -132,6 +236,15 @@ impl Client {
         &self.file_name
     }
 
+    /// Returns the hex encoded sha1 hash of the whole torrent file.
+    ///
+    /// Unlike the [`Client::info_hash`], it changes with the keys outside of the info dictionary,
+    /// so two files of the same torrent with different trackers or comments have different
+    /// checksums.
+    pub fn file_checksum(&self) -> &str {
+        &self.file_checksum
+    }
+
     /// Returns the info hash of the torrent.
     ///
     /// It is the 20 byte sha1 hash of the bencoded form of the `info` value from the metainfo
@@ -158,9 +271,11 @@ impl Client {
         &self.info_hash
     }
 
-    /// Builds and returns the file tree structure of the torrent.
+    /// Returns a copy of the file tree structure of the torrent which can be modified (e.g.
+    /// sorted) freely.
     ///
-    /// This method also caches the number of files if not already done.
+    /// The tree is copied from the one cached by [`Client::file_tree_cached`], so it is built only
+    /// once.
     ///
     /// # Examples
     ///
@@ -173,11 +288,18 @@ impl Client {
     /// # }
     /// ```
     pub fn file_tree(&self) -> FileTree<'_> {
-        let tree = self.meta_info.info.build_file_tree();
-        if self.num_files.get().is_none() {
-            self.num_files.set(tree.num_of_files).unwrap(); // num_files is None.
-        }
-        tree
+        FileTree::clone(&self.file_tree_cached())
+    }
+
+    /// Returns the file tree structure of the torrent, building it on the first call.
+    ///
+    /// Building the tree is O(n) in the number of files, which adds up for torrents with
+    /// thousands of files. Use this over [`Client::file_tree`] when the tree is not modified.
+    pub fn file_tree_cached(&self) -> Arc<FileTree<'static>> {
+        let tree = self
+            .file_tree
+            .get_or_init(|| Arc::new(self.meta_info.info().build_file_tree().into_owned()));
+        Arc::clone(tree)
     }
 
     /// Returns the total number of files in the torrent.
@@ -197,9 +319,182 @@ impl Client {
     /// # }
     /// ```
     pub fn number_of_files(&self) -> usize {
-        *self
-            .num_files
-            .get_or_init(|| self.meta_info.info().build_file_tree().number_of_files())
+        self.file_tree_cached().number_of_files()
+    }
+
+    /// Returns the location of every file (including the padding files) within the byte stream
+    /// of the torrent. See [`FileSpan`] for more information.
+    ///
+    /// The spans are computed on the first call and cached afterwards.
+    pub fn file_spans(&self) -> &[FileSpan] {
+        self.file_spans
+            .get_or_init(|| self.meta_info.info().file_spans())
+    }
+
+    /// Returns the range of the indices of the pieces required to get all of the bytes of the
+    /// file at the provided path, or `None` if no such file exists in the torrent.
+    ///
+    /// The path is relative to the torrent root with the components separated by `/`. For single
+    /// file torrents this is the name of the torrent.
+    ///
+    /// # Examples
+    ///
+    /// ```rust
+    /// use zung_torrent::Client;
+    ///
+    /// # fn client(path_to_torrent: &str) {
+    /// let client = Client::new(path_to_torrent).expect("Failed to create client");
+    /// if let Some(pieces) = client.pieces_for_file("video/episode-1.mp4") {
+    ///     println!("Download pieces {} to {} to preview the file", pieces.start, pieces.end);
+    /// }
+    /// # }
+    /// ```
+    pub fn pieces_for_file(&self, path: &str) -> Option<Range<usize>> {
+        let path = path.trim_matches('/');
+        self.file_spans()
+            .iter()
+            .find(|span| !span.padding && span.path == path)
+            .map(|span| span.piece_range(self.meta_info.piece_length()))
+    }
+
+    /// Verifies the complete contents of the file at the `/` separated path using the BEP 47
+    /// `sha1` of the file, which avoids hashing the neighbouring files of the pieces it overlaps.
+    ///
+    /// Returns `None` if there is no such file or the torrent does not provide a hash for it. Use
+    /// [`Client::pieces_for_file`] and verify the pieces instead in that case.
+    pub fn verify_file(&self, path: &str, data: &[u8]) -> Option<bool> {
+        let path = path.trim_matches('/');
+        self.file_spans()
+            .iter()
+            .find(|span| !span.padding && span.path == path)?
+            .verify(data)
+    }
+
+    /// Returns the files which have some of their bytes within the piece at the provided index.
+    ///
+    /// A piece may overlap file boundaries, so more than one file can be returned. Padding files
+    /// are not included. Returns an empty `Vec` if the index is out of bounds.
+    pub fn file_for_piece(&self, piece: usize) -> Vec<&FileSpan> {
+        let piece_length = self.meta_info.piece_length();
+        let total = self
+            .file_spans()
+            .last()
+            .map_or(0, |span| span.byte_range().end);
+
+        let start = piece.saturating_mul(piece_length);
+        let bytes = start..start.saturating_add(piece_length).min(total);
+        if bytes.is_empty() {
+            return Vec::new();
+        }
+        self.file_spans()
+            .iter()
+            .filter(|span| !span.padding && span.overlaps(&bytes))
+            .collect()
+    }
+
+    /// Lists every file of the torrent (except the padding files) with its location in the
+    /// pieces and its expected hashes: the BEP 47 `sha1` of the file if the torrent provides one,
+    /// the hashes of the pieces covering the file otherwise. See [`FileManifest`].
+    ///
+    /// # Examples
+    ///
+    /// ```rust
+    /// use zung_torrent::Client;
+    ///
+    /// # fn client(path_to_torrent: &str) {
+    /// let client = Client::new(path_to_torrent).expect("Failed to create client");
+    /// std::fs::write("files.csv", client.manifest().to_csv()).expect("Failed to write");
+    /// # }
+    /// ```
+    pub fn manifest(&self) -> FileManifest {
+        let info = self.meta_info.info();
+        let piece_length = self.meta_info.piece_length();
+        let files = self
+            .file_spans()
+            .iter()
+            .filter(|span| !span.padding)
+            .map(|span| {
+                let pieces = span.piece_range(piece_length);
+                let piece_hashes = match span.sha1 {
+                    Some(_) => Vec::new(),
+                    None => pieces
+                        .clone()
+                        .filter_map(|piece| info.piece_hash(piece).map(hex::encode))
+                        .collect(),
+                };
+                ManifestFile {
+                    path: span.path.clone(),
+                    length: span.length,
+                    offset: span.offset,
+                    first_piece: pieces.start,
+                    last_piece: pieces.end.checked_sub(1).filter(|_| !pieces.is_empty()),
+                    sha1: span.sha1.map(|sha1| hex::encode(sha1.as_bytes())),
+                    piece_hashes,
+                }
+            })
+            .collect();
+
+        FileManifest {
+            name: info.name().to_string(),
+            info_hash: self.info_hash.clone(),
+            piece_length,
+            files,
+        }
+    }
+
+    /// Returns the [`Storage`] of the content of the torrent, laid out as set by the
+    /// [`DownloadOptions`].
+    ///
+    /// The file of a single file torrent is stored in the download directory, the files of a
+    /// multi file torrent under a directory named after the torrent (unless
+    /// [`DownloadOptions::flatten_root`] is set). Returns an error if the
+    /// [`DownloadOptions::file_rules`] would store a file outside of the directory, or two files
+    /// at the same path.
+    pub fn storage(&self, options: &DownloadOptions) -> Result<Storage> {
+        let dir = &options.download_dir;
+        let root = match self.meta_info.info().files() {
+            Files::MultiFile { .. } if !options.flatten_root => {
+                dir.join(self.meta_info.info().name())
+            }
+            _ => dir.clone(),
+        };
+        Storage::with_rules(
+            self.file_spans(),
+            &root,
+            &options.file_rules,
+            self.meta_info.piece_length(),
+        )
+    }
+
+    /// Returns the [`BlockScheduler`] of every block of the torrent, set up with the
+    /// [`DownloadOptions`], for the peers identified by `P`.
+    pub fn block_scheduler<P>(&self, options: &DownloadOptions) -> BlockScheduler<P>
+    where
+        P: Clone + Eq + Hash,
+    {
+        BlockScheduler::new(
+            self.meta_info.info().content_length() as u64,
+            self.meta_info.piece_length() as u64,
+        )
+        .endgame_threshold(options.endgame_threshold)
+    }
+
+    /// Returns the bytes of disk space the files selected by the [`DownloadOptions`] still need,
+    /// i.e. without the files skipped by the [`DownloadOptions::file_rules`] and the bytes already
+    /// on the disk. See [`Storage::required_space`].
+    pub fn required_space(&self, selection: &DownloadOptions) -> Result<u64> {
+        Ok(self.storage(selection)?.required_space())
+    }
+
+    /// Checks that the download directory has enough free space for the files selected by the
+    /// [`DownloadOptions`], before they are allocated. The error is an
+    /// [`InsufficientSpace`](crate::storage::InsufficientSpace), which can be told apart with
+    /// [`anyhow::Error::downcast_ref`]. Passes if [`DownloadOptions::force`] is set.
+    pub fn check_space(&self, options: &DownloadOptions) -> Result<()> {
+        if options.force {
+            return Ok(());
+        }
+        self.storage(options)?.check_space(&options.download_dir)
     }
 
     /// Returns the [`PeerID`] of this [`Client`].
@@ -207,12 +502,128 @@ impl Client {
         self.peer_id
     }
 
+    /// Returns the `key` sent to the trackers with every announce of this torrent. It is
+    /// generated randomly when the [`Client`] is created.
+    pub fn announce_key(&self) -> AnnounceKey {
+        self.announce_key
+    }
+
+    /// Replaces the announce key, e.g. with the one saved along with a previous session of the
+    /// torrent, so that the trackers keep recognising the client.
+    pub fn set_announce_key(&mut self, key: AnnounceKey) {
+        self.announce_key = key;
+    }
+
+    /// Sets the [`IpFilter`] the peers of this torrent are checked against, or removes it with
+    /// `None`.
+    pub fn set_ip_filter(&mut self, filter: Option<Arc<IpFilter>>) {
+        self.ip_filter = filter;
+    }
+
+    /// Returns the [`IpFilter`] of this torrent, if any.
+    pub fn ip_filter(&self) -> Option<&IpFilter> {
+        self.ip_filter.as_deref()
+    }
+
+    /// Removes the peers blocked by the [`IpFilter`] from the peers returned by a tracker.
+    /// Returns the number of removed peers, which are also counted in [`Client::stats`].
+    pub fn filter_peers(&self, peers: &mut Vec<TrackerPeer>) -> usize {
+        let Some(filter) = &self.ip_filter else {
+            return 0;
+        };
+        let before = peers.len();
+        peers.retain(|peer| !filter.is_blocked(peer.addr.ip()));
+        let filtered = before - peers.len();
+        self.stats.add_filtered_peers(filtered);
+        filtered
+    }
+
+    /// Returns `true` if an incoming connection from the address may be accepted, i.e. it is not
+    /// blocked by the [`IpFilter`]. Rejected connections are counted in [`Client::stats`].
+    pub fn admits(&self, addr: SocketAddr) -> bool {
+        let blocked = self
+            .ip_filter
+            .as_ref()
+            .is_some_and(|filter| filter.is_blocked(addr.ip()));
+        if blocked {
+            self.stats.add_filtered_peers(1);
+        }
+        !blocked
+    }
+
+    /// Returns the transfer counters of this torrent session.
+    pub fn stats(&self) -> &SessionStats {
+        &self.stats
+    }
+
+    /// Returns the values reported to the trackers with the next announce: the transfer counters
+    /// from [`Client::stats`] and the bytes still to be downloaded given the pieces which have
+    /// been verified so far.
+    pub fn progress(&self, have: &Bitfield) -> Progress {
+        Progress {
+            uploaded: self.stats.uploaded(),
+            downloaded: self.stats.downloaded(),
+            left: self.meta_info.bytes_left(have),
+        }
+    }
+
+    /// Returns the default [`AnnounceOptions`] with the per torrent values (like the
+    /// [`Client::announce_key`]) filled in, and the [`Client::progress`] of the verified pieces.
+    pub fn announce_options(&self, have: &Bitfield) -> AnnounceOptions {
+        self.announce_options_for(have, &DownloadOptions::default(), 0)
+    }
+
+    /// Same as [`Client::announce_options`] but asks for only as many peers as there are free
+    /// slots with `connected_peers` peers already connected. See
+    /// [`DownloadOptions::peers_wanted`].
+    pub fn announce_options_for(
+        &self,
+        have: &Bitfield,
+        download: &DownloadOptions,
+        connected_peers: usize,
+    ) -> AnnounceOptions {
+        AnnounceOptions {
+            key: Some(self.announce_key),
+            numwant: download.peers_wanted(connected_peers),
+            ..AnnounceOptions::new(self.progress(have))
+        }
+    }
+
+    /// Adds trackers to the ones of the torrent file, e.g. the trackers of another file of the
+    /// same torrent. The trackers already known are skipped. Returns the number of added trackers.
+    pub fn add_trackers<'a>(&mut self, urls: impl IntoIterator<Item = &'a str>) -> usize {
+        let mut added = 0;
+        for url in urls {
+            if !url.is_empty() && !self.trackers().any(|known| known == url) {
+                self.extra_trackers.push(url.to_string());
+                added += 1;
+            }
+        }
+        added
+    }
+
+    /// Returns the urls of the trackers added with [`Client::add_trackers`].
+    pub fn added_trackers(&self) -> &[String] {
+        &self.extra_trackers
+    }
+
+    /// Returns the urls of the trackers of the torrent file followed by the ones added with
+    /// [`Client::add_trackers`].
+    pub fn trackers(&self) -> impl Iterator<Item = &str> {
+        self.meta_info
+            .all_trackers()
+            .map(|(_, url)| url)
+            .chain(self.extra_trackers.iter().map(String::as_str))
+    }
+
     /// Returns the [`DownloadSources`] generated from the information contained in the
-    /// [`MetaInfo`] type.
+    /// [`MetaInfo`] type, and the trackers added with [`Client::add_trackers`].
     ///
     /// See the type documentation for more information on the usage.
     pub fn sources(&self) -> DownloadSources {
-        DownloadSources::new(self.meta_info())
+        let mut sources = DownloadSources::new(self.meta_info());
+        sources.add_trackers(&self.extra_trackers);
+        sources
     }
 }
 
@@ -233,74 +644,73 @@ impl Client {
     /// # }
     /// ```
     pub fn print_torrent_info(&self) {
-        println!("\"{}\" ", self.file_name.magenta().bold().underline(),);
-
-        let info_hash = self.info_hash().to_string();
+        self.print_torrent_info_with(InfoOptions::default());
+    }
 
-        let mut handle = Vec::new();
+    /// Same as [`Client::print_torrent_info`] but formats the output as per the provided
+    /// [`InfoOptions`].
+    pub fn print_torrent_info_with(&self, opts: InfoOptions) {
+        let summary = self.summary();
+        println!("\"{}\" ", summary.file_name.magenta().bold().underline());
 
-        // Title
-        let meta_info = Arc::clone(&self.meta_info);
-        handle.push(thread::spawn(move || {
-            print_info("Title", meta_info.title());
-        }));
+        print_info("Title", summary.title);
+        println!(
+            "\n{} Number of pieces: {} each {} in size. Total torrent size: {}",
+            "==>".green().bold(),
+            summary.number_of_pieces.to_string().bold().cyan(),
+            opts.size_format.format(summary.piece_length).bold().cyan(),
+            opts.size_format.format(summary.torrent_size).bold().cyan()
+        );
+        print_info("Number of Files", Some(summary.number_of_files));
 
-        // Length and pieces details
-        let meta_info = Arc::clone(&self.meta_info);
-        handle.push(thread::spawn(move || {
-            let npieces = meta_info.number_of_pieces();
-            let plen = meta_info.piece_length();
-            let size = (npieces * plen) as f64;
+        let created_on = summary.created_on.map(|datetime| {
+            if opts.utc {
+                datetime.to_rfc2822()
+            } else {
+                datetime.with_timezone(&Local).to_rfc2822()
+            }
+        });
+        print_info("Created on", created_on);
+        print_info("Created by", summary.created_by);
+        print_info("Comment", summary.comment);
+        print_info("Encoded in", summary.encoding);
+        print_info("Info Hash", Some(summary.info_hash));
+        print_info("Capabilities", Some(summary.capabilities));
+    }
 
-            println!(
-                "\n{} Number of pieces: {} each {} in size. Total torrent size: {}",
-                "==>".green().bold(),
-                npieces.to_string().bold().cyan(),
-                human_bytes(plen as f64).bold().cyan(),
-                human_bytes(size).bold().cyan()
-            );
-        }));
-
-        // number of Files
-        let meta_info = Arc::clone(&self.meta_info);
-        handle.push(thread::spawn(move || {
-            print_info(
-                "Number of Files",
-                Some(meta_info.info().build_file_tree().number_of_files()),
-            );
-        }));
-
-        // created on
-        let meta_info = Arc::clone(&self.meta_info);
-        handle.push(thread::spawn(move || {
-            print_info("Created on", meta_info.creation_date());
-        }));
-
-        // created by
-        let meta_info = Arc::clone(&self.meta_info);
-        handle.push(thread::spawn(move || {
-            print_info("Created by", meta_info.created_by());
-        }));
-
-        // comment
-        let meta_info = Arc::clone(&self.meta_info);
-        handle.push(thread::spawn(move || {
-            print_info("Comment", meta_info.comment());
-        }));
-
-        // Encoded in
-        let meta_info = Arc::clone(&self.meta_info);
-        handle.push(thread::spawn(move || {
-            print_info("Encoded in", meta_info.encoding());
-        }));
-
-        // info_hash
-        handle.push(thread::spawn(move || {
-            print_info("Info Hash", Some(info_hash));
-        }));
-
-        for h in handle {
-            h.join().expect("Failed to print information");
+    /// Collects the general information about the torrent printed by
+    /// [`Client::print_torrent_info`] into a [`ClientSummary`], which can also be serialized.
+    ///
+    /// # Examples
+    ///
+    /// ```rust
+    /// use zung_torrent::Client;
+    ///
+    /// # fn client(path_to_torrent: &str) {
+    /// let client = Client::new(path_to_torrent).expect("Failed to create client");
+    /// let summary = client.summary();
+    /// println!("{} has {} files", summary.name, summary.number_of_files);
+    /// # }
+    /// ```
+    pub fn summary(&self) -> ClientSummary {
+        let meta_info = &self.meta_info;
+        ClientSummary {
+            file_name: self.file_name.clone(),
+            name: meta_info.info().name().to_string(),
+            title: meta_info.title().cloned(),
+            info_hash: self.info_hash.clone(),
+            number_of_pieces: meta_info.number_of_pieces(),
+            piece_length: meta_info.piece_length(),
+            torrent_size: meta_info.size(),
+            content_length: meta_info.info().content_length(),
+            number_of_files: self.number_of_files(),
+            number_of_trackers: meta_info.number_of_trackers(),
+            number_of_web_seeds: meta_info.number_of_httpsources(),
+            created_on: meta_info.creation_date_utc(),
+            created_by: meta_info.created_by().cloned(),
+            comment: meta_info.comment().cloned(),
+            encoding: meta_info.encoding().cloned(),
+            capabilities: meta_info.capabilities(),
         }
     }
 
@@ -346,50 +756,107 @@ impl Client {
     ///
     /// client.print_files_by_name(SortOrd::Ascending);
     /// # }
+    /// ```
     pub fn print_files_by_name(&self, ord: SortOrd) {
         println!("\n{} Files:", "==>".green().bold());
         let mut filetree = self.file_tree();
-        filetree.sort_by_size(ord);
+        filetree.sort_by_name(ord);
         filetree.print();
     }
 
-    /// Prints the download sources generated from the [`MetaInfo`] file to stdout.
-    pub fn print_download_sources(&self) {
-        #[inline]
-        fn print_trackers(tracker_list: TrackerList) {
-            print_header("Trackers");
-            for (mut i, tracker) in tracker_list.iter().enumerate() {
-                i += 1;
-                println!("\t{i}. {}", tracker.url().bold().cyan())
-            }
+    /// Prints the aggregate statistics of the files in the torrent. See
+    /// [`FileStats`](crate::meta_info::FileStats).
+    pub fn print_file_stats(&self) {
+        self.print_file_stats_with(InfoOptions::default());
+    }
+
+    /// Same as [`Client::print_file_stats`] but formats the output as per the provided
+    /// [`InfoOptions`].
+    pub fn print_file_stats_with(&self, opts: InfoOptions) {
+        let tree = self.file_tree_cached();
+        let stats = tree.stats();
+
+        print_header("File Stats");
+        println!(
+            "\tFiles: {}, Total size: {}",
+            stats.files.to_string().bold().cyan(),
+            opts.size_format.format(stats.total_size).bold().cyan()
+        );
+        if let Some(mean) = stats.mean_size() {
+            println!(
+                "\tMean size: {}",
+                opts.size_format.format(mean).bold().cyan()
+            );
+        }
+        if stats.empty_files > 0 {
+            println!("\tEmpty files: {}", stats.empty_files.to_string().yellow());
         }
+        if stats.skipped > 0 {
+            println!(
+                "\tSkipped: {} {}",
+                stats.skipped.to_string().yellow(),
+                "(empty or conflicting paths)".italic().dimmed()
+            );
+        }
+        if let Some((path, size)) = &stats.smallest {
+            println!(
+                "\tSmallest: {} ({})",
+                path.bold(),
+                opts.size_format.format(*size)
+            );
+        }
+        if let Some((path, size)) = &stats.largest {
+            println!(
+                "\tLargest: {} ({})",
+                path.bold(),
+                opts.size_format.format(*size)
+            );
+        }
+
+        println!("\tBy extension:");
+        for (i, (extension, ext_stats)) in stats.extensions_by_size().into_iter().enumerate() {
+            let extension = if extension.is_empty() {
+                "(none)".italic().dimmed()
+            } else {
+                extension.bold().cyan()
+            };
+            println!(
+                "\t\t{}. {extension}: {} files, {}",
+                i + 1,
+                ext_stats.files,
+                opts.size_format.format(ext_stats.size)
+            );
+        }
+    }
 
+    /// Prints the download sources generated from the [`MetaInfo`] file to stdout.
+    pub fn print_download_sources(&self) {
         #[inline]
-        fn print_http_seeders(http_seeder_list: HttpSeederList<'_>) {
-            print_header("HTTP Seeders");
-            for (mut i, http) in http_seeder_list.iter().enumerate() {
-                i += 1;
-                println!("\t{i} : {}", http.0.bold().cyan());
-                for (mut j, url) in http.1.urls().iter().enumerate() {
-                    j += 1;
-                    println!("\t\t{j}. {url}")
-                }
+        fn print_trackers(tracker_list: &TrackerList) {
+            print_source_list(tracker_list);
+            if tracker_list.duplicates() > 0 {
+                println!(
+                    "\t{}",
+                    format!("({} duplicate trackers ignored)", tracker_list.duplicates())
+                        .italic()
+                        .dimmed()
+                );
             }
         }
 
         match self.sources() {
             DownloadSources::Trackers { tracker_list } => {
-                print_trackers(tracker_list);
+                print_trackers(&tracker_list);
             }
             DownloadSources::HttpSeeders { http_seeder_list } => {
-                print_http_seeders(http_seeder_list);
+                print_source_list(&http_seeder_list);
             }
             DownloadSources::Hybrid {
                 tracker_list,
                 http_seeder_list,
             } => {
-                print_trackers(tracker_list);
-                print_http_seeders(http_seeder_list);
+                print_trackers(&tracker_list);
+                print_source_list(&http_seeder_list);
             }
         }
     }
@@ -412,6 +879,219 @@ fn print_info<T: Display>(header: &str, value: Option<T>) {
     }
 }
 
+fn print_source_list<S: SourceList>(source_list: &S) {
+    print_header(&source_list.kind().to_string());
+    for (i, row) in source_list.display_rows().into_iter().enumerate() {
+        println!("\t{}. {}", i + 1, row.source.bold().cyan());
+        for (j, detail) in row.details.iter().enumerate() {
+            println!("\t\t{}. {detail}", j + 1)
+        }
+    }
+}
+
 fn print_header(header: &str) {
     println!("\n{} {header}: ", "==>".green().bold(),);
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_peers_wanted() {
+        let options = DownloadOptions {
+            max_peers: 80,
+            numwant: 50,
+            ..Default::default()
+        };
+        assert_eq!(options.peers_wanted(0), 50);
+        assert_eq!(options.peers_wanted(50), 30);
+        assert_eq!(options.peers_wanted(80), 0);
+        assert_eq!(options.peers_wanted(100), 0);
+    }
+
+    #[tokio::test]
+    async fn test_announce_left() {
+        use futures::StreamExt;
+
+        let path = crate::testing::TorrentBuilder::single_file("left.bin", 1000)
+            .announce("http://localhost/announce")
+            .write_to(std::env::temp_dir())
+            .unwrap();
+        let client = Client::new(&path).unwrap();
+        std::fs::remove_file(&path).unwrap();
+
+        let trackers = client.sources().trackers().unwrap().clone();
+        let url = |have: &Bitfield| {
+            let mut requests = trackers.generate_requests_with(
+                client.info_hash().as_encoded(),
+                client.peer_id(),
+                client.announce_options(have),
+            );
+            async move {
+                let outcome = requests.next().await.unwrap().unwrap();
+                outcome.result.unwrap().to_url().unwrap()
+            }
+        };
+
+        let pieces = client.meta_info().number_of_pieces();
+        let nothing = url(&Bitfield::new(pieces)).await;
+        assert!(
+            nothing.contains("uploaded=0&downloaded=0&left=1000"),
+            "{nothing}"
+        );
+        let everything = url(&Bitfield::full(pieces)).await;
+        assert!(everything.contains("left=0"), "{everything}");
+    }
+
+    #[test]
+    fn test_block_scheduler() {
+        use crate::peers::BLOCK_LENGTH;
+
+        // A single file of 40000 bytes in pieces of two blocks.
+        let mut torrent = format!(
+            "d4:infod6:lengthi40000e4:name10:blocks.bin12:piece lengthi{}e6:pieces40:",
+            2 * BLOCK_LENGTH
+        )
+        .into_bytes();
+        torrent.extend_from_slice(&[0; 40]);
+        torrent.extend_from_slice(b"ee");
+
+        let path = std::env::temp_dir().join("zung_torrent_block_scheduler.torrent");
+        std::fs::write(&path, torrent).unwrap();
+        let client = Client::new(&path).unwrap();
+        std::fs::remove_file(&path).unwrap();
+
+        let all = Bitfield::full(client.meta_info().number_of_pieces());
+        let now = std::time::Instant::now();
+        let mut scheduler = client.block_scheduler(&DownloadOptions::default());
+        assert_eq!(scheduler.next_requests(1, &all, now).len(), 3);
+        assert!(scheduler.in_endgame());
+
+        let options = DownloadOptions {
+            endgame_threshold: 0,
+            ..Default::default()
+        };
+        let mut scheduler = client.block_scheduler(&options);
+        assert_eq!(scheduler.next_requests(1, &all, now).len(), 3);
+        assert!(!scheduler.in_endgame());
+    }
+
+    #[test]
+    fn test_storage_layout() {
+        let path = crate::testing::TorrentBuilder::multi_file("layout")
+            .file("CD1/a.mp3", 10)
+            .file("CD1/cover.jpg", 10)
+            .write_to(std::env::temp_dir())
+            .unwrap();
+        let client = Client::new(&path).unwrap();
+        std::fs::remove_file(&path).unwrap();
+
+        let paths = |options: &DownloadOptions| -> Vec<Option<PathBuf>> {
+            let storage = client.storage(options).unwrap();
+            storage
+                .files()
+                .iter()
+                .map(|(_, path)| path.clone())
+                .collect()
+        };
+
+        let mut options = DownloadOptions {
+            download_dir: PathBuf::from("/downloads"),
+            ..Default::default()
+        };
+        assert_eq!(
+            paths(&options),
+            [
+                Some(PathBuf::from("/downloads/layout/CD1/a.mp3")),
+                Some(PathBuf::from("/downloads/layout/CD1/cover.jpg"))
+            ]
+        );
+
+        options.flatten_root = true;
+        options.file_rules = vec![
+            FileRule::skip(r"\.jpg$").unwrap(),
+            FileRule::rename("^CD1/", "").unwrap(),
+        ];
+        assert_eq!(
+            paths(&options),
+            [Some(PathBuf::from("/downloads/a.mp3")), None]
+        );
+    }
+
+    #[test]
+    fn test_required_space() {
+        let path = crate::testing::TorrentBuilder::multi_file("space")
+            .file("a.bin", 1000)
+            .file("b.nfo", 24)
+            .write_to(std::env::temp_dir())
+            .unwrap();
+        let client = Client::new(&path).unwrap();
+        std::fs::remove_file(&path).unwrap();
+
+        let mut options = DownloadOptions {
+            download_dir: std::env::temp_dir().join("zung-space-missing"),
+            ..Default::default()
+        };
+        assert_eq!(client.required_space(&options).unwrap(), 1024);
+        client.check_space(&options).unwrap();
+
+        options.file_rules = vec![FileRule::skip(r"\.nfo$").unwrap()];
+        assert_eq!(client.required_space(&options).unwrap(), 1000);
+    }
+
+    #[test]
+    fn test_manifest() {
+        let builder = crate::testing::TorrentBuilder::multi_file("manifest")
+            .piece_length(16)
+            .file("a.bin", 20)
+            .padding_file(12)
+            .file("empty", 0)
+            .file("b.bin", 8);
+        let path = builder.write_to(std::env::temp_dir()).unwrap();
+        let client = Client::new(&path).unwrap();
+        std::fs::remove_file(&path).unwrap();
+
+        let manifest = client.manifest();
+        let paths: Vec<_> = manifest.files.iter().map(|f| f.path.as_str()).collect();
+        assert_eq!(paths, ["a.bin", "empty", "b.bin"]);
+
+        let piece = |index| hex::encode(client.meta_info().info().piece_hash(index).unwrap());
+        let a = &manifest.files[0];
+        assert_eq!((a.first_piece, a.last_piece), (0, Some(1)));
+        assert_eq!(a.piece_hashes, [piece(0), piece(1)]);
+        assert_eq!(manifest.files[1].last_piece, None);
+        assert_eq!(manifest.files[2].offset, 32);
+        assert_eq!(manifest.files[2].piece_hashes, [piece(2)]);
+
+        let csv = manifest.to_csv();
+        assert_eq!(csv.lines().count(), 4);
+        assert_eq!(csv.lines().nth(2), Some("empty,0,32,2,,,"));
+
+        let hashed = builder
+            .file_hashes()
+            .write_to(std::env::temp_dir())
+            .unwrap();
+        let client = Client::new(&hashed).unwrap();
+        std::fs::remove_file(&hashed).unwrap();
+        let b = &client.manifest().files[2];
+        assert!(b.sha1.is_some() && b.piece_hashes.is_empty());
+    }
+
+    #[test]
+    fn test_info_hash_of_unsorted_keys() {
+        // The keys of the info dictionary are not sorted, so encoding it again changes its bytes.
+        let info = [
+            &b"d4:name5:a.bin6:lengthi1e12:piece lengthi16384e6:pieces20:"[..],
+            &[0; 20],
+            b"e",
+        ]
+        .concat();
+        let path = std::env::temp_dir().join("zung-unsorted-info.torrent");
+        std::fs::write(&path, [&b"d4:info"[..], &info, b"e"].concat()).unwrap();
+        let client = Client::new(&path).unwrap();
+        std::fs::remove_file(&path).unwrap();
+
+        assert_eq!(client.info_hash(), &InfoHash::new(&info));
+    }
+}
@@ -0,0 +1,111 @@
+use std::sync::atomic::{AtomicUsize, Ordering};
+
+use crate::peers::HealthEvent;
+
+/// Transfer counters of a torrent session.
+///
+/// The counters start at zero when the session starts and are reported to the trackers with every
+/// announce (see [`Progress`]). They can be shared between the tasks transferring the pieces.
+#[derive(Debug, Default)]
+pub struct SessionStats {
+    downloaded: AtomicUsize,
+    uploaded: AtomicUsize,
+    filtered_peers: AtomicUsize,
+    keep_alives: AtomicUsize,
+    snubbed_peers: AtomicUsize,
+    timed_out_peers: AtomicUsize,
+}
+
+impl SessionStats {
+    /// Counts bytes received from the peers and the web seeds.
+    pub fn add_downloaded(&self, bytes: usize) {
+        self.downloaded.fetch_add(bytes, Ordering::Relaxed);
+    }
+
+    /// Counts bytes sent to the peers.
+    pub fn add_uploaded(&self, bytes: usize) {
+        self.uploaded.fetch_add(bytes, Ordering::Relaxed);
+    }
+
+    /// Counts peers dropped by the [`IpFilter`](crate::ipfilter::IpFilter).
+    pub fn add_filtered_peers(&self, peers: usize) {
+        self.filtered_peers.fetch_add(peers, Ordering::Relaxed);
+    }
+
+    /// Counts an event of the [`PeerHealth`](crate::peers::PeerHealth) of a peer connection.
+    pub fn add_health_event(&self, event: HealthEvent) {
+        let counter = match event {
+            HealthEvent::KeepAlive => &self.keep_alives,
+            HealthEvent::Snubbed => &self.snubbed_peers,
+            HealthEvent::TimedOut => &self.timed_out_peers,
+        };
+        counter.fetch_add(1, Ordering::Relaxed);
+    }
+
+    /// Total bytes downloaded in this session.
+    pub fn downloaded(&self) -> usize {
+        self.downloaded.load(Ordering::Relaxed)
+    }
+
+    /// Total bytes uploaded in this session.
+    pub fn uploaded(&self) -> usize {
+        self.uploaded.load(Ordering::Relaxed)
+    }
+
+    /// Number of peers (announced by the trackers or connecting to us) which were dropped by the
+    /// [`IpFilter`](crate::ipfilter::IpFilter) in this session.
+    pub fn filtered_peers(&self) -> usize {
+        self.filtered_peers.load(Ordering::Relaxed)
+    }
+
+    /// Number of keep-alives sent to the peers in this session.
+    pub fn keep_alives(&self) -> usize {
+        self.keep_alives.load(Ordering::Relaxed)
+    }
+
+    /// Number of times a peer was snubbed for sending no block in this session.
+    pub fn snubbed_peers(&self) -> usize {
+        self.snubbed_peers.load(Ordering::Relaxed)
+    }
+
+    /// Number of peers dropped for sending nothing in this session.
+    pub fn timed_out_peers(&self) -> usize {
+        self.timed_out_peers.load(Ordering::Relaxed)
+    }
+}
+
+/// The `uploaded`, `downloaded` and `left` values of an announce. See
+/// [`Client::progress`](crate::Client::progress).
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct Progress {
+    /// Bytes uploaded since the `started` announce.
+    pub uploaded: usize,
+
+    /// Bytes downloaded since the `started` announce.
+    pub downloaded: usize,
+
+    /// Bytes still needed for the torrent to be complete.
+    pub left: usize,
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_session_stats() {
+        let stats = SessionStats::default();
+        stats.add_downloaded(10);
+        stats.add_downloaded(5);
+        stats.add_uploaded(3);
+        assert_eq!(stats.downloaded(), 15);
+        assert_eq!(stats.uploaded(), 3);
+
+        stats.add_health_event(HealthEvent::KeepAlive);
+        stats.add_health_event(HealthEvent::KeepAlive);
+        stats.add_health_event(HealthEvent::Snubbed);
+        assert_eq!(stats.keep_alives(), 2);
+        assert_eq!(stats.snubbed_peers(), 1);
+        assert_eq!(stats.timed_out_peers(), 0);
+    }
+}
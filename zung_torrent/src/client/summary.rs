@@ -0,0 +1,83 @@
+use std::fmt::Display;
+
+use chrono::{DateTime, Utc};
+use serde::Serialize;
+
+use crate::meta_info::{Capabilities, InfoHash, SizeFormat};
+
+/// The general information about a torrent, as printed by [`Client::print_torrent_info`]. See
+/// [`Client::summary`].
+///
+/// It can be serialized (e.g. to JSON) and its [`Display`] implementation prints one `key: value`
+/// line per field, with the sizes in powers of 1024 and the dates in UTC.
+///
+/// [`Client::print_torrent_info`]: crate::Client::print_torrent_info
+/// [`Client::summary`]: crate::Client::summary
+#[derive(Debug, Clone, Serialize)]
+pub struct ClientSummary {
+    /// Name of the torrent file.
+    pub file_name: String,
+
+    /// The advisory name of the file or the directory of the torrent.
+    pub name: String,
+
+    pub title: Option<String>,
+
+    pub info_hash: InfoHash,
+
+    pub number_of_pieces: usize,
+
+    /// Length of each piece in bytes.
+    pub piece_length: usize,
+
+    /// Number of pieces times the piece length.
+    pub torrent_size: usize,
+
+    /// Sum of the lengths of all the files, including the padding files.
+    pub content_length: usize,
+
+    pub number_of_files: usize,
+
+    pub number_of_trackers: usize,
+
+    pub number_of_web_seeds: usize,
+
+    pub created_on: Option<DateTime<Utc>>,
+
+    pub created_by: Option<String>,
+
+    pub comment: Option<String>,
+
+    pub encoding: Option<String>,
+
+    pub capabilities: Capabilities,
+}
+
+impl Display for ClientSummary {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        let size = |bytes| SizeFormat::Binary.format(bytes);
+        let or_absent = |value: Option<&String>| value.cloned().unwrap_or("not present".into());
+
+        writeln!(f, "File: {}", self.file_name)?;
+        writeln!(f, "Name: {}", self.name)?;
+        writeln!(f, "Title: {}", or_absent(self.title.as_ref()))?;
+        writeln!(f, "Info Hash: {}", self.info_hash)?;
+        writeln!(
+            f,
+            "Pieces: {} of {} each",
+            self.number_of_pieces,
+            size(self.piece_length)
+        )?;
+        writeln!(f, "Torrent size: {}", size(self.torrent_size))?;
+        writeln!(f, "Content length: {}", size(self.content_length))?;
+        writeln!(f, "Number of Files: {}", self.number_of_files)?;
+        writeln!(f, "Trackers: {}", self.number_of_trackers)?;
+        writeln!(f, "Web seeds: {}", self.number_of_web_seeds)?;
+        let created_on = self.created_on.map(|date| date.to_rfc2822());
+        writeln!(f, "Created on: {}", or_absent(created_on.as_ref()))?;
+        writeln!(f, "Created by: {}", or_absent(self.created_by.as_ref()))?;
+        writeln!(f, "Comment: {}", or_absent(self.comment.as_ref()))?;
+        writeln!(f, "Encoded in: {}", or_absent(self.encoding.as_ref()))?;
+        write!(f, "Capabilities: {}", self.capabilities)
+    }
+}
@@ -0,0 +1,321 @@
+//! Downloads the content of a torrent from its peers, for the `download` command.
+//!
+//! Every peer gets a connection of its own, while the blocks to request are shared through the
+//! [`BlockScheduler`]. The blocks are kept in a [`WriteCache`] until their piece is complete, and
+//! each piece is verified before it is written to the [`Storage`]. The verified pieces are
+//! published on a [`watch`] channel, e.g. for [`storage::serve_file`](crate::storage::serve_file).
+
+use std::io::Write;
+use std::net::SocketAddr;
+use std::sync::{Arc, Mutex};
+use std::time::{Duration, Instant};
+
+use anyhow::{bail, Context, Result};
+use colored::Colorize;
+use futures::StreamExt;
+use tokio::sync::watch;
+use tokio::time::timeout;
+
+use crate::peers::{
+    Bitfield, BlockRequest, BlockScheduler, Handshake, Message, PeerTransport, TcpTransport,
+    DEFAULT_PEER_TIMEOUT,
+};
+use crate::storage::{BlockAdded, Storage, WriteCache, DEFAULT_WRITE_CACHE_SIZE};
+use crate::Client;
+
+/// Time given to a peer to connect and answer the handshake.
+const CONNECT_TIMEOUT: Duration = Duration::from_secs(10);
+
+/// The download of the content of a torrent.
+pub(crate) struct Download<'a> {
+    client: &'a Client,
+    storage: Arc<Storage>,
+    shared: Mutex<Shared>,
+    verified: watch::Sender<Bitfield>,
+}
+
+/// The state shared by the connections to the peers.
+struct Shared {
+    scheduler: BlockScheduler<SocketAddr>,
+    cache: WriteCache,
+}
+
+impl<'a> Download<'a> {
+    /// Creates the download of the content of the torrent into the storage, requesting the
+    /// blocks as decided by the scheduler.
+    pub(crate) fn new(
+        client: &'a Client,
+        storage: Arc<Storage>,
+        scheduler: BlockScheduler<SocketAddr>,
+    ) -> Self {
+        let info = client.meta_info().info();
+        let pieces = storage.number_of_pieces() as usize;
+        Self {
+            client,
+            storage,
+            shared: Mutex::new(Shared {
+                scheduler,
+                cache: WriteCache::new(
+                    DEFAULT_WRITE_CACHE_SIZE,
+                    info.content_length(),
+                    client.meta_info().piece_length(),
+                ),
+            }),
+            verified: watch::channel(Bitfield::new(pieces)).0,
+        }
+    }
+
+    /// Returns a receiver of the pieces verified so far, updated as the pieces are written.
+    pub(crate) fn verified(&self) -> watch::Receiver<Bitfield> {
+        self.verified.subscribe()
+    }
+
+    /// Downloads from the peers, connected to at most `max_peers` at the same time, until every
+    /// piece is verified. Returns an error if the peers all disconnected before.
+    pub(crate) async fn run(&self, peers: Vec<SocketAddr>, max_peers: usize) -> Result<()> {
+        let connections = futures::stream::iter(peers)
+            .map(|addr| async move {
+                // The peers which can not be reached or misbehave are only dropped.
+                let _ = self.connect(addr).await;
+                self.shared
+                    .lock()
+                    .unwrap()
+                    .scheduler
+                    .peer_disconnected(&addr);
+            })
+            .buffer_unordered(max_peers.max(1))
+            .collect::<()>();
+
+        // The remaining connections are closed once the download completes.
+        let mut verified = self.verified();
+        tokio::select! {
+            _ = connections => {}
+            _ = verified.wait_for(Bitfield::is_complete) => {}
+        }
+        println!();
+
+        let verified = self.verified.borrow();
+        if !verified.is_complete() {
+            bail!(
+                "Every peer disconnected with {} of {} pieces verified",
+                verified.count(),
+                verified.len()
+            )
+        }
+        Ok(())
+    }
+
+    // Connects to the peer and downloads from it until it disconnects.
+    async fn connect(&self, addr: SocketAddr) -> Result<()> {
+        let handshake = Handshake::new(
+            self.client.info_hash().as_bytes(),
+            self.client.peer_id().as_bytes(),
+        );
+        let (transport, _) = timeout(
+            CONNECT_TIMEOUT,
+            TcpTransport::connect_with_handshake(addr, &handshake),
+        )
+        .await
+        .with_context(|| format!("{addr} did not answer the handshake in time"))??;
+        self.exchange(addr, transport).await
+    }
+
+    /// Exchanges the messages with a peer, connected through the transport after the
+    /// handshakes, until it disconnects or every piece is verified.
+    pub(crate) async fn exchange<T: PeerTransport>(
+        &self,
+        peer: SocketAddr,
+        mut transport: T,
+    ) -> Result<()> {
+        let pieces = self.storage.number_of_pieces() as usize;
+        let mut has = Bitfield::new(pieces);
+        let mut choked = true;
+        transport.send(&Message::Interested.to_frame()).await?;
+
+        while !self.verified.borrow().is_complete() {
+            let Some(frame) = timeout(DEFAULT_PEER_TIMEOUT, transport.recv())
+                .await
+                .with_context(|| format!("{peer} timed out"))??
+            else {
+                return Ok(());
+            };
+
+            let message = Message::from_frame(frame)?;
+            if let Some(bitfield) = message.bitfield(pieces)? {
+                has = bitfield;
+            }
+            match message {
+                Message::Have(piece) => {
+                    has.set(piece as usize);
+                }
+                Message::Choke => {
+                    choked = true;
+                    // The requests are dropped by the peer when it chokes the connection.
+                    self.shared
+                        .lock()
+                        .unwrap()
+                        .scheduler
+                        .peer_disconnected(&peer);
+                }
+                Message::Unchoke => choked = false,
+                Message::Piece {
+                    piece,
+                    offset,
+                    data,
+                } => {
+                    let block = BlockRequest {
+                        piece,
+                        offset,
+                        length: data.len() as u32,
+                    };
+                    self.block_received(peer, &block, &data)?;
+                }
+                _ => {}
+            }
+
+            if !choked {
+                let requests = {
+                    let mut shared = self.shared.lock().unwrap();
+                    let now = Instant::now();
+                    shared.scheduler.requeue_timed_out(now);
+                    // The endgame copies of the blocks are received and ignored instead of
+                    // cancelled.
+                    shared.scheduler.take_cancels();
+                    shared.scheduler.next_requests(peer, &has, now)
+                };
+                for request in requests {
+                    transport
+                        .send(&Message::Request(request).to_frame())
+                        .await?;
+                }
+            }
+        }
+        Ok(())
+    }
+
+    // Adds the block to its piece, and writes the piece once it is complete and verified.
+    fn block_received(&self, peer: SocketAddr, block: &BlockRequest, data: &[u8]) -> Result<()> {
+        self.client.stats().add_downloaded(data.len());
+        let mut shared = self.shared.lock().unwrap();
+        if self.verified.borrow().has(block.piece as usize) {
+            shared.scheduler.block_received(&peer, block);
+            return Ok(());
+        }
+
+        let piece = match shared.cache.add_block(block.piece, block.offset, data)? {
+            BlockAdded::Buffered => {
+                shared.scheduler.block_received(&peer, block);
+                return Ok(());
+            }
+            BlockAdded::CacheFull => {
+                shared.scheduler.block_rejected(&peer, block);
+                return Ok(());
+            }
+            BlockAdded::PieceComplete(piece) => {
+                shared.scheduler.block_received(&peer, block);
+                piece
+            }
+        };
+
+        let index = block.piece;
+        if !self
+            .client
+            .meta_info()
+            .info()
+            .verify_piece(index as usize, &piece)
+        {
+            shared.scheduler.piece_failed(index);
+            return Ok(());
+        }
+        self.storage.write_piece(index, &piece)?;
+
+        self.verified.send_modify(|verified| {
+            verified.set(index as usize);
+        });
+        let verified = self.verified.borrow();
+        shared.scheduler.skip_pieces(&verified);
+        self.storage.finish_files(index, &verified)?;
+
+        print!(
+            "\r{} {} of {} pieces verified",
+            "==>".green().bold(),
+            verified.count().to_string().bold().cyan(),
+            verified.len()
+        );
+        let _ = std::io::stdout().flush();
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::peers::FramedTransport;
+    use crate::testing::TorrentBuilder;
+    use crate::DownloadOptions;
+
+    // Answers the requests of the connected peer with the content, after unchoking it.
+    async fn seed<T: PeerTransport>(mut transport: T, pieces: usize, content: Vec<u8>) {
+        let all = Message::pieces(&Bitfield::full(pieces), false);
+        transport.send(&all.to_frame()).await.unwrap();
+        transport.send(&Message::Unchoke.to_frame()).await.unwrap();
+
+        while let Ok(Some(frame)) = transport.recv().await {
+            if let Message::Request(request) = Message::from_frame(frame).unwrap() {
+                let start = request.piece as usize * 16 + request.offset as usize;
+                let piece = Message::Piece {
+                    piece: request.piece,
+                    offset: request.offset,
+                    data: content[start..start + request.length as usize]
+                        .to_vec()
+                        .into(),
+                };
+                if transport.send(&piece.to_frame()).await.is_err() {
+                    return;
+                }
+            }
+        }
+    }
+
+    #[tokio::test]
+    async fn test_download_from_a_peer() {
+        let dir = std::env::temp_dir().join(format!("zung-download-{}", std::process::id()));
+        let _ = std::fs::remove_dir_all(&dir);
+        std::fs::create_dir_all(&dir).unwrap();
+
+        let builder = TorrentBuilder::multi_file("download")
+            .piece_length(16)
+            .file("a", 20)
+            .file("dir/b", 30);
+        let client = Client::new(builder.write_to(&dir).unwrap()).unwrap();
+        let options = DownloadOptions {
+            download_dir: dir.clone(),
+            ..Default::default()
+        };
+        let storage = Arc::new(client.storage(&options).unwrap());
+        let download = Download::new(&client, storage, client.block_scheduler(&options));
+
+        let (ours, theirs) = tokio::io::duplex(64 * 1024);
+        let seeder = tokio::spawn(seed(FramedTransport::new(theirs), 4, builder.content()));
+        download
+            .exchange(
+                "127.0.0.1:6881".parse().unwrap(),
+                FramedTransport::new(ours),
+            )
+            .await
+            .unwrap();
+
+        assert!(download.verified().borrow().is_complete());
+        let content = builder.content();
+        assert_eq!(
+            std::fs::read(dir.join("download/a")).unwrap(),
+            content[..20]
+        );
+        assert_eq!(
+            std::fs::read(dir.join("download/dir/b")).unwrap(),
+            content[20..]
+        );
+        assert_eq!(client.stats().downloaded(), 50);
+        seeder.abort();
+    }
+}
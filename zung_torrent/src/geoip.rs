@@ -0,0 +1,504 @@
+//! Annotating peers with their country and autonomous system from a MaxMind database.
+//!
+//! This module is only available with the `geoip` feature. The lookups are strictly offline: the
+//! databases are [MMDB](https://maxmind.github.io/MaxMind-DB/) files supplied by the user (such as
+//! GeoLite2-Country and GeoLite2-ASN), read fully into memory and never updated or downloaded by
+//! this library.
+//!
+//! # Example
+//!
+//! ```no_run
+//! use zung_torrent::geoip::GeoIp;
+//!
+//! # fn run() -> anyhow::Result<()> {
+//! let geoip = GeoIp::open(["GeoLite2-Country.mmdb", "GeoLite2-ASN.mmdb"])?;
+//! let geo = geoip.lookup("1.1.1.1".parse()?);
+//! println!("{geo}");
+//! # Ok(())
+//! # }
+//! ```
+
+use std::fmt::Display;
+use std::net::IpAddr;
+use std::path::Path;
+
+use anyhow::{bail, Context, Result};
+
+/// Marks the start of the metadata section, at the end of the file.
+const METADATA_MARKER: &[u8] = b"\xAB\xCD\xEFMaxMind.com";
+
+/// Size of the separator between the search tree and the data section.
+const DATA_SEPARATOR: usize = 16;
+
+/// What is known about the location and the network of an IP address.
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct PeerGeo {
+    /// ISO 3166-1 alpha-2 code of the country, e.g. `NL`.
+    pub country: Option<String>,
+
+    /// The autonomous system number, e.g. `13335`.
+    pub asn: Option<u32>,
+
+    /// The organization operating the autonomous system.
+    pub organization: Option<String>,
+}
+
+impl PeerGeo {
+    /// Returns `true` if nothing is known about the address.
+    pub fn is_empty(&self) -> bool {
+        self.country.is_none() && self.asn.is_none() && self.organization.is_none()
+    }
+}
+
+/// Prints as `NL AS1136 KPN`, skipping the missing parts, or `-` when nothing is known.
+impl Display for PeerGeo {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        if self.is_empty() {
+            return write!(f, "-");
+        }
+
+        let parts = [
+            self.country.clone(),
+            self.asn.map(|asn| format!("AS{asn}")),
+            self.organization.clone(),
+        ];
+        let parts: Vec<_> = parts.into_iter().flatten().collect();
+        write!(f, "{}", parts.join(" "))
+    }
+}
+
+/// A set of MMDB databases. The lookups combine the results of all the databases, so a country
+/// and an ASN database can be used together.
+#[derive(Debug, Default)]
+pub struct GeoIp {
+    databases: Vec<Mmdb>,
+}
+
+impl GeoIp {
+    /// Reads the databases at the provided paths.
+    pub fn open<I, P>(paths: I) -> Result<Self>
+    where
+        I: IntoIterator<Item = P>,
+        P: AsRef<Path>,
+    {
+        let databases = paths
+            .into_iter()
+            .map(|path| {
+                let path = path.as_ref();
+                let bytes = std::fs::read(path)
+                    .with_context(|| format!("Unable to read {}", path.display()))?;
+                Mmdb::from_bytes(bytes)
+                    .with_context(|| format!("Invalid MaxMind database: {}", path.display()))
+            })
+            .collect::<Result<_>>()?;
+        Ok(Self { databases })
+    }
+
+    /// Builds the set from databases already read into memory.
+    pub fn from_bytes(databases: Vec<Vec<u8>>) -> Result<Self> {
+        let databases = databases
+            .into_iter()
+            .map(Mmdb::from_bytes)
+            .collect::<Result<_>>()?;
+        Ok(Self { databases })
+    }
+
+    /// Looks the address up in every database. Errors of corrupted databases are treated as the
+    /// address not being found.
+    pub fn lookup(&self, ip: IpAddr) -> PeerGeo {
+        let mut geo = PeerGeo::default();
+        for record in self
+            .databases
+            .iter()
+            .filter_map(|db| db.lookup(ip).ok().flatten())
+        {
+            let country = record
+                .get("country")
+                .or_else(|| record.get("registered_country"))
+                .and_then(|country| country.get("iso_code"))
+                .and_then(Data::as_str);
+            geo.country = geo.country.or(country.map(String::from));
+
+            let asn = record
+                .get("autonomous_system_number")
+                .and_then(Data::as_uint)
+                .and_then(|asn| u32::try_from(asn).ok());
+            geo.asn = geo.asn.or(asn);
+
+            let organization = record
+                .get("autonomous_system_organization")
+                .and_then(Data::as_str);
+            geo.organization = geo.organization.or(organization.map(String::from));
+        }
+        geo
+    }
+}
+
+/// A value of the data section.
+#[derive(Debug, Clone, PartialEq)]
+enum Data {
+    String(String),
+    Double(f64),
+    Bytes(Vec<u8>),
+    Uint(u128),
+    Int(i32),
+    Map(Vec<(String, Data)>),
+    Array(Vec<Data>),
+    Bool(bool),
+    Float(f32),
+}
+
+impl Data {
+    fn get(&self, key: &str) -> Option<&Data> {
+        match self {
+            Data::Map(entries) => entries.iter().find(|(k, _)| k == key).map(|(_, v)| v),
+            _ => None,
+        }
+    }
+
+    fn as_str(&self) -> Option<&str> {
+        match self {
+            Data::String(s) => Some(s),
+            _ => None,
+        }
+    }
+
+    fn as_uint(&self) -> Option<u128> {
+        match self {
+            Data::Uint(n) => Some(*n),
+            _ => None,
+        }
+    }
+}
+
+/// A MaxMind DB file.
+#[derive(Debug)]
+struct Mmdb {
+    bytes: Vec<u8>,
+    node_count: usize,
+    record_size: usize,
+    ip_version: u128,
+    data_start: usize,
+}
+
+impl Mmdb {
+    fn from_bytes(bytes: Vec<u8>) -> Result<Self> {
+        let marker = bytes
+            .windows(METADATA_MARKER.len())
+            .rposition(|window| window == METADATA_MARKER)
+            .context("No metadata section found")?;
+        let metadata_start = marker + METADATA_MARKER.len();
+        let (metadata, _) = Decoder::new(&bytes[metadata_start..]).decode(0)?;
+
+        let field = |key| {
+            metadata
+                .get(key)
+                .and_then(Data::as_uint)
+                .with_context(|| format!("The metadata has no {key}"))
+        };
+        let node_count = field("node_count")? as usize;
+        let record_size = field("record_size")? as usize;
+        let ip_version = field("ip_version")?;
+
+        if ![24, 28, 32].contains(&record_size) {
+            bail!("Unsupported record size: {record_size}")
+        }
+        if ![4, 6].contains(&ip_version) {
+            bail!("Unsupported IP version: {ip_version}")
+        }
+
+        let data_start = node_count * record_size / 4 + DATA_SEPARATOR;
+        if data_start > marker {
+            bail!("The search tree is larger than the file")
+        }
+
+        Ok(Self {
+            bytes,
+            node_count,
+            record_size,
+            ip_version,
+            data_start,
+        })
+    }
+
+    /// Reads the left (`bit` false) or the right record of the node.
+    fn record(&self, node: usize, bit: bool) -> Result<usize> {
+        let node_size = self.record_size / 4;
+        let offset = node * node_size;
+        let b = self
+            .bytes
+            .get(offset..offset + node_size)
+            .context("Node out of bounds")?;
+
+        let be = |bytes: &[u8]| bytes.iter().fold(0, |n, &b| (n << 8) | b as usize);
+        Ok(match (self.record_size, bit) {
+            (24, false) => be(&b[0..3]),
+            (24, true) => be(&b[3..6]),
+            (28, false) => ((b[3] as usize & 0xF0) << 20) | be(&b[0..3]),
+            (28, true) => ((b[3] as usize & 0x0F) << 24) | be(&b[4..7]),
+            (_, false) => be(&b[0..4]),
+            (_, true) => be(&b[4..8]),
+        })
+    }
+
+    fn lookup(&self, ip: IpAddr) -> Result<Option<Data>> {
+        let (bits, len) = match ip.to_canonical() {
+            IpAddr::V4(ip) if self.ip_version == 4 => (u32::from(ip) as u128, 32),
+            IpAddr::V6(_) if self.ip_version == 4 => return Ok(None),
+            // IPv4 addresses are found under ::/96 in the IPv6 databases.
+            IpAddr::V4(ip) => (u32::from(ip) as u128, 128),
+            IpAddr::V6(ip) => (u128::from(ip), 128),
+        };
+
+        let mut node = 0;
+        for i in (0..len).rev() {
+            node = self.record(node, (bits >> i) & 1 == 1)?;
+            if node == self.node_count {
+                return Ok(None);
+            }
+            if node > self.node_count {
+                let offset = node - self.node_count - DATA_SEPARATOR;
+                let data = Decoder::new(&self.bytes[self.data_start..]);
+                return Ok(Some(data.decode(offset)?.0));
+            }
+        }
+        bail!("The search tree is deeper than the address")
+    }
+}
+
+/// Decodes the values of a data section. Pointers are relative to the start of the section.
+struct Decoder<'a> {
+    section: &'a [u8],
+}
+
+impl<'a> Decoder<'a> {
+    fn new(section: &'a [u8]) -> Self {
+        Self { section }
+    }
+
+    fn bytes(&self, offset: usize, len: usize) -> Result<&'a [u8]> {
+        self.section
+            .get(offset..offset + len)
+            .context("Data out of bounds")
+    }
+
+    fn uint(&self, offset: usize, len: usize) -> Result<u128> {
+        Ok(self
+            .bytes(offset, len)?
+            .iter()
+            .fold(0, |n, &b| (n << 8) | b as u128))
+    }
+
+    /// Decodes the value at `offset`, returning it along with the offset right after it.
+    fn decode(&self, offset: usize) -> Result<(Data, usize)> {
+        let control = *self.bytes(offset, 1)?.first().expect("One byte");
+        let mut offset = offset + 1;
+
+        let mut kind = control >> 5;
+        if kind == 1 {
+            let (target, next) = self.pointer(control, offset)?;
+            let (data, _) = self.decode(target)?;
+            return Ok((data, next));
+        }
+        if kind == 0 {
+            kind = 7 + self.bytes(offset, 1)?[0];
+            offset += 1;
+        }
+
+        let size = match control & 0x1F {
+            size @ 0..=28 => size as usize,
+            29 => 29 + self.uint(offset, 1)? as usize,
+            30 => 285 + self.uint(offset, 2)? as usize,
+            _ => 65_821 + self.uint(offset, 3)? as usize,
+        };
+        offset += match control & 0x1F {
+            29 => 1,
+            30 => 2,
+            31 => 3,
+            _ => 0,
+        };
+
+        let data = match kind {
+            2 => {
+                let string = std::str::from_utf8(self.bytes(offset, size)?)?;
+                (Data::String(string.to_string()), offset + size)
+            }
+            3 => {
+                let bytes = self.bytes(offset, 8)?;
+                let double = f64::from_be_bytes(bytes.try_into()?);
+                (Data::Double(double), offset + 8)
+            }
+            4 => (
+                Data::Bytes(self.bytes(offset, size)?.to_vec()),
+                offset + size,
+            ),
+            5 | 6 | 9 | 10 => (Data::Uint(self.uint(offset, size)?), offset + size),
+            8 => {
+                // Stored in as few bytes as needed and sign extended from 32 bits.
+                let int = self.uint(offset, size)? as u32 as i32;
+                (Data::Int(int), offset + size)
+            }
+            7 => {
+                let mut entries = Vec::with_capacity(size);
+                for _ in 0..size {
+                    let (key, next) = self.decode(offset)?;
+                    let Data::String(key) = key else {
+                        bail!("Map keys must be strings")
+                    };
+                    let (value, next) = self.decode(next)?;
+                    entries.push((key, value));
+                    offset = next;
+                }
+                (Data::Map(entries), offset)
+            }
+            11 => {
+                let mut items = Vec::with_capacity(size);
+                for _ in 0..size {
+                    let (item, next) = self.decode(offset)?;
+                    items.push(item);
+                    offset = next;
+                }
+                (Data::Array(items), offset)
+            }
+            14 => (Data::Bool(size != 0), offset),
+            15 => {
+                let bytes = self.bytes(offset, 4)?;
+                (
+                    Data::Float(f32::from_be_bytes(bytes.try_into()?)),
+                    offset + 4,
+                )
+            }
+            kind => bail!("Unsupported data type: {kind}"),
+        };
+        Ok(data)
+    }
+
+    /// Decodes a pointer, returning its target along with the offset right after it.
+    fn pointer(&self, control: u8, offset: usize) -> Result<(usize, usize)> {
+        let value = (control & 0x07) as usize;
+        let len = ((control >> 3) & 0x03) as usize + 1;
+        let bytes = self.uint(offset, len)? as usize;
+        let target = match len {
+            1 => (value << 8) | bytes,
+            2 => ((value << 16) | bytes) + 2048,
+            3 => ((value << 24) | bytes) + 526_336,
+            _ => bytes,
+        };
+        Ok((target, offset + len))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn string(s: &str) -> Vec<u8> {
+        // Sizes from 29 take an extra byte.
+        let mut bytes = match s.len() {
+            len @ 0..=28 => vec![(2 << 5) | len as u8],
+            len => vec![(2 << 5) | 29, (len - 29) as u8],
+        };
+        bytes.extend_from_slice(s.as_bytes());
+        bytes
+    }
+
+    fn uint32(n: u32) -> Vec<u8> {
+        let mut bytes = vec![(6 << 5) | 4];
+        bytes.extend_from_slice(&n.to_be_bytes());
+        bytes
+    }
+
+    fn map(entries: &[(&str, Vec<u8>)]) -> Vec<u8> {
+        let mut bytes = vec![(7 << 5) | entries.len() as u8];
+        for (key, value) in entries {
+            bytes.extend(string(key));
+            bytes.extend_from_slice(value);
+        }
+        bytes
+    }
+
+    /// An IPv4 database with 24 bit records in which only `prefix`/8 has the record.
+    fn database(prefix: u8, record: Vec<u8>) -> Vec<u8> {
+        let node_count = 8;
+
+        // The organization is stored first and pointed to from the record.
+        let mut data = string("KPN");
+        let record_offset = data.len();
+        data.extend(record);
+
+        let mut tree = Vec::new();
+        for i in 0..node_count {
+            let next = if i == node_count - 1 {
+                node_count + DATA_SEPARATOR + record_offset
+            } else {
+                i + 1
+            };
+            let bit = (prefix >> (7 - i)) & 1 == 1;
+            let (left, right) = if bit {
+                (node_count, next)
+            } else {
+                (next, node_count)
+            };
+            tree.extend_from_slice(&(left as u32).to_be_bytes()[1..]);
+            tree.extend_from_slice(&(right as u32).to_be_bytes()[1..]);
+        }
+
+        let mut bytes = tree;
+        bytes.extend_from_slice(&[0; DATA_SEPARATOR]);
+        bytes.extend(data);
+        bytes.extend_from_slice(METADATA_MARKER);
+        bytes.extend(map(&[
+            ("node_count", uint32(node_count as u32)),
+            ("record_size", uint32(24)),
+            ("ip_version", uint32(4)),
+        ]));
+        bytes
+    }
+
+    #[test]
+    fn test_lookup() {
+        let record = map(&[
+            ("country", map(&[("iso_code", string("NL"))])),
+            ("autonomous_system_number", uint32(1136)),
+            // A pointer to the string at the start of the data section.
+            ("autonomous_system_organization", vec![1 << 5, 0]),
+        ]);
+        let geoip = GeoIp::from_bytes(vec![database(10, record)]).unwrap();
+
+        let geo = geoip.lookup("10.1.2.3".parse().unwrap());
+        assert_eq!(
+            geo,
+            PeerGeo {
+                country: Some("NL".into()),
+                asn: Some(1136),
+                organization: Some("KPN".into()),
+            }
+        );
+        assert_eq!(geo.to_string(), "NL AS1136 KPN");
+
+        let geo = geoip.lookup("11.1.2.3".parse().unwrap());
+        assert!(geo.is_empty());
+        assert_eq!(geo.to_string(), "-");
+        assert!(geoip
+            .lookup("::ffff:10.0.0.1".parse().unwrap())
+            .asn
+            .is_some());
+        assert!(geoip.lookup("2001:db8::1".parse().unwrap()).is_empty());
+    }
+
+    #[test]
+    fn test_databases_are_combined() {
+        let country = database(10, map(&[("country", map(&[("iso_code", string("NL"))]))]));
+        let asn = database(10, map(&[("autonomous_system_number", uint32(1136))]));
+        let geoip = GeoIp::from_bytes(vec![country, asn]).unwrap();
+
+        let geo = geoip.lookup("10.0.0.1".parse().unwrap());
+        assert_eq!(geo.country.as_deref(), Some("NL"));
+        assert_eq!(geo.asn, Some(1136));
+    }
+
+    #[test]
+    fn test_invalid_database() {
+        assert!(GeoIp::from_bytes(vec![b"not a database".to_vec()]).is_err());
+    }
+}
@@ -0,0 +1,174 @@
+//! User commands and webhooks run when a torrent completes.
+
+use std::fmt;
+use std::path::PathBuf;
+use std::str::FromStr;
+
+use anyhow::{bail, Context, Result};
+use serde::Serialize;
+
+use crate::{
+    meta_info::Files,
+    net::{http::HttpClient, redact},
+    Client, DownloadOptions,
+};
+
+/// A hook run when a torrent completes, parsed from a url for a webhook and from any other
+/// string for a command.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum CompletionHook {
+    /// A command run by the shell (`sh -c` or `cmd /C` on Windows), with the torrent in the
+    /// `ZUNG_NAME`, `ZUNG_PATH` and `ZUNG_INFO_HASH` environment variables.
+    Command(String),
+
+    /// A url receiving the [`CompletedTorrent`] as JSON in a POST request.
+    Webhook(String),
+}
+
+/// The completed torrent given to the [`CompletionHook`]s.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize)]
+pub struct CompletedTorrent {
+    /// Always `complete`, to tell the event apart in the webhooks.
+    pub event: &'static str,
+    pub name: String,
+
+    /// The file of a single file torrent or the directory of a multi file torrent.
+    pub path: PathBuf,
+    pub info_hash: String,
+}
+
+impl CompletedTorrent {
+    /// The torrent of the client, stored as set by the options.
+    pub fn new(client: &Client, options: &DownloadOptions) -> Self {
+        let info = client.meta_info().info();
+        let path = match info.files() {
+            Files::MultiFile { .. } if options.flatten_root => options.download_dir.clone(),
+            _ => options.download_dir.join(info.name()),
+        };
+        Self {
+            event: "complete",
+            name: info.name().to_string(),
+            path,
+            info_hash: client.info_hash().to_hex(),
+        }
+    }
+}
+
+impl CompletionHook {
+    /// Runs the hook for the torrent. Fails if the command does not succeed or if the webhook
+    /// does not respond with a `2xx` status.
+    pub async fn run(&self, torrent: &CompletedTorrent) -> Result<()> {
+        match self {
+            CompletionHook::Command(command) => {
+                let mut shell = if cfg!(windows) {
+                    let mut shell = tokio::process::Command::new("cmd");
+                    shell.arg("/C");
+                    shell
+                } else {
+                    let mut shell = tokio::process::Command::new("sh");
+                    shell.arg("-c");
+                    shell
+                };
+                let status = shell
+                    .arg(command)
+                    .env("ZUNG_NAME", &torrent.name)
+                    .env("ZUNG_PATH", &torrent.path)
+                    .env("ZUNG_INFO_HASH", &torrent.info_hash)
+                    .status()
+                    .await
+                    .with_context(|| format!("Unable to run {command:?}"))?;
+                if !status.success() {
+                    bail!("The hook {command:?} failed ({status})")
+                }
+            }
+            CompletionHook::Webhook(url) => {
+                HttpClient::shared()
+                    .post_json(url, torrent)
+                    .await?
+                    .error_for_status(url)
+                    .with_context(|| format!("The webhook {} failed", redact::display_url(url)))?;
+            }
+        }
+        Ok(())
+    }
+}
+
+impl FromStr for CompletionHook {
+    type Err = anyhow::Error;
+
+    fn from_str(s: &str) -> Result<Self> {
+        let s = s.trim();
+        if s.is_empty() {
+            bail!("Invalid hook - Expected a command or a url")
+        }
+        if s.starts_with("http://") || s.starts_with("https://") {
+            Ok(CompletionHook::Webhook(s.to_string()))
+        } else {
+            Ok(CompletionHook::Command(s.to_string()))
+        }
+    }
+}
+
+impl fmt::Display for CompletionHook {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            CompletionHook::Command(hook) | CompletionHook::Webhook(hook) => f.write_str(hook),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::testing::MockHttpTracker;
+
+    fn torrent() -> CompletedTorrent {
+        CompletedTorrent {
+            event: "complete",
+            name: "done.bin".to_string(),
+            path: PathBuf::from("/downloads/done.bin"),
+            info_hash: "00".repeat(20),
+        }
+    }
+
+    #[test]
+    fn test_parse() {
+        assert_eq!(
+            "https://example.com/hook"
+                .parse::<CompletionHook>()
+                .unwrap(),
+            CompletionHook::Webhook("https://example.com/hook".to_string())
+        );
+        assert_eq!(
+            "notify-send done".parse::<CompletionHook>().unwrap(),
+            CompletionHook::Command("notify-send done".to_string())
+        );
+        assert!(" ".parse::<CompletionHook>().is_err());
+    }
+
+    #[cfg(unix)]
+    #[tokio::test]
+    async fn test_command() {
+        let out = std::env::temp_dir().join(format!("zung-hook-{}", std::process::id()));
+        let hook = CompletionHook::Command(format!(
+            "echo \"$ZUNG_NAME $ZUNG_PATH\" > {}",
+            out.display()
+        ));
+        hook.run(&torrent()).await.unwrap();
+        assert_eq!(
+            std::fs::read_to_string(&out).unwrap(),
+            "done.bin /downloads/done.bin\n"
+        );
+
+        let failing = CompletionHook::Command("exit 3".to_string());
+        assert!(failing.run(&torrent()).await.is_err());
+    }
+
+    #[tokio::test]
+    async fn test_webhook() {
+        let mock = MockHttpTracker::start("ok").await.unwrap();
+        let hook = CompletionHook::Webhook(mock.url());
+        hook.run(&torrent()).await.unwrap();
+        assert_eq!(mock.requests(), ["/announce"]);
+    }
+}
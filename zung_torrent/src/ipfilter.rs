@@ -0,0 +1,210 @@
+//! Blocking peers by their IP address.
+//!
+//! An [`IpFilter`] is loaded from a blocklist with one entry per line, in either of the commonly
+//! distributed formats:
+//!
+//! - PeerGuardian (`.p2p`): `description:first-last`, e.g. `Some Org:1.2.3.0-1.2.3.255`.
+//! - CIDR: `1.2.3.0/24` or `2001:db8::/32`.
+//!
+//! Plain `first-last` ranges and single addresses are accepted as well. Empty lines and comments
+//! (starting with `#` or `//`) are ignored, and malformed lines are skipped (see
+//! [`IpFilter::skipped`]) as published lists often contain a few.
+//!
+//! # Example
+//!
+//! ```
+//! use zung_torrent::ipfilter::IpFilter;
+//!
+//! let filter = IpFilter::parse("Bad Org:10.0.0.0-10.0.0.255\n192.168.0.0/16");
+//! assert!(filter.is_blocked("10.0.0.7".parse().unwrap()));
+//! assert!(!filter.is_blocked("8.8.8.8".parse().unwrap()));
+//! ```
+
+use std::net::{IpAddr, Ipv4Addr, Ipv6Addr};
+use std::ops::RangeInclusive;
+use std::path::Path;
+
+use anyhow::{Context, Result};
+
+/// A set of blocked IP address ranges.
+#[derive(Debug, Clone, Default)]
+pub struct IpFilter {
+    // Sorted and non-overlapping, so that lookups are a binary search. The IPv4 ranges are
+    // widened to u128 only to share the code with the IPv6 ones.
+    v4: Vec<RangeInclusive<u128>>,
+    v6: Vec<RangeInclusive<u128>>,
+    skipped: usize,
+}
+
+impl IpFilter {
+    /// Reads and parses the blocklist at the provided path.
+    pub fn load<P: AsRef<Path>>(path: P) -> Result<Self> {
+        let path = path.as_ref();
+        let list = std::fs::read(path)
+            .with_context(|| format!("Unable to read the blocklist {}", path.display()))?;
+        Ok(Self::parse(&String::from_utf8_lossy(&list)))
+    }
+
+    /// Parses a blocklist in the PeerGuardian or the CIDR format. See the [module](self)
+    /// documentation.
+    pub fn parse(list: &str) -> Self {
+        let mut filter = IpFilter::default();
+        for line in list.lines().map(str::trim) {
+            if line.is_empty() || line.starts_with('#') || line.starts_with("//") {
+                continue;
+            }
+
+            // The description of PeerGuardian entries may contain anything but the range.
+            let range = parse_range(line).or_else(|| {
+                line.rsplit_once(':')
+                    .and_then(|(_, range)| parse_range(range))
+            });
+            match range {
+                Some((IpAddr::V4(first), IpAddr::V4(last))) => {
+                    filter
+                        .v4
+                        .push(u32::from(first).into()..=u32::from(last).into());
+                }
+                Some((IpAddr::V6(first), IpAddr::V6(last))) => {
+                    filter.v6.push(u128::from(first)..=u128::from(last));
+                }
+                _ => filter.skipped += 1,
+            }
+        }
+
+        merge(&mut filter.v4);
+        merge(&mut filter.v6);
+        filter
+    }
+
+    /// Returns `true` if the address is in any of the blocked ranges. IPv4-mapped IPv6 addresses
+    /// are checked against the IPv4 ranges.
+    pub fn is_blocked(&self, ip: IpAddr) -> bool {
+        match ip.to_canonical() {
+            IpAddr::V4(ip) => contains(&self.v4, u32::from(ip).into()),
+            IpAddr::V6(ip) => contains(&self.v6, u128::from(ip)),
+        }
+    }
+
+    /// Returns the number of blocked ranges, after merging the overlapping ones.
+    pub fn len(&self) -> usize {
+        self.v4.len() + self.v6.len()
+    }
+
+    /// Returns `true` if nothing is blocked.
+    pub fn is_empty(&self) -> bool {
+        self.len() == 0
+    }
+
+    /// Returns the number of malformed lines which were skipped while parsing.
+    pub fn skipped(&self) -> usize {
+        self.skipped
+    }
+}
+
+/// Parses `first-last`, `ip/prefix` or a single `ip`.
+fn parse_range(range: &str) -> Option<(IpAddr, IpAddr)> {
+    if let Some((first, last)) = range.split_once('-') {
+        let first: IpAddr = first.trim().parse().ok()?;
+        let last: IpAddr = last.trim().parse().ok()?;
+        return (first <= last).then_some((first, last));
+    }
+
+    if let Some((ip, prefix)) = range.split_once('/') {
+        let prefix: u32 = prefix.trim().parse().ok()?;
+        return match ip.trim().parse().ok()? {
+            IpAddr::V4(ip) if prefix <= 32 => {
+                let mask = u32::MAX.checked_shl(32 - prefix).unwrap_or(0);
+                let first = u32::from(ip) & mask;
+                let last = first | !mask;
+                Some((Ipv4Addr::from(first).into(), Ipv4Addr::from(last).into()))
+            }
+            IpAddr::V6(ip) if prefix <= 128 => {
+                let mask = u128::MAX.checked_shl(128 - prefix).unwrap_or(0);
+                let first = u128::from(ip) & mask;
+                let last = first | !mask;
+                Some((Ipv6Addr::from(first).into(), Ipv6Addr::from(last).into()))
+            }
+            _ => None,
+        };
+    }
+
+    let ip: IpAddr = range.trim().parse().ok()?;
+    Some((ip, ip))
+}
+
+/// Sorts the ranges and merges the overlapping and adjacent ones.
+fn merge(ranges: &mut Vec<RangeInclusive<u128>>) {
+    ranges.sort_by_key(|range| *range.start());
+    let mut merged: Vec<RangeInclusive<u128>> = Vec::with_capacity(ranges.len());
+    for range in ranges.drain(..) {
+        match merged.last_mut() {
+            Some(last) if *range.start() <= last.end().saturating_add(1) => {
+                if range.end() > last.end() {
+                    *last = *last.start()..=*range.end();
+                }
+            }
+            _ => merged.push(range),
+        }
+    }
+    *ranges = merged;
+}
+
+fn contains(ranges: &[RangeInclusive<u128>], ip: u128) -> bool {
+    let index = ranges.partition_point(|range| *range.start() <= ip);
+    index > 0 && ip <= *ranges[index - 1].end()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn blocked(filter: &IpFilter, ip: &str) -> bool {
+        filter.is_blocked(ip.parse().unwrap())
+    }
+
+    #[test]
+    fn test_peerguardian_format() {
+        let filter = IpFilter::parse(
+            "# comment\n\
+             Some Org: Inc:1.2.3.0-1.2.3.255\n\
+             \n\
+             Other-Org:5.6.7.8-5.6.7.8\n",
+        );
+        assert_eq!(filter.len(), 2);
+        assert!(blocked(&filter, "1.2.3.0"));
+        assert!(blocked(&filter, "1.2.3.255"));
+        assert!(!blocked(&filter, "1.2.4.0"));
+        assert!(blocked(&filter, "5.6.7.8"));
+        assert!(!blocked(&filter, "5.6.7.9"));
+        assert!(blocked(&filter, "::ffff:1.2.3.4"));
+    }
+
+    #[test]
+    fn test_cidr_format() {
+        let filter = IpFilter::parse("10.0.0.0/8\n2001:db8::/32\n0.0.0.0/32\n");
+        assert!(blocked(&filter, "10.255.255.255"));
+        assert!(!blocked(&filter, "11.0.0.0"));
+        assert!(blocked(&filter, "0.0.0.0"));
+        assert!(blocked(&filter, "2001:db8:ffff::1"));
+        assert!(!blocked(&filter, "2001:db9::1"));
+
+        let everything = IpFilter::parse("0.0.0.0/0");
+        assert!(blocked(&everything, "255.255.255.255"));
+    }
+
+    #[test]
+    fn test_ranges_are_merged() {
+        let filter = IpFilter::parse("1.0.0.0-1.0.0.10\n1.0.0.5-1.0.0.20\n1.0.0.21\n9.9.9.9");
+        assert_eq!(filter.len(), 2);
+        assert!(blocked(&filter, "1.0.0.21"));
+        assert!(!blocked(&filter, "1.0.0.22"));
+    }
+
+    #[test]
+    fn test_malformed_lines_are_skipped() {
+        let filter = IpFilter::parse("garbage\n1.2.3.4/33\n5.5.5.5-1.1.1.1\n1.1.1.1-::1\n8.8.8.8");
+        assert_eq!(filter.skipped(), 4);
+        assert_eq!(filter.len(), 1);
+    }
+}
@@ -2,19 +2,56 @@
 
 #[cfg(feature = "client")]
 mod client;
+mod download;
+#[cfg(feature = "geoip")]
+pub mod geoip;
+pub mod hooks;
+pub mod ipfilter;
 pub mod meta_info;
+#[cfg(feature = "metrics")]
+pub mod metrics;
+pub mod net;
+pub mod peers;
 // pub mod parked_sources;
 pub mod sources;
+mod state;
+pub mod storage;
+#[cfg(any(test, feature = "testing"))]
+pub mod testing;
 
 pub use client::Client;
+pub use client::DownloadOptions;
+pub use client::InfoOptions;
 pub use client::PeerID;
-use colored::Colorize;
+pub use client::{ClientSummary, Progress, SessionStats};
+use colored::{ColoredString, Colorize};
 use futures::StreamExt;
-use meta_info::MetaInfo;
+use meta_info::{InfoHash, MetaInfo, MetaInfoBuilder, PathMapping, PieceLength};
+use net::UtpSocket;
+use peers::{
+    guess_client, Bitfield, EncryptedTransport, EncryptionPolicy, Handshake, UtpTransport,
+};
+use sources::{
+    AnnounceOptions, DiscoveredPeers, Tracker, TrackerError, TrackerList, TrackerOutcome,
+    TrackerStats, MAX_PARALLEL_REQUESTS, TIMEOUT_DURATION,
+};
+pub use state::Resumed;
 
-use clap::{Args, Subcommand};
-use meta_info::SortOrd;
-use std::path::PathBuf;
+use hooks::CompletionHook;
+
+use anyhow::{bail, Context};
+use clap::{Args, Subcommand, ValueEnum};
+use ipfilter::IpFilter;
+use meta_info::{PrintOptions, SizeFormat, SortOrd};
+use std::collections::HashMap;
+use std::net::{IpAddr, Ipv4Addr, Ipv6Addr, SocketAddr};
+use std::path::{Path, PathBuf};
+use std::sync::Arc;
+use std::time::Duration;
+use tokio::time::timeout;
+
+/// Version of the crate, as published on crates.io.
+pub const VERSION: &str = env!("CARGO_PKG_VERSION");
 
 /// Interact with torrent on the commandline. Install the [`zung`](https://crates.io/crates/zung)
 /// crate and run `zung torrent --help` to see what options are available
@@ -23,6 +60,26 @@ use std::path::PathBuf;
 pub struct TorrentArgs {
     #[command(subcommand)]
     command: TorrentCommands,
+
+    /// Print the announce urls as they are, with the passkeys of the private trackers and the
+    /// other secrets they contain. They are redacted by default.
+    #[arg(long, global = true)]
+    show_secrets: bool,
+
+    /// Record every network request and response to this file as newline-delimited JSON.
+    #[arg(long, global = true)]
+    capture: Option<PathBuf>,
+
+    /// Drop the peers whose address is in this blocklist, in the PeerGuardian (.p2p) or the CIDR
+    /// format.
+    #[arg(long, global = true)]
+    blocklist: Option<PathBuf>,
+
+    /// Expose the metrics of the network activity in the Prometheus text format on this address
+    /// while the command runs, for example `127.0.0.1:9184`.
+    #[cfg(feature = "metrics")]
+    #[arg(long, global = true)]
+    metrics_listen: Option<std::net::SocketAddr>,
 }
 
 #[derive(Clone, Subcommand, Debug)]
@@ -39,61 +96,1780 @@ enum TorrentCommands {
         #[arg(long, required = false)]
         with_files: bool,
 
+        /// What to sort the files by when printed with `--with-files`.
+        #[arg(long, value_enum, default_value_t = SortBy::Size, requires = "with_files")]
+        sort: SortBy,
+
+        /// Order of the sorted files when printed with `--with-files`.
+        #[arg(long, value_enum, default_value_t = Order::Asc, requires = "with_files")]
+        order: Order,
+
+        /// Only print the directories up to this depth when printed with `--with-files`. Deeper
+        /// directories are summarized.
+        #[arg(long, requires = "with_files")]
+        depth: Option<usize>,
+
+        /// Only print the N largest entries of each directory when printed with `--with-files`.
+        #[arg(long, requires = "with_files")]
+        top: Option<usize>,
+
+        /// Only print the directories when printed with `--with-files`.
+        #[arg(long, requires = "with_files")]
+        dirs_only: bool,
+
         /// Print the download sources contained within the torrent file.
         #[arg(long, required = false)]
         with_sources: bool,
+
+        /// Print statistics about the files contained in the torrent.
+        #[arg(long, required = false)]
+        with_stats: bool,
+
+        /// Print the dates in UTC instead of the local timezone.
+        #[arg(long, required = false)]
+        utc: bool,
+
+        /// Print the sizes in powers of 1000 (kB, MB, ...) instead of powers of 1024 (KiB, MiB,
+        /// ...).
+        #[arg(long, required = false, conflicts_with = "bytes")]
+        si: bool,
+
+        /// Print the sizes as the exact number of bytes.
+        #[arg(long, required = false)]
+        bytes: bool,
+
+        /// Print the general information as JSON. The sizes are in bytes and the dates in UTC.
+        #[arg(long, conflicts_with_all = ["with_files", "with_sources", "with_stats"])]
+        json: bool,
+
+        /// Fail on the oddities of the torrent file, such as an invalid creation date, instead of
+        /// printing them as warnings.
+        #[arg(long, required = false)]
+        strict: bool,
+    },
+
+    /// Announces to the trackers of the torrent and prints the peers they return, without
+    /// duplicates.
+    Peers {
+        /// Torrent File to process
+        #[arg(short, long, required = true)]
+        file: PathBuf,
+
+        /// Connect to every peer and exchange handshakes, to tell which client software the peers
+        /// run from their peer ids.
+        #[arg(long)]
+        probe: bool,
+
+        /// Whether the probed connections are encrypted (Message Stream Encryption).
+        #[arg(long, value_enum, default_value_t = Encryption::Prefer, requires = "probe")]
+        encryption: Encryption,
+
+        /// The transport of the probed connections.
+        #[arg(long, value_enum, default_value_t = Transport::Tcp, requires = "probe")]
+        transport: Transport,
+
+        /// Annotate the peers with their country and autonomous system from this MaxMind
+        /// database (.mmdb). Can be repeated to combine e.g. a country and an ASN database.
+        #[cfg(feature = "geoip")]
+        #[arg(long)]
+        geoip: Vec<PathBuf>,
     },
 
-    Test {
+    /// Announces to the trackers of the torrent and prints how each of them responded. The
+    /// reliability of the trackers is recorded, so that the most reliable trackers are contacted
+    /// first the next time (see `zung torrent trackers stats`).
+    Announce {
         /// Torrent File to process
         #[arg(short, long, required = true)]
         file: PathBuf,
+
+        /// Seconds given to each tracker to respond.
+        #[arg(long, default_value_t = TIMEOUT_DURATION.as_secs())]
+        timeout: u64,
+
+        /// Maximum number of trackers contacted at the same time.
+        #[arg(long, default_value_t = MAX_PARALLEL_REQUESTS)]
+        max_parallel: usize,
+
+        /// Only contact the trackers of this scheme. The timed out trackers are then not retried
+        /// with the other scheme.
+        #[arg(long, value_enum)]
+        scheme_filter: Option<Scheme>,
+
+        /// Do not retry the timed out trackers with the other scheme (`udp` <-> `http`).
+        #[arg(long)]
+        no_fallback: bool,
     },
+
+    /// Downloads the content of the torrent from the peers returned by its trackers, into the
+    /// download directory of the session.
+    Download {
+        /// Torrent File to process
+        #[arg(short, long, required = true)]
+        file: PathBuf,
+
+        /// Directory to store the content in instead of the download directory of the session.
+        #[arg(short, long)]
+        output: Option<PathBuf>,
+
+        /// Download the pieces of this file of the torrent first and in order, and serve the file
+        /// over HTTP while it downloads, e.g. to preview a video in a media player. The path is
+        /// relative to the torrent root and separated by `/`.
+        #[arg(long, value_name = "FILE-IN-TORRENT")]
+        stream: Option<String>,
+
+        /// Address the `--stream` file is served on.
+        #[arg(long, default_value = "127.0.0.1:8888")]
+        stream_addr: SocketAddr,
+
+        /// Start the download even if the disk does not have enough free space for the files.
+        #[arg(long)]
+        force: bool,
+
+        /// Command run by the shell, or url receiving a JSON POST, once the download completes.
+        /// The command gets the torrent in the `ZUNG_NAME`, `ZUNG_PATH` and `ZUNG_INFO_HASH`
+        /// environment variables. Can be repeated, and runs after the `on_complete` hooks of the
+        /// session state file.
+        #[arg(long, value_name = "CMD|URL")]
+        on_complete: Vec<CompletionHook>,
+    },
+
+    /// Creates a torrent file from a file or a directory.
+    Create {
+        /// The file or directory to create the torrent of
+        source: PathBuf,
+
+        /// Where to write the torrent file. Defaults to `<name>.torrent` in the current directory.
+        #[arg(short, long)]
+        output: Option<PathBuf>,
+
+        /// Name of the torrent. Defaults to the file name of the source.
+        #[arg(long)]
+        name: Option<String>,
+
+        /// Tracker to announce to. Can be repeated, each tracker is then in a tier of its own.
+        #[arg(short, long)]
+        announce: Vec<String>,
+
+        /// Piece length: `auto`, bytes, or KiB or MiB such as `512KiB`. `auto` picks the power of
+        /// two which cuts the content in 1000 to 2000 pieces, between 16 KiB and 16 MiB.
+        #[arg(long, default_value = "auto")]
+        piece_length: PieceLength,
+
+        /// Web seed (BEP 19) to download the content from over HTTP or FTP. Can be repeated.
+        #[arg(long)]
+        web_seed: Vec<String>,
+
+        /// Align the files to the pieces with padding files (BEP 47), so that no piece holds the
+        /// data of two files.
+        #[arg(long)]
+        padded: bool,
+
+        #[arg(long)]
+        comment: Option<String>,
+
+        /// Only get peers from the trackers of the torrent (BEP 27).
+        #[arg(long)]
+        private: bool,
+
+        /// Leave out the files and directories matching this `.gitignore` style pattern, e.g.
+        /// `*.tmp`, `/build/` or `!keep.tmp`. Can be repeated.
+        #[arg(long)]
+        exclude: Vec<String>,
+
+        /// Include the hidden files and directories, whose name starts with a `.`.
+        #[arg(long)]
+        include_hidden: bool,
+
+        /// Only print the files which would be in the torrent and its piece length, without
+        /// hashing the files.
+        #[arg(long)]
+        dry_run: bool,
+
+        /// Always create the same torrent file from the same inputs: sort the files bytewise and
+        /// leave out the creation date and the creating program.
+        #[arg(long)]
+        deterministic: bool,
+    },
+
+    /// Renames files inside a torrent and prints its new info hash, e.g. to cross-seed data which
+    /// was renamed locally.
+    ///
+    /// The pieces are kept as they are, since renaming files does not change their content,
+    /// unless `--data` is passed: the pieces and the file lengths are then hashed again from the
+    /// local files.
+    Rehash {
+        /// Torrent File to process
+        #[arg(short, long, required = true)]
+        file: PathBuf,
+
+        /// Rename the file or directory `old` to `new`, with `/` separated paths relative to the
+        /// torrent's directory. Can be repeated.
+        #[arg(short, long, value_name = "OLD=NEW", required = true)]
+        map: Vec<PathMapping>,
+
+        /// The directory containing the renamed data, to hash the pieces again from.
+        #[arg(long)]
+        data: Option<PathBuf>,
+
+        /// Where to write the new torrent file.
+        #[arg(short, long, required = true)]
+        output: PathBuf,
+    },
+
+    /// Prints the keys which were added, removed or changed between two torrent files, and
+    /// whether they share the same info hash (i.e. are the same torrent to the swarm).
+    Diff {
+        /// The old torrent file
+        a: PathBuf,
+
+        /// The new torrent file
+        b: PathBuf,
+    },
+
+    /// Prints or writes the manifest of the files of the torrent: their sizes, the pieces they
+    /// span and their expected hashes (the BEP 47 `sha1` of the file, or the hashes of the pieces
+    /// covering it), e.g. to verify downloaded data with external tools.
+    ExportFiles {
+        /// Torrent File to process
+        #[arg(short, long, required = true)]
+        file: PathBuf,
+
+        /// Format of the manifest. The CSV has a row per file, with the piece hashes separated by
+        /// spaces.
+        #[arg(long, value_enum, default_value_t = ManifestFormat::Json)]
+        format: ManifestFormat,
+
+        /// Where to write the manifest instead of printing it.
+        #[arg(short, long)]
+        output: Option<PathBuf>,
+    },
+
+    /// Adds torrent files to the session, detecting the files of the same torrent by their info
+    /// hash. In the `zung repl`, the torrents stay added for the next commands.
+    Add {
+        /// The torrent files
+        #[arg(required = true)]
+        files: Vec<PathBuf>,
+
+        /// Merge the trackers of a file of an already added torrent into it, instead of refusing
+        /// the file.
+        #[arg(long)]
+        merge_trackers: bool,
+    },
+
+    /// Restores the torrents and the download options saved by the previous runs, e.g. when the
+    /// `zung repl` exits or after `zung torrent add`. The torrent files which no longer exist or
+    /// were replaced by another torrent are dropped.
+    ResumeAll {
+        /// The state file to resume from instead of the default one.
+        #[arg(long)]
+        state: Option<PathBuf>,
+    },
+
+    /// Inspect the locally stored information about trackers.
+    Trackers {
+        #[command(subcommand)]
+        command: TrackerCommands,
+    },
+}
+
+#[derive(Clone, Copy, Debug, ValueEnum)]
+enum SortBy {
+    Name,
+    Size,
+}
+
+#[derive(Clone, Copy, Debug, ValueEnum)]
+enum Scheme {
+    Udp,
+    /// Both `http` and `https`.
+    Http,
+}
+
+impl Scheme {
+    fn matches(&self, tracker: &Tracker) -> bool {
+        matches!(
+            (self, tracker),
+            (Scheme::Udp, Tracker::Udp(_)) | (Scheme::Http, Tracker::Http(_))
+        )
+    }
+}
+
+#[derive(Clone, Copy, Debug, ValueEnum)]
+enum ManifestFormat {
+    Json,
+    Csv,
+}
+
+#[derive(Clone, Copy, Debug, ValueEnum)]
+enum Order {
+    Asc,
+    Desc,
+}
+
+impl From<Order> for SortOrd {
+    fn from(order: Order) -> Self {
+        match order {
+            Order::Asc => SortOrd::Ascending,
+            Order::Desc => SortOrd::Desending,
+        }
+    }
+}
+
+#[derive(Clone, Copy, Debug, ValueEnum)]
+enum Encryption {
+    /// Only the peers which encrypt the connection.
+    Require,
+    /// Encrypt if the peer supports it, plaintext otherwise.
+    Prefer,
+    /// Plaintext only.
+    Disable,
+}
+
+impl From<Encryption> for EncryptionPolicy {
+    fn from(encryption: Encryption) -> Self {
+        match encryption {
+            Encryption::Require => EncryptionPolicy::Require,
+            Encryption::Prefer => EncryptionPolicy::Prefer,
+            Encryption::Disable => EncryptionPolicy::Disable,
+        }
+    }
+}
+
+#[derive(Clone, Copy, Debug, ValueEnum)]
+enum Transport {
+    Tcp,
+    /// The Micro Transport Protocol over UDP, which yields to the other traffic of the network.
+    Utp,
+}
+
+#[derive(Clone, Subcommand, Debug)]
+enum TrackerCommands {
+    /// Prints the historical reliability of the trackers contacted so far.
+    Stats,
+}
+
+/// State kept between the torrent commands when they are run one after another, for example from
+/// the `zung repl`.
+#[derive(Debug, Default)]
+pub struct TorrentSession {
+    loaded: Option<(PathBuf, Client)>,
+    ip_filter: Option<Arc<IpFilter>>,
+    torrents: Vec<(PathBuf, Client)>,
+    options: DownloadOptions,
+
+    /// Bytes transferred by the torrents in the previous sessions, by info hash.
+    previous_totals: HashMap<String, (u64, u64)>,
+
+    /// Whether the saved state was resumed, i.e. the session holds every saved torrent.
+    resumed: bool,
+
+    /// Whether the options were resumed, loaded or set, instead of the default ones.
+    options_loaded: bool,
+
+    /// Whether the torrents or the options changed since the session started.
+    modified: bool,
+}
+
+/// What [`TorrentSession::add_torrent`] does with a torrent which has the same info hash as a
+/// torrent already added.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub enum OnDuplicate {
+    /// Returns an error naming the torrent already added.
+    #[default]
+    Refuse,
+
+    /// Adds the trackers of the new file to the torrent already added.
+    MergeTrackers,
+}
+
+/// The result of [`TorrentSession::add_torrent`].
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum AddedTorrent {
+    /// The torrent was not added before.
+    New,
+
+    /// The torrent was already added from the file `existing`, and its trackers were merged.
+    Merged {
+        existing: PathBuf,
+
+        /// Whether both files are identical, not only the same torrent.
+        same_file: bool,
+
+        /// The number of trackers added to the existing torrent.
+        trackers: usize,
+    },
+}
+
+impl TorrentSession {
+    /// Returns the [`Client`] of the torrent file. The client built by a previous command is
+    /// reused if it was built from the same file.
+    pub fn client(&mut self, file: impl Into<PathBuf>) -> anyhow::Result<&Client> {
+        let file = file.into();
+        let file = std::fs::canonicalize(&file).unwrap_or(file);
+
+        if !matches!(&self.loaded, Some((loaded, _)) if *loaded == file) {
+            let mut client = Client::new(&file)?;
+            client.set_ip_filter(self.ip_filter.clone());
+            self.loaded = Some((file, client));
+        }
+
+        Ok(&self.loaded.as_ref().expect("Loaded above").1)
+    }
+
+    /// Returns the [`Client`] loaded by the last command, if any.
+    pub fn loaded(&self) -> Option<&Client> {
+        self.loaded.as_ref().map(|(_, client)| client)
+    }
+
+    /// Adds the torrent file to the torrents of the session.
+    ///
+    /// A torrent is identified by its info hash, so the same torrent is detected even from files
+    /// with different names, trackers or comments. See [`OnDuplicate`] for what happens then.
+    pub fn add_torrent(
+        &mut self,
+        file: impl Into<PathBuf>,
+        on_duplicate: OnDuplicate,
+    ) -> anyhow::Result<AddedTorrent> {
+        let file = file.into();
+        let file = std::fs::canonicalize(&file).unwrap_or(file);
+        let mut client = Client::new(&file)?;
+
+        let Some((existing, added)) = self
+            .torrents
+            .iter_mut()
+            .find(|(_, added)| added.info_hash() == client.info_hash())
+        else {
+            client.set_ip_filter(self.ip_filter.clone());
+            self.torrents.push((file, client));
+            self.modified = true;
+            return Ok(AddedTorrent::New);
+        };
+
+        match on_duplicate {
+            OnDuplicate::Refuse => bail!(
+                "{} is already added as {} (info hash {})",
+                file.display(),
+                existing.display(),
+                client.info_hash().to_hex()
+            ),
+            OnDuplicate::MergeTrackers => {
+                let trackers = added.add_trackers(client.trackers());
+                self.modified |= trackers > 0;
+                Ok(AddedTorrent::Merged {
+                    existing: existing.clone(),
+                    same_file: added.file_checksum() == client.file_checksum(),
+                    trackers,
+                })
+            }
+        }
+    }
+
+    /// Returns the torrents added with [`TorrentSession::add_torrent`], with their file.
+    pub fn torrents(&self) -> impl Iterator<Item = (&Path, &Client)> {
+        self.torrents
+            .iter()
+            .map(|(file, client)| (file.as_path(), client))
+    }
+
+    /// Returns the torrent of the session with this info hash, with its file.
+    pub fn torrent(&self, info_hash: &InfoHash) -> Option<(&Path, &Client)> {
+        self.torrents()
+            .find(|(_, client)| client.info_hash() == info_hash)
+    }
+
+    /// Returns the [`DownloadOptions`] of the torrents of the session.
+    pub fn download_options(&self) -> &DownloadOptions {
+        &self.options
+    }
+
+    /// Sets the [`DownloadOptions`] of the torrents of the session. They are saved along with the
+    /// torrents.
+    pub fn set_download_options(&mut self, options: DownloadOptions) {
+        self.options = options;
+        self.options_loaded = true;
+        self.modified = true;
+    }
+
+    /// Loads the [`DownloadOptions`] saved at `path` by [`TorrentSession::save`], such as the
+    /// completion hooks written in the state file, without resuming the saved torrents. The
+    /// options the session already resumed or set are kept instead.
+    pub fn load_download_options(&mut self, path: &Path) -> anyhow::Result<&DownloadOptions> {
+        if !self.options_loaded {
+            self.options = state::SessionState::load(path)?.options.try_into()?;
+            self.options_loaded = true;
+        }
+        Ok(&self.options)
+    }
+
+    /// Runs the [`DownloadOptions::on_complete`] hooks for the torrent, once it finished
+    /// downloading with the `options`, e.g. the options of the session with the hooks of the
+    /// command. Every hook runs even if some fail, and the failures are returned together.
+    pub async fn torrent_completed(
+        &self,
+        client: &Client,
+        options: &DownloadOptions,
+    ) -> anyhow::Result<()> {
+        let torrent = hooks::CompletedTorrent::new(client, options);
+        let mut failures = Vec::new();
+        for hook in &options.on_complete {
+            if let Err(e) = hook.run(&torrent).await {
+                failures.push(format!("{e:#}"));
+            }
+        }
+        if !failures.is_empty() {
+            bail!(
+                "{} completion hooks failed: {}",
+                failures.len(),
+                failures.join("; ")
+            )
+        }
+        Ok(())
+    }
+
+    /// Returns the bytes downloaded and uploaded by the torrent over all the sessions.
+    pub fn transferred(&self, client: &Client) -> (u64, u64) {
+        let (downloaded, uploaded) = self
+            .previous_totals
+            .get(&client.info_hash().to_hex())
+            .copied()
+            .unwrap_or_default();
+        (
+            downloaded + client.stats().downloaded() as u64,
+            uploaded + client.stats().uploaded() as u64,
+        )
+    }
+
+    /// Returns the path of the state file in the data directory of the user, if there is one.
+    pub fn default_state_path() -> Option<PathBuf> {
+        state::SessionState::default_path()
+    }
+
+    /// Restores the torrents and the options saved by [`TorrentSession::save`] at `path`. Returns
+    /// what happened to each saved torrent, with its file.
+    ///
+    /// The torrent files are read again: the files which no longer exist, can not be read or were
+    /// replaced by another torrent are dropped from the session, without stopping the others from
+    /// being resumed. A missing state file resumes nothing.
+    pub fn resume(&mut self, path: &Path) -> anyhow::Result<Vec<(PathBuf, Resumed)>> {
+        let state = state::SessionState::load(path)?;
+        self.options = state.options.try_into()?;
+        self.options_loaded = true;
+        self.resumed = true;
+
+        let mut resumed = Vec::with_capacity(state.torrents.len());
+        for saved in state.torrents {
+            let result = if !saved.file.exists() {
+                Resumed::Missing
+            } else {
+                match Client::new(&saved.file) {
+                    Ok(client) => self.resume_torrent(&saved, client),
+                    Err(e) => Resumed::Unreadable {
+                        error: format!("{e:#}"),
+                    },
+                }
+            };
+            self.modified |=
+                !matches!(result, Resumed::Added { .. } | Resumed::AlreadyAdded { .. });
+            resumed.push((saved.file, result));
+        }
+        Ok(resumed)
+    }
+
+    // Adds the client of the saved torrent to the session, with what was saved about it.
+    fn resume_torrent(&mut self, saved: &state::SavedTorrent, mut client: Client) -> Resumed {
+        let info_hash = client.info_hash().to_hex();
+        if info_hash != saved.info_hash {
+            return Resumed::Replaced { info_hash };
+        }
+        if self.torrent(client.info_hash()).is_some() {
+            return Resumed::AlreadyAdded { info_hash };
+        }
+
+        client.set_announce_key(saved.announce_key);
+        client.add_trackers(saved.trackers.iter().map(String::as_str));
+        client.set_ip_filter(self.ip_filter.clone());
+        self.previous_totals
+            .insert(info_hash.clone(), (saved.downloaded, saved.uploaded));
+        self.torrents.push((saved.file.clone(), client));
+        Resumed::Added { info_hash }
+    }
+
+    /// Saves the torrents and the options of the session at `path`.
+    ///
+    /// Unless the session was resumed from `path` first, the saved torrents which are not in the
+    /// session are kept, so that adding a torrent does not forget the others.
+    pub fn save(&self, path: &Path) -> anyhow::Result<()> {
+        let mut torrents = Vec::new();
+        if !self.resumed {
+            torrents = state::SessionState::load(path)?.torrents;
+            torrents.retain(|saved| {
+                !self
+                    .torrents
+                    .iter()
+                    .any(|(_, client)| client.info_hash().to_hex() == saved.info_hash)
+            });
+        }
+        for (file, client) in &self.torrents {
+            let (downloaded, uploaded) = self.transferred(client);
+            torrents.push(state::SavedTorrent {
+                file: file.clone(),
+                info_hash: client.info_hash().to_hex(),
+                announce_key: client.announce_key(),
+                trackers: client.added_trackers().to_vec(),
+                downloaded,
+                uploaded,
+            });
+        }
+
+        let state = state::SessionState {
+            options: (&self.options).into(),
+            torrents,
+        };
+        state.save(path)
+    }
+
+    /// Saves the session at the [default path](TorrentSession::default_state_path) if its
+    /// torrents or options changed, e.g. when zung exits.
+    pub fn save_if_modified(&self) -> anyhow::Result<()> {
+        match Self::default_state_path() {
+            Some(path) if self.modified => self.save(&path),
+            _ => Ok(()),
+        }
+    }
+
+    /// Sets the [`IpFilter`] of the loaded client, of the added torrents and of the clients loaded
+    /// afterwards.
+    pub fn set_ip_filter(&mut self, filter: Option<IpFilter>) {
+        self.ip_filter = filter.map(Arc::new);
+        let loaded = self.loaded.iter_mut();
+        for (_, client) in loaded.chain(&mut self.torrents) {
+            client.set_ip_filter(self.ip_filter.clone());
+        }
+    }
 }
 
 impl TorrentArgs {
     pub async fn run(self) -> anyhow::Result<()> {
+        self.run_in(&mut TorrentSession::default()).await
+    }
+
+    /// Runs the command, reusing the state of the previous commands run in the same `session`.
+    pub async fn run_in(self, session: &mut TorrentSession) -> anyhow::Result<()> {
+        net::redact::show_secrets(self.show_secrets);
+
+        if let Some(path) = &self.capture {
+            net::capture::install(net::Capture::to_file(path)?);
+        }
+
+        if let Some(path) = &self.blocklist {
+            let filter = IpFilter::load(path)?;
+            // Printed to stderr so that it does not end up in the JSON outputs.
+            eprintln!(
+                "{} Blocklist: {} ranges ({} malformed lines skipped)",
+                "==>".green().bold(),
+                filter.len().to_string().bold().cyan(),
+                filter.skipped()
+            );
+            session.set_ip_filter(Some(filter));
+        }
+
+        #[cfg(feature = "metrics")]
+        let metrics_server = match self.metrics_listen {
+            Some(addr) => Some(metrics::serve(addr).await?),
+            None => None,
+        };
+
+        let result = self.run_command(session).await;
+
+        if self.capture.is_some() {
+            net::capture::uninstall();
+        }
+        #[cfg(feature = "metrics")]
+        if let Some(server) = metrics_server {
+            server.abort();
+        }
+        result
+    }
+
+    async fn run_command(&self, session: &mut TorrentSession) -> anyhow::Result<()> {
         // Run the commands
-        match self.command {
+        match self.command.clone() {
             TorrentCommands::Info {
                 file,
                 with_files,
+                sort,
+                order,
+                depth,
+                top,
+                dirs_only,
                 with_sources,
+                with_stats,
+                utc,
+                si,
+                bytes,
+                json,
+                strict,
             } => {
-                let torrent = Client::new(file)?;
+                let torrent = session.client(file)?;
+
+                let warnings = torrent.meta_info().warnings();
+                if strict && !warnings.is_empty() {
+                    let warnings: Vec<String> = warnings.iter().map(ToString::to_string).collect();
+                    bail!(
+                        "The torrent file is not strictly valid: {}",
+                        warnings.join(", ")
+                    );
+                }
+                for warning in warnings {
+                    eprintln!("{} {warning}", "warning:".yellow().bold());
+                }
+
+                if json {
+                    println!("{}", serde_json::to_string_pretty(&torrent.summary())?);
+                    return Ok(());
+                }
 
-                torrent.print_torrent_info();
+                let size_format = if bytes {
+                    SizeFormat::Bytes
+                } else if si {
+                    SizeFormat::Si
+                } else {
+                    SizeFormat::Binary
+                };
+                let info_options = InfoOptions { utc, size_format };
+
+                torrent.print_torrent_info_with(info_options);
 
                 if with_files {
-                    torrent.print_files_by_size(SortOrd::Ascending);
+                    let mut tree = torrent.file_tree();
+                    match sort {
+                        SortBy::Name => tree.sort_by_name(order.into()),
+                        SortBy::Size => tree.sort_by_size(order.into()),
+                    }
+
+                    println!("\n{} Files:", "==>".green().bold());
+                    tree.print_with(&PrintOptions {
+                        max_depth: depth,
+                        top,
+                        dirs_only,
+                        size_format,
+                    });
+                }
+
+                if with_stats {
+                    torrent.print_file_stats_with(info_options);
                 }
 
                 if with_sources {
                     torrent.print_download_sources();
                 }
             }
-            TorrentCommands::Test { file } => {
-                let torrent = Client::new(file)?;
-                let mut list = torrent
-                    .sources()
-                    .tracker_requests(torrent.info_hash().as_encoded(), torrent.peer_id())
-                    .unwrap();
+            TorrentCommands::Peers {
+                file,
+                probe,
+                encryption,
+                transport,
+                #[cfg(feature = "geoip")]
+                geoip,
+            } => {
+                let torrent = session.client(file)?;
+
+                // Looks up the location of a peer, if databases were provided.
+                #[cfg(feature = "geoip")]
+                let locate = {
+                    let geoip = match geoip.is_empty() {
+                        true => None,
+                        false => Some(geoip::GeoIp::open(&geoip)?),
+                    };
+                    move |ip| geoip.as_ref().map(|geoip| geoip.lookup(ip).to_string())
+                };
+                #[cfg(not(feature = "geoip"))]
+                let locate = |_| None;
+
+                let (mut peers, blocked) = discover_peers(torrent).await?;
+                if probe {
+                    probe_peers(torrent, &mut peers, encryption.into(), transport).await;
+                }
+
+                print_peers(&peers, locate);
+                println!(
+                    "\n{} unique peers ({blocked} blocked)",
+                    peers.len().to_string().bold().cyan(),
+                );
+            }
+            TorrentCommands::Announce {
+                file,
+                timeout,
+                max_parallel,
+                scheme_filter,
+                no_fallback,
+            } => {
+                let torrent = session.client(file)?;
+                let stats_path = TrackerStats::default_path();
+                let mut stats = match &stats_path {
+                    Some(path) => TrackerStats::load(path)?,
+                    None => TrackerStats::default(),
+                };
+
+                let sources = torrent.sources();
+                let mut trackers = sources
+                    .trackers()
+                    .context("The torrent does not contain any trackers")?
+                    .clone();
+                if let Some(scheme) = scheme_filter {
+                    trackers.retain(|tracker| scheme.matches(tracker));
+                }
+                if trackers.is_empty() {
+                    bail!("The torrent does not contain any trackers of the requested scheme")
+                }
+                trackers.sort_by_stats(&stats);
+                trackers.use_compact_preferences(&stats);
 
-                // Waits for ALL futures to complete
-                while let Some(result) = list.next().await {
+                // Nothing is downloaded by this command, so the whole torrent is left.
+                let have = Bitfield::new(torrent.meta_info().number_of_pieces());
+                let options = AnnounceOptions {
+                    timeout: Duration::from_secs(timeout),
+                    max_parallel,
+                    scheme_fallback: !no_fallback && scheme_filter.is_none(),
+                    ..torrent.announce_options(&have)
+                };
+                let mut announced = announce_all(torrent, &trackers, options).await;
+
+                for outcome in &announced {
+                    stats.record(outcome);
+                    if let (Some(tracker), Ok(request)) = (outcome.worked_with(), &outcome.result) {
+                        stats.record_compact(tracker, request.is_compact());
+                    }
+                }
+                if let Some(path) = stats_path {
+                    stats.save(path)?;
+                }
+
+                // The trackers that responded first, the fastest ones at the top.
+                announced.sort_by_key(|outcome| (outcome.result.is_err(), outcome.elapsed));
+                print_announced(&announced);
+            }
+            TorrentCommands::Download {
+                file,
+                output,
+                stream,
+                stream_addr,
+                force,
+                on_complete,
+            } => {
+                let mut options = match TorrentSession::default_state_path() {
+                    Some(path) => session.load_download_options(&path)?.clone(),
+                    None => session.download_options().clone(),
+                };
+                if let Some(output) = output {
+                    options.download_dir = output;
+                }
+                options.force = force;
+                options.on_complete.extend(on_complete);
+                session.client(file)?;
+                let torrent = session.loaded().expect("Loaded above");
+                // The files are allocated as their pieces arrive, which is too late to find out.
+                torrent.check_space(&options)?;
+                let storage = Arc::new(torrent.storage(&options)?);
+                let mut scheduler = torrent.block_scheduler(&options);
+
+                let streamed = match &stream {
+                    Some(path) => {
+                        let pieces = torrent
+                            .pieces_for_file(path)
+                            .with_context(|| format!("The torrent has no file {path}"))?;
+                        scheduler.prioritize(pieces.start as u32..pieces.end as u32);
+                        let path = path.trim_matches('/');
+                        torrent
+                            .file_spans()
+                            .iter()
+                            .find(|span| span.path == path)
+                            .cloned()
+                    }
+                    None => None,
+                };
+
+                let download = download::Download::new(torrent, Arc::clone(&storage), scheduler);
+                let server = match streamed {
+                    Some(span) => {
+                        let server =
+                            storage::serve_file(stream_addr, storage, span, download.verified())
+                                .await?;
+                        println!(
+                            "{} Streaming {} on {}",
+                            "==>".green().bold(),
+                            stream.as_deref().unwrap_or_default().bold(),
+                            format!("http://{stream_addr}").cyan()
+                        );
+                        Some(server)
+                    }
+                    None => None,
+                };
+
+                let (peers, blocked) = discover_peers(torrent).await?;
+                println!(
+                    "{} Downloading from {} peers ({blocked} blocked)",
+                    "==>".green().bold(),
+                    peers.len().to_string().bold().cyan()
+                );
+                let mut result = download
+                    .run(peers.addrs().collect(), options.max_peers)
+                    .await;
+                if result.is_ok() {
+                    println!(
+                        "{} Downloaded {} into {}",
+                        "==>".green().bold(),
+                        torrent.meta_info().info().name().bold(),
+                        options.download_dir.display()
+                    );
+                    result = session.torrent_completed(torrent, &options).await;
+                }
+
+                if let Some(server) = server {
+                    if result.is_ok() {
+                        println!(
+                            "{} Downloaded, still streaming until Ctrl-C",
+                            "==>".green().bold()
+                        );
+                        tokio::signal::ctrl_c().await?;
+                    }
+                    server.abort();
+                }
+                result?;
+            }
+            TorrentCommands::Create {
+                source,
+                output,
+                name,
+                announce,
+                piece_length,
+                web_seed,
+                padded,
+                comment,
+                private,
+                exclude,
+                include_hidden,
+                dry_run,
+                deterministic,
+            } => {
+                let mut builder = MetaInfoBuilder::new(source)
+                    .piece_length(piece_length)
+                    .padded(padded)
+                    .private(private)
+                    .include_hidden(include_hidden)
+                    .deterministic(deterministic);
+                for url in announce {
+                    builder = builder.announce(url);
+                }
+                for url in web_seed {
+                    builder = builder.web_seed(url);
+                }
+                for pattern in exclude {
+                    builder = builder.exclude(pattern);
+                }
+                if let Some(name) = name {
+                    builder = builder.name(name);
+                }
+                if let Some(comment) = comment {
+                    builder = builder.comment(comment);
+                }
+
+                let files = builder.files()?;
+                let total: u64 = files.iter().map(|file| file.length).sum();
+                let piece_length = builder.piece_length_for(&files);
+                println!(
+                    "{} {} files ({}) in {} pieces of {}",
+                    if dry_run { "Would hash" } else { "Hashing" },
+                    files.len(),
+                    SizeFormat::Binary.format(total as usize),
+                    builder.number_of_pieces_for(&files),
+                    SizeFormat::Binary.format(piece_length)
+                );
+
+                if dry_run {
+                    for file in &files {
+                        let path = match file.path.is_empty() {
+                            true => file.source.display().to_string(),
+                            false => file.path.join("/"),
+                        };
+                        println!(
+                            "{:>12}  {path}",
+                            SizeFormat::Binary.format(file.length as usize)
+                        );
+                    }
+                    return Ok(());
+                }
+
+                let torrent = builder.build_from(files)?;
+                let output = match output {
+                    Some(output) => output,
+                    None => {
+                        let meta_info = MetaInfo::from_bytes(&torrent.bytes)?;
+                        PathBuf::from(format!("{}.torrent", meta_info.info().name))
+                    }
+                };
+                std::fs::write(&output, &torrent.bytes)
+                    .with_context(|| format!("Unable to write {}", output.display()))?;
+
+                println!(
+                    "{} {} ({} pieces)",
+                    "Created".green().bold(),
+                    output.display(),
+                    torrent.number_of_pieces
+                );
+                println!("{} {}", "Info hash:".bold(), torrent.info_hash);
+            }
+            TorrentCommands::Rehash {
+                file,
+                map,
+                data,
+                output,
+            } => {
+                let old_hash = Client::new(&file)?.info_hash().to_hex();
+                let mut torrent = zung_parsers::bencode::parse(&std::fs::read(&file)?)?;
+
+                let renamed = meta_info::rename_files(&mut torrent, &map)?;
+                println!("Renamed {renamed} files");
+                match data {
+                    Some(data) => {
+                        if meta_info::rehash_pieces(&mut torrent, &data)? {
+                            println!(
+                                "Pieces: hashed again from {} (the content changed)",
+                                data.display()
+                            );
+                        } else {
+                            println!("Pieces: unchanged (the content is the same)");
+                        }
+                    }
+                    None => println!("Pieces: reused"),
+                }
+
+                std::fs::write(&output, zung_parsers::bencode::to_bytes(&torrent)?)
+                    .with_context(|| format!("Unable to write {}", output.display()))?;
+                let new_hash = Client::new(&output)?.info_hash().to_hex();
+                println!(
+                    "{} {}",
+                    "Info hash:".bold(),
+                    format!("{old_hash} -> {new_hash}").yellow()
+                );
+            }
+            TorrentCommands::Diff { a, b } => {
+                let old_hash = Client::new(&a)?.info_hash().to_hex();
+                let new_hash = Client::new(&b)?.info_hash().to_hex();
+                if old_hash == new_hash {
+                    println!("{} {old_hash} (same torrent)", "Info hash:".bold());
+                } else {
+                    println!(
+                        "{} {}",
+                        "Info hash:".bold(),
+                        format!("{old_hash} -> {new_hash}").yellow()
+                    );
+                }
+
+                let old = zung_parsers::bencode::parse(&std::fs::read(&a)?)?;
+                let new = zung_parsers::bencode::parse(&std::fs::read(&b)?)?;
+                print_diff(&zung_parsers::bencode::diff(&old, &new));
+            }
+            TorrentCommands::ExportFiles {
+                file,
+                format,
+                output,
+            } => {
+                let manifest = session.client(file)?.manifest();
+                let manifest = match format {
+                    ManifestFormat::Json => serde_json::to_string_pretty(&manifest)? + "\n",
+                    ManifestFormat::Csv => manifest.to_csv(),
+                };
+                match output {
+                    Some(output) => std::fs::write(&output, manifest)
+                        .with_context(|| format!("Unable to write file: {}", output.display()))?,
+                    None => print!("{manifest}"),
+                }
+            }
+            TorrentCommands::Add {
+                files,
+                merge_trackers,
+            } => {
+                let on_duplicate = if merge_trackers {
+                    OnDuplicate::MergeTrackers
+                } else {
+                    OnDuplicate::Refuse
+                };
+                for file in files {
+                    match session.add_torrent(&file, on_duplicate)? {
+                        AddedTorrent::New => println!(
+                            "{} Added {}",
+                            "==>".green().bold(),
+                            file.display().to_string().bold()
+                        ),
+                        AddedTorrent::Merged {
+                            existing,
+                            same_file,
+                            trackers,
+                        } => println!(
+                            "{} {} is already added as {}{}: merged {} trackers",
+                            "==>".yellow().bold(),
+                            file.display().to_string().bold(),
+                            existing.display(),
+                            if same_file { " (identical file)" } else { "" },
+                            trackers.to_string().cyan()
+                        ),
+                    }
+                }
+            }
+            TorrentCommands::ResumeAll { state } => {
+                let Some(path) = state.or_else(TorrentSession::default_state_path) else {
+                    bail!("No data directory to read the session state from")
+                };
+                let resumed = session.resume(&path)?;
+                println!(
+                    "{} Resumed {} torrents from {}",
+                    "==>".green().bold(),
+                    resumed.len().to_string().bold().cyan(),
+                    path.display()
+                );
+                let size = |bytes: u64| SizeFormat::Binary.format(bytes as usize);
+                for (file, result) in resumed {
+                    let file = file.display().to_string();
                     match result {
-                        Ok(a) => match a {
-                            Ok(a) => println!("Connected! {}", a.to_url().unwrap().green()),
-                            Err(e) => println!("{}", e.to_string().red()),
-                        },
-                        Err(e) => {
-                            println!("{}", e.to_string().red())
+                        Resumed::Added { info_hash } | Resumed::AlreadyAdded { info_hash } => {
+                            let added = InfoHash::from_hex(&info_hash)
+                                .ok()
+                                .and_then(|info_hash| session.torrent(&info_hash));
+                            let Some((added, client)) = added else {
+                                println!("\t{}", file.bold());
+                                continue;
+                            };
+                            let (downloaded, uploaded) = session.transferred(client);
+                            let added = added.display().to_string();
+                            println!(
+                                "\t{} ({} downloaded, {} uploaded){}",
+                                file.bold(),
+                                size(downloaded),
+                                size(uploaded),
+                                match added == file {
+                                    true => String::new(),
+                                    false => format!(", already added as {added}"),
+                                }
+                            );
+                        }
+                        Resumed::Missing => {
+                            println!("\t{} {}", file.bold(), "missing, dropped".yellow())
+                        }
+                        Resumed::Unreadable { error } => {
+                            println!("\t{} {}", file.bold(), format!("{error}, dropped").red())
                         }
+                        Resumed::Replaced { info_hash } => println!(
+                            "\t{} {}",
+                            file.bold(),
+                            format!("replaced by {info_hash}, dropped").yellow()
+                        ),
                     }
                 }
             }
+            TorrentCommands::Trackers { command } => match command {
+                TrackerCommands::Stats => {
+                    let stats = match TrackerStats::default_path() {
+                        Some(path) => TrackerStats::load(path)?,
+                        None => TrackerStats::default(),
+                    };
+                    print_tracker_stats(&stats);
+                }
+            },
         }
 
         Ok(())
     }
 }
+
+/// Maximum number of peers probed at the same time by `zung torrent peers --probe`.
+const MAX_PARALLEL_PROBES: usize = 32;
+
+/// Time given to a peer to connect and answer the handshake.
+const PROBE_TIMEOUT: Duration = Duration::from_secs(5);
+
+/// Announces to the trackers, with at most [`AnnounceOptions::max_parallel`] announces at the
+/// same time and each of them given [`AnnounceOptions::timeout`] to complete. See
+/// [`TrackerList::announce_with`].
+async fn announce_all(
+    torrent: &Client,
+    trackers: &TrackerList,
+    options: AnnounceOptions,
+) -> Vec<TrackerOutcome> {
+    trackers
+        .announce_with(torrent.info_hash().as_encoded(), torrent.peer_id(), options)
+        .filter_map(|outcome| async move {
+            outcome
+                .inspect_err(|e| eprintln!("{}", e.to_string().red()))
+                .ok()
+        })
+        .collect()
+        .await
+}
+
+/// Announces to all the trackers of the torrent and collects the peers they return, without the
+/// ones blocked by the [`IpFilter`]. Returns the peers along with the number of blocked peers.
+///
+/// The trackers which fail are reported on stderr.
+async fn discover_peers(torrent: &Client) -> anyhow::Result<(DiscoveredPeers, usize)> {
+    let trackers = torrent
+        .sources()
+        .trackers()
+        .context("The torrent does not contain any trackers")?
+        .clone();
+
+    let mut peers = DiscoveredPeers::new();
+    let mut blocked = 0;
+    // Nothing is downloaded to find the peers, so the whole torrent is left.
+    let have = Bitfield::new(torrent.meta_info().number_of_pieces());
+    let options = torrent.announce_options(&have);
+    for outcome in announce_all(torrent, &trackers, options).await {
+        let tracker = outcome.fallback.as_ref().unwrap_or(&outcome.tracker);
+        if let Some(mut response) = outcome.response {
+            blocked += torrent.filter_peers(&mut response.peers);
+            peers.add(tracker.url(), response.peers);
+        } else if let Err(e) = &outcome.result {
+            eprintln!("{} {}", tracker.to_string().bold(), format!("{e:#}").red())
+        }
+    }
+    Ok((peers, blocked))
+}
+
+/// Exchanges handshakes with the peers to learn their peer ids, over the transport and
+/// encrypting the connections according to the policy. The peers which can not be reached in
+/// time are left as they are.
+async fn probe_peers(
+    torrent: &Client,
+    peers: &mut DiscoveredPeers,
+    encryption: EncryptionPolicy,
+    transport: Transport,
+) {
+    let handshake = Handshake::new(torrent.info_hash().as_bytes(), torrent.peer_id().as_bytes());
+
+    // A uTP socket per address family, shared by the probes.
+    let utp = match transport {
+        Transport::Tcp => None,
+        Transport::Utp => Some((
+            UtpSocket::bind((Ipv4Addr::UNSPECIFIED, 0)).await.ok(),
+            UtpSocket::bind((Ipv6Addr::UNSPECIFIED, 0)).await.ok(),
+        )),
+    };
+    let utp = &utp;
+
+    let probes: Vec<_> = futures::stream::iter(peers.addrs().collect::<Vec<_>>())
+        .map(|addr| async move {
+            let probe = async {
+                let theirs = match utp {
+                    None => {
+                        EncryptedTransport::connect_with_handshake(addr, &handshake, encryption)
+                            .await?
+                            .1
+                    }
+                    Some((v4, v6)) => {
+                        let socket = if addr.is_ipv6() { v6 } else { v4 };
+                        let socket = socket.as_ref().context("Unable to bind a uTP socket")?;
+                        UtpTransport::connect_with_handshake(socket, addr, &handshake, encryption)
+                            .await?
+                            .1
+                    }
+                };
+                anyhow::Ok(theirs)
+            };
+            match timeout(PROBE_TIMEOUT, probe).await {
+                Ok(Ok(theirs)) => Some((addr, theirs.peer_id)),
+                _ => None,
+            }
+        })
+        .buffer_unordered(MAX_PARALLEL_PROBES)
+        .collect()
+        .await;
+
+    for (addr, peer_id) in probes.into_iter().flatten() {
+        peers.set_peer_id(addr, peer_id);
+    }
+}
+
+/// Prints the peers as a table. The location column is only printed if `locate` knows about any
+/// peer.
+fn print_peers(peers: &DiscoveredPeers, locate: impl Fn(IpAddr) -> Option<String>) {
+    if peers.is_empty() {
+        println!("{}", "No peers returned by the trackers.".italic().dimmed());
+        return;
+    }
+
+    let mut header = vec!["Address", "Client", "Trackers"];
+    let rows: Vec<Vec<ColoredString>> = peers
+        .iter()
+        .map(|peer| {
+            let client = match &peer.peer_id {
+                Some(peer_id) => guess_client(peer_id).unwrap_or_else(|| "unknown".into()),
+                None => "-".into(),
+            };
+            let trackers: Vec<_> = peer
+                .trackers
+                .iter()
+                .map(|url| Tracker::new(url).host().unwrap_or(url).to_string())
+                .collect();
+            let mut row = vec![peer.addr.to_string(), client, trackers.join(", ")];
+            if let Some(location) = locate(peer.addr.ip()) {
+                row.insert(2, location);
+            }
+            row.into_iter().map(ColoredString::from).collect()
+        })
+        .collect();
+    if rows[0].len() == 4 {
+        header.insert(2, "Location");
+    }
+
+    print_table(&header, &rows);
+}
+
+/// Prints each entry on its own line, colored by the kind of change.
+fn print_diff(entries: &[zung_parsers::bencode::DiffEntry]) {
+    use zung_parsers::bencode::Change;
+
+    if entries.is_empty() {
+        println!("No differences");
+        return;
+    }
+    for entry in entries {
+        let line = entry.to_string();
+        match entry.change {
+            Change::Added(_) => println!("{}", line.green()),
+            Change::Removed(_) => println!("{}", line.red()),
+            Change::Changed { .. } => println!("{}", line.yellow()),
+        }
+    }
+}
+
+/// Prints how each tracker responded to the announce as a table.
+fn print_announced(announced: &[TrackerOutcome]) {
+    let rows: Vec<Vec<ColoredString>> = announced
+        .iter()
+        .map(|outcome| {
+            let count = |n: Option<i64>| n.map_or("-".into(), |n| n.to_string()).normal();
+            let response = &outcome.response;
+            let (status, details) = match (&outcome.result, response) {
+                (Ok(_), _) => (
+                    "ok".green(),
+                    response
+                        .as_ref()
+                        .and_then(|response| response.warning_message.clone())
+                        .unwrap_or_default()
+                        .yellow(),
+                ),
+                (Err(e), _) => match e.downcast_ref::<TrackerError>() {
+                    Some(TrackerError::Failure(reason)) => {
+                        ("failed".red(), format!("Tracker failure: {reason}").red())
+                    }
+                    // The outer errors repeat the whole announce url.
+                    None => ("failed".red(), e.root_cause().to_string().red()),
+                },
+            };
+            let fallback = match &outcome.fallback {
+                Some(fallback) => format!(" (timed out, used {fallback})"),
+                None => String::new(),
+            };
+
+            vec![
+                format!("{}{fallback}", outcome.tracker).bold(),
+                status,
+                format!("{}ms", outcome.elapsed.as_millis()).normal(),
+                count(response.as_ref().and_then(|r| r.complete)),
+                count(response.as_ref().and_then(|r| r.incomplete)),
+                count(response.as_ref().map(|r| r.peers.len() as i64)),
+                details,
+            ]
+        })
+        .collect();
+
+    print_table(
+        &[
+            "Tracker", "Status", "Time", "Seeders", "Leechers", "Peers", "Details",
+        ],
+        &rows,
+    );
+
+    let responded = announced.iter().filter(|a| a.response.is_some()).count();
+    println!(
+        "\n{} of {} trackers responded",
+        responded.to_string().bold().cyan(),
+        announced.len()
+    );
+}
+
+/// Prints the rows as columns aligned on the widest cell, under a bold header.
+fn print_table(header: &[&str], rows: &[Vec<ColoredString>]) {
+    let header: Vec<_> = header.iter().map(|title| title.bold()).collect();
+    let rows: Vec<_> = std::iter::once(&header).chain(rows).collect();
+
+    // The widths are counted on the text as the escape codes of the colors take no space.
+    let widths: Vec<_> = (0..header.len())
+        .map(|i| {
+            rows.iter()
+                .map(|row| row[i].chars().count())
+                .max()
+                .unwrap_or(0)
+        })
+        .collect();
+    for row in rows {
+        let cells: Vec<_> = row
+            .iter()
+            .zip(&widths)
+            .map(|(cell, width)| format!("{cell}{}", " ".repeat(width - cell.chars().count())))
+            .collect();
+        println!("{}", cells.join("  ").trim_end());
+    }
+}
+
+fn print_tracker_stats(stats: &TrackerStats) {
+    if stats.is_empty() {
+        println!(
+            "{}",
+            "No tracker stats recorded yet. Run `zung torrent announce` to record some."
+                .italic()
+                .dimmed()
+        );
+        return;
+    }
+
+    println!("\n{} Tracker Stats: ", "==>".green().bold());
+    for (i, (host, record)) in stats.iter().enumerate() {
+        println!("\t{}. {}", i + 1, host.bold().cyan());
+        println!(
+            "\t\tSuccesses: {} Failures: {} ({:.0}%)",
+            record.successes.to_string().green(),
+            record.failures.to_string().red(),
+            record.success_rate() * 100.0
+        );
+        if let Some(rtt) = record.average_rtt() {
+            println!("\t\tAverage RTT: {}ms", rtt.as_millis());
+        }
+        if let Some(time) = record.last_failure_time() {
+            println!(
+                "\t\tLast Failure: {} {}",
+                time.format("%Y-%m-%d %H:%M:%S UTC"),
+                record
+                    .last_failure_reason
+                    .as_deref()
+                    .unwrap_or_default()
+                    .red()
+            );
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::testing::{MockHttpTracker, ScriptedPeer, TorrentBuilder};
+
+    #[tokio::test]
+    async fn test_discover_and_probe_peers() {
+        let path = TorrentBuilder::single_file("discovered.bin", 10)
+            .write_to(std::env::temp_dir())
+            .unwrap();
+        let client = Client::new(&path).unwrap();
+        std::fs::remove_file(&path).unwrap();
+
+        let peer = ScriptedPeer::start(
+            client.info_hash().as_bytes(),
+            *b"-qB4630-abcdefghijkl",
+            vec![],
+        )
+        .await
+        .unwrap();
+        let std::net::IpAddr::V4(ip) = peer.addr().ip() else {
+            unreachable!("The peer listens on 127.0.0.1")
+        };
+
+        let response = |peers: &[([u8; 4], u16)]| {
+            let mut body = format!("d8:intervali1800e5:peers{}:", peers.len() * 6).into_bytes();
+            for (ip, port) in peers {
+                body.extend_from_slice(ip);
+                body.extend_from_slice(&port.to_be_bytes());
+            }
+            body.push(b'e');
+            body
+        };
+
+        // Both trackers return the scripted peer, the second one also returns a blocked peer.
+        let scripted = (ip.octets(), peer.addr().port());
+        let first = MockHttpTracker::start(response(&[scripted])).await.unwrap();
+        let second = MockHttpTracker::start(response(&[([10, 0, 0, 1], 6881), scripted]))
+            .await
+            .unwrap();
+
+        let path = TorrentBuilder::single_file("discovered.bin", 10)
+            .announce_list(&[&[&first.url(), &second.url()]])
+            .write_to(std::env::temp_dir())
+            .unwrap();
+        let mut client = Client::new(&path).unwrap();
+        std::fs::remove_file(path).unwrap();
+        client.set_ip_filter(Some(IpFilter::parse("10.0.0.0/8").into()));
+
+        let (mut peers, blocked) = discover_peers(&client).await.unwrap();
+        assert_eq!(blocked, 1);
+        assert_eq!(peers.len(), 1);
+        assert_eq!(peers.iter().next().unwrap().trackers.len(), 2);
+
+        probe_peers(
+            &client,
+            &mut peers,
+            EncryptionPolicy::Disable,
+            Transport::Tcp,
+        )
+        .await;
+        let peer_id = peers.get(&peer.addr()).unwrap().peer_id.unwrap();
+        assert_eq!(guess_client(&peer_id).unwrap(), "qBittorrent 4.6.3");
+        peer.finish().await.unwrap();
+    }
+
+    #[tokio::test]
+    async fn test_announce_all() {
+        let ok = MockHttpTracker::start("d8:completei3e10:incompletei1e5:peers0:e")
+            .await
+            .unwrap();
+        let failing = MockHttpTracker::start("d14:failure reason6:bannede")
+            .await
+            .unwrap();
+        let path = TorrentBuilder::single_file("announced.bin", 10)
+            .announce_list(&[&[&ok.url(), &failing.url()]])
+            .write_to(std::env::temp_dir())
+            .unwrap();
+        let client = Client::new(&path).unwrap();
+        std::fs::remove_file(path).unwrap();
+
+        let mut trackers = client.sources().trackers().unwrap().clone();
+        let have = Bitfield::new(client.meta_info().number_of_pieces());
+        let announced = announce_all(&client, &trackers, client.announce_options(&have)).await;
+        assert_eq!(announced.len(), 2);
+        for outcome in &announced {
+            let response = &outcome.response;
+            if outcome.tracker.url() == ok.url() {
+                assert!(outcome.result.is_ok());
+                assert_eq!(response.as_ref().unwrap().complete, Some(3));
+            } else {
+                let err = outcome.result.as_ref().unwrap_err();
+                assert_eq!(
+                    err.downcast_ref::<TrackerError>(),
+                    Some(&TrackerError::Failure("banned".into()))
+                );
+                assert!(response.is_none());
+            }
+        }
+
+        trackers.retain(|tracker| Scheme::Udp.matches(tracker));
+        assert!(trackers.is_empty());
+    }
+
+    #[test]
+    fn test_add_duplicate_torrents() {
+        let dir = std::env::temp_dir().join(format!("zung-add-{}", std::process::id()));
+        std::fs::create_dir_all(dir.join("other")).unwrap();
+        let torrent = TorrentBuilder::single_file("added.bin", 10);
+        let first = torrent
+            .clone()
+            .announce("http://a.example/announce")
+            .write_to(&dir)
+            .unwrap();
+        // The same torrent, from a file with another name and other trackers.
+        let second = dir.join("renamed.torrent");
+        let bytes = torrent
+            .announce_list(&[&["http://a.example/announce"], &["udp://b.example:80"]])
+            .build();
+        std::fs::write(&second, bytes).unwrap();
+
+        let mut session = TorrentSession::default();
+        assert_eq!(
+            session.add_torrent(&first, OnDuplicate::Refuse).unwrap(),
+            AddedTorrent::New
+        );
+        let error = session
+            .add_torrent(&second, OnDuplicate::Refuse)
+            .unwrap_err();
+        assert!(error.to_string().contains("added.bin.torrent"));
+
+        let merged = session
+            .add_torrent(&second, OnDuplicate::MergeTrackers)
+            .unwrap();
+        assert_eq!(
+            merged,
+            AddedTorrent::Merged {
+                existing: std::fs::canonicalize(&first).unwrap(),
+                same_file: false,
+                trackers: 1,
+            }
+        );
+        let (_, client) = session.torrents().next().unwrap();
+        assert_eq!(
+            client.trackers().collect::<Vec<_>>(),
+            ["http://a.example/announce", "udp://b.example:80"]
+        );
+        assert_eq!(client.sources().trackers().unwrap().len(), 2);
+
+        let merged = session
+            .add_torrent(&first, OnDuplicate::MergeTrackers)
+            .unwrap();
+        assert!(matches!(
+            merged,
+            AddedTorrent::Merged {
+                same_file: true,
+                trackers: 0,
+                ..
+            }
+        ));
+        assert_eq!(session.torrents().count(), 1);
+    }
+
+    #[test]
+    fn test_save_and_resume() {
+        let dir = std::env::temp_dir().join(format!("zung-resume-{}", std::process::id()));
+        std::fs::create_dir_all(&dir).unwrap();
+        let state = dir.join("session.json");
+        let kept = TorrentBuilder::single_file("kept.bin", 10)
+            .write_to(&dir)
+            .unwrap();
+        let replaced = TorrentBuilder::single_file("replaced.bin", 10)
+            .write_to(&dir)
+            .unwrap();
+        let missing = TorrentBuilder::single_file("missing.bin", 10)
+            .write_to(&dir)
+            .unwrap();
+
+        let mut session = TorrentSession::default();
+        session.add_torrent(&kept, OnDuplicate::Refuse).unwrap();
+        session.add_torrent(&replaced, OnDuplicate::Refuse).unwrap();
+        let (_, client) = session.torrents().next().unwrap();
+        client.stats().add_uploaded(100);
+        session.save(&state).unwrap();
+
+        // Another run adds a torrent without forgetting the saved ones.
+        let mut session = TorrentSession::default();
+        session.add_torrent(&missing, OnDuplicate::Refuse).unwrap();
+        session.save(&state).unwrap();
+
+        std::fs::remove_file(&missing).unwrap();
+        let other = TorrentBuilder::single_file("other.bin", 20).build();
+        std::fs::write(&replaced, other).unwrap();
+
+        let mut session = TorrentSession::default();
+        let resumed = session.resume(&state).unwrap();
+        let results: Vec<_> = resumed.iter().map(|(_, result)| result.clone()).collect();
+        assert!(matches!(
+            results.as_slice(),
+            [
+                Resumed::Added { .. },
+                Resumed::Replaced { .. },
+                Resumed::Missing
+            ]
+        ));
+        let (_, client) = session.torrents().next().unwrap();
+        assert_eq!(session.transferred(client), (0, 100));
+
+        session.save(&state).unwrap();
+        let mut session = TorrentSession::default();
+        assert_eq!(session.resume(&state).unwrap().len(), 1);
+        let missing = TorrentSession::default()
+            .resume(&dir.join("none.json"))
+            .unwrap();
+        assert!(missing.is_empty());
+    }
+
+    #[cfg(unix)]
+    #[tokio::test]
+    async fn test_completion_hooks() {
+        let dir = std::env::temp_dir().join(format!("zung-completed-{}", std::process::id()));
+        std::fs::create_dir_all(&dir).unwrap();
+        let state = dir.join("session.json");
+
+        let mut saved = TorrentSession::default();
+        saved.set_download_options(DownloadOptions {
+            download_dir: dir.clone(),
+            on_complete: vec!["true".parse().unwrap()],
+            ..Default::default()
+        });
+        saved.save(&state).unwrap();
+
+        // The hooks of the state file run along with the ones of the command.
+        let mut session = TorrentSession::default();
+        let mut options = session.load_download_options(&state).unwrap().clone();
+        let marker = dir.join("completed");
+        options.on_complete.push(CompletionHook::Command(format!(
+            "echo \"$ZUNG_NAME\" > '{}'",
+            marker.display()
+        )));
+        let file = TorrentBuilder::single_file("done.bin", 10)
+            .write_to(&dir)
+            .unwrap();
+        let client = Client::new(file).unwrap();
+        session.torrent_completed(&client, &options).await.unwrap();
+        assert_eq!(options.on_complete.len(), 2);
+        assert_eq!(std::fs::read_to_string(&marker).unwrap().trim(), "done.bin");
+
+        // The options set on the session are not replaced.
+        session.set_download_options(DownloadOptions::default());
+        let options = session.load_download_options(&state).unwrap();
+        assert!(options.on_complete.is_empty());
+        std::fs::remove_dir_all(&dir).unwrap();
+    }
+
+    #[tokio::test]
+    async fn test_resume_all_goes_on() {
+        let dir = std::env::temp_dir().join(format!("zung-resume-all-{}", std::process::id()));
+        std::fs::create_dir_all(&dir).unwrap();
+        let state = dir.join("session.json");
+        let torrent = TorrentBuilder::single_file("twice.bin", 10);
+        let saved = torrent.clone().write_to(&dir).unwrap();
+        let corrupt = TorrentBuilder::single_file("corrupt.bin", 10)
+            .write_to(&dir)
+            .unwrap();
+        let kept = TorrentBuilder::single_file("kept.bin", 10)
+            .write_to(&dir)
+            .unwrap();
+
+        let mut session = TorrentSession::default();
+        for file in [&saved, &corrupt, &kept] {
+            session.add_torrent(file, OnDuplicate::Refuse).unwrap();
+        }
+        session.save(&state).unwrap();
+        std::fs::write(&corrupt, b"d4:infoe").unwrap();
+
+        // The same torrent is added from another file before resuming, as in the repl.
+        let copy = dir.join("copy.torrent");
+        std::fs::write(&copy, torrent.build()).unwrap();
+        let mut session = TorrentSession::default();
+        session.add_torrent(&copy, OnDuplicate::Refuse).unwrap();
+
+        let args = TorrentArgs {
+            command: TorrentCommands::ResumeAll {
+                state: Some(state.clone()),
+            },
+            show_secrets: false,
+            capture: None,
+            blocklist: None,
+            #[cfg(feature = "metrics")]
+            metrics_listen: None,
+        };
+        args.run_in(&mut session).await.unwrap();
+        assert_eq!(session.torrents().count(), 2);
+
+        let results: Vec<_> = TorrentSession::default()
+            .resume(&state)
+            .unwrap()
+            .into_iter()
+            .map(|(_, result)| result)
+            .collect();
+        assert!(matches!(
+            results.as_slice(),
+            [
+                Resumed::Added { .. },
+                Resumed::Unreadable { .. },
+                Resumed::Added { .. }
+            ]
+        ));
+        std::fs::remove_dir_all(&dir).unwrap();
+    }
+}
@@ -0,0 +1,72 @@
+use serde::{Deserialize, Deserializer, Serialize, Serializer};
+
+/// The tiers of trackers of the `announce-list` key.
+///
+/// Some torrents have a flat list of urls instead of a list of tiers, which would fail the whole
+/// torrent if it was read strictly. Each url which is not in a list is read as a tier of its own,
+/// as the tools creating torrents put every tracker in its own tier, and the list is flagged as
+/// [`ParseWarning::FlatAnnounceList`](super::ParseWarning::FlatAnnounceList).
+///
+/// It is always written back as a list of tiers.
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub(crate) struct AnnounceList {
+    pub(crate) tiers: Vec<Vec<String>>,
+
+    // Some of the tiers were a url instead of a list of urls.
+    pub(crate) flat: bool,
+}
+
+#[derive(Deserialize)]
+#[serde(untagged)]
+enum Tier {
+    Urls(Vec<String>),
+    Url(String),
+}
+
+impl<'de> Deserialize<'de> for AnnounceList {
+    fn deserialize<D>(deserializer: D) -> Result<Self, D::Error>
+    where
+        D: Deserializer<'de>,
+    {
+        let mut list = AnnounceList::default();
+        for tier in Vec::<Tier>::deserialize(deserializer)? {
+            match tier {
+                Tier::Urls(urls) => list.tiers.push(urls),
+                Tier::Url(url) => {
+                    list.tiers.push(vec![url]);
+                    list.flat = true;
+                }
+            }
+        }
+        Ok(list)
+    }
+}
+
+impl Serialize for AnnounceList {
+    fn serialize<S>(&self, serializer: S) -> Result<S::Ok, S::Error>
+    where
+        S: Serializer,
+    {
+        self.tiers.serialize(serializer)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use zung_parsers::bencode;
+
+    #[test]
+    fn test_announce_list_shapes() {
+        let list: AnnounceList = bencode::from_bytes(b"ll1:ael1:b1:cee").unwrap();
+        assert_eq!(list.tiers, [vec!["a"], vec!["b", "c"]]);
+        assert!(!list.flat);
+
+        let list: AnnounceList = bencode::from_bytes(b"l1:al1:b1:ce1:de").unwrap();
+        assert_eq!(list.tiers, [vec!["a"], vec!["b", "c"], vec!["d"]]);
+        assert!(list.flat);
+        assert_eq!(bencode::to_bytes(&list).unwrap(), b"ll1:ael1:b1:cel1:dee");
+
+        assert!(bencode::from_bytes::<AnnounceList>(b"li1ee").is_err());
+    }
+}
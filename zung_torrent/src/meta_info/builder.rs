@@ -0,0 +1,787 @@
+use std::collections::HashMap;
+use std::fs::File;
+use std::io::Read;
+use std::path::{Path, PathBuf};
+use std::str::FromStr;
+
+use anyhow::{bail, Context, Result};
+use ignore::gitignore::{Gitignore, GitignoreBuilder};
+use zung_parsers::bencode::{self, Value};
+
+use super::InfoHash;
+
+/// The smallest piece length picked by [`PieceLength::Auto`]: 16 KiB.
+pub const MIN_AUTO_PIECE_LENGTH: usize = 16 * 1024;
+
+/// The largest piece length picked by [`PieceLength::Auto`]: 16 MiB.
+pub const MAX_AUTO_PIECE_LENGTH: usize = 16 * 1024 * 1024;
+
+/// The number of pieces [`PieceLength::Auto`] aims for at most. Fewer pieces make a smaller
+/// torrent file, more pieces let peers share the content sooner.
+const TARGET_PIECES: u64 = 2000;
+
+/// The piece length of a torrent created by a [`MetaInfoBuilder`].
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub enum PieceLength {
+    /// The power of two which cuts the content in 1000 to 2000 pieces, within
+    /// [`MIN_AUTO_PIECE_LENGTH`] and [`MAX_AUTO_PIECE_LENGTH`]. Small content has fewer pieces and
+    /// large content more.
+    #[default]
+    Auto,
+
+    /// A piece length in bytes.
+    Fixed(usize),
+}
+
+impl PieceLength {
+    /// Returns the piece length in bytes for content of `total_length` bytes.
+    ///
+    /// ```
+    /// use zung_torrent::meta_info::PieceLength;
+    ///
+    /// // 4 GiB in 1024 pieces of 4 MiB.
+    /// assert_eq!(PieceLength::Auto.resolve(4 << 30), 4 << 20);
+    /// assert_eq!(PieceLength::Fixed(1024).resolve(4 << 30), 1024);
+    /// ```
+    pub fn resolve(self, total_length: u64) -> usize {
+        match self {
+            PieceLength::Fixed(length) => length,
+            PieceLength::Auto => {
+                let length = total_length.div_ceil(TARGET_PIECES).next_power_of_two();
+                (length as usize).clamp(MIN_AUTO_PIECE_LENGTH, MAX_AUTO_PIECE_LENGTH)
+            }
+        }
+    }
+}
+
+impl From<usize> for PieceLength {
+    fn from(length: usize) -> Self {
+        PieceLength::Fixed(length)
+    }
+}
+
+/// Parses `auto`, a number of bytes or a number of KiB or MiB such as `512KiB` or `4MiB`.
+impl FromStr for PieceLength {
+    type Err = anyhow::Error;
+
+    fn from_str(s: &str) -> Result<Self> {
+        if s.eq_ignore_ascii_case("auto") {
+            return Ok(PieceLength::Auto);
+        }
+
+        let (number, unit) = match s.find(|c: char| !c.is_ascii_digit()) {
+            Some(i) => s.split_at(i),
+            None => (s, ""),
+        };
+        let unit = match unit.trim().to_ascii_lowercase().as_str() {
+            "" | "b" => 1,
+            "k" | "kib" => 1024,
+            "m" | "mib" => 1024 * 1024,
+            _ => bail!("Invalid piece length {s:?} - Expected auto, bytes, KiB or MiB"),
+        };
+        let length = number
+            .parse::<usize>()
+            .ok()
+            .and_then(|n| n.checked_mul(unit))
+            .filter(|&n| n > 0)
+            .with_context(|| format!("Invalid piece length {s:?}"))?;
+        Ok(PieceLength::Fixed(length))
+    }
+}
+
+/// Creates a torrent file from a file or a directory on the disk.
+///
+/// A file is turned into a single file torrent and a directory into a multi file torrent with
+/// every file found under it, except for the hidden files (whose name starts with a `.`) and the
+/// files matching the [`MetaInfoBuilder::exclude`] patterns.
+///
+/// # Deterministic mode
+///
+/// With [`MetaInfoBuilder::deterministic`] the same inputs always produce the same torrent file,
+/// byte for byte, and so the same info hash:
+///
+/// - the files are sorted bytewise by their path, instead of the order the file system lists
+///   them in,
+/// - the `creation date` and `created by` keys, which change with every run and every version, are
+///   left out.
+///
+/// The keys of the dictionaries are always sorted, as the bencode encoder requires, so the key
+/// order does not depend on the mode. The info hash only depends on the name, the piece length,
+/// the private flag and the paths and content of the files.
+///
+/// # Example
+///
+/// ```no_run
+/// use zung_torrent::meta_info::{MetaInfo, MetaInfoBuilder};
+///
+/// let torrent = MetaInfoBuilder::new("path/to/directory")
+///     .announce("udp://tracker.opentrackr.org:1337/announce")
+///     .deterministic(true)
+///     .build()
+///     .unwrap();
+///
+/// std::fs::write("directory.torrent", &torrent.bytes).unwrap();
+/// println!("Info hash: {}", torrent.info_hash);
+/// ```
+#[derive(Debug, Clone)]
+pub struct MetaInfoBuilder {
+    source: PathBuf,
+    name: Option<String>,
+    piece_length: PieceLength,
+    trackers: Vec<Vec<String>>,
+    web_seeds: Vec<String>,
+    padded: bool,
+    comment: Option<String>,
+    private: bool,
+    deterministic: bool,
+    exclude: Vec<String>,
+    include_hidden: bool,
+}
+
+/// A file found under the source of a [`MetaInfoBuilder`].
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct SourceFile {
+    /// The path of the file on the disk.
+    pub source: PathBuf,
+
+    /// The path of the file in the torrent, relative to the source directory. Empty for a single
+    /// file torrent.
+    pub path: Vec<String>,
+
+    pub length: u64,
+}
+
+/// A torrent file created by a [`MetaInfoBuilder`].
+#[derive(Debug, Clone)]
+pub struct NewTorrent {
+    /// The bencoded torrent file.
+    pub bytes: Vec<u8>,
+
+    pub info_hash: InfoHash,
+
+    pub number_of_pieces: usize,
+}
+
+impl MetaInfoBuilder {
+    pub fn new(source: impl Into<PathBuf>) -> Self {
+        Self {
+            source: source.into(),
+            name: None,
+            piece_length: PieceLength::Auto,
+            trackers: Vec::new(),
+            web_seeds: Vec::new(),
+            padded: false,
+            comment: None,
+            private: false,
+            deterministic: false,
+            exclude: Vec::new(),
+            include_hidden: false,
+        }
+    }
+
+    /// Sets the name of the torrent. Defaults to the file name of the source.
+    pub fn name(mut self, name: impl Into<String>) -> Self {
+        self.name = Some(name.into());
+        self
+    }
+
+    /// Sets the piece length, or the piece length in bytes. Defaults to [`PieceLength::Auto`].
+    pub fn piece_length(mut self, piece_length: impl Into<PieceLength>) -> Self {
+        self.piece_length = piece_length.into();
+        self
+    }
+
+    /// Returns the piece length in bytes of the torrent of these files, as returned by
+    /// [`MetaInfoBuilder::files`].
+    pub fn piece_length_for(&self, files: &[SourceFile]) -> usize {
+        self.piece_length
+            .resolve(files.iter().map(|file| file.length).sum())
+    }
+
+    /// Returns the number of pieces of the torrent of these files, padding included.
+    pub fn number_of_pieces_for(&self, files: &[SourceFile]) -> usize {
+        let piece_length = self.piece_length_for(files);
+        if piece_length == 0 {
+            return 0;
+        }
+        let padded = self.padded && files.len() > 1;
+        let length: u64 = files
+            .iter()
+            .enumerate()
+            .map(|(i, file)| match padded && i + 1 < files.len() {
+                true => file.length + padding(file.length, piece_length),
+                false => file.length,
+            })
+            .sum();
+        length.div_ceil(piece_length as u64) as usize
+    }
+
+    /// Adds a tracker in a tier of its own. The first tracker is also the `announce` key.
+    pub fn announce(mut self, url: impl Into<String>) -> Self {
+        self.trackers.push(vec![url.into()]);
+        self
+    }
+
+    /// Adds a web seed (BEP 19): an HTTP or FTP url the content can be downloaded from, in the
+    /// `url-list` key.
+    pub fn web_seed(mut self, url: impl Into<String>) -> Self {
+        self.web_seeds.push(url.into());
+        self
+    }
+
+    /// Adds padding files (BEP 47) after the files of a multi file torrent, so that every file
+    /// starts at the start of a piece. The padding files are zeros and have the `p` attribute, so
+    /// clients do not write them to the disk.
+    ///
+    /// A piece then never holds the data of two files, which lets clients download and verify
+    /// each file on its own and web seeds serve whole pieces from a single file.
+    pub fn padded(mut self, padded: bool) -> Self {
+        self.padded = padded;
+        self
+    }
+
+    pub fn comment(mut self, comment: impl Into<String>) -> Self {
+        self.comment = Some(comment.into());
+        self
+    }
+
+    /// Marks the torrent as private (BEP 27), so that clients only get peers from its trackers.
+    pub fn private(mut self, private: bool) -> Self {
+        self.private = private;
+        self
+    }
+
+    /// Makes the same inputs always produce the same torrent file. See the
+    /// [type](MetaInfoBuilder#deterministic-mode) documentation.
+    pub fn deterministic(mut self, deterministic: bool) -> Self {
+        self.deterministic = deterministic;
+        self
+    }
+
+    /// Leaves out the files and directories matching the pattern, with the syntax and the
+    /// semantics of a `.gitignore` line relative to the source directory: `*.tmp` matches at any
+    /// depth, `/build` only at the top, `cache/` only directories and `!keep.tmp` includes a
+    /// file back.
+    pub fn exclude(mut self, pattern: impl Into<String>) -> Self {
+        self.exclude.push(pattern.into());
+        self
+    }
+
+    /// Includes the hidden files and directories, whose name starts with a `.`. They are left out
+    /// by default.
+    pub fn include_hidden(mut self, include_hidden: bool) -> Self {
+        self.include_hidden = include_hidden;
+        self
+    }
+
+    /// Returns the files which will be in the torrent, in the order of the torrent.
+    ///
+    /// Returns an error if an exclude pattern is invalid or if no file is left.
+    pub fn files(&self) -> Result<Vec<SourceFile>> {
+        let metadata = std::fs::metadata(&self.source)
+            .with_context(|| format!("Unable to read {}", self.source.display()))?;
+
+        if metadata.is_file() {
+            return Ok(vec![SourceFile {
+                source: self.source.clone(),
+                path: Vec::new(),
+                length: metadata.len(),
+            }]);
+        }
+
+        let mut exclude = GitignoreBuilder::new(&self.source);
+        for pattern in &self.exclude {
+            exclude
+                .add_line(None, pattern)
+                .with_context(|| format!("Invalid exclude pattern {pattern:?}"))?;
+        }
+        let filter = Filter {
+            exclude: exclude.build()?,
+            include_hidden: self.include_hidden,
+        };
+
+        let mut files = Vec::new();
+        walk(&self.source, &filter, &mut Vec::new(), &mut files)?;
+        if self.deterministic {
+            files.sort_by(|a, b| a.path.cmp(&b.path));
+        }
+        if files.is_empty() {
+            bail!("No files found in {}", self.source.display())
+        }
+        Ok(files)
+    }
+
+    /// Hashes the files and builds the torrent file.
+    pub fn build(&self) -> Result<NewTorrent> {
+        self.build_from(self.files()?)
+    }
+
+    /// Same as [`MetaInfoBuilder::build`] with the files already returned by
+    /// [`MetaInfoBuilder::files`], e.g. to report on them before hashing.
+    pub fn build_from(&self, files: Vec<SourceFile>) -> Result<NewTorrent> {
+        let piece_length = self.piece_length_for(&files);
+        if piece_length == 0 {
+            bail!("The piece length must not be 0")
+        }
+
+        let name = match &self.name {
+            Some(name) => name.clone(),
+            None => self
+                .source
+                .canonicalize()?
+                .file_name()
+                .and_then(|name| name.to_str())
+                .map(String::from)
+                .context("The name of the source is not valid UTF-8, pass a name instead")?,
+        };
+
+        let padded = self.padded && files.len() > 1;
+        let pieces = hash_pieces(&files, piece_length, padded)?;
+        let number_of_pieces = pieces.len() / 20;
+
+        let mut info = HashMap::from([
+            ("name".to_string(), Value::String(name)),
+            (
+                "piece length".to_string(),
+                Value::Integer(piece_length as i64),
+            ),
+            ("pieces".to_string(), Value::Bytes(pieces)),
+        ]);
+        match files.as_slice() {
+            [file] if file.path.is_empty() => {
+                info.insert("length".to_string(), Value::Integer(file.length as i64));
+            }
+            files => {
+                let entry = |length: u64, path: Vec<String>| {
+                    HashMap::from([
+                        ("length".to_string(), Value::Integer(length as i64)),
+                        (
+                            "path".to_string(),
+                            Value::List(path.into_iter().map(Value::String).collect()),
+                        ),
+                    ])
+                };
+
+                let mut entries = Vec::new();
+                for (i, file) in files.iter().enumerate() {
+                    entries.push(Value::from(entry(file.length, file.path.clone())));
+
+                    let padding = padding(file.length, piece_length);
+                    if padded && padding > 0 && i + 1 < files.len() {
+                        let mut padding =
+                            entry(padding, vec![".pad".to_string(), padding.to_string()]);
+                        padding.insert("attr".to_string(), Value::String("p".to_string()));
+                        entries.push(Value::from(padding));
+                    }
+                }
+                info.insert("files".to_string(), Value::List(entries));
+            }
+        }
+        if self.private {
+            info.insert("private".to_string(), Value::Integer(1));
+        }
+
+        let info = Value::from(info);
+        let info_hash = InfoHash::new(&bencode::to_bytes(&info)?);
+
+        let mut torrent = HashMap::from([("info".to_string(), info)]);
+        if let Some(first) = self.trackers.first() {
+            torrent.insert("announce".to_string(), Value::String(first[0].clone()));
+        }
+        if self.trackers.len() > 1 {
+            let tiers = self
+                .trackers
+                .iter()
+                .map(|tier| Value::List(tier.iter().cloned().map(Value::String).collect()))
+                .collect();
+            torrent.insert("announce-list".to_string(), Value::List(tiers));
+        }
+        if !self.web_seeds.is_empty() {
+            let urls = self.web_seeds.iter().cloned().map(Value::String).collect();
+            torrent.insert("url-list".to_string(), Value::List(urls));
+        }
+        if let Some(comment) = &self.comment {
+            torrent.insert("comment".to_string(), Value::String(comment.clone()));
+        }
+        if !self.deterministic {
+            torrent.insert(
+                "creation date".to_string(),
+                Value::Integer(chrono::Utc::now().timestamp()),
+            );
+            torrent.insert(
+                "created by".to_string(),
+                Value::String(format!("zung/{}", crate::VERSION)),
+            );
+        }
+
+        Ok(NewTorrent {
+            bytes: bencode::to_bytes(&Value::from(torrent))?,
+            info_hash,
+            number_of_pieces,
+        })
+    }
+}
+
+// Which of the files found under the source are left out.
+struct Filter {
+    exclude: Gitignore,
+    include_hidden: bool,
+}
+
+// Collects the files under `dir`, in the order the file system lists them.
+fn walk(
+    dir: &Path,
+    filter: &Filter,
+    path: &mut Vec<String>,
+    files: &mut Vec<SourceFile>,
+) -> Result<()> {
+    let entries =
+        std::fs::read_dir(dir).with_context(|| format!("Unable to read {}", dir.display()))?;
+
+    for entry in entries {
+        let entry = entry?;
+        let source = entry.path();
+        let Some(name) = entry.file_name().to_str().map(String::from) else {
+            bail!("The path {} is not valid UTF-8", source.display())
+        };
+        if name.starts_with('.') && !filter.include_hidden {
+            continue;
+        }
+        // Follows the symlinks, so that their targets are in the torrent.
+        let metadata = std::fs::metadata(&source)
+            .with_context(|| format!("Unable to read {}", source.display()))?;
+        if filter
+            .exclude
+            .matched(&source, metadata.is_dir())
+            .is_ignore()
+        {
+            continue;
+        }
+
+        path.push(name);
+        if metadata.is_dir() {
+            walk(&source, filter, path, files)?;
+        } else {
+            files.push(SourceFile {
+                source,
+                path: path.clone(),
+                length: metadata.len(),
+            });
+        }
+        path.pop();
+    }
+    Ok(())
+}
+
+// The number of zeros which move the end of a file of `length` bytes to the end of a piece.
+fn padding(length: u64, piece_length: usize) -> u64 {
+    let piece_length = piece_length as u64;
+    (piece_length - length % piece_length) % piece_length
+}
+
+// Hashes the files as one continuous stream of bytes, cut in pieces of `piece_length`. When
+// `padded`, the last piece of every file but the last is filled with zeros.
+fn hash_pieces(files: &[SourceFile], piece_length: usize, padded: bool) -> Result<Vec<u8>> {
+    let mut hasher = PieceHasher::new(piece_length);
+    for (i, file) in files.iter().enumerate() {
+        if padded && i > 0 {
+            hasher.pad();
+        }
+        let reader = File::open(&file.source)
+            .with_context(|| format!("Unable to read {}", file.source.display()))?;
+        hasher.update(reader)?;
+    }
+    Ok(hasher.finish())
+}
+
+/// Hashes a stream of bytes in pieces, as in the `pieces` key of the info dictionary.
+pub(super) struct PieceHasher {
+    piece_length: usize,
+    piece: Vec<u8>,
+    pieces: Vec<u8>,
+}
+
+impl PieceHasher {
+    pub(super) fn new(piece_length: usize) -> Self {
+        Self {
+            piece_length,
+            piece: Vec::with_capacity(piece_length),
+            pieces: Vec::new(),
+        }
+    }
+
+    /// Appends everything read from the reader to the stream.
+    pub(super) fn update(&mut self, mut reader: impl Read) -> Result<()> {
+        loop {
+            let filled = self.piece.len();
+            self.piece.resize(self.piece_length, 0);
+            let read = reader.read(&mut self.piece[filled..])?;
+            self.piece.truncate(filled + read);
+
+            if self.piece.len() == self.piece_length {
+                self.hash_piece();
+            }
+            if read == 0 {
+                return Ok(());
+            }
+        }
+    }
+
+    /// Fills the current piece with zeros, if it is started.
+    pub(super) fn pad(&mut self) {
+        if !self.piece.is_empty() {
+            self.piece.resize(self.piece_length, 0);
+            self.hash_piece();
+        }
+    }
+
+    /// Returns the hashes of the pieces, the last one possibly shorter.
+    pub(super) fn finish(mut self) -> Vec<u8> {
+        if !self.piece.is_empty() {
+            self.hash_piece();
+        }
+        self.pieces
+    }
+
+    fn hash_piece(&mut self) {
+        let digest = sha1_smol::Sha1::from(&self.piece).digest();
+        self.pieces.extend(digest.bytes());
+        self.piece.clear();
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::meta_info::MetaInfo;
+
+    // Creates a directory with the files, written in the given order.
+    fn source(name: &str, files: &[(&str, &[u8])]) -> PathBuf {
+        let dir = std::env::temp_dir().join(format!("zung-builder-{}-{name}", std::process::id()));
+        let _ = std::fs::remove_dir_all(&dir);
+        for (path, content) in files {
+            let path = dir.join(name).join(path);
+            std::fs::create_dir_all(path.parent().unwrap()).unwrap();
+            std::fs::write(path, content).unwrap();
+        }
+        dir.join(name)
+    }
+
+    #[test]
+    fn test_single_file() {
+        let dir = source("single", &[("file.bin", &[7; 40])]);
+        let torrent = MetaInfoBuilder::new(dir.join("file.bin"))
+            .piece_length(16)
+            .announce("http://tracker/announce")
+            .build()
+            .unwrap();
+
+        let meta_info = MetaInfo::from_bytes(&torrent.bytes).unwrap();
+        assert_eq!(meta_info.info().name, "file.bin");
+        assert_eq!(meta_info.number_of_pieces(), 3);
+        assert_eq!(torrent.number_of_pieces, 3);
+        assert_eq!(meta_info.announce().unwrap(), "http://tracker/announce");
+        assert_eq!(
+            meta_info.created_by().unwrap(),
+            &format!("zung/{}", crate::VERSION)
+        );
+    }
+
+    #[test]
+    fn test_pieces_span_files() {
+        let dir = source("span", &[("a", b"0123456789"), ("b/c", b"abcdefghij")]);
+        let torrent = MetaInfoBuilder::new(&dir)
+            .piece_length(16)
+            .deterministic(true)
+            .build()
+            .unwrap();
+
+        let value = bencode::parse(&torrent.bytes).unwrap();
+        let pieces = value.pointer("info/pieces").unwrap();
+        let expected: Vec<u8> = [&b"0123456789abcdef"[..], b"ghij"]
+            .iter()
+            .flat_map(|piece| sha1_smol::Sha1::from(piece).digest().bytes())
+            .collect();
+        assert_eq!(pieces, &Value::Bytes(expected));
+        assert_eq!(
+            value.pointer("info/files/1/path").unwrap().to_string(),
+            "[b, c]"
+        );
+    }
+
+    #[test]
+    fn test_padding_and_web_seeds() {
+        let dir = source(
+            "padded",
+            &[("a", b"0123456789"), ("b", &[1; 16]), ("c", b"abc")],
+        );
+        let torrent = MetaInfoBuilder::new(&dir)
+            .piece_length(16)
+            .padded(true)
+            .web_seed("http://seed/files/")
+            .deterministic(true)
+            .build()
+            .unwrap();
+
+        let value = bencode::parse(&torrent.bytes).unwrap();
+        let paths: Vec<_> = value
+            .query("info/files/*/path")
+            .iter()
+            .map(|path| path.to_string())
+            .collect();
+        // The last file and the file already ending at a piece boundary are not padded.
+        assert_eq!(paths, ["[a]", "[.pad, 6]", "[b]", "[c]"]);
+        assert_eq!(value.pointer("info/files/1/attr").unwrap().to_string(), "p");
+        assert_eq!(
+            value.pointer("url-list/0").unwrap().to_string(),
+            "http://seed/files/"
+        );
+
+        let mut first = b"0123456789".to_vec();
+        first.resize(16, 0);
+        let expected: Vec<u8> = [&first[..], &[1; 16], b"abc"]
+            .iter()
+            .flat_map(|piece| sha1_smol::Sha1::from(piece).digest().bytes())
+            .collect();
+        assert_eq!(
+            value.pointer("info/pieces").unwrap(),
+            &Value::Bytes(expected)
+        );
+
+        // The padding is part of the content of the torrent.
+        let meta_info = MetaInfo::from_bytes(&torrent.bytes).unwrap();
+        assert_eq!(meta_info.number_of_pieces(), 3);
+        assert_eq!(torrent.number_of_pieces, 3);
+        let builder = MetaInfoBuilder::new(&dir).piece_length(16).padded(true);
+        assert_eq!(builder.number_of_pieces_for(&builder.files().unwrap()), 3);
+    }
+
+    #[test]
+    fn test_deterministic() {
+        let files: &[(&str, &[u8])] = &[("b.txt", b"b"), ("a/z.txt", b"z"), ("B.txt", b"B")];
+        let first = source("first", files);
+        let mut reversed = files.to_vec();
+        reversed.reverse();
+        let second = source("second", &reversed);
+
+        let build = |dir: &Path| {
+            MetaInfoBuilder::new(dir)
+                .name("same")
+                .piece_length(16)
+                .announce("http://tracker/announce")
+                .deterministic(true)
+                .build()
+                .unwrap()
+        };
+        let (first, second) = (build(&first), build(&second));
+        assert_eq!(first.bytes, second.bytes);
+        assert_eq!(first.info_hash, second.info_hash);
+
+        let value = bencode::parse(&first.bytes).unwrap();
+        assert!(value.get_from_dictionary("creation date").is_none());
+        assert!(value.get_from_dictionary("created by").is_none());
+        let paths: Vec<_> = value
+            .query("info/files/*/path")
+            .iter()
+            .map(|path| path.to_string())
+            .collect();
+        assert_eq!(paths, ["[B.txt]", "[a, z.txt]", "[b.txt]"]);
+    }
+
+    #[test]
+    fn test_auto_piece_length() {
+        const KIB: u64 = 1024;
+        const MIB: u64 = 1024 * KIB;
+
+        let auto = |total| PieceLength::Auto.resolve(total) as u64;
+        assert_eq!(auto(0), 16 * KIB);
+        assert_eq!(auto(10 * MIB), 16 * KIB);
+        assert_eq!(auto(700 * MIB), 512 * KIB);
+        assert_eq!(auto(100 * 1024 * MIB), 16 * MIB);
+
+        // 1000 to 2000 pieces in between the bounds.
+        for total in [32 * MIB, 33 * MIB, 1000 * MIB, 20 * 1024 * MIB] {
+            let pieces = total.div_ceil(auto(total));
+            assert!((1000..=2000).contains(&pieces), "{total}: {pieces}");
+        }
+    }
+
+    #[test]
+    fn test_parse_piece_length() {
+        assert_eq!("auto".parse::<PieceLength>().unwrap(), PieceLength::Auto);
+        assert_eq!(
+            "16384".parse::<PieceLength>().unwrap(),
+            PieceLength::Fixed(16384)
+        );
+        assert_eq!(
+            "512KiB".parse::<PieceLength>().unwrap(),
+            PieceLength::Fixed(512 * 1024)
+        );
+        assert_eq!(
+            "4m".parse::<PieceLength>().unwrap(),
+            PieceLength::Fixed(4 << 20)
+        );
+        assert!("0".parse::<PieceLength>().is_err());
+        assert!("4GiB".parse::<PieceLength>().is_err());
+        assert!("big".parse::<PieceLength>().is_err());
+    }
+
+    #[test]
+    fn test_exclude_and_hidden_files() {
+        let dir = source(
+            "filter",
+            &[
+                ("a.txt", b"a"),
+                ("a.tmp", b"a"),
+                ("keep.tmp", b"k"),
+                ("build/out", b"o"),
+                ("src/build/out", b"o"),
+                (".git/HEAD", b"h"),
+                ("src/.hidden", b"h"),
+            ],
+        );
+        let paths = |builder: MetaInfoBuilder| -> Vec<String> {
+            let files = builder.deterministic(true).files().unwrap();
+            files.iter().map(|file| file.path.join("/")).collect()
+        };
+
+        assert_eq!(
+            paths(MetaInfoBuilder::new(&dir)),
+            ["a.tmp", "a.txt", "build/out", "keep.tmp", "src/build/out"]
+        );
+        assert_eq!(
+            paths(
+                MetaInfoBuilder::new(&dir)
+                    .exclude("*.tmp")
+                    .exclude("!keep.tmp")
+                    .exclude("/build/")
+            ),
+            ["a.txt", "keep.tmp", "src/build/out"]
+        );
+        assert_eq!(
+            paths(
+                MetaInfoBuilder::new(&dir)
+                    .include_hidden(true)
+                    .exclude(".git")
+            ),
+            [
+                "a.tmp",
+                "a.txt",
+                "build/out",
+                "keep.tmp",
+                "src/.hidden",
+                "src/build/out"
+            ]
+        );
+
+        let everything = MetaInfoBuilder::new(&dir).exclude("*");
+        assert!(everything.files().is_err());
+    }
+
+    #[test]
+    fn test_empty_directory() {
+        let dir = source("empty", &[]);
+        std::fs::create_dir_all(&dir).unwrap();
+        assert!(MetaInfoBuilder::new(&dir).build().is_err());
+    }
+}
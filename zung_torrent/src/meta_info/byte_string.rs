@@ -0,0 +1,217 @@
+use std::{
+    borrow::Borrow,
+    cmp::Ordering,
+    fmt,
+    hash::{Hash, Hasher},
+    ops::Deref,
+};
+
+use serde::{de::Visitor, Deserialize, Deserializer, Serialize, Serializer};
+
+/// A string of a torrent file, such as the name of a file, which is not always valid UTF-8.
+///
+/// Torrents created on systems whose file names are not UTF-8 have names which are not valid
+/// strings. They are read with the invalid sequences replaced by `U+FFFD` (�), so that the name
+/// can be displayed and used as a path, and the original bytes are kept to write the torrent back
+/// unchanged. A `ByteString` reads as a `&str`:
+///
+/// ```
+/// use zung_torrent::meta_info::ByteString;
+///
+/// let name = ByteString::from_bytes(b"caf\xe9.txt");
+/// assert_eq!(name, "caf\u{FFFD}.txt");
+/// assert_eq!(name.as_bytes(), b"caf\xe9.txt");
+/// assert!(name.is_lossy());
+/// assert!(name.ends_with(".txt"));
+/// ```
+///
+/// It is compared and hashed as its string, like the paths made of it.
+#[derive(Clone, Default)]
+pub struct ByteString {
+    string: String,
+
+    // The bytes read, when they are not valid UTF-8.
+    raw: Option<Box<[u8]>>,
+}
+
+impl ByteString {
+    pub fn from_bytes(bytes: &[u8]) -> Self {
+        match std::str::from_utf8(bytes) {
+            Ok(string) => ByteString::from(string),
+            Err(_) => ByteString {
+                string: String::from_utf8_lossy(bytes).into_owned(),
+                raw: Some(bytes.into()),
+            },
+        }
+    }
+
+    /// The string, with the invalid UTF-8 sequences replaced by `U+FFFD`.
+    pub fn as_str(&self) -> &str {
+        &self.string
+    }
+
+    /// The bytes as they are in the torrent file.
+    pub fn as_bytes(&self) -> &[u8] {
+        self.raw.as_deref().unwrap_or(self.string.as_bytes())
+    }
+
+    /// Returns `true` if the bytes are not valid UTF-8, and [`as_str`](Self::as_str) differs
+    /// from them.
+    pub fn is_lossy(&self) -> bool {
+        self.raw.is_some()
+    }
+}
+
+impl Deref for ByteString {
+    type Target = str;
+
+    fn deref(&self) -> &str {
+        &self.string
+    }
+}
+
+impl AsRef<str> for ByteString {
+    fn as_ref(&self) -> &str {
+        &self.string
+    }
+}
+
+impl Borrow<str> for ByteString {
+    fn borrow(&self) -> &str {
+        &self.string
+    }
+}
+
+impl PartialEq for ByteString {
+    fn eq(&self, other: &Self) -> bool {
+        self.string == other.string
+    }
+}
+
+impl Eq for ByteString {}
+
+impl PartialOrd for ByteString {
+    fn partial_cmp(&self, other: &Self) -> Option<Ordering> {
+        Some(self.cmp(other))
+    }
+}
+
+impl Ord for ByteString {
+    fn cmp(&self, other: &Self) -> Ordering {
+        self.string.cmp(&other.string)
+    }
+}
+
+impl Hash for ByteString {
+    fn hash<H: Hasher>(&self, state: &mut H) {
+        self.string.hash(state)
+    }
+}
+
+impl From<&str> for ByteString {
+    fn from(string: &str) -> Self {
+        ByteString::from(string.to_string())
+    }
+}
+
+impl From<String> for ByteString {
+    fn from(string: String) -> Self {
+        ByteString { string, raw: None }
+    }
+}
+
+impl PartialEq<str> for ByteString {
+    fn eq(&self, other: &str) -> bool {
+        self.string == other
+    }
+}
+
+impl PartialEq<&str> for ByteString {
+    fn eq(&self, other: &&str) -> bool {
+        self.string == *other
+    }
+}
+
+impl PartialEq<String> for ByteString {
+    fn eq(&self, other: &String) -> bool {
+        &self.string == other
+    }
+}
+
+impl fmt::Debug for ByteString {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        fmt::Debug::fmt(&self.string, f)
+    }
+}
+
+impl fmt::Display for ByteString {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.write_str(&self.string)
+    }
+}
+
+// Written as a string when it is valid UTF-8, so that it stays readable in the formats with
+// strings and bytes (e.g. JSON), and as the original bytes otherwise.
+impl Serialize for ByteString {
+    fn serialize<S>(&self, serializer: S) -> Result<S::Ok, S::Error>
+    where
+        S: Serializer,
+    {
+        match &self.raw {
+            Some(raw) => serializer.serialize_bytes(raw),
+            None => serializer.serialize_str(&self.string),
+        }
+    }
+}
+
+impl<'de> Deserialize<'de> for ByteString {
+    fn deserialize<D>(deserializer: D) -> Result<Self, D::Error>
+    where
+        D: Deserializer<'de>,
+    {
+        deserializer.deserialize_bytes(ByteStringVisitor)
+    }
+}
+
+struct ByteStringVisitor;
+
+impl Visitor<'_> for ByteStringVisitor {
+    type Value = ByteString;
+
+    fn expecting(&self, formatter: &mut fmt::Formatter) -> fmt::Result {
+        write!(formatter, "a string")
+    }
+
+    fn visit_str<E>(self, v: &str) -> Result<ByteString, E> {
+        Ok(ByteString::from(v))
+    }
+
+    fn visit_string<E>(self, v: String) -> Result<ByteString, E> {
+        Ok(ByteString::from(v))
+    }
+
+    fn visit_bytes<E>(self, v: &[u8]) -> Result<ByteString, E> {
+        Ok(ByteString::from_bytes(v))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use zung_parsers::bencode;
+
+    #[test]
+    fn test_byte_string() {
+        let name: ByteString = bencode::from_bytes(b"4:name").unwrap();
+        assert_eq!(name, "name");
+        assert!(!name.is_lossy());
+        assert_eq!(bencode::to_bytes(&name).unwrap(), b"4:name");
+
+        let input = b"l3:\xff\xfea4:namee";
+        let names: Vec<ByteString> = bencode::from_bytes(input).unwrap();
+        assert_eq!(names[0], "\u{FFFD}\u{FFFD}a");
+        assert_eq!(names[1], "name");
+        assert_eq!(bencode::to_bytes(&names).unwrap(), input);
+        assert_eq!(serde_json::to_string(&names[1]).unwrap(), "\"name\"");
+    }
+}
@@ -0,0 +1,86 @@
+use std::fmt::Display;
+
+use serde::Serialize;
+
+/// The version of the BitTorrent protocol a torrent is made for.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize)]
+#[serde(rename_all = "lowercase")]
+pub enum TorrentVersion {
+    /// The original protocol: SHA1 piece hashes only.
+    V1,
+
+    /// [BEP: 52](https://www.bittorrent.org/beps/bep_0052.html) only: a `file tree` with SHA256
+    /// merkle roots and no v1 `pieces`.
+    V2,
+
+    /// Both the v1 and the v2 metadata, so that the torrent can be shared in both swarms.
+    Hybrid,
+}
+
+impl Display for TorrentVersion {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            TorrentVersion::V1 => write!(f, "v1"),
+            TorrentVersion::V2 => write!(f, "v2"),
+            TorrentVersion::Hybrid => write!(f, "hybrid v1+v2"),
+        }
+    }
+}
+
+/// A summary of the protocol features used by a torrent. See
+/// [`MetaInfo::capabilities`](super::MetaInfo::capabilities).
+///
+/// The [`Display`] implementation lists the features in a single line, such as `hybrid v1+v2,
+/// padded, private, web seeds`.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize)]
+pub struct Capabilities {
+    pub version: TorrentVersion,
+
+    /// The files are aligned to the pieces with padding files ([BEP:
+    /// 47](https://www.bittorrent.org/beps/bep_0047.html)).
+    pub padded: bool,
+
+    /// Peers may only be obtained from the trackers of the torrent ([BEP:
+    /// 27](https://www.bittorrent.org/beps/bep_0027.html)).
+    pub private: bool,
+
+    /// The torrent has HTTP or FTP servers as seeds ([BEP:
+    /// 19](https://www.bittorrent.org/beps/bep_0019.html)).
+    pub web_seeds: bool,
+}
+
+impl Display for Capabilities {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(f, "{}", self.version)?;
+        let flags = [
+            (self.padded, "padded"),
+            (self.private, "private"),
+            (self.web_seeds, "web seeds"),
+        ];
+        for (_, label) in flags.iter().filter(|(set, _)| *set) {
+            write!(f, ", {label}")?;
+        }
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_capabilities_display() {
+        let mut capabilities = Capabilities {
+            version: TorrentVersion::V1,
+            padded: false,
+            private: false,
+            web_seeds: false,
+        };
+        assert_eq!(capabilities.to_string(), "v1");
+
+        capabilities.version = TorrentVersion::Hybrid;
+        capabilities.padded = true;
+        capabilities.web_seeds = true;
+        assert_eq!(capabilities.to_string(), "hybrid v1+v2, padded, web seeds");
+    }
+}
@@ -1,8 +1,10 @@
-use std::{borrow::Cow, fmt::Display};
+use std::{borrow::Cow, collections::BTreeMap, fmt::Display};
 
-use human_bytes::human_bytes;
 use indexmap::IndexMap;
-use serde::{Deserialize, Serialize};
+use serde::{de::Visitor, Deserialize, Serialize};
+use zung_parsers::bencode::Value;
+
+use super::{ByteString, SizeFormat};
 
 const PADDING_ATTR: &str = "p";
 const SYMLINK_ATTR: &str = "l";
@@ -15,7 +17,7 @@ const HIDDEN_ATTR: &str = "h";
 ///
 /// - `SingleFile`: Represents a single file with its length in bytes and an optional MD5 checksum.
 /// - `MultiFile`: Represents multiple files with a vector of data containing information about the
-///     file.
+///   file.
 ///
 /// As per the [The BitTorrent Protocol
 /// Specification](https://www.bittorrent.org/beps/bep_0003.html), in a torrent files there is
@@ -38,6 +40,9 @@ pub enum Files {
         // = symlink, x = executable, h = hidden, p = padding file. Characters appear in no
         // particular order and unknown characters should be ignored.
         attr: Option<FileAttr>,
+
+        // (optional) SHA1 hash of the contents of the file (BEP 47).
+        sha1: Option<FileHash>,
     },
     MultiFile {
         // a list of dictionaries, one for each file. Each dictionary in this list contains the following keys:
@@ -45,6 +50,11 @@ pub enum Files {
     },
 }
 
+impl Files {
+    /// Keys of the info dictionary from which [`Files`] is deserialized.
+    pub(crate) const KEYS: &'static [&'static str] = &["length", "md5sum", "attr", "sha1", "files"];
+}
+
 /// Reprasents the multifile state of the torrent.
 #[derive(Debug, Serialize, Deserialize)]
 pub struct MultiFiles {
@@ -61,19 +71,173 @@ pub struct MultiFiles {
     // element) the filename. For example, a the file "dir1/dir2/file.ext" would consist of three
     // string elements: "dir1", "dir2", and "file.ext". This is encoded as a bencoded list of
     // strings such as l4:dir14:dir28:file.exte
-    pub(crate) path: Vec<String>,
+    pub(crate) path: Vec<ByteString>,
 
     // A variable-length string. When present the characters each represent a file attribute. l
     // = symlink, x = executable, h = hidden, p = padding file. Characters appear in no
     // particular order and unknown characters should be ignored.
     pub(crate) attr: Option<FileAttr>,
+
+    // (optional) SHA1 hash of the contents of the file (BEP 47).
+    pub(crate) sha1: Option<FileHash>,
+
+    // (optional) path of the target of the symlink relative to the torrent root, in the same form
+    // as `path`. Only meaningful when the `l` attr is set (BEP 47).
+    #[serde(rename = "symlink path")]
+    pub(crate) symlink_path: Option<Vec<ByteString>>,
+
+    // Any other keys (e.g. `mtime`) which are not known to this library.
+    #[serde(flatten)]
+    pub(crate) extra: BTreeMap<String, Value>,
+}
+
+impl MultiFiles {
+    /// Path of the file relative to the torrent root.
+    pub fn path(&self) -> &[ByteString] {
+        &self.path
+    }
+
+    /// Length of the file in bytes.
+    pub fn length(&self) -> usize {
+        self.length
+    }
+
+    /// The BEP 47 attributes of the file.
+    pub fn attr(&self) -> Option<&FileAttr> {
+        self.attr.as_ref()
+    }
+
+    /// The BEP 47 SHA1 hash of the contents of the file.
+    pub fn sha1(&self) -> Option<&FileHash> {
+        self.sha1.as_ref()
+    }
+
+    /// Path of the target of the symlink relative to the torrent root, if the file is a symlink.
+    pub fn symlink_path(&self) -> Option<&[ByteString]> {
+        self.symlink_path.as_deref()
+    }
+}
+
+/// SHA1 hash of the contents of a single file, from the `sha1` key of [BEP
+/// 47](https://www.bittorrent.org/beps/bep_0047.html).
+///
+/// Unlike the piece hashes it covers exactly the bytes of one file, so a complete file can be
+/// verified without reading the files it shares pieces with.
+///
+/// The BEP specifies the 20 raw bytes of the hash, but some torrents (e.g. the ones generated by
+/// archive.org) use the 40 character hex form instead. Both are accepted and the hash is
+/// serialized back in the form it was read in, so that the info hash does not change.
+#[derive(Clone, Copy, Eq)]
+pub struct FileHash {
+    bytes: [u8; 20],
+    hex: bool,
+}
+
+impl FileHash {
+    pub fn as_bytes(&self) -> &[u8; 20] {
+        &self.bytes
+    }
+
+    /// Returns `true` if the data hashes to this hash.
+    pub fn verify(&self, data: &[u8]) -> bool {
+        sha1_smol::Sha1::from(data).digest().bytes() == self.bytes
+    }
+}
+
+impl From<[u8; 20]> for FileHash {
+    fn from(bytes: [u8; 20]) -> Self {
+        FileHash { bytes, hex: false }
+    }
+}
+
+impl PartialEq for FileHash {
+    fn eq(&self, other: &Self) -> bool {
+        self.bytes == other.bytes
+    }
+}
+
+impl std::hash::Hash for FileHash {
+    fn hash<H: std::hash::Hasher>(&self, state: &mut H) {
+        self.bytes.hash(state);
+    }
+}
+
+impl Display for FileHash {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(f, "{}", hex::encode(self.bytes))
+    }
+}
+
+impl std::fmt::Debug for FileHash {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.debug_tuple("FileHash").field(&self.to_string()).finish()
+    }
+}
+
+struct FileHashVisitor;
+
+impl Visitor<'_> for FileHashVisitor {
+    type Value = FileHash;
+
+    fn expecting(&self, formatter: &mut std::fmt::Formatter) -> std::fmt::Result {
+        write!(
+            formatter,
+            "A 20 byte sha1 hash of the file or its 40 character hex form"
+        )
+    }
+
+    fn visit_bytes<E>(self, v: &[u8]) -> Result<Self::Value, E>
+    where
+        E: serde::de::Error,
+    {
+        let mut bytes = [0; 20];
+        match v.len() {
+            20 => bytes.copy_from_slice(v),
+            40 => hex::decode_to_slice(v, &mut bytes)
+                .map_err(|_| E::invalid_value(serde::de::Unexpected::Bytes(v), &self))?,
+            len => return Err(E::invalid_length(len, &self)),
+        }
+        Ok(FileHash {
+            bytes,
+            hex: v.len() == 40,
+        })
+    }
+
+    fn visit_str<E>(self, v: &str) -> Result<Self::Value, E>
+    where
+        E: serde::de::Error,
+    {
+        self.visit_bytes(v.as_bytes())
+    }
+}
+
+impl Serialize for FileHash {
+    fn serialize<S>(&self, serializer: S) -> Result<S::Ok, S::Error>
+    where
+        S: serde::Serializer,
+    {
+        if self.hex {
+            serializer.serialize_str(&self.to_string())
+        } else {
+            serializer.serialize_bytes(&self.bytes)
+        }
+    }
+}
+
+impl<'de> Deserialize<'de> for FileHash {
+    fn deserialize<D>(deserializer: D) -> Result<Self, D::Error>
+    where
+        D: serde::Deserializer<'de>,
+    {
+        deserializer.deserialize_bytes(FileHashVisitor)
+    }
 }
 
 /// Reprasents the various values of a attr field within files of the torrent.
 ///
 /// This is a bittorent extension as described in [BEP
 /// 47](https://www.bittorrent.org/beps/bep_0047.html)
-#[derive(Debug, PartialEq, PartialOrd)]
+#[derive(Debug, Clone, PartialEq, PartialOrd)]
 pub enum FileAttr {
     /// Padding files are synthetic files inserted into the file list to let the following file
     /// start at a piece boundary. That means their length should fill up the remainder of the
@@ -116,12 +280,48 @@ pub enum FileAttr {
 
     Hidden,
 
+    /// Unknown attributes and combinations of several attributes (e.g. `xh`).
     Other(String),
 }
 
 impl FileAttr {
     pub fn is_padding_file(&self) -> bool {
-        matches!(self, FileAttr::Padding)
+        self.has(PADDING_ATTR)
+    }
+
+    pub fn is_symlink(&self) -> bool {
+        self.has(SYMLINK_ATTR)
+    }
+
+    pub fn is_executable(&self) -> bool {
+        self.has(EXECUTABLE_ATTR)
+    }
+
+    pub fn is_hidden(&self) -> bool {
+        self.has(HIDDEN_ATTR)
+    }
+
+    /// The characters of the attributes which are not known to this library, if any.
+    pub(crate) fn unknown(&self) -> Option<String> {
+        let FileAttr::Other(s) = self else {
+            return None;
+        };
+        let known = [PADDING_ATTR, SYMLINK_ATTR, EXECUTABLE_ATTR, HIDDEN_ATTR];
+        let unknown: String = s
+            .chars()
+            .filter(|c| !known.iter().any(|attr| attr.starts_with(*c)))
+            .collect();
+        (!unknown.is_empty()).then_some(unknown)
+    }
+
+    fn has(&self, attr: &str) -> bool {
+        match self {
+            FileAttr::Padding => attr == PADDING_ATTR,
+            FileAttr::Symlink => attr == SYMLINK_ATTR,
+            FileAttr::Executable => attr == EXECUTABLE_ATTR,
+            FileAttr::Hidden => attr == HIDDEN_ATTR,
+            FileAttr::Other(s) => s.contains(attr),
+        }
     }
 }
 
@@ -169,6 +369,133 @@ impl<'de> Deserialize<'de> for FileAttr {
 pub struct FileTree<'a> {
     pub(crate) node: FileNode<'a>,
     pub(crate) num_of_files: usize,
+    pub(crate) stats: FileStats,
+}
+
+/// A file in a [`FileTree`], as returned by [`FileTree::file`].
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct FileEntry<'t> {
+    /// Name of the file.
+    pub name: &'t str,
+
+    /// Length of the file in bytes.
+    pub length: usize,
+
+    /// The BEP 47 attributes of the file.
+    pub attr: Option<&'t FileAttr>,
+
+    /// The BEP 47 SHA1 hash of the contents of the file.
+    pub sha1: Option<&'t FileHash>,
+
+    /// Path of the target of the symlink relative to the torrent root, if the file is a symlink.
+    pub symlink_path: Option<&'t [ByteString]>,
+}
+
+/// Options to control which files are added to a [`FileTree`]. See
+/// [`MetaInfo::build_file_tree_with`](super::MetaInfo::build_file_tree_with).
+#[derive(Debug, Clone, Copy, Default)]
+pub struct TreeOptions {
+    /// Leave the zero-length files out of the tree. They are still counted in
+    /// [`FileStats::empty_files`].
+    pub skip_empty_files: bool,
+}
+
+/// Aggregate statistics of the files in a [`FileTree`], collected while the tree is built.
+///
+/// Padding files are not included.
+#[derive(Debug, Clone, Default, PartialEq)]
+pub struct FileStats {
+    /// Number of files in the tree.
+    pub files: usize,
+
+    /// Sum of the sizes of all the files in bytes.
+    pub total_size: usize,
+
+    /// Number of zero-length files in the torrent, whether or not they were added to the tree.
+    pub empty_files: usize,
+
+    /// Number of files left out of the tree because their path is empty, goes through another
+    /// file or is the same as the path of a previous entry.
+    pub skipped: usize,
+
+    /// Path and size of the smallest non-empty file.
+    pub smallest: Option<(String, usize)>,
+
+    /// Path and size of the largest file.
+    pub largest: Option<(String, usize)>,
+
+    /// Number of files and their total size per (lowercased) file extension. Files without an
+    /// extension are counted under the empty string.
+    pub extensions: BTreeMap<String, ExtensionStats>,
+}
+
+/// Number of files and their total size for a single file extension in [`FileStats`].
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub struct ExtensionStats {
+    pub files: usize,
+    pub size: usize,
+}
+
+impl FileStats {
+    pub(crate) fn add(&mut self, path: &[ByteString], length: usize) {
+        self.files += 1;
+        self.total_size += length;
+
+        let path: Vec<&str> = path
+            .iter()
+            .map(ByteString::as_str)
+            .filter(|c| !c.is_empty())
+            .collect();
+
+        if length > 0 && self.smallest.as_ref().is_none_or(|(_, min)| length < *min) {
+            self.smallest = Some((path.join("/"), length));
+        }
+        if self.largest.as_ref().is_none_or(|(_, max)| length > *max) {
+            self.largest = Some((path.join("/"), length));
+        }
+
+        let extension = path
+            .last()
+            .and_then(|name| name.trim_start_matches('.').rsplit_once('.'))
+            .map(|(_, ext)| ext.to_lowercase())
+            .unwrap_or_default();
+        let entry = self.extensions.entry(extension).or_default();
+        entry.files += 1;
+        entry.size += length;
+    }
+
+    /// Mean size of the files in bytes, if there are any files.
+    pub fn mean_size(&self) -> Option<usize> {
+        self.total_size.checked_div(self.files)
+    }
+
+    /// The extensions sorted by their total size, largest first.
+    pub fn extensions_by_size(&self) -> Vec<(&str, ExtensionStats)> {
+        let mut extensions: Vec<_> = self
+            .extensions
+            .iter()
+            .map(|(ext, stats)| (ext.as_str(), *stats))
+            .collect();
+        extensions.sort_by_key(|(_, stats)| std::cmp::Reverse(stats.size));
+        extensions
+    }
+}
+
+/// Options to limit the output of [`FileTree::print_with`].
+#[derive(Debug, Clone, Copy, Default)]
+pub struct PrintOptions {
+    /// Directories deeper than this are summarized instead of printing their contents. The root
+    /// directory is at depth 0.
+    pub max_depth: Option<usize>,
+
+    /// Print only the N largest entries of each directory. The rest are summarized.
+    pub top: Option<usize>,
+
+    /// Print only the directories along with the number of files in them.
+    pub dirs_only: bool,
+
+    /// How the sizes of the files and directories are printed.
+    pub size_format: SizeFormat,
 }
 
 /// Value enum to be passed as an argument to [`FileTree::sort_by_name`] or
@@ -197,12 +524,89 @@ impl<'a> FileTree<'a> {
     /// indentation.
     #[cfg(feature = "client")]
     pub fn print(&self) {
-        self.node.print_tree(4);
+        self.print_with(&PrintOptions::default());
+    }
+
+    /// Same as [`FileTree::print`] but limits the output as per the provided [`PrintOptions`].
+    /// Useful for torrents containing thousands of files.
+    #[cfg(feature = "client")]
+    pub fn print_with(&self, opts: &PrintOptions) {
+        self.node.print_tree(4, 0, opts);
     }
 
     pub fn number_of_files(&self) -> usize {
         self.num_of_files
     }
+
+    /// Converts the tree into one that owns all of its data, so that it no longer borrows from
+    /// the [`Info`](super::Info) it was built from.
+    pub fn into_owned(self) -> FileTree<'static> {
+        FileTree {
+            node: self.node.into_owned(),
+            num_of_files: self.num_of_files,
+            stats: self.stats,
+        }
+    }
+
+    /// Returns the aggregate statistics of the files in the tree.
+    pub fn stats(&self) -> &FileStats {
+        &self.stats
+    }
+
+    /// Looks up the file at the `/` separated path relative to the torrent root. In the single
+    /// file case the path is the name of the torrent. Returns `None` for directories.
+    pub fn file(&self, path: &str) -> Option<FileEntry<'_>> {
+        let mut components = path.split('/').filter(|c| !c.is_empty());
+        let node = match &self.node {
+            FileNode::Dir { .. } => components.try_fold(&self.node, |node, name| match node {
+                FileNode::Dir { children, .. } => children.get(name),
+                FileNode::File { .. } => None,
+            })?,
+            FileNode::File { name, .. } => {
+                let found = components.next() == Some(name.as_ref()) && components.next().is_none();
+                found.then_some(&self.node)?
+            }
+        };
+
+        match node {
+            FileNode::File { name, length, meta } => Some(FileEntry {
+                name,
+                length: *length,
+                attr: meta.attr.as_deref(),
+                sha1: meta.sha1.as_ref(),
+                symlink_path: meta.symlink_path.as_deref(),
+            }),
+            FileNode::Dir { .. } => None,
+        }
+    }
+}
+
+/// The BEP 47 details of a file in the tree.
+#[derive(Debug, Clone, Default, PartialEq)]
+pub(crate) struct FileMeta<'a> {
+    pub(crate) attr: Option<Cow<'a, FileAttr>>,
+    pub(crate) sha1: Option<FileHash>,
+    pub(crate) symlink_path: Option<Cow<'a, [ByteString]>>,
+}
+
+impl<'a> FileMeta<'a> {
+    fn into_owned(self) -> FileMeta<'static> {
+        FileMeta {
+            attr: self.attr.map(|attr| Cow::Owned(attr.into_owned())),
+            sha1: self.sha1,
+            symlink_path: self.symlink_path.map(|path| Cow::Owned(path.into_owned())),
+        }
+    }
+}
+
+impl<'a> From<&'a MultiFiles> for FileMeta<'a> {
+    fn from(file: &'a MultiFiles) -> Self {
+        FileMeta {
+            attr: file.attr.as_ref().map(Cow::Borrowed),
+            sha1: file.sha1,
+            symlink_path: file.symlink_path.as_deref().map(Cow::Borrowed),
+        }
+    }
 }
 
 #[derive(Debug, Clone, PartialEq)]
@@ -215,6 +619,7 @@ pub(crate) enum FileNode<'a> {
     File {
         name: Cow<'a, str>,
         length: usize,
+        meta: FileMeta<'a>,
     },
 }
 
@@ -232,46 +637,80 @@ impl<'a> FileNode<'a> {
     }
 
     #[inline]
-    pub(crate) fn new_file(name: &'a str, length: usize) -> Self {
+    pub(crate) fn new_file(name: &'a str, length: usize, meta: FileMeta<'a>) -> Self {
         FileNode::File {
             name: Cow::from(name),
             length,
+            meta,
         }
     }
 
-    #[inline]
-    pub(crate) fn add_child(&mut self, path: &'a [String], size: usize) {
-        if path.is_empty() {
-            return;
+    /// Adds the file at the path (relative to this directory) to the tree. Empty components of
+    /// the path are ignored.
+    ///
+    /// Returns `false`, leaving the tree untouched, if the file can not be added: the path is
+    /// empty, goes through an existing file or is the path of an existing file or directory.
+    pub(crate) fn add_child(
+        &mut self,
+        path: &'a [ByteString],
+        size: usize,
+        meta: FileMeta<'a>,
+    ) -> bool {
+        let components: Vec<&'a ByteString> = path.iter().filter(|c| !c.is_empty()).collect();
+        !components.is_empty() && self.insert(&components, size, meta)
+    }
+
+    fn insert(&mut self, path: &[&'a ByteString], size: usize, meta: FileMeta<'a>) -> bool {
+        let FileNode::Dir {
+            children, length, ..
+        } = self
+        else {
+            return false;
+        };
+        let Some((current, rest)) = path.split_first() else {
+            return false;
+        };
+
+        let added = if rest.is_empty() {
+            if children.contains_key(current.as_str()) {
+                false
+            } else {
+                children.insert(current.to_string(), FileNode::new_file(current, size, meta));
+                true
+            }
+        } else {
+            // Sub directories are created as needed.
+            children
+                .entry(current.to_string())
+                .or_insert_with(|| FileNode::new_dir(current))
+                .insert(rest, size, meta)
+        };
+
+        if added {
+            *length += size;
         }
+        added
+    }
 
+    fn into_owned(self) -> FileNode<'static> {
         match self {
             FileNode::Dir {
-                children, length, ..
-            } => {
-                if path.is_empty() {
-                    return;
-                }
-
-                let current = path.first().unwrap();
-                let child = children
-                    .entry(current.clone())
-                    .or_insert_with(|| FileNode::new_dir(current));
-
-                // Add sub directories recursively. The the last entry in the files list is hit,
-                // change FilesNode::Dir entry to FilesNode::Files
-                if path.len() > 1 {
-                    *length += size;
-                    child.add_child(&path[1..], size);
-                } else {
-                    *child = FileNode::new_file(current, size);
-                    *length += size;
-                }
-            }
-            FileNode::File { .. } => {
-                // If we're trying to add a path to a file, something's wrong
-                panic!("Attempting to add a path to a file node");
-            }
+                parent,
+                children,
+                length,
+            } => FileNode::Dir {
+                parent: Cow::Owned(parent.into_owned()),
+                children: children
+                    .into_iter()
+                    .map(|(name, child)| (name, child.into_owned()))
+                    .collect(),
+                length,
+            },
+            FileNode::File { name, length, meta } => FileNode::File {
+                name: Cow::Owned(name.into_owned()),
+                length,
+                meta: meta.into_owned(),
+            },
         }
     }
 
@@ -304,7 +743,7 @@ impl<'a> FileNode<'a> {
                 children.sort_by(|k1, _, k2, _| k2.to_lowercase().cmp(&k1.to_lowercase()));
 
                 for child in children.values_mut() {
-                    child.sort_by_name_ascending();
+                    child.sort_by_name_desending();
                 }
             }
             FileNode::File { .. } => (),
@@ -332,50 +771,137 @@ impl<'a> FileNode<'a> {
                 children.sort_by(|_, v1, _, v2| v2.len().cmp(&v1.len()));
 
                 for child in children.values_mut() {
-                    child.sort_by_size_ascending();
+                    child.sort_by_size_desending();
                 }
             }
             FileNode::File { .. } => (),
         }
     }
 
+    #[inline]
+    fn number_of_files(&self) -> usize {
+        match self {
+            FileNode::Dir { children, .. } => children.values().map(Self::number_of_files).sum(),
+            FileNode::File { .. } => 1,
+        }
+    }
+
+    /// Selects the children to be printed as per the options, keeping their current order.
+    /// Returns the selected children along with the number and total size of the rest.
+    fn select_children(&self, opts: &PrintOptions) -> (Vec<&FileNode<'a>>, usize, usize) {
+        let FileNode::Dir { children, .. } = self else {
+            return (Vec::new(), 0, 0);
+        };
+
+        let mut selected: Vec<&FileNode<'a>> = children
+            .values()
+            .filter(|child| !opts.dirs_only || matches!(child, FileNode::Dir { .. }))
+            .collect();
+
+        let mut hidden = (0, 0);
+        if let Some(top) = opts.top {
+            if selected.len() > top {
+                let mut by_size: Vec<usize> = (0..selected.len()).collect();
+                by_size.sort_by_key(|&i| std::cmp::Reverse(selected[i].len()));
+                let mut keep = vec![false; selected.len()];
+                for &i in &by_size[..top] {
+                    keep[i] = true;
+                }
+
+                let mut keep = keep.into_iter();
+                selected.retain(|child| {
+                    let keep = keep.next().unwrap_or_default();
+                    if !keep {
+                        hidden.0 += 1;
+                        hidden.1 += child.len();
+                    }
+                    keep
+                });
+            }
+        }
+
+        (selected, hidden.0, hidden.1)
+    }
+
     /// Recursively prints the file tree in a human-readable format, using indentation.
     ///
     /// ## Arguments:
     ///
     /// `indent`: Indentation step to use for printing child data in the file structure hirarcy.
+    /// `depth`: Depth of this node in the tree.
+    /// `opts`: Limits on what is printed.
     #[cfg(feature = "client")]
     #[inline]
-    fn print_tree(&self, mut indent: usize) {
+    fn print_tree(&self, mut indent: usize, depth: usize, opts: &PrintOptions) {
         use colored::Colorize;
 
         match self {
-            FileNode::Dir {
-                parent,
-                children,
-                length,
-            } => {
+            FileNode::Dir { parent, length, .. } => {
+                let collapsed = opts.max_depth.is_some_and(|max| depth >= max);
+
                 println!();
-                println!(
-                    "{:indent$} - {} ({})",
-                    "",
-                    parent.bold().underline().green(),
-                    human_bytes(*length as f64),
-                    indent = indent,
-                );
+                if collapsed || opts.dirs_only {
+                    println!(
+                        "{:indent$} - {} ({}, {} files)",
+                        "",
+                        parent.bold().underline().green(),
+                        opts.size_format.format(*length),
+                        self.number_of_files(),
+                        indent = indent,
+                    );
+                } else {
+                    println!(
+                        "{:indent$} - {} ({})",
+                        "",
+                        parent.bold().underline().green(),
+                        opts.size_format.format(*length),
+                        indent = indent,
+                    );
+                }
+
+                if collapsed {
+                    return;
+                }
 
                 indent += 4;
 
-                for child in children.values() {
-                    child.print_tree(indent);
+                let (children, hidden, hidden_length) = self.select_children(opts);
+                for child in children {
+                    child.print_tree(indent, depth + 1, opts);
+                }
+
+                if hidden > 0 {
+                    println!(
+                        "{:indent$} {}",
+                        "",
+                        format!(
+                            "... and {hidden} more ({})",
+                            opts.size_format.format(hidden_length)
+                        )
+                        .italic()
+                        .dimmed(),
+                        indent = indent
+                    );
                 }
             }
-            FileNode::File { name, length } => {
+            FileNode::File { name, length, meta } => {
+                let target = meta
+                    .symlink_path
+                    .as_ref()
+                    .map(|path| format!(" -> {}", path.join("/")).dimmed().to_string())
+                    .unwrap_or_default();
+                let attr = meta
+                    .attr
+                    .as_ref()
+                    .map(|attr| format!(" ({attr})").yellow().to_string())
+                    .unwrap_or_default();
                 println!(
-                    "{:indent$} - {} ({})",
+                    "{:indent$} - {}{}{} ({})",
                     "",
                     name.bold(),
-                    human_bytes(*length as f64).cyan(),
+                    target,
+                    attr,
+                    opts.size_format.format(*length).cyan(),
                     indent = indent
                 );
             }
@@ -412,11 +938,11 @@ mod files_tests {
     fn test_create_new_file() {
         let file_name = "file.txt";
         let file_size = 1024;
-        let file = FileNode::new_file(file_name, file_size);
+        let file = FileNode::new_file(file_name, file_size, FileMeta::default());
 
         // Test if the file is created successfully
         match file {
-            FileNode::File { name, length } => {
+            FileNode::File { name, length, .. } => {
                 assert_eq!(name, Cow::from(file_name));
                 assert_eq!(length, file_size);
             }
@@ -428,11 +954,11 @@ mod files_tests {
     fn test_add_file_to_directory() {
         let mut root = FileNode::new_dir("root");
 
-        let path = vec![String::from("file.txt")];
+        let path = vec![ByteString::from("file.txt")];
         let size = 512;
 
         // Add a file to the root directory
-        root.add_child(&path, size);
+        root.add_child(&path, size, FileMeta::default());
 
         // Test if the file was added to the directory
         match root {
@@ -448,7 +974,7 @@ mod files_tests {
                     .get("file.txt")
                     .expect("File not found in directory!");
                 match child {
-                    FileNode::File { name, length } => {
+                    FileNode::File { name, length, .. } => {
                         assert_eq!(name, "file.txt");
                         assert_eq!(*length, size);
                     }
@@ -460,10 +986,213 @@ mod files_tests {
     }
 
     #[test]
-    #[should_panic(expected = "Attempting to add a path to a file node")]
-    fn test_add_child_to_file_should_panic() {
-        let mut file = FileNode::new_file("file.txt", 1024);
-        let path = vec![String::from("new_file.txt")];
-        file.add_child(&path, 512); // This should panic as we can't add children to a file node.
+    fn test_file_attr_flags() {
+        let attr: FileAttr = zung_parsers::bencode::from_str("2:xh").unwrap();
+        assert_eq!(attr, FileAttr::Other(String::from("xh")));
+        assert!(attr.is_executable());
+        assert!(attr.is_hidden());
+        assert!(!attr.is_symlink());
+        assert!(!attr.is_padding_file());
+
+        assert!(FileAttr::Symlink.is_symlink());
+        assert!(!FileAttr::Symlink.is_executable());
+    }
+
+    #[test]
+    fn test_file_hash() {
+        let hash = FileHash::from(sha1_smol::Sha1::from("spam").digest().bytes());
+        assert!(hash.verify(b"spam"));
+        assert!(!hash.verify(b"eggs"));
+
+        let encoded = zung_parsers::bencode::to_bytes(&hash).unwrap();
+        assert_eq!(
+            zung_parsers::bencode::from_bytes::<FileHash>(&encoded).unwrap(),
+            hash
+        );
+        assert!(zung_parsers::bencode::from_str::<FileHash>("4:spam").is_err());
+
+        let encoded = format!("40:{hash}");
+        let hex_hash = zung_parsers::bencode::from_str::<FileHash>(&encoded).unwrap();
+        assert_eq!(hex_hash, hash);
+        assert_eq!(
+            zung_parsers::bencode::to_bytes(&hex_hash).unwrap(),
+            encoded.as_bytes()
+        );
+    }
+
+    #[test]
+    fn test_add_child_keeps_attr() {
+        let mut root = FileNode::new_dir("root");
+        let path = vec![ByteString::from("bin"), ByteString::from("run.sh")];
+        let meta = FileMeta {
+            attr: Some(Cow::Owned(FileAttr::Executable)),
+            ..Default::default()
+        };
+        assert!(root.add_child(&path, 10, meta));
+
+        let FileNode::Dir { children, .. } = root.into_owned() else {
+            panic!("Expected a directory node!");
+        };
+        let FileNode::Dir { children, .. } = &children["bin"] else {
+            panic!("Expected a directory node!");
+        };
+        assert_eq!(
+            children["run.sh"],
+            FileNode::File {
+                name: Cow::from("run.sh"),
+                length: 10,
+                meta: FileMeta {
+                    attr: Some(Cow::Owned(FileAttr::Executable)),
+                    ..Default::default()
+                },
+            }
+        );
+    }
+
+    #[test]
+    fn test_add_child_to_file_is_rejected() {
+        let mut file = FileNode::new_file("file.txt", 1024, FileMeta::default());
+        let path = vec![ByteString::from("new_file.txt")];
+        assert!(!file.add_child(&path, 512, FileMeta::default()));
+        assert_eq!(
+            file,
+            FileNode::new_file("file.txt", 1024, FileMeta::default())
+        );
+    }
+
+    #[test]
+    fn test_add_child_conflicts_and_empty_components() {
+        let paths = [
+            vec![
+                ByteString::from("dir"),
+                ByteString::default(),
+                ByteString::from("a.txt"),
+            ],
+            vec![ByteString::from("dir"), ByteString::from("a.txt")],
+            vec![
+                ByteString::from("dir"),
+                ByteString::from("a.txt"),
+                ByteString::from("b"),
+            ],
+            vec![ByteString::from("dir")],
+            vec![ByteString::default()],
+            vec![],
+        ];
+        let mut root = FileNode::new_dir("root");
+
+        assert!(root.add_child(&paths[0], 4, FileMeta::default()));
+        // The same path once the empty component is ignored.
+        assert!(!root.add_child(&paths[1], 8, FileMeta::default()));
+        // Goes through a file.
+        assert!(!root.add_child(&paths[2], 8, FileMeta::default()));
+        // Replaces a directory.
+        assert!(!root.add_child(&paths[3], 8, FileMeta::default()));
+        assert!(!root.add_child(&paths[4], 8, FileMeta::default()));
+        assert!(!root.add_child(&paths[5], 8, FileMeta::default()));
+
+        assert_eq!(root.number_of_files(), 1);
+        assert_eq!(root.len(), 4);
+    }
+
+    #[test]
+    fn test_sort_recurses_with_the_same_order() {
+        let paths = [
+            vec![ByteString::from("b"), ByteString::from("small")],
+            vec![ByteString::from("b"), ByteString::from("large")],
+            vec![ByteString::from("a.txt")],
+        ];
+        let mut root = FileNode::new_dir("root");
+        root.add_child(&paths[0], 1, FileMeta::default());
+        root.add_child(&paths[1], 100, FileMeta::default());
+        root.add_child(&paths[2], 10, FileMeta::default());
+
+        fn names(node: &FileNode) -> Vec<String> {
+            match node {
+                FileNode::Dir { children, .. } => children
+                    .iter()
+                    .flat_map(|(name, child)| std::iter::once(name.clone()).chain(names(child)))
+                    .collect(),
+                FileNode::File { .. } => Vec::new(),
+            }
+        }
+
+        root.sort_by_name_desending();
+        assert_eq!(names(&root), ["b", "small", "large", "a.txt"]);
+
+        root.sort_by_name_ascending();
+        assert_eq!(names(&root), ["a.txt", "b", "large", "small"]);
+
+        root.sort_by_size_desending();
+        assert_eq!(names(&root), ["b", "large", "small", "a.txt"]);
+
+        root.sort_by_size_ascending();
+        assert_eq!(names(&root), ["a.txt", "b", "small", "large"]);
+    }
+
+    #[test]
+    fn test_select_children() {
+        let paths = [
+            vec![ByteString::from("a"), ByteString::from("x")],
+            vec![ByteString::from("b.txt")],
+            vec![ByteString::from("c.txt")],
+            vec![ByteString::from("d.txt")],
+        ];
+        let mut root = FileNode::new_dir("root");
+        root.add_child(&paths[0], 5, FileMeta::default());
+        root.add_child(&paths[1], 50, FileMeta::default());
+        root.add_child(&paths[2], 1, FileMeta::default());
+        root.add_child(&paths[3], 20, FileMeta::default());
+        assert_eq!(root.number_of_files(), 4);
+
+        let opts = PrintOptions {
+            top: Some(2),
+            ..Default::default()
+        };
+        let (selected, hidden, hidden_length) = root.select_children(&opts);
+        let lengths: Vec<usize> = selected.iter().map(|child| child.len()).collect();
+        assert_eq!(lengths, [50, 20]);
+        assert_eq!((hidden, hidden_length), (2, 6));
+
+        let opts = PrintOptions {
+            dirs_only: true,
+            ..Default::default()
+        };
+        let (selected, hidden, _) = root.select_children(&opts);
+        assert_eq!(selected.len(), 1);
+        assert_eq!(hidden, 0);
+    }
+
+    #[test]
+    fn test_file_stats() {
+        let mut stats = FileStats::default();
+        assert_eq!(stats.mean_size(), None);
+
+        stats.add(&[ByteString::from("dir"), ByteString::from("a.MP4")], 100);
+        stats.add(&[ByteString::from("b.mp4")], 300);
+        stats.add(&[ByteString::from("c.srt")], 2);
+        stats.add(&[ByteString::from(".hidden")], 6);
+        stats.add(&[ByteString::from("empty.srt")], 0);
+
+        assert_eq!(stats.files, 5);
+        assert_eq!(stats.total_size, 408);
+        assert_eq!(stats.mean_size(), Some(81));
+        assert_eq!(stats.smallest, Some((String::from("c.srt"), 2)));
+        assert_eq!(stats.largest, Some((String::from("b.mp4"), 300)));
+
+        let extensions = stats.extensions_by_size();
+        assert_eq!(
+            extensions,
+            [
+                (
+                    "mp4",
+                    ExtensionStats {
+                        files: 2,
+                        size: 400
+                    }
+                ),
+                ("", ExtensionStats { files: 1, size: 6 }),
+                ("srt", ExtensionStats { files: 2, size: 2 }),
+            ]
+        );
     }
 }
@@ -1,13 +1,20 @@
 use std::{
     borrow::Cow,
+    collections::BTreeMap,
     fmt::{Debug, Display},
     ops::Deref,
+    str::FromStr,
 };
 
+use anyhow::{Context, Result};
+
 use serde::{Deserialize, Serialize};
+use zung_parsers::bencode::Value;
 
 use super::{
-    files::{FileAttr, FileNode, FileTree, Files},
+    byte_string::ByteString,
+    capabilities::TorrentVersion,
+    files::{FileAttr, FileMeta, FileNode, FileStats, FileTree, Files, TreeOptions},
     pieces::Pieces,
 };
 
@@ -71,7 +78,24 @@ pub struct Info {
 
     // In the single file state this is the filename. In the multifile state this is the the name
     // of the directory in which to store all the files. This is purely advisory. (string)
-    pub(crate) name: String,
+    pub(crate) name: ByteString,
+
+    // Any other keys (e.g. from extensions) which are not known to this library.
+    #[serde(flatten, deserialize_with = "deserialize_extra")]
+    pub(crate) extra: BTreeMap<String, Value>,
+}
+
+// The untagged `files` enum does not consume the keys it is deserialized from, so they have to be
+// removed from the unknown keys by hand.
+fn deserialize_extra<'de, D>(deserializer: D) -> Result<BTreeMap<String, Value>, D::Error>
+where
+    D: serde::Deserializer<'de>,
+{
+    let mut extra = BTreeMap::<String, Value>::deserialize(deserializer)?;
+    for key in Files::KEYS {
+        extra.remove(*key);
+    }
+    Ok(extra)
 }
 
 impl<'a> Info {
@@ -83,68 +107,187 @@ impl<'a> Info {
         n_pieces * plen
     }
 
+    /// Length of the contents of the torrent in bytes, i.e. the sum of the lengths of all the
+    /// files (including the padding files).
+    pub fn content_length(&self) -> usize {
+        match &self.files {
+            Files::SingleFile { length, .. } => *length,
+            Files::MultiFile { files } => files.iter().map(|file| file.length).sum(),
+        }
+    }
+
     /// Builds the file tree of the torrent file.
     pub(crate) fn build_file_tree(&'a self) -> FileTree<'a> {
+        self.build_file_tree_with(TreeOptions::default())
+    }
+
+    /// Builds the file tree of the torrent file as per the provided [`TreeOptions`].
+    pub(crate) fn build_file_tree_with(&'a self, opts: TreeOptions) -> FileTree<'a> {
         // self.files enum is constructed while deserializing the torrent file.
         match &self.files {
             // TODO: Support for md5sum
             Files::SingleFile {
                 length,
                 md5sum: _,
-                attr: _, // TODO: attr support for single file case?
+                attr,
+                sha1,
             } => {
                 let node = FileNode::File {
-                    name: Cow::from(&self.name),
+                    name: Cow::from(self.name.as_str()),
                     length: *length,
+                    meta: FileMeta {
+                        attr: attr.as_ref().map(Cow::Borrowed),
+                        sha1: *sha1,
+                        symlink_path: None,
+                    },
                 };
+                let mut stats = FileStats::default();
+                if *length == 0 {
+                    stats.empty_files += 1;
+                }
+                stats.add(std::slice::from_ref(&self.name), *length);
                 FileTree {
                     node,
                     num_of_files: 1,
+                    stats,
                 } // File count is 1 of singlefile state. duh.
             }
             Files::MultiFile { files } => {
                 let mut root = FileNode::new_dir(&self.name);
                 let mut num_of_files = 0;
+                let mut stats = FileStats::default();
                 for file in files {
-                    if let Some(FileAttr::Padding) = file.attr {
+                    if file.attr.as_ref().is_some_and(FileAttr::is_padding_file) {
                         continue;
                     }
 
+                    if file.length == 0 {
+                        stats.empty_files += 1;
+                        if opts.skip_empty_files {
+                            continue;
+                        }
+                    }
+
                     let path = &file.path;
 
-                    root.add_child(path, file.length);
-                    num_of_files += 1;
+                    if root.add_child(path, file.length, FileMeta::from(file)) {
+                        stats.add(path, file.length);
+                        num_of_files += 1;
+                    } else {
+                        stats.skipped += 1;
+                    }
                 }
                 FileTree {
                     node: root,
                     num_of_files,
+                    stats,
                 }
             }
         }
     }
 
+    /// The name of the file or directory of the torrent, with the invalid UTF-8 sequences
+    /// replaced by `U+FFFD`.
     pub fn name(&self) -> &str {
         &self.name
     }
+
+    /// The name as it is in the torrent file, which is not always valid UTF-8.
+    pub fn name_bytes(&self) -> &[u8] {
+        self.name.as_bytes()
+    }
+
+    /// Returns the single file or multi file state of the torrent along with the files.
+    pub fn files(&self) -> &Files {
+        &self.files
+    }
+
+    /// Returns `true` if the data hashes to the SHA1 hash of the piece at `index`.
+    pub fn verify_piece(&self, index: usize, data: &[u8]) -> bool {
+        self.pieces.verify(index, data)
+    }
+
+    /// Returns the SHA1 hash of the piece at `index`, or `None` if there is no such piece.
+    pub fn piece_hash(&self, index: usize) -> Option<&[u8; 20]> {
+        self.pieces.get(index)
+    }
+
+    /// Returns the keys of the info dictionary which are not known to this library along with
+    /// their values.
+    pub fn extra_keys(&self) -> &BTreeMap<String, Value> {
+        &self.extra
+    }
+
+    /// Returns `true` if the `private` flag is set, in which case peers may only be obtained from
+    /// the trackers of the torrent.
+    pub fn is_private(&self) -> bool {
+        self.private == Some(1)
+    }
+
+    /// Returns `true` if any file of the torrent is a padding file (BEP: 47).
+    pub fn has_padding_files(&self) -> bool {
+        match &self.files {
+            Files::SingleFile { attr, .. } => attr.as_ref().is_some_and(FileAttr::is_padding_file),
+            Files::MultiFile { files } => files
+                .iter()
+                .any(|file| file.attr.as_ref().is_some_and(FileAttr::is_padding_file)),
+        }
+    }
+
+    /// Returns the protocol version of the torrent.
+    ///
+    /// A torrent is v2 (BEP: 52) when its info dictionary has a `meta version` of 2 or a `file
+    /// tree`, and hybrid when it also has the v1 `pieces`.
+    pub fn version(&self) -> TorrentVersion {
+        let is_v2 = matches!(self.extra.get("meta version"), Some(Value::Integer(2)))
+            || self.extra.contains_key("file tree");
+        match (is_v2, self.pieces.is_empty()) {
+            (false, _) => TorrentVersion::V1,
+            (true, true) => TorrentVersion::V2,
+            (true, false) => TorrentVersion::Hybrid,
+        }
+    }
 }
 
 /// Urlencoded 20-byte SHA1 hash of the value of the info key from the Metainfo file.
-#[derive(Clone, PartialEq, Eq)]
+///
+/// The [`Display`] form is the 40 character lowercase hex string used by magnet URIs and most
+/// torrent clients, which can be parsed back with [`InfoHash::from_hex`] or [`str::parse`].
+///
+/// ```
+/// use zung_torrent::meta_info::InfoHash;
+///
+/// let hash: InfoHash = "7F06F8280A3B496F2AF0F78131CED619DF14A0C3".parse().unwrap();
+/// assert_eq!(hash.to_hex(), "7f06f8280a3b496f2af0f78131ced619df14a0c3");
+/// ```
+#[derive(Clone, PartialEq, Eq, Hash)]
 pub struct InfoHash {
-    sha1: sha1_smol::Sha1,
+    sha1: [u8; 20],
 }
 
 impl InfoHash {
     pub(crate) fn new(bytes: &[u8]) -> Self {
-        let mut sha1 = sha1_smol::Sha1::new();
-        sha1.update(bytes);
-        InfoHash { sha1 }
+        InfoHash {
+            sha1: sha1_smol::Sha1::from(bytes).digest().bytes(),
+        }
+    }
+
+    /// Parses the 40 character hex form of an info hash. The case of the letters is ignored.
+    pub fn from_hex(hex: &str) -> Result<Self> {
+        Ok(InfoHash {
+            sha1: decode_hex(hex)?,
+        })
+    }
+
+    /// Returns the 40 character lowercase hex form of the info hash.
+    pub fn to_hex(&self) -> String {
+        hex::encode(self.sha1)
     }
 
     /// Returns the infohash sha1 value as bytes.
     #[inline]
     pub fn as_bytes(&self) -> [u8; 20] {
-        self.sha1.digest().bytes()
+        self.sha1
     }
 
     /// Returns the infohash sha1 value as bytes.
@@ -168,10 +311,41 @@ impl InfoHash {
 
 impl Display for InfoHash {
     fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
-        write!(f, "{}", self.sha1.digest())
+        write!(f, "{}", self.to_hex())
+    }
+}
+
+impl Serialize for InfoHash {
+    /// Serializes the hex form of the info hash.
+    fn serialize<S>(&self, serializer: S) -> std::result::Result<S::Ok, S::Error>
+    where
+        S: serde::Serializer,
+    {
+        serializer.collect_str(self)
+    }
+}
+
+impl FromStr for InfoHash {
+    type Err = anyhow::Error;
+
+    fn from_str(s: &str) -> Result<Self> {
+        Self::from_hex(s)
     }
 }
 
+impl From<InfoHashEncoded> for InfoHash {
+    fn from(encoded: InfoHashEncoded) -> Self {
+        InfoHash { sha1: encoded.0 }
+    }
+}
+
+fn decode_hex(hex: &str) -> Result<[u8; 20]> {
+    let mut bytes = [0; 20];
+    hex::decode_to_slice(hex.trim(), &mut bytes)
+        .with_context(|| format!("Invalid info hash {hex:?} - Expected 40 hex characters"))?;
+    Ok(bytes)
+}
+
 impl Debug for InfoHash {
     fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
         f.debug_struct("InfoHash")
@@ -181,10 +355,20 @@ impl Debug for InfoHash {
 }
 
 /// 20 byte encoded form of the [`InfoHash`].
-#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
 pub struct InfoHashEncoded([u8; 20]);
 
 impl InfoHashEncoded {
+    /// Parses the 40 character hex form of an info hash. The case of the letters is ignored.
+    pub fn from_hex(hex: &str) -> Result<Self> {
+        decode_hex(hex).map(InfoHashEncoded)
+    }
+
+    /// Returns the 40 character lowercase hex form of the info hash.
+    pub fn to_hex(&self) -> String {
+        hex::encode(self.0)
+    }
+
     pub fn to_url_encoded(&self) -> String {
         let bytes = **self;
         let mut buff = String::with_capacity(60);
@@ -196,6 +380,14 @@ impl InfoHashEncoded {
     }
 }
 
+impl FromStr for InfoHashEncoded {
+    type Err = anyhow::Error;
+
+    fn from_str(s: &str) -> Result<Self> {
+        Self::from_hex(s)
+    }
+}
+
 impl Deref for InfoHashEncoded {
     type Target = [u8; 20];
 
@@ -224,8 +416,10 @@ mod tests {
                 length: 4096,
                 md5sum: None,
                 attr: None,
+                sha1: None,
             },
-            name: "test_file.txt".to_string(),
+            name: "test_file.txt".into(),
+            extra: BTreeMap::new(),
         };
 
         // We expect 4 pieces, each of size 1024 bytes
@@ -243,15 +437,17 @@ mod tests {
                 length: 4096,
                 md5sum: None,
                 attr: None,
+                sha1: None,
             },
-            name: "test_file.txt".to_string(),
+            name: "test_file.txt".into(),
+            extra: BTreeMap::new(),
         };
 
         let file_tree = info.build_file_tree();
 
         // Check if the file tree is built correctly for a single file
         match file_tree.node {
-            FileNode::File { name, length } => {
+            FileNode::File { name, length, .. } => {
                 assert_eq!(name, Cow::from("test_file.txt"));
                 assert_eq!(length, 4096);
             }
@@ -266,14 +462,20 @@ mod tests {
             MultiFiles {
                 length: 1024,
                 md5sum: None,
-                path: vec!["folder".to_string(), "file1.txt".to_string()],
+                path: vec!["folder".into(), "file1.txt".into()],
                 attr: None,
+                sha1: None,
+                symlink_path: None,
+                extra: BTreeMap::new(),
             },
             MultiFiles {
                 length: 2048,
                 md5sum: None,
-                path: vec!["folder".to_string(), "file2.txt".to_string()],
+                path: vec!["folder".into(), "file2.txt".into()],
                 attr: None,
+                sha1: None,
+                symlink_path: None,
+                extra: BTreeMap::new(),
             },
         ];
 
@@ -282,7 +484,8 @@ mod tests {
             pieces: Pieces::__test_build(), // Mocked 4 pieces
             private: None,
             files: Files::MultiFile { files },
-            name: "root_folder".to_string(),
+            name: "root_folder".into(),
+            extra: BTreeMap::new(),
         };
 
         let file_tree = info.build_file_tree();
@@ -300,7 +503,7 @@ mod tests {
                         assert_eq!(children.len(), 2);
                         let file1 = children.get("file1.txt").expect("File1 not found");
                         match file1 {
-                            FileNode::File { name, length } => {
+                            FileNode::File { name, length, .. } => {
                                 assert_eq!(name, "file1.txt");
                                 assert_eq!(*length, 1024);
                             }
@@ -309,7 +512,7 @@ mod tests {
 
                         let file2 = children.get("file2.txt").expect("File2 not found");
                         match file2 {
-                            FileNode::File { name, length } => {
+                            FileNode::File { name, length, .. } => {
                                 assert_eq!(name, "file2.txt");
                                 assert_eq!(*length, 2048);
                             }
@@ -322,4 +525,24 @@ mod tests {
             _ => panic!("Expected a directory node for 'root_folder'"),
         }
     }
+
+    #[test]
+    fn test_info_hash_hex() {
+        let hash = InfoHash::new(b"test info_hash");
+        let hex = hash.to_hex();
+        assert_eq!(hex, hash.to_string());
+        assert_eq!(hex.len(), 40);
+
+        assert_eq!(InfoHash::from_hex(&hex).unwrap(), hash);
+        assert_eq!(hex.to_uppercase().parse::<InfoHash>().unwrap(), hash);
+        assert_eq!(hex.parse::<InfoHashEncoded>().unwrap(), hash.as_encoded());
+        assert_eq!(hash.as_encoded().to_hex(), hex);
+        assert_eq!(InfoHash::from(hash.as_encoded()), hash);
+
+        assert!(InfoHash::from_hex(&hex[1..]).is_err());
+        assert!(InfoHash::from_hex(&hex.replace(&hex[..1], "z")).is_err());
+
+        let map = std::collections::HashMap::from([(hash.clone(), 1)]);
+        assert_eq!(map[&InfoHash::from_hex(&hex).unwrap()], 1);
+    }
 }
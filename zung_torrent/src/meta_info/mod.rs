@@ -29,16 +29,43 @@
 //!
 //! ```
 
+mod announce_list;
+mod builder;
+mod byte_string;
+mod capabilities;
 mod files;
 mod info;
 mod pieces;
-
-use anyhow::Result;
-use chrono::{DateTime, Utc};
+mod rename;
+mod size;
+mod spans;
+mod warnings;
+
+use anyhow::{Context, Result};
+use chrono::{DateTime, Datelike, Utc};
+use std::collections::BTreeMap;
+use std::path::Path;
 use zung_parsers::bencode;
 
-pub use files::{FileAttr, FileTree, Files, SortOrd};
+use crate::peers::Bitfield;
+
+pub use builder::{
+    MetaInfoBuilder, NewTorrent, PieceLength, SourceFile, MAX_AUTO_PIECE_LENGTH,
+    MIN_AUTO_PIECE_LENGTH,
+};
+pub use byte_string::ByteString;
+pub use capabilities::{Capabilities, TorrentVersion};
+pub use files::{
+    ExtensionStats, FileAttr, FileEntry, FileHash, FileStats, FileTree, Files, MultiFiles,
+    PrintOptions, SortOrd, TreeOptions,
+};
 pub use info::{Info, InfoHash, InfoHashEncoded};
+pub use rename::{rehash_pieces, rename_files, PathMapping};
+pub use size::SizeFormat;
+pub use spans::FileSpan;
+pub use warnings::ParseWarning;
+
+use announce_list::AnnounceList;
 
 use serde::{Deserialize, Serialize};
 
@@ -66,9 +93,9 @@ pub struct MetaInfo {
     pub(crate) url_list: Option<Vec<String>>,
 
     // (BEP: 12) This is an extension to the official specification, offering
-    // backwards-compatibility. (list of lists of strings).
+    // backwards-compatibility. (list of lists of strings, or of strings in some torrents).
     #[serde(rename = "announce-list")]
-    pub(crate) announce_list: Option<Vec<Vec<String>>>,
+    pub(crate) announce_list: Option<AnnounceList>,
 
     // Title of the torrent file
     pub(crate) title: Option<String>,
@@ -87,6 +114,10 @@ pub struct MetaInfo {
     // The string encoding format used to generate the pieces part of the info dictionary in
     // the .torrent metafile (string)
     pub(crate) encoding: Option<String>,
+
+    // Any other keys (e.g. from extensions) which are not known to this library.
+    #[serde(flatten)]
+    pub(crate) extra: BTreeMap<String, bencode::Value>,
 }
 
 /// Processors: process information from a torrent file.
@@ -99,17 +130,121 @@ impl MetaInfo {
         Ok(meta_info)
     }
 
+    /// Like [`MetaInfo::from_bytes`], also returning the oddities of the torrent file which did
+    /// not prevent reading it. See [`MetaInfo::warnings`].
+    pub fn from_bytes_with_warnings(bytes: &[u8]) -> Result<(Self, Vec<ParseWarning>)> {
+        let meta_info = Self::from_bytes(bytes)?;
+        let warnings = meta_info.warnings();
+        Ok((meta_info, warnings))
+    }
+
+    /// Serializes [`Self`] back into the bytes of a valid torrent file.
+    ///
+    /// The keys of every dictionary are written in sorted order, as required by the
+    /// specification, and unknown keys (see [`MetaInfo::extra_keys`]) are preserved. Hence the
+    /// `info` dictionary of an unmodified torrent is written byte for byte as it was read and its
+    /// info hash does not change.
+    pub fn to_bytes(&self) -> Result<Vec<u8>> {
+        let bytes = bencode::to_bytes(self)?;
+        Ok(bytes)
+    }
+
+    /// Writes [`Self`] as a torrent file to the provided path. See [`MetaInfo::to_bytes`].
+    pub fn save<P: AsRef<Path>>(&self, path: P) -> Result<()> {
+        let path = path.as_ref();
+        let bytes = self.to_bytes()?;
+        std::fs::write(path, bytes)
+            .with_context(|| format!("Unable to write file: {}", path.display()))
+    }
+
     pub fn build_file_tree(&self) -> FileTree<'_> {
         self.info.build_file_tree()
     }
 
+    /// Same as [`MetaInfo::build_file_tree`] but decides which files are added to the tree as per
+    /// the provided [`TreeOptions`].
+    pub fn build_file_tree_with(&self, opts: TreeOptions) -> FileTree<'_> {
+        self.info.build_file_tree_with(opts)
+    }
+
     pub fn size(&self) -> usize {
         self.info.torrent_size()
     }
+
+    /// Returns the length of the piece at `index` in bytes. Every piece is [`piece
+    /// length`](MetaInfo::piece_length) long except for the last one, which may be shorter.
+    pub fn piece_size(&self, index: usize) -> Option<usize> {
+        if index >= self.number_of_pieces() {
+            return None;
+        }
+        let start = index * self.piece_length();
+        Some(
+            self.info
+                .content_length()
+                .saturating_sub(start)
+                .min(self.piece_length()),
+        )
+    }
+
+    /// Returns the number of bytes still to be downloaded when the pieces set in the bitfield
+    /// have been downloaded and verified. This is the `left` value reported to the trackers.
+    pub fn bytes_left(&self, have: &Bitfield) -> usize {
+        let have: usize = have
+            .pieces()
+            .filter_map(|index| self.piece_size(index))
+            .sum();
+        self.info.content_length().saturating_sub(have)
+    }
+
+    /// Inspects the torrent for the protocol features it uses: its [version](TorrentVersion),
+    /// padding files, the `private` flag and web seeds.
+    ///
+    /// ```
+    /// use zung_torrent::meta_info::{MetaInfo, TorrentVersion};
+    ///
+    /// # fn capabilities(meta_info: &MetaInfo) {
+    /// let capabilities = meta_info.capabilities();
+    /// if capabilities.version == TorrentVersion::Hybrid {
+    ///     println!("Can be shared in both the v1 and the v2 swarms");
+    /// }
+    /// println!("{capabilities}");
+    /// # }
+    /// ```
+    pub fn capabilities(&self) -> Capabilities {
+        Capabilities {
+            version: self.version(),
+            padded: self.info.has_padding_files(),
+            private: self.info.is_private(),
+            web_seeds: self.number_of_httpsources() > 0,
+        }
+    }
+
+    /// Returns the protocol version of the torrent. See [`Info::version`].
+    ///
+    /// Besides the info dictionary, the top level `piece layers` key of v2 torrents is also
+    /// considered.
+    pub fn version(&self) -> TorrentVersion {
+        match self.info.version() {
+            TorrentVersion::V1 if self.extra.contains_key("piece layers") => TorrentVersion::Hybrid,
+            version => version,
+        }
+    }
+
+    /// Returns `true` if the torrent is private. See [`Info::is_private`].
+    pub fn is_private(&self) -> bool {
+        self.info.is_private()
+    }
 }
 
 /// Getters: These are a set of getter functions to get various keys from a torrent files.
 impl MetaInfo {
+    /// Returns the top level keys of the torrent file which are not known to this library,
+    /// such as `x_cross_seed` or `collections`, along with their values. See
+    /// [`Info::extra_keys`] for the unknown keys of the info dictionary.
+    pub fn extra_keys(&self) -> &BTreeMap<String, bencode::Value> {
+        &self.extra
+    }
+
     /// Returns the `title` key of the torrent file (if any)
     pub fn title(&self) -> Option<&String> {
         self.title.as_ref()
@@ -123,9 +258,19 @@ impl MetaInfo {
     /// Returns the creation time of the torrent parsed in [RFC
     /// 2822](https://www.rfc-editor.org/rfc/rfc2822) format
     pub fn creation_date(&self) -> Option<String> {
+        self.creation_date_utc()
+            .map(|datetime| datetime.to_rfc2822())
+    }
+
+    /// Returns the creation time of the torrent as a UTC [`DateTime`].
+    ///
+    /// Returns `None` if the key is absent or the timestamp is not a date between the years 0 and
+    /// 9999, see [`ParseWarning::InvalidCreationDate`]. Use [`DateTime::with_timezone`] to convert
+    /// it to any other timezone.
+    pub fn creation_date_utc(&self) -> Option<DateTime<Utc>> {
         self.creation_date
             .and_then(|datetime| DateTime::<Utc>::from_timestamp(datetime, 0))
-            .map(|datetime| datetime.to_rfc2822())
+            .filter(|datetime| (0..=9999).contains(&datetime.year()))
     }
 
     /// Returns the creation time of the torrent, in standard UNIX epoch format.
@@ -161,12 +306,27 @@ impl MetaInfo {
         self.announce.as_ref()
     }
 
-    /// Returns the `url_list` key contained in the torrent file (if any).
+    /// Returns the web seeds of the `url_list` key contained in the torrent file.
     ///
     /// The `url_list` key refers to a one or more URLs, and will contain a list of web addresses
     /// where torrent data can be retrieved. The urls contained in this key are intended for using
     /// HTTP or FTP servers as seeds for BitTorrent downloads.
-    pub fn url_list(&self) -> Option<&Vec<String>> {
+    ///
+    /// Only the valid absolute urls are returned, with the surrounding whitespace trimmed. Many
+    /// torrents are created with a `url_list` of a single empty string, or of relative paths which
+    /// can not be downloaded from. See [`MetaInfo::raw_url_list`] for the key as it is.
+    pub fn url_list(&self) -> impl Iterator<Item = &str> + '_ {
+        self.url_list.iter().flatten().filter_map(|url| {
+            let url = url.trim();
+            reqwest::Url::parse(url)
+                .is_ok_and(|parsed| parsed.has_host())
+                .then_some(url)
+        })
+    }
+
+    /// Returns the `url_list` key contained in the torrent file (if any), including the entries
+    /// which are not valid urls.
+    pub fn raw_url_list(&self) -> Option<&Vec<String>> {
         self.url_list.as_ref()
     }
 
@@ -179,8 +339,106 @@ impl MetaInfo {
     /// This key refers to a list of lists of URLs that contain a list of tiers of announces. If
     /// the `announce-list` key is present in a torrent file, [`announce`](MetaInfo::announce) key will
     /// be ignored and only this key will be used.
+    ///
+    /// A flat list of urls is read as one tier per url, see [`ParseWarning::FlatAnnounceList`].
     pub fn announce_list(&self) -> Option<&Vec<Vec<String>>> {
-        self.announce_list.as_ref()
+        self.announce_list.as_ref().map(|list| &list.tiers)
+    }
+
+    /// Returns the oddities of the torrent file, which was read in spite of them: the keys which
+    /// are read in a tolerant way or are partly ignored. See [`ParseWarning`].
+    pub fn warnings(&self) -> Vec<ParseWarning> {
+        let mut warnings = Vec::new();
+
+        if self.announce_list.as_ref().is_some_and(|list| list.flat) {
+            warnings.push(ParseWarning::FlatAnnounceList);
+        }
+        let empty_tracker_urls = self
+            .announce
+            .iter()
+            .chain(self.announce_list().into_iter().flatten().flatten())
+            .filter(|url| url.is_empty())
+            .count();
+        warnings.extend((0..empty_tracker_urls).map(|_| ParseWarning::EmptyTrackerUrl));
+
+        let mut web_seeds = self.url_list().peekable();
+        for url in self.raw_url_list().into_iter().flatten() {
+            if web_seeds.next_if(|seed| *seed == url.trim()).is_none() {
+                warnings.push(ParseWarning::InvalidWebSeed(url.clone()));
+            }
+        }
+
+        if let Some(date) = self.creation_date {
+            if self.creation_date_utc().is_none() {
+                warnings.push(ParseWarning::InvalidCreationDate(date));
+            }
+        }
+
+        if self.info.name.is_lossy() {
+            warnings.push(ParseWarning::NonUtf8FileName(self.info.name.to_string()));
+        }
+        let files: Vec<(String, bool, Option<&FileAttr>)> = match &self.info.files {
+            Files::SingleFile { attr, .. } => {
+                vec![(self.info.name.to_string(), false, attr.as_ref())]
+            }
+            Files::MultiFile { files } => files
+                .iter()
+                .map(|file| {
+                    let lossy = file.path.iter().any(ByteString::is_lossy);
+                    (file.path.join("/"), lossy, file.attr.as_ref())
+                })
+                .collect(),
+        };
+        for (path, lossy, attr) in files {
+            if lossy {
+                warnings.push(ParseWarning::NonUtf8FileName(path.clone()));
+            }
+            if let Some(attr) = attr.and_then(FileAttr::unknown) {
+                warnings.push(ParseWarning::UnknownFileAttr { path, attr });
+            }
+        }
+
+        warnings
+    }
+
+    /// Returns all the tracker urls of the torrent along with the index of their tier.
+    ///
+    /// This implements the precedence rules of [BEP: 12 - Multitracker Metadata
+    /// Extension](https://www.bittorrent.org/beps/bep_0012.html): if the `announce-list` key is
+    /// present, its trackers are returned tier by tier and the `announce` key is ignored. Otherwise
+    /// the `announce` key (if any) is returned as the only tracker of the first tier. Empty urls
+    /// are skipped, and an `announce-list` without any url is treated as absent.
+    ///
+    /// # Example
+    ///
+    /// ```
+    /// use zung_torrent::meta_info::MetaInfo;
+    ///
+    /// # fn trackers(meta_info: &MetaInfo) {
+    /// for (tier, url) in meta_info.all_trackers() {
+    ///     println!("{tier}: {url}");
+    /// }
+    /// # }
+    /// ```
+    pub fn all_trackers(&self) -> impl Iterator<Item = (usize, &str)> + '_ {
+        let tiers = self
+            .announce_list()
+            .into_iter()
+            .flatten()
+            .enumerate()
+            .flat_map(|(tier, urls)| {
+                urls.iter()
+                    .filter(|url| !url.is_empty())
+                    .map(move |url| (tier, url.as_str()))
+            });
+
+        let announce = if tiers.clone().next().is_none() {
+            self.announce.as_deref().filter(|url| !url.is_empty())
+        } else {
+            None
+        };
+
+        tiers.chain(announce.map(|url| (0, url)))
     }
 
     /// Returns the value of the `piece length` from the [`Info`] type.
@@ -202,7 +460,7 @@ impl MetaInfo {
             trackers = 1;
         }
 
-        if let Some(list) = &self.announce_list {
+        if let Some(list) = self.announce_list() {
             for i in list {
                 for _ in i {
                     trackers += 1;
@@ -218,10 +476,6 @@ impl MetaInfo {
     /// If no http_sources are presents (meaning only the trackers are present), then this will
     /// simply return 0;
     pub fn number_of_httpsources(&self) -> usize {
-        if let Some(list) = &self.url_list {
-            list.len()
-        } else {
-            0
-        }
+        self.url_list().count()
     }
 }
@@ -1,16 +1,16 @@
 use std::ops::Deref;
 
-use rayon::{
-    iter::{IndexedParallelIterator, ParallelIterator},
-    slice::ParallelSlice,
-};
+use bytes::Bytes;
 use serde::{de::Visitor, Deserialize, Serialize};
 
 /// This is a string consisting of the concatenation of all 20-byte sha1 hash values, one per piece
 /// (byte string, i.e. not urlencoded)
-#[derive(Debug)]
+///
+/// The hashes are kept in a single contiguous buffer, exactly as they appear in the torrent file,
+/// and are viewed as 20 byte chunks through [`Deref`].
+#[derive(Debug, Clone)]
 pub struct Pieces {
-    bytes: Vec<[u8; 20]>,
+    bytes: Bytes,
 }
 
 struct PiecesVisitor;
@@ -29,22 +29,15 @@ impl<'de> Visitor<'de> for PiecesVisitor {
     where
         E: serde::de::Error,
     {
-        if v.len() % 20 != 0 {
+        if !v.len().is_multiple_of(20) {
             return Err(E::custom(
                 "Invalid Torrent File - Pieces should be in 20 byte chunks always",
             ));
         }
-        let len = v.len() / 20;
-        let mut chunks = Vec::with_capacity(len);
 
-        v.par_chunks_exact(20)
-            .map(|c| {
-                c.try_into()
-                    .expect("Unable to divide pieces into 20 byte chunks")
-            })
-            .collect_into_vec(&mut chunks);
-
-        Ok(Pieces { bytes: chunks })
+        Ok(Pieces {
+            bytes: Bytes::copy_from_slice(v),
+        })
     }
 }
 
@@ -53,7 +46,7 @@ impl Serialize for Pieces {
     where
         S: serde::Serializer,
     {
-        serializer.serialize_bytes(self.bytes.as_flattened())
+        serializer.serialize_bytes(&self.bytes)
     }
 }
 
@@ -67,17 +60,40 @@ impl<'de> Deserialize<'de> for Pieces {
 }
 
 impl Deref for Pieces {
-    type Target = Vec<[u8; 20]>;
+    type Target = [[u8; 20]];
 
     fn deref(&self) -> &Self::Target {
-        &self.bytes
+        // The length is checked to be a multiple of 20 while deserializing.
+        self.bytes.as_chunks().0
     }
 }
 
 impl Pieces {
+    /// Returns `true` if the SHA1 hash of the data matches the hash of the piece at `index`.
+    /// Returns `false` if there is no such piece.
+    pub fn verify(&self, index: usize, data: &[u8]) -> bool {
+        #[cfg(feature = "metrics")]
+        let start = std::time::Instant::now();
+
+        let valid = self
+            .get(index)
+            .is_some_and(|hash| sha1_smol::Sha1::from(data).digest().bytes() == *hash);
+
+        #[cfg(feature = "metrics")]
+        crate::metrics::Metrics::global().record_piece_verify(start.elapsed());
+        valid
+    }
+
     pub(crate) fn __test_build() -> Self {
         Self {
-            bytes: [[1; 20], [2; 20], [3; 20]].to_vec(),
+            bytes: Bytes::copy_from_slice([[1; 20], [2; 20], [3; 20]].as_flattened()),
+        }
+    }
+
+    #[cfg(test)]
+    fn from_hashes(hashes: &[[u8; 20]]) -> Self {
+        Self {
+            bytes: Bytes::copy_from_slice(hashes.as_flattened()),
         }
     }
 }
@@ -92,9 +108,7 @@ mod pieces_tests {
 
     #[test]
     fn test_pieces_serialization() {
-        let pieces = Pieces {
-            bytes: TEST_BYTES.to_vec(),
-        };
+        let pieces = Pieces::from_hashes(&TEST_BYTES);
         let serialized = bencode::to_bytes(&pieces).unwrap();
         assert_eq!(serialized, SERIALIZED_BYTES);
     }
@@ -102,14 +116,12 @@ mod pieces_tests {
     #[test]
     fn test_pieces_deserialization() {
         let pieces: Pieces = bencode::from_bytes(SERIALIZED_BYTES).unwrap();
-        assert_eq!(pieces.bytes, vec![[1; 20], [2; 20], [3; 20]]);
+        assert_eq!(&*pieces, &[[1; 20], [2; 20], [3; 20]]);
     }
 
     #[test]
     fn test_pieces_roundtrip() {
-        let original = Pieces {
-            bytes: vec![[1; 20], [2; 20], [3; 20], [4; 20]],
-        };
+        let original = Pieces::from_hashes(&[[1; 20], [2; 20], [3; 20], [4; 20]]);
         let serialized = bencode::to_bytes(&original).unwrap();
         let deserialized: Pieces = bencode::from_bytes(&serialized).unwrap();
         assert_eq!(original.bytes, deserialized.bytes);
@@ -124,18 +136,24 @@ mod pieces_tests {
 
     #[test]
     fn test_pieces_empty() {
-        let pieces = Pieces { bytes: vec![] };
+        let pieces = Pieces::from_hashes(&[]);
         let serialized = bencode::to_bytes(&pieces).unwrap();
         assert_eq!(serialized, b"0:");
         let deserialized: Pieces = bencode::from_bytes(&serialized).unwrap();
         assert!(deserialized.is_empty())
     }
 
+    #[test]
+    fn test_pieces_verify() {
+        let pieces = Pieces::from_hashes(&[sha1_smol::Sha1::from(b"piece").digest().bytes()]);
+        assert!(pieces.verify(0, b"piece"));
+        assert!(!pieces.verify(0, b"other"));
+        assert!(!pieces.verify(1, b"piece"));
+    }
+
     #[test]
     fn test_pieces_deref() {
-        let pieces = Pieces {
-            bytes: vec![[1; 20], [2; 20]],
-        };
+        let pieces = Pieces::from_hashes(&[[1; 20], [2; 20]]);
         assert_eq!(pieces.len(), 2);
         assert_eq!(pieces[0], [1; 20]);
         assert_eq!(pieces[1], [2; 20]);
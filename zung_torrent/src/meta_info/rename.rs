@@ -0,0 +1,270 @@
+use std::fs::File;
+use std::io::Read;
+use std::path::Path;
+use std::str::FromStr;
+
+use anyhow::{bail, Context, Result};
+use zung_parsers::bencode::Value;
+
+use super::builder::PieceHasher;
+
+/// Renames a file or a directory inside a torrent, parsed from `old=new`.
+///
+/// The paths are `/` separated and relative to the torrent's directory, such as `dir/file.txt`,
+/// or are the name of the file of a single file torrent. A mapping of a directory renames every
+/// file under it.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct PathMapping {
+    pub from: Vec<String>,
+    pub to: Vec<String>,
+}
+
+impl FromStr for PathMapping {
+    type Err = anyhow::Error;
+
+    fn from_str(s: &str) -> Result<Self> {
+        let split = |path: &str| -> Vec<String> {
+            path.split('/')
+                .filter(|segment| !segment.is_empty())
+                .map(String::from)
+                .collect()
+        };
+
+        let Some((from, to)) = s.split_once('=') else {
+            bail!("Invalid mapping {s:?} - Expected old=new")
+        };
+        let (from, to) = (split(from), split(to));
+        if from.is_empty() || to.is_empty() {
+            bail!("Invalid mapping {s:?} - The paths must not be empty")
+        }
+        Ok(Self { from, to })
+    }
+}
+
+/// Applies the mappings to the paths of the files in the info dictionary of a torrent file, the
+/// first matching mapping winning. Returns the number of renamed files.
+///
+/// Only the paths change, so the pieces of the torrent stay valid for the same content. The info
+/// hash does change. Returns an error if a mapping matches no file, or for v2 torrents whose file
+/// tree is not supported.
+pub fn rename_files(torrent: &mut Value, mappings: &[PathMapping]) -> Result<usize> {
+    let Some(Value::Dictionary(info)) = torrent.pointer("info") else {
+        bail!("Invalid Torrent File - No info dictionary provided")
+    };
+    if info.contains_key("file tree") {
+        bail!("Renaming the files of v2 torrents is not supported yet")
+    }
+
+    let mut used = vec![false; mappings.len()];
+    let mut rename = |path: &mut Vec<String>| -> bool {
+        let Some(i) = mappings
+            .iter()
+            .position(|mapping| path.starts_with(&mapping.from))
+        else {
+            return false;
+        };
+        used[i] = true;
+        let mapping = &mappings[i];
+        path.splice(..mapping.from.len(), mapping.to.iter().cloned());
+        true
+    };
+
+    let mut renamed = 0;
+    let Some(Value::Dictionary(info)) = dictionary_mut(torrent, "info") else {
+        unreachable!("Checked above")
+    };
+    match info.get_mut("files") {
+        Some(Value::List(files)) => {
+            for file in files {
+                let Some(Value::List(path)) = dictionary_mut(file, "path") else {
+                    bail!("Invalid Torrent File - A file has no path")
+                };
+                let mut segments: Vec<String> = path.iter().map(ToString::to_string).collect();
+                if rename(&mut segments) {
+                    *path = segments.into_iter().map(Value::String).collect();
+                    renamed += 1;
+                }
+            }
+        }
+        _ => {
+            let Some(name) = info.get_mut("name") else {
+                bail!("Invalid Torrent File - No name provided")
+            };
+            let mut segments = vec![name.to_string()];
+            if rename(&mut segments) {
+                if segments.len() > 1 {
+                    bail!("The file of a single file torrent can not be moved into a directory")
+                }
+                *name = Value::String(segments.remove(0));
+                renamed += 1;
+            }
+        }
+    }
+
+    if let Some(i) = used.iter().position(|used| !used) {
+        bail!(
+            "No file matches the mapping {}={}",
+            mappings[i].from.join("/"),
+            mappings[i].to.join("/")
+        )
+    }
+    Ok(renamed)
+}
+
+/// Hashes the content of the torrent from the files in `data`, the directory which contains the
+/// torrent's file or directory, and replaces the pieces and the lengths of the files with it.
+/// Returns whether the content changed.
+///
+/// The padding files (BEP 47) are hashed as zeros and are not looked for on the disk.
+pub fn rehash_pieces(torrent: &mut Value, data: &Path) -> Result<bool> {
+    let Some(Value::Dictionary(info)) = dictionary_mut(torrent, "info") else {
+        bail!("Invalid Torrent File - No info dictionary provided")
+    };
+    let Some(Value::Integer(piece_length)) = info.get("piece length") else {
+        bail!("Invalid Torrent File - No piece length provided")
+    };
+    let Some(name) = info.get("name").map(ToString::to_string) else {
+        bail!("Invalid Torrent File - No name provided")
+    };
+
+    let mut hasher = PieceHasher::new(usize::try_from(*piece_length)?);
+    let root = data.join(&name);
+    let mut update = |path: &Path, length: &mut Value, padding: bool| -> Result<()> {
+        if padding {
+            let Value::Integer(padding) = length else {
+                bail!("Invalid Torrent File - A padding file has no length")
+            };
+            return hasher.update(std::io::repeat(0).take(u64::try_from(*padding)?));
+        }
+        let file =
+            File::open(path).with_context(|| format!("Unable to read {}", path.display()))?;
+        *length = Value::Integer(file.metadata()?.len() as i64);
+        hasher.update(file)
+    };
+
+    match info.get_mut("files") {
+        Some(Value::List(files)) => {
+            for file in files {
+                let Value::Dictionary(file) = file else {
+                    bail!("Invalid Torrent File - A file is not a dictionary")
+                };
+                let padding = file
+                    .get("attr")
+                    .is_some_and(|attr| attr.to_string().contains('p'));
+                let path = match file.get("path") {
+                    Some(Value::List(path)) => path
+                        .iter()
+                        .fold(root.clone(), |dir, segment| dir.join(segment.to_string())),
+                    _ => bail!("Invalid Torrent File - A file has no path"),
+                };
+                let Some(length) = file.get_mut("length") else {
+                    bail!("Invalid Torrent File - A file has no length")
+                };
+                update(&path, length, padding)?;
+            }
+        }
+        _ => {
+            let Some(length) = info.get_mut("length") else {
+                bail!("Invalid Torrent File - No length provided")
+            };
+            update(&root, length, false)?;
+        }
+    }
+
+    let pieces = Value::Bytes(hasher.finish());
+    let changed = info.get("pieces") != Some(&pieces);
+    info.insert("pieces".into(), pieces);
+    Ok(changed)
+}
+
+fn dictionary_mut<'a>(value: &'a mut Value, key: &str) -> Option<&'a mut Value> {
+    match value {
+        Value::Dictionary(dictionary) => dictionary.get_mut(key),
+        _ => None,
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::testing::TorrentBuilder;
+    use zung_parsers::bencode;
+
+    fn mappings(mappings: &[&str]) -> Vec<PathMapping> {
+        mappings.iter().map(|m| m.parse().unwrap()).collect()
+    }
+
+    fn paths(torrent: &Value) -> Vec<String> {
+        torrent
+            .query("info/files/*/path")
+            .iter()
+            .map(|path| path.to_string())
+            .collect()
+    }
+
+    #[test]
+    fn test_parse_mapping() {
+        let mapping: PathMapping = "/old dir/a.txt=new/b.txt".parse().unwrap();
+        assert_eq!(mapping.from, ["old dir", "a.txt"]);
+        assert_eq!(mapping.to, ["new", "b.txt"]);
+        assert!("old".parse::<PathMapping>().is_err());
+        assert!("=new".parse::<PathMapping>().is_err());
+    }
+
+    #[test]
+    fn test_rename_files() {
+        let bytes = TorrentBuilder::multi_file("rename")
+            .file("dir/a.txt", 10)
+            .file("dir/b.txt", 10)
+            .file("c.txt", 10)
+            .build();
+        let mut torrent = bencode::parse(&bytes).unwrap();
+        let pieces = torrent.pointer("info/pieces").cloned();
+
+        let renamed =
+            rename_files(&mut torrent, &mappings(&["dir/b.txt=b.txt", "dir=folder"])).unwrap();
+        assert_eq!(renamed, 2);
+        assert_eq!(paths(&torrent), ["[folder, a.txt]", "[b.txt]", "[c.txt]"]);
+        assert_eq!(torrent.pointer("info/pieces").cloned(), pieces);
+
+        let error = rename_files(&mut torrent, &mappings(&["missing=x"])).unwrap_err();
+        assert_eq!(error.to_string(), "No file matches the mapping missing=x");
+    }
+
+    #[test]
+    fn test_rename_single_file() {
+        let bytes = TorrentBuilder::single_file("old.bin", 10).build();
+        let mut torrent = bencode::parse(&bytes).unwrap();
+        rename_files(&mut torrent, &mappings(&["old.bin=new.bin"])).unwrap();
+        assert_eq!(torrent.pointer("info/name").unwrap().to_string(), "new.bin");
+    }
+
+    #[test]
+    fn test_rehash_pieces() {
+        let builder = TorrentBuilder::multi_file("rehash")
+            .file("a.txt", 10)
+            .padding_file(6)
+            .file("b.txt", 20);
+        let mut torrent = bencode::parse(&builder.build()).unwrap();
+
+        let data = std::env::temp_dir().join(format!("zung-rehash-{}", std::process::id()));
+        let root = data.join("rehash");
+        std::fs::create_dir_all(&root).unwrap();
+        let content = builder.content();
+        std::fs::write(root.join("a.txt"), &content[..10]).unwrap();
+        std::fs::write(root.join("b.txt"), &content[16..]).unwrap();
+
+        // The same content: the pieces are the same.
+        assert!(!rehash_pieces(&mut torrent, &data).unwrap());
+
+        std::fs::write(root.join("b.txt"), b"changed").unwrap();
+        assert!(rehash_pieces(&mut torrent, &data).unwrap());
+        assert_eq!(
+            torrent.pointer("info/files/2/length"),
+            Some(&Value::Integer(7))
+        );
+
+        std::fs::remove_file(root.join("a.txt")).unwrap();
+        assert!(rehash_pieces(&mut torrent, &data).is_err());
+    }
+}
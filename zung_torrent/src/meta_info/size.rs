@@ -0,0 +1,69 @@
+/// How sizes (in bytes) are formatted when printed.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub enum SizeFormat {
+    /// Powers of 1024 with binary units such as `KiB` and `MiB`.
+    #[default]
+    Binary,
+
+    /// Powers of 1000 with SI units such as `kB` and `MB`.
+    Si,
+
+    /// The exact number of bytes, without any unit. Useful for scripts.
+    Bytes,
+}
+
+const BINARY_UNITS: [&str; 7] = ["B", "KiB", "MiB", "GiB", "TiB", "PiB", "EiB"];
+const SI_UNITS: [&str; 7] = ["B", "kB", "MB", "GB", "TB", "PB", "EB"];
+
+impl SizeFormat {
+    /// Formats the provided number of bytes.
+    ///
+    /// # Example
+    ///
+    /// ```
+    /// use zung_torrent::meta_info::SizeFormat;
+    ///
+    /// assert_eq!(SizeFormat::Binary.format(524288), "512 KiB");
+    /// assert_eq!(SizeFormat::Si.format(524288), "524.3 kB");
+    /// assert_eq!(SizeFormat::Bytes.format(524288), "524288");
+    /// ```
+    pub fn format(self, bytes: usize) -> String {
+        let (base, units) = match self {
+            SizeFormat::Binary => (1024.0, &BINARY_UNITS),
+            SizeFormat::Si => (1000.0, &SI_UNITS),
+            SizeFormat::Bytes => return bytes.to_string(),
+        };
+
+        let mut size = bytes as f64;
+        let mut unit = 0;
+        while size >= base && unit < units.len() - 1 {
+            size /= base;
+            unit += 1;
+        }
+
+        if unit == 0 {
+            format!("{bytes} B")
+        } else {
+            let size = format!("{size:.1}");
+            format!("{} {}", size.trim_end_matches(".0"), units[unit])
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_format() {
+        assert_eq!(SizeFormat::Binary.format(0), "0 B");
+        assert_eq!(SizeFormat::Binary.format(1023), "1023 B");
+        assert_eq!(SizeFormat::Binary.format(1536), "1.5 KiB");
+        assert_eq!(SizeFormat::Binary.format(1001730048), "955.3 MiB");
+        assert_eq!(SizeFormat::Si.format(999), "999 B");
+        assert_eq!(SizeFormat::Si.format(1000), "1 kB");
+        assert_eq!(SizeFormat::Si.format(4102389760), "4.1 GB");
+        assert_eq!(SizeFormat::Bytes.format(4102389760), "4102389760");
+        assert_eq!(SizeFormat::Binary.format(usize::MAX), "16 EiB");
+    }
+}
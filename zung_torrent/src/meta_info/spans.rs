@@ -0,0 +1,166 @@
+use std::ops::Range;
+
+use super::{
+    files::{FileHash, Files},
+    Info,
+};
+
+/// The location of a single file within the contiguous byte stream of a torrent.
+///
+/// For the purposes of piece boundaries the file data of a torrent is considered one long
+/// continuous stream, composed of the concatenation of each file in the order listed in the files
+/// list. A [`FileSpan`] describes where in that stream a file lives, which makes it possible to map
+/// files to pieces and pieces back to files.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct FileSpan {
+    /// Path of the file relative to the torrent root, joined with `/`. In the single file case
+    /// this is the name of the torrent.
+    pub path: String,
+
+    /// Offset of the first byte of the file in the torrent's byte stream.
+    pub offset: usize,
+
+    /// Length of the file in bytes.
+    pub length: usize,
+
+    /// Whether the file is a [padding file](super::FileAttr::Padding).
+    pub padding: bool,
+
+    /// The BEP 47 SHA1 hash of the contents of the file, if the torrent provides one.
+    pub sha1: Option<FileHash>,
+
+    /// Whether the file is [executable](super::FileAttr::Executable).
+    pub executable: bool,
+
+    /// Path of the target relative to the torrent root, joined with `/`, if the file is a
+    /// [symlink](super::FileAttr::Symlink).
+    pub symlink: Option<String>,
+}
+
+impl FileSpan {
+    /// Range of bytes occupied by the file in the torrent's byte stream.
+    pub fn byte_range(&self) -> Range<usize> {
+        self.offset..self.offset + self.length
+    }
+
+    /// Range of the indices of the pieces that contain some of the bytes of this file.
+    ///
+    /// Returns an empty range for zero-length files.
+    pub fn piece_range(&self, piece_length: usize) -> Range<usize> {
+        pieces_for_bytes(self.byte_range(), piece_length)
+    }
+
+    /// Verifies the complete contents of the file against its [`FileSpan::sha1`]. Returns `None`
+    /// if the torrent does not provide a hash for the file, in which case the pieces of the file
+    /// have to be verified instead.
+    pub fn verify(&self, data: &[u8]) -> Option<bool> {
+        self.sha1
+            .map(|sha1| data.len() == self.length && sha1.verify(data))
+    }
+
+    /// Returns `true` if some of the bytes of the file are within the provided byte range.
+    pub fn overlaps(&self, bytes: &Range<usize>) -> bool {
+        self.length > 0 && self.offset < bytes.end && bytes.start < self.offset + self.length
+    }
+}
+
+/// Range of the indices of the pieces covering the provided byte range.
+fn pieces_for_bytes(bytes: Range<usize>, piece_length: usize) -> Range<usize> {
+    if bytes.is_empty() || piece_length == 0 {
+        let start = bytes.start.checked_div(piece_length).unwrap_or_default();
+        return start..start;
+    }
+    bytes.start / piece_length..bytes.end.div_ceil(piece_length)
+}
+
+impl Info {
+    /// Builds the [`FileSpan`] of every file in the torrent (including the padding files) in the
+    /// order in which they appear in the torrent.
+    pub fn file_spans(&self) -> Vec<FileSpan> {
+        match &self.files {
+            Files::SingleFile {
+                length, sha1, attr, ..
+            } => vec![FileSpan {
+                path: self.name.to_string(),
+                offset: 0,
+                length: *length,
+                padding: false,
+                sha1: *sha1,
+                executable: attr.as_ref().is_some_and(|a| a.is_executable()),
+                // A symlink is relative to the torrent root, which a single file does not have.
+                symlink: None,
+            }],
+            Files::MultiFile { files } => {
+                let mut offset = 0;
+                files
+                    .iter()
+                    .map(|file| {
+                        let span = FileSpan {
+                            path: file.path.join("/"),
+                            offset,
+                            length: file.length,
+                            padding: file.attr.as_ref().is_some_and(|a| a.is_padding_file()),
+                            sha1: file.sha1,
+                            executable: file.attr.as_ref().is_some_and(|a| a.is_executable()),
+                            symlink: file
+                                .symlink_path
+                                .as_ref()
+                                .filter(|_| file.attr.as_ref().is_some_and(|a| a.is_symlink()))
+                                .map(|target| target.join("/")),
+                        };
+                        offset += file.length;
+                        span
+                    })
+                    .collect()
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn span(offset: usize, length: usize) -> FileSpan {
+        FileSpan {
+            path: String::from("file"),
+            offset,
+            length,
+            padding: false,
+            sha1: None,
+            executable: false,
+            symlink: None,
+        }
+    }
+
+    #[test]
+    fn test_piece_range() {
+        assert_eq!(span(0, 10).piece_range(4), 0..3);
+        assert_eq!(span(4, 4).piece_range(4), 1..2);
+        assert_eq!(span(3, 2).piece_range(4), 0..2);
+        assert_eq!(span(8, 0).piece_range(4), 2..2);
+    }
+
+    #[test]
+    fn test_overlaps() {
+        let file = span(4, 4);
+        assert!(file.overlaps(&(0..5)));
+        assert!(file.overlaps(&(7..12)));
+        assert!(!file.overlaps(&(0..4)));
+        assert!(!file.overlaps(&(8..12)));
+        assert!(!span(4, 0).overlaps(&(0..8)));
+    }
+
+    #[test]
+    fn test_verify() {
+        assert_eq!(span(0, 4).verify(b"spam"), None);
+
+        let file = FileSpan {
+            sha1: Some(sha1_smol::Sha1::from("spam").digest().bytes().into()),
+            ..span(0, 4)
+        };
+        assert_eq!(file.verify(b"spam"), Some(true));
+        assert_eq!(file.verify(b"eggs"), Some(false));
+        assert_eq!(file.verify(b"spam!"), Some(false));
+    }
+}
@@ -0,0 +1,52 @@
+use std::fmt::Display;
+
+/// An oddity of a torrent file which did not prevent reading it. See [`MetaInfo::warnings`].
+///
+/// [`MetaInfo::warnings`]: super::MetaInfo::warnings
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum ParseWarning {
+    /// The `announce-list` is a list of urls instead of a list of tiers of urls. Each url was
+    /// read as a tier of its own.
+    FlatAnnounceList,
+
+    /// The `announce` key or a tier of the `announce-list` holds an empty url, which is skipped.
+    EmptyTrackerUrl,
+
+    /// An entry of the `url-list` is not an absolute url, and is not used as a web seed.
+    InvalidWebSeed(String),
+
+    /// The `creation date` is not a date between the years 0 and 9999, e.g. a number of
+    /// milliseconds instead of seconds.
+    InvalidCreationDate(i64),
+
+    /// The `attr` of the file has characters which are not known attributes. They are ignored.
+    UnknownFileAttr { path: String, attr: String },
+
+    /// The name or the path of a file is not valid UTF-8. It is shown with the invalid sequences
+    /// replaced by `U+FFFD`, and its bytes are kept as they are.
+    NonUtf8FileName(String),
+}
+
+impl Display for ParseWarning {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            ParseWarning::FlatAnnounceList => write!(
+                f,
+                "announce-list is a list of urls instead of a list of tiers, each url was read as its own tier"
+            ),
+            ParseWarning::EmptyTrackerUrl => write!(f, "empty tracker url skipped"),
+            ParseWarning::InvalidWebSeed(url) => {
+                write!(f, "url-list entry {url:?} is not an absolute url, skipped")
+            }
+            ParseWarning::InvalidCreationDate(date) => {
+                write!(f, "creation date {date} is not a valid date, ignored")
+            }
+            ParseWarning::UnknownFileAttr { path, attr } => {
+                write!(f, "unknown attributes {attr:?} of {path} ignored")
+            }
+            ParseWarning::NonUtf8FileName(path) => {
+                write!(f, "file name {path:?} is not valid UTF-8")
+            }
+        }
+    }
+}
@@ -0,0 +1,257 @@
+//! Counters and gauges of the network activity, exported in the [Prometheus text
+//! format](https://prometheus.io/docs/instrumenting/exposition_formats/).
+//!
+//! This module is only available with the `metrics` feature. The library records into the
+//! [`Metrics::global`] instance as it works, and [`serve`] exposes them over HTTP so that long
+//! running sessions can be scraped.
+//!
+//! # Example
+//!
+//! ```no_run
+//! use zung_torrent::metrics;
+//!
+//! # async fn run() -> anyhow::Result<()> {
+//! let server = metrics::serve("127.0.0.1:9184".parse()?).await?;
+//! // ... the metrics are now available on http://127.0.0.1:9184/metrics ...
+//! server.abort();
+//! # Ok(())
+//! # }
+//! ```
+
+use std::fmt::Write;
+use std::net::SocketAddr;
+use std::sync::atomic::{AtomicI64, AtomicU64, Ordering};
+use std::time::Duration;
+
+use anyhow::{Context, Result};
+use tokio::io::{AsyncReadExt, AsyncWriteExt};
+use tokio::net::TcpListener;
+use tokio::task::JoinHandle;
+
+/// The default address of the metrics endpoint.
+pub const DEFAULT_LISTEN: &str = "127.0.0.1:9184";
+
+/// Time waited after failing to accept a connection, e.g. when the process is out of file
+/// descriptors, before accepting again.
+const ACCEPT_RETRY_DELAY: Duration = Duration::from_millis(100);
+
+static GLOBAL: Metrics = Metrics::new();
+
+/// The recorded metrics. All the methods are lock free and can be called from any thread.
+#[derive(Debug, Default)]
+pub struct Metrics {
+    announce_successes: AtomicU64,
+    announce_failures: AtomicU64,
+    bytes_downloaded: AtomicU64,
+    bytes_uploaded: AtomicU64,
+    peers: AtomicI64,
+    piece_verify_count: AtomicU64,
+    piece_verify_micros: AtomicU64,
+}
+
+impl Metrics {
+    /// Builds a new set of metrics with every value at zero.
+    pub const fn new() -> Self {
+        Self {
+            announce_successes: AtomicU64::new(0),
+            announce_failures: AtomicU64::new(0),
+            bytes_downloaded: AtomicU64::new(0),
+            bytes_uploaded: AtomicU64::new(0),
+            peers: AtomicI64::new(0),
+            piece_verify_count: AtomicU64::new(0),
+            piece_verify_micros: AtomicU64::new(0),
+        }
+    }
+
+    /// The metrics recorded by the library.
+    pub fn global() -> &'static Metrics {
+        &GLOBAL
+    }
+
+    /// Records the result of an announce to a tracker.
+    pub fn record_announce(&self, success: bool) {
+        let counter = if success {
+            &self.announce_successes
+        } else {
+            &self.announce_failures
+        };
+        counter.fetch_add(1, Ordering::Relaxed);
+    }
+
+    /// Records bytes received from peers and web seeds.
+    pub fn record_downloaded(&self, bytes: usize) {
+        self.bytes_downloaded
+            .fetch_add(bytes as u64, Ordering::Relaxed);
+    }
+
+    /// Records bytes sent to peers.
+    pub fn record_uploaded(&self, bytes: usize) {
+        self.bytes_uploaded
+            .fetch_add(bytes as u64, Ordering::Relaxed);
+    }
+
+    /// Records a newly connected peer.
+    pub fn peer_connected(&self) {
+        self.peers.fetch_add(1, Ordering::Relaxed);
+    }
+
+    /// Records a disconnected peer.
+    pub fn peer_disconnected(&self) {
+        self.peers.fetch_sub(1, Ordering::Relaxed);
+    }
+
+    /// Records the time taken to verify the hash of a piece.
+    pub fn record_piece_verify(&self, elapsed: Duration) {
+        self.piece_verify_count.fetch_add(1, Ordering::Relaxed);
+        self.piece_verify_micros
+            .fetch_add(elapsed.as_micros() as u64, Ordering::Relaxed);
+    }
+
+    /// Renders the metrics in the Prometheus text format.
+    pub fn render(&self) -> String {
+        let load = |value: &AtomicU64| value.load(Ordering::Relaxed);
+        let mut out = String::new();
+
+        let mut metric = |name: &str, kind: &str, help: &str, samples: &[(&str, String)]| {
+            let _ = writeln!(out, "# HELP {name} {help}");
+            let _ = writeln!(out, "# TYPE {name} {kind}");
+            for (suffix, value) in samples {
+                let _ = writeln!(out, "{name}{suffix} {value}");
+            }
+        };
+
+        metric(
+            "zung_announces_total",
+            "counter",
+            "Announces sent to the trackers by result.",
+            &[
+                (
+                    "{result=\"success\"}",
+                    load(&self.announce_successes).to_string(),
+                ),
+                (
+                    "{result=\"failure\"}",
+                    load(&self.announce_failures).to_string(),
+                ),
+            ],
+        );
+        metric(
+            "zung_downloaded_bytes_total",
+            "counter",
+            "Bytes received from peers and web seeds.",
+            &[("", load(&self.bytes_downloaded).to_string())],
+        );
+        metric(
+            "zung_uploaded_bytes_total",
+            "counter",
+            "Bytes sent to peers.",
+            &[("", load(&self.bytes_uploaded).to_string())],
+        );
+        metric(
+            "zung_peers",
+            "gauge",
+            "Currently connected peers.",
+            &[("", self.peers.load(Ordering::Relaxed).to_string())],
+        );
+        metric(
+            "zung_piece_verify_seconds",
+            "summary",
+            "Time taken to verify the hash of a piece.",
+            &[
+                (
+                    "_sum",
+                    (load(&self.piece_verify_micros) as f64 / 1e6).to_string(),
+                ),
+                ("_count", load(&self.piece_verify_count).to_string()),
+            ],
+        );
+
+        out
+    }
+}
+
+/// Serves the [`Metrics::global`] metrics over HTTP on the provided address until the returned
+/// task is aborted. Every path answers with the metrics, although `/metrics` is the conventional
+/// one.
+///
+/// The connections which can not be accepted are reported on stderr and do not stop the server.
+pub async fn serve(addr: SocketAddr) -> Result<JoinHandle<()>> {
+    let listener = TcpListener::bind(addr)
+        .await
+        .with_context(|| format!("Unable to listen for metrics on {addr}"))?;
+
+    Ok(tokio::spawn(async move {
+        loop {
+            let mut stream = match listener.accept().await {
+                Ok((stream, _)) => stream,
+                Err(e) => {
+                    // Waiting lets the file descriptors free up instead of failing in a loop.
+                    eprintln!("Unable to accept a metrics connection: {e}");
+                    tokio::time::sleep(ACCEPT_RETRY_DELAY).await;
+                    continue;
+                }
+            };
+            tokio::spawn(async move {
+                // The request itself does not matter, only that one was sent.
+                let mut buf = [0_u8; 1024];
+                if stream.read(&mut buf).await.is_err() {
+                    return;
+                }
+
+                let body = Metrics::global().render();
+                let response = format!(
+                    "HTTP/1.1 200 OK\r\nContent-Type: text/plain; version=0.0.4\r\nContent-Length: {}\r\nConnection: close\r\n\r\n{body}",
+                    body.len()
+                );
+                let _ = stream.write_all(response.as_bytes()).await;
+            });
+        }
+    }))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_render() {
+        let metrics = Metrics::new();
+        metrics.record_announce(true);
+        metrics.record_announce(true);
+        metrics.record_announce(false);
+        metrics.record_downloaded(1024);
+        metrics.peer_connected();
+        metrics.peer_connected();
+        metrics.peer_disconnected();
+        metrics.record_piece_verify(Duration::from_millis(1500));
+
+        let text = metrics.render();
+        assert!(text.contains("# TYPE zung_announces_total counter\n"));
+        assert!(text.contains("zung_announces_total{result=\"success\"} 2\n"));
+        assert!(text.contains("zung_announces_total{result=\"failure\"} 1\n"));
+        assert!(text.contains("zung_downloaded_bytes_total 1024\n"));
+        assert!(text.contains("zung_uploaded_bytes_total 0\n"));
+        assert!(text.contains("zung_peers 1\n"));
+        assert!(text.contains("zung_piece_verify_seconds_sum 1.5\n"));
+        assert!(text.contains("zung_piece_verify_seconds_count 1\n"));
+    }
+
+    #[tokio::test]
+    async fn test_serve() {
+        let listener = TcpListener::bind("127.0.0.1:0").await.unwrap();
+        let addr = listener.local_addr().unwrap();
+        drop(listener);
+
+        let server = serve(addr).await.unwrap();
+        let response = crate::net::HttpClient::new()
+            .unwrap()
+            .get(&format!("http://{addr}/metrics"))
+            .await
+            .unwrap();
+        server.abort();
+
+        assert!(response.is_success());
+        let body = String::from_utf8(response.body.to_vec()).unwrap();
+        assert!(body.contains("# TYPE zung_peers gauge"));
+    }
+}
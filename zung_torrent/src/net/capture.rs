@@ -0,0 +1,249 @@
+//! Records the network requests and responses as newline-delimited JSON.
+//!
+//! A [`Capture`] installed with [`install`] receives a [`Record`] for every packet sent or
+//! received through the networking layer, which is useful for debugging misbehaving trackers.
+//! Nothing is recorded (and nothing is serialized) while no capture is installed.
+//!
+//! The `key` sent to the trackers identifies the client across IP changes and the announce urls
+//! of private trackers embed a passkey, so they are redacted from the records (see
+//! [`redact`](super::redact)), even with [`show_secrets`](super::redact::show_secrets).
+//!
+//! # Example
+//!
+//! ```no_run
+//! use zung_torrent::net::capture::{self, Capture};
+//!
+//! # fn run() -> anyhow::Result<()> {
+//! capture::install(Capture::to_file("trackers.ndjson")?);
+//! // ... contact the trackers ...
+//! capture::uninstall();
+//! # Ok(())
+//! # }
+//! ```
+
+use std::borrow::Cow;
+use std::fs::File;
+use std::io::{BufWriter, Write};
+use std::path::Path;
+use std::sync::Mutex;
+
+use anyhow::{Context, Result};
+use chrono::{DateTime, Utc};
+use serde::Serialize;
+
+pub use super::redact::{redact_url, REDACTED};
+
+/// Offset and length of the `key` in a UDP announce request.
+const UDP_KEY: std::ops::Range<usize> = 88..92;
+
+static CAPTURE: Mutex<Option<Capture>> = Mutex::new(None);
+
+/// Installs the capture receiving the records of all the following network traffic, replacing
+/// any previously installed capture.
+pub fn install(capture: Capture) {
+    *CAPTURE.lock().expect("Capture lock poisoned") = Some(capture);
+}
+
+/// Removes the installed capture, if any, flushing it.
+pub fn uninstall() {
+    if let Some(mut capture) = CAPTURE.lock().expect("Capture lock poisoned").take() {
+        let _ = capture.writer.flush();
+    }
+}
+
+/// Returns `true` if a capture is installed.
+pub fn is_installed() -> bool {
+    CAPTURE.lock().expect("Capture lock poisoned").is_some()
+}
+
+/// Records to the installed capture, if any. The record is only built when needed.
+pub(crate) fn record<'a>(build: impl FnOnce() -> Record<'a>) {
+    if let Some(capture) = CAPTURE.lock().expect("Capture lock poisoned").as_mut() {
+        // Failing to capture must never fail the request itself.
+        let _ = capture.record(&build());
+    }
+}
+
+/// A sink for the [`Record`]s of the network traffic.
+pub struct Capture {
+    writer: Box<dyn Write + Send>,
+}
+
+impl std::fmt::Debug for Capture {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.debug_struct("Capture").finish_non_exhaustive()
+    }
+}
+
+impl Capture {
+    /// Captures to the provided writer.
+    pub fn new<W: Write + Send + 'static>(writer: W) -> Self {
+        Self {
+            writer: Box::new(writer),
+        }
+    }
+
+    /// Captures to the file at the provided path, truncating it if it exists.
+    pub fn to_file<P: AsRef<Path>>(path: P) -> Result<Self> {
+        let path = path.as_ref();
+        let file = File::create(path)
+            .with_context(|| format!("Unable to create the capture file {}", path.display()))?;
+        Ok(Self::new(BufWriter::new(file)))
+    }
+
+    /// Writes the record as a single line of JSON. Every record is flushed right away so that
+    /// the capture is complete even if the process is killed.
+    pub fn record(&mut self, record: &Record) -> Result<()> {
+        serde_json::to_writer(&mut self.writer, record)?;
+        self.writer.write_all(b"\n")?;
+        self.writer.flush()?;
+        Ok(())
+    }
+}
+
+/// The protocol of a captured packet.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize)]
+#[serde(rename_all = "lowercase")]
+pub enum Protocol {
+    Http,
+    Udp,
+}
+
+/// Whether a captured packet was sent or received.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize)]
+#[serde(rename_all = "lowercase")]
+pub enum Direction {
+    Request,
+    Response,
+}
+
+/// A single captured packet.
+#[derive(Debug, Clone, Serialize)]
+pub struct Record<'a> {
+    pub time: DateTime<Utc>,
+    pub protocol: Protocol,
+    pub direction: Direction,
+
+    /// The url (for HTTP) or the address (for UDP) of the other side.
+    pub remote: Cow<'a, str>,
+
+    /// Status code of HTTP responses.
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub status: Option<u16>,
+
+    /// Length of the payload in bytes.
+    pub len: usize,
+
+    /// The payload, as hex.
+    pub data: String,
+}
+
+impl<'a> Record<'a> {
+    /// Builds the record of a HTTP request. The secrets of the url are redacted.
+    pub fn http_request(url: &str) -> Self {
+        Record {
+            time: Utc::now(),
+            protocol: Protocol::Http,
+            direction: Direction::Request,
+            remote: Cow::Owned(redact_url(url).into_owned()),
+            status: None,
+            len: 0,
+            data: String::new(),
+        }
+    }
+
+    /// Builds the record of a HTTP response.
+    pub fn http_response(url: &str, status: u16, body: &[u8]) -> Self {
+        Record {
+            time: Utc::now(),
+            protocol: Protocol::Http,
+            direction: Direction::Response,
+            remote: Cow::Owned(redact_url(url).into_owned()),
+            status: Some(status),
+            len: body.len(),
+            data: hex::encode(body),
+        }
+    }
+
+    /// Builds the record of a UDP packet. The `key` of announce requests is redacted.
+    pub fn udp(direction: Direction, remote: impl Into<Cow<'a, str>>, packet: &[u8]) -> Self {
+        Record {
+            time: Utc::now(),
+            protocol: Protocol::Udp,
+            direction,
+            remote: remote.into(),
+            status: None,
+            len: packet.len(),
+            data: hex::encode(redact_udp(direction, packet)),
+        }
+    }
+}
+
+/// Zeroes the `key` of UDP announce requests.
+fn redact_udp(direction: Direction, packet: &[u8]) -> Vec<u8> {
+    let mut packet = packet.to_vec();
+    let is_announce = packet.get(8..12) == Some(&1_i32.to_be_bytes());
+    if direction == Direction::Request && is_announce && packet.len() >= UDP_KEY.end {
+        packet[UDP_KEY].fill(0);
+    }
+    packet
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::sync::Arc;
+
+    #[derive(Clone, Default)]
+    struct Shared(Arc<Mutex<Vec<u8>>>);
+
+    impl Write for Shared {
+        fn write(&mut self, buf: &[u8]) -> std::io::Result<usize> {
+            self.0.lock().unwrap().write(buf)
+        }
+
+        fn flush(&mut self) -> std::io::Result<()> {
+            Ok(())
+        }
+    }
+
+    #[test]
+    fn test_redact_udp_announce() {
+        let mut announce = vec![0_u8; 98];
+        announce[8..12].copy_from_slice(&1_i32.to_be_bytes());
+        announce[UDP_KEY].copy_from_slice(&[1, 2, 3, 4]);
+
+        let redacted = redact_udp(Direction::Request, &announce);
+        assert_eq!(&redacted[UDP_KEY], &[0, 0, 0, 0]);
+
+        // Responses and connect requests are left as is.
+        assert_eq!(redact_udp(Direction::Response, &announce), announce);
+        let connect = [0_u8; 16];
+        assert_eq!(redact_udp(Direction::Request, &connect), connect);
+    }
+
+    #[test]
+    fn test_records_are_ndjson() {
+        let shared = Shared::default();
+        let mut capture = Capture::new(shared.clone());
+        capture
+            .record(&Record::udp(Direction::Request, "127.0.0.1:6969", &[0xab]))
+            .unwrap();
+        capture
+            .record(&Record::http_response("http://t.example", 200, b"de"))
+            .unwrap();
+
+        let output = String::from_utf8(shared.0.lock().unwrap().clone()).unwrap();
+        let lines: Vec<serde_json::Value> = output
+            .lines()
+            .map(|line| serde_json::from_str(line).unwrap())
+            .collect();
+
+        assert_eq!(lines.len(), 2);
+        assert_eq!(lines[0]["protocol"], "udp");
+        assert_eq!(lines[0]["data"], "ab");
+        assert!(lines[0].get("status").is_none());
+        assert_eq!(lines[1]["direction"], "response");
+        assert_eq!(lines[1]["status"], 200);
+    }
+}
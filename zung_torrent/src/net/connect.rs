@@ -0,0 +1,167 @@
+//! Dual-stack connections following [Happy Eyeballs](https://www.rfc-editor.org/rfc/rfc8305).
+//!
+//! A host frequently resolves to both IPv6 and IPv4 addresses, and one of the families is often
+//! broken on the local network. Trying the addresses one after the other then waits for a full
+//! connect timeout before the working family is tried. Instead the addresses are sorted so that
+//! the families alternate and a new connection attempt is started every
+//! [`CONNECTION_ATTEMPT_DELAY`] (or as soon as the previous attempt fails) until one of them
+//! succeeds. The remaining attempts are then cancelled.
+//!
+//! Peer connections use [`connect`] directly, while the [`HttpClient`](super::HttpClient) uses
+//! [`Resolver`] to sort the addresses it races.
+
+use std::io::{self, ErrorKind};
+use std::net::SocketAddr;
+use std::time::Duration;
+
+use futures::stream::{FuturesUnordered, StreamExt};
+use reqwest::dns::{Addrs, Name, Resolve, Resolving};
+use tokio::net::{lookup_host, TcpStream, ToSocketAddrs};
+
+/// Time after which the next address is tried while the previous attempts are still pending, as
+/// recommended by RFC 8305.
+pub const CONNECTION_ATTEMPT_DELAY: Duration = Duration::from_millis(250);
+
+/// The error of a host that could not be resolved. It is returned as the inner error of an
+/// [`io::Error`] of the [`ErrorKind::Other`] kind, the kind the resolver reports.
+#[derive(Debug)]
+pub struct ResolveError(io::Error);
+
+impl std::fmt::Display for ResolveError {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(f, "Unable to resolve the host: {}", self.0)
+    }
+}
+
+impl std::error::Error for ResolveError {
+    fn source(&self) -> Option<&(dyn std::error::Error + 'static)> {
+        Some(&self.0)
+    }
+}
+
+/// Resolves the address into the addresses sorted by [`interleave`]. Failures are returned as a
+/// [`ResolveError`].
+pub async fn resolve<A: ToSocketAddrs>(addr: A) -> io::Result<Vec<SocketAddr>> {
+    match lookup_host(addr).await {
+        Ok(addrs) => Ok(interleave(addrs)),
+        Err(e) => Err(io::Error::other(ResolveError(e))),
+    }
+}
+
+/// Resolves the address and connects to the first of the resolved addresses that answers.
+pub async fn connect<A: ToSocketAddrs>(addr: A) -> io::Result<TcpStream> {
+    let addrs = resolve(addr).await?;
+    connect_to(&addrs, CONNECTION_ATTEMPT_DELAY).await
+}
+
+/// Races connections to the addresses in the provided order, starting a new attempt every
+/// `delay`. Returns the first established connection or the error of the last attempt if all of
+/// them fail.
+pub async fn connect_to(addrs: &[SocketAddr], delay: Duration) -> io::Result<TcpStream> {
+    let mut pending = addrs.iter().copied();
+    let mut attempts = FuturesUnordered::new();
+    let mut last_error = None;
+
+    loop {
+        if let Some(addr) = pending.next() {
+            attempts.push(TcpStream::connect(addr));
+        } else if attempts.is_empty() {
+            return Err(last_error.unwrap_or_else(|| {
+                io::Error::new(ErrorKind::InvalidInput, "No addresses to connect to")
+            }));
+        }
+
+        let started_all = pending.len() == 0;
+        tokio::select! {
+            Some(result) = attempts.next() => match result {
+                Ok(stream) => return Ok(stream),
+                // The next address is tried right away.
+                Err(e) => last_error = Some(e),
+            },
+            _ = tokio::time::sleep(delay), if !started_all => {}
+        }
+    }
+}
+
+/// Sorts the addresses so that the families alternate, starting with IPv6 (RFC 8305 section 4).
+/// The order within each family is kept.
+pub fn interleave(addrs: impl IntoIterator<Item = SocketAddr>) -> Vec<SocketAddr> {
+    let (v6, v4): (Vec<_>, Vec<_>) = addrs.into_iter().partition(SocketAddr::is_ipv6);
+    let mut sorted = Vec::with_capacity(v6.len() + v4.len());
+    let (mut v6, mut v4) = (v6.into_iter(), v4.into_iter());
+
+    loop {
+        match (v6.next(), v4.next()) {
+            (None, None) => return sorted,
+            (a, b) => sorted.extend(a.into_iter().chain(b)),
+        }
+    }
+}
+
+/// DNS resolver of the [`HttpClient`](super::HttpClient) which returns the addresses sorted by
+/// [`interleave`].
+#[derive(Debug, Default, Clone, Copy)]
+pub struct Resolver;
+
+impl Resolve for Resolver {
+    fn resolve(&self, name: Name) -> Resolving {
+        Box::pin(async move {
+            let addrs: Addrs = Box::new(resolve((name.as_str(), 0)).await?.into_iter());
+            Ok(addrs)
+        })
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use tokio::net::TcpListener;
+
+    #[test]
+    fn test_interleave() {
+        let addrs: Vec<SocketAddr> = ["1.1.1.1:1", "2.2.2.2:1", "[::1]:1", "3.3.3.3:1", "[::2]:1"]
+            .iter()
+            .map(|a| a.parse().unwrap())
+            .collect();
+
+        let sorted: Vec<String> = interleave(addrs).iter().map(|a| a.to_string()).collect();
+        assert_eq!(
+            sorted,
+            ["[::1]:1", "1.1.1.1:1", "[::2]:1", "2.2.2.2:1", "3.3.3.3:1"]
+        );
+    }
+
+    // Returns an address on which nothing listens.
+    async fn closed_addr() -> SocketAddr {
+        let listener = TcpListener::bind("127.0.0.1:0").await.unwrap();
+        listener.local_addr().unwrap()
+    }
+
+    #[tokio::test]
+    async fn test_falls_back_to_next_address() {
+        let listener = TcpListener::bind("127.0.0.1:0").await.unwrap();
+        let open = listener.local_addr().unwrap();
+        let closed = closed_addr().await;
+
+        // A large delay shows that a failed attempt starts the next one right away.
+        let stream = connect_to(&[closed, open], Duration::from_secs(60))
+            .await
+            .unwrap();
+        assert_eq!(stream.peer_addr().unwrap(), open);
+    }
+
+    #[tokio::test]
+    async fn test_all_addresses_fail() {
+        let closed = closed_addr().await;
+        assert!(connect_to(&[closed], CONNECTION_ATTEMPT_DELAY)
+            .await
+            .is_err());
+        assert_eq!(
+            connect_to(&[], CONNECTION_ATTEMPT_DELAY)
+                .await
+                .unwrap_err()
+                .kind(),
+            ErrorKind::InvalidInput
+        );
+    }
+}
@@ -0,0 +1,383 @@
+//! The HTTP client shared by the trackers and the web seeds.
+//!
+//! Building a client is expensive and every client keeps its own pool of connections, so a single
+//! [`HttpClient`] is shared by the whole library (see [`HttpClient::shared`]). Connections to the
+//! same host are reused across requests and HTTP/2 is used when the server offers it during the
+//! TLS handshake. The addresses of dual-stack hosts are raced as described in [`super::connect`].
+//!
+//! # Example
+//!
+//! ```no_run
+//! use zung_torrent::net::http::HttpClient;
+//!
+//! # async fn run() -> anyhow::Result<()> {
+//! // Optionally configure the shared client before its first use.
+//! let client = HttpClient::builder().user_agent("my-app/1.0").build()?;
+//! HttpClient::set_shared(client).expect("Shared client already in use");
+//!
+//! let response = HttpClient::shared().get("https://example.com/announce").await?;
+//! println!("{} bytes", response.body.len());
+//! # Ok(())
+//! # }
+//! ```
+
+use std::ops::Range;
+use std::sync::{Arc, OnceLock};
+use std::time::Duration;
+
+use anyhow::{bail, Context, Result};
+use bytes::Bytes;
+use reqwest::header;
+use zung_parsers::bencode::{self, Status, StreamParser};
+
+use super::capture::{self, Record};
+use super::connect::Resolver;
+use super::redact;
+
+/// The user agent sent by default, such as `zung/0.1.0`.
+pub const USER_AGENT: &str = concat!("zung/", env!("CARGO_PKG_VERSION"));
+
+/// Idle connections are closed after this duration.
+const POOL_IDLE_TIMEOUT: Duration = Duration::from_secs(90);
+
+/// Default time given to a request to complete.
+const REQUEST_TIMEOUT: Duration = Duration::from_secs(30);
+
+static SHARED: OnceLock<HttpClient> = OnceLock::new();
+
+/// An HTTP client with connection pooling. Cloning the client is cheap and the clones share the
+/// same pool of connections.
+#[derive(Debug, Clone)]
+pub struct HttpClient {
+    inner: reqwest::Client,
+}
+
+/// The response to a request made by the [`HttpClient`].
+#[derive(Debug, Clone)]
+pub struct HttpResponse {
+    /// The HTTP status code.
+    pub status: u16,
+
+    /// The full body of the response.
+    pub body: Bytes,
+}
+
+impl HttpResponse {
+    /// Returns `true` if the status code is in the `2xx` range.
+    pub fn is_success(&self) -> bool {
+        (200..300).contains(&self.status)
+    }
+
+    /// Returns the response if its status code is a success, or a [`StatusError`] for the url
+    /// of the request otherwise.
+    pub fn error_for_status(self, url: &str) -> Result<Self, StatusError> {
+        if self.is_success() {
+            Ok(self)
+        } else {
+            Err(StatusError::new(url, self.status))
+        }
+    }
+}
+
+/// The error of a request answered with a status code other than a success.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct StatusError {
+    /// The url of the request, without its secrets.
+    pub url: String,
+
+    /// The HTTP status code.
+    pub status: u16,
+}
+
+impl StatusError {
+    fn new(url: &str, status: u16) -> Self {
+        Self {
+            url: redact::display_url(url).to_string(),
+            status,
+        }
+    }
+}
+
+impl std::fmt::Display for StatusError {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(f, "{} responded with status {}", self.url, self.status)
+    }
+}
+
+impl std::error::Error for StatusError {}
+
+impl HttpClient {
+    /// Builds a client with the default configuration.
+    pub fn new() -> Result<Self> {
+        Self::builder().build()
+    }
+
+    /// Returns a builder to configure a new client.
+    pub fn builder() -> HttpClientBuilder {
+        HttpClientBuilder::default()
+    }
+
+    /// Returns the client shared by the whole library. A client with the default configuration is
+    /// built on the first call unless one was set with [`HttpClient::set_shared`].
+    pub fn shared() -> &'static HttpClient {
+        SHARED.get_or_init(|| HttpClient::new().expect("Default HTTP client must build"))
+    }
+
+    /// Sets the client returned by [`HttpClient::shared`]. Fails, returning the client, if the
+    /// shared client is already set or was already used.
+    pub fn set_shared(client: HttpClient) -> Result<(), HttpClient> {
+        SHARED.set(client)
+    }
+
+    /// Sends a GET request to the url and reads the whole response.
+    pub async fn get(&self, url: &str) -> Result<HttpResponse> {
+        self.send(url, self.inner.get(url)).await
+    }
+
+    /// Sends a POST request to the url with the value serialized as JSON in the body.
+    pub async fn post_json<T: serde::Serialize>(
+        &self,
+        url: &str,
+        value: &T,
+    ) -> Result<HttpResponse> {
+        let request = self
+            .inner
+            .post(url)
+            .header(header::CONTENT_TYPE, "application/json")
+            .body(serde_json::to_vec(value)?);
+        self.send(url, request).await
+    }
+
+    /// Sends a GET request for the provided range of bytes of the resource at the url.
+    ///
+    /// Fails if the server ignores the range and responds with the whole resource.
+    pub async fn get_range(&self, url: &str, range: Range<usize>) -> Result<HttpResponse> {
+        if range.is_empty() {
+            bail!("Requested an empty range of {}", redact::display_url(url));
+        }
+
+        let request = self.inner.get(url).header(
+            header::RANGE,
+            format!("bytes={}-{}", range.start, range.end - 1),
+        );
+        let response = self.send(url, request).await?;
+
+        if response.status != 206 && response.body.len() != range.len() {
+            bail!(
+                "{} does not support range requests (status {})",
+                redact::display_url(url),
+                response.status
+            );
+        }
+        Ok(response)
+    }
+
+    /// Sends a GET request to the url and reads the response until its body holds a complete
+    /// bencode value, which is returned. The rest of the body is not waited for.
+    ///
+    /// Fails if the status code is not a success, if the body is not valid bencode or if it ends
+    /// before the end of the value.
+    pub async fn get_bencode(&self, url: &str) -> Result<bencode::Value> {
+        let mut response = self.start(url, self.inner.get(url)).await?;
+        let status = response.status().as_u16();
+        if !response.status().is_success() {
+            capture::record(|| Record::http_response(url, status, &[]));
+            return Err(StatusError::new(url, status).into());
+        }
+
+        let mut parser = StreamParser::new();
+        let mut body = Vec::new();
+        let value = loop {
+            let chunk = response
+                .chunk()
+                .await
+                .map_err(reqwest::Error::without_url)
+                .with_context(|| {
+                    format!(
+                        "Failed to read the response of {}",
+                        redact::display_url(url)
+                    )
+                })?;
+            let Some(chunk) = chunk else {
+                bail!(
+                    "The response of {} ended before the end of the bencode value",
+                    redact::display_url(url)
+                );
+            };
+            if capture::is_installed() {
+                body.extend_from_slice(&chunk);
+            }
+
+            let status = parser.feed(&chunk).with_context(|| {
+                format!(
+                    "Invalid bencode in the response of {}",
+                    redact::display_url(url)
+                )
+            })?;
+            if let Status::Done(value) = status {
+                break value;
+            }
+        };
+
+        capture::record(|| Record::http_response(url, status, &body));
+        Ok(value)
+    }
+
+    async fn send(&self, url: &str, request: reqwest::RequestBuilder) -> Result<HttpResponse> {
+        let response = self.start(url, request).await?;
+        let status = response.status().as_u16();
+        let body = response
+            .bytes()
+            .await
+            .map_err(reqwest::Error::without_url)
+            .with_context(|| {
+                format!(
+                    "Failed to read the response of {}",
+                    redact::display_url(url)
+                )
+            })?;
+
+        capture::record(|| Record::http_response(url, status, &body));
+        Ok(HttpResponse { status, body })
+    }
+
+    // Sends the request, returning the response once its headers are received.
+    async fn start(
+        &self,
+        url: &str,
+        request: reqwest::RequestBuilder,
+    ) -> Result<reqwest::Response> {
+        capture::record(|| Record::http_request(url));
+
+        request
+            .send()
+            .await
+            // The errors of reqwest repeat the url, with its secrets.
+            .map_err(reqwest::Error::without_url)
+            .with_context(|| format!("Request to {} failed", redact::display_url(url)))
+    }
+}
+
+/// Configures an [`HttpClient`].
+#[derive(Debug, Clone)]
+pub struct HttpClientBuilder {
+    user_agent: String,
+    timeout: Duration,
+    connect_timeout: Option<Duration>,
+}
+
+impl Default for HttpClientBuilder {
+    fn default() -> Self {
+        Self {
+            user_agent: USER_AGENT.to_string(),
+            timeout: REQUEST_TIMEOUT,
+            connect_timeout: None,
+        }
+    }
+}
+
+impl HttpClientBuilder {
+    /// Sets the `User-Agent` header sent with every request. Defaults to [`USER_AGENT`].
+    pub fn user_agent(mut self, user_agent: impl Into<String>) -> Self {
+        self.user_agent = user_agent.into();
+        self
+    }
+
+    /// Sets the time given to each request to complete, including reading the body.
+    pub fn timeout(mut self, timeout: Duration) -> Self {
+        self.timeout = timeout;
+        self
+    }
+
+    /// Sets the time given to establish a connection. Only the total timeout applies by default.
+    pub fn connect_timeout(mut self, timeout: Duration) -> Self {
+        self.connect_timeout = Some(timeout);
+        self
+    }
+
+    /// Builds the client.
+    pub fn build(self) -> Result<HttpClient> {
+        let mut builder = reqwest::Client::builder()
+            .user_agent(self.user_agent)
+            .timeout(self.timeout)
+            .pool_idle_timeout(POOL_IDLE_TIMEOUT)
+            .dns_resolver(Arc::new(Resolver))
+            .use_rustls_tls();
+
+        if let Some(timeout) = self.connect_timeout {
+            builder = builder.connect_timeout(timeout);
+        }
+
+        Ok(HttpClient {
+            inner: builder.build().context("Failed to build the HTTP client")?,
+        })
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::testing::MockHttpTracker;
+
+    #[tokio::test]
+    async fn test_get() {
+        let mock = MockHttpTracker::start("d8:intervali900ee").await.unwrap();
+        let client = HttpClient::new().unwrap();
+
+        let response = client.get(&format!("{}?a=1", mock.url())).await.unwrap();
+        assert!(response.is_success());
+        assert_eq!(&response.body[..], b"d8:intervali900ee");
+
+        // The pool is shared between the clones.
+        client.clone().get(&mock.url()).await.unwrap();
+        assert_eq!(mock.requests(), vec!["/announce?a=1", "/announce"]);
+    }
+
+    #[tokio::test]
+    async fn test_get_range_requires_support() {
+        // The mock ignores the Range header and sends the whole body.
+        let mock = MockHttpTracker::start("0123456789").await.unwrap();
+        let client = HttpClient::new().unwrap();
+
+        assert!(client.get_range(&mock.url(), 0..4).await.is_err());
+        assert_eq!(
+            &client.get_range(&mock.url(), 0..10).await.unwrap().body[..],
+            b"0123456789"
+        );
+        assert!(client.get_range(&mock.url(), 3..3).await.is_err());
+    }
+
+    #[tokio::test]
+    async fn test_get_bencode_does_not_wait_for_the_body() {
+        use tokio::io::{AsyncReadExt, AsyncWriteExt};
+
+        // Announces a longer body than it sends and keeps the connection open.
+        let listener = tokio::net::TcpListener::bind("127.0.0.1:0").await.unwrap();
+        let url = format!("http://{}/announce", listener.local_addr().unwrap());
+        let server = tokio::spawn(async move {
+            let (mut stream, _) = listener.accept().await.unwrap();
+            let _ = stream.read(&mut [0; 1024]).await.unwrap();
+            stream
+                .write_all(b"HTTP/1.1 200 OK\r\nContent-Length: 100\r\n\r\nd8:interval")
+                .await
+                .unwrap();
+            tokio::time::sleep(Duration::from_millis(20)).await;
+            stream.write_all(b"i900ee").await.unwrap();
+            tokio::time::sleep(Duration::from_secs(60)).await;
+        });
+
+        let client = HttpClient::builder()
+            .timeout(Duration::from_secs(5))
+            .build()
+            .unwrap();
+        let value = client.get_bencode(&url).await.unwrap();
+        assert_eq!(value, bencode::parse("d8:intervali900ee").unwrap());
+        server.abort();
+
+        let mock = MockHttpTracker::start("not bencode").await.unwrap();
+        let err = client.get_bencode(&mock.url()).await.unwrap_err();
+        assert!(err
+            .to_string()
+            .starts_with("Invalid bencode in the response of"));
+    }
+}
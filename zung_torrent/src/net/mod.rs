@@ -0,0 +1,16 @@
+//! The networking layer shared by the trackers, the web seeds and the peers.
+//!
+//! All traffic of the library goes through the helpers in this module, which makes it possible to
+//! observe it in one place. See [`capture`] for recording the traffic to a file.
+
+pub mod capture;
+pub mod connect;
+pub mod http;
+pub mod rate;
+pub mod redact;
+pub(crate) mod udp;
+pub mod utp;
+
+pub use capture::Capture;
+pub use http::HttpClient;
+pub use utp::{UtpSocket, UtpStream};
@@ -0,0 +1,357 @@
+//! Limits of the transfer rate, which can change with the time of the day.
+
+use std::fmt;
+use std::str::FromStr;
+use std::sync::{Arc, Mutex};
+use std::time::{Duration, Instant};
+
+use anyhow::{bail, Context, Result};
+use chrono::{Local, NaiveTime, Timelike};
+use tokio::task::JoinHandle;
+
+/// Limits the rate of a transfer, in bytes per second, shared by every connection of the
+/// transfer.
+///
+/// It is a token bucket holding up to a second of transfer: after a pause, the connections can
+/// send a second worth of bytes at once before they are slowed down.
+#[derive(Debug)]
+pub struct RateLimiter {
+    bucket: Mutex<Bucket>,
+}
+
+#[derive(Debug)]
+struct Bucket {
+    rate: Option<u64>,
+
+    /// Bytes which can be sent right away. Negative when bytes were reserved in advance.
+    tokens: f64,
+    updated: Instant,
+}
+
+impl Default for RateLimiter {
+    fn default() -> Self {
+        Self::new(None)
+    }
+}
+
+impl RateLimiter {
+    /// A limiter of `rate` bytes per second, or an unlimited one.
+    pub fn new(rate: Option<u64>) -> Self {
+        Self {
+            bucket: Mutex::new(Bucket {
+                rate,
+                tokens: rate.unwrap_or(0) as f64,
+                updated: Instant::now(),
+            }),
+        }
+    }
+
+    /// Returns the current rate, `None` meaning unlimited.
+    pub fn rate(&self) -> Option<u64> {
+        self.bucket.lock().expect("Not poisoned").rate
+    }
+
+    /// Changes the rate. The transfers waiting for the limiter keep the delay they were given.
+    pub fn set_rate(&self, rate: Option<u64>) {
+        let mut bucket = self.bucket.lock().expect("Not poisoned");
+        bucket.refill(Instant::now());
+        bucket.rate = rate;
+        if let Some(rate) = rate {
+            bucket.tokens = bucket.tokens.min(rate as f64);
+        }
+    }
+
+    /// Waits until `bytes` can be transferred.
+    pub async fn acquire(&self, bytes: usize) {
+        let wait = self.reserve(bytes, Instant::now());
+        if !wait.is_zero() {
+            tokio::time::sleep(wait).await;
+        }
+    }
+
+    /// Reserves `bytes` at `now` and returns how long to wait before transferring them.
+    fn reserve(&self, bytes: usize, now: Instant) -> Duration {
+        let mut bucket = self.bucket.lock().expect("Not poisoned");
+        let Some(rate) = bucket.rate.filter(|&rate| rate > 0) else {
+            return Duration::ZERO;
+        };
+        bucket.refill(now);
+        bucket.tokens -= bytes as f64;
+        if bucket.tokens >= 0.0 {
+            Duration::ZERO
+        } else {
+            Duration::from_secs_f64(-bucket.tokens / rate as f64)
+        }
+    }
+}
+
+impl Bucket {
+    fn refill(&mut self, now: Instant) {
+        let elapsed = now.saturating_duration_since(self.updated).as_secs_f64();
+        self.updated = now;
+        if let Some(rate) = self.rate {
+            self.tokens = (self.tokens + elapsed * rate as f64).min(rate as f64);
+        }
+    }
+}
+
+/// The rate of a time of the day, from `start` until `end` (excluded). A period ending before it
+/// starts runs over midnight, and a period ending when it starts lasts the whole day.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct RatePeriod {
+    pub start: NaiveTime,
+    pub end: NaiveTime,
+
+    /// Bytes per second, `None` meaning unlimited.
+    pub rate: Option<u64>,
+}
+
+impl RatePeriod {
+    fn contains(&self, time: NaiveTime) -> bool {
+        match self.start.cmp(&self.end) {
+            std::cmp::Ordering::Less => self.start <= time && time < self.end,
+            std::cmp::Ordering::Greater => self.start <= time || time < self.end,
+            std::cmp::Ordering::Equal => true,
+        }
+    }
+}
+
+/// Rate limits changing with the local time of the day, such as unlimited at night and 1 MiB/s
+/// during work hours.
+///
+/// It is parsed from comma separated `start-end=rate` periods, the first period containing the
+/// time applying and the rate being unlimited outside of the periods. A rate without a period
+/// applies all day.
+///
+/// # Example
+///
+/// ```
+/// use chrono::NaiveTime;
+/// use zung_torrent::net::rate::RateSchedule;
+///
+/// let schedule: RateSchedule = "09:00-18:00=1MiB, 18:00-23:00=256k".parse().unwrap();
+/// let time = |h| NaiveTime::from_hms_opt(h, 0, 0).unwrap();
+/// assert_eq!(schedule.rate_at(time(10)), Some(1024 * 1024));
+/// assert_eq!(schedule.rate_at(time(20)), Some(256 * 1024));
+/// assert_eq!(schedule.rate_at(time(2)), None);
+/// ```
+#[derive(Debug, Default, Clone, PartialEq, Eq)]
+pub struct RateSchedule {
+    periods: Vec<RatePeriod>,
+}
+
+impl RateSchedule {
+    /// A schedule with the periods, the first period containing the time applying.
+    pub fn new(periods: Vec<RatePeriod>) -> Self {
+        Self { periods }
+    }
+
+    /// Returns the periods of the schedule.
+    pub fn periods(&self) -> &[RatePeriod] {
+        &self.periods
+    }
+
+    /// Returns `true` if the schedule never limits the rate.
+    pub fn is_unlimited(&self) -> bool {
+        self.periods.iter().all(|period| period.rate.is_none())
+    }
+
+    /// Returns the rate at the time of the day, `None` meaning unlimited.
+    pub fn rate_at(&self, time: NaiveTime) -> Option<u64> {
+        self.periods
+            .iter()
+            .find(|period| period.contains(time))
+            .and_then(|period| period.rate)
+    }
+
+    /// Returns the time from `time` until the next start or end of a period, or `None` if the
+    /// rate never changes.
+    pub fn next_change(&self, time: NaiveTime) -> Option<Duration> {
+        let seconds = |time: NaiveTime| i64::from(time.num_seconds_from_midnight());
+        self.periods
+            .iter()
+            .filter(|period| period.start != period.end)
+            .flat_map(|period| [period.start, period.end])
+            .map(
+                |boundary| match (seconds(boundary) - seconds(time)).rem_euclid(86_400) {
+                    0 => 86_400,
+                    delay => delay,
+                },
+            )
+            .min()
+            .map(|delay| Duration::from_secs(delay as u64))
+    }
+
+    /// Spawns a task setting the rate of the limiter from the schedule whenever it changes,
+    /// following the local time.
+    pub fn spawn(self, limiter: Arc<RateLimiter>) -> JoinHandle<()> {
+        tokio::spawn(async move {
+            loop {
+                let now = Local::now().time();
+                limiter.set_rate(self.rate_at(now));
+                let Some(delay) = self.next_change(now) else {
+                    return;
+                };
+                // Woken up at least hourly, to follow the changes of the clock (e.g. daylight
+                // saving time) without waiting for the next period.
+                tokio::time::sleep(delay.min(Duration::from_secs(3600))).await;
+            }
+        })
+    }
+}
+
+impl FromStr for RateSchedule {
+    type Err = anyhow::Error;
+
+    fn from_str(s: &str) -> Result<Self> {
+        let time = |time: &str| {
+            NaiveTime::parse_from_str(time.trim(), "%H:%M")
+                .with_context(|| format!("Invalid time {time:?} - Expected HH:MM"))
+        };
+
+        let mut periods = Vec::new();
+        for period in s.split(',').map(str::trim).filter(|p| !p.is_empty()) {
+            let (start, end, rate) = match period.split_once('=') {
+                Some((times, rate)) => {
+                    let Some((start, end)) = times.split_once('-') else {
+                        bail!("Invalid period {period:?} - Expected start-end=rate")
+                    };
+                    (time(start)?, time(end)?, rate)
+                }
+                None => (NaiveTime::MIN, NaiveTime::MIN, period),
+            };
+            periods.push(RatePeriod {
+                start,
+                end,
+                rate: parse_rate(rate)?,
+            });
+        }
+        Ok(Self { periods })
+    }
+}
+
+impl fmt::Display for RateSchedule {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        for (i, period) in self.periods.iter().enumerate() {
+            if i > 0 {
+                write!(f, ", ")?;
+            }
+            if period.start != period.end {
+                write!(
+                    f,
+                    "{}-{}=",
+                    period.start.format("%H:%M"),
+                    period.end.format("%H:%M")
+                )?;
+            }
+            match period.rate {
+                None => write!(f, "unlimited")?,
+                Some(rate) if rate % (1024 * 1024) == 0 => write!(f, "{}MiB", rate >> 20)?,
+                Some(rate) if rate % 1024 == 0 => write!(f, "{}KiB", rate >> 10)?,
+                Some(rate) => write!(f, "{rate}")?,
+            }
+        }
+        Ok(())
+    }
+}
+
+// Parses `unlimited` or bytes per second, such as `500k`, `1MiB` or `1MiB/s`.
+fn parse_rate(rate: &str) -> Result<Option<u64>> {
+    let rate = rate.trim();
+    if rate.eq_ignore_ascii_case("unlimited") {
+        return Ok(None);
+    }
+    let bytes = rate.strip_suffix("/s").unwrap_or(rate);
+    let (number, unit) = match bytes.find(|c: char| !c.is_ascii_digit()) {
+        Some(i) => bytes.split_at(i),
+        None => (bytes, ""),
+    };
+    let unit = match unit.trim().to_ascii_lowercase().as_str() {
+        "" | "b" => 1,
+        "k" | "kib" => 1024,
+        "m" | "mib" => 1024 * 1024,
+        _ => bail!("Invalid rate {rate:?} - Expected unlimited, bytes, KiB or MiB"),
+    };
+    number
+        .parse::<u64>()
+        .ok()
+        .and_then(|n| n.checked_mul(unit))
+        .filter(|&n| n > 0)
+        .map(Some)
+        .with_context(|| format!("Invalid rate {rate:?}"))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn time(hour: u32, minute: u32) -> NaiveTime {
+        NaiveTime::from_hms_opt(hour, minute, 0).unwrap()
+    }
+
+    #[test]
+    fn test_token_bucket() {
+        let limiter = RateLimiter::new(Some(1000));
+        let start = Instant::now();
+
+        // A second worth of bytes goes through at once, the rest waits.
+        assert_eq!(limiter.reserve(1000, start), Duration::ZERO);
+        assert_eq!(limiter.reserve(500, start), Duration::from_millis(500));
+        assert_eq!(
+            limiter.reserve(500, start + Duration::from_millis(500)),
+            Duration::from_millis(500)
+        );
+
+        limiter.set_rate(None);
+        assert_eq!(limiter.reserve(1_000_000, start), Duration::ZERO);
+    }
+
+    #[test]
+    fn test_schedule() {
+        let schedule: RateSchedule = "22:00-07:00=unlimited, 09:00-18:00=1MiB/s, 100k"
+            .parse()
+            .unwrap();
+        assert_eq!(schedule.rate_at(time(23, 0)), None);
+        assert_eq!(schedule.rate_at(time(6, 59)), None);
+        assert_eq!(schedule.rate_at(time(9, 0)), Some(1024 * 1024));
+        assert_eq!(schedule.rate_at(time(8, 0)), Some(100 * 1024));
+        assert_eq!(
+            schedule.to_string(),
+            "22:00-07:00=unlimited, 09:00-18:00=1MiB, 100KiB"
+        );
+        assert_eq!(
+            schedule.to_string().parse::<RateSchedule>().unwrap(),
+            schedule
+        );
+
+        assert!("9-18=1MiB".parse::<RateSchedule>().is_err());
+        assert!("09:00-18:00=fast".parse::<RateSchedule>().is_err());
+        assert!("".parse::<RateSchedule>().unwrap().is_unlimited());
+    }
+
+    #[test]
+    fn test_next_change() {
+        let schedule: RateSchedule = "09:00-18:00=1MiB".parse().unwrap();
+        let hours = |h: u64| Some(Duration::from_secs(h * 3600));
+        assert_eq!(schedule.next_change(time(8, 0)), hours(1));
+        assert_eq!(schedule.next_change(time(9, 0)), hours(9));
+        assert_eq!(schedule.next_change(time(20, 0)), hours(13));
+        assert_eq!(
+            "1MiB"
+                .parse::<RateSchedule>()
+                .unwrap()
+                .next_change(time(0, 0)),
+            None
+        );
+    }
+
+    #[tokio::test]
+    async fn test_spawn() {
+        let limiter = Arc::new(RateLimiter::default());
+        let schedule: RateSchedule = "64k".parse().unwrap();
+
+        // A schedule which never changes sets the rate once and stops.
+        schedule.spawn(limiter.clone()).await.unwrap();
+        assert_eq!(limiter.rate(), Some(64 * 1024));
+    }
+}
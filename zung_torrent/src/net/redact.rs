@@ -0,0 +1,185 @@
+//! Redaction of the secrets in the announce urls.
+//!
+//! The announce urls of private trackers embed a passkey identifying the user, either in the
+//! query (`?passkey=...`) or as a path segment (`/1a2b3c.../announce`), and the `key` sent to the
+//! trackers identifies the client across IP changes. Anyone who gets hold of them can announce as
+//! the user, so they are left out of everything zung prints: the [`Display`](std::fmt::Display)
+//! and [`Debug`] implementations of the trackers, the errors and the CLI output.
+//!
+//! [`show_secrets`] opts out of the redaction of the printed urls (`--show-secrets`), e.g. to
+//! copy an url to another client. The [capture](super::capture) files are always redacted since
+//! they are meant to be shared.
+
+use std::borrow::Cow;
+use std::sync::atomic::{AtomicBool, Ordering};
+
+/// Replacement of the redacted values.
+pub const REDACTED: &str = "REDACTED";
+
+/// Query parameters holding a secret, compared without case.
+const SECRET_PARAMS: &[&str] = &[
+    "key",
+    "passkey",
+    "authkey",
+    "torrent_pass",
+    "pk",
+    "token",
+    "secret",
+    "apikey",
+    "api_key",
+];
+
+/// Minimum length of a path segment to be taken for a passkey.
+const MIN_PASSKEY_LENGTH: usize = 16;
+
+static SHOW_SECRETS: AtomicBool = AtomicBool::new(false);
+
+/// Sets whether [`display_url`] shows the urls as they are instead of redacting them.
+pub fn show_secrets(show: bool) {
+    SHOW_SECRETS.store(show, Ordering::Relaxed);
+}
+
+/// Returns `true` if the urls are printed as they are. See [`show_secrets`].
+pub fn secrets_shown() -> bool {
+    SHOW_SECRETS.load(Ordering::Relaxed)
+}
+
+/// Returns the url to print: redacted by [`redact_url`] unless [`show_secrets`] is set.
+pub fn display_url(url: &str) -> Cow<'_, str> {
+    if secrets_shown() {
+        Cow::Borrowed(url)
+    } else {
+        redact_url(url)
+    }
+}
+
+/// Replaces the secrets of the url with [`REDACTED`]: the user info, the values of the secret
+/// query parameters (`key`, `passkey`, `authkey`, `torrent_pass`, ...) and the path segments which
+/// look like a passkey, i.e. long alphanumeric segments with digits.
+///
+/// # Example
+///
+/// ```
+/// use zung_torrent::net::redact::redact_url;
+///
+/// assert_eq!(
+///     redact_url("https://t.example/0123456789abcdef0123456789abcdef/announce?uid=7"),
+///     "https://t.example/REDACTED/announce?uid=7"
+/// );
+/// assert_eq!(
+///     redact_url("http://t.example/announce.php?passkey=s3cret&info_hash=%12"),
+///     "http://t.example/announce.php?passkey=REDACTED&info_hash=%12"
+/// );
+/// ```
+pub fn redact_url(url: &str) -> Cow<'_, str> {
+    let (scheme, rest) = match url.split_once("://") {
+        Some((scheme, rest)) => (Some(scheme), rest),
+        None => (None, url),
+    };
+    let (rest, fragment) = match rest.split_once('#') {
+        Some((rest, fragment)) => (rest, Some(fragment)),
+        None => (rest, None),
+    };
+    let (rest, query) = match rest.split_once('?') {
+        Some((rest, query)) => (rest, Some(query)),
+        None => (rest, None),
+    };
+    let (authority, path) = match (scheme, rest.find('/')) {
+        (Some(_), Some(i)) => rest.split_at(i),
+        (Some(_), None) => (rest, ""),
+        (None, _) => ("", rest),
+    };
+
+    let mut redacted = false;
+    let authority = match authority.rsplit_once('@') {
+        Some((_, host)) => {
+            redacted = true;
+            format!("{REDACTED}@{host}")
+        }
+        None => authority.to_string(),
+    };
+    let path: Vec<_> = path
+        .split('/')
+        .map(|segment| {
+            if is_passkey(segment) {
+                redacted = true;
+                REDACTED
+            } else {
+                segment
+            }
+        })
+        .collect();
+    let query: Option<Vec<_>> = query.map(|query| {
+        query
+            .split('&')
+            .map(|param| match param.split_once('=') {
+                Some((name, value))
+                    if !value.is_empty()
+                        && SECRET_PARAMS.iter().any(|p| p.eq_ignore_ascii_case(name)) =>
+                {
+                    redacted = true;
+                    Cow::Owned(format!("{name}={REDACTED}"))
+                }
+                _ => Cow::Borrowed(param),
+            })
+            .collect()
+    });
+
+    if !redacted {
+        return Cow::Borrowed(url);
+    }
+    let mut url = String::with_capacity(url.len());
+    if let Some(scheme) = scheme {
+        url.push_str(scheme);
+        url.push_str("://");
+    }
+    url.push_str(&authority);
+    url.push_str(&path.join("/"));
+    if let Some(query) = query {
+        url.push('?');
+        url.push_str(&query.join("&"));
+    }
+    if let Some(fragment) = fragment {
+        url.push('#');
+        url.push_str(fragment);
+    }
+    Cow::Owned(url)
+}
+
+fn is_passkey(segment: &str) -> bool {
+    segment.len() >= MIN_PASSKEY_LENGTH
+        && segment.bytes().all(|b| b.is_ascii_alphanumeric())
+        && segment.bytes().any(|b| b.is_ascii_digit())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_redact_url() {
+        let unchanged = [
+            "http://t.example/announce",
+            "udp://tracker.example.com:6969/announce",
+            "http://t.example/announce?passkey=",
+            "http://t.example/announcements/announce?uid=12",
+            "not an url",
+        ];
+        for url in unchanged {
+            assert!(matches!(redact_url(url), Cow::Borrowed(_)), "{url}");
+        }
+
+        assert_eq!(
+            redact_url("http://user:pw@t.example:80/a/0123456789abcdef01/announce#top"),
+            "http://REDACTED@t.example:80/a/REDACTED/announce#top"
+        );
+        assert_eq!(
+            redact_url("https://t.example/announce?info_hash=%12&KEY=1a2b&authkey=x&port=6881"),
+            "https://t.example/announce?info_hash=%12&KEY=REDACTED&authkey=REDACTED&port=6881"
+        );
+        assert_eq!(
+            redact_url("udp://t.example:6969/announce/0123456789abcdef01"),
+            "udp://t.example:6969/announce/REDACTED"
+        );
+    }
+}
@@ -0,0 +1,285 @@
+//! A pool of UDP sockets shared by all the UDP tracker requests.
+//!
+//! Instead of binding a new socket for every request, a single unconnected socket is bound per
+//! address family and reused for the connects, announces and scrapes of every tracker. A
+//! background task receives the datagrams of each socket and routes them to the pending
+//! [`Transaction`] with the matching `transaction_id`, hence any number of requests can be in
+//! flight at once without exhausting the local ports.
+//!
+//! The transaction ids are random, so that responses can not be forged without seeing the
+//! requests, and a datagram is only accepted from the remote its transaction was sent to. Every
+//! other datagram is dropped.
+//!
+//! The sockets are bound per tokio runtime as a socket can not outlive the runtime driving it.
+
+use std::collections::HashMap;
+use std::net::{Ipv4Addr, Ipv6Addr, SocketAddr};
+use std::ops::Range;
+use std::sync::{Arc, Mutex};
+
+use anyhow::{bail, Context, Result};
+use tokio::net::UdpSocket;
+use tokio::runtime::{self, Handle};
+use tokio::sync::oneshot;
+use tokio::task::JoinHandle;
+
+use super::capture::{record, Direction, Record};
+
+/// Size of the receive buffer, large enough for any UDP datagram.
+const MAX_DATAGRAM: usize = 65_536;
+
+/// Offset and length of the `transaction_id` in every response of the UDP tracker protocol.
+const TRANSACTION_ID: Range<usize> = 4..8;
+
+static POOL: Mutex<Vec<Arc<PooledSocket>>> = Mutex::new(Vec::new());
+
+/// Returns the pooled socket for the address family of `remote`, binding it on first use.
+///
+/// Must be called from within a tokio runtime.
+pub(crate) fn socket_for(remote: SocketAddr) -> Result<Arc<PooledSocket>> {
+    let runtime = Handle::try_current()
+        .context("UDP requests must be made from within a tokio runtime")?
+        .id();
+
+    let mut pool = POOL.lock().expect("UDP pool lock poisoned");
+
+    // The sockets of the runtimes which were shut down can not be used anymore.
+    pool.retain(|socket| !socket.task.is_finished());
+
+    let socket = pool
+        .iter()
+        .find(|socket| socket.runtime == runtime && socket.is_ipv6 == remote.is_ipv6());
+    if let Some(socket) = socket {
+        return Ok(Arc::clone(socket));
+    }
+
+    let socket = Arc::new(PooledSocket::bind(remote.is_ipv6())?);
+    pool.push(Arc::clone(&socket));
+    Ok(socket)
+}
+
+#[derive(Debug)]
+struct Shared {
+    socket: UdpSocket,
+    pending: Mutex<HashMap<i32, Pending>>,
+}
+
+/// A transaction waiting for its response.
+#[derive(Debug)]
+struct Pending {
+    remote: SocketAddr,
+    sender: oneshot::Sender<Vec<u8>>,
+}
+
+/// An unconnected UDP socket shared by all the requests to the remotes of an address family.
+#[derive(Debug)]
+pub(crate) struct PooledSocket {
+    runtime: runtime::Id,
+    is_ipv6: bool,
+    shared: Arc<Shared>,
+    task: JoinHandle<()>,
+}
+
+impl PooledSocket {
+    fn bind(is_ipv6: bool) -> Result<Self> {
+        let addr: SocketAddr = if is_ipv6 {
+            (Ipv6Addr::UNSPECIFIED, 0).into()
+        } else {
+            (Ipv4Addr::UNSPECIFIED, 0).into()
+        };
+
+        // Bound synchronously so that the pool lock is never held across an await point.
+        let socket = std::net::UdpSocket::bind(addr)
+            .with_context(|| format!("Unable to bind a UDP socket on {addr}"))?;
+        socket.set_nonblocking(true)?;
+
+        let shared = Arc::new(Shared {
+            socket: UdpSocket::from_std(socket)?,
+            pending: Mutex::new(HashMap::new()),
+        });
+
+        Ok(Self {
+            runtime: Handle::current().id(),
+            is_ipv6,
+            task: tokio::spawn(route(Arc::clone(&shared))),
+            shared,
+        })
+    }
+
+    /// Starts a new transaction with the remote, reserving a random `transaction_id` not used by
+    /// any other pending transaction of this socket.
+    pub(crate) fn transaction(&self, remote: SocketAddr) -> Transaction {
+        let (sender, receiver) = oneshot::channel();
+
+        let mut pending = self.shared.pending.lock().expect("UDP pool lock poisoned");
+        let id = loop {
+            // Drawn from a CSPRNG so that the ids of the other transactions can not be guessed.
+            let id = rand::random();
+            if !pending.contains_key(&id) {
+                break id;
+            }
+        };
+        pending.insert(id, Pending { remote, sender });
+
+        Transaction {
+            id,
+            remote,
+            shared: Arc::clone(&self.shared),
+            receiver,
+        }
+    }
+}
+
+impl Drop for PooledSocket {
+    fn drop(&mut self) {
+        self.task.abort();
+    }
+}
+
+/// Receives the datagrams of the socket and hands them to the pending transactions.
+async fn route(shared: Arc<Shared>) {
+    let mut buf = vec![0_u8; MAX_DATAGRAM];
+    loop {
+        // Errors on an unconnected socket are about a single datagram and do not stop the socket.
+        let Ok((len, from)) = shared.socket.recv_from(&mut buf).await else {
+            continue;
+        };
+        let packet = &buf[..len];
+        record(|| Record::udp(Direction::Response, from.to_string(), packet));
+
+        let Some(id) = packet.get(TRANSACTION_ID) else {
+            continue;
+        };
+        let id = i32::from_be_bytes(id.try_into().expect("Range of 4 bytes"));
+
+        let mut pending = shared.pending.lock().expect("UDP pool lock poisoned");
+        // Responses from any other address are spoofed (or stray) and must not complete the
+        // transaction.
+        if pending
+            .get(&id)
+            .is_some_and(|pending| pending.remote == from)
+        {
+            let pending = pending.remove(&id).expect("Checked above");
+            let _ = pending.sender.send(packet.to_vec());
+        }
+    }
+}
+
+/// A single request and its response, sent through a [`PooledSocket`].
+///
+/// The `transaction_id` stays reserved until the response is received or the transaction is
+/// dropped.
+#[derive(Debug)]
+pub(crate) struct Transaction {
+    id: i32,
+    remote: SocketAddr,
+    shared: Arc<Shared>,
+    receiver: oneshot::Receiver<Vec<u8>>,
+}
+
+impl Transaction {
+    /// The `transaction_id` to be sent in the request.
+    pub(crate) fn id(&self) -> i32 {
+        self.id
+    }
+
+    pub(crate) async fn send(&self, packet: &[u8]) -> Result<()> {
+        record(|| Record::udp(Direction::Request, self.remote.to_string(), packet));
+        self.shared.socket.send_to(packet, self.remote).await?;
+        Ok(())
+    }
+
+    /// Waits for the response carrying the `transaction_id` of this transaction.
+    pub(crate) async fn recv(&mut self) -> Result<Vec<u8>> {
+        match (&mut self.receiver).await {
+            Ok(response) => Ok(response),
+            Err(_) => bail!("The UDP socket was closed"),
+        }
+    }
+}
+
+impl Drop for Transaction {
+    fn drop(&mut self) {
+        if let Ok(mut pending) = self.shared.pending.lock() {
+            pending.remove(&self.id);
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[tokio::test]
+    async fn test_socket_is_shared_per_family() {
+        let v4: SocketAddr = (Ipv4Addr::LOCALHOST, 6969).into();
+        let first = socket_for(v4).unwrap();
+        let second = socket_for((Ipv4Addr::LOCALHOST, 1337).into()).unwrap();
+        assert!(Arc::ptr_eq(&first, &second));
+        assert!(!first.shared.socket.local_addr().unwrap().is_ipv6());
+
+        let first = first.transaction(v4);
+        let second = second.transaction(v4);
+        assert_ne!(first.id(), second.id());
+    }
+
+    #[tokio::test]
+    async fn test_responses_are_routed_by_transaction_id() {
+        let server = UdpSocket::bind((Ipv4Addr::LOCALHOST, 0)).await.unwrap();
+        let remote = server.local_addr().unwrap();
+        let socket = socket_for(remote).unwrap();
+
+        let mut transactions: Vec<_> = (0..3).map(|_| socket.transaction(remote)).collect();
+        for transaction in &transactions {
+            transaction
+                .send(&transaction.id().to_be_bytes())
+                .await
+                .unwrap();
+        }
+
+        let mut requests = Vec::new();
+        for _ in 0..3 {
+            let mut buf = [0_u8; 4];
+            let (_, from) = server.recv_from(&mut buf).await.unwrap();
+            assert_eq!(
+                from.port(),
+                socket.shared.socket.local_addr().unwrap().port()
+            );
+            requests.push((buf, from));
+        }
+
+        // Answer in the reverse order, each response echoing the transaction id.
+        for (id, from) in requests.iter().rev() {
+            let mut response = vec![0_u8; 4];
+            response.extend_from_slice(id);
+            server.send_to(&response, from).await.unwrap();
+        }
+
+        for transaction in &mut transactions {
+            let response = transaction.recv().await.unwrap();
+            assert_eq!(response[TRANSACTION_ID], transaction.id().to_be_bytes());
+        }
+    }
+
+    #[tokio::test]
+    async fn test_responses_from_other_addresses_are_ignored() {
+        let server = UdpSocket::bind((Ipv4Addr::LOCALHOST, 0)).await.unwrap();
+        let spoofer = UdpSocket::bind((Ipv4Addr::LOCALHOST, 0)).await.unwrap();
+        let remote = server.local_addr().unwrap();
+
+        let mut transaction = socket_for(remote).unwrap().transaction(remote);
+        transaction.send(b"ping").await.unwrap();
+        let mut buf = [0_u8; 4];
+        let (_, from) = server.recv_from(&mut buf).await.unwrap();
+
+        let mut spoofed = b"nope".to_vec();
+        spoofed.extend_from_slice(&transaction.id().to_be_bytes());
+        spoofer.send_to(&spoofed, from).await.unwrap();
+
+        let mut response = b"pong".to_vec();
+        response.extend_from_slice(&transaction.id().to_be_bytes());
+        server.send_to(&response, from).await.unwrap();
+
+        assert_eq!(transaction.recv().await.unwrap(), response);
+    }
+}
@@ -0,0 +1,1114 @@
+//! Peer connections over UDP with the [Micro Transport
+//! Protocol](https://www.bittorrent.org/beps/bep_0029.html) (uTP).
+//!
+//! uTP provides ordered and reliable streams like TCP, but its congestion control, LEDBAT, backs
+//! off as soon as the delay of the packets grows instead of when they are lost. The downloads then
+//! use the bandwidth nobody else uses, without filling the buffers of the routers and slowing down
+//! the other traffic of the network (bufferbloat).
+//!
+//! A [`UtpSocket`] is bound on a single UDP port for any number of connections. A background task
+//! receives its datagrams and routes them to the connection with the matching connection id, each
+//! of them driven by a task of its own. A [`UtpStream`] is an [`AsyncRead`] + [`AsyncWrite`]
+//! stream, so the handshakes and the [`FramedTransport`](crate::peers::FramedTransport) of the
+//! peers work on top of it as they do on a TCP stream.
+//!
+//! Only the base protocol is supported: the extensions of the packets received, such as the
+//! selective acks, are skipped and the lost packets are detected by duplicate acks and timeouts.
+
+use std::collections::{HashMap, VecDeque};
+use std::io::{self, ErrorKind};
+use std::net::SocketAddr;
+use std::pin::Pin;
+use std::sync::{Arc, Mutex, OnceLock};
+use std::task::{Context as TaskContext, Poll, Waker};
+use std::time::{Duration, Instant};
+
+use anyhow::{bail, Context, Result};
+use tokio::io::{AsyncRead, AsyncWrite, ReadBuf};
+use tokio::net::{ToSocketAddrs, UdpSocket};
+use tokio::sync::{mpsc, oneshot, Notify};
+use tokio::task::JoinHandle;
+
+/// Size of the receive buffer, large enough for any UDP datagram.
+const MAX_DATAGRAM: usize = 65_536;
+
+/// Length of the header of every packet, without the extensions.
+const HEADER_LENGTH: usize = 20;
+
+/// The version of the protocol, in the low bits of the first byte.
+const VERSION: u8 = 1;
+
+/// Maximum number of bytes of data in a packet. Leaves room for the headers within the MTU of
+/// most links, tunnels included.
+const MAX_PAYLOAD: usize = 1200;
+
+/// Bytes received and not read yet after which the packets are dropped until the stream is read.
+const RECEIVE_BUFFER: usize = 1 << 20;
+
+/// Bytes written and not sent yet after which the writes wait.
+const SEND_BUFFER: usize = 1 << 20;
+
+/// The queuing delay LEDBAT aims for, in microseconds. The window grows while the packets are
+/// delayed less than this and shrinks once they are delayed more.
+const TARGET_DELAY: f64 = 100_000.0;
+
+/// Maximum growth of the congestion window in one round trip, in bytes.
+const MAX_WINDOW_INCREASE: f64 = 3000.0;
+
+const MIN_WINDOW: f64 = MAX_PAYLOAD as f64;
+const INITIAL_WINDOW: f64 = 4.0 * MAX_PAYLOAD as f64;
+
+/// The base delay is the lowest delay of the last two of these periods.
+const BASE_DELAY_PERIOD: Duration = Duration::from_secs(60);
+
+const INITIAL_TIMEOUT: Duration = Duration::from_secs(1);
+const MIN_TIMEOUT: Duration = Duration::from_millis(500);
+const MAX_TIMEOUT: Duration = Duration::from_secs(30);
+
+/// Number of times a packet is sent again before giving up on the connection.
+const MAX_RETRANSMISSIONS: u32 = 5;
+
+/// Number of duplicate acks after which the oldest packet in flight is considered lost.
+const DUPLICATE_ACKS: u32 = 3;
+
+/// Maximum number of packets received ahead of a missing one which are kept.
+const MAX_REORDERED: u16 = 512;
+
+/// Maximum number of connections waiting to be accepted.
+const BACKLOG: usize = 32;
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+enum PacketType {
+    Data = 0,
+    Fin = 1,
+    State = 2,
+    Reset = 3,
+    Syn = 4,
+}
+
+/// A packet of the protocol.
+#[derive(Debug, Clone, PartialEq, Eq)]
+struct Packet {
+    kind: PacketType,
+    connection_id: u16,
+    timestamp: u32,
+    timestamp_difference: u32,
+    window: u32,
+    seq_nr: u16,
+    ack_nr: u16,
+    payload: Vec<u8>,
+}
+
+impl Packet {
+    fn decode(bytes: &[u8]) -> Result<Self> {
+        if bytes.len() < HEADER_LENGTH {
+            bail!("uTP packet too short: {} bytes", bytes.len());
+        }
+        if bytes[0] & 0x0F != VERSION {
+            bail!("Unsupported uTP version {}", bytes[0] & 0x0F);
+        }
+        let kind = match bytes[0] >> 4 {
+            0 => PacketType::Data,
+            1 => PacketType::Fin,
+            2 => PacketType::State,
+            3 => PacketType::Reset,
+            4 => PacketType::Syn,
+            kind => bail!("Unknown uTP packet type {kind}"),
+        };
+        let u16_at = |i: usize| u16::from_be_bytes([bytes[i], bytes[i + 1]]);
+        let u32_at = |i: usize| u32::from_be_bytes(bytes[i..i + 4].try_into().expect("4 bytes"));
+
+        // Each extension starts with the type of the next one and its length.
+        let (mut extension, mut offset) = (bytes[1], HEADER_LENGTH);
+        while extension != 0 {
+            let header = bytes
+                .get(offset..offset + 2)
+                .context("Truncated uTP extension")?;
+            (extension, offset) = (header[0], offset + 2 + header[1] as usize);
+            if offset > bytes.len() {
+                bail!("Truncated uTP extension");
+            }
+        }
+
+        Ok(Self {
+            kind,
+            connection_id: u16_at(2),
+            timestamp: u32_at(4),
+            timestamp_difference: u32_at(8),
+            window: u32_at(12),
+            seq_nr: u16_at(16),
+            ack_nr: u16_at(18),
+            payload: bytes[offset..].to_vec(),
+        })
+    }
+
+    fn encode(&self) -> Vec<u8> {
+        let mut bytes = Vec::with_capacity(HEADER_LENGTH + self.payload.len());
+        bytes.push((self.kind as u8) << 4 | VERSION);
+        bytes.push(0);
+        bytes.extend_from_slice(&self.connection_id.to_be_bytes());
+        bytes.extend_from_slice(&self.timestamp.to_be_bytes());
+        bytes.extend_from_slice(&self.timestamp_difference.to_be_bytes());
+        bytes.extend_from_slice(&self.window.to_be_bytes());
+        bytes.extend_from_slice(&self.seq_nr.to_be_bytes());
+        bytes.extend_from_slice(&self.ack_nr.to_be_bytes());
+        bytes.extend_from_slice(&self.payload);
+        bytes
+    }
+}
+
+// The current time in microseconds, as sent in the packets. Wraps every 71 minutes, which the
+// differences of timestamps take into account.
+fn timestamp() -> u32 {
+    static START: OnceLock<Instant> = OnceLock::new();
+    START.get_or_init(Instant::now).elapsed().as_micros() as u32
+}
+
+// Returns `true` if the sequence number `a` comes after `b`, taking the wrapping into account.
+fn after(a: u16, b: u16) -> bool {
+    a != b && a.wrapping_sub(b) < 0x8000
+}
+
+/// The LEDBAT congestion control: the window grows while the one-way delay of the packets stays
+/// below the target delay above the lowest delay seen recently (the base delay), and shrinks as
+/// the delay exceeds it, which happens as soon as the packets queue up in a router.
+#[derive(Debug)]
+struct Ledbat {
+    window: f64,
+
+    /// The start of the last periods and the lowest delay seen during each of them.
+    base_delays: VecDeque<(Instant, u32)>,
+}
+
+impl Ledbat {
+    fn new() -> Self {
+        Self {
+            window: INITIAL_WINDOW,
+            base_delays: VecDeque::new(),
+        }
+    }
+
+    /// Number of bytes which may be in flight.
+    fn window(&self) -> usize {
+        self.window as usize
+    }
+
+    /// Grows or shrinks the window after `bytes` were acked, by an ack which reports the packets
+    /// took `delay` microseconds to arrive. The delays include the offset between the clocks of
+    /// the peers, which the base delay cancels out.
+    fn on_ack(&mut self, bytes: usize, delay: u32, now: Instant) {
+        if bytes == 0 {
+            return;
+        }
+        let queuing = (delay.wrapping_sub(self.base_delay(delay, now)) as i32).max(0) as f64;
+        let off_target = (TARGET_DELAY - queuing) / TARGET_DELAY;
+        let window_factor = bytes as f64 / self.window.max(bytes as f64);
+        self.window =
+            (self.window + MAX_WINDOW_INCREASE * off_target * window_factor).max(MIN_WINDOW);
+    }
+
+    /// Halves the window after a packet was lost.
+    fn on_loss(&mut self) {
+        self.window = (self.window / 2.0).max(MIN_WINDOW);
+    }
+
+    /// Starts over with a single packet after a timeout.
+    fn on_timeout(&mut self) {
+        self.window = MIN_WINDOW;
+    }
+
+    // Records the delay and returns the lowest delay of the last two periods.
+    fn base_delay(&mut self, delay: u32, now: Instant) -> u32 {
+        let lower = |a: u32, b: u32| if (a.wrapping_sub(b) as i32) < 0 { a } else { b };
+        match self.base_delays.back_mut() {
+            Some((start, lowest)) if now.duration_since(*start) < BASE_DELAY_PERIOD => {
+                *lowest = lower(delay, *lowest);
+            }
+            _ => {
+                self.base_delays.push_back((now, delay));
+                if self.base_delays.len() > 2 {
+                    self.base_delays.pop_front();
+                }
+            }
+        }
+        self.base_delays
+            .iter()
+            .map(|(_, lowest)| *lowest)
+            .reduce(lower)
+            .unwrap_or(delay)
+    }
+}
+
+#[derive(Debug)]
+struct Shared {
+    socket: UdpSocket,
+
+    /// The connections of the socket by remote address and the id of their incoming packets.
+    connections: Mutex<HashMap<(SocketAddr, u16), mpsc::UnboundedSender<Packet>>>,
+}
+
+/// A UDP socket carrying uTP connections, to and from any number of peers.
+///
+/// The connections stop once the socket is dropped.
+#[derive(Debug)]
+pub struct UtpSocket {
+    shared: Arc<Shared>,
+    incoming: tokio::sync::Mutex<mpsc::Receiver<UtpStream>>,
+    task: JoinHandle<()>,
+}
+
+impl UtpSocket {
+    /// Binds the UDP socket. Must be called from within a tokio runtime.
+    pub async fn bind<A: ToSocketAddrs>(addr: A) -> Result<Self> {
+        let socket = UdpSocket::bind(addr)
+            .await
+            .context("Unable to bind the uTP socket")?;
+        let shared = Arc::new(Shared {
+            socket,
+            connections: Mutex::new(HashMap::new()),
+        });
+        let (accept, incoming) = mpsc::channel(BACKLOG);
+
+        Ok(Self {
+            task: tokio::spawn(route(Arc::clone(&shared), accept)),
+            shared,
+            incoming: tokio::sync::Mutex::new(incoming),
+        })
+    }
+
+    /// The address the socket is bound to.
+    pub fn local_addr(&self) -> Result<SocketAddr> {
+        Ok(self.shared.socket.local_addr()?)
+    }
+
+    /// Opens a connection to the remote. Fails if the remote does not answer after a few
+    /// attempts.
+    pub async fn connect(&self, remote: SocketAddr) -> Result<UtpStream> {
+        let (sender, packets) = mpsc::unbounded_channel();
+        let recv_id = {
+            let mut connections = self.shared.connections.lock().expect("uTP lock poisoned");
+            let recv_id = loop {
+                let id: u16 = rand::random();
+                if !connections.contains_key(&(remote, id)) {
+                    break id;
+                }
+            };
+            connections.insert((remote, recv_id), sender);
+            recv_id
+        };
+
+        let stream = Arc::new(StreamShared::default());
+        let (on_connect, connected) = oneshot::channel();
+        let mut connection = Connection::new(
+            Arc::clone(&self.shared),
+            remote,
+            recv_id,
+            recv_id.wrapping_add(1),
+            Arc::clone(&stream),
+        );
+        connection.seq_nr = 1;
+        connection.on_connect = Some(on_connect);
+        tokio::spawn(connection.run(packets));
+
+        // Dropped while connecting, the stream stops the connection.
+        let stream = UtpStream {
+            shared: stream,
+            remote,
+        };
+        match connected.await {
+            Ok(Ok(())) => Ok(stream),
+            Ok(Err(e)) => Err(e).with_context(|| format!("Unable to connect to {remote} over uTP")),
+            Err(_) => bail!("The uTP socket was closed"),
+        }
+    }
+
+    /// Waits for a remote to connect to the socket.
+    pub async fn accept(&self) -> Result<UtpStream> {
+        self.incoming
+            .lock()
+            .await
+            .recv()
+            .await
+            .context("The uTP socket was closed")
+    }
+}
+
+impl Drop for UtpSocket {
+    fn drop(&mut self) {
+        self.task.abort();
+        // Closes the channels of the connections, which stops them.
+        if let Ok(mut connections) = self.shared.connections.lock() {
+            connections.clear();
+        }
+    }
+}
+
+/// Receives the datagrams of the socket and hands them to their connection. A `syn` packet of an
+/// unknown connection starts a new one, which waits to be accepted.
+async fn route(shared: Arc<Shared>, accept: mpsc::Sender<UtpStream>) {
+    let mut buf = vec![0_u8; MAX_DATAGRAM];
+    loop {
+        // Errors on an unconnected socket are about a single datagram and do not stop the socket.
+        let Ok((len, from)) = shared.socket.recv_from(&mut buf).await else {
+            continue;
+        };
+        let Ok(packet) = Packet::decode(&buf[..len]) else {
+            continue;
+        };
+
+        // The `syn` carries the id of the packets the initiator receives, which it sends with
+        // the next id.
+        let key = match packet.kind {
+            PacketType::Syn => (from, packet.connection_id.wrapping_add(1)),
+            _ => (from, packet.connection_id),
+        };
+        let mut connections = shared.connections.lock().expect("uTP lock poisoned");
+        if let Some(connection) = connections.get(&key) {
+            let _ = connection.send(packet);
+            continue;
+        }
+        if packet.kind != PacketType::Syn {
+            continue;
+        }
+        // The connections are dropped while the backlog is full.
+        let Ok(permit) = accept.try_reserve() else {
+            continue;
+        };
+
+        let (sender, packets) = mpsc::unbounded_channel();
+        let stream = Arc::new(StreamShared::default());
+        let mut connection = Connection::new(
+            Arc::clone(&shared),
+            from,
+            key.1,
+            packet.connection_id,
+            Arc::clone(&stream),
+        );
+        connection.seq_nr = rand::random();
+        connection.ack_nr = packet.seq_nr;
+        connection.connected = true;
+        let _ = sender.send(packet);
+        connections.insert(key, sender);
+        tokio::spawn(connection.run(packets));
+
+        permit.send(UtpStream {
+            shared: stream,
+            remote: from,
+        });
+    }
+}
+
+/// A packet sent and not acked yet.
+#[derive(Debug)]
+struct Sent {
+    kind: PacketType,
+    seq_nr: u16,
+    payload: Vec<u8>,
+    sent_at: Instant,
+    transmissions: u32,
+}
+
+/// The state of a connection, driven by a task of its own.
+#[derive(Debug)]
+struct Connection {
+    shared: Arc<Shared>,
+    remote: SocketAddr,
+    recv_id: u16,
+    send_id: u16,
+    stream: Arc<StreamShared>,
+
+    /// Set until the initiator of the connection gets the answer to its `syn`.
+    on_connect: Option<oneshot::Sender<io::Result<()>>>,
+    connected: bool,
+
+    /// The sequence number of the next packet sent.
+    seq_nr: u16,
+
+    /// The sequence number of the last packet received in order.
+    ack_nr: u16,
+
+    in_flight: VecDeque<Sent>,
+    duplicate_acks: u32,
+
+    /// The packets received after a missing one, by sequence number.
+    reordered: HashMap<u16, Packet>,
+
+    /// Number of bytes the remote can receive.
+    remote_window: usize,
+
+    /// The delay of the last packet received, sent back to the remote for its congestion
+    /// control.
+    reply_delay: u32,
+    ledbat: Ledbat,
+
+    /// The smoothed round trip time and its variation.
+    rtt: Option<(Duration, Duration)>,
+    timeout: Duration,
+
+    fin_sent: bool,
+    fin_received: bool,
+}
+
+impl Connection {
+    fn new(
+        shared: Arc<Shared>,
+        remote: SocketAddr,
+        recv_id: u16,
+        send_id: u16,
+        stream: Arc<StreamShared>,
+    ) -> Self {
+        Self {
+            shared,
+            remote,
+            recv_id,
+            send_id,
+            stream,
+            on_connect: None,
+            connected: false,
+            seq_nr: 0,
+            ack_nr: 0,
+            in_flight: VecDeque::new(),
+            duplicate_acks: 0,
+            reordered: HashMap::new(),
+            remote_window: MAX_PAYLOAD,
+            reply_delay: 0,
+            ledbat: Ledbat::new(),
+            rtt: None,
+            timeout: INITIAL_TIMEOUT,
+            fin_sent: false,
+            fin_received: false,
+        }
+    }
+
+    async fn run(mut self, mut packets: mpsc::UnboundedReceiver<Packet>) {
+        let result = self.drive(&mut packets).await;
+
+        self.shared
+            .connections
+            .lock()
+            .expect("uTP lock poisoned")
+            .remove(&(self.remote, self.recv_id));
+
+        let mut state = self.stream.lock();
+        if let Err(e) = result {
+            state.error = Some(e.kind());
+            if let Some(on_connect) = self.on_connect.take() {
+                let _ = on_connect.send(Err(e));
+            }
+        }
+        state.finished = true;
+        state.wake();
+    }
+
+    async fn drive(&mut self, packets: &mut mpsc::UnboundedReceiver<Packet>) -> io::Result<()> {
+        if self.on_connect.is_some() {
+            self.send_new(PacketType::Syn, Vec::new()).await?;
+        }
+
+        let stream = Arc::clone(&self.stream);
+        loop {
+            self.send_data().await?;
+            if self.is_done() {
+                return Ok(());
+            }
+
+            let deadline = self
+                .in_flight
+                .front()
+                .map(|sent| sent.sent_at + self.timeout);
+            let timeout = async {
+                match deadline {
+                    Some(deadline) => tokio::time::sleep_until(deadline.into()).await,
+                    None => std::future::pending().await,
+                }
+            };
+            tokio::select! {
+                packet = packets.recv() => match packet {
+                    Some(packet) => self.on_packet(packet).await?,
+                    None => return Err(io::Error::new(ErrorKind::NotConnected, "The uTP socket was closed")),
+                },
+                _ = stream.notify.notified() => {}
+                _ = timeout => self.on_timeout().await?,
+            }
+        }
+    }
+
+    // Returns `true` once both ends closed the connection, or we did and nobody reads from it
+    // anymore.
+    fn is_done(&self) -> bool {
+        let state = self.stream.lock();
+        if !self.connected {
+            return state.dropped;
+        }
+        self.fin_sent && self.in_flight.is_empty() && (self.fin_received || state.dropped)
+    }
+
+    async fn on_packet(&mut self, packet: Packet) -> io::Result<()> {
+        if packet.kind == PacketType::Reset {
+            return Err(ErrorKind::ConnectionReset.into());
+        }
+        if packet.timestamp != 0 {
+            self.reply_delay = timestamp().wrapping_sub(packet.timestamp);
+        }
+        self.remote_window = packet.window as usize;
+
+        match packet.kind {
+            // The answer to the `syn` was lost.
+            PacketType::Syn if self.on_connect.is_none() => return self.send_state().await,
+            PacketType::Syn => return Ok(()),
+            _ if !self.connected => {
+                if packet.kind != PacketType::State || packet.ack_nr != 1 {
+                    return Ok(());
+                }
+                // The `state` answering the `syn` does not take a sequence number.
+                self.connected = true;
+                self.ack_nr = packet.seq_nr.wrapping_sub(1);
+                if let Some(on_connect) = self.on_connect.take() {
+                    let _ = on_connect.send(Ok(()));
+                }
+            }
+            _ => {}
+        }
+
+        self.on_ack(&packet).await?;
+        if matches!(packet.kind, PacketType::Data | PacketType::Fin) {
+            self.on_data(packet);
+            self.send_state().await?;
+        }
+        Ok(())
+    }
+
+    async fn on_ack(&mut self, packet: &Packet) -> io::Result<()> {
+        let now = Instant::now();
+        let mut acked = None;
+        while let Some(sent) = self.in_flight.front() {
+            if after(sent.seq_nr, packet.ack_nr) {
+                break;
+            }
+            let sent = self.in_flight.pop_front().expect("Checked above");
+            *acked.get_or_insert(0) += sent.payload.len();
+            if sent.transmissions == 1 {
+                self.update_rtt(now.duration_since(sent.sent_at));
+            }
+        }
+
+        if let Some(bytes) = acked {
+            self.duplicate_acks = 0;
+            self.ledbat.on_ack(bytes, packet.timestamp_difference, now);
+            return Ok(());
+        }
+        // The packets after a lost one are acked with the sequence number before it. It is only
+        // sent again once that way, the timeout takes care of the rest.
+        let first = self.in_flight.front().map(|sent| sent.transmissions);
+        if packet.kind == PacketType::State && first == Some(1) {
+            self.duplicate_acks += 1;
+            if self.duplicate_acks == DUPLICATE_ACKS {
+                self.duplicate_acks = 0;
+                self.ledbat.on_loss();
+                self.retransmit().await?;
+            }
+        }
+        Ok(())
+    }
+
+    fn on_data(&mut self, packet: Packet) {
+        let expected = self.ack_nr.wrapping_add(1);
+        if packet.seq_nr != expected {
+            if after(packet.seq_nr, expected)
+                && packet.seq_nr.wrapping_sub(expected) < MAX_REORDERED
+            {
+                self.reordered.insert(packet.seq_nr, packet);
+            }
+            return;
+        }
+
+        let mut next = Some(packet);
+        while let Some(packet) = next {
+            if !self.deliver(packet) {
+                return;
+            }
+            next = self.reordered.remove(&self.ack_nr.wrapping_add(1));
+        }
+    }
+
+    // Hands the next packet in order to the stream. Returns `false` if it was dropped because
+    // the stream is not read.
+    fn deliver(&mut self, packet: Packet) -> bool {
+        if self.fin_received {
+            return true;
+        }
+        let mut state = self.stream.lock();
+        match packet.kind {
+            PacketType::Fin => {
+                self.fin_received = true;
+                state.eof = true;
+            }
+            _ if state.received.len() + packet.payload.len() > RECEIVE_BUFFER => return false,
+            _ => state.received.extend(&packet.payload),
+        }
+        self.ack_nr = packet.seq_nr;
+        state.wake();
+        true
+    }
+
+    async fn on_timeout(&mut self) -> io::Result<()> {
+        let Some(sent) = self.in_flight.front() else {
+            return Ok(());
+        };
+        if sent.transmissions > MAX_RETRANSMISSIONS {
+            return Err(io::Error::new(
+                ErrorKind::TimedOut,
+                "The uTP connection timed out",
+            ));
+        }
+        self.ledbat.on_timeout();
+        self.timeout = (self.timeout * 2).min(MAX_TIMEOUT);
+        self.retransmit().await
+    }
+
+    // Updates the round trip time and the timeout as the protocol specifies.
+    fn update_rtt(&mut self, sample: Duration) {
+        let (rtt, variation) = match self.rtt {
+            None => (sample, sample / 2),
+            Some((rtt, variation)) => {
+                let delta = rtt.abs_diff(sample);
+                let variation = if delta > variation {
+                    variation + (delta - variation) / 4
+                } else {
+                    variation - (variation - delta) / 4
+                };
+                let rtt = if sample > rtt {
+                    rtt + (sample - rtt) / 8
+                } else {
+                    rtt - (rtt - sample) / 8
+                };
+                (rtt, variation)
+            }
+        };
+        self.rtt = Some((rtt, variation));
+        self.timeout = (rtt + 4 * variation).clamp(MIN_TIMEOUT, MAX_TIMEOUT);
+    }
+
+    // Sends the data written to the stream as the windows allow, then the `fin` once the stream
+    // is shut down.
+    async fn send_data(&mut self) -> io::Result<()> {
+        if !self.connected || self.fin_sent {
+            return Ok(());
+        }
+        loop {
+            let in_flight: usize = self.in_flight.iter().map(|sent| sent.payload.len()).sum();
+            let window = self.ledbat.window().min(self.remote_window);
+
+            let payload = {
+                let mut state = self.stream.lock();
+                if state.unsent.is_empty() {
+                    if !state.closing {
+                        return Ok(());
+                    }
+                    None
+                } else {
+                    let length = state.unsent.len().min(MAX_PAYLOAD);
+                    // A packet is always allowed in flight, so that a closed window is probed.
+                    if in_flight > 0 && in_flight + length > window {
+                        return Ok(());
+                    }
+                    let payload = state.unsent.drain(..length).collect();
+                    state.wake();
+                    Some(payload)
+                }
+            };
+
+            match payload {
+                Some(payload) => self.send_new(PacketType::Data, payload).await?,
+                None => {
+                    self.fin_sent = true;
+                    return self.send_new(PacketType::Fin, Vec::new()).await;
+                }
+            }
+        }
+    }
+
+    async fn send_new(&mut self, kind: PacketType, payload: Vec<u8>) -> io::Result<()> {
+        let seq_nr = self.seq_nr;
+        self.seq_nr = self.seq_nr.wrapping_add(1);
+        self.send(kind, seq_nr, payload.clone()).await?;
+        self.in_flight.push_back(Sent {
+            kind,
+            seq_nr,
+            payload,
+            sent_at: Instant::now(),
+            transmissions: 1,
+        });
+        Ok(())
+    }
+
+    // Sends the oldest packet in flight again.
+    async fn retransmit(&mut self) -> io::Result<()> {
+        let Some(sent) = self.in_flight.front_mut() else {
+            return Ok(());
+        };
+        sent.sent_at = Instant::now();
+        sent.transmissions += 1;
+        let (kind, seq_nr, payload) = (sent.kind, sent.seq_nr, sent.payload.clone());
+        self.send(kind, seq_nr, payload).await
+    }
+
+    // Acks the packets received so far. The `state` packets do not take a sequence number.
+    async fn send_state(&mut self) -> io::Result<()> {
+        self.send(PacketType::State, self.seq_nr, Vec::new()).await
+    }
+
+    async fn send(&self, kind: PacketType, seq_nr: u16, payload: Vec<u8>) -> io::Result<()> {
+        let window = RECEIVE_BUFFER.saturating_sub(self.stream.lock().received.len());
+        let packet = Packet {
+            kind,
+            connection_id: match kind {
+                PacketType::Syn => self.recv_id,
+                _ => self.send_id,
+            },
+            timestamp: timestamp(),
+            timestamp_difference: self.reply_delay,
+            window: window as u32,
+            seq_nr,
+            ack_nr: self.ack_nr,
+            payload,
+        };
+        self.shared
+            .socket
+            .send_to(&packet.encode(), self.remote)
+            .await?;
+        Ok(())
+    }
+}
+
+/// The state shared by a [`UtpStream`] and its connection.
+#[derive(Debug, Default)]
+struct StreamShared {
+    state: Mutex<StreamState>,
+
+    /// Wakes the connection up after the stream was written to, read from or shut down.
+    notify: Notify,
+}
+
+impl StreamShared {
+    fn lock(&self) -> std::sync::MutexGuard<'_, StreamState> {
+        self.state.lock().expect("uTP stream lock poisoned")
+    }
+}
+
+#[derive(Debug, Default)]
+struct StreamState {
+    received: VecDeque<u8>,
+    unsent: VecDeque<u8>,
+
+    /// The remote closed the connection once `received` is read.
+    eof: bool,
+    error: Option<ErrorKind>,
+
+    /// The stream was shut down, the connection sends the `fin` after the data.
+    closing: bool,
+    dropped: bool,
+
+    /// The connection stopped.
+    finished: bool,
+
+    reader: Option<Waker>,
+    writer: Option<Waker>,
+}
+
+impl StreamState {
+    fn wake(&mut self) {
+        if let Some(reader) = self.reader.take() {
+            reader.wake();
+        }
+        if let Some(writer) = self.writer.take() {
+            writer.wake();
+        }
+    }
+
+    fn error(&self) -> Option<io::Error> {
+        match (self.error, self.finished) {
+            (Some(kind), _) => Some(kind.into()),
+            (None, true) => Some(ErrorKind::NotConnected.into()),
+            (None, false) => None,
+        }
+    }
+}
+
+/// A uTP connection, opened with [`UtpSocket::connect`] or [`UtpSocket::accept`].
+///
+/// Shutting the stream down sends the data written so far and then closes the connection. The
+/// connection is closed as well once the stream is dropped.
+#[derive(Debug)]
+pub struct UtpStream {
+    shared: Arc<StreamShared>,
+    remote: SocketAddr,
+}
+
+impl UtpStream {
+    /// The address of the remote end of the connection.
+    pub fn peer_addr(&self) -> SocketAddr {
+        self.remote
+    }
+}
+
+impl Drop for UtpStream {
+    fn drop(&mut self) {
+        let mut state = self.shared.lock();
+        state.closing = true;
+        state.dropped = true;
+        self.shared.notify.notify_one();
+    }
+}
+
+impl AsyncRead for UtpStream {
+    fn poll_read(
+        self: Pin<&mut Self>,
+        cx: &mut TaskContext<'_>,
+        buf: &mut ReadBuf<'_>,
+    ) -> Poll<io::Result<()>> {
+        let mut state = self.shared.lock();
+        if !state.received.is_empty() {
+            let length = state.received.len().min(buf.remaining());
+            let (front, back) = state.received.as_slices();
+            let from_front = length.min(front.len());
+            buf.put_slice(&front[..from_front]);
+            buf.put_slice(&back[..length - from_front]);
+            state.received.drain(..length);
+            // The window of the remote opened.
+            self.shared.notify.notify_one();
+            return Poll::Ready(Ok(()));
+        }
+        if state.eof {
+            return Poll::Ready(Ok(()));
+        }
+        if let Some(e) = state.error() {
+            return Poll::Ready(Err(e));
+        }
+        state.reader = Some(cx.waker().clone());
+        Poll::Pending
+    }
+}
+
+impl AsyncWrite for UtpStream {
+    fn poll_write(
+        self: Pin<&mut Self>,
+        cx: &mut TaskContext<'_>,
+        buf: &[u8],
+    ) -> Poll<io::Result<usize>> {
+        let mut state = self.shared.lock();
+        if let Some(e) = state.error() {
+            return Poll::Ready(Err(e));
+        }
+        if state.closing {
+            return Poll::Ready(Err(ErrorKind::BrokenPipe.into()));
+        }
+        let free = SEND_BUFFER - state.unsent.len();
+        if free == 0 {
+            state.writer = Some(cx.waker().clone());
+            return Poll::Pending;
+        }
+        let length = buf.len().min(free);
+        state.unsent.extend(&buf[..length]);
+        self.shared.notify.notify_one();
+        Poll::Ready(Ok(length))
+    }
+
+    /// Waits for the data written to be sent, not for it to be acked.
+    fn poll_flush(self: Pin<&mut Self>, cx: &mut TaskContext<'_>) -> Poll<io::Result<()>> {
+        let mut state = self.shared.lock();
+        if state.unsent.is_empty() {
+            return Poll::Ready(Ok(()));
+        }
+        if let Some(e) = state.error() {
+            return Poll::Ready(Err(e));
+        }
+        state.writer = Some(cx.waker().clone());
+        Poll::Pending
+    }
+
+    fn poll_shutdown(self: Pin<&mut Self>, _: &mut TaskContext<'_>) -> Poll<io::Result<()>> {
+        self.shared.lock().closing = true;
+        self.shared.notify.notify_one();
+        Poll::Ready(Ok(()))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::net::Ipv4Addr;
+    use tokio::io::{AsyncReadExt, AsyncWriteExt};
+
+    #[test]
+    fn test_packet() {
+        let packet = Packet {
+            kind: PacketType::Data,
+            connection_id: 0x1234,
+            timestamp: 7,
+            timestamp_difference: 8,
+            window: 1 << 20,
+            seq_nr: 1,
+            ack_nr: 0xFFFF,
+            payload: b"data".to_vec(),
+        };
+        let bytes = packet.encode();
+        assert_eq!(&bytes[..4], &[0x01, 0, 0x12, 0x34]);
+        assert_eq!(bytes.len(), HEADER_LENGTH + 4);
+        assert_eq!(Packet::decode(&bytes).unwrap(), packet);
+
+        // A selective ack extension of 4 bytes is skipped.
+        let mut extended = bytes[..HEADER_LENGTH].to_vec();
+        extended[1] = 1;
+        extended.extend_from_slice(&[0, 4, 0xFF, 0, 0, 0]);
+        extended.extend_from_slice(b"data");
+        assert_eq!(Packet::decode(&extended).unwrap(), packet);
+
+        assert!(Packet::decode(&bytes[..10]).is_err());
+        assert!(Packet::decode(&extended[..HEADER_LENGTH + 3]).is_err());
+        let mut unknown = bytes.clone();
+        unknown[0] = 0x51;
+        assert!(Packet::decode(&unknown).is_err());
+    }
+
+    #[test]
+    fn test_after() {
+        assert!(after(2, 1));
+        assert!(!after(1, 2));
+        assert!(!after(1, 1));
+        assert!(after(0, 0xFFFF));
+        assert!(!after(0xFFFF, 0));
+    }
+
+    #[test]
+    fn test_ledbat() {
+        let now = Instant::now();
+        let mut ledbat = Ledbat::new();
+
+        // The base delay is the lowest one, whatever the offset between the clocks.
+        let base = u32::MAX - 1000;
+        ledbat.on_ack(MAX_PAYLOAD, base, now);
+        let window = ledbat.window();
+        assert!(window > INITIAL_WINDOW as usize);
+
+        // Growing while the packets are not queued.
+        ledbat.on_ack(MAX_PAYLOAD, base.wrapping_add(10_000), now);
+        assert!(ledbat.window() > window);
+
+        // Shrinking once they are delayed past the target.
+        let window = ledbat.window();
+        ledbat.on_ack(MAX_PAYLOAD, base.wrapping_add(300_000), now);
+        assert!(ledbat.window() < window);
+
+        ledbat.on_loss();
+        assert!(ledbat.window() < window / 2 + 1);
+        ledbat.on_timeout();
+        assert_eq!(ledbat.window(), MAX_PAYLOAD);
+
+        // The delays older than two periods are forgotten.
+        let mut ledbat = Ledbat::new();
+        ledbat.base_delay(10, now);
+        ledbat.base_delay(50, now + BASE_DELAY_PERIOD);
+        assert_eq!(ledbat.base_delay(60, now + 2 * BASE_DELAY_PERIOD), 50);
+    }
+
+    async fn pair() -> (UtpSocket, UtpSocket, UtpStream, UtpStream) {
+        let a = UtpSocket::bind((Ipv4Addr::LOCALHOST, 0)).await.unwrap();
+        let b = UtpSocket::bind((Ipv4Addr::LOCALHOST, 0)).await.unwrap();
+        let (client, server) = tokio::join!(a.connect(b.local_addr().unwrap()), b.accept());
+        let (client, server) = (client.unwrap(), server.unwrap());
+        assert_eq!(server.peer_addr(), a.local_addr().unwrap());
+        (a, b, client, server)
+    }
+
+    #[tokio::test]
+    async fn test_transfer() {
+        let (_a, _b, mut client, mut server) = pair().await;
+        let data: Vec<u8> = (0..1 << 20).map(|i| (i % 251) as u8).collect();
+
+        let sent = data.clone();
+        let upload = tokio::spawn(async move {
+            client.write_all(&sent).await.unwrap();
+            client.shutdown().await.unwrap();
+            let mut answer = Vec::new();
+            client.read_to_end(&mut answer).await.unwrap();
+            answer
+        });
+
+        let mut received = Vec::new();
+        server.read_to_end(&mut received).await.unwrap();
+        assert!(received == data);
+        server.write_all(b"thanks").await.unwrap();
+        server.shutdown().await.unwrap();
+
+        assert_eq!(upload.await.unwrap(), b"thanks");
+    }
+
+    #[tokio::test]
+    async fn test_lost_packets() {
+        let a = UtpSocket::bind((Ipv4Addr::LOCALHOST, 0)).await.unwrap();
+        let b = UtpSocket::bind((Ipv4Addr::LOCALHOST, 0)).await.unwrap();
+        let (a_addr, b_addr) = (a.local_addr().unwrap(), b.local_addr().unwrap());
+
+        // Relays the datagrams between the sockets, dropping the third of every 20 datagrams of
+        // each direction. The first ones go through, so that the connection is established
+        // without waiting for a timeout.
+        let proxy = UdpSocket::bind((Ipv4Addr::LOCALHOST, 0)).await.unwrap();
+        let proxy_addr = proxy.local_addr().unwrap();
+        let relay = tokio::spawn(async move {
+            let mut buf = vec![0; MAX_DATAGRAM];
+            let mut count = HashMap::new();
+            loop {
+                let (len, from) = proxy.recv_from(&mut buf).await.unwrap();
+                let to = if from == a_addr { b_addr } else { a_addr };
+                let count = count.entry(from).or_insert(0);
+                *count += 1;
+                if *count % 20 != 3 {
+                    proxy.send_to(&buf[..len], to).await.unwrap();
+                }
+            }
+        });
+
+        // The packets of the proxy come from its address, so `b` answers to it.
+        let (client, server) = tokio::join!(a.connect(proxy_addr), b.accept());
+        let (mut client, mut server) = (client.unwrap(), server.unwrap());
+
+        let data: Vec<u8> = (0..100_000).map(|i| (i % 253) as u8).collect();
+        let sent = data.clone();
+        tokio::spawn(async move {
+            client.write_all(&sent).await.unwrap();
+            client.shutdown().await.unwrap();
+            let _ = client.read(&mut [0]).await;
+        });
+
+        let mut received = Vec::new();
+        tokio::time::timeout(Duration::from_secs(30), server.read_to_end(&mut received))
+            .await
+            .unwrap()
+            .unwrap();
+        assert!(received == data);
+        relay.abort();
+    }
+
+    #[tokio::test]
+    async fn test_connect_timeout() {
+        let a = UtpSocket::bind((Ipv4Addr::LOCALHOST, 0)).await.unwrap();
+        // Nobody answers on this socket.
+        let silent = UdpSocket::bind((Ipv4Addr::LOCALHOST, 0)).await.unwrap();
+        let connect = a.connect(silent.local_addr().unwrap());
+        assert!(tokio::time::timeout(Duration::from_millis(200), connect)
+            .await
+            .is_err());
+
+        // The connection stops once the connect is given up.
+        tokio::time::sleep(Duration::from_millis(50)).await;
+        assert!(a.shared.connections.lock().unwrap().is_empty());
+    }
+
+    #[tokio::test]
+    async fn test_socket_dropped() {
+        let (a, _b, mut client, _server) = pair().await;
+        drop(a);
+        assert!(client.read(&mut [0]).await.is_err());
+    }
+}
@@ -0,0 +1,161 @@
+use anyhow::{bail, Result};
+
+/// The set of pieces a peer has, as exchanged in the `bitfield` message of the peer wire
+/// protocol. Also used to track which pieces of a torrent have been downloaded and verified.
+///
+/// The first byte corresponds to the pieces 0-7 from the high bit to the low bit, the next one to
+/// the pieces 8-15 and so on. The spare bits at the end are always cleared.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Bitfield {
+    bytes: Vec<u8>,
+    len: usize,
+}
+
+impl Bitfield {
+    /// An empty bitfield (no pieces) for a torrent with `len` pieces.
+    pub fn new(len: usize) -> Self {
+        Self {
+            bytes: vec![0; len.div_ceil(8)],
+            len,
+        }
+    }
+
+    /// A bitfield with all the `len` pieces set.
+    pub fn full(len: usize) -> Self {
+        let mut bitfield = Self {
+            bytes: vec![0xFF; len.div_ceil(8)],
+            len,
+        };
+        bitfield.clear_spare_bits();
+        bitfield
+    }
+
+    /// Parses the payload of a `bitfield` message for a torrent with `len` pieces.
+    ///
+    /// Fails if the payload has the wrong length or any of the spare bits are set, both of which
+    /// the spec requires peers to be disconnected for.
+    pub fn from_bytes(bytes: &[u8], len: usize) -> Result<Self> {
+        if bytes.len() != len.div_ceil(8) {
+            bail!(
+                "Invalid bitfield - Expected {} bytes for {len} pieces, got {}",
+                len.div_ceil(8),
+                bytes.len()
+            );
+        }
+        let bitfield = Self {
+            bytes: bytes.to_vec(),
+            len,
+        };
+        if bitfield.has_spare_bits() {
+            bail!("Invalid bitfield - The spare bits are set");
+        }
+        Ok(bitfield)
+    }
+
+    /// The payload of the `bitfield` message.
+    pub fn as_bytes(&self) -> &[u8] {
+        &self.bytes
+    }
+
+    /// Number of pieces of the torrent.
+    pub fn len(&self) -> usize {
+        self.len
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.len == 0
+    }
+
+    /// Returns `true` if the piece at `index` is set. Out of bounds indices are never set.
+    pub fn has(&self, index: usize) -> bool {
+        index < self.len && self.bytes[index / 8] & (0x80 >> (index % 8)) != 0
+    }
+
+    /// Sets the piece at `index`. Returns `false` if the index is out of bounds.
+    pub fn set(&mut self, index: usize) -> bool {
+        if index >= self.len {
+            return false;
+        }
+        self.bytes[index / 8] |= 0x80 >> (index % 8);
+        true
+    }
+
+    /// Clears the piece at `index`, e.g. when it fails verification.
+    pub fn unset(&mut self, index: usize) {
+        if index < self.len {
+            self.bytes[index / 8] &= !(0x80 >> (index % 8));
+        }
+    }
+
+    /// Number of pieces which are set.
+    pub fn count(&self) -> usize {
+        self.bytes.iter().map(|b| b.count_ones() as usize).sum()
+    }
+
+    /// Returns `true` if all the pieces are set.
+    pub fn is_complete(&self) -> bool {
+        self.count() == self.len
+    }
+
+    /// Indices of the pieces which are set, in ascending order.
+    pub fn pieces(&self) -> impl Iterator<Item = usize> + '_ {
+        (0..self.len).filter(|index| self.has(*index))
+    }
+
+    fn has_spare_bits(&self) -> bool {
+        let spare = self.bytes.len() * 8 - self.len;
+        spare > 0
+            && self
+                .bytes
+                .last()
+                .is_some_and(|b| b & ((1 << spare) - 1) != 0)
+    }
+
+    fn clear_spare_bits(&mut self) {
+        let spare = self.bytes.len() * 8 - self.len;
+        if let Some(last) = self.bytes.last_mut() {
+            *last &= !((1_u16 << spare) - 1) as u8;
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_set_and_has() {
+        let mut bitfield = Bitfield::new(10);
+        assert_eq!(bitfield.as_bytes(), &[0, 0]);
+
+        assert!(bitfield.set(0));
+        assert!(bitfield.set(9));
+        assert!(!bitfield.set(10));
+        assert_eq!(bitfield.as_bytes(), &[0b1000_0000, 0b0100_0000]);
+        assert!(bitfield.has(0) && bitfield.has(9));
+        assert!(!bitfield.has(1) && !bitfield.has(10));
+        assert_eq!(bitfield.pieces().collect::<Vec<_>>(), [0, 9]);
+
+        bitfield.unset(0);
+        assert_eq!(bitfield.count(), 1);
+        assert!(!bitfield.is_complete());
+    }
+
+    #[test]
+    fn test_full() {
+        let bitfield = Bitfield::full(10);
+        assert_eq!(bitfield.as_bytes(), &[0xFF, 0b1100_0000]);
+        assert!(bitfield.is_complete());
+        assert_eq!(Bitfield::full(8).as_bytes(), &[0xFF]);
+        assert!(Bitfield::full(0).is_complete());
+    }
+
+    #[test]
+    fn test_from_bytes() {
+        let bitfield = Bitfield::from_bytes(&[0xFF, 0b1100_0000], 10).unwrap();
+        assert_eq!(bitfield, Bitfield::full(10));
+
+        assert!(Bitfield::from_bytes(&[0xFF], 10).is_err());
+        assert!(Bitfield::from_bytes(&[0xFF, 0b1110_0000], 10).is_err());
+    }
+}
@@ -0,0 +1,120 @@
+/// Client codes of the ["Azureus-style"](https://wiki.theory.org/BitTorrentSpecification#peer_id)
+/// peer ids (`-XX1234-...`) of the most common clients.
+const AZUREUS_CLIENTS: &[(&[u8; 2], &str)] = &[
+    (b"AZ", "Vuze"),
+    (b"BC", "BitComet"),
+    (b"BI", "BiglyBT"),
+    (b"BT", "BitTorrent"),
+    (b"DE", "Deluge"),
+    (b"FD", "Free Download Manager"),
+    (b"KT", "KTorrent"),
+    (b"LT", "libtorrent"),
+    (b"lt", "libTorrent (rakshasa)"),
+    (b"qB", "qBittorrent"),
+    (b"RT", "rTorrent"),
+    (b"SD", "Thunder"),
+    (b"TR", "Transmission"),
+    (b"UT", "µTorrent"),
+    (b"UW", "µTorrent Web"),
+    (b"WW", "WebTorrent"),
+    (b"XL", "Xunlei"),
+    (b"ZG", "zung"),
+];
+
+/// Guesses the client software of a peer from its peer id, along with its version when it is
+/// encoded in the peer id. Returns `None` if the peer id follows no known convention.
+///
+/// ```
+/// use zung_torrent::peers::guess_client;
+///
+/// assert_eq!(guess_client(b"-qB4630-abcdefghijkl").as_deref(), Some("qBittorrent 4.6.3"));
+/// assert_eq!(guess_client(b"M7-4-0--abcdefghijkl").as_deref(), Some("Mainline 7.4.0"));
+/// ```
+pub fn guess_client(peer_id: &[u8; 20]) -> Option<String> {
+    // Azureus-style: -XX1234-
+    if peer_id[0] == b'-' && peer_id[7] == b'-' {
+        let code: &[u8; 2] = peer_id[1..3].try_into().expect("2 bytes");
+        let version = &peer_id[3..7];
+        if !code.iter().chain(version).all(u8::is_ascii_alphanumeric) {
+            return None;
+        }
+
+        let name = AZUREUS_CLIENTS
+            .iter()
+            .find(|(known, _)| *known == code)
+            .map(|(_, name)| name.to_string())
+            .unwrap_or_else(|| format!("Unknown ({})", String::from_utf8_lossy(code)));
+        return Some(format!("{name} {}", azureus_version(version)));
+    }
+
+    // Mainline: M1-2-3-- or M1-22-3-
+    if peer_id[0] == b'M' {
+        let end = peer_id[1..8].iter().rposition(|&b| b != b'-')? + 2;
+        let version = std::str::from_utf8(&peer_id[1..end]).ok()?;
+        let parts: Vec<_> = version.split('-').collect();
+        if parts.len() == 3 && parts.iter().all(|p| p.parse::<u8>().is_ok()) {
+            return Some(format!("Mainline {}", parts.join(".")));
+        }
+    }
+
+    None
+}
+
+/// Each character is a component of the version and the trailing zeros are dropped, keeping at
+/// least a major and a minor version: `4630` is `4.6.3` and `2000` is `2.0`.
+fn azureus_version(version: &[u8]) -> String {
+    let mut parts: Vec<_> = version
+        .iter()
+        .map(|&b| match b {
+            b'0'..=b'9' => (b - b'0').to_string(),
+            // Some clients use letters for the components above 9.
+            b'A'..=b'Z' => (b - b'A' + 10).to_string(),
+            b => (b as char).to_string(),
+        })
+        .collect();
+    while parts.len() > 2 && parts.last().is_some_and(|part| part == "0") {
+        parts.pop();
+    }
+    parts.join(".")
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_azureus_style() {
+        assert_eq!(
+            guess_client(b"-TR4050-abcdefghijkl").unwrap(),
+            "Transmission 4.0.5"
+        );
+        assert_eq!(
+            guess_client(b"-UT2000-abcdefghijkl").unwrap(),
+            "µTorrent 2.0"
+        );
+        assert_eq!(
+            guess_client(b"-LT1B20-abcdefghijkl").unwrap(),
+            "libtorrent 1.11.2"
+        );
+        assert_eq!(
+            guess_client(b"-XX1230-abcdefghijkl").unwrap(),
+            "Unknown (XX) 1.2.3"
+        );
+        assert!(guess_client(b"-X\x001230-abcdefghijkl").is_none());
+    }
+
+    #[test]
+    fn test_mainline_style() {
+        assert_eq!(
+            guess_client(b"M4-20-8-abcdefghijkl").unwrap(),
+            "Mainline 4.20.8"
+        );
+        assert!(guess_client(b"Mabc----abcdefghijkl").is_none());
+    }
+
+    #[test]
+    fn test_unknown() {
+        assert!(guess_client(&[0; 20]).is_none());
+        assert!(guess_client(b"abcdefghijklmnopqrst").is_none());
+    }
+}
@@ -0,0 +1,148 @@
+use anyhow::{bail, Result};
+use tokio::io::{AsyncRead, AsyncReadExt, AsyncWrite, AsyncWriteExt};
+
+/// The protocol string sent at the start of every handshake.
+const PROTOCOL: &[u8; 19] = b"BitTorrent protocol";
+
+/// The bit of the reserved bytes advertising the [Fast
+/// Extension](https://www.bittorrent.org/beps/bep_0006.html): byte 7, `0x04`.
+const FAST_EXTENSION: u8 = 0x04;
+
+/// Length of a handshake on the wire.
+pub const HANDSHAKE_LENGTH: usize = 1 + PROTOCOL.len() + 8 + 20 + 20;
+
+/// The first message exchanged with a peer: the protocol string, 8 reserved bytes advertising the
+/// supported extensions, the info hash of the torrent and the peer id of the sender.
+///
+/// Unlike the other messages it is not length prefixed, so it is exchanged on the raw stream
+/// before wrapping it in a [`PeerTransport`](super::PeerTransport).
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct Handshake {
+    pub reserved: [u8; 8],
+    pub info_hash: [u8; 20],
+    pub peer_id: [u8; 20],
+}
+
+impl Handshake {
+    /// Creates a handshake which advertises no extensions.
+    pub fn new(info_hash: [u8; 20], peer_id: [u8; 20]) -> Self {
+        Self {
+            reserved: [0; 8],
+            info_hash,
+            peer_id,
+        }
+    }
+
+    /// Advertises the Fast Extension, whose messages (see
+    /// [`Message::is_fast`](super::Message::is_fast)) can then be exchanged with the peers which
+    /// advertise it too.
+    pub fn with_fast_extension(mut self) -> Self {
+        self.reserved[7] |= FAST_EXTENSION;
+        self
+    }
+
+    /// Returns `true` if the handshake advertises the Fast Extension.
+    pub fn supports_fast_extension(&self) -> bool {
+        self.reserved[7] & FAST_EXTENSION != 0
+    }
+
+    pub fn to_bytes(&self) -> [u8; HANDSHAKE_LENGTH] {
+        let mut bytes = [0; HANDSHAKE_LENGTH];
+        bytes[0] = PROTOCOL.len() as u8;
+        bytes[1..20].copy_from_slice(PROTOCOL);
+        bytes[20..28].copy_from_slice(&self.reserved);
+        bytes[28..48].copy_from_slice(&self.info_hash);
+        bytes[48..68].copy_from_slice(&self.peer_id);
+        bytes
+    }
+
+    pub fn from_bytes(bytes: &[u8; HANDSHAKE_LENGTH]) -> Result<Self> {
+        if bytes[0] as usize != PROTOCOL.len() || &bytes[1..20] != PROTOCOL {
+            bail!("The peer does not speak the BitTorrent protocol")
+        }
+
+        Ok(Self {
+            reserved: bytes[20..28].try_into().expect("8 bytes"),
+            info_hash: bytes[28..48].try_into().expect("20 bytes"),
+            peer_id: bytes[48..68].try_into().expect("20 bytes"),
+        })
+    }
+
+    /// Sends this handshake on the stream and reads the handshake of the peer, which must be for
+    /// the same torrent.
+    pub async fn exchange<S>(&self, stream: &mut S) -> Result<Handshake>
+    where
+        S: AsyncRead + AsyncWrite + Unpin,
+    {
+        stream.write_all(&self.to_bytes()).await?;
+        stream.flush().await?;
+
+        let mut bytes = [0; HANDSHAKE_LENGTH];
+        stream.read_exact(&mut bytes).await?;
+        let theirs = Handshake::from_bytes(&bytes)?;
+
+        if theirs.info_hash != self.info_hash {
+            bail!("The peer answered with the info hash of another torrent")
+        }
+        Ok(theirs)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_handshake_roundtrip() {
+        let handshake = Handshake::new([1; 20], *b"-ZG0001-abcdefghijkl");
+        let bytes = handshake.to_bytes();
+        assert_eq!(&bytes[..20], b"\x13BitTorrent protocol");
+        assert_eq!(Handshake::from_bytes(&bytes).unwrap(), handshake);
+
+        let mut bytes = bytes;
+        bytes[1] = b'b';
+        assert!(Handshake::from_bytes(&bytes).is_err());
+    }
+
+    #[test]
+    fn test_fast_extension() {
+        let handshake = Handshake::new([1; 20], [2; 20]);
+        assert!(!handshake.supports_fast_extension());
+
+        let handshake = handshake.with_fast_extension();
+        assert!(handshake.supports_fast_extension());
+        assert_eq!(handshake.reserved, [0, 0, 0, 0, 0, 0, 0, 0x04]);
+        assert_eq!(handshake.to_bytes()[27], 0x04);
+    }
+
+    #[tokio::test]
+    async fn test_exchange() {
+        let (mut ours, mut theirs) = tokio::io::duplex(256);
+        let peer = tokio::spawn(async move {
+            let mut bytes = [0; HANDSHAKE_LENGTH];
+            theirs.read_exact(&mut bytes).await.unwrap();
+            let mut answer = Handshake::from_bytes(&bytes).unwrap();
+            answer.peer_id = *b"-qB4630-abcdefghijkl";
+            theirs.write_all(&answer.to_bytes()).await.unwrap();
+        });
+
+        let handshake = Handshake::new([7; 20], [0; 20]);
+        let answer = handshake.exchange(&mut ours).await.unwrap();
+        assert_eq!(&answer.peer_id, b"-qB4630-abcdefghijkl");
+        peer.await.unwrap();
+    }
+
+    #[tokio::test]
+    async fn test_exchange_rejects_other_torrents() {
+        let (mut ours, mut theirs) = tokio::io::duplex(256);
+        tokio::spawn(async move {
+            let answer = Handshake::new([9; 20], [0; 20]);
+            theirs.write_all(&answer.to_bytes()).await.unwrap();
+            let mut bytes = [0; HANDSHAKE_LENGTH];
+            let _ = theirs.read_exact(&mut bytes).await;
+        });
+
+        let handshake = Handshake::new([7; 20], [0; 20]);
+        assert!(handshake.exchange(&mut ours).await.is_err());
+    }
+}
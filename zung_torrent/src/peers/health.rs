@@ -0,0 +1,230 @@
+use std::time::{Duration, Instant};
+
+/// Default time after which a keep-alive is sent to a peer nothing else was sent to. Peers
+/// usually drop the connections silent for more than 2 minutes.
+pub const DEFAULT_KEEP_ALIVE_INTERVAL: Duration = Duration::from_secs(120);
+
+/// Default time without any block from a peer which has requests in flight after which it is
+/// snubbed.
+pub const DEFAULT_SNUB_TIMEOUT: Duration = Duration::from_secs(60);
+
+/// Default time without any message, not even a keep-alive, after which a peer is dropped.
+pub const DEFAULT_PEER_TIMEOUT: Duration = Duration::from_secs(180);
+
+/// Something to do about a peer connection, returned by [`PeerHealth::poll`].
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum HealthEvent {
+    /// Nothing was sent to the peer for the keep-alive interval: send a keep-alive (an empty
+    /// frame of the [`PeerTransport`](super::PeerTransport)).
+    KeepAlive,
+
+    /// The peer sent no block for the snub timeout although it has requests in flight. It should
+    /// be given fewer requests, see [`BlockScheduler::set_snubbed`](super::BlockScheduler).
+    Snubbed,
+
+    /// The peer sent nothing for the peer timeout: drop the connection.
+    TimedOut,
+}
+
+/// Keeps track of the liveness of a peer connection.
+///
+/// The connection reports what it sends and receives, and polls for the [`HealthEvent`]s once in
+/// a while, at the latest at [`next_check`](Self::next_check). Like the
+/// [`BlockScheduler`](super::BlockScheduler), it does no IO and takes the current time as an
+/// argument, so that the connection decides how to act on the events.
+///
+/// # Example
+///
+/// ```
+/// use std::time::{Duration, Instant};
+/// use zung_torrent::peers::{HealthEvent, PeerHealth};
+///
+/// let start = Instant::now();
+/// let mut health = PeerHealth::new(start);
+/// health.set_awaiting_blocks(true, start);
+///
+/// let later = start + Duration::from_secs(120);
+/// health.message_received(later);
+/// assert_eq!(health.poll(later), [HealthEvent::Snubbed, HealthEvent::KeepAlive]);
+/// assert!(health.is_snubbed());
+/// ```
+#[derive(Debug, Clone)]
+pub struct PeerHealth {
+    keep_alive_interval: Duration,
+    snub_timeout: Duration,
+    peer_timeout: Duration,
+
+    last_sent: Instant,
+    last_received: Instant,
+
+    /// Since when blocks are awaited from the peer, or the last block was received if later.
+    /// `None` when no request is in flight.
+    awaiting_since: Option<Instant>,
+    snubbed: bool,
+}
+
+impl PeerHealth {
+    /// Tracks a connection established at `now`.
+    pub fn new(now: Instant) -> Self {
+        Self {
+            keep_alive_interval: DEFAULT_KEEP_ALIVE_INTERVAL,
+            snub_timeout: DEFAULT_SNUB_TIMEOUT,
+            peer_timeout: DEFAULT_PEER_TIMEOUT,
+            last_sent: now,
+            last_received: now,
+            awaiting_since: None,
+            snubbed: false,
+        }
+    }
+
+    /// Sets the time after which a keep-alive is sent. Defaults to
+    /// [`DEFAULT_KEEP_ALIVE_INTERVAL`].
+    pub fn keep_alive_interval(mut self, interval: Duration) -> Self {
+        self.keep_alive_interval = interval;
+        self
+    }
+
+    /// Sets the time without blocks after which the peer is snubbed. Defaults to
+    /// [`DEFAULT_SNUB_TIMEOUT`].
+    pub fn snub_timeout(mut self, timeout: Duration) -> Self {
+        self.snub_timeout = timeout;
+        self
+    }
+
+    /// Sets the time without messages after which the peer is dropped. Defaults to
+    /// [`DEFAULT_PEER_TIMEOUT`].
+    pub fn peer_timeout(mut self, timeout: Duration) -> Self {
+        self.peer_timeout = timeout;
+        self
+    }
+
+    /// Records a message, keep-alives included, sent to the peer.
+    pub fn message_sent(&mut self, now: Instant) {
+        self.last_sent = now;
+    }
+
+    /// Records a message, keep-alives included, received from the peer.
+    pub fn message_received(&mut self, now: Instant) {
+        self.last_received = now;
+    }
+
+    /// Records a block received from the peer, which also counts as a message. A snubbed peer is
+    /// no longer snubbed.
+    pub fn block_received(&mut self, now: Instant) {
+        self.message_received(now);
+        self.snubbed = false;
+        if self.awaiting_since.is_some() {
+            self.awaiting_since = Some(now);
+        }
+    }
+
+    /// Records whether requests are in flight with the peer, e.g. after sending requests or once
+    /// the peer chokes the connection, which drops them. The snub timeout only runs while they
+    /// are.
+    pub fn set_awaiting_blocks(&mut self, awaiting: bool, now: Instant) {
+        match (awaiting, self.awaiting_since) {
+            (true, None) => self.awaiting_since = Some(now),
+            (false, _) => self.awaiting_since = None,
+            (true, Some(_)) => {}
+        }
+    }
+
+    /// Returns `true` if the peer was snubbed and sent no block since.
+    pub fn is_snubbed(&self) -> bool {
+        self.snubbed
+    }
+
+    /// Returns what is to be done about the connection at `now`. A timed out peer gets no other
+    /// event, and a peer is only reported as [`HealthEvent::Snubbed`] once until it sends a block.
+    pub fn poll(&mut self, now: Instant) -> Vec<HealthEvent> {
+        if now.duration_since(self.last_received) >= self.peer_timeout {
+            return vec![HealthEvent::TimedOut];
+        }
+
+        let mut events = Vec::new();
+        let starved = self
+            .awaiting_since
+            .is_some_and(|since| now.duration_since(since) >= self.snub_timeout);
+        if starved && !self.snubbed {
+            self.snubbed = true;
+            events.push(HealthEvent::Snubbed);
+        }
+        if now.duration_since(self.last_sent) >= self.keep_alive_interval {
+            events.push(HealthEvent::KeepAlive);
+        }
+        events
+    }
+
+    /// Returns when [`poll`](Self::poll) may next return an event, if nothing is sent or received
+    /// until then.
+    pub fn next_check(&self) -> Instant {
+        let mut next =
+            (self.last_sent + self.keep_alive_interval).min(self.last_received + self.peer_timeout);
+        if let (Some(since), false) = (self.awaiting_since, self.snubbed) {
+            next = next.min(since + self.snub_timeout);
+        }
+        next
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const SECOND: Duration = Duration::from_secs(1);
+
+    #[test]
+    fn test_keep_alive() {
+        let start = Instant::now();
+        let mut health = PeerHealth::new(start).keep_alive_interval(10 * SECOND);
+        assert_eq!(health.next_check(), start + 10 * SECOND);
+        assert!(health.poll(start + 9 * SECOND).is_empty());
+
+        let due = start + 10 * SECOND;
+        health.message_received(due);
+        assert_eq!(health.poll(due), [HealthEvent::KeepAlive]);
+        health.message_sent(due);
+        assert!(health.poll(due).is_empty());
+        assert_eq!(health.next_check(), due + 10 * SECOND);
+    }
+
+    #[test]
+    fn test_snubbed() {
+        let start = Instant::now();
+        let mut health = PeerHealth::new(start).snub_timeout(5 * SECOND);
+
+        // Not snubbed without requests in flight.
+        assert!(health.poll(start + 6 * SECOND).is_empty());
+
+        health.set_awaiting_blocks(true, start + 6 * SECOND);
+        assert_eq!(health.next_check(), start + 11 * SECOND);
+        health.block_received(start + 10 * SECOND);
+        assert!(health.poll(start + 14 * SECOND).is_empty());
+
+        assert_eq!(health.poll(start + 15 * SECOND), [HealthEvent::Snubbed]);
+        assert!(health.is_snubbed());
+        assert!(health.poll(start + 16 * SECOND).is_empty());
+
+        health.block_received(start + 17 * SECOND);
+        assert!(!health.is_snubbed());
+
+        // The timeout stops once the requests are dropped.
+        health.set_awaiting_blocks(false, start + 17 * SECOND);
+        assert!(health.poll(start + 30 * SECOND).is_empty());
+    }
+
+    #[test]
+    fn test_timed_out() {
+        let start = Instant::now();
+        let mut health = PeerHealth::new(start)
+            .keep_alive_interval(10 * SECOND)
+            .snub_timeout(30 * SECOND)
+            .peer_timeout(20 * SECOND);
+        health.set_awaiting_blocks(true, start);
+
+        health.message_received(start + 15 * SECOND);
+        assert_eq!(health.next_check(), start + 10 * SECOND);
+        assert_eq!(health.poll(start + 34 * SECOND).len(), 2);
+        assert_eq!(health.poll(start + 35 * SECOND), [HealthEvent::TimedOut]);
+    }
+}
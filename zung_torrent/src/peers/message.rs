@@ -0,0 +1,356 @@
+use std::net::Ipv4Addr;
+
+use anyhow::{bail, Result};
+use bytes::{Buf, Bytes};
+
+use super::{Bitfield, BlockRequest};
+
+/// Number of pieces of the allowed fast set given to each peer, as suggested by the [Fast
+/// Extension](https://www.bittorrent.org/beps/bep_0006.html).
+pub const DEFAULT_ALLOWED_FAST: usize = 10;
+
+/// A message of the [peer wire protocol](https://www.bittorrent.org/beps/bep_0003.html#peer-messages),
+/// including the messages of the [Fast Extension](https://www.bittorrent.org/beps/bep_0006.html).
+///
+/// Messages are converted from and to the frames of a [`PeerTransport`](super::PeerTransport):
+/// the message id followed by the payload, without the length prefix.
+///
+/// The messages of the Fast Extension (see [`is_fast`](Self::is_fast)) may only be exchanged
+/// once both [handshakes](super::Handshake::supports_fast_extension) advertised it. A peer
+/// sending them otherwise must be disconnected.
+///
+/// # Example
+///
+/// ```
+/// use zung_torrent::peers::{BlockRequest, Message};
+///
+/// let request = Message::Request(BlockRequest { piece: 1, offset: 0, length: 16384 });
+/// let frame = request.to_frame();
+/// assert_eq!(frame, [6, 0, 0, 0, 1, 0, 0, 0, 0, 0, 0, 64, 0]);
+/// assert_eq!(Message::from_frame(frame.into()).unwrap(), request);
+/// ```
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum Message {
+    /// The empty frame, sent to keep a connection open.
+    KeepAlive,
+    Choke,
+    Unchoke,
+    Interested,
+    NotInterested,
+
+    /// The sender has verified the piece.
+    Have(u32),
+
+    /// The pieces the sender has, sent right after the handshake. The payload is parsed with
+    /// [`bitfield`](Self::bitfield), which knows the number of pieces of the torrent.
+    Bitfield(Bytes),
+
+    Request(BlockRequest),
+    Piece {
+        piece: u32,
+        offset: u32,
+        data: Bytes,
+    },
+    Cancel(BlockRequest),
+
+    /// The port of the DHT node of the sender.
+    Port(u16),
+
+    /// Fast Extension: the sender would rather upload this piece, e.g. because it is in its
+    /// cache. Only a hint which may be ignored.
+    SuggestPiece(u32),
+
+    /// Fast Extension: the sender has every piece, instead of a full bitfield.
+    HaveAll,
+
+    /// Fast Extension: the sender has no piece, instead of an empty bitfield.
+    HaveNone,
+
+    /// Fast Extension: the request will not be answered. Peers send it for every request they
+    /// drop, including the ones dropped when choking, see
+    /// [`BlockScheduler::block_rejected`](super::BlockScheduler::block_rejected).
+    RejectRequest(BlockRequest),
+
+    /// Fast Extension: the piece may be requested even while the sender chokes the connection,
+    /// see [`allowed_fast_set`].
+    AllowedFast(u32),
+}
+
+impl Message {
+    /// Parses a frame received from a [`PeerTransport`](super::PeerTransport).
+    pub fn from_frame(mut frame: Bytes) -> Result<Self> {
+        if frame.is_empty() {
+            return Ok(Self::KeepAlive);
+        }
+
+        let id = frame.get_u8();
+        let expected = match id {
+            0..=3 | 14 | 15 => Some(0),
+            4 | 13 | 17 => Some(4),
+            6 | 8 | 16 => Some(12),
+            9 => Some(2),
+            5 | 7 => None,
+            _ => bail!("Unknown message id {id}"),
+        };
+        match expected {
+            Some(length) if frame.len() != length => {
+                bail!(
+                    "Invalid message {id} - Expected {length} bytes of payload, got {}",
+                    frame.len()
+                )
+            }
+            None if id == 7 && frame.len() < 8 => {
+                bail!("Invalid piece message - Expected at least 8 bytes of payload")
+            }
+            _ => {}
+        }
+
+        let block = |frame: &mut Bytes| BlockRequest {
+            piece: frame.get_u32(),
+            offset: frame.get_u32(),
+            length: frame.get_u32(),
+        };
+        Ok(match id {
+            0 => Self::Choke,
+            1 => Self::Unchoke,
+            2 => Self::Interested,
+            3 => Self::NotInterested,
+            4 => Self::Have(frame.get_u32()),
+            5 => Self::Bitfield(frame),
+            6 => Self::Request(block(&mut frame)),
+            7 => Self::Piece {
+                piece: frame.get_u32(),
+                offset: frame.get_u32(),
+                data: frame,
+            },
+            8 => Self::Cancel(block(&mut frame)),
+            9 => Self::Port(frame.get_u16()),
+            13 => Self::SuggestPiece(frame.get_u32()),
+            14 => Self::HaveAll,
+            15 => Self::HaveNone,
+            16 => Self::RejectRequest(block(&mut frame)),
+            17 => Self::AllowedFast(frame.get_u32()),
+            _ => unreachable!("The ids are checked above"),
+        })
+    }
+
+    /// The frame to send with a [`PeerTransport`](super::PeerTransport).
+    pub fn to_frame(&self) -> Vec<u8> {
+        let block = |id: u8, block: &BlockRequest| {
+            let mut frame = vec![id];
+            frame.extend_from_slice(&block.piece.to_be_bytes());
+            frame.extend_from_slice(&block.offset.to_be_bytes());
+            frame.extend_from_slice(&block.length.to_be_bytes());
+            frame
+        };
+        let piece = |id: u8, piece: u32| {
+            let mut frame = vec![id];
+            frame.extend_from_slice(&piece.to_be_bytes());
+            frame
+        };
+
+        match self {
+            Self::KeepAlive => Vec::new(),
+            Self::Choke => vec![0],
+            Self::Unchoke => vec![1],
+            Self::Interested => vec![2],
+            Self::NotInterested => vec![3],
+            Self::Have(index) => piece(4, *index),
+            Self::Bitfield(bytes) => [&[5], &bytes[..]].concat(),
+            Self::Request(request) => block(6, request),
+            Self::Piece {
+                piece: index,
+                offset,
+                data,
+            } => {
+                let mut frame = piece(7, *index);
+                frame.extend_from_slice(&offset.to_be_bytes());
+                frame.extend_from_slice(data);
+                frame
+            }
+            Self::Cancel(request) => block(8, request),
+            Self::Port(port) => [&[9], &port.to_be_bytes()[..]].concat(),
+            Self::SuggestPiece(index) => piece(13, *index),
+            Self::HaveAll => vec![14],
+            Self::HaveNone => vec![15],
+            Self::RejectRequest(request) => block(16, request),
+            Self::AllowedFast(index) => piece(17, *index),
+        }
+    }
+
+    /// The message announcing the pieces we have after the handshake. With the Fast Extension, a
+    /// complete or empty bitfield is sent as `have all` or `have none`, which spares the peers
+    /// from sending and parsing the whole bitfield.
+    pub fn pieces(have: &Bitfield, fast_extension: bool) -> Self {
+        match (fast_extension, have.count()) {
+            (true, 0) => Self::HaveNone,
+            (true, _) if have.is_complete() => Self::HaveAll,
+            _ => Self::Bitfield(Bytes::copy_from_slice(have.as_bytes())),
+        }
+    }
+
+    /// Returns the pieces announced by a `bitfield`, `have all` or `have none` message, for a
+    /// torrent with `len` pieces. `None` for the other messages.
+    pub fn bitfield(&self, len: usize) -> Result<Option<Bitfield>> {
+        Ok(match self {
+            Self::Bitfield(bytes) => Some(Bitfield::from_bytes(bytes, len)?),
+            Self::HaveAll => Some(Bitfield::full(len)),
+            Self::HaveNone => Some(Bitfield::new(len)),
+            _ => None,
+        })
+    }
+
+    /// Returns `true` for the messages of the Fast Extension.
+    pub fn is_fast(&self) -> bool {
+        matches!(
+            self,
+            Self::SuggestPiece(_)
+                | Self::HaveAll
+                | Self::HaveNone
+                | Self::RejectRequest(_)
+                | Self::AllowedFast(_)
+        )
+    }
+}
+
+/// Returns the `k` pieces a peer at `ip` may request while it is choked, to be sent in
+/// [`Message::AllowedFast`] messages. A new peer gets its first pieces from them instead of
+/// waiting to be unchoked.
+///
+/// The set is computed as the [Fast Extension](https://www.bittorrent.org/beps/bep_0006.html#allowed-fast)
+/// specifies, so that a peer reconnecting from another port of the same network gets the same
+/// pieces and can not collect more of them.
+pub fn allowed_fast_set(
+    ip: Ipv4Addr,
+    info_hash: &[u8; 20],
+    number_of_pieces: u32,
+    k: usize,
+) -> Vec<u32> {
+    let k = k.min(number_of_pieces as usize);
+    let mut pieces = Vec::with_capacity(k);
+
+    let mut x = (u32::from(ip) & 0xFFFF_FF00).to_be_bytes().to_vec();
+    x.extend_from_slice(info_hash);
+    while pieces.len() < k {
+        x = sha1_smol::Sha1::from(&x).digest().bytes().to_vec();
+        for y in x.chunks_exact(4) {
+            if pieces.len() == k {
+                break;
+            }
+            let piece = u32::from_be_bytes(y.try_into().expect("4 bytes")) % number_of_pieces;
+            if !pieces.contains(&piece) {
+                pieces.push(piece);
+            }
+        }
+    }
+    pieces
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_roundtrip() {
+        let block = BlockRequest {
+            piece: 1,
+            offset: 16384,
+            length: 16384,
+        };
+        let messages = [
+            Message::KeepAlive,
+            Message::Choke,
+            Message::Unchoke,
+            Message::Interested,
+            Message::NotInterested,
+            Message::Have(7),
+            Message::Bitfield(Bytes::from_static(&[0b1010_0000])),
+            Message::Request(block),
+            Message::Piece {
+                piece: 1,
+                offset: 16384,
+                data: Bytes::from_static(b"data"),
+            },
+            Message::Cancel(block),
+            Message::Port(6881),
+            Message::SuggestPiece(3),
+            Message::HaveAll,
+            Message::HaveNone,
+            Message::RejectRequest(block),
+            Message::AllowedFast(4),
+        ];
+        for message in messages {
+            let frame = Bytes::from(message.to_frame());
+            assert_eq!(Message::from_frame(frame).unwrap(), message);
+        }
+
+        assert_eq!(Message::HaveAll.to_frame(), [0x0E]);
+        assert_eq!(
+            Message::RejectRequest(block).to_frame(),
+            [0x10, 0, 0, 0, 1, 0, 0, 64, 0, 0, 0, 64, 0]
+        );
+        assert_eq!(Message::AllowedFast(4).to_frame(), [0x11, 0, 0, 0, 4]);
+    }
+
+    #[test]
+    fn test_invalid_frames() {
+        assert!(Message::from_frame(Bytes::from_static(&[0x12])).is_err());
+        assert!(Message::from_frame(Bytes::from_static(&[0x0E, 0])).is_err());
+        assert!(Message::from_frame(Bytes::from_static(&[0x11, 0, 0, 1])).is_err());
+        assert!(Message::from_frame(Bytes::from_static(&[7, 0, 0, 0, 1])).is_err());
+    }
+
+    #[test]
+    fn test_pieces() {
+        let mut have = Bitfield::new(10);
+        assert_eq!(Message::pieces(&have, true), Message::HaveNone);
+        assert_eq!(
+            Message::pieces(&have, false),
+            Message::Bitfield(Bytes::from_static(&[0, 0]))
+        );
+
+        have.set(3);
+        assert!(matches!(Message::pieces(&have, true), Message::Bitfield(_)));
+        assert_eq!(Message::pieces(&Bitfield::full(10), true), Message::HaveAll);
+
+        assert_eq!(
+            Message::HaveAll.bitfield(10).unwrap(),
+            Some(Bitfield::full(10))
+        );
+        assert_eq!(
+            Message::HaveNone.bitfield(10).unwrap(),
+            Some(Bitfield::new(10))
+        );
+        assert_eq!(
+            Message::pieces(&have, true).bitfield(10).unwrap(),
+            Some(have)
+        );
+        assert!(Message::Choke.bitfield(10).unwrap().is_none());
+        assert!(Message::Bitfield(Bytes::from_static(&[0]))
+            .bitfield(10)
+            .is_err());
+    }
+
+    #[test]
+    fn test_allowed_fast_set() {
+        // The example of the spec.
+        let ip = Ipv4Addr::new(80, 4, 4, 200);
+        let info_hash = [0xAA; 20];
+        assert_eq!(
+            allowed_fast_set(ip, &info_hash, 1313, 7),
+            [1059, 431, 808, 1217, 287, 376, 1188]
+        );
+        assert_eq!(
+            allowed_fast_set(ip, &info_hash, 1313, 9),
+            [1059, 431, 808, 1217, 287, 376, 1188, 353, 508]
+        );
+
+        // The same for the whole /24 network.
+        assert_eq!(
+            allowed_fast_set(Ipv4Addr::new(80, 4, 4, 1), &info_hash, 1313, 7),
+            allowed_fast_set(ip, &info_hash, 1313, 7)
+        );
+        assert_eq!(allowed_fast_set(ip, &info_hash, 3, 10).len(), 3);
+        assert!(allowed_fast_set(ip, &info_hash, 0, 10).is_empty());
+    }
+}
@@ -0,0 +1,32 @@
+//! For communicating with the peers of a torrent.
+//!
+//! Peer connections carry length-prefixed messages as described in the [peer wire
+//! protocol](https://www.bittorrent.org/beps/bep_0003.html#peer-messages), see [`Message`]. The
+//! protocol logic is written against the [`PeerTransport`] trait rather than a socket, so that
+//! alternative transports (such as uTP or encrypted streams) and in-memory transports for tests
+//! can be swapped in without touching it. [`TcpTransport`] is the default transport,
+//! [`EncryptedTransport`] encrypts the TCP connections and [`UtpTransport`] runs over uTP.
+
+mod bitfield;
+mod fingerprint;
+mod handshake;
+mod health;
+mod message;
+mod mse;
+mod scheduler;
+mod transport;
+
+pub use bitfield::Bitfield;
+pub use fingerprint::guess_client;
+pub use handshake::{Handshake, HANDSHAKE_LENGTH};
+pub use health::{
+    HealthEvent, PeerHealth, DEFAULT_KEEP_ALIVE_INTERVAL, DEFAULT_PEER_TIMEOUT,
+    DEFAULT_SNUB_TIMEOUT,
+};
+pub use message::{allowed_fast_set, Message, DEFAULT_ALLOWED_FAST};
+pub use mse::{EncryptedTransport, EncryptionPolicy, MseStream};
+pub use scheduler::{
+    BlockRequest, BlockScheduler, BLOCK_LENGTH, DEFAULT_ENDGAME_THRESHOLD, DEFAULT_PIPELINE_DEPTH,
+    DEFAULT_REQUEST_TIMEOUT,
+};
+pub use transport::{FramedTransport, PeerTransport, TcpTransport, UtpTransport, MAX_FRAME_LENGTH};
@@ -0,0 +1,629 @@
+use std::future::Future;
+use std::io;
+use std::pin::Pin;
+use std::task::{ready, Context as TaskContext, Poll};
+
+use anyhow::{bail, ensure, Context, Result};
+use num_bigint::BigUint;
+use rand::Rng;
+use tokio::io::{AsyncRead, AsyncReadExt, AsyncWrite, AsyncWriteExt, ReadBuf};
+use tokio::net::{TcpStream, ToSocketAddrs};
+
+use super::{FramedTransport, Handshake};
+
+/// The prime of the Diffie-Hellman key exchange, whose generator is 2.
+const PRIME: &[u8] = b"FFFFFFFFFFFFFFFFC90FDAA22168C234C4C6628B80DC1CD129024E088A67CC74020BBEA63B139B22514A08798E3404DDEF9519B3CD3A431B302B0A6DF25F14374FE1356D6D51C245E485B576625E7EC6F44C42E9A63A36210000000000090563";
+
+/// Length of the public keys on the wire.
+const KEY_LENGTH: usize = 96;
+
+/// The verification constant, encrypted to tell where the padding ends.
+const VC: [u8; 8] = [0; 8];
+
+/// Maximum length of the random padding after the public keys and in the encrypted headers.
+const MAX_PADDING: usize = 512;
+
+const CRYPTO_PLAINTEXT: u32 = 0x01;
+const CRYPTO_RC4: u32 = 0x02;
+
+/// Whether the connections to the peers are encrypted with the [Message Stream
+/// Encryption](https://wiki.vuze.com/w/Message_Stream_Encryption) (MSE, also known as Protocol
+/// Encryption), which keeps ISPs from throttling the BitTorrent traffic.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub enum EncryptionPolicy {
+    /// Only connect to the peers which encrypt the whole connection with RC4.
+    Require,
+
+    /// Encrypt the connections to the peers which support it and fall back to plaintext for the
+    /// others.
+    #[default]
+    Prefer,
+
+    /// Use the plain BitTorrent protocol.
+    Disable,
+}
+
+impl EncryptionPolicy {
+    // The crypto methods offered to the peers, as a bit field.
+    fn provided(self) -> u32 {
+        match self {
+            Self::Require => CRYPTO_RC4,
+            Self::Prefer => CRYPTO_RC4 | CRYPTO_PLAINTEXT,
+            Self::Disable => CRYPTO_PLAINTEXT,
+        }
+    }
+
+    // Selects one of the crypto methods offered by a peer.
+    fn select(self, provided: u32) -> Result<u32> {
+        let preferred = match self {
+            Self::Require => &[CRYPTO_RC4][..],
+            Self::Prefer => &[CRYPTO_RC4, CRYPTO_PLAINTEXT],
+            Self::Disable => &[CRYPTO_PLAINTEXT],
+        };
+        match preferred.iter().find(|&&method| provided & method != 0) {
+            Some(&method) => Ok(method),
+            None => bail!("The peer offers no acceptable encryption ({provided:#x})"),
+        }
+    }
+}
+
+/// A byte stream going through the handshake of the Message Stream Encryption, after which the
+/// data is encrypted with RC4 or left as is, as agreed with the peer.
+///
+/// The handshake only obfuscates the connection: the keys are exchanged with Diffie-Hellman and
+/// derived from the info hash of the torrent, so it does not authenticate the peer. The
+/// [`Handshake`] of the BitTorrent protocol is then exchanged on this stream as usual.
+#[derive(Debug)]
+pub struct MseStream<S> {
+    stream: S,
+    cipher: Option<Cipher>,
+
+    /// The payload the initiator sent along with the handshake, already decrypted, returned
+    /// before reading from the stream.
+    initial: Vec<u8>,
+
+    /// Encrypted bytes not written to the stream yet.
+    pending: Vec<u8>,
+}
+
+#[derive(Debug)]
+struct Cipher {
+    encrypt: Rc4,
+    decrypt: Rc4,
+}
+
+impl<S> MseStream<S>
+where
+    S: AsyncRead + AsyncWrite + Unpin + Send,
+{
+    /// Wraps a stream which goes through no encryption handshake, e.g. to fall back to the
+    /// plain protocol.
+    pub fn plain(stream: S) -> Self {
+        Self {
+            stream,
+            cipher: None,
+            initial: Vec::new(),
+            pending: Vec::new(),
+        }
+    }
+
+    /// Goes through the encryption handshake with the peer the stream connects to, for the
+    /// torrent of `info_hash`.
+    pub async fn connect(
+        mut stream: S,
+        info_hash: [u8; 20],
+        policy: EncryptionPolicy,
+    ) -> Result<Self> {
+        let keys = KeyPair::generate();
+        stream
+            .write_all(&[&keys.public[..], &padding()].concat())
+            .await?;
+        stream.flush().await?;
+
+        let mut theirs = [0; KEY_LENGTH];
+        stream.read_exact(&mut theirs).await?;
+        let secret = keys.secret(&theirs)?;
+
+        let mut encrypt = Rc4::discarded(&hash(&[b"keyA", &secret, &info_hash]));
+        let mut decrypt = Rc4::discarded(&hash(&[b"keyB", &secret, &info_hash]));
+
+        let pad_c = padding();
+        let mut header = VC.to_vec();
+        header.extend_from_slice(&policy.provided().to_be_bytes());
+        header.extend_from_slice(&(pad_c.len() as u16).to_be_bytes());
+        header.extend_from_slice(&pad_c);
+        // No initial payload: the BitTorrent handshake follows the encryption handshake.
+        header.extend_from_slice(&0_u16.to_be_bytes());
+        encrypt.apply(&mut header);
+
+        let mut message = hash(&[b"req1", &secret]).to_vec();
+        message.extend(xor(hash(&[b"req2", &info_hash]), hash(&[b"req3", &secret])));
+        message.extend_from_slice(&header);
+        stream.write_all(&message).await?;
+        stream.flush().await?;
+
+        // The answer starts after the padding of the peer, with the encrypted VC.
+        let mut vc = VC;
+        decrypt.apply(&mut vc);
+        synchronize(&mut stream, &vc)
+            .await
+            .context("The peer does not support the encryption")?;
+
+        let mut answer = [0; 6];
+        stream.read_exact(&mut answer).await?;
+        decrypt.apply(&mut answer);
+        let selected = u32::from_be_bytes(answer[..4].try_into().expect("4 bytes"));
+        if selected.count_ones() != 1 || policy.provided() & selected == 0 {
+            bail!("The peer selected an encryption which was not offered ({selected:#x})");
+        }
+        let mut pad_d = vec![0; padding_length(&answer[4..])?];
+        stream.read_exact(&mut pad_d).await?;
+        decrypt.apply(&mut pad_d);
+
+        Ok(Self {
+            stream,
+            cipher: (selected == CRYPTO_RC4).then_some(Cipher { encrypt, decrypt }),
+            initial: Vec::new(),
+            pending: Vec::new(),
+        })
+    }
+
+    /// Opens a connection with `connect` and goes through the encryption handshake according to
+    /// the policy. With [`EncryptionPolicy::Prefer`], the peers which fail the encryption
+    /// handshake, such as the ones which do not support it, are connected to again without
+    /// encryption.
+    pub async fn connect_with<F, Fut>(
+        connect: F,
+        info_hash: [u8; 20],
+        policy: EncryptionPolicy,
+    ) -> Result<Self>
+    where
+        F: Fn() -> Fut,
+        Fut: Future<Output = Result<S>>,
+    {
+        match policy {
+            EncryptionPolicy::Disable => Ok(Self::plain(connect().await?)),
+            EncryptionPolicy::Require => Self::connect(connect().await?, info_hash, policy).await,
+            EncryptionPolicy::Prefer => {
+                let encrypted = async { Self::connect(connect().await?, info_hash, policy).await };
+                match encrypted.await {
+                    Ok(stream) => Ok(stream),
+                    Err(_) => Ok(Self::plain(connect().await?)),
+                }
+            }
+        }
+    }
+
+    /// Answers the encryption handshake of a peer which connected to us, for one of the torrents
+    /// of `info_hashes`. Returns the stream along with the info hash of the torrent the peer
+    /// asked for.
+    pub async fn accept(
+        mut stream: S,
+        info_hashes: &[[u8; 20]],
+        policy: EncryptionPolicy,
+    ) -> Result<(Self, [u8; 20])> {
+        let mut theirs = [0; KEY_LENGTH];
+        stream.read_exact(&mut theirs).await?;
+        let keys = KeyPair::generate();
+        let secret = keys.secret(&theirs)?;
+        stream
+            .write_all(&[&keys.public[..], &padding()].concat())
+            .await?;
+        stream.flush().await?;
+
+        // The request starts after the padding of the peer.
+        synchronize(&mut stream, &hash(&[b"req1", &secret]))
+            .await
+            .context("The peer does not follow the encryption handshake")?;
+        let mut requested = [0; 20];
+        stream.read_exact(&mut requested).await?;
+        let requested = xor(requested, hash(&[b"req3", &secret]));
+        let info_hash = *info_hashes
+            .iter()
+            .find(|info_hash| hash(&[b"req2", *info_hash]) == requested)
+            .context("The peer asked for an unknown torrent")?;
+
+        let mut encrypt = Rc4::discarded(&hash(&[b"keyB", &secret, &info_hash]));
+        let mut decrypt = Rc4::discarded(&hash(&[b"keyA", &secret, &info_hash]));
+
+        let mut header = [0; 14];
+        stream.read_exact(&mut header).await?;
+        decrypt.apply(&mut header);
+        ensure!(header[..8] == VC, "Invalid verification constant");
+        let provided = u32::from_be_bytes(header[8..12].try_into().expect("4 bytes"));
+        let selected = policy.select(provided)?;
+
+        let mut pad_c = vec![0; padding_length(&header[12..])?];
+        stream.read_exact(&mut pad_c).await?;
+        decrypt.apply(&mut pad_c);
+
+        let mut length = [0; 2];
+        stream.read_exact(&mut length).await?;
+        decrypt.apply(&mut length);
+        let mut initial = vec![0; u16::from_be_bytes(length) as usize];
+        stream.read_exact(&mut initial).await?;
+        decrypt.apply(&mut initial);
+
+        let pad_d = padding();
+        let mut answer = VC.to_vec();
+        answer.extend_from_slice(&selected.to_be_bytes());
+        answer.extend_from_slice(&(pad_d.len() as u16).to_be_bytes());
+        answer.extend_from_slice(&pad_d);
+        encrypt.apply(&mut answer);
+        stream.write_all(&answer).await?;
+        stream.flush().await?;
+
+        let stream = Self {
+            stream,
+            cipher: (selected == CRYPTO_RC4).then_some(Cipher { encrypt, decrypt }),
+            initial,
+            pending: Vec::new(),
+        };
+        Ok((stream, info_hash))
+    }
+
+    /// Returns `true` if the data is encrypted, `false` if the peer and us agreed on plaintext.
+    pub fn is_encrypted(&self) -> bool {
+        self.cipher.is_some()
+    }
+
+    // Writes the encrypted bytes which are still pending.
+    fn poll_pending(&mut self, cx: &mut TaskContext<'_>) -> Poll<io::Result<()>> {
+        while !self.pending.is_empty() {
+            let written = ready!(Pin::new(&mut self.stream).poll_write(cx, &self.pending))?;
+            if written == 0 {
+                return Poll::Ready(Err(io::ErrorKind::WriteZero.into()));
+            }
+            self.pending.drain(..written);
+        }
+        Poll::Ready(Ok(()))
+    }
+}
+
+/// A [`PeerTransport`](super::PeerTransport) over a TCP connection which is encrypted as agreed
+/// with the peer, see [`EncryptionPolicy`].
+pub type EncryptedTransport = FramedTransport<MseStream<TcpStream>>;
+
+impl EncryptedTransport {
+    /// Connects to the peer, goes through the encryption handshake according to the policy (see
+    /// [`MseStream::connect_with`]) and exchanges the handshakes (see [`Handshake::exchange`]).
+    pub async fn connect_with_handshake<A: ToSocketAddrs + Clone>(
+        addr: A,
+        handshake: &Handshake,
+        policy: EncryptionPolicy,
+    ) -> Result<(Self, Handshake)> {
+        let connect = || async {
+            let stream = crate::net::connect::connect(addr.clone())
+                .await
+                .context("Failed to connect to the peer")?;
+            stream.set_nodelay(true)?;
+            anyhow::Ok(stream)
+        };
+
+        let mut stream = MseStream::connect_with(connect, handshake.info_hash, policy).await?;
+        let theirs = handshake.exchange(&mut stream).await?;
+        Ok((Self::new(stream), theirs))
+    }
+}
+
+impl<S> AsyncRead for MseStream<S>
+where
+    S: AsyncRead + AsyncWrite + Unpin + Send,
+{
+    fn poll_read(
+        self: Pin<&mut Self>,
+        cx: &mut TaskContext<'_>,
+        buf: &mut ReadBuf<'_>,
+    ) -> Poll<io::Result<()>> {
+        let this = self.get_mut();
+        if !this.initial.is_empty() {
+            let length = this.initial.len().min(buf.remaining());
+            buf.put_slice(&this.initial[..length]);
+            this.initial.drain(..length);
+            return Poll::Ready(Ok(()));
+        }
+
+        let start = buf.filled().len();
+        ready!(Pin::new(&mut this.stream).poll_read(cx, buf))?;
+        if let Some(cipher) = &mut this.cipher {
+            cipher.decrypt.apply(&mut buf.filled_mut()[start..]);
+        }
+        Poll::Ready(Ok(()))
+    }
+}
+
+impl<S> AsyncWrite for MseStream<S>
+where
+    S: AsyncRead + AsyncWrite + Unpin + Send,
+{
+    fn poll_write(
+        self: Pin<&mut Self>,
+        cx: &mut TaskContext<'_>,
+        buf: &[u8],
+    ) -> Poll<io::Result<usize>> {
+        let this = self.get_mut();
+        if this.cipher.is_none() {
+            return Pin::new(&mut this.stream).poll_write(cx, buf);
+        }
+
+        // The keystream moves on with every byte encrypted, so the bytes are encrypted once and
+        // kept until the stream takes them.
+        ready!(this.poll_pending(cx))?;
+        this.pending.extend_from_slice(buf);
+        let start = this.pending.len() - buf.len();
+        if let Some(cipher) = &mut this.cipher {
+            cipher.encrypt.apply(&mut this.pending[start..]);
+        }
+        let _ = this.poll_pending(cx)?;
+        Poll::Ready(Ok(buf.len()))
+    }
+
+    fn poll_flush(self: Pin<&mut Self>, cx: &mut TaskContext<'_>) -> Poll<io::Result<()>> {
+        let this = self.get_mut();
+        ready!(this.poll_pending(cx))?;
+        Pin::new(&mut this.stream).poll_flush(cx)
+    }
+
+    fn poll_shutdown(self: Pin<&mut Self>, cx: &mut TaskContext<'_>) -> Poll<io::Result<()>> {
+        let this = self.get_mut();
+        ready!(this.poll_pending(cx))?;
+        Pin::new(&mut this.stream).poll_shutdown(cx)
+    }
+}
+
+/// The private and public keys of the Diffie-Hellman key exchange.
+struct KeyPair {
+    private: BigUint,
+    public: [u8; KEY_LENGTH],
+}
+
+impl KeyPair {
+    fn generate() -> Self {
+        let private = BigUint::from_bytes_be(&rand::random::<[u8; 20]>());
+        let public = BigUint::from(2_u32).modpow(&private, &prime());
+        Self {
+            private,
+            public: to_key(&public),
+        }
+    }
+
+    // The secret shared with the peer which sent the public key.
+    fn secret(&self, theirs: &[u8; KEY_LENGTH]) -> Result<[u8; KEY_LENGTH]> {
+        let prime = prime();
+        let theirs = BigUint::from_bytes_be(theirs);
+        if theirs <= BigUint::from(1_u32) || theirs >= &prime - 1_u32 {
+            bail!("The peer sent an invalid public key");
+        }
+        Ok(to_key(&theirs.modpow(&self.private, &prime)))
+    }
+}
+
+fn prime() -> BigUint {
+    BigUint::parse_bytes(PRIME, 16).expect("The prime is valid hex")
+}
+
+// Pads a number to the length of the keys on the wire.
+fn to_key(n: &BigUint) -> [u8; KEY_LENGTH] {
+    let bytes = n.to_bytes_be();
+    let mut key = [0; KEY_LENGTH];
+    key[KEY_LENGTH - bytes.len()..].copy_from_slice(&bytes);
+    key
+}
+
+fn hash(parts: &[&[u8]]) -> [u8; 20] {
+    let mut sha1 = sha1_smol::Sha1::new();
+    for part in parts {
+        sha1.update(part);
+    }
+    sha1.digest().bytes()
+}
+
+fn xor(mut a: [u8; 20], b: [u8; 20]) -> [u8; 20] {
+    a.iter_mut().zip(b).for_each(|(a, b)| *a ^= b);
+    a
+}
+
+// Random bytes of a random length, to make the handshake harder to recognize.
+fn padding() -> Vec<u8> {
+    let mut rng = rand::thread_rng();
+    let mut padding = vec![0; rng.gen_range(0..=MAX_PADDING)];
+    rng.fill(&mut padding[..]);
+    padding
+}
+
+fn padding_length(bytes: &[u8]) -> Result<usize> {
+    let length = u16::from_be_bytes(bytes.try_into().expect("2 bytes")) as usize;
+    ensure!(length <= MAX_PADDING, "Padding too long: {length} bytes");
+    Ok(length)
+}
+
+// Reads the stream up to the end of the pattern, which follows a padding of up to
+// `MAX_PADDING` bytes. Reads a byte at a time so that nothing past the pattern is consumed.
+async fn synchronize<S>(stream: &mut S, pattern: &[u8]) -> Result<()>
+where
+    S: AsyncRead + Unpin,
+{
+    let mut window = vec![0; pattern.len()];
+    stream.read_exact(&mut window).await?;
+    for _ in 0..MAX_PADDING {
+        if window == pattern {
+            return Ok(());
+        }
+        window.remove(0);
+        window.push(stream.read_u8().await?);
+    }
+    ensure!(window == pattern, "The handshake was not found");
+    Ok(())
+}
+
+/// The RC4 stream cipher. Weak as a cipher, but it is what the protocol uses.
+#[derive(Debug, Clone)]
+struct Rc4 {
+    state: [u8; 256],
+    i: u8,
+    j: u8,
+}
+
+impl Rc4 {
+    fn new(key: &[u8]) -> Self {
+        let mut state = [0; 256];
+        for (i, byte) in state.iter_mut().enumerate() {
+            *byte = i as u8;
+        }
+        let mut j = 0_u8;
+        for i in 0..256 {
+            j = j.wrapping_add(state[i]).wrapping_add(key[i % key.len()]);
+            state.swap(i, j as usize);
+        }
+        Self { state, i: 0, j: 0 }
+    }
+
+    // The cipher of the protocol, which drops the first 1024 bytes of the keystream as they leak
+    // information about the key.
+    fn discarded(key: &[u8]) -> Self {
+        let mut rc4 = Self::new(key);
+        rc4.apply(&mut [0; 1024]);
+        rc4
+    }
+
+    // Encrypts or decrypts the data in place.
+    fn apply(&mut self, data: &mut [u8]) {
+        for byte in data {
+            self.i = self.i.wrapping_add(1);
+            self.j = self.j.wrapping_add(self.state[self.i as usize]);
+            self.state.swap(self.i as usize, self.j as usize);
+            let k = self.state[self.i as usize].wrapping_add(self.state[self.j as usize]);
+            *byte ^= self.state[k as usize];
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use tokio::io::duplex;
+
+    #[test]
+    fn test_rc4() {
+        let mut data = *b"Plaintext";
+        Rc4::new(b"Key").apply(&mut data);
+        assert_eq!(hex::encode(data), "bbf316e8d940af0ad3");
+
+        let mut data = *b"pedia";
+        Rc4::new(b"Wiki").apply(&mut data);
+        assert_eq!(hex::encode(data), "1021bf0420");
+    }
+
+    #[test]
+    fn test_key_exchange() {
+        let (a, b) = (KeyPair::generate(), KeyPair::generate());
+        assert_eq!(a.secret(&b.public).unwrap(), b.secret(&a.public).unwrap());
+        assert!(a.secret(&to_key(&BigUint::from(1_u32))).is_err());
+    }
+
+    #[test]
+    fn test_select() {
+        let both = CRYPTO_RC4 | CRYPTO_PLAINTEXT;
+        assert_eq!(EncryptionPolicy::Prefer.select(both).unwrap(), CRYPTO_RC4);
+        assert_eq!(
+            EncryptionPolicy::Prefer.select(CRYPTO_PLAINTEXT).unwrap(),
+            CRYPTO_PLAINTEXT
+        );
+        assert_eq!(
+            EncryptionPolicy::Disable.select(both).unwrap(),
+            CRYPTO_PLAINTEXT
+        );
+        assert!(EncryptionPolicy::Require.select(CRYPTO_PLAINTEXT).is_err());
+    }
+
+    // Goes through the handshake on both ends and exchanges some data both ways.
+    async fn handshake(ours: EncryptionPolicy, theirs: EncryptionPolicy) -> Result<bool> {
+        let (a, b) = duplex(4096);
+        let peer = tokio::spawn(async move {
+            let (mut stream, info_hash) = MseStream::accept(b, &[[1; 20], [7; 20]], theirs).await?;
+            assert_eq!(info_hash, [7; 20]);
+            let mut data = [0; 5];
+            stream.read_exact(&mut data).await?;
+            stream.write_all(&[&data[..], b" back"].concat()).await?;
+            stream.flush().await?;
+            anyhow::Ok(stream.is_encrypted())
+        });
+
+        let result = async {
+            let mut stream = MseStream::connect(a, [7; 20], ours).await?;
+            stream.write_all(b"hello").await?;
+            stream.flush().await?;
+            let mut data = [0; 10];
+            stream.read_exact(&mut data).await?;
+            assert_eq!(&data, b"hello back");
+            anyhow::Ok(stream.is_encrypted())
+        }
+        .await;
+
+        let encrypted = peer.await.unwrap();
+        let encrypted = result.and_then(|ours| Ok((ours, encrypted?)))?;
+        assert_eq!(encrypted.0, encrypted.1);
+        Ok(encrypted.0)
+    }
+
+    #[tokio::test]
+    async fn test_handshake() {
+        use EncryptionPolicy::*;
+
+        assert!(handshake(Prefer, Prefer).await.unwrap());
+        assert!(handshake(Require, Prefer).await.unwrap());
+        assert!(handshake(Prefer, Require).await.unwrap());
+        assert!(!handshake(Prefer, Disable).await.unwrap());
+        assert!(!handshake(Disable, Prefer).await.unwrap());
+        assert!(handshake(Require, Disable).await.is_err());
+    }
+
+    #[tokio::test]
+    async fn test_accept_unknown_torrent() {
+        let (a, b) = duplex(4096);
+        let peer = tokio::spawn(async move {
+            MseStream::accept(b, &[[1; 20]], EncryptionPolicy::Prefer)
+                .await
+                .map(|_| ())
+        });
+        let result = MseStream::connect(a, [7; 20], EncryptionPolicy::Prefer).await;
+        assert!(peer.await.unwrap().is_err());
+        assert!(result.is_err());
+    }
+
+    #[tokio::test]
+    async fn test_initial_payload() {
+        let (a, b) = duplex(4096);
+        let peer = tokio::spawn(async move {
+            MseStream::accept(b, &[[7; 20]], EncryptionPolicy::Require).await
+        });
+
+        // Sends the handshake as another client would, with some payload.
+        let mut a = a;
+        let keys = KeyPair::generate();
+        a.write_all(&keys.public).await.unwrap();
+        let mut theirs = [0; KEY_LENGTH];
+        a.read_exact(&mut theirs).await.unwrap();
+        let secret = keys.secret(&theirs).unwrap();
+        let mut encrypt = Rc4::discarded(&hash(&[b"keyA", &secret, &[7; 20]]));
+
+        let mut header = VC.to_vec();
+        header.extend_from_slice(&CRYPTO_RC4.to_be_bytes());
+        header.extend_from_slice(&[0, 0, 0, 5]);
+        header.extend_from_slice(b"hello");
+        encrypt.apply(&mut header);
+        let mut message = hash(&[b"req1", &secret]).to_vec();
+        message.extend(xor(hash(&[b"req2", &[7; 20]]), hash(&[b"req3", &secret])));
+        message.extend_from_slice(&header);
+        a.write_all(&message).await.unwrap();
+
+        let mut more = *b" world";
+        encrypt.apply(&mut more);
+        a.write_all(&more).await.unwrap();
+
+        let (mut stream, _) = peer.await.unwrap().unwrap();
+        let mut data = [0; 11];
+        stream.read_exact(&mut data).await.unwrap();
+        assert_eq!(&data, b"hello world");
+    }
+}
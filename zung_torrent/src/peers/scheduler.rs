@@ -0,0 +1,650 @@
+use std::collections::{BTreeSet, HashMap, HashSet, VecDeque};
+use std::hash::Hash;
+use std::ops::Range;
+use std::time::{Duration, Instant};
+
+use super::Bitfield;
+
+/// Length of the blocks requested from the peers. Every client accepts requests of 16 KiB, many
+/// reject longer ones.
+pub const BLOCK_LENGTH: u32 = 16 * 1024;
+
+/// Default number of requests kept in flight with each peer. Enough to keep a connection busy
+/// while the next requests travel, without committing too many blocks to a slow peer.
+pub const DEFAULT_PIPELINE_DEPTH: usize = 8;
+
+/// Default time after which a request which was not answered is given to another peer.
+pub const DEFAULT_REQUEST_TIMEOUT: Duration = Duration::from_secs(30);
+
+/// Default number of blocks left in flight under which the scheduler enters the endgame. About
+/// two pipelines, so that the last blocks of a slow peer do not hold the whole download back.
+pub const DEFAULT_ENDGAME_THRESHOLD: usize = 2 * DEFAULT_PIPELINE_DEPTH;
+
+/// A block of a piece, as sent in the `request`, `piece` and `cancel` messages.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord, Hash)]
+pub struct BlockRequest {
+    pub piece: u32,
+    pub offset: u32,
+    pub length: u32,
+}
+
+/// Decides which blocks to request from which peer.
+///
+/// The blocks which are not downloaded yet are shared by all the peers: each peer gets the first
+/// blocks it has, in piece order so that the pieces complete one after the other, up to its
+/// pipeline depth. No peer can hold more than the pipeline depth, so the fast peers, which free
+/// their slots sooner, get more blocks without starving the others.
+///
+/// The blocks of a peer go back to the shared blocks when their request times out, when the peer
+/// disconnects and when their piece fails the hash check. A peer can have a pipeline depth of its
+/// own, e.g. the `reqq` of its extension handshake, and a [snubbed](Self::set_snubbed) peer only
+/// gets one request at a time.
+///
+/// Once every block is requested and only a few are left in flight (see
+/// [`endgame_threshold`](Self::endgame_threshold)), the scheduler enters the endgame: the peers
+/// with free slots are also given the blocks in flight with the others, so that the download does
+/// not stall on the last blocks of a slow peer. The first copy of a block received wins, and the
+/// requests of the other copies are returned by [`take_cancels`](Self::take_cancels).
+///
+/// The scheduler does no IO: the peer
+/// connections ask it for requests and report what they receive, so it is driven by `P`, any key
+/// identifying the peers such as their address.
+///
+/// # Example
+///
+/// ```
+/// use std::time::Instant;
+/// use zung_torrent::peers::{Bitfield, BlockScheduler, BLOCK_LENGTH};
+///
+/// // Two pieces of 2 blocks each.
+/// let piece_length = 2 * BLOCK_LENGTH as u64;
+/// let mut scheduler = BlockScheduler::new(2 * piece_length, piece_length).pipeline_depth(3);
+///
+/// let now = Instant::now();
+/// let requests = scheduler.next_requests("peer", &Bitfield::full(2), now);
+/// assert_eq!(requests.len(), 3);
+///
+/// assert_eq!(scheduler.block_received(&"peer", &requests[0]), None);
+/// assert_eq!(scheduler.block_received(&"peer", &requests[1]), Some(0));
+/// ```
+#[derive(Debug, Clone)]
+pub struct BlockScheduler<P> {
+    total_length: u64,
+    piece_length: u64,
+    pipeline_depth: usize,
+    request_timeout: Duration,
+    endgame_threshold: usize,
+
+    /// Blocks nobody is requested for, in piece order.
+    pending: BTreeSet<BlockRequest>,
+
+    /// The requests in flight with each peer, the oldest first.
+    in_flight: HashMap<P, VecDeque<(BlockRequest, Instant)>>,
+
+    /// The pipeline depths of the peers which do not use the default one.
+    peer_depths: HashMap<P, usize>,
+    snubbed: HashSet<P>,
+
+    /// Number of blocks of each piece which were not received yet.
+    remaining: Vec<u32>,
+
+    /// Pieces whose blocks are requested before the others.
+    priority: Range<u32>,
+
+    /// Duplicate requests of the endgame which were answered by another peer.
+    cancels: Vec<(P, BlockRequest)>,
+}
+
+impl<P> BlockScheduler<P>
+where
+    P: Clone + Eq + Hash,
+{
+    /// A scheduler of every block of a torrent of `total_length` bytes.
+    pub fn new(total_length: u64, piece_length: u64) -> Self {
+        assert!(piece_length > 0, "The piece length must not be 0");
+
+        let number_of_pieces = total_length.div_ceil(piece_length) as usize;
+        let mut scheduler = Self {
+            total_length,
+            piece_length,
+            pipeline_depth: DEFAULT_PIPELINE_DEPTH,
+            request_timeout: DEFAULT_REQUEST_TIMEOUT,
+            endgame_threshold: DEFAULT_ENDGAME_THRESHOLD,
+            pending: BTreeSet::new(),
+            in_flight: HashMap::new(),
+            peer_depths: HashMap::new(),
+            snubbed: HashSet::new(),
+            remaining: vec![0; number_of_pieces],
+            priority: 0..0,
+            cancels: Vec::new(),
+        };
+        for piece in 0..number_of_pieces as u32 {
+            scheduler.requeue_piece(piece);
+        }
+        scheduler
+    }
+
+    /// Sets the number of requests kept in flight with each peer. Defaults to
+    /// [`DEFAULT_PIPELINE_DEPTH`].
+    pub fn pipeline_depth(mut self, depth: usize) -> Self {
+        self.pipeline_depth = depth.max(1);
+        self
+    }
+
+    /// Sets the time after which an unanswered request is given to another peer. Defaults to
+    /// [`DEFAULT_REQUEST_TIMEOUT`].
+    pub fn request_timeout(mut self, timeout: Duration) -> Self {
+        self.request_timeout = timeout;
+        self
+    }
+
+    /// Sets the number of blocks left in flight under which the same blocks are requested from
+    /// several peers. Defaults to [`DEFAULT_ENDGAME_THRESHOLD`], and `0` disables the endgame.
+    pub fn endgame_threshold(mut self, blocks: usize) -> Self {
+        self.endgame_threshold = blocks;
+        self
+    }
+
+    /// Sets the number of requests kept in flight with the peer, instead of the pipeline depth of
+    /// the scheduler, e.g. the number of requests the peer accepts.
+    pub fn set_peer_pipeline_depth(&mut self, peer: P, depth: usize) {
+        self.peer_depths.insert(peer, depth.max(1));
+    }
+
+    /// Snubs the peer, which sent no block for a while (see
+    /// [`PeerHealth`](super::PeerHealth)), or stops snubbing it once it sends one again.
+    ///
+    /// A snubbed peer only gets one request at a time, so that the blocks it holds back are
+    /// downloaded from the other peers. Returns its requests in flight but the oldest, which are
+    /// given back to the other peers, to send a `cancel` message for each.
+    pub fn set_snubbed(&mut self, peer: &P, snubbed: bool) -> Vec<BlockRequest> {
+        if !snubbed {
+            self.snubbed.remove(peer);
+            return Vec::new();
+        }
+
+        self.snubbed.insert(peer.clone());
+        let requeued: Vec<_> = match self.in_flight.get_mut(peer) {
+            Some(queue) if queue.len() > 1 => queue.drain(1..).map(|(block, _)| block).collect(),
+            _ => Vec::new(),
+        };
+        self.pending.extend(&requeued);
+        requeued
+    }
+
+    /// Returns `true` if the peer is snubbed.
+    pub fn is_snubbed(&self, peer: &P) -> bool {
+        self.snubbed.contains(peer)
+    }
+
+    /// Skips the pieces which are already downloaded, e.g. from the resume data.
+    pub fn skip_pieces(&mut self, have: &Bitfield) {
+        self.pending.retain(|block| !have.has(block.piece as usize));
+        for piece in have.pieces() {
+            if let Some(remaining) = self.remaining.get_mut(piece) {
+                *remaining = 0;
+            }
+        }
+    }
+
+    /// Requests the blocks of the pieces before any other, in piece order, e.g. the pieces of a
+    /// file streamed while it downloads, as returned by `Client::pieces_for_file`. The other
+    /// pieces follow once the peers have no block of these left to download.
+    pub fn prioritize(&mut self, pieces: Range<u32>) {
+        self.priority = pieces.start..pieces.end.max(pieces.start);
+    }
+
+    /// Returns the requests to send to the peer, which has the pieces in `has`, to fill its
+    /// pipeline. They are in flight from `now`.
+    pub fn next_requests(&mut self, peer: P, has: &Bitfield, now: Instant) -> Vec<BlockRequest> {
+        let depth = if self.snubbed.contains(&peer) {
+            1
+        } else {
+            self.peer_depths
+                .get(&peer)
+                .copied()
+                .unwrap_or(self.pipeline_depth)
+        };
+        let queue = self.in_flight.entry(peer.clone()).or_default();
+        let free = depth.saturating_sub(queue.len());
+
+        // The empty blocks sort before every block of their piece.
+        let bound = |piece| BlockRequest {
+            piece,
+            offset: 0,
+            length: 0,
+        };
+        let (start, end) = (bound(self.priority.start), bound(self.priority.end));
+        let mut requests: Vec<_> = self
+            .pending
+            .range(start..end)
+            .chain(self.pending.range(..start))
+            .chain(self.pending.range(end..))
+            .filter(|block| has.has(block.piece as usize))
+            .filter(|block| !queue.iter().any(|(request, _)| request == *block))
+            .take(free)
+            .copied()
+            .collect();
+        for block in &requests {
+            self.pending.remove(block);
+        }
+
+        if requests.len() < free && self.in_endgame() {
+            // The blocks in flight with the other peers, each once and in order.
+            let in_flight: BTreeSet<_> = self
+                .in_flight
+                .values()
+                .flatten()
+                .map(|(block, _)| *block)
+                .collect();
+            let queue = &self.in_flight[&peer];
+            requests.extend(
+                in_flight
+                    .into_iter()
+                    .filter(|block| has.has(block.piece as usize))
+                    .filter(|block| !queue.iter().any(|(request, _)| request == block))
+                    .take(free - requests.len()),
+            );
+        }
+
+        let queue = self
+            .in_flight
+            .get_mut(&peer)
+            .expect("The queue was added above");
+        queue.extend(requests.iter().map(|block| (*block, now)));
+        requests
+    }
+
+    /// Returns `true` once every block is requested and no more than the endgame threshold are
+    /// left to receive.
+    pub fn in_endgame(&self) -> bool {
+        self.pending.is_empty() && {
+            let left: usize = self.remaining.iter().map(|&blocks| blocks as usize).sum();
+            left > 0 && left <= self.endgame_threshold
+        }
+    }
+
+    /// Returns the requests of the endgame whose block was received from another peer since the
+    /// last call, with their peer, to send a `cancel` message for each.
+    pub fn take_cancels(&mut self) -> Vec<(P, BlockRequest)> {
+        std::mem::take(&mut self.cancels)
+    }
+
+    /// Records a block received from the peer. Returns the piece if it was its last block, so
+    /// that it can be checked against its hash.
+    ///
+    /// Blocks which were not requested from the peer, such as the blocks of a timed out request
+    /// received late or the endgame copies of a block received from another peer, are ignored, as
+    /// are the blocks of the pieces skipped by [`BlockScheduler::skip_pieces`].
+    pub fn block_received(&mut self, peer: &P, block: &BlockRequest) -> Option<u32> {
+        let queue = self.in_flight.get_mut(peer)?;
+        let i = queue.iter().position(|(request, _)| request == block)?;
+        queue.remove(i);
+
+        // The other copies of the block, requested in the endgame or given back to the pending
+        // blocks by a timeout, are not needed anymore.
+        self.pending.remove(block);
+        for (other, queue) in &mut self.in_flight {
+            if let Some(i) = queue.iter().position(|(request, _)| request == block) {
+                queue.remove(i);
+                self.cancels.push((other.clone(), *block));
+            }
+        }
+
+        // The blocks of the pieces skipped while they were in flight are not needed anymore.
+        let remaining = self.remaining.get_mut(block.piece as usize)?;
+        *remaining = remaining.checked_sub(1)?;
+        (*remaining == 0).then_some(block.piece)
+    }
+
+    /// Gives a request the peer rejected back to the other peers, e.g. when it chokes the
+    /// connection with the [Fast Extension](super::Message::RejectRequest). Returns `false` if the
+    /// block was not requested from the peer.
+    pub fn block_rejected(&mut self, peer: &P, block: &BlockRequest) -> bool {
+        let Some(queue) = self.in_flight.get_mut(peer) else {
+            return false;
+        };
+        let Some(i) = queue.iter().position(|(request, _)| request == block) else {
+            return false;
+        };
+        queue.remove(i);
+
+        // The endgame copies of the block are still in flight with the other peers.
+        if !self
+            .in_flight
+            .values()
+            .flatten()
+            .any(|(request, _)| request == block)
+        {
+            self.pending.insert(*block);
+        }
+        true
+    }
+
+    /// Gives the requests in flight for longer than the request timeout back to the other peers.
+    /// Returns them with their peer, to send a `cancel` message for each.
+    pub fn requeue_timed_out(&mut self, now: Instant) -> Vec<(P, BlockRequest)> {
+        let mut timed_out = Vec::new();
+        for (peer, queue) in &mut self.in_flight {
+            queue.retain(|(block, requested)| {
+                let expired = now.duration_since(*requested) >= self.request_timeout;
+                if expired {
+                    timed_out.push((peer.clone(), *block));
+                }
+                !expired
+            });
+        }
+        self.pending
+            .extend(timed_out.iter().map(|(_, block)| *block));
+        timed_out
+    }
+
+    /// Gives the requests in flight with the peer back to the other peers.
+    pub fn peer_disconnected(&mut self, peer: &P) {
+        self.peer_depths.remove(peer);
+        self.snubbed.remove(peer);
+        if let Some(queue) = self.in_flight.remove(peer) {
+            self.pending
+                .extend(queue.into_iter().map(|(block, _)| block));
+        }
+    }
+
+    /// Downloads every block of the piece again, after it failed the hash check.
+    pub fn piece_failed(&mut self, piece: u32) {
+        for queue in self.in_flight.values_mut() {
+            queue.retain(|(block, _)| block.piece != piece);
+        }
+        self.requeue_piece(piece);
+    }
+
+    /// Returns the number of requests in flight with the peer.
+    pub fn in_flight(&self, peer: &P) -> usize {
+        self.in_flight.get(peer).map_or(0, VecDeque::len)
+    }
+
+    /// Returns `true` once every block was received.
+    pub fn is_complete(&self) -> bool {
+        self.remaining.iter().all(|&remaining| remaining == 0)
+    }
+
+    // Marks every block of the piece as pending.
+    fn requeue_piece(&mut self, piece: u32) {
+        let start = piece as u64 * self.piece_length;
+        let length = self.piece_length.min(self.total_length - start) as u32;
+
+        let mut blocks = 0;
+        for offset in (0..length).step_by(BLOCK_LENGTH as usize) {
+            self.pending.insert(BlockRequest {
+                piece,
+                offset,
+                length: BLOCK_LENGTH.min(length - offset),
+            });
+            blocks += 1;
+        }
+        self.remaining[piece as usize] = blocks;
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const PIECE: u64 = 2 * BLOCK_LENGTH as u64;
+
+    fn block(piece: u32, offset: u32) -> BlockRequest {
+        BlockRequest {
+            piece,
+            offset,
+            length: BLOCK_LENGTH,
+        }
+    }
+
+    #[test]
+    fn test_blocks_of_the_last_piece() {
+        let mut scheduler = BlockScheduler::new(PIECE + 100, PIECE);
+        let requests = scheduler.next_requests(1, &Bitfield::full(2), Instant::now());
+        assert_eq!(
+            requests,
+            [
+                block(0, 0),
+                block(0, BLOCK_LENGTH),
+                BlockRequest {
+                    piece: 1,
+                    offset: 0,
+                    length: 100
+                }
+            ]
+        );
+    }
+
+    #[test]
+    fn test_pipeline_depth_is_shared_fairly() {
+        let mut scheduler = BlockScheduler::new(4 * PIECE, PIECE).pipeline_depth(3);
+        let all = Bitfield::full(4);
+        let now = Instant::now();
+
+        let first = scheduler.next_requests("a", &all, now);
+        let second = scheduler.next_requests("b", &all, now);
+        assert_eq!(first, [block(0, 0), block(0, BLOCK_LENGTH), block(1, 0)]);
+        assert_eq!(
+            second,
+            [block(1, BLOCK_LENGTH), block(2, 0), block(2, BLOCK_LENGTH)]
+        );
+
+        // A full pipeline gets nothing more until a block is received.
+        assert!(scheduler.next_requests("a", &all, now).is_empty());
+        assert_eq!(scheduler.block_received(&"a", &first[0]), None);
+        assert_eq!(scheduler.block_received(&"a", &first[1]), Some(0));
+        assert_eq!(
+            scheduler.next_requests("a", &all, now),
+            [block(3, 0), block(3, BLOCK_LENGTH)]
+        );
+        assert_eq!(scheduler.in_flight(&"a"), 3);
+    }
+
+    #[test]
+    fn test_only_pieces_of_the_peer() {
+        let mut scheduler = BlockScheduler::new(3 * PIECE, PIECE);
+        let mut has = Bitfield::new(3);
+        has.set(2);
+        let requests = scheduler.next_requests(1, &has, Instant::now());
+        assert!(requests.iter().all(|block| block.piece == 2));
+        assert_eq!(requests.len(), 2);
+    }
+
+    #[test]
+    fn test_requeue() {
+        let mut scheduler = BlockScheduler::new(2 * PIECE, PIECE)
+            .pipeline_depth(2)
+            .request_timeout(Duration::from_secs(10));
+        let all = Bitfield::full(2);
+        let start = Instant::now();
+
+        let slow = scheduler.next_requests("slow", &all, start);
+        let gone = scheduler.next_requests("gone", &all, start + Duration::from_secs(5));
+
+        // Only the requests of the slow peer timed out.
+        let timed_out = scheduler.requeue_timed_out(start + Duration::from_secs(10));
+        assert_eq!(timed_out, [("slow", slow[0]), ("slow", slow[1])]);
+        assert_eq!(scheduler.next_requests("fast", &all, start), slow);
+
+        // A late block of a timed out request is ignored.
+        assert_eq!(scheduler.block_received(&"slow", &slow[0]), None);
+
+        scheduler.peer_disconnected(&"gone");
+        assert_eq!(scheduler.next_requests("other", &all, start), gone);
+
+        scheduler.block_received(&"fast", &slow[0]);
+        assert_eq!(scheduler.block_received(&"fast", &slow[1]), Some(0));
+        scheduler.piece_failed(0);
+        assert_eq!(scheduler.next_requests("slow", &all, start), slow);
+        assert!(!scheduler.is_complete());
+    }
+
+    #[test]
+    fn test_skip_pieces() {
+        let mut scheduler = BlockScheduler::<u8>::new(2 * PIECE, PIECE);
+        let mut have = Bitfield::new(2);
+        have.set(0);
+        scheduler.skip_pieces(&have);
+        let requests = scheduler.next_requests(1, &Bitfield::full(2), Instant::now());
+        assert_eq!(requests, [block(1, 0), block(1, BLOCK_LENGTH)]);
+
+        for request in &requests {
+            scheduler.block_received(&1, request);
+        }
+        assert!(scheduler.is_complete());
+    }
+
+    #[test]
+    fn test_skip_pieces_in_flight() {
+        let mut scheduler = BlockScheduler::<u8>::new(2 * PIECE, PIECE);
+        let requests = scheduler.next_requests(1, &Bitfield::full(2), Instant::now());
+        assert_eq!(requests[..2], [block(0, 0), block(0, BLOCK_LENGTH)]);
+
+        // The piece was verified from another source while its blocks were in flight.
+        let mut have = Bitfield::new(2);
+        have.set(0);
+        scheduler.skip_pieces(&have);
+        assert_eq!(scheduler.block_received(&1, &requests[0]), None);
+        assert_eq!(scheduler.block_received(&1, &requests[1]), None);
+
+        assert_eq!(scheduler.block_received(&1, &requests[2]), None);
+        assert_eq!(scheduler.block_received(&1, &requests[3]), Some(1));
+        assert!(scheduler.is_complete());
+    }
+
+    #[test]
+    fn test_prioritize() {
+        let mut scheduler = BlockScheduler::new(4 * PIECE, PIECE).pipeline_depth(3);
+        scheduler.prioritize(2..4);
+        let all = Bitfield::full(4);
+        let now = Instant::now();
+
+        assert_eq!(
+            scheduler.next_requests("a", &all, now),
+            [block(2, 0), block(2, BLOCK_LENGTH), block(3, 0)]
+        );
+
+        // The other pieces follow, in order.
+        let mut has = Bitfield::new(4);
+        has.set(0);
+        has.set(3);
+        assert_eq!(
+            scheduler.next_requests("b", &has, now),
+            [block(3, BLOCK_LENGTH), block(0, 0), block(0, BLOCK_LENGTH)]
+        );
+    }
+
+    #[test]
+    fn test_endgame() {
+        let mut scheduler = BlockScheduler::new(2 * PIECE, PIECE)
+            .pipeline_depth(2)
+            .endgame_threshold(3);
+        let all = Bitfield::full(2);
+        let now = Instant::now();
+
+        let slow = scheduler.next_requests("slow", &all, now);
+        assert!(!scheduler.in_endgame());
+        let fast = scheduler.next_requests("fast", &all, now);
+        assert_eq!(fast, [block(1, 0), block(1, BLOCK_LENGTH)]);
+
+        // Every block is requested, but 4 are left.
+        assert!(!scheduler.in_endgame());
+        assert_eq!(scheduler.block_received(&"fast", &fast[0]), None);
+        assert!(scheduler.in_endgame());
+
+        // The fast peer gets the blocks of the slow one, the second peer the rest.
+        assert_eq!(scheduler.next_requests("fast", &all, now), [slow[0]]);
+        assert_eq!(
+            scheduler.next_requests("other", &all, now),
+            [slow[0], slow[1]]
+        );
+
+        // The first copy received wins and the others are cancelled.
+        assert_eq!(scheduler.block_received(&"fast", &slow[0]), None);
+        assert_eq!(
+            scheduler
+                .take_cancels()
+                .into_iter()
+                .collect::<BTreeSet<_>>(),
+            BTreeSet::from([("other", slow[0]), ("slow", slow[0])])
+        );
+        assert!(scheduler.take_cancels().is_empty());
+        assert_eq!(scheduler.block_received(&"slow", &slow[0]), None);
+        assert_eq!(scheduler.block_received(&"other", &slow[1]), Some(0));
+        assert_eq!(scheduler.take_cancels(), [("slow", slow[1])]);
+
+        assert_eq!(scheduler.block_received(&"fast", &fast[1]), Some(1));
+        assert!(scheduler.is_complete());
+        assert!(!scheduler.in_endgame());
+    }
+
+    #[test]
+    fn test_endgame_disabled() {
+        let mut scheduler = BlockScheduler::new(PIECE, PIECE).endgame_threshold(0);
+        let all = Bitfield::full(1);
+        let now = Instant::now();
+
+        assert_eq!(scheduler.next_requests("a", &all, now).len(), 2);
+        assert!(!scheduler.in_endgame());
+        assert!(scheduler.next_requests("b", &all, now).is_empty());
+    }
+
+    #[test]
+    fn test_peer_pipeline_depth_and_snubbed() {
+        let mut scheduler = BlockScheduler::new(4 * PIECE, PIECE).pipeline_depth(3);
+        let all = Bitfield::full(4);
+        let now = Instant::now();
+
+        scheduler.set_peer_pipeline_depth("small", 2);
+        assert_eq!(scheduler.next_requests("small", &all, now).len(), 2);
+        let slow = scheduler.next_requests("slow", &all, now);
+        assert_eq!(slow.len(), 3);
+
+        // The requests of the snubbed peer but the oldest go to the others.
+        assert_eq!(scheduler.set_snubbed(&"slow", true), &slow[1..]);
+        assert!(scheduler.is_snubbed(&"slow"));
+        assert_eq!(scheduler.in_flight(&"slow"), 1);
+        assert!(scheduler
+            .next_requests("fast", &all, now)
+            .starts_with(&slow[1..]));
+        assert!(scheduler.next_requests("slow", &all, now).is_empty());
+
+        // One request at a time until it is no longer snubbed.
+        scheduler.block_received(&"slow", &slow[0]);
+        assert_eq!(scheduler.next_requests("slow", &all, now).len(), 1);
+        assert!(scheduler.set_snubbed(&"slow", false).is_empty());
+        assert_eq!(scheduler.next_requests("slow", &all, now).len(), 2);
+
+        scheduler.peer_disconnected(&"slow");
+        assert!(!scheduler.is_snubbed(&"slow"));
+    }
+
+    #[test]
+    fn test_block_rejected() {
+        let mut scheduler = BlockScheduler::new(2 * PIECE, PIECE)
+            .pipeline_depth(2)
+            .endgame_threshold(0);
+        let now = Instant::now();
+
+        // A choked peer only gets the blocks of its allowed fast pieces.
+        let mut allowed = Bitfield::new(2);
+        allowed.set(1);
+        let requests = scheduler.next_requests("a", &allowed, now);
+        assert_eq!(requests, [block(1, 0), block(1, BLOCK_LENGTH)]);
+
+        assert!(scheduler.block_rejected(&"a", &requests[1]));
+        assert!(!scheduler.block_rejected(&"a", &requests[1]));
+        assert!(!scheduler.block_rejected(&"b", &requests[0]));
+        assert_eq!(scheduler.in_flight(&"a"), 1);
+        assert_eq!(
+            scheduler.next_requests("b", &Bitfield::full(2), now),
+            [block(0, 0), block(0, BLOCK_LENGTH)]
+        );
+        assert_eq!(
+            scheduler.next_requests("c", &Bitfield::full(2), now),
+            [block(1, BLOCK_LENGTH)]
+        );
+    }
+}
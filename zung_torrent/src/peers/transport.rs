@@ -0,0 +1,251 @@
+use std::future::Future;
+use std::io::ErrorKind;
+use std::net::SocketAddr;
+
+use anyhow::{bail, Context, Result};
+use bytes::{Bytes, BytesMut};
+use tokio::io::{AsyncRead, AsyncReadExt, AsyncWrite, AsyncWriteExt};
+use tokio::net::{TcpStream, ToSocketAddrs};
+
+use super::{EncryptionPolicy, Handshake, MseStream};
+use crate::net::{UtpSocket, UtpStream};
+
+/// Maximum length of a single message accepted from a peer.
+///
+/// Piece messages carry blocks of 16 KiB and bitfields of even the largest torrents stay well
+/// below this, so anything longer is treated as a misbehaving peer.
+pub const MAX_FRAME_LENGTH: usize = 1 << 20;
+
+/// A connection to a peer that sends and receives whole messages.
+///
+/// Every message on the wire is prefixed by its length as a 4 byte big-endian integer. Transports
+/// take care of this framing; the frames passed to and returned from them are the message id
+/// followed by the payload. An empty frame is a keep-alive message.
+pub trait PeerTransport: Send {
+    /// Sends a single message frame to the peer.
+    fn send(&mut self, frame: &[u8]) -> impl Future<Output = Result<()>> + Send;
+
+    /// Receives the next message frame from the peer. Returns `None` if the peer closed the
+    /// connection cleanly between two messages.
+    fn recv(&mut self) -> impl Future<Output = Result<Option<Bytes>>> + Send;
+}
+
+/// A [`PeerTransport`] over any async byte stream.
+#[derive(Debug)]
+pub struct FramedTransport<S> {
+    stream: S,
+}
+
+/// The default [`PeerTransport`], over a TCP connection.
+pub type TcpTransport = FramedTransport<TcpStream>;
+
+impl<S> FramedTransport<S>
+where
+    S: AsyncRead + AsyncWrite + Unpin + Send,
+{
+    /// Wraps the provided stream. Anything exchanged before the framed messages (such as the
+    /// handshake) must already have been read from or written to the stream.
+    pub fn new(stream: S) -> Self {
+        Self { stream }
+    }
+
+    /// Returns the underlying stream.
+    pub fn into_inner(self) -> S {
+        self.stream
+    }
+}
+
+/// A [`PeerTransport`] over a uTP connection (see [`net::utp`](crate::net::utp)), which is
+/// encrypted as agreed with the peer.
+pub type UtpTransport = FramedTransport<MseStream<UtpStream>>;
+
+impl TcpTransport {
+    /// Opens a TCP connection to the peer, racing its IPv6 and IPv4 addresses (see
+    /// [`net::connect`](crate::net::connect)).
+    pub async fn connect<A: ToSocketAddrs>(addr: A) -> Result<Self> {
+        let stream = crate::net::connect::connect(addr)
+            .await
+            .context("Failed to connect to the peer")?;
+        stream.set_nodelay(true)?;
+        Ok(Self::new(stream))
+    }
+
+    /// Connects to the peer and exchanges the handshakes (see [`Handshake::exchange`]). Returns
+    /// the transport, ready for the messages, along with the handshake of the peer.
+    pub async fn connect_with_handshake<A: ToSocketAddrs>(
+        addr: A,
+        handshake: &Handshake,
+    ) -> Result<(Self, Handshake)> {
+        let mut transport = Self::connect(addr).await?;
+        let theirs = handshake.exchange(&mut transport.stream).await?;
+        Ok((transport, theirs))
+    }
+}
+
+impl UtpTransport {
+    /// Connects to the peer over uTP from the socket, goes through the encryption handshake
+    /// according to the policy (see [`MseStream::connect_with`]) and exchanges the handshakes
+    /// (see [`Handshake::exchange`]).
+    pub async fn connect_with_handshake(
+        socket: &UtpSocket,
+        addr: SocketAddr,
+        handshake: &Handshake,
+        policy: EncryptionPolicy,
+    ) -> Result<(Self, Handshake)> {
+        let connect = || async {
+            socket
+                .connect(addr)
+                .await
+                .context("Failed to connect to the peer")
+        };
+        let mut stream = MseStream::connect_with(connect, handshake.info_hash, policy).await?;
+        let theirs = handshake.exchange(&mut stream).await?;
+        Ok((Self::new(stream), theirs))
+    }
+}
+
+impl<S> PeerTransport for FramedTransport<S>
+where
+    S: AsyncRead + AsyncWrite + Unpin + Send,
+{
+    async fn send(&mut self, frame: &[u8]) -> Result<()> {
+        if frame.len() > MAX_FRAME_LENGTH {
+            bail!("Message too long: {} bytes", frame.len())
+        }
+
+        let mut buf = Vec::with_capacity(4 + frame.len());
+        buf.extend_from_slice(&(frame.len() as u32).to_be_bytes());
+        buf.extend_from_slice(frame);
+
+        self.stream.write_all(&buf).await?;
+        self.stream.flush().await?;
+
+        #[cfg(feature = "metrics")]
+        crate::metrics::Metrics::global().record_uploaded(buf.len());
+        Ok(())
+    }
+
+    async fn recv(&mut self) -> Result<Option<Bytes>> {
+        let mut len = [0_u8; 4];
+        match self.stream.read_exact(&mut len).await {
+            Ok(_) => {}
+            Err(e) if e.kind() == ErrorKind::UnexpectedEof => return Ok(None),
+            Err(e) => return Err(e.into()),
+        }
+
+        let len = u32::from_be_bytes(len) as usize;
+        if len > MAX_FRAME_LENGTH {
+            bail!("Message too long: {len} bytes")
+        }
+
+        let mut frame = BytesMut::zeroed(len);
+        self.stream
+            .read_exact(&mut frame)
+            .await
+            .context("Connection closed in the middle of a message")?;
+
+        #[cfg(feature = "metrics")]
+        crate::metrics::Metrics::global().record_downloaded(4 + len);
+        Ok(Some(frame.freeze()))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::testing::ScriptedPeer;
+    use tokio::io::duplex;
+    use tokio::net::TcpListener;
+
+    #[tokio::test]
+    async fn test_framed_roundtrip() {
+        let (a, b) = duplex(64);
+        let mut a = FramedTransport::new(a);
+        let mut b = FramedTransport::new(b);
+
+        a.send(&[]).await.unwrap();
+        a.send(&[4, 0, 0, 0, 7]).await.unwrap();
+        drop(a);
+
+        assert_eq!(b.recv().await.unwrap().unwrap(), Bytes::new());
+        assert_eq!(b.recv().await.unwrap().unwrap(), &[4, 0, 0, 0, 7][..]);
+        assert!(b.recv().await.unwrap().is_none());
+    }
+
+    #[tokio::test]
+    async fn test_rejects_long_frames() {
+        let (mut a, b) = duplex(64);
+        let mut b = FramedTransport::new(b);
+
+        a.write_all(&(MAX_FRAME_LENGTH as u32 + 1).to_be_bytes())
+            .await
+            .unwrap();
+        assert!(b.recv().await.is_err());
+    }
+
+    #[tokio::test]
+    async fn test_tcp_transport() {
+        let listener = TcpListener::bind("127.0.0.1:0").await.unwrap();
+        let addr = listener.local_addr().unwrap();
+
+        let server = tokio::spawn(async move {
+            let (stream, _) = listener.accept().await.unwrap();
+            let mut transport = FramedTransport::new(stream);
+            let frame = transport.recv().await.unwrap().unwrap();
+            transport.send(&frame).await.unwrap();
+        });
+
+        let mut client = TcpTransport::connect(addr).await.unwrap();
+        client.send(b"\x02").await.unwrap();
+        assert_eq!(client.recv().await.unwrap().unwrap(), &b"\x02"[..]);
+        server.await.unwrap();
+    }
+
+    #[tokio::test]
+    async fn test_connect_with_handshake() {
+        let info_hash = [1; 20];
+        let peer = ScriptedPeer::start(info_hash, *b"-TR4050-abcdefghijkl", vec![vec![1]])
+            .await
+            .unwrap();
+
+        let handshake = Handshake::new(info_hash, [3; 20]);
+        let (mut transport, theirs) = TcpTransport::connect_with_handshake(peer.addr(), &handshake)
+            .await
+            .unwrap();
+        assert_eq!(&theirs.peer_id, b"-TR4050-abcdefghijkl");
+        assert_eq!(transport.recv().await.unwrap().unwrap(), &[1][..]);
+        peer.finish().await.unwrap();
+    }
+
+    #[tokio::test]
+    async fn test_utp_transport() {
+        let info_hash = [1; 20];
+        let ours = UtpSocket::bind("127.0.0.1:0").await.unwrap();
+        let theirs = UtpSocket::bind("127.0.0.1:0").await.unwrap();
+        let addr = theirs.local_addr().unwrap();
+
+        let peer = tokio::spawn(async move {
+            let stream = theirs.accept().await.unwrap();
+            let (mut stream, _) =
+                MseStream::accept(stream, &[info_hash], EncryptionPolicy::Require)
+                    .await
+                    .unwrap();
+            let answer = Handshake::new(info_hash, *b"-TR4050-abcdefghijkl");
+            answer.exchange(&mut stream).await.unwrap();
+            let mut transport = FramedTransport::new(stream);
+            transport.send(&[1]).await.unwrap();
+            // Keeps the connection until the frame is received.
+            transport.recv().await.unwrap();
+        });
+
+        let handshake = Handshake::new(info_hash, [3; 20]);
+        let (mut transport, theirs) =
+            UtpTransport::connect_with_handshake(&ours, addr, &handshake, EncryptionPolicy::Prefer)
+                .await
+                .unwrap();
+        assert_eq!(&theirs.peer_id, b"-TR4050-abcdefghijkl");
+        assert_eq!(transport.recv().await.unwrap().unwrap(), &[1][..]);
+        drop(transport);
+        peer.await.unwrap();
+    }
+}
@@ -0,0 +1,231 @@
+//! Announce state of a tracker over a torrent session.
+
+use anyhow::Result;
+
+use super::trackers::{Event, TrackerRequest, TrackerResponse};
+
+/// Announces to a single tracker, sending the right event with every announce.
+///
+/// As per the spec:
+///
+/// - The first announce carries the `started` event.
+/// - The regular re-announces carry no event.
+/// - `completed` is sent once when the download completes, but not if it was already complete
+///   when the tracker was first announced to.
+/// - `stopped` is sent when the client shuts down gracefully. Announcing again after that starts
+///   over with `started`.
+///
+/// An event is only considered sent once the tracker responds, so a failed announce is retried
+/// with the same event.
+#[derive(Debug)]
+pub struct Announcer {
+    request: TrackerRequest,
+    state: AnnounceState,
+    pending: Option<Event>,
+}
+
+/// Where an [`Announcer`] is in its lifecycle.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum AnnounceState {
+    /// Nothing was announced yet, or the last announce was `stopped`.
+    Idle,
+
+    /// `started` was announced while the download was incomplete.
+    Downloading,
+
+    /// `started` was announced and the download is complete.
+    Seeding,
+}
+
+impl Announcer {
+    /// Creates the announcer from the request generated for the tracker. See
+    /// [`Tracker::generate_request`](super::Tracker::generate_request).
+    pub fn new(request: TrackerRequest) -> Self {
+        Self {
+            request,
+            state: AnnounceState::Idle,
+            pending: None,
+        }
+    }
+
+    pub fn state(&self) -> AnnounceState {
+        self.state
+    }
+
+    /// The request sent with the announces, e.g. to update the
+    /// [progress](TrackerRequest::set_progress) before announcing.
+    pub fn request_mut(&mut self) -> &mut TrackerRequest {
+        &mut self.request
+    }
+
+    pub fn request(&self) -> &TrackerRequest {
+        &self.request
+    }
+
+    /// The event that the next announce carries.
+    pub fn next_event(&self) -> Event {
+        match (self.state, self.pending) {
+            (AnnounceState::Idle, _) => Event::Started,
+            (_, Some(event)) => event,
+            (_, None) => Event::None,
+        }
+    }
+
+    /// Marks the download as complete so that the next announce carries `completed`. Nothing is
+    /// sent if the tracker never saw the download incomplete.
+    pub fn complete(&mut self) {
+        if self.state == AnnounceState::Downloading && self.pending.is_none() {
+            self.pending = Some(Event::Completed);
+        }
+    }
+
+    /// Marks the session as shutting down so that the next announce carries `stopped`. Returns
+    /// `false` if nothing was announced, in which case there is nothing to stop.
+    pub fn stop(&mut self) -> bool {
+        if self.state == AnnounceState::Idle {
+            return false;
+        }
+        self.pending = Some(Event::Stopped);
+        true
+    }
+
+    /// Announces to the tracker with the [next event](Announcer::next_event). `left` is the
+    /// number of bytes still to be downloaded, which is also sent to the tracker.
+    pub async fn announce(&mut self, left: usize) -> Result<TrackerResponse> {
+        let event = self.next_event();
+        self.request.set_left(left);
+        self.request.set_event(event);
+
+        let response = self.request.announce().await?;
+        self.sent(event, left);
+        Ok(response)
+    }
+
+    // Moves to the next state once the tracker accepted the event.
+    fn sent(&mut self, event: Event, left: usize) {
+        match event {
+            Event::Started => {
+                self.state = if left == 0 {
+                    AnnounceState::Seeding
+                } else {
+                    AnnounceState::Downloading
+                };
+                self.pending = None;
+            }
+            Event::Completed => {
+                self.state = AnnounceState::Seeding;
+                self.pending = None;
+            }
+            Event::Stopped => {
+                self.state = AnnounceState::Idle;
+                self.pending = None;
+            }
+            Event::None => {}
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{
+        meta_info::InfoHash, sources::Tracker, testing::MockHttpTracker, PeerID, Progress,
+    };
+
+    const PROGRESS: Progress = Progress {
+        uploaded: 0,
+        downloaded: 0,
+        left: 10,
+    };
+
+    async fn announcer(url: &str) -> Announcer {
+        let info_hash = InfoHash::new(b"test info_hash").as_encoded();
+        let request = Tracker::new(url)
+            .generate_request(info_hash, PeerID::default(), PROGRESS)
+            .await
+            .unwrap();
+        Announcer::new(request)
+    }
+
+    #[tokio::test]
+    async fn test_event_transitions() {
+        let mut announcer = announcer("http://example.com/announce").await;
+        assert_eq!(announcer.next_event(), Event::Started);
+        // Nothing to complete or stop before `started` is sent.
+        announcer.complete();
+        assert!(!announcer.stop());
+        assert_eq!(announcer.next_event(), Event::Started);
+
+        announcer.sent(Event::Started, 10);
+        assert_eq!(announcer.state(), AnnounceState::Downloading);
+        assert_eq!(announcer.next_event(), Event::None);
+        announcer.sent(Event::None, 10);
+        assert_eq!(announcer.next_event(), Event::None);
+
+        announcer.complete();
+        assert_eq!(announcer.next_event(), Event::Completed);
+        announcer.sent(Event::Completed, 0);
+        assert_eq!(announcer.state(), AnnounceState::Seeding);
+        assert_eq!(announcer.next_event(), Event::None);
+        // Completed is sent only once.
+        announcer.complete();
+        assert_eq!(announcer.next_event(), Event::None);
+
+        assert!(announcer.stop());
+        assert_eq!(announcer.next_event(), Event::Stopped);
+        announcer.sent(Event::Stopped, 0);
+        assert_eq!(announcer.state(), AnnounceState::Idle);
+        assert_eq!(announcer.next_event(), Event::Started);
+    }
+
+    #[tokio::test]
+    async fn test_complete_when_started_as_seeder() {
+        let mut announcer = announcer("http://example.com/announce").await;
+        announcer.sent(Event::Started, 0);
+        assert_eq!(announcer.state(), AnnounceState::Seeding);
+
+        announcer.complete();
+        assert_eq!(announcer.next_event(), Event::None);
+    }
+
+    #[tokio::test]
+    async fn test_stop_while_completed_is_pending() {
+        let mut announcer = announcer("http://example.com/announce").await;
+        announcer.sent(Event::Started, 10);
+        announcer.complete();
+        assert!(announcer.stop());
+        assert_eq!(announcer.next_event(), Event::Stopped);
+    }
+
+    #[tokio::test]
+    async fn test_announce_sends_events() {
+        let mock = MockHttpTracker::start("d8:intervali900ee").await.unwrap();
+        let mut announcer = announcer(&mock.url()).await;
+
+        announcer.announce(10).await.unwrap();
+        announcer.announce(10).await.unwrap();
+        announcer.complete();
+        announcer.announce(0).await.unwrap();
+        announcer.stop();
+        announcer.announce(0).await.unwrap();
+
+        let requests = mock.requests();
+        assert_eq!(requests.len(), 4);
+        assert!(requests[0].contains("left=10") && requests[0].contains("event=started"));
+        assert!(!requests[1].contains("event="));
+        assert!(requests[2].contains("left=0") && requests[2].contains("event=completed"));
+        assert!(requests[3].contains("event=stopped"));
+    }
+
+    #[tokio::test]
+    async fn test_failed_announce_keeps_the_event() {
+        let mock = MockHttpTracker::start("d14:failure reason6:bannede")
+            .await
+            .unwrap();
+        let mut announcer = announcer(&mock.url()).await;
+
+        assert!(announcer.announce(10).await.is_err());
+        assert_eq!(announcer.state(), AnnounceState::Idle);
+        assert_eq!(announcer.next_event(), Event::Started);
+    }
+}
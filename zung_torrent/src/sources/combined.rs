@@ -0,0 +1,311 @@
+use std::fmt::Display;
+use std::net::SocketAddr;
+use std::sync::Arc;
+
+use zung_parsers::bencode::Value;
+
+use super::{DownloadSources, HttpSeeder, HttpSeederList, Tracker, TrackerList};
+use crate::meta_info::MetaInfo;
+
+/// A DHT node to bootstrap from, as contained in the `nodes` key of the torrent file ([BEP:
+/// 5](https://www.bittorrent.org/beps/bep_0005.html)).
+#[derive(Debug, Clone, PartialEq, Eq, Hash)]
+pub struct DhtNode {
+    pub host: String,
+    pub port: u16,
+}
+
+impl Display for DhtNode {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(f, "{}:{}", self.host, self.port)
+    }
+}
+
+/// All the sources of a torrent: the trackers, the web seeds, the DHT nodes and the peers learned
+/// through peer exchange.
+///
+/// Unlike [`DownloadSources`], which is a view of the sources contained in the torrent file,
+/// sources can be added while the torrent is running (e.g. trackers from a magnet link or peers
+/// from PEX) and the sources of two torrents can be [merged](Sources::merge). Every component is
+/// `None` until it has at least one source and duplicates are never added.
+///
+/// # Example
+///
+/// ```
+/// use zung_torrent::sources::Sources;
+///
+/// # fn sources(client: &zung_torrent::Client) {
+/// let mut sources = Sources::new(client.meta_info());
+/// sources.add_tracker("udp://tracker.opentrackr.org:1337/announce");
+/// sources.add_peer("127.0.0.1:6881".parse().unwrap());
+/// # }
+/// ```
+#[derive(Debug, Clone, Default)]
+pub struct Sources {
+    trackers: Option<TrackerList>,
+    web_seeds: Option<HttpSeederList>,
+    dht_nodes: Option<Vec<DhtNode>>,
+    peers: Option<Vec<SocketAddr>>,
+}
+
+impl Sources {
+    /// Collects the trackers, the web seeds and the DHT nodes contained in the torrent file.
+    pub fn new(meta_info: &MetaInfo) -> Self {
+        let mut sources = Self::from(DownloadSources::new(meta_info));
+        for node in dht_nodes(meta_info) {
+            sources.add_dht_node(node);
+        }
+        sources
+    }
+
+    pub fn trackers(&self) -> Option<&TrackerList> {
+        self.trackers.as_ref()
+    }
+
+    pub fn web_seeds(&self) -> Option<&HttpSeederList> {
+        self.web_seeds.as_ref()
+    }
+
+    pub fn dht_nodes(&self) -> Option<&[DhtNode]> {
+        self.dht_nodes.as_deref()
+    }
+
+    /// Returns the peers learned through peer exchange.
+    pub fn peers(&self) -> Option<&[SocketAddr]> {
+        self.peers.as_deref()
+    }
+
+    /// Returns `true` if there are no sources at all.
+    pub fn is_empty(&self) -> bool {
+        self.trackers.is_none()
+            && self.web_seeds.is_none()
+            && self.dht_nodes.is_none()
+            && self.peers.is_none()
+    }
+
+    /// Adds a tracker unless it is already known. Returns `true` if the tracker was added.
+    pub fn add_tracker(&mut self, url: &str) -> bool {
+        self.push_tracker(Tracker::new(url))
+    }
+
+    fn push_tracker(&mut self, tracker: Tracker) -> bool {
+        match &mut self.trackers {
+            Some(trackers) => trackers.push(tracker),
+            None => {
+                self.trackers = Some(TrackerList::new(vec![tracker]));
+                true
+            }
+        }
+    }
+
+    /// Adds a web seed serving the files of the torrent unless it is already known. Returns
+    /// `true` if the web seed was added.
+    pub fn add_web_seed(&mut self, url: &str, meta_info: &MetaInfo) -> bool {
+        self.push_web_seed(Arc::from(url), HttpSeeder::new(url, meta_info))
+    }
+
+    fn push_web_seed(&mut self, url: Arc<str>, seeder: HttpSeeder) -> bool {
+        self.web_seeds
+            .get_or_insert_with(|| HttpSeederList::new(Vec::new()))
+            .push(url, seeder)
+    }
+
+    /// Adds a DHT node unless it is already known. Returns `true` if the node was added.
+    pub fn add_dht_node(&mut self, node: DhtNode) -> bool {
+        push_unique(&mut self.dht_nodes, node)
+    }
+
+    /// Adds a peer learned through peer exchange unless it is already known. Returns `true` if
+    /// the peer was added.
+    pub fn add_peer(&mut self, addr: SocketAddr) -> bool {
+        push_unique(&mut self.peers, addr)
+    }
+
+    /// Adds all the sources of `other` which are not known yet, keeping the order of the sources
+    /// of `self` first.
+    pub fn merge(&mut self, other: Sources) {
+        for tracker in other.trackers.into_iter().flat_map(TrackerList::into_vec) {
+            self.push_tracker(tracker);
+        }
+        for (url, seeder) in other.web_seeds.iter().flat_map(|list| list.iter()) {
+            self.push_web_seed(Arc::clone(url), seeder.clone());
+        }
+        for node in other.dht_nodes.into_iter().flatten() {
+            self.add_dht_node(node);
+        }
+        for peer in other.peers.into_iter().flatten() {
+            self.add_peer(peer);
+        }
+    }
+
+    /// Returns the trackers and the web seeds as a [`DownloadSources`], or `None` if there are
+    /// neither.
+    pub fn as_download_sources(&self) -> Option<DownloadSources> {
+        match (self.trackers.clone(), self.web_seeds.clone()) {
+            (Some(tracker_list), Some(http_seeder_list)) => Some(DownloadSources::Hybrid {
+                tracker_list,
+                http_seeder_list,
+            }),
+            (Some(tracker_list), None) => Some(DownloadSources::Trackers { tracker_list }),
+            (None, Some(http_seeder_list)) => {
+                Some(DownloadSources::HttpSeeders { http_seeder_list })
+            }
+            (None, None) => None,
+        }
+    }
+}
+
+impl From<DownloadSources> for Sources {
+    fn from(sources: DownloadSources) -> Self {
+        let (trackers, web_seeds) = match sources {
+            DownloadSources::Trackers { tracker_list } => (Some(tracker_list), None),
+            DownloadSources::HttpSeeders { http_seeder_list } => (None, Some(http_seeder_list)),
+            DownloadSources::Hybrid {
+                tracker_list,
+                http_seeder_list,
+            } => (Some(tracker_list), Some(http_seeder_list)),
+        };
+
+        Self {
+            trackers: trackers.filter(|list| !list.is_empty()),
+            web_seeds: web_seeds.filter(|list| !list.is_empty()),
+            dht_nodes: None,
+            peers: None,
+        }
+    }
+}
+
+fn push_unique<T: PartialEq>(list: &mut Option<Vec<T>>, item: T) -> bool {
+    let list = list.get_or_insert_with(Vec::new);
+    if list.contains(&item) {
+        false
+    } else {
+        list.push(item);
+        true
+    }
+}
+
+/// Reads the `nodes` key: a list of `[host, port]` pairs. Malformed entries are skipped.
+fn dht_nodes(meta_info: &MetaInfo) -> Vec<DhtNode> {
+    let Some(Value::List(nodes)) = meta_info.extra_keys().get("nodes") else {
+        return Vec::new();
+    };
+
+    nodes
+        .iter()
+        .filter_map(|node| {
+            let Value::List(pair) = node else {
+                return None;
+            };
+            let host = match pair.first()? {
+                Value::String(host) => host.clone(),
+                Value::Bytes(host) => String::from_utf8(host.clone()).ok()?,
+                _ => return None,
+            };
+            let Value::Integer(port) = pair.get(1)? else {
+                return None;
+            };
+            Some(DhtNode {
+                host,
+                port: u16::try_from(*port).ok()?,
+            })
+        })
+        .collect()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::testing::TorrentBuilder;
+
+    fn meta_info(builder: TorrentBuilder) -> MetaInfo {
+        MetaInfo::from_bytes(&builder.build()).unwrap()
+    }
+
+    #[test]
+    fn test_add_sources() {
+        let meta_info = meta_info(TorrentBuilder::single_file("a.bin", 10));
+        let mut sources = Sources::new(&meta_info);
+        assert!(sources.is_empty());
+        assert!(sources.as_download_sources().is_none());
+
+        assert!(sources.add_tracker("http://tracker.example.com/announce"));
+        assert!(!sources.add_tracker("HTTP://Tracker.Example.com/announce"));
+        assert!(sources.add_web_seed("http://seed.example.com/", &meta_info));
+        assert!(!sources.add_web_seed("http://seed.example.com/", &meta_info));
+
+        let peer = "127.0.0.1:6881".parse().unwrap();
+        assert!(sources.add_peer(peer));
+        assert!(!sources.add_peer(peer));
+
+        assert_eq!(sources.trackers().unwrap().len(), 1);
+        assert_eq!(
+            sources.web_seeds().unwrap()[0].1.urls(),
+            ["http://seed.example.com/a.bin"]
+        );
+        assert_eq!(sources.peers(), Some(&[peer][..]));
+        assert!(sources.dht_nodes().is_none());
+        assert!(sources.as_download_sources().unwrap().is_hybrid());
+    }
+
+    #[test]
+    fn test_merge() {
+        let first = meta_info(
+            TorrentBuilder::single_file("a.bin", 10)
+                .announce_list(&[&["http://one.example.com/announce"]]),
+        );
+        let second = meta_info(
+            TorrentBuilder::single_file("a.bin", 10)
+                .announce_list(&[&[
+                    "http://one.example.com/announce",
+                    "udp://two.example.com:6969/announce",
+                ]])
+                .url_list(&["http://seed.example.com/"]),
+        );
+
+        let mut sources = Sources::new(&first);
+        let mut other = Sources::new(&second);
+        other.add_dht_node(DhtNode {
+            host: "router.example.com".into(),
+            port: 6881,
+        });
+        sources.merge(other);
+
+        let trackers: Vec<_> = sources
+            .trackers()
+            .unwrap()
+            .iter()
+            .map(Tracker::url)
+            .collect();
+        assert_eq!(
+            trackers,
+            [
+                "http://one.example.com/announce",
+                "udp://two.example.com:6969/announce"
+            ]
+        );
+        assert_eq!(sources.web_seeds().unwrap().len(), 1);
+        assert_eq!(
+            sources.dht_nodes().unwrap()[0].to_string(),
+            "router.example.com:6881"
+        );
+    }
+
+    #[test]
+    fn test_dht_nodes() {
+        let mut bytes = b"d4:infod6:lengthi1e4:name1:a12:piece lengthi16e6:pieces20:".to_vec();
+        bytes.extend_from_slice(&[0; 20]);
+        bytes.extend_from_slice(b"e5:nodesll9:127.0.0.1i6881eel4:hosti70000eei1eee");
+        let meta_info = MetaInfo::from_bytes(&bytes).unwrap();
+
+        // The node with an out of range port and the malformed entry are skipped.
+        let sources = Sources::new(&meta_info);
+        assert_eq!(
+            sources.dht_nodes().unwrap(),
+            [DhtNode {
+                host: "127.0.0.1".into(),
+                port: 6881
+            }]
+        );
+    }
+}
@@ -0,0 +1,131 @@
+use std::net::SocketAddr;
+use std::sync::Arc;
+
+use indexmap::IndexMap;
+
+use super::TrackerPeer;
+
+/// A peer returned by one or more trackers.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct DiscoveredPeer {
+    pub addr: SocketAddr,
+
+    /// The peer id, as returned by a tracker in the dictionary model of the response or as
+    /// received in the handshake with the peer.
+    pub peer_id: Option<[u8; 20]>,
+
+    /// The urls of the trackers which returned the peer, in the order they did.
+    pub trackers: Vec<Arc<str>>,
+}
+
+/// The peers returned by the trackers of a torrent, without duplicates.
+///
+/// The same peer is usually returned by several trackers, sometimes as an IPv4-mapped IPv6
+/// address. Such peers are kept once, in the order they were first returned, along with every
+/// tracker that returned them.
+#[derive(Debug, Clone, Default)]
+pub struct DiscoveredPeers {
+    peers: IndexMap<SocketAddr, DiscoveredPeer>,
+}
+
+impl DiscoveredPeers {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Adds the peers returned by the tracker. Returns the number of peers which were not known
+    /// yet.
+    pub fn add<I>(&mut self, tracker: &str, peers: I) -> usize
+    where
+        I: IntoIterator<Item = TrackerPeer>,
+    {
+        let tracker: Arc<str> = Arc::from(tracker);
+        let before = self.peers.len();
+
+        for peer in peers {
+            let addr = SocketAddr::new(peer.addr.ip().to_canonical(), peer.addr.port());
+            let entry = self.peers.entry(addr).or_insert_with(|| DiscoveredPeer {
+                addr,
+                peer_id: None,
+                trackers: Vec::new(),
+            });
+            entry.peer_id = entry.peer_id.or(peer.peer_id);
+            if !entry.trackers.contains(&tracker) {
+                entry.trackers.push(Arc::clone(&tracker));
+            }
+        }
+
+        self.peers.len() - before
+    }
+
+    /// Sets the peer id of a known peer, e.g. once the handshake with it is completed.
+    pub fn set_peer_id(&mut self, addr: SocketAddr, peer_id: [u8; 20]) {
+        let addr = SocketAddr::new(addr.ip().to_canonical(), addr.port());
+        if let Some(peer) = self.peers.get_mut(&addr) {
+            peer.peer_id = Some(peer_id);
+        }
+    }
+
+    pub fn get(&self, addr: &SocketAddr) -> Option<&DiscoveredPeer> {
+        self.peers.get(addr)
+    }
+
+    pub fn iter(&self) -> impl Iterator<Item = &DiscoveredPeer> {
+        self.peers.values()
+    }
+
+    pub fn addrs(&self) -> impl Iterator<Item = SocketAddr> + '_ {
+        self.peers.keys().copied()
+    }
+
+    pub fn len(&self) -> usize {
+        self.peers.len()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.peers.is_empty()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn peer(addr: &str) -> TrackerPeer {
+        TrackerPeer {
+            addr: addr.parse().unwrap(),
+            peer_id: None,
+        }
+    }
+
+    #[test]
+    fn test_peers_are_deduplicated() {
+        let mut peers = DiscoveredPeers::new();
+        assert_eq!(
+            peers.add("http://a/announce", [peer("1.1.1.1:1"), peer("2.2.2.2:2")]),
+            2
+        );
+
+        let mut with_id = peer("[::ffff:1.1.1.1]:1");
+        with_id.peer_id = Some([7; 20]);
+        assert_eq!(
+            peers.add("http://b/announce", [with_id, peer("1.1.1.1:3")]),
+            1
+        );
+        assert_eq!(peers.len(), 3);
+
+        let first = peers.get(&"1.1.1.1:1".parse().unwrap()).unwrap();
+        assert_eq!(first.peer_id, Some([7; 20]));
+        assert_eq!(
+            first.trackers,
+            [
+                Arc::from("http://a/announce"),
+                Arc::from("http://b/announce")
+            ]
+        );
+
+        peers.set_peer_id("2.2.2.2:2".parse().unwrap(), [9; 20]);
+        let addrs: Vec<_> = peers.iter().map(|peer| peer.peer_id).collect();
+        assert_eq!(addrs, [Some([7; 20]), Some([9; 20]), None]);
+    }
+}
@@ -1,24 +1,75 @@
-use std::ops::Deref;
+use std::ops::{Deref, Range};
+use std::sync::Arc;
 
+use anyhow::{Context, Result};
+use bytes::Bytes;
+
+use super::{SourceKind, SourceList, SourceRow};
 use crate::meta_info::{FileAttr, Files, MetaInfo};
+use crate::net::HttpClient;
 
+/// The web seeds of a torrent along with the `url-list` entry each of them was built from.
+///
+/// The list owns its urls and does not borrow the [`MetaInfo`], so it can be stored alongside the
+/// client or moved into spawned tasks. Cloning it is cheap as the urls are reference counted.
 #[derive(Debug, Clone)]
-pub struct HttpSeederList<'a> {
-    http_seeder_list: Vec<(&'a str, HttpSeeder)>,
+pub struct HttpSeederList {
+    http_seeder_list: Vec<(Arc<str>, HttpSeeder)>,
 }
 
-impl<'a> HttpSeederList<'a> {
-    pub fn new(http_seeder_list: Vec<(&'a str, HttpSeeder)>) -> Self {
+impl HttpSeederList {
+    pub fn new(http_seeder_list: Vec<(Arc<str>, HttpSeeder)>) -> Self {
         Self { http_seeder_list }
     }
 
-    pub fn http_seeder_list(&self) -> &[(&'a str, HttpSeeder)] {
+    pub fn http_seeder_list(&self) -> &[(Arc<str>, HttpSeeder)] {
         &self.http_seeder_list
     }
+
+    /// Appends a web seed unless a seeder with the same url is already in the list. Returns
+    /// `true` if the seeder was added.
+    pub fn push(&mut self, url: Arc<str>, seeder: HttpSeeder) -> bool {
+        if self
+            .http_seeder_list
+            .iter()
+            .any(|(existing, _)| *existing == url)
+        {
+            false
+        } else {
+            self.http_seeder_list.push((url, seeder));
+            true
+        }
+    }
+}
+
+impl SourceList for HttpSeederList {
+    type Item = (Arc<str>, HttpSeeder);
+
+    fn len(&self) -> usize {
+        self.http_seeder_list.len()
+    }
+
+    fn iter(&self) -> std::slice::Iter<'_, Self::Item> {
+        self.http_seeder_list.iter()
+    }
+
+    fn kind(&self) -> SourceKind {
+        SourceKind::HttpSeeders
+    }
+
+    fn display_rows(&self) -> Vec<SourceRow> {
+        self.http_seeder_list
+            .iter()
+            .map(|(url, seeder)| SourceRow {
+                source: url.to_string(),
+                details: seeder.urls().to_vec(),
+            })
+            .collect()
+    }
 }
 
-impl<'a> Deref for HttpSeederList<'a> {
-    type Target = [(&'a str, HttpSeeder)];
+impl Deref for HttpSeederList {
+    type Target = [(Arc<str>, HttpSeeder)];
 
     fn deref(&self) -> &Self::Target {
         self.http_seeder_list()
@@ -26,18 +77,19 @@ impl<'a> Deref for HttpSeederList<'a> {
 }
 
 // Iterator implementation
-impl<'a> IntoIterator for &'a HttpSeederList<'a> {
-    type Item = &'a (&'a str, HttpSeeder);
-    type IntoIter = std::slice::Iter<'a, (&'a str, HttpSeeder)>;
+impl<'a> IntoIterator for &'a HttpSeederList {
+    type Item = &'a (Arc<str>, HttpSeeder);
+    type IntoIter = std::slice::Iter<'a, (Arc<str>, HttpSeeder)>;
 
     fn into_iter(self) -> Self::IntoIter {
         self.http_seeder_list.iter()
     }
 }
 
+/// The urls of the files of a torrent on a single web seed.
 #[derive(Debug, Clone)]
 pub struct HttpSeeder {
-    urls: Vec<String>,
+    urls: Arc<[String]>,
 }
 
 impl Deref for HttpSeeder {
@@ -63,12 +115,14 @@ impl HttpSeeder {
         let name = meta_info.info().name();
         match &meta_info.info().files {
             Files::SingleFile { attr, .. } => {
-                if let Some(FileAttr::Padding) = attr {
-                    HttpSeeder { urls: Vec::new() }
+                if attr.as_ref().is_some_and(FileAttr::is_padding_file) {
+                    HttpSeeder { urls: Arc::new([]) }
                 } else {
                     let mut url = base_url.to_string();
                     url.push_str(name);
-                    HttpSeeder { urls: vec![url] }
+                    HttpSeeder {
+                        urls: Arc::new([url]),
+                    }
                 }
             }
             Files::MultiFile { files } => {
@@ -92,7 +146,7 @@ impl HttpSeeder {
                         urls.push(url);
                     }
                 }
-                HttpSeeder { urls }
+                HttpSeeder { urls: urls.into() }
             }
         }
     }
@@ -100,4 +154,21 @@ impl HttpSeeder {
     pub fn urls(&self) -> &[String] {
         &self.urls
     }
+
+    /// Downloads the provided range of bytes of the file at `index` in [`HttpSeeder::urls`]
+    /// through the [`HttpClient::shared`] client.
+    pub async fn fetch_range(&self, index: usize, range: Range<usize>) -> Result<Bytes> {
+        let url = self
+            .urls
+            .get(index)
+            .with_context(|| format!("The web seed has no file at index {index}"))?;
+        let response = HttpClient::shared()
+            .get_range(url, range)
+            .await?
+            .error_for_status(url)?;
+
+        #[cfg(feature = "metrics")]
+        crate::metrics::Metrics::global().record_downloaded(response.body.len());
+        Ok(response.body)
+    }
 }
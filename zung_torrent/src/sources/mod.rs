@@ -7,75 +7,143 @@
 
 use crate::{
     meta_info::{InfoHashEncoded, MetaInfo},
-    PeerID,
+    PeerID, Progress,
 };
 
-use anyhow::Result;
 use futures::stream::FuturesUnordered;
+use std::fmt::Display;
+use std::sync::Arc;
 use tokio::task::JoinHandle;
 
+mod announcer;
+mod combined;
+mod discovered;
 mod http_seeders;
+mod tracker_stats;
 mod trackers;
 
+pub use announcer::{AnnounceState, Announcer};
+pub use combined::{DhtNode, Sources};
+pub use discovered::{DiscoveredPeer, DiscoveredPeers};
 pub use http_seeders::{HttpSeeder, HttpSeederList};
-pub use trackers::{Action, Event, Tracker, TrackerList, TrackerRequest};
+pub use tracker_stats::{TrackerRecord, TrackerStats};
+pub use trackers::{
+    Action, AnnounceKey, AnnounceOptions, Event, HttpTrackerRequestParams, ScrapeStats, Tracker,
+    TrackerError, TrackerList, TrackerOutcome, TrackerPeer, TrackerRequest, TrackerResponse,
+    DEFAULT_NUMWANT, MAX_PARALLEL_REQUESTS, TIMEOUT_DURATION, UDP_CONNECTION_LIFETIME,
+};
+
+/// The kind of sources contained in a [`SourceList`].
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum SourceKind {
+    /// Trackers as contained in the `announce` and `announce-list` keys.
+    Trackers,
+
+    /// HTTP/FTP seeders as contained in the `url-list` key.
+    HttpSeeders,
+}
+
+impl Display for SourceKind {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            SourceKind::Trackers => write!(f, "Trackers"),
+            SourceKind::HttpSeeders => write!(f, "HTTP Seeders"),
+        }
+    }
+}
+
+/// A single printable row of a [`SourceList`].
+///
+/// `source` is the main entry of the row (such as the tracker or the seeder url) and `details`
+/// contains any additional lines to be displayed under it.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct SourceRow {
+    pub source: String,
+    pub details: Vec<String>,
+}
+
+/// Common interface over the lists of download sources such as the [`TrackerList`] and the
+/// [`HttpSeederList`].
+///
+/// This allows the lists to be processed and rendered generically.
+///
+/// # Example
+///
+/// ```
+/// use zung_torrent::sources::SourceList;
+///
+/// fn print_list<S: SourceList>(list: &S) {
+///     println!("{} ({}):", list.kind(), list.len());
+///     for row in list.display_rows() {
+///         println!("{}", row.source);
+///     }
+/// }
+/// ```
+pub trait SourceList {
+    /// Type of the individual sources in the list.
+    type Item;
+
+    /// Returns the number of sources in the list.
+    fn len(&self) -> usize;
+
+    /// Returns `true` if the list contains no sources.
+    fn is_empty(&self) -> bool {
+        self.len() == 0
+    }
+
+    /// Returns an iterator over the sources in the list.
+    fn iter(&self) -> std::slice::Iter<'_, Self::Item>;
+
+    /// Returns the [`SourceKind`] of the list.
+    fn kind(&self) -> SourceKind;
+
+    /// Returns the rows to be displayed for the list, one per source.
+    fn display_rows(&self) -> Vec<SourceRow>;
+}
 
 /// Representing different data sources (trackers and HTTP seeders) for a torrent.
 ///
 /// This enum is constructed with the [`sources`](crate::Client::sources) method.
 #[derive(Debug, Clone)]
-pub enum DownloadSources<'a> {
+pub enum DownloadSources {
     /// Genarated if only `announce` or `announce_list` keys are specified in the [`MetaInfo`]
     /// file.
     Trackers { tracker_list: TrackerList },
 
     /// Genarated if only `url_list` key is specified in the [`MetaInfo`] file.
-    HttpSeeders {
-        http_seeder_list: HttpSeederList<'a>,
-    },
+    HttpSeeders { http_seeder_list: HttpSeederList },
 
     /// Genarated if both `announce` / `announce_list` and `url_list` keys are specified in the
     /// [`MetaInfo`] file.
     Hybrid {
         tracker_list: TrackerList,
-        http_seeder_list: HttpSeederList<'a>,
+        http_seeder_list: HttpSeederList,
     },
 }
 
-impl<'a> DownloadSources<'a> {
-    pub fn new(meta_info: &'a MetaInfo) -> Self {
+impl DownloadSources {
+    pub fn new(meta_info: &MetaInfo) -> Self {
         fn tracker_list(meta_info: &MetaInfo) -> TrackerList {
-            // As per the torrent specification, if the `announce_list` field is present, the
-            // `announce` field is ignored.
-            if let Some(announce_list) = meta_info.announce_list() {
-                let mut tracker_list = Vec::new();
-                for tracker_url in announce_list.iter().flatten() {
-                    tracker_list.push(Tracker::new(tracker_url));
-                }
-                TrackerList::new(tracker_list)
-            } else if let Some(announce) = meta_info.announce() {
-                TrackerList::new(vec![Tracker::new(announce)])
-            } else {
-                unreachable!()
-            }
+            TrackerList::new(
+                meta_info
+                    .all_trackers()
+                    .map(|(_, url)| Tracker::new(url))
+                    .collect(),
+            )
         }
 
-        fn http_seeder_list<'a>(
-            url_list: &'a Vec<String>,
-            meta_info: &'a MetaInfo,
-        ) -> HttpSeederList<'a> {
-            let mut list = Vec::with_capacity(url_list.len());
-            for url in url_list {
-                if !url.is_empty() {
-                    list.push((url.as_str(), HttpSeeder::new(url, meta_info)));
-                }
-            }
-            HttpSeederList::new(list)
+        fn http_seeder_list(meta_info: &MetaInfo) -> HttpSeederList {
+            HttpSeederList::new(
+                meta_info
+                    .url_list()
+                    .map(|url| (Arc::from(url), HttpSeeder::new(url, meta_info)))
+                    .collect(),
+            )
         }
 
-        if let Some(url_list) = meta_info.url_list() {
+        if meta_info.raw_url_list().is_some() {
             if meta_info.announce.is_some() || meta_info.announce_list.is_some() {
-                let http_seeder_list = http_seeder_list(url_list, meta_info);
+                let http_seeder_list = http_seeder_list(meta_info);
                 if http_seeder_list.is_empty() {
                     return Self::Trackers {
                         tracker_list: tracker_list(meta_info),
@@ -87,7 +155,7 @@ impl<'a> DownloadSources<'a> {
                 }
             } else {
                 Self::HttpSeeders {
-                    http_seeder_list: http_seeder_list(url_list, meta_info),
+                    http_seeder_list: http_seeder_list(meta_info),
                 }
             }
         } else {
@@ -97,6 +165,29 @@ impl<'a> DownloadSources<'a> {
         }
     }
 
+    /// Adds the trackers to the list of trackers, skipping the duplicates. Sources with only HTTP
+    /// seeders become [`Hybrid`](DownloadSources::Hybrid).
+    pub fn add_trackers(&mut self, urls: &[String]) {
+        if urls.is_empty() {
+            return;
+        }
+        if let Self::HttpSeeders { http_seeder_list } = self {
+            *self = Self::Hybrid {
+                tracker_list: TrackerList::new(Vec::new()),
+                http_seeder_list: std::mem::replace(
+                    http_seeder_list,
+                    HttpSeederList::new(Vec::new()),
+                ),
+            };
+        }
+        let (Self::Trackers { tracker_list } | Self::Hybrid { tracker_list, .. }) = self else {
+            unreachable!("Converted above")
+        };
+        for url in urls {
+            tracker_list.push(Tracker::new(url));
+        }
+    }
+
     /// Returns a reference to the list of trackers, if available.
     ///
     /// # Example
@@ -189,15 +280,29 @@ impl<'a> DownloadSources<'a> {
         matches!(self, Self::Hybrid { .. })
     }
 
+    /// Generates the [`TrackerRequest`]s for all the trackers (if any) using the default
+    /// [`AnnounceOptions`] for the progress. See [`TrackerList::generate_requests_with`].
     pub fn tracker_requests(
         &self,
         info_hash: InfoHashEncoded,
         peer_id: PeerID,
-    ) -> Option<FuturesUnordered<JoinHandle<Result<TrackerRequest>>>> {
+        progress: Progress,
+    ) -> Option<FuturesUnordered<JoinHandle<TrackerOutcome>>> {
+        self.tracker_requests_with(info_hash, peer_id, AnnounceOptions::new(progress))
+    }
+
+    /// Generates the [`TrackerRequest`]s for all the trackers (if any) with the provided
+    /// [`AnnounceOptions`]. See [`TrackerList::generate_requests_with`].
+    pub fn tracker_requests_with(
+        &self,
+        info_hash: InfoHashEncoded,
+        peer_id: PeerID,
+        options: AnnounceOptions,
+    ) -> Option<FuturesUnordered<JoinHandle<TrackerOutcome>>> {
         match self {
             DownloadSources::Trackers { tracker_list }
             | DownloadSources::Hybrid { tracker_list, .. } => {
-                Some(tracker_list.generate_requests(info_hash, peer_id))
+                Some(tracker_list.generate_requests_with(info_hash, peer_id, options))
             }
             DownloadSources::HttpSeeders { .. } => None,
         }
@@ -0,0 +1,314 @@
+//! Historical reliability of trackers.
+//!
+//! Every announce attempt can be recorded in [`TrackerStats`], which keeps a [`TrackerRecord`]
+//! per tracker host. The stats are persisted on disk as a bencoded dictionary and are used to
+//! contact the most reliable trackers first (see [`TrackerList::sort_by_stats`]). The stats also
+//! remember the trackers which refuse compact responses (see
+//! [`TrackerList::use_compact_preferences`]).
+//!
+//! [`TrackerList::sort_by_stats`]: super::TrackerList::sort_by_stats
+//! [`TrackerList::use_compact_preferences`]: super::TrackerList::use_compact_preferences
+
+use std::cmp::Reverse;
+use std::collections::{BTreeMap, BTreeSet};
+use std::io::ErrorKind;
+use std::path::{Path, PathBuf};
+use std::time::Duration;
+
+use anyhow::{Context, Result};
+use chrono::{DateTime, Utc};
+use serde::{Deserialize, Serialize};
+use zung_parsers::bencode;
+
+use super::trackers::normalize_url;
+use super::{Tracker, TrackerOutcome};
+
+/// Name of the file in which the stats are stored inside the data directory.
+const STATS_FILE_NAME: &str = "tracker_stats.bencode";
+
+/// Per-host history of announce attempts.
+#[derive(Debug, Default, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct TrackerStats {
+    hosts: BTreeMap<String, TrackerRecord>,
+
+    /// Normalized announce urls of the trackers which refuse compact responses. Kept per url
+    /// rather than per host as the trackers of a host do not have to behave alike.
+    #[serde(default, skip_serializing_if = "BTreeSet::is_empty")]
+    no_compact: BTreeSet<String>,
+}
+
+/// The history of a single tracker host.
+#[derive(Debug, Default, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct TrackerRecord {
+    /// Number of successful announce attempts.
+    pub successes: u64,
+
+    /// Number of failed announce attempts.
+    pub failures: u64,
+
+    /// Sum of the round trip times (in milliseconds) of the successful attempts.
+    pub total_rtt_ms: u64,
+
+    /// Unix timestamp of the last failure.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub last_failure: Option<i64>,
+
+    /// The error of the last failure.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub last_failure_reason: Option<String>,
+}
+
+impl TrackerRecord {
+    /// Average round trip time of the successful attempts.
+    pub fn average_rtt(&self) -> Option<Duration> {
+        self.total_rtt_ms
+            .checked_div(self.successes)
+            .map(Duration::from_millis)
+    }
+
+    /// Fraction of the attempts that succeeded, between `0.0` and `1.0`.
+    pub fn success_rate(&self) -> f64 {
+        let attempts = self.successes + self.failures;
+        if attempts == 0 {
+            0.0
+        } else {
+            self.successes as f64 / attempts as f64
+        }
+    }
+
+    /// Time of the last failure, if any.
+    pub fn last_failure_time(&self) -> Option<DateTime<Utc>> {
+        self.last_failure
+            .and_then(|secs| DateTime::from_timestamp(secs, 0))
+    }
+}
+
+impl TrackerStats {
+    /// The default location of the stats file, inside the user's data directory.
+    pub fn default_path() -> Option<PathBuf> {
+        dirs::data_dir().map(|dir| dir.join("zung").join(STATS_FILE_NAME))
+    }
+
+    /// Loads the stats from the provided path. A missing file is not an error and results in
+    /// empty stats.
+    pub fn load<P: AsRef<Path>>(path: P) -> Result<Self> {
+        let path = path.as_ref();
+        match std::fs::read(path) {
+            Ok(bytes) => bencode::from_bytes(&bytes)
+                .with_context(|| format!("Invalid tracker stats file: {}", path.display())),
+            Err(e) if e.kind() == ErrorKind::NotFound => Ok(Self::default()),
+            Err(e) => Err(e).with_context(|| format!("Unable to read file: {}", path.display())),
+        }
+    }
+
+    /// Saves the stats to the provided path, creating the parent directories if required.
+    pub fn save<P: AsRef<Path>>(&self, path: P) -> Result<()> {
+        let path = path.as_ref();
+        if let Some(parent) = path.parent() {
+            std::fs::create_dir_all(parent)
+                .with_context(|| format!("Unable to create directory: {}", parent.display()))?;
+        }
+        let bytes = bencode::to_bytes(self)?;
+        std::fs::write(path, bytes)
+            .with_context(|| format!("Unable to write file: {}", path.display()))
+    }
+
+    /// Records the result of an announce attempt against the host of the tracker.
+    ///
+    /// Only the announces answered by the tracker count as successes, see
+    /// [`TrackerList::announce_with`](super::TrackerList::announce_with). An outcome of generating
+    /// the request alone, e.g. the connect exchange of a UDP tracker, says nothing about the
+    /// announces and is ignored. So are the invalid trackers (without a host).
+    pub fn record(&mut self, outcome: &TrackerOutcome) {
+        let Some(host) = outcome.tracker.host() else {
+            return;
+        };
+        if outcome.result.is_ok() && outcome.response.is_none() {
+            return;
+        }
+
+        let record = self.hosts.entry(host.to_lowercase()).or_default();
+        match &outcome.result {
+            Ok(_) => {
+                record.successes += 1;
+                record.total_rtt_ms += outcome.elapsed.as_millis() as u64;
+            }
+            Err(e) => {
+                record.failures += 1;
+                record.last_failure = Some(Utc::now().timestamp());
+                record.last_failure_reason = Some(e.to_string());
+            }
+        }
+    }
+
+    /// Records whether the tracker accepts compact responses, as found out by
+    /// [`TrackerRequest::announce`](super::TrackerRequest::announce).
+    pub fn record_compact(&mut self, tracker: &Tracker, compact: bool) {
+        let url = normalize_url(tracker.url());
+        if compact {
+            self.no_compact.remove(&url);
+        } else {
+            self.no_compact.insert(url);
+        }
+    }
+
+    /// Returns `false` if the tracker is known to refuse compact responses.
+    pub fn prefers_compact(&self, tracker: &Tracker) -> bool {
+        !self.no_compact.contains(&normalize_url(tracker.url()))
+    }
+
+    /// Returns the record of the provided host, if any.
+    pub fn get(&self, host: &str) -> Option<&TrackerRecord> {
+        self.hosts.get(&host.to_lowercase())
+    }
+
+    /// Iterates over the hosts and their records, sorted by the host.
+    pub fn iter(&self) -> impl Iterator<Item = (&str, &TrackerRecord)> {
+        self.hosts
+            .iter()
+            .map(|(host, record)| (host.as_str(), record))
+    }
+
+    /// Returns the number of hosts with a record.
+    pub fn len(&self) -> usize {
+        self.hosts.len()
+    }
+
+    /// Returns `true` if no host has a record.
+    pub fn is_empty(&self) -> bool {
+        self.hosts.is_empty()
+    }
+
+    // Sort key for the tracker. Reliable hosts come first (fastest first), then the hosts without
+    // any history and finally the hosts that fail more often than not.
+    pub(crate) fn rank(&self, tracker: &Tracker) -> (u8, Reverse<u64>, u64) {
+        match tracker.host().and_then(|host| self.get(host)) {
+            Some(record) => {
+                let rate = (record.success_rate() * 1000.0) as u64;
+                let rtt = record
+                    .average_rtt()
+                    .map_or(u64::MAX, |d| d.as_millis() as u64);
+                let class = if rate >= 500 { 0 } else { 2 };
+                (class, Reverse(rate), rtt)
+            }
+            None => (1, Reverse(0), u64::MAX),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::meta_info::InfoHash;
+    use crate::sources::{HttpTrackerRequestParams, TrackerList, TrackerRequest, TrackerResponse};
+    use crate::{PeerID, Progress};
+    use anyhow::anyhow;
+
+    fn outcome(url: &str, ok: bool, elapsed_ms: u64) -> TrackerOutcome {
+        TrackerOutcome {
+            tracker: Tracker::new(url),
+            fallback: None,
+            result: if ok {
+                Ok(TrackerRequest::Http {
+                    url: url.into(),
+                    params: HttpTrackerRequestParams::new(
+                        InfoHash::new(b"test").as_encoded(),
+                        PeerID::default(),
+                        Progress {
+                            uploaded: 0,
+                            downloaded: 0,
+                            left: 10,
+                        },
+                    ),
+                })
+            } else {
+                Err(anyhow!("Timed out: {url}"))
+            },
+            response: ok.then(TrackerResponse::default),
+            elapsed: Duration::from_millis(elapsed_ms),
+        }
+    }
+
+    #[test]
+    fn test_record_and_roundtrip() {
+        let mut stats = TrackerStats::default();
+        stats.record(&outcome("udp://Fast.example.com:1337/announce", true, 100));
+        stats.record(&outcome("http://fast.example.com/announce", true, 300));
+        stats.record(&outcome("udp://dead.example.com:80", false, 10_000));
+
+        let fast = stats.get("fast.example.com").unwrap();
+        assert_eq!(fast.successes, 2);
+        assert_eq!(fast.average_rtt(), Some(Duration::from_millis(200)));
+        assert!(fast.last_failure.is_none());
+
+        let dead = stats.get("dead.example.com").unwrap();
+        assert_eq!(dead.failures, 1);
+        assert_eq!(dead.average_rtt(), None);
+        assert!(dead.last_failure_time().is_some());
+
+        let dir = std::env::temp_dir().join(format!("zung-tracker-stats-{}", std::process::id()));
+        let path = dir.join(STATS_FILE_NAME);
+        stats.save(&path).unwrap();
+        assert_eq!(TrackerStats::load(&path).unwrap(), stats);
+        std::fs::remove_dir_all(dir).unwrap();
+
+        assert!(TrackerStats::load("/does/not/exist").unwrap().is_empty());
+    }
+
+    #[test]
+    fn test_record_ignores_requests_without_announce() {
+        let mut connected = outcome("udp://connected.example.com:1337", true, 100);
+        connected.response = None;
+
+        let mut stats = TrackerStats::default();
+        stats.record(&connected);
+        assert!(stats.get("connected.example.com").is_none());
+    }
+
+    #[test]
+    fn test_sort_by_stats() {
+        let mut stats = TrackerStats::default();
+        stats.record(&outcome("udp://slow.example.com", true, 900));
+        stats.record(&outcome("udp://fast.example.com", true, 50));
+        stats.record(&outcome("udp://dead.example.com", false, 0));
+
+        let mut list = TrackerList::new(vec![
+            Tracker::new("udp://dead.example.com"),
+            Tracker::new("udp://new.example.com"),
+            Tracker::new("udp://slow.example.com"),
+            Tracker::new("udp://fast.example.com"),
+        ]);
+        list.sort_by_stats(&stats);
+
+        let hosts: Vec<_> = list.iter().filter_map(Tracker::host).collect();
+        assert_eq!(
+            hosts,
+            [
+                "fast.example.com",
+                "slow.example.com",
+                "new.example.com",
+                "dead.example.com"
+            ]
+        );
+    }
+
+    #[test]
+    fn test_record_compact() {
+        let tracker = Tracker::new("http://Tracker.example.com/announce");
+        let mut stats = TrackerStats::default();
+        assert!(stats.prefers_compact(&tracker));
+
+        stats.record_compact(&tracker, false);
+        assert!(!stats.prefers_compact(&Tracker::new("HTTP://tracker.example.com/announce")));
+        // The other trackers of the host are not affected.
+        assert!(stats.prefers_compact(&Tracker::new("http://tracker.example.com/other/announce")));
+        assert!(stats.prefers_compact(&Tracker::new("udp://tracker.example.com:80")));
+
+        let bytes = bencode::to_bytes(&stats).unwrap();
+        let mut stats: TrackerStats = bencode::from_bytes(&bytes).unwrap();
+        assert!(!stats.prefers_compact(&tracker));
+
+        stats.record_compact(&tracker, true);
+        assert!(stats.prefers_compact(&tracker));
+    }
+}
@@ -0,0 +1,216 @@
+//! The state of a [`TorrentSession`](crate::TorrentSession) saved between the runs of zung.
+
+use std::io::ErrorKind;
+use std::path::{Path, PathBuf};
+
+use anyhow::{Context, Result};
+use serde::{Deserialize, Serialize};
+
+use crate::{
+    sources::AnnounceKey,
+    storage::{FileAction, FileRule},
+    DownloadOptions,
+};
+
+const STATE_FILE_NAME: &str = "session.json";
+
+/// The saved state of a session: its torrents and its download options.
+#[derive(Debug, Default, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub(crate) struct SessionState {
+    pub(crate) options: SavedOptions,
+    pub(crate) torrents: Vec<SavedTorrent>,
+}
+
+/// A torrent of the session.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub(crate) struct SavedTorrent {
+    pub(crate) file: PathBuf,
+
+    /// The hex encoded info hash, to detect the torrent files replaced since.
+    pub(crate) info_hash: String,
+    pub(crate) announce_key: AnnounceKey,
+
+    /// The trackers added to the ones of the torrent file.
+    #[serde(default)]
+    pub(crate) trackers: Vec<String>,
+
+    /// Bytes transferred over all the sessions.
+    #[serde(default)]
+    pub(crate) downloaded: u64,
+    #[serde(default)]
+    pub(crate) uploaded: u64,
+}
+
+/// The [`DownloadOptions`], except the `force` override which only applies to one download.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub(crate) struct SavedOptions {
+    max_peers: usize,
+    numwant: usize,
+    download_dir: PathBuf,
+    flatten_root: bool,
+
+    /// The pattern of each rule with the replacement of the renames.
+    file_rules: Vec<(String, Option<String>)>,
+
+    /// The rate schedules, as parsed by [`RateSchedule`](crate::net::rate::RateSchedule), e.g.
+    /// `09:00-18:00=1MiB`. They can be edited in the state file.
+    #[serde(default)]
+    download_rate: String,
+    #[serde(default)]
+    upload_rate: String,
+
+    /// The commands and webhooks run when a torrent completes.
+    #[serde(default)]
+    on_complete: Vec<String>,
+
+    endgame_threshold: usize,
+}
+
+impl Default for SavedOptions {
+    fn default() -> Self {
+        Self::from(&DownloadOptions::default())
+    }
+}
+
+impl From<&DownloadOptions> for SavedOptions {
+    fn from(options: &DownloadOptions) -> Self {
+        Self {
+            max_peers: options.max_peers,
+            numwant: options.numwant,
+            download_dir: options.download_dir.clone(),
+            flatten_root: options.flatten_root,
+            file_rules: options
+                .file_rules
+                .iter()
+                .map(|rule| {
+                    let replacement = match rule.action() {
+                        FileAction::Rename(replacement) => Some(replacement.clone()),
+                        FileAction::Skip => None,
+                    };
+                    (rule.pattern().to_string(), replacement)
+                })
+                .collect(),
+            download_rate: options.download_rate.to_string(),
+            upload_rate: options.upload_rate.to_string(),
+            on_complete: options
+                .on_complete
+                .iter()
+                .map(ToString::to_string)
+                .collect(),
+            endgame_threshold: options.endgame_threshold,
+        }
+    }
+}
+
+impl TryFrom<SavedOptions> for DownloadOptions {
+    type Error = anyhow::Error;
+
+    fn try_from(saved: SavedOptions) -> Result<Self> {
+        let file_rules = saved
+            .file_rules
+            .iter()
+            .map(|(pattern, replacement)| match replacement {
+                Some(replacement) => FileRule::rename(pattern, replacement),
+                None => FileRule::skip(pattern),
+            })
+            .collect::<Result<_>>()?;
+        Ok(Self {
+            max_peers: saved.max_peers,
+            numwant: saved.numwant,
+            download_dir: saved.download_dir,
+            flatten_root: saved.flatten_root,
+            file_rules,
+            download_rate: saved.download_rate.parse()?,
+            upload_rate: saved.upload_rate.parse()?,
+            on_complete: saved
+                .on_complete
+                .iter()
+                .map(|hook| hook.parse())
+                .collect::<Result<_>>()?,
+            endgame_threshold: saved.endgame_threshold,
+            ..Default::default()
+        })
+    }
+}
+
+impl SessionState {
+    /// The path of the state file in the data directory of the user, if there is one.
+    pub(crate) fn default_path() -> Option<PathBuf> {
+        dirs::data_dir().map(|dir| dir.join("zung").join(STATE_FILE_NAME))
+    }
+
+    /// Loads the state from the provided path. A missing file is not an error and results in an
+    /// empty state.
+    pub(crate) fn load(path: &Path) -> Result<Self> {
+        match std::fs::read(path) {
+            Ok(bytes) => serde_json::from_slice(&bytes)
+                .with_context(|| format!("Invalid session state file: {}", path.display())),
+            Err(e) if e.kind() == ErrorKind::NotFound => Ok(Self::default()),
+            Err(e) => Err(e).with_context(|| format!("Unable to read file: {}", path.display())),
+        }
+    }
+
+    /// Saves the state to the provided path, creating the parent directories if required. The
+    /// file is replaced atomically, so a crash while saving keeps the previous state.
+    pub(crate) fn save(&self, path: &Path) -> Result<()> {
+        if let Some(parent) = path.parent() {
+            std::fs::create_dir_all(parent)
+                .with_context(|| format!("Unable to create directory: {}", parent.display()))?;
+        }
+        let temporary = path.with_extension("json.tmp");
+        std::fs::write(&temporary, serde_json::to_vec_pretty(self)?)
+            .with_context(|| format!("Unable to write file: {}", temporary.display()))?;
+        std::fs::rename(&temporary, path)
+            .with_context(|| format!("Unable to write file: {}", path.display()))
+    }
+}
+
+/// What happened to a torrent of the saved session when it was resumed by
+/// [`TorrentSession::resume`](crate::TorrentSession::resume).
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum Resumed {
+    /// The torrent, with this info hash, is back in the session.
+    Added { info_hash: String },
+
+    /// The torrent, with this info hash, was already in the session. It may have been added from
+    /// another file.
+    AlreadyAdded { info_hash: String },
+
+    /// The torrent file no longer exists. The torrent is dropped from the session.
+    Missing,
+
+    /// The torrent file can not be read or is not a valid torrent anymore, with this error. The
+    /// torrent is dropped from the session.
+    Unreadable { error: String },
+
+    /// The torrent file was replaced by another torrent, with this info hash. The torrent is
+    /// dropped from the session.
+    Replaced { info_hash: String },
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_options_round_trip() {
+        let options = DownloadOptions {
+            download_dir: PathBuf::from("/downloads"),
+            flatten_root: true,
+            file_rules: vec![
+                FileRule::skip(r"\.nfo$").unwrap(),
+                FileRule::rename("^CD1/", "").unwrap(),
+            ],
+            force: true,
+            download_rate: "09:00-18:00=1MiB".parse().unwrap(),
+            on_complete: vec!["https://example.com/done".parse().unwrap()],
+            endgame_threshold: 4,
+            ..Default::default()
+        };
+        let saved = SavedOptions::from(&options);
+        let restored = DownloadOptions::try_from(saved.clone()).unwrap();
+        assert_eq!(SavedOptions::from(&restored), saved);
+        assert!(!restored.force);
+        assert_eq!(restored.endgame_threshold, 4);
+    }
+}
@@ -0,0 +1,184 @@
+use std::collections::HashSet;
+use std::path::{Component, Path, PathBuf};
+
+use anyhow::{bail, Context, Result};
+use regex::Regex;
+
+use crate::meta_info::FileSpan;
+
+/// What a [`FileRule`] does with the files it matches.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum FileAction {
+    /// Stores the file at the path given by replacing the match with the replacement, which can
+    /// refer to the groups of the pattern such as `$1` (see [`Regex::replace`]).
+    Rename(String),
+
+    /// Does not store the file.
+    Skip,
+}
+
+/// Renames or skips the files of a torrent whose path matches a regular expression.
+///
+/// The pattern is matched against the `/` separated path of the file relative to the torrent's
+/// directory, or the name of the file of a single file torrent.
+///
+/// # Example
+///
+/// ```
+/// use zung_torrent::storage::FileRule;
+///
+/// let rule = FileRule::rename(r"^Season (\d+)/", "S$1/").unwrap();
+/// assert_eq!(rule.apply("Season 1/e01.mkv"), Some(Some("S1/e01.mkv".to_string())));
+///
+/// let rule = FileRule::skip(r"\.nfo$").unwrap();
+/// assert_eq!(rule.apply("info.nfo"), Some(None));
+/// assert_eq!(rule.apply("e01.mkv"), None);
+/// ```
+#[derive(Debug, Clone)]
+pub struct FileRule {
+    pattern: Regex,
+    action: FileAction,
+}
+
+impl FileRule {
+    /// A rule renaming the files matching the pattern. See [`FileAction::Rename`].
+    pub fn rename(pattern: &str, replacement: impl Into<String>) -> Result<Self> {
+        Ok(Self {
+            pattern: Regex::new(pattern).with_context(|| format!("Invalid pattern {pattern:?}"))?,
+            action: FileAction::Rename(replacement.into()),
+        })
+    }
+
+    /// A rule skipping the files matching the pattern.
+    pub fn skip(pattern: &str) -> Result<Self> {
+        Ok(Self {
+            pattern: Regex::new(pattern).with_context(|| format!("Invalid pattern {pattern:?}"))?,
+            action: FileAction::Skip,
+        })
+    }
+
+    /// Returns the regular expression of the rule.
+    pub fn pattern(&self) -> &str {
+        self.pattern.as_str()
+    }
+
+    /// Returns the action of the rule.
+    pub fn action(&self) -> &FileAction {
+        &self.action
+    }
+
+    /// Applies the rule to the path. Returns `None` if the rule does not match, and otherwise
+    /// the new path, or `None` if the file is skipped.
+    pub fn apply(&self, path: &str) -> Option<Option<String>> {
+        if !self.pattern.is_match(path) {
+            return None;
+        }
+        match &self.action {
+            FileAction::Rename(replacement) => {
+                Some(Some(self.pattern.replace(path, replacement).into_owned()))
+            }
+            FileAction::Skip => Some(None),
+        }
+    }
+}
+
+/// Returns the path of each file under `root`, after applying the first matching rule. The
+/// padding files and the skipped files have no path.
+///
+/// Returns an error if a file is renamed to a path outside of `root`, or if two files end up at
+/// the same path.
+pub(super) fn lay_out(
+    spans: &[FileSpan],
+    root: &Path,
+    rules: &[FileRule],
+) -> Result<Vec<(FileSpan, Option<PathBuf>)>> {
+    let mut paths = HashSet::new();
+    let mut files = Vec::with_capacity(spans.len());
+
+    for span in spans {
+        if span.padding {
+            files.push((span.clone(), None));
+            continue;
+        }
+        let path = match rules.iter().find_map(|rule| rule.apply(&span.path)) {
+            None => Some(span.path.clone()),
+            Some(renamed) => renamed,
+        };
+        let Some(path) = path else {
+            files.push((span.clone(), None));
+            continue;
+        };
+
+        let relative = Path::new(&path);
+        let inside = relative
+            .components()
+            .all(|component| matches!(component, Component::Normal(_)));
+        if path.is_empty() || !inside {
+            bail!(
+                "Invalid path {path:?} for {} - The files must stay within the download directory",
+                span.path
+            )
+        }
+        let path = root.join(relative);
+        if !paths.insert(path.clone()) {
+            bail!("Two files would be stored at {}", path.display())
+        }
+        files.push((span.clone(), Some(path)));
+    }
+    Ok(files)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn spans(paths: &[&str]) -> Vec<FileSpan> {
+        paths
+            .iter()
+            .enumerate()
+            .map(|(i, path)| FileSpan {
+                path: path.to_string(),
+                offset: i,
+                length: 1,
+                padding: path.starts_with(".pad"),
+                sha1: None,
+                executable: false,
+                symlink: None,
+            })
+            .collect()
+    }
+
+    #[test]
+    fn test_lay_out() {
+        let rules = [
+            FileRule::skip(r"(?i)\.txt$").unwrap(),
+            FileRule::rename(r"^CD(\d)/", "disc-$1/").unwrap(),
+        ];
+        let files = lay_out(
+            &spans(&["CD1/a.mp3", ".pad/1", "CD2/b.mp3", "README.TXT"]),
+            Path::new("/music"),
+            &rules,
+        )
+        .unwrap();
+        let paths: Vec<_> = files.into_iter().map(|(_, path)| path).collect();
+        assert_eq!(
+            paths,
+            [
+                Some(PathBuf::from("/music/disc-1/a.mp3")),
+                None,
+                Some(PathBuf::from("/music/disc-2/b.mp3")),
+                None
+            ]
+        );
+    }
+
+    #[test]
+    fn test_invalid_paths() {
+        let escape = [FileRule::rename("^", "../").unwrap()];
+        assert!(lay_out(&spans(&["a"]), Path::new("/dir"), &escape).is_err());
+
+        let collide = [FileRule::rename(r"\d", "").unwrap()];
+        let error = lay_out(&spans(&["a1", "a2"]), Path::new("/dir"), &collide).unwrap_err();
+        assert_eq!(error.to_string(), "Two files would be stored at /dir/a");
+    }
+}
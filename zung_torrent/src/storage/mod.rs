@@ -0,0 +1,388 @@
+//! For reading and writing the content of a torrent on the disk.
+//!
+//! The content of a torrent is one continuous stream of bytes cut in pieces, and stored as the
+//! files of the torrent (see [`FileSpan`]). [`Storage`] maps the pieces to the files and back,
+//! while [`WriteCache`] keeps the blocks of the pieces being downloaded in memory until they are
+//! complete, so that each piece is verified before it reaches the disk and written in one go.
+//! [`ReadCache`] keeps the pieces read for the peers in memory while seeding, and [`serve_file`]
+//! streams a file over HTTP as its pieces are verified.
+
+mod layout;
+mod read_cache;
+mod space;
+mod stream;
+mod write_cache;
+
+use std::fs::{File, OpenOptions};
+use std::io::{Read, Seek, SeekFrom, Write};
+use std::ops::Range;
+use std::path::{Path, PathBuf};
+
+use anyhow::{bail, Context, Result};
+
+use crate::meta_info::FileSpan;
+use crate::peers::Bitfield;
+
+pub use layout::{FileAction, FileRule};
+pub use read_cache::{ReadCache, DEFAULT_READ_AHEAD, DEFAULT_READ_CACHE_SIZE};
+pub use space::{available_space, InsufficientSpace};
+pub use stream::serve_file;
+pub use write_cache::{BlockAdded, WriteCache, DEFAULT_WRITE_CACHE_SIZE};
+
+/// The files of a torrent on the disk.
+///
+/// The files without a path, such as the padding files and the files skipped by a [`FileRule`],
+/// are never written nor read: their bytes read as zeros.
+#[derive(Debug, Clone)]
+pub struct Storage {
+    files: Vec<(FileSpan, Option<PathBuf>)>,
+    piece_length: usize,
+    total_length: usize,
+}
+
+impl Storage {
+    /// Creates the storage of the files, each with the path it is stored at.
+    pub fn new(files: Vec<(FileSpan, Option<PathBuf>)>, piece_length: usize) -> Self {
+        let total_length = files.last().map_or(0, |(span, _)| span.byte_range().end);
+        Self {
+            files,
+            piece_length,
+            total_length,
+        }
+    }
+
+    /// Creates the storage of the files under `root`, renamed or skipped by the first
+    /// [`FileRule`] matching their path.
+    ///
+    /// Returns an error if a file would be stored outside of `root`, or if two files would be
+    /// stored at the same path.
+    pub fn with_rules(
+        files: &[FileSpan],
+        root: &Path,
+        rules: &[FileRule],
+        piece_length: usize,
+    ) -> Result<Self> {
+        Ok(Self::new(
+            layout::lay_out(files, root, rules)?,
+            piece_length,
+        ))
+    }
+
+    /// Returns the files with the path they are stored at.
+    pub fn files(&self) -> &[(FileSpan, Option<PathBuf>)] {
+        &self.files
+    }
+
+    /// Returns the number of pieces of the content.
+    pub fn number_of_pieces(&self) -> u32 {
+        self.total_length.div_ceil(self.piece_length) as u32
+    }
+
+    /// Returns the bytes the files still need on the disk.
+    ///
+    /// The files are allocated sparsely, as their pieces arrive, so the bytes of the files
+    /// already on the disk, e.g. from a previous run, are not needed again.
+    pub fn required_space(&self) -> u64 {
+        self.files
+            .iter()
+            .filter_map(|(span, path)| Some((span, path.as_ref()?)))
+            .map(|(span, path)| (span.length as u64).saturating_sub(space::allocated(path)))
+            .sum()
+    }
+
+    /// Checks that the file system of `dir` has enough free space for the files. Returns an
+    /// [`InsufficientSpace`] error otherwise.
+    ///
+    /// The check passes on the platforms where the free space is not known.
+    pub fn check_space(&self, dir: &Path) -> Result<()> {
+        let required = self.required_space();
+        match available_space(dir)? {
+            Some(available) if available < required => Err(InsufficientSpace {
+                dir: dir.to_path_buf(),
+                required,
+                available,
+            }
+            .into()),
+            _ => Ok(()),
+        }
+    }
+
+    /// Returns the range of bytes of the piece in the torrent's byte stream.
+    pub fn piece_range(&self, piece: u32) -> Range<usize> {
+        let start = (piece as usize * self.piece_length).min(self.total_length);
+        start..(start + self.piece_length).min(self.total_length)
+    }
+
+    /// Writes a whole piece, usually once it is verified. The missing files and directories are
+    /// created.
+    pub fn write_piece(&self, piece: u32, data: &[u8]) -> Result<()> {
+        let range = self.piece_range(piece);
+        if data.len() != range.len() {
+            bail!(
+                "Invalid piece {piece} - Expected {} bytes, got {}",
+                range.len(),
+                data.len()
+            )
+        }
+        self.write(range.start, data)
+    }
+
+    /// Applies the BEP 47 attributes of the files once their content is on the disk. `piece` is
+    /// the piece just written and `have` holds the verified pieces, `piece` included.
+    ///
+    /// The executable files whose last missing piece is `piece` get their executable bit, and the
+    /// symlinks are created once every piece is verified, when their targets exist. The attributes
+    /// are only applied on unix.
+    pub fn finish_files(&self, piece: u32, have: &Bitfield) -> Result<()> {
+        #[cfg(unix)]
+        for (span, path) in &self.files {
+            let Some(path) = path else { continue };
+            if let Some(target) = &span.symlink {
+                if have.is_complete() {
+                    self.create_symlink(span, path, target)?;
+                }
+            } else if span.executable {
+                let mut pieces = span.piece_range(self.piece_length);
+                if pieces.contains(&(piece as usize)) && pieces.all(|p| have.has(p)) {
+                    set_executable(path)?;
+                }
+            }
+        }
+        #[cfg(not(unix))]
+        let _ = (piece, have);
+        Ok(())
+    }
+
+    // Creates the symlink at `path`, unless it already exists.
+    #[cfg(unix)]
+    fn create_symlink(&self, span: &FileSpan, path: &Path, target: &str) -> Result<()> {
+        if path
+            .symlink_metadata()
+            .is_ok_and(|metadata| metadata.file_type().is_symlink())
+        {
+            return Ok(());
+        }
+        if target.starts_with('/') || target.split('/').any(|part| part == "..") {
+            bail!("The symlink {} points outside of the torrent", span.path)
+        }
+
+        // The rules may have moved the target, which is then linked where it is stored. Other
+        // targets, such as the directories, are linked as laid out in the torrent.
+        let link = match self.files.iter().find(|(file, _)| file.path == target) {
+            Some((_, Some(stored))) => relative_to(path.parent().unwrap_or(Path::new("")), stored),
+            _ => span
+                .path
+                .split('/')
+                .skip(1)
+                .map(|_| "..")
+                .chain(target.split('/'))
+                .collect(),
+        };
+        if let Some(dir) = path.parent() {
+            std::fs::create_dir_all(dir)
+                .with_context(|| format!("Unable to create {}", dir.display()))?;
+        }
+        std::os::unix::fs::symlink(&link, path)
+            .with_context(|| format!("Unable to create the symlink {}", path.display()))
+    }
+
+    /// Reads a whole piece.
+    pub fn read_piece(&self, piece: u32) -> Result<Vec<u8>> {
+        let range = self.piece_range(piece);
+        self.read(range.start, range.len())
+    }
+
+    /// Writes the bytes at the offset of the torrent's byte stream, to every file they overlap.
+    /// Each file is written with a single call.
+    pub fn write(&self, offset: usize, data: &[u8]) -> Result<()> {
+        for (span, path, bytes) in self.overlapping(offset..offset + data.len()) {
+            if let Some(dir) = path.parent() {
+                std::fs::create_dir_all(dir)
+                    .with_context(|| format!("Unable to create {}", dir.display()))?;
+            }
+            let mut file = OpenOptions::new()
+                .write(true)
+                .create(true)
+                .truncate(false)
+                .open(path)
+                .with_context(|| format!("Unable to open {}", path.display()))?;
+            file.seek(SeekFrom::Start((bytes.start - span.offset) as u64))?;
+            file.write_all(&data[bytes.start - offset..bytes.end - offset])
+                .with_context(|| format!("Unable to write {}", path.display()))?;
+        }
+        Ok(())
+    }
+
+    /// Reads `length` bytes at the offset of the torrent's byte stream.
+    pub fn read(&self, offset: usize, length: usize) -> Result<Vec<u8>> {
+        let mut data = vec![0; length];
+        for (span, path, bytes) in self.overlapping(offset..offset + length) {
+            let mut file =
+                File::open(path).with_context(|| format!("Unable to open {}", path.display()))?;
+            file.seek(SeekFrom::Start((bytes.start - span.offset) as u64))?;
+            file.read_exact(&mut data[bytes.start - offset..bytes.end - offset])
+                .with_context(|| format!("Unable to read {}", path.display()))?;
+        }
+        Ok(data)
+    }
+
+    // The files overlapping the bytes, except the files without a path, with the overlapping
+    // bytes.
+    fn overlapping(
+        &self,
+        bytes: Range<usize>,
+    ) -> impl Iterator<Item = (&FileSpan, &PathBuf, Range<usize>)> {
+        self.files.iter().filter_map(move |(span, path)| {
+            let path = path.as_ref()?;
+            if span.padding || !span.overlaps(&bytes) {
+                return None;
+            }
+            let file = span.byte_range();
+            Some((
+                span,
+                path,
+                bytes.start.max(file.start)..bytes.end.min(file.end),
+            ))
+        })
+    }
+}
+
+// Sets the executable bit of the file for everyone who can read it.
+#[cfg(unix)]
+fn set_executable(path: &Path) -> Result<()> {
+    use std::os::unix::fs::PermissionsExt;
+
+    let mut permissions = std::fs::metadata(path)
+        .with_context(|| format!("Unable to read the permissions of {}", path.display()))?
+        .permissions();
+    permissions.set_mode(permissions.mode() | (permissions.mode() & 0o444) >> 2);
+    std::fs::set_permissions(path, permissions)
+        .with_context(|| format!("Unable to make {} executable", path.display()))
+}
+
+// The path relative to the directory, when both are relative to the same directory.
+#[cfg(unix)]
+fn relative_to(dir: &Path, path: &Path) -> PathBuf {
+    use std::path::Component;
+
+    let dir: Vec<_> = dir.components().collect();
+    let path: Vec<_> = path.components().collect();
+    let common = dir.iter().zip(&path).take_while(|(a, b)| a == b).count();
+
+    let mut relative: PathBuf = dir[common..].iter().map(|_| Component::ParentDir).collect();
+    relative.extend(&path[common..]);
+    relative
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn span(path: &str, offset: usize, length: usize, padding: bool) -> FileSpan {
+        FileSpan {
+            path: path.to_string(),
+            offset,
+            length,
+            padding,
+            sha1: None,
+            executable: false,
+            symlink: None,
+        }
+    }
+
+    #[test]
+    fn test_pieces_across_files() {
+        let dir = std::env::temp_dir().join(format!("zung-storage-{}", std::process::id()));
+        let _ = std::fs::remove_dir_all(&dir);
+        let storage = Storage::new(
+            vec![
+                (span("a", 0, 6, false), Some(dir.join("a"))),
+                (span(".pad/2", 6, 2, true), None),
+                (span("dir/b", 8, 10, false), Some(dir.join("dir/b"))),
+            ],
+            8,
+        );
+
+        assert_eq!(storage.number_of_pieces(), 3);
+        assert_eq!(storage.piece_range(2), 16..18);
+        assert_eq!(storage.required_space(), 16);
+        storage.write_piece(2, b"yz").unwrap();
+        storage.write_piece(0, b"abcdef\0\0").unwrap();
+        storage.write_piece(1, b"01234567").unwrap();
+        assert!(storage.write_piece(1, b"short").is_err());
+
+        assert_eq!(std::fs::read(dir.join("a")).unwrap(), b"abcdef");
+        assert_eq!(std::fs::read(dir.join("dir/b")).unwrap(), b"01234567yz");
+        assert!(!dir.join(".pad").exists());
+
+        assert_eq!(storage.read_piece(0).unwrap(), b"abcdef\0\0");
+        storage.check_space(&dir).unwrap();
+        assert_eq!(storage.read(4, 6).unwrap(), [b'e', b'f', 0, 0, b'0', b'1']);
+    }
+
+    #[cfg(unix)]
+    #[test]
+    fn test_finish_files() {
+        use std::os::unix::fs::PermissionsExt;
+
+        let dir = std::env::temp_dir().join(format!("zung-attrs-{}", std::process::id()));
+        let _ = std::fs::remove_dir_all(&dir);
+        let spans = [
+            FileSpan {
+                executable: true,
+                ..span("bin/run", 0, 10, false)
+            },
+            span("data", 10, 6, false),
+            FileSpan {
+                symlink: Some("bin/run".to_string()),
+                ..span("links/latest", 16, 0, false)
+            },
+            FileSpan {
+                symlink: Some("bin".to_string()),
+                ..span("links/bin", 16, 0, false)
+            },
+        ];
+        let storage = Storage::new(
+            spans
+                .into_iter()
+                .map(|span| {
+                    let path = dir.join(&span.path);
+                    (span, Some(path))
+                })
+                .collect(),
+            8,
+        );
+        let mut have = Bitfield::new(2);
+
+        storage.write_piece(0, b"abcdefgh").unwrap();
+        have.set(0);
+        storage.finish_files(0, &have).unwrap();
+        let mode = |path: &str| {
+            std::fs::metadata(dir.join(path))
+                .unwrap()
+                .permissions()
+                .mode()
+        };
+        assert_eq!(mode("bin/run") & 0o111, 0);
+        assert!(!dir.join("links").exists());
+
+        storage.write_piece(1, b"ij012345").unwrap();
+        have.set(1);
+        storage.finish_files(1, &have).unwrap();
+        assert_ne!(mode("bin/run") & 0o111, 0);
+        assert_eq!(mode("data") & 0o111, 0);
+        assert_eq!(
+            std::fs::read_link(dir.join("links/latest")).unwrap(),
+            Path::new("../bin/run")
+        );
+        assert_eq!(
+            std::fs::read(dir.join("links/latest")).unwrap(),
+            b"abcdefghij"
+        );
+        assert!(dir.join("links/bin").is_dir());
+
+        // Finishing again keeps the symlinks.
+        storage.finish_files(1, &have).unwrap();
+        assert!(dir.join("links/latest").is_symlink());
+    }
+}
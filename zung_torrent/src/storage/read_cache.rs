@@ -0,0 +1,218 @@
+use std::collections::{BTreeMap, HashMap};
+
+use anyhow::{bail, Result};
+
+use super::Storage;
+
+/// Default size of a [`ReadCache`]: 64 MiB.
+pub const DEFAULT_READ_CACHE_SIZE: usize = 64 * 1024 * 1024;
+
+/// Default number of pieces read ahead of a sequential reader.
+pub const DEFAULT_READ_AHEAD: u32 = 2;
+
+/// Keeps the most recently read pieces in memory, to answer the requests of the peers while
+/// seeding.
+///
+/// The peers request a piece in blocks of 16 KiB, so reading the whole piece once and serving
+/// the next blocks from memory avoids a disk read per block. When the pieces are requested one
+/// after the other, as by a peer streaming the content, the next pieces are read ahead so that
+/// they are ready when their requests arrive.
+///
+/// Once the cache is full, the least recently used pieces are evicted.
+#[derive(Debug, Clone)]
+pub struct ReadCache {
+    capacity: usize,
+    read_ahead: u32,
+
+    /// Bytes of the cached pieces.
+    used: usize,
+
+    /// The cached pieces, with the tick of their last use.
+    pieces: HashMap<u32, (Vec<u8>, u64)>,
+
+    /// The cached pieces by the tick of their last use, the least recently used first.
+    by_use: BTreeMap<u64, u32>,
+    tick: u64,
+
+    last_piece: Option<u32>,
+}
+
+impl ReadCache {
+    /// Creates a cache of `capacity` bytes.
+    pub fn new(capacity: usize) -> Self {
+        Self {
+            capacity,
+            read_ahead: DEFAULT_READ_AHEAD,
+            used: 0,
+            pieces: HashMap::new(),
+            by_use: BTreeMap::new(),
+            tick: 0,
+            last_piece: None,
+        }
+    }
+
+    /// Sets the number of pieces read ahead when the pieces are requested sequentially. Defaults
+    /// to [`DEFAULT_READ_AHEAD`], and `0` disables the read-ahead.
+    pub fn read_ahead(mut self, pieces: u32) -> Self {
+        self.read_ahead = pieces;
+        self
+    }
+
+    /// Returns the block requested by a peer, reading its piece from the storage if it is not
+    /// cached.
+    pub fn read_block(
+        &mut self,
+        storage: &Storage,
+        piece: u32,
+        offset: u32,
+        length: u32,
+    ) -> Result<Vec<u8>> {
+        let sequential = self.last_piece.is_some_and(|last| piece == last + 1);
+        self.last_piece = Some(piece);
+
+        if !self.pieces.contains_key(&piece) {
+            let data = storage.read_piece(piece)?;
+            if data.len() > self.capacity {
+                return block_of(&data, piece, offset, length);
+            }
+            self.insert(piece, data);
+        }
+        let block = block_of(
+            self.touch(piece).expect("Cached above"),
+            piece,
+            offset,
+            length,
+        )?;
+
+        if sequential {
+            let last = storage.number_of_pieces();
+            for next in (piece + 1..last).take(self.read_ahead as usize) {
+                // The pieces ahead may not be downloaded yet, which is not an error of this read.
+                if !self.pieces.contains_key(&next) {
+                    if let Ok(data) = storage.read_piece(next) {
+                        self.insert(next, data);
+                    }
+                }
+            }
+        }
+        Ok(block)
+    }
+
+    /// Drops the piece, e.g. when its content changed on the disk.
+    pub fn invalidate(&mut self, piece: u32) {
+        if let Some((data, tick)) = self.pieces.remove(&piece) {
+            self.by_use.remove(&tick);
+            self.used -= data.len();
+        }
+    }
+
+    /// Returns `true` if the piece is cached.
+    pub fn contains(&self, piece: u32) -> bool {
+        self.pieces.contains_key(&piece)
+    }
+
+    /// Returns the number of bytes of the cached pieces.
+    pub fn used(&self) -> usize {
+        self.used
+    }
+
+    // Marks the piece as the most recently used and returns it.
+    fn touch(&mut self, piece: u32) -> Option<&[u8]> {
+        let (data, tick) = self.pieces.get_mut(&piece)?;
+        self.by_use.remove(tick);
+        self.tick += 1;
+        *tick = self.tick;
+        self.by_use.insert(self.tick, piece);
+        Some(data)
+    }
+
+    // Caches the piece, evicting the least recently used pieces to make room.
+    fn insert(&mut self, piece: u32, data: Vec<u8>) {
+        while self.used + data.len() > self.capacity {
+            let Some((_, oldest)) = self.by_use.pop_first() else {
+                break;
+            };
+            let (evicted, _) = self
+                .pieces
+                .remove(&oldest)
+                .expect("Pieces are in both maps");
+            self.used -= evicted.len();
+        }
+
+        self.tick += 1;
+        self.used += data.len();
+        self.by_use.insert(self.tick, piece);
+        self.pieces.insert(piece, (data, self.tick));
+    }
+}
+
+fn block_of(data: &[u8], piece: u32, offset: u32, length: u32) -> Result<Vec<u8>> {
+    match data.get(offset as usize..offset as usize + length as usize) {
+        Some(block) => Ok(block.to_vec()),
+        None => bail!("Invalid request - {length} bytes at {offset} are not within piece {piece}"),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::meta_info::FileSpan;
+
+    // A storage of 4 pieces of 4 bytes in a single file.
+    fn storage(name: &str) -> Storage {
+        let dir = std::env::temp_dir().join(format!("zung-{name}-{}", std::process::id()));
+        let span = FileSpan {
+            path: "data".to_string(),
+            offset: 0,
+            length: 16,
+            padding: false,
+            sha1: None,
+            executable: false,
+            symlink: None,
+        };
+        let storage = Storage::new(vec![(span, Some(dir.join("data")))], 4);
+        storage.write(0, b"0123456789abcdef").unwrap();
+        storage
+    }
+
+    #[test]
+    fn test_least_recently_used_pieces_are_evicted() {
+        let storage = storage("read-cache-lru");
+        let mut cache = ReadCache::new(8).read_ahead(0);
+
+        assert_eq!(cache.read_block(&storage, 0, 1, 2).unwrap(), b"12");
+        assert_eq!(cache.read_block(&storage, 2, 0, 4).unwrap(), b"89ab");
+        assert_eq!(cache.read_block(&storage, 0, 0, 1).unwrap(), b"0");
+        assert_eq!(cache.read_block(&storage, 3, 2, 2).unwrap(), b"ef");
+        assert!(cache.contains(0) && cache.contains(3) && !cache.contains(2));
+        assert_eq!(cache.used(), 8);
+
+        cache.invalidate(0);
+        assert!(!cache.contains(0));
+        assert_eq!(cache.used(), 4);
+
+        assert!(cache.read_block(&storage, 3, 2, 4).is_err());
+    }
+
+    #[test]
+    fn test_read_ahead() {
+        let storage = storage("read-cache-ahead");
+        let mut cache = ReadCache::new(64).read_ahead(2);
+
+        cache.read_block(&storage, 1, 0, 4).unwrap();
+        assert!(!cache.contains(2));
+
+        // Only the sequential requests are read ahead, up to the last piece.
+        cache.read_block(&storage, 2, 0, 4).unwrap();
+        assert!(cache.contains(3));
+        assert_eq!(cache.used(), 12);
+    }
+
+    #[test]
+    fn test_pieces_larger_than_the_cache() {
+        let storage = storage("read-cache-large");
+        let mut cache = ReadCache::new(2);
+        assert_eq!(cache.read_block(&storage, 1, 0, 2).unwrap(), b"45");
+        assert_eq!(cache.used(), 0);
+    }
+}
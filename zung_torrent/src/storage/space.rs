@@ -0,0 +1,99 @@
+use std::fmt;
+use std::path::{Path, PathBuf};
+
+use anyhow::Result;
+
+use crate::meta_info::SizeFormat;
+
+/// The error of a disk space check: the file system of `dir` has less free space than the files
+/// to download need.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct InsufficientSpace {
+    pub dir: PathBuf,
+
+    /// Bytes the files still need on the disk.
+    pub required: u64,
+
+    /// Free bytes of the file system.
+    pub available: u64,
+}
+
+impl fmt::Display for InsufficientSpace {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let size = |bytes: u64| SizeFormat::Binary.format(bytes as usize);
+        write!(
+            f,
+            "Not enough disk space in {} - {} required, {} available",
+            self.dir.display(),
+            size(self.required),
+            size(self.available)
+        )
+    }
+}
+
+impl std::error::Error for InsufficientSpace {}
+
+/// Returns the free space, in bytes, of the file system which contains `path`, available to the
+/// current user. The path does not have to exist yet: its closest existing parent is used.
+///
+/// Returns `None` on the platforms where the free space is not known.
+pub fn available_space(path: &Path) -> Result<Option<u64>> {
+    let existing = path
+        .ancestors()
+        .find(|dir| dir.exists())
+        .unwrap_or(Path::new("."));
+    free_space(existing)
+}
+
+#[cfg(unix)]
+fn free_space(path: &Path) -> Result<Option<u64>> {
+    let stat = rustix::fs::statvfs(path)?;
+    Ok(Some(stat.f_bavail.saturating_mul(stat.f_frsize)))
+}
+
+#[cfg(not(unix))]
+fn free_space(_path: &Path) -> Result<Option<u64>> {
+    Ok(None)
+}
+
+/// Returns the bytes of the file already allocated on the disk, which are not needed again to
+/// write the file. Only the allocated blocks count, so a sparse file which is mostly holes still
+/// needs most of its length.
+pub(super) fn allocated(path: &Path) -> u64 {
+    let Ok(metadata) = std::fs::metadata(path) else {
+        return 0;
+    };
+    #[cfg(unix)]
+    {
+        use std::os::unix::fs::MetadataExt;
+        (metadata.blocks() * 512).min(metadata.len())
+    }
+    #[cfg(not(unix))]
+    metadata.len()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_available_space() {
+        let missing = std::env::temp_dir().join("zung-missing/dir");
+        assert!(available_space(&missing)
+            .unwrap()
+            .is_some_and(|free| free > 0));
+    }
+
+    #[test]
+    fn test_display() {
+        let error = InsufficientSpace {
+            dir: PathBuf::from("/downloads"),
+            required: 3 * 1024 * 1024,
+            available: 512 * 1024,
+        };
+        assert_eq!(
+            error.to_string(),
+            "Not enough disk space in /downloads - 3 MiB required, 512 KiB available"
+        );
+    }
+}
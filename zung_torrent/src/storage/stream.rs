@@ -0,0 +1,303 @@
+use std::net::SocketAddr;
+use std::ops::Range;
+use std::path::Path;
+use std::sync::Arc;
+use std::time::Duration;
+
+use anyhow::{bail, Context, Result};
+use tokio::io::{AsyncReadExt, AsyncWriteExt};
+use tokio::net::{TcpListener, TcpStream};
+use tokio::sync::watch;
+use tokio::task::JoinHandle;
+
+use super::Storage;
+use crate::meta_info::FileSpan;
+use crate::peers::Bitfield;
+
+/// Number of bytes read from the storage and sent at once.
+const CHUNK_LENGTH: usize = 64 * 1024;
+
+/// Time waited after failing to accept a connection before accepting again.
+const ACCEPT_RETRY_DELAY: Duration = Duration::from_millis(100);
+
+/// Serves a file of a torrent over HTTP while it downloads, e.g. to preview a video in a media
+/// player before the download completes.
+///
+/// `verified` holds the pieces verified so far. Each response sends the bytes of the file in
+/// order, waiting for every piece to be verified before reading it from the storage, so request
+/// the pieces of the file first and in order with
+/// [`BlockScheduler::prioritize`](crate::peers::BlockScheduler::prioritize). A `Range` header is
+/// answered with the part of the file it asks for, which lets the players seek.
+///
+/// The server runs until the returned task is aborted. The responses waiting for a piece end when
+/// the sender of `verified` is dropped.
+///
+/// # Example
+///
+/// ```no_run
+/// use std::sync::Arc;
+/// use tokio::sync::watch;
+/// use zung_torrent::{peers::Bitfield, storage::{self, Storage}};
+///
+/// # async fn run(storage: Storage, file: zung_torrent::meta_info::FileSpan) -> anyhow::Result<()> {
+/// let (verified, pieces) = watch::channel(Bitfield::new(storage.number_of_pieces() as usize));
+/// let server = storage::serve_file("127.0.0.1:8080".parse()?, Arc::new(storage), file, pieces).await?;
+/// // ... the file is now available on http://127.0.0.1:8080, and grows with every
+/// // `verified.send_modify(|pieces| { pieces.set(piece); })` ...
+/// server.abort();
+/// # Ok(())
+/// # }
+/// ```
+pub async fn serve_file(
+    addr: SocketAddr,
+    storage: Arc<Storage>,
+    file: FileSpan,
+    verified: watch::Receiver<Bitfield>,
+) -> Result<JoinHandle<()>> {
+    let listener = TcpListener::bind(addr)
+        .await
+        .with_context(|| format!("Unable to listen for the stream on {addr}"))?;
+    let file = Arc::new(file);
+
+    Ok(tokio::spawn(async move {
+        loop {
+            let stream = match listener.accept().await {
+                Ok((stream, _)) => stream,
+                Err(e) => {
+                    eprintln!("Unable to accept a stream connection: {e}");
+                    tokio::time::sleep(ACCEPT_RETRY_DELAY).await;
+                    continue;
+                }
+            };
+            let (storage, file, verified) =
+                (Arc::clone(&storage), Arc::clone(&file), verified.clone());
+            tokio::spawn(async move {
+                // The player closing the connection, e.g. to seek, is not an error.
+                let _ = respond(stream, storage, &file, verified).await;
+            });
+        }
+    }))
+}
+
+// Answers a single request with the bytes of the file.
+async fn respond(
+    mut stream: TcpStream,
+    storage: Arc<Storage>,
+    file: &FileSpan,
+    mut verified: watch::Receiver<Bitfield>,
+) -> Result<()> {
+    let mut buf = [0_u8; 4096];
+    let read = stream.read(&mut buf).await?;
+    let request = String::from_utf8_lossy(&buf[..read]);
+
+    let content_type = content_type(Path::new(&file.path));
+    let (range, partial) = match requested_range(&request, file.length) {
+        Ok(None) => (0..file.length, false),
+        Ok(Some(range)) => (range, true),
+        Err(_) => {
+            let response = format!(
+                "HTTP/1.1 416 Range Not Satisfiable\r\nContent-Range: bytes */{}\r\nContent-Length: 0\r\nConnection: close\r\n\r\n",
+                file.length
+            );
+            stream.write_all(response.as_bytes()).await?;
+            return Ok(());
+        }
+    };
+
+    let status = if partial {
+        "206 Partial Content"
+    } else {
+        "200 OK"
+    };
+    let mut head = format!(
+        "HTTP/1.1 {status}\r\nContent-Type: {content_type}\r\nContent-Length: {}\r\nAccept-Ranges: bytes\r\nConnection: close\r\n",
+        range.len()
+    );
+    if partial {
+        head.push_str(&format!(
+            "Content-Range: bytes {}-{}/{}\r\n",
+            range.start,
+            range.end - 1,
+            file.length
+        ));
+    }
+    head.push_str("\r\n");
+    stream.write_all(head.as_bytes()).await?;
+
+    let (mut offset, end) = (file.offset + range.start, file.offset + range.end);
+    while offset < end {
+        let piece = offset / storage.piece_length;
+        verified
+            .wait_for(|pieces| pieces.has(piece))
+            .await
+            .context("The pieces are no longer verified")?;
+
+        let length = (end.min(storage.piece_range(piece as u32).end) - offset).min(CHUNK_LENGTH);
+        let storage = Arc::clone(&storage);
+        let data = tokio::task::spawn_blocking(move || storage.read(offset, length)).await??;
+        stream.write_all(&data).await?;
+        offset += length;
+    }
+    Ok(())
+}
+
+// Returns the bytes of a file of `length` bytes asked for by the `Range` header of the request,
+// or `None` for the whole file. Only a single range is supported: the others get the whole file,
+// as the header may be ignored.
+fn requested_range(request: &str, length: usize) -> Result<Option<Range<usize>>> {
+    let Some(value) = request.lines().find_map(|line| {
+        let (name, value) = line.split_once(':')?;
+        name.trim()
+            .eq_ignore_ascii_case("range")
+            .then(|| value.trim())
+    }) else {
+        return Ok(None);
+    };
+    let Some((start, end)) = value
+        .strip_prefix("bytes=")
+        .filter(|ranges| !ranges.contains(','))
+        .and_then(|range| range.split_once('-'))
+    else {
+        return Ok(None);
+    };
+
+    let range = match (start.trim(), end.trim()) {
+        // The last bytes of the file.
+        ("", suffix) => length.saturating_sub(suffix.parse()?)..length,
+        (start, "") => start.parse()?..length,
+        (start, end) => start.parse()?..length.min(end.parse::<usize>()?.saturating_add(1)),
+    };
+    if range.start >= length || range.is_empty() {
+        bail!("Range not satisfiable: {value}");
+    }
+    Ok(Some(range))
+}
+
+// The media types of the files players are likely to stream.
+fn content_type(path: &Path) -> &'static str {
+    let extension = path
+        .extension()
+        .and_then(|extension| extension.to_str())
+        .map(str::to_ascii_lowercase);
+    match extension.as_deref() {
+        Some("mp4" | "m4v") => "video/mp4",
+        Some("mkv") => "video/x-matroska",
+        Some("webm") => "video/webm",
+        Some("avi") => "video/x-msvideo",
+        Some("mp3") => "audio/mpeg",
+        Some("flac") => "audio/flac",
+        Some("ogg") => "audio/ogg",
+        _ => "application/octet-stream",
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_requested_range() {
+        let request = |range: &str| format!("GET / HTTP/1.1\r\nHost: x\r\n{range}\r\n\r\n");
+        assert_eq!(requested_range(&request(""), 10).unwrap(), None);
+        assert_eq!(
+            requested_range(&request("Range: bytes=2-"), 10).unwrap(),
+            Some(2..10)
+        );
+        assert_eq!(
+            requested_range(&request("range: bytes=2-4"), 10).unwrap(),
+            Some(2..5)
+        );
+        assert_eq!(
+            requested_range(&request("Range: bytes=2-100"), 10).unwrap(),
+            Some(2..10)
+        );
+        assert_eq!(
+            requested_range(&request("Range: bytes=-3"), 10).unwrap(),
+            Some(7..10)
+        );
+        assert_eq!(
+            requested_range(&request("Range: bytes=0-1,4-5"), 10).unwrap(),
+            None
+        );
+        assert!(requested_range(&request("Range: bytes=10-"), 10).is_err());
+        assert!(requested_range(&request("Range: bytes=4-2"), 10).is_err());
+        assert!(requested_range(&request("Range: bytes=x-"), 10).is_err());
+    }
+
+    #[tokio::test]
+    async fn test_serve_file() {
+        let dir = std::env::temp_dir().join(format!("zung-stream-{}", std::process::id()));
+        let _ = std::fs::remove_dir_all(&dir);
+        let file = FileSpan {
+            path: String::from("dir/video.mp4"),
+            offset: 6,
+            length: 12,
+            padding: false,
+            sha1: None,
+            executable: false,
+            symlink: None,
+        };
+        let first = FileSpan {
+            path: String::from("a"),
+            offset: 0,
+            length: 6,
+            padding: false,
+            sha1: None,
+            executable: false,
+            symlink: None,
+        };
+        let storage = Storage::new(
+            vec![
+                (first, Some(dir.join("a"))),
+                (file.clone(), Some(dir.join("dir/video.mp4"))),
+            ],
+            8,
+        );
+        storage.write_piece(0, b"abcdef01").unwrap();
+        storage.write_piece(1, b"23456789").unwrap();
+
+        let (verified, pieces) = watch::channel(Bitfield::new(3));
+        verified.send_modify(|pieces| {
+            pieces.set(0);
+            pieces.set(1);
+        });
+
+        let listener = TcpListener::bind("127.0.0.1:0").await.unwrap();
+        let addr = listener.local_addr().unwrap();
+        drop(listener);
+        let server = serve_file(addr, Arc::new(storage.clone()), file, pieces)
+            .await
+            .unwrap();
+
+        // The verified pieces are sent right away.
+        let response = get(addr, "Range: bytes=1-5\r\n").await;
+        assert!(response.starts_with("HTTP/1.1 206 Partial Content\r\n"));
+        assert!(response.contains("Content-Range: bytes 1-5/12\r\n"));
+        assert!(response.ends_with("\r\n\r\n12345"));
+
+        // The whole file waits for its last piece.
+        let whole = tokio::spawn(get(addr, ""));
+        tokio::time::sleep(Duration::from_millis(50)).await;
+        assert!(!whole.is_finished());
+        storage.write_piece(2, b"ab").unwrap();
+        verified.send_modify(|pieces| {
+            pieces.set(2);
+        });
+
+        let response = whole.await.unwrap();
+        server.abort();
+        assert!(response.starts_with("HTTP/1.1 200 OK\r\n"));
+        assert!(response.contains("Content-Type: video/mp4\r\n"));
+        assert!(response.ends_with("\r\n\r\n0123456789ab"));
+    }
+
+    // Sends a GET request with the headers and returns the whole response.
+    async fn get(addr: SocketAddr, headers: &str) -> String {
+        let mut stream = TcpStream::connect(addr).await.unwrap();
+        let request = format!("GET / HTTP/1.1\r\nHost: {addr}\r\n{headers}\r\n");
+        stream.write_all(request.as_bytes()).await.unwrap();
+        let mut response = String::new();
+        stream.read_to_string(&mut response).await.unwrap();
+        response
+    }
+}
@@ -0,0 +1,183 @@
+use std::collections::{BTreeMap, HashMap};
+
+use anyhow::{bail, Result};
+
+/// Default size of a [`WriteCache`]: 64 MiB.
+pub const DEFAULT_WRITE_CACHE_SIZE: usize = 64 * 1024 * 1024;
+
+/// What happened to a block given to [`WriteCache::add_block`].
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum BlockAdded {
+    /// The block is kept until the rest of its piece arrives.
+    Buffered,
+
+    /// The block completed its piece, which is returned and no longer cached. It should be
+    /// verified against its hash and then written with
+    /// [`Storage::write_piece`](super::Storage::write_piece).
+    PieceComplete(Vec<u8>),
+
+    /// The cache has no room to start another piece. The block is dropped and should be
+    /// requested again once some pieces have completed.
+    CacheFull,
+}
+
+/// Keeps the blocks of the pieces being downloaded in memory until their piece is complete.
+///
+/// A piece is only written once it is complete and verified, in a single write per file instead
+/// of one per block, so a corrupt piece never reaches the disk. The cache is bounded: a new piece
+/// is only started while the pieces already started fit in the capacity, so that the downloads
+/// slow down instead of using more memory. The pieces already started always accept their
+/// blocks, so they can complete and free their space.
+#[derive(Debug, Clone)]
+pub struct WriteCache {
+    capacity: usize,
+    piece_length: usize,
+    total_length: usize,
+
+    /// Bytes reserved by the started pieces.
+    used: usize,
+
+    pieces: HashMap<u32, PartialPiece>,
+}
+
+#[derive(Debug, Clone)]
+struct PartialPiece {
+    data: Vec<u8>,
+
+    /// The offset and length of the received blocks.
+    blocks: BTreeMap<u32, u32>,
+    received: usize,
+}
+
+impl WriteCache {
+    /// Creates a cache of `capacity` bytes for a torrent of `total_length` bytes.
+    pub fn new(capacity: usize, total_length: usize, piece_length: usize) -> Self {
+        Self {
+            capacity,
+            piece_length,
+            total_length,
+            used: 0,
+            pieces: HashMap::new(),
+        }
+    }
+
+    /// Adds a block received from a peer.
+    ///
+    /// Returns an error if the block is not within its piece. A block received twice is only
+    /// counted once.
+    pub fn add_block(&mut self, piece: u32, offset: u32, data: &[u8]) -> Result<BlockAdded> {
+        let start = piece as usize * self.piece_length;
+        if start >= self.total_length {
+            bail!("Invalid block - There is no piece {piece}")
+        }
+        let length = self.piece_length.min(self.total_length - start);
+        let end = offset as usize + data.len();
+        if data.is_empty() || end > length {
+            bail!(
+                "Invalid block - {} bytes at {offset} do not fit in piece {piece} of {length} bytes",
+                data.len()
+            )
+        }
+
+        if !self.pieces.contains_key(&piece) {
+            if !self.pieces.is_empty() && self.used + length > self.capacity {
+                return Ok(BlockAdded::CacheFull);
+            }
+            self.used += length;
+            self.pieces.insert(
+                piece,
+                PartialPiece {
+                    data: vec![0; length],
+                    blocks: BTreeMap::new(),
+                    received: 0,
+                },
+            );
+        }
+
+        let partial = self.pieces.get_mut(&piece).expect("Inserted above");
+        if partial.blocks.insert(offset, data.len() as u32).is_none() {
+            partial.received += data.len();
+        }
+        partial.data[offset as usize..end].copy_from_slice(data);
+
+        if partial.received < length {
+            return Ok(BlockAdded::Buffered);
+        }
+        let partial = self.pieces.remove(&piece).expect("Present above");
+        self.used -= length;
+        Ok(BlockAdded::PieceComplete(partial.data))
+    }
+
+    /// Drops the blocks of the piece, e.g. when its download is abandoned.
+    pub fn discard(&mut self, piece: u32) {
+        if let Some(partial) = self.pieces.remove(&piece) {
+            self.used -= partial.data.len();
+        }
+    }
+
+    /// Returns the number of bytes reserved by the pieces being downloaded.
+    pub fn used(&self) -> usize {
+        self.used
+    }
+
+    /// Returns the number of pieces being downloaded.
+    pub fn len(&self) -> usize {
+        self.pieces.len()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.pieces.is_empty()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_pieces_complete() {
+        let mut cache = WriteCache::new(100, 10, 4);
+        assert_eq!(cache.add_block(0, 2, b"cd").unwrap(), BlockAdded::Buffered);
+        assert_eq!(cache.add_block(0, 2, b"cd").unwrap(), BlockAdded::Buffered);
+        assert_eq!(cache.used(), 4);
+        assert_eq!(
+            cache.add_block(0, 0, b"ab").unwrap(),
+            BlockAdded::PieceComplete(b"abcd".to_vec())
+        );
+        assert!(cache.is_empty());
+        assert_eq!(cache.used(), 0);
+
+        // The last piece is shorter.
+        assert_eq!(
+            cache.add_block(2, 0, b"yz").unwrap(),
+            BlockAdded::PieceComplete(b"yz".to_vec())
+        );
+    }
+
+    #[test]
+    fn test_invalid_blocks() {
+        let mut cache = WriteCache::new(100, 10, 4);
+        assert!(cache.add_block(3, 0, b"a").is_err());
+        assert!(cache.add_block(0, 3, b"ab").is_err());
+        assert!(cache.add_block(2, 2, b"a").is_err());
+        assert!(cache.add_block(0, 0, b"").is_err());
+    }
+
+    #[test]
+    fn test_capacity() {
+        let mut cache = WriteCache::new(8, 16, 4);
+        assert_eq!(cache.add_block(0, 0, b"a").unwrap(), BlockAdded::Buffered);
+        assert_eq!(cache.add_block(1, 0, b"a").unwrap(), BlockAdded::Buffered);
+        assert_eq!(cache.add_block(2, 0, b"a").unwrap(), BlockAdded::CacheFull);
+
+        // The started pieces still complete.
+        assert_eq!(cache.add_block(1, 1, b"b").unwrap(), BlockAdded::Buffered);
+        cache.discard(0);
+        assert_eq!(cache.len(), 1);
+        assert_eq!(cache.add_block(2, 0, b"a").unwrap(), BlockAdded::Buffered);
+
+        // A piece larger than the cache can still be downloaded on its own.
+        let mut cache = WriteCache::new(2, 16, 4);
+        assert_eq!(cache.add_block(0, 0, b"a").unwrap(), BlockAdded::Buffered);
+    }
+}
@@ -0,0 +1,321 @@
+use std::collections::HashMap;
+use std::path::{Path, PathBuf};
+
+use anyhow::{Context, Result};
+use zung_parsers::bencode::{self, Value};
+
+/// Default piece length of the generated torrents. Kept small so that tiny files still span
+/// multiple pieces.
+const DEFAULT_PIECE_LENGTH: usize = 16;
+
+/// Builds small torrent files programmatically.
+///
+/// The content of the torrent is generated deterministically (the byte at offset `n` of the
+/// torrent's byte stream is `n % 251`, padding files are all zeros) and the piece hashes are
+/// computed from it, so the generated torrents are valid and their content can be reproduced with
+/// [`TorrentBuilder::content`].
+///
+/// # Example
+///
+/// ```rust
+/// use zung_torrent::meta_info::MetaInfo;
+/// use zung_torrent::testing::TorrentBuilder;
+///
+/// let bytes = TorrentBuilder::multi_file("fixture")
+///     .file("a.txt", 10)
+///     .padding_file(6)
+///     .file("dir/b.txt", 40)
+///     .announce("udp://localhost:6969/announce")
+///     .build();
+///
+/// let meta_info = MetaInfo::from_bytes(&bytes).unwrap();
+/// assert_eq!(meta_info.number_of_pieces(), 4);
+/// ```
+#[derive(Debug, Clone)]
+pub struct TorrentBuilder {
+    name: String,
+    piece_length: usize,
+    single_file: bool,
+    files: Vec<FixtureFile>,
+    announce: Option<String>,
+    announce_list: Option<Vec<Vec<String>>>,
+    url_list: Option<Vec<String>>,
+    creation_date: Option<i64>,
+    file_hashes: bool,
+    private: bool,
+    hybrid: bool,
+}
+
+#[derive(Debug, Clone)]
+struct FixtureFile {
+    path: Vec<String>,
+    length: usize,
+    padding: bool,
+    attr: Option<String>,
+    symlink_path: Option<Vec<String>>,
+}
+
+impl TorrentBuilder {
+    /// A single file torrent with a file of the provided length.
+    pub fn single_file(name: &str, length: usize) -> Self {
+        let mut builder = Self::new(name, true);
+        builder.files.push(FixtureFile {
+            path: vec![name.to_string()],
+            length,
+            padding: false,
+            attr: None,
+            symlink_path: None,
+        });
+        builder
+    }
+
+    /// A multi file torrent without any files. Add files with [`TorrentBuilder::file`].
+    pub fn multi_file(name: &str) -> Self {
+        Self::new(name, false)
+    }
+
+    fn new(name: &str, single_file: bool) -> Self {
+        Self {
+            name: name.to_string(),
+            piece_length: DEFAULT_PIECE_LENGTH,
+            single_file,
+            files: Vec::new(),
+            announce: None,
+            announce_list: None,
+            url_list: None,
+            creation_date: None,
+            file_hashes: false,
+            private: false,
+            hybrid: false,
+        }
+    }
+
+    /// Adds a file at the `/` separated path. Ignored for single file torrents.
+    pub fn file(mut self, path: &str, length: usize) -> Self {
+        if !self.single_file {
+            self.files.push(FixtureFile {
+                path: path.split('/').map(String::from).collect(),
+                length,
+                padding: false,
+                attr: None,
+                symlink_path: None,
+            });
+        }
+        self
+    }
+
+    /// Adds a file with the BEP 47 `attr` string, e.g. `x` for an executable. Ignored for single
+    /// file torrents.
+    pub fn file_with_attr(mut self, path: &str, length: usize, attr: &str) -> Self {
+        if !self.single_file {
+            self.files.push(FixtureFile {
+                path: path.split('/').map(String::from).collect(),
+                length,
+                padding: attr.contains('p'),
+                attr: Some(attr.to_string()),
+                symlink_path: None,
+            });
+        }
+        self
+    }
+
+    /// Adds a zero-length BEP 47 symlink to the `/` separated target. Ignored for single file
+    /// torrents.
+    pub fn symlink(mut self, path: &str, target: &str) -> Self {
+        if !self.single_file {
+            self.files.push(FixtureFile {
+                path: path.split('/').map(String::from).collect(),
+                length: 0,
+                padding: false,
+                attr: Some("l".to_string()),
+                symlink_path: Some(target.split('/').map(String::from).collect()),
+            });
+        }
+        self
+    }
+
+    /// Adds the BEP 47 `sha1` of the contents of every file (except the padding files).
+    pub fn file_hashes(mut self) -> Self {
+        self.file_hashes = true;
+        self
+    }
+
+    /// Adds a BEP 47 padding file. Ignored for single file torrents.
+    pub fn padding_file(mut self, length: usize) -> Self {
+        if !self.single_file {
+            self.files.push(FixtureFile {
+                path: vec![".pad".to_string(), length.to_string()],
+                length,
+                padding: true,
+                attr: Some("p".to_string()),
+                symlink_path: None,
+            });
+        }
+        self
+    }
+
+    pub fn piece_length(mut self, piece_length: usize) -> Self {
+        self.piece_length = piece_length;
+        self
+    }
+
+    pub fn announce(mut self, url: &str) -> Self {
+        self.announce = Some(url.to_string());
+        self
+    }
+
+    pub fn announce_list(mut self, tiers: &[&[&str]]) -> Self {
+        self.announce_list = Some(
+            tiers
+                .iter()
+                .map(|tier| tier.iter().map(|url| url.to_string()).collect())
+                .collect(),
+        );
+        self
+    }
+
+    /// Sets the BEP 19 `url-list`. An empty slice produces an empty list.
+    pub fn url_list(mut self, urls: &[&str]) -> Self {
+        self.url_list = Some(urls.iter().map(|url| url.to_string()).collect());
+        self
+    }
+
+    pub fn creation_date(mut self, timestamp: i64) -> Self {
+        self.creation_date = Some(timestamp);
+        self
+    }
+
+    /// Sets the `private` flag of the info dictionary.
+    pub fn private(mut self) -> Self {
+        self.private = true;
+        self
+    }
+
+    /// Adds the (empty) BEP 52 `meta version`, `file tree` and `piece layers` keys alongside the
+    /// v1 keys, as found in hybrid torrents.
+    pub fn hybrid(mut self) -> Self {
+        self.hybrid = true;
+        self
+    }
+
+    /// The content of the torrent as one continuous byte stream.
+    pub fn content(&self) -> Vec<u8> {
+        let mut content = Vec::new();
+        for file in &self.files {
+            if file.padding {
+                content.resize(content.len() + file.length, 0);
+            } else {
+                let start = content.len();
+                content.extend((start..start + file.length).map(|n| (n % 251) as u8));
+            }
+        }
+        content
+    }
+
+    /// Builds the bencoded torrent file.
+    pub fn build(&self) -> Vec<u8> {
+        let content = self.content();
+        let pieces: Vec<u8> = content
+            .chunks(self.piece_length.max(1))
+            .flat_map(|piece| sha1_smol::Sha1::from(piece).digest().bytes())
+            .collect();
+
+        let mut info = HashMap::from([
+            ("name".to_string(), Value::String(self.name.clone())),
+            (
+                "piece length".to_string(),
+                Value::Integer(self.piece_length as i64),
+            ),
+            ("pieces".to_string(), Value::Bytes(pieces)),
+        ]);
+
+        let sha1 =
+            |bytes: &[u8]| Value::Bytes(sha1_smol::Sha1::from(bytes).digest().bytes().to_vec());
+        if self.single_file {
+            info.insert("length".to_string(), Value::Integer(content.len() as i64));
+            if self.file_hashes {
+                info.insert("sha1".to_string(), sha1(&content));
+            }
+        } else {
+            let mut offset = 0;
+            let files = self
+                .files
+                .iter()
+                .map(|file| {
+                    let mut value = file.to_value();
+                    if self.file_hashes && !file.padding {
+                        if let Value::Dictionary(dict) = &mut value {
+                            let data = &content[offset..offset + file.length];
+                            dict.insert("sha1".into(), sha1(data));
+                        }
+                    }
+                    offset += file.length;
+                    value
+                })
+                .collect();
+            info.insert("files".to_string(), Value::List(files));
+        }
+
+        if self.private {
+            info.insert("private".to_string(), Value::Integer(1));
+        }
+        if self.hybrid {
+            info.insert("meta version".to_string(), Value::Integer(2));
+            info.insert("file tree".to_string(), Value::Dictionary(HashMap::new()));
+        }
+
+        let mut torrent = HashMap::from([("info".to_string(), Value::from(info))]);
+        if self.hybrid {
+            torrent.insert(
+                "piece layers".to_string(),
+                Value::Dictionary(HashMap::new()),
+            );
+        }
+        if let Some(announce) = &self.announce {
+            torrent.insert("announce".to_string(), Value::String(announce.clone()));
+        }
+        if let Some(tiers) = &self.announce_list {
+            torrent.insert("announce-list".to_string(), string_lists(tiers));
+        }
+        if let Some(urls) = &self.url_list {
+            torrent.insert("url-list".to_string(), strings(urls));
+        }
+        if let Some(date) = self.creation_date {
+            torrent.insert("creation date".to_string(), Value::Integer(date));
+        }
+
+        bencode::to_bytes(&Value::from(torrent)).expect("fixture should serialize")
+    }
+
+    /// Builds the torrent and writes it to `<dir>/<name>.torrent`, returning the path.
+    pub fn write_to<P: AsRef<Path>>(&self, dir: P) -> Result<PathBuf> {
+        let path = dir.as_ref().join(format!("{}.torrent", self.name));
+        std::fs::write(&path, self.build())
+            .with_context(|| format!("Unable to write file: {}", path.display()))?;
+        Ok(path)
+    }
+}
+
+impl FixtureFile {
+    fn to_value(&self) -> Value {
+        let mut file = HashMap::from([
+            ("length".to_string(), Value::Integer(self.length as i64)),
+            ("path".to_string(), strings(&self.path)),
+        ]);
+        if let Some(attr) = &self.attr {
+            file.insert("attr".to_string(), Value::String(attr.clone()));
+        }
+        if let Some(target) = &self.symlink_path {
+            file.insert("symlink path".to_string(), strings(target));
+        }
+        Value::from(file)
+    }
+}
+
+fn strings(list: &[String]) -> Value {
+    Value::List(list.iter().cloned().map(Value::String).collect())
+}
+
+fn string_lists(lists: &[Vec<String>]) -> Value {
+    Value::List(lists.iter().map(|list| strings(list)).collect())
+}
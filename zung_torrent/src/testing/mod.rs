@@ -0,0 +1,388 @@
+//! In-process trackers, peers and torrent files for deterministic tests.
+//!
+//! This module is only available for the tests of this crate or with the `testing` feature. Every
+//! mock binds to an ephemeral port on localhost, serves canned responses from a background task
+//! and stops when dropped, so tests do not depend on live trackers. Small torrent files can be
+//! generated with the [`TorrentBuilder`].
+//!
+//! # Example
+//!
+//! ```rust
+//! use zung_torrent::testing::{MockUdpTracker, UdpBehaviour};
+//! use zung_torrent::sources::Tracker;
+//!
+//! # async fn test() {
+//! let mock = MockUdpTracker::start(UdpBehaviour::Connect { connection_id: 42 })
+//!     .await
+//!     .unwrap();
+//! let tracker = Tracker::new(&mock.url());
+//! # }
+//! ```
+
+use std::net::{Ipv4Addr, SocketAddr, SocketAddrV4};
+use std::sync::{Arc, Mutex};
+
+use anyhow::{bail, Result};
+use tokio::io::{AsyncReadExt, AsyncWriteExt};
+use tokio::net::{TcpListener, UdpSocket};
+use tokio::task::JoinHandle;
+
+use crate::peers::{FramedTransport, Handshake, PeerTransport};
+
+pub use crate::peers::HANDSHAKE_LENGTH;
+use crate::sources::Action;
+
+mod fixtures;
+
+pub use fixtures::TorrentBuilder;
+
+/// How a [`MockUdpTracker`] answers the requests sent to it.
+#[derive(Debug, Clone)]
+pub enum UdpBehaviour {
+    /// Answer connect requests with the provided connection id.
+    Connect { connection_id: i64 },
+
+    /// Answer connect requests with the provided connection id, and the announces and scrapes
+    /// carrying that connection id with the swarm. Announces and scrapes with any other
+    /// connection id are answered with an error, as real trackers do.
+    Swarm {
+        connection_id: i64,
+        interval: i32,
+        seeders: i32,
+        leechers: i32,
+        peers: Vec<SocketAddrV4>,
+    },
+
+    /// Answer every request with an error response carrying the message.
+    Error(String),
+
+    /// Never answer, to simulate a tracker that times out.
+    Silent,
+}
+
+/// A UDP tracker running in a background task.
+#[derive(Debug)]
+pub struct MockUdpTracker {
+    addr: SocketAddr,
+    requests: Arc<Mutex<usize>>,
+    task: JoinHandle<()>,
+}
+
+impl MockUdpTracker {
+    /// Binds a socket on localhost and starts answering requests as per the behaviour.
+    pub async fn start(behaviour: UdpBehaviour) -> Result<Self> {
+        let socket = UdpSocket::bind((Ipv4Addr::LOCALHOST, 0)).await?;
+        let addr = socket.local_addr()?;
+        let requests = Arc::new(Mutex::new(0));
+
+        let counter = Arc::clone(&requests);
+        let task = tokio::spawn(async move {
+            let mut buf = [0_u8; 1024];
+            while let Ok((len, from)) = socket.recv_from(&mut buf).await {
+                *counter.lock().expect("poisoned") += 1;
+                if len < 16 {
+                    continue;
+                }
+
+                let transaction_id = &buf[12..16];
+                let mut response = Vec::new();
+                match &behaviour {
+                    UdpBehaviour::Connect { connection_id } => {
+                        response.extend_from_slice(&(Action::Connect as i32).to_be_bytes());
+                        response.extend_from_slice(transaction_id);
+                        response.extend_from_slice(&connection_id.to_be_bytes());
+                    }
+                    UdpBehaviour::Swarm {
+                        connection_id,
+                        interval,
+                        seeders,
+                        leechers,
+                        peers,
+                    } => {
+                        let action = i32::from_be_bytes(buf[8..12].try_into().expect("4 bytes"));
+                        if action == Action::Connect as i32 {
+                            response.extend_from_slice(&(Action::Connect as i32).to_be_bytes());
+                            response.extend_from_slice(transaction_id);
+                            response.extend_from_slice(&connection_id.to_be_bytes());
+                        } else if buf[0..8] != connection_id.to_be_bytes() {
+                            response.extend_from_slice(&(Action::Error as i32).to_be_bytes());
+                            response.extend_from_slice(transaction_id);
+                            response.extend_from_slice(b"Connection ID missmatch");
+                        } else if action == Action::Announce as i32 {
+                            response.extend_from_slice(&action.to_be_bytes());
+                            response.extend_from_slice(transaction_id);
+                            for field in [interval, leechers, seeders] {
+                                response.extend_from_slice(&field.to_be_bytes());
+                            }
+                            for peer in peers {
+                                response.extend_from_slice(&peer.ip().octets());
+                                response.extend_from_slice(&peer.port().to_be_bytes());
+                            }
+                        } else {
+                            response.extend_from_slice(&action.to_be_bytes());
+                            response.extend_from_slice(transaction_id);
+                            // Only the torrent of the request is scraped, which was never completed.
+                            for field in [seeders, &0, leechers] {
+                                response.extend_from_slice(&field.to_be_bytes());
+                            }
+                        }
+                    }
+                    UdpBehaviour::Error(message) => {
+                        response.extend_from_slice(&(Action::Error as i32).to_be_bytes());
+                        response.extend_from_slice(transaction_id);
+                        response.extend_from_slice(message.as_bytes());
+                    }
+                    UdpBehaviour::Silent => continue,
+                }
+                let _ = socket.send_to(&response, from).await;
+            }
+        });
+
+        Ok(Self {
+            addr,
+            requests,
+            task,
+        })
+    }
+
+    /// The announce url of the tracker.
+    pub fn url(&self) -> String {
+        format!("udp://{}/announce", self.addr)
+    }
+
+    /// Number of requests received so far.
+    pub fn requests(&self) -> usize {
+        *self.requests.lock().expect("poisoned")
+    }
+}
+
+impl Drop for MockUdpTracker {
+    fn drop(&mut self) {
+        self.task.abort();
+    }
+}
+
+/// An HTTP tracker running in a background task which answers every request with the same
+/// bencoded body, or with the body chosen for the request by [`MockHttpTracker::start_with`].
+#[derive(Debug)]
+pub struct MockHttpTracker {
+    addr: SocketAddr,
+    requests: Arc<Mutex<Vec<String>>>,
+    task: JoinHandle<()>,
+}
+
+impl MockHttpTracker {
+    /// Binds a listener on localhost and starts answering requests with the provided body.
+    pub async fn start(body: impl Into<Vec<u8>>) -> Result<Self> {
+        let body = body.into();
+        Self::start_with(move |_| body.clone()).await
+    }
+
+    /// Same as [`MockHttpTracker::start`] but answers every request with the body returned by
+    /// `respond` for the request target (path and query).
+    pub async fn start_with<F>(respond: F) -> Result<Self>
+    where
+        F: Fn(&str) -> Vec<u8> + Send + Sync + 'static,
+    {
+        let listener = TcpListener::bind((Ipv4Addr::LOCALHOST, 0)).await?;
+        let addr = listener.local_addr()?;
+        let respond = Arc::new(respond);
+        let requests = Arc::new(Mutex::new(Vec::new()));
+
+        let log = Arc::clone(&requests);
+        let task = tokio::spawn(async move {
+            while let Ok((mut stream, _)) = listener.accept().await {
+                let respond = Arc::clone(&respond);
+                let log = Arc::clone(&log);
+                tokio::spawn(async move {
+                    let mut buf = vec![0_u8; 8192];
+                    let Ok(len) = stream.read(&mut buf).await else {
+                        return;
+                    };
+
+                    // Only the request target of the request line is of interest.
+                    let request = String::from_utf8_lossy(&buf[..len]);
+                    let target = request.split_whitespace().nth(1).unwrap_or_default();
+                    log.lock().expect("poisoned").push(target.to_string());
+                    let body = respond(target);
+
+                    let header = format!(
+                        "HTTP/1.1 200 OK\r\nContent-Type: text/plain\r\nContent-Length: {}\r\nConnection: close\r\n\r\n",
+                        body.len()
+                    );
+                    let _ = stream.write_all(header.as_bytes()).await;
+                    let _ = stream.write_all(&body).await;
+                });
+            }
+        });
+
+        Ok(Self {
+            addr,
+            requests,
+            task,
+        })
+    }
+
+    /// The announce url of the tracker.
+    pub fn url(&self) -> String {
+        format!("http://{}/announce", self.addr)
+    }
+
+    /// The address the tracker listens on.
+    pub fn addr(&self) -> SocketAddr {
+        self.addr
+    }
+
+    /// The request targets (path and query) received so far.
+    pub fn requests(&self) -> Vec<String> {
+        self.requests.lock().expect("poisoned").clone()
+    }
+}
+
+impl Drop for MockHttpTracker {
+    fn drop(&mut self) {
+        self.task.abort();
+    }
+}
+
+/// A peer running in a background task which accepts a single connection, completes the
+/// handshake and then sends the scripted message frames in order.
+#[derive(Debug)]
+pub struct ScriptedPeer {
+    addr: SocketAddr,
+    task: JoinHandle<Result<()>>,
+}
+
+impl ScriptedPeer {
+    /// Binds a listener on localhost for a peer with the provided identity.
+    ///
+    /// The handshake of the connecting peer must carry the same `info_hash`, otherwise the
+    /// connection is dropped.
+    pub async fn start(
+        info_hash: [u8; 20],
+        peer_id: [u8; 20],
+        script: Vec<Vec<u8>>,
+    ) -> Result<Self> {
+        let listener = TcpListener::bind((Ipv4Addr::LOCALHOST, 0)).await?;
+        let addr = listener.local_addr()?;
+
+        let task = tokio::spawn(async move {
+            let (mut stream, _) = listener.accept().await?;
+
+            let mut handshake = [0_u8; HANDSHAKE_LENGTH];
+            stream.read_exact(&mut handshake).await?;
+            if handshake[28..48] != info_hash {
+                bail!("Handshake with a different info_hash")
+            }
+            stream
+                .write_all(&handshake_bytes(info_hash, peer_id))
+                .await?;
+
+            let mut transport = FramedTransport::new(stream);
+            for frame in script {
+                transport.send(&frame).await?;
+            }
+            Ok(())
+        });
+
+        Ok(Self { addr, task })
+    }
+
+    /// The address the peer listens on.
+    pub fn addr(&self) -> SocketAddr {
+        self.addr
+    }
+
+    /// Waits for the peer to finish its script.
+    pub async fn finish(self) -> Result<()> {
+        self.task.await?
+    }
+}
+
+/// Builds the handshake of the peer wire protocol, with all the reserved bits unset.
+pub fn handshake_bytes(info_hash: [u8; 20], peer_id: [u8; 20]) -> [u8; HANDSHAKE_LENGTH] {
+    Handshake::new(info_hash, peer_id).to_bytes()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::meta_info::InfoHash;
+    use crate::sources::{Tracker, TrackerError, TrackerResponse};
+    use crate::{PeerID, Progress};
+    use tokio::net::TcpStream;
+
+    const PROGRESS: Progress = Progress {
+        uploaded: 0,
+        downloaded: 0,
+        left: 10,
+    };
+
+    #[tokio::test]
+    async fn test_mock_udp_tracker() {
+        let info_hash = InfoHash::new(b"test").as_encoded();
+
+        let mock = MockUdpTracker::start(UdpBehaviour::Connect { connection_id: 42 })
+            .await
+            .unwrap();
+        let request = Tracker::new(&mock.url())
+            .generate_request(info_hash, PeerID::default(), PROGRESS)
+            .await
+            .unwrap();
+        assert_eq!(request.connection_id(), Some(42));
+        assert_eq!(mock.requests(), 1);
+
+        let mock = MockUdpTracker::start(UdpBehaviour::Error("banned".into()))
+            .await
+            .unwrap();
+        let err = Tracker::new(&mock.url())
+            .generate_request(info_hash, PeerID::default(), PROGRESS)
+            .await
+            .unwrap_err();
+        assert_eq!(
+            err.downcast_ref::<TrackerError>(),
+            Some(&TrackerError::Failure("banned".into()))
+        );
+    }
+
+    #[tokio::test]
+    async fn test_mock_http_tracker() {
+        let mock = MockHttpTracker::start("d14:failure reason6:bannede")
+            .await
+            .unwrap();
+
+        let mut stream = TcpStream::connect(mock.addr()).await.unwrap();
+        stream
+            .write_all(b"GET /announce?port=6881 HTTP/1.1\r\n\r\n")
+            .await
+            .unwrap();
+        let mut response = Vec::new();
+        stream.read_to_end(&mut response).await.unwrap();
+
+        let body = response.split(|&b| b == b'\n').next_back().unwrap();
+        assert!(TrackerResponse::from_bytes(body).is_err());
+        assert_eq!(mock.requests(), ["/announce?port=6881"]);
+    }
+
+    #[tokio::test]
+    async fn test_scripted_peer() {
+        let info_hash = [1; 20];
+        let peer = ScriptedPeer::start(info_hash, [2; 20], vec![vec![1], vec![]])
+            .await
+            .unwrap();
+
+        let mut stream = TcpStream::connect(peer.addr()).await.unwrap();
+        stream
+            .write_all(&handshake_bytes(info_hash, [3; 20]))
+            .await
+            .unwrap();
+        let mut handshake = [0_u8; HANDSHAKE_LENGTH];
+        stream.read_exact(&mut handshake).await.unwrap();
+        assert_eq!(handshake, handshake_bytes(info_hash, [2; 20]));
+
+        let mut transport = FramedTransport::new(stream);
+        assert_eq!(transport.recv().await.unwrap().unwrap(), &[1][..]);
+        assert!(transport.recv().await.unwrap().unwrap().is_empty());
+        peer.finish().await.unwrap();
+    }
+}
@@ -50,6 +50,19 @@ mod getters {
         );
     }
 
+    #[test]
+    fn creation_date_utc() {
+        let arch = CLIENT.arch.meta_info();
+        assert_eq!(
+            arch.creation_date_utc().map(|date| date.timestamp()),
+            arch.creation_date_raw()
+        );
+        assert_eq!(
+            arch.creation_date_utc().map(|date| date.to_rfc2822()),
+            arch.creation_date()
+        );
+    }
+
     #[test]
     fn creation_date_raw() {
         assert_eq!(
@@ -123,12 +136,35 @@ mod getters {
 
     #[test]
     fn url_list() {
-        assert!(CLIENT.arch.meta_info().url_list().is_some());
-        assert!(CLIENT.mit.meta_info().url_list().is_some());
-        assert!(CLIENT.kali.meta_info().url_list().is_some());
-        assert!(CLIENT.mc.meta_info().url_list().is_some()); // in this the url-list is [""].
-                                                             // However, this should still return Some there is a entry of url list present in the
-                                                             // torrent file.
+        assert!(CLIENT.arch.meta_info().url_list().next().is_some());
+        assert!(CLIENT.mit.meta_info().url_list().next().is_some());
+        assert!(CLIENT.kali.meta_info().url_list().next().is_some());
+
+        // The url-list of this one is [""], which is not a web seed.
+        let mc = CLIENT.mc.meta_info();
+        assert_eq!(mc.raw_url_list(), Some(&vec![String::new()]));
+        assert_eq!(mc.url_list().count(), 0);
+        assert_eq!(mc.number_of_httpsources(), 0);
+        assert!(!mc.capabilities().web_seeds);
+    }
+
+    #[test]
+    fn all_trackers() {
+        assert_eq!(CLIENT.arch.meta_info().all_trackers().count(), 0);
+        assert_eq!(
+            CLIENT.kali.meta_info().all_trackers().collect::<Vec<_>>(),
+            [
+                (0, "http://tracker.kali.org:6969/announce"),
+                (0, "udp://tracker.kali.org:6969/announce")
+            ]
+        );
+        assert_eq!(
+            CLIENT.mit.meta_info().all_trackers().collect::<Vec<_>>(),
+            [
+                (0, "http://bt1.archive.org:6969/announce"),
+                (1, "http://bt2.archive.org:6969/announce")
+            ]
+        );
     }
 
     #[test]
@@ -196,4 +232,498 @@ mod calculators {
         assert_eq!(CLIENT.mit.number_of_files(), 154);
         assert_eq!(CLIENT.mc.number_of_files(), 131934);
     }
+
+    #[test]
+    fn file_tree_is_cached() {
+        let first = CLIENT.mc.file_tree_cached();
+        let second = CLIENT.mc.file_tree_cached();
+        assert!(std::sync::Arc::ptr_eq(&first, &second));
+        assert_eq!(
+            CLIENT.mc.file_tree().number_of_files(),
+            first.number_of_files()
+        );
+    }
+
+    #[test]
+    fn pieces_for_file() {
+        let arch = &CLIENT.arch;
+        let name = arch.meta_info().info().name();
+        assert_eq!(arch.pieces_for_file(name), Some(0..1911));
+        assert_eq!(arch.pieces_for_file("not/a/file"), None);
+
+        let mit = &CLIENT.mit;
+        let last = mit.file_spans().iter().rfind(|span| !span.padding).unwrap();
+        let pieces = mit.pieces_for_file(&last.path).unwrap();
+        assert_eq!(pieces.end, mit.meta_info().number_of_pieces());
+    }
+
+    #[test]
+    fn file_for_piece() {
+        let arch = &CLIENT.arch;
+        let files = arch.file_for_piece(0);
+        assert_eq!(files.len(), 1);
+        assert_eq!(files[0].path, arch.meta_info().info().name());
+        assert!(arch.file_for_piece(1911).is_empty());
+
+        // Every file maps to pieces which map back to the file.
+        let mit = &CLIENT.mit;
+        for span in mit.file_spans().iter().filter(|span| !span.padding) {
+            let pieces = mit.pieces_for_file(&span.path).unwrap();
+            for piece in [pieces.start, pieces.end - 1] {
+                assert!(mit.file_for_piece(piece).contains(&span));
+            }
+        }
+    }
+}
+
+// Hermetic tests against generated torrents.
+mod serialization {
+    use super::*;
+    use zung_torrent::meta_info::MetaInfo;
+
+    #[test]
+    fn to_bytes_roundtrip() {
+        for client in [&CLIENT.arch, &CLIENT.mit, &CLIENT.kali, &CLIENT.mc] {
+            let bytes = client.meta_info().to_bytes().unwrap();
+            let meta_info = MetaInfo::from_bytes(&bytes).unwrap();
+
+            assert_eq!(meta_info.to_bytes().unwrap(), bytes);
+            assert_eq!(meta_info.extra_keys(), client.meta_info().extra_keys());
+        }
+    }
+
+    #[test]
+    fn save_keeps_info_hash() {
+        for (i, client) in [&CLIENT.arch, &CLIENT.mit, &CLIENT.kali, &CLIENT.mc]
+            .into_iter()
+            .enumerate()
+        {
+            let path =
+                std::env::temp_dir().join(format!("zung-save-{}-{i}.torrent", std::process::id()));
+            client.meta_info().save(&path).unwrap();
+            let saved = zung_torrent::Client::new(&path).unwrap();
+            std::fs::remove_file(path).unwrap();
+
+            assert_eq!(saved.info_hash(), client.info_hash());
+            assert_eq!(saved.number_of_files(), client.number_of_files());
+        }
+    }
+}
+
+mod fixtures {
+    use zung_parsers::bencode::Value;
+    use zung_torrent::meta_info::{Files, MetaInfo, ParseWarning, TorrentVersion};
+    use zung_torrent::sources::DownloadSources;
+    use zung_torrent::testing::TorrentBuilder;
+    use zung_torrent::Client;
+
+    #[test]
+    fn single_file() {
+        let bytes = TorrentBuilder::single_file("single.bin", 40)
+            .announce("http://localhost/announce")
+            .creation_date(1711994429)
+            .build();
+        let meta_info = MetaInfo::from_bytes(&bytes).unwrap();
+
+        assert_eq!(meta_info.number_of_pieces(), 3);
+        assert_eq!(meta_info.info().name(), "single.bin");
+        assert_eq!(meta_info.creation_date_raw(), Some(1711994429));
+        assert!(matches!(
+            DownloadSources::new(&meta_info),
+            DownloadSources::Trackers { .. }
+        ));
+    }
+
+    #[test]
+    fn multi_file_with_padding() {
+        let builder = TorrentBuilder::multi_file("fixture-padding")
+            .file("a.txt", 10)
+            .padding_file(6)
+            .file("dir/b.txt", 40)
+            .announce_list(&[&["udp://localhost:6969"], &["http://localhost/announce"]])
+            .url_list(&["http://localhost/files/"]);
+
+        let dir = std::env::temp_dir();
+        let path = builder.write_to(&dir).unwrap();
+        let client = Client::new(&path).unwrap();
+        std::fs::remove_file(path).unwrap();
+
+        // Padding files are not counted.
+        assert_eq!(client.number_of_files(), 2);
+        assert_eq!(client.meta_info().number_of_pieces(), 4);
+        assert_eq!(client.pieces_for_file("a.txt"), Some(0..1));
+        assert_eq!(client.pieces_for_file("dir/b.txt"), Some(1..4));
+        assert!(client.sources().is_hybrid());
+    }
+
+    #[test]
+    fn empty_url_list() {
+        let bytes = TorrentBuilder::single_file("empty-url-list", 8)
+            .announce("http://localhost/announce")
+            .url_list(&[])
+            .build();
+        let meta_info = MetaInfo::from_bytes(&bytes).unwrap();
+
+        assert_eq!(meta_info.raw_url_list(), Some(&vec![]));
+        assert_eq!(meta_info.number_of_httpsources(), 0);
+    }
+
+    #[test]
+    fn url_list_skips_invalid_urls() {
+        let urls = [
+            "http://seed.example.com/",
+            "",
+            " \t ",
+            "relative/path/",
+            "/absolute/path/",
+            "mailto:seed@example.com",
+            " ftp://mirror.example.com/pub/ \n",
+        ];
+        let bytes = TorrentBuilder::single_file("web-seeds", 8)
+            .url_list(&urls)
+            .build();
+        let meta_info = MetaInfo::from_bytes(&bytes).unwrap();
+
+        assert_eq!(
+            meta_info.url_list().collect::<Vec<_>>(),
+            ["http://seed.example.com/", "ftp://mirror.example.com/pub/"]
+        );
+        assert_eq!(meta_info.raw_url_list().unwrap().len(), urls.len());
+        assert_eq!(meta_info.number_of_httpsources(), 2);
+        let sources = DownloadSources::new(&meta_info);
+        assert_eq!(sources.http_seeders().unwrap().len(), 2);
+
+        // Trackers without any valid web seed.
+        let bytes = TorrentBuilder::single_file("web-seeds", 8)
+            .announce("http://localhost/announce")
+            .url_list(&["  ", "seeds/"])
+            .build();
+        let meta_info = MetaInfo::from_bytes(&bytes).unwrap();
+        assert!(matches!(
+            DownloadSources::new(&meta_info),
+            DownloadSources::Trackers { .. }
+        ));
+    }
+
+    #[test]
+    fn flat_announce_list() {
+        let bytes = TorrentBuilder::single_file("flat", 8)
+            .announce_list(&[&["udp://a:1"]])
+            .build();
+        let meta_info = MetaInfo::from_bytes(&bytes).unwrap();
+        assert!(meta_info.warnings().is_empty());
+
+        // Some torrents have a list of urls instead of a list of tiers.
+        let mut torrent = zung_parsers::bencode::parse(&bytes).unwrap();
+        let Value::Dictionary(dictionary) = &mut torrent else {
+            panic!("Expected a dictionary");
+        };
+        dictionary.insert(
+            "announce-list".into(),
+            Value::List(vec![
+                Value::String("udp://a:1".to_string()),
+                Value::String("http://b/announce".to_string()),
+            ]),
+        );
+        let bytes = zung_parsers::bencode::to_bytes(&torrent).unwrap();
+        let meta_info = MetaInfo::from_bytes(&bytes).unwrap();
+
+        assert_eq!(meta_info.warnings(), [ParseWarning::FlatAnnounceList]);
+        assert_eq!(
+            meta_info.all_trackers().collect::<Vec<_>>(),
+            [(0, "udp://a:1"), (1, "http://b/announce")]
+        );
+        assert_eq!(meta_info.number_of_trackers(), 2);
+    }
+
+    #[test]
+    fn from_bytes_with_warnings() {
+        let bytes = TorrentBuilder::multi_file("odd")
+            .file("a.txt", 10)
+            .file_with_attr("b.sh", 10, "xz")
+            .file_with_attr("c.txt", 10, "hx")
+            .announce("")
+            .url_list(&["", "http://seed.example.com/", " seeds/"])
+            .creation_date(1711994429000)
+            .build();
+        let (meta_info, warnings) = MetaInfo::from_bytes_with_warnings(&bytes).unwrap();
+
+        assert_eq!(
+            warnings,
+            [
+                ParseWarning::EmptyTrackerUrl,
+                ParseWarning::InvalidWebSeed(String::new()),
+                ParseWarning::InvalidWebSeed(" seeds/".to_string()),
+                ParseWarning::InvalidCreationDate(1711994429000),
+                ParseWarning::UnknownFileAttr {
+                    path: "b.sh".to_string(),
+                    attr: "z".to_string()
+                },
+            ]
+        );
+        assert_eq!(meta_info.creation_date_utc(), None);
+        assert_eq!(meta_info.creation_date_raw(), Some(1711994429000));
+        assert_eq!(
+            warnings[3].to_string(),
+            "creation date 1711994429000 is not a valid date, ignored"
+        );
+
+        let bytes = TorrentBuilder::single_file("fine", 8)
+            .announce("http://localhost/announce")
+            .creation_date(1711994429)
+            .build();
+        let (_, warnings) = MetaInfo::from_bytes_with_warnings(&bytes).unwrap();
+        assert!(warnings.is_empty());
+    }
+
+    #[test]
+    fn all_trackers() {
+        let bytes = TorrentBuilder::single_file("tiers", 8)
+            .announce("http://ignored/announce")
+            .announce_list(&[&["udp://a:1", ""], &[], &["http://b/announce", "udp://c:2"]])
+            .build();
+        let meta_info = MetaInfo::from_bytes(&bytes).unwrap();
+        assert_eq!(
+            meta_info.all_trackers().collect::<Vec<_>>(),
+            [(0, "udp://a:1"), (2, "http://b/announce"), (2, "udp://c:2")]
+        );
+
+        // An empty announce-list falls back to announce.
+        let bytes = TorrentBuilder::single_file("empty-tiers", 8)
+            .announce("http://only/announce")
+            .announce_list(&[&[""]])
+            .build();
+        let meta_info = MetaInfo::from_bytes(&bytes).unwrap();
+        assert_eq!(
+            meta_info.all_trackers().collect::<Vec<_>>(),
+            [(0, "http://only/announce")]
+        );
+    }
+
+    #[test]
+    fn zero_length_files_and_empty_components() {
+        use zung_torrent::meta_info::TreeOptions;
+
+        let bytes = TorrentBuilder::multi_file("odd-paths")
+            .file("a.txt", 10)
+            .file("empty.txt", 0)
+            .file("dir//b.txt", 6)
+            .file("dir/b.txt", 6)
+            .file("", 4)
+            .build();
+        let meta_info = MetaInfo::from_bytes(&bytes).unwrap();
+
+        let tree = meta_info.build_file_tree();
+        let stats = tree.stats();
+        assert_eq!(tree.number_of_files(), 3);
+        assert_eq!(stats.files, 3);
+        assert_eq!(stats.total_size, 16);
+        assert_eq!(stats.empty_files, 1);
+        assert_eq!(stats.skipped, 2);
+        assert_eq!(stats.smallest, Some((String::from("dir/b.txt"), 6)));
+
+        let tree = meta_info.build_file_tree_with(TreeOptions {
+            skip_empty_files: true,
+        });
+        assert_eq!(tree.number_of_files(), 2);
+        assert_eq!(tree.stats().empty_files, 1);
+    }
+
+    #[test]
+    fn file_attrs() {
+        let bytes = TorrentBuilder::multi_file("attrs")
+            .file_with_attr("run.sh", 10, "x")
+            .file_with_attr(".config", 4, "xh")
+            .file_with_attr(".pad/6", 6, "ph")
+            .file("data.bin", 20)
+            .build();
+        let meta_info = MetaInfo::from_bytes(&bytes).unwrap();
+
+        let tree = meta_info.build_file_tree();
+        // Padding files are left out even when combined with other attrs.
+        assert_eq!(tree.number_of_files(), 3);
+        assert_eq!(tree.stats().total_size, 34);
+    }
+
+    #[test]
+    fn file_hashes_and_symlinks() {
+        let builder = TorrentBuilder::multi_file("bep47")
+            .piece_length(16)
+            .file_with_attr("bin/run", 10, "x")
+            .padding_file(6)
+            .file("data.bin", 20)
+            .symlink("latest", "bin/run")
+            .file_hashes();
+        let meta_info = MetaInfo::from_bytes(&builder.build()).unwrap();
+
+        let tree = meta_info.build_file_tree();
+        let link = tree.file("latest").unwrap();
+        assert!(link.attr.unwrap().is_symlink());
+        assert_eq!(
+            link.symlink_path.map(|path| path.join("/")),
+            Some("bin/run".to_string())
+        );
+        assert!(tree.file("bin").is_none());
+
+        let content = builder.content();
+        let data = tree.file("data.bin").unwrap();
+        assert!(data.sha1.unwrap().verify(&content[16..36]));
+        assert!(!data.sha1.unwrap().verify(&content[..20]));
+
+        let Files::MultiFile { files } = meta_info.info().files() else {
+            panic!("Expected a multi file torrent");
+        };
+        assert_eq!(files.iter().filter(|file| file.sha1().is_some()).count(), 3);
+        assert_eq!(files[3].symlink_path(), link.symlink_path);
+
+        let spans = meta_info.info().file_spans();
+        assert_eq!(spans[3].symlink.as_deref(), Some("bin/run"));
+        assert!(spans[0].executable);
+        assert!(!spans[2].executable);
+    }
+
+    #[test]
+    fn bytes_left() {
+        use zung_torrent::peers::Bitfield;
+
+        let meta_info = MetaInfo::from_bytes(
+            &TorrentBuilder::multi_file("left")
+                .piece_length(16)
+                .file("a", 20)
+                .file("b", 20)
+                .build(),
+        )
+        .unwrap();
+        assert_eq!(meta_info.number_of_pieces(), 3);
+        assert_eq!(meta_info.piece_size(2), Some(8));
+        assert_eq!(meta_info.piece_size(3), None);
+
+        let mut have = Bitfield::new(3);
+        assert_eq!(meta_info.bytes_left(&have), 40);
+        have.set(2);
+        assert_eq!(meta_info.bytes_left(&have), 32);
+        have.set(0);
+        have.set(1);
+        assert_eq!(meta_info.bytes_left(&have), 0);
+    }
+
+    #[test]
+    fn extra_keys() {
+        let bytes = b"d8:announce25:http://localhost/announce11:collectionsl4:demoe\
+4:infod6:lengthi8e4:name5:extra12:piece lengthi16e6:pieces20:aaaaaaaaaaaaaaaaaaaa\
+6:sourcei3ee12:x_cross_seed4:seede";
+        let meta_info = MetaInfo::from_bytes(bytes).unwrap();
+
+        let extra = meta_info.extra_keys();
+        assert_eq!(extra.len(), 2);
+        assert_eq!(extra["x_cross_seed"], Value::String("seed".into()));
+        assert_eq!(
+            extra["collections"],
+            Value::List(vec![Value::String("demo".into())])
+        );
+        // The keys of the `files` enum are not unknown.
+        assert_eq!(meta_info.info().extra_keys().len(), 1);
+        assert_eq!(meta_info.info().extra_keys()["source"], Value::Integer(3));
+        assert_eq!(meta_info.info().name(), "extra");
+    }
+
+    #[test]
+    fn non_utf8_file_names() {
+        let bytes = b"d4:infod5:filesld6:lengthi8e4:pathl4:caf\xe95:a.txteed6:lengthi8e\
+4:pathl5:b.txteee4:name4:\xff\xfeok12:piece lengthi16e6:pieces20:aaaaaaaaaaaaaaaaaaaaee";
+        let meta_info = MetaInfo::from_bytes(bytes).unwrap();
+
+        assert_eq!(meta_info.info().name(), "\u{FFFD}\u{FFFD}ok");
+        assert_eq!(meta_info.info().name_bytes(), b"\xff\xfeok");
+        let Files::MultiFile { files } = meta_info.info().files() else {
+            panic!("Expected a multi file torrent");
+        };
+        assert_eq!(files[0].path()[0], "caf\u{FFFD}");
+        assert_eq!(files[0].path()[0].as_bytes(), b"caf\xe9");
+        assert!(!files[1].path()[0].is_lossy());
+
+        let tree = meta_info.build_file_tree();
+        assert_eq!(tree.number_of_files(), 2);
+        assert_eq!(tree.file("caf\u{FFFD}/a.txt").unwrap().length, 8);
+
+        assert_eq!(
+            meta_info.warnings(),
+            [
+                ParseWarning::NonUtf8FileName("\u{FFFD}\u{FFFD}ok".to_string()),
+                ParseWarning::NonUtf8FileName("caf\u{FFFD}/a.txt".to_string()),
+            ]
+        );
+
+        // The original bytes are written back, so the info hash does not change.
+        assert_eq!(meta_info.to_bytes().unwrap(), bytes);
+    }
+
+    #[test]
+    fn capabilities() {
+        let bytes = TorrentBuilder::single_file("plain.bin", 40).build();
+        let capabilities = MetaInfo::from_bytes(&bytes).unwrap().capabilities();
+        assert_eq!(capabilities.version, TorrentVersion::V1);
+        assert!(!capabilities.padded && !capabilities.private && !capabilities.web_seeds);
+        assert_eq!(capabilities.to_string(), "v1");
+
+        let bytes = TorrentBuilder::multi_file("hybrid")
+            .file("a.txt", 10)
+            .padding_file(6)
+            .file("b.txt", 20)
+            .url_list(&["http://localhost/files/"])
+            .private()
+            .hybrid()
+            .build();
+        let meta_info = MetaInfo::from_bytes(&bytes).unwrap();
+        assert!(meta_info.is_private());
+        assert_eq!(
+            meta_info.capabilities().to_string(),
+            "hybrid v1+v2, padded, private, web seeds"
+        );
+    }
+
+    #[test]
+    fn client_summary() {
+        let builder = TorrentBuilder::multi_file("summary")
+            .file("a.txt", 10)
+            .padding_file(6)
+            .file("dir/b.txt", 40)
+            .announce("udp://localhost:6969/announce")
+            .creation_date(1711994429);
+
+        let path = builder.write_to(std::env::temp_dir()).unwrap();
+        let client = Client::new(&path).unwrap();
+        std::fs::remove_file(path).unwrap();
+
+        let summary = client.summary();
+        assert_eq!(summary.file_name, "summary.torrent");
+        assert_eq!(summary.name, "summary");
+        assert_eq!(&summary.info_hash, client.info_hash());
+        assert_eq!(summary.number_of_pieces, 4);
+        assert_eq!(summary.content_length, 56);
+        assert_eq!(summary.number_of_files, 2);
+        assert_eq!(summary.number_of_trackers, 1);
+        assert_eq!(summary.created_on.unwrap().timestamp(), 1711994429);
+        assert!(summary.capabilities.padded);
+
+        let json = serde_json::to_value(&summary).unwrap();
+        assert_eq!(json["info_hash"], client.info_hash().to_string());
+        assert_eq!(json["capabilities"]["version"], "v1");
+        assert!(summary.to_string().contains("Number of Files: 2"));
+    }
+
+    #[test]
+    fn empty_url_list_roundtrip() {
+        for urls in [&[][..], &[""]] {
+            let bytes = TorrentBuilder::single_file("empty.bin", 10)
+                .url_list(urls)
+                .build();
+            let meta_info = MetaInfo::from_bytes(&bytes).unwrap();
+
+            // An empty list is kept, and the missing keys are not written.
+            assert_eq!(meta_info.raw_url_list().unwrap().len(), urls.len());
+            assert_eq!(meta_info.announce(), None);
+            assert_eq!(meta_info.to_bytes().unwrap(), bytes);
+        }
+    }
 }
@@ -1,6 +1,16 @@
 use futures::StreamExt;
 use utilities::torrent::CLIENT;
-use zung_torrent::sources::DownloadSources;
+use zung_torrent::ipfilter::IpFilter;
+use zung_torrent::meta_info::MetaInfo;
+use zung_torrent::peers::Bitfield;
+use zung_torrent::sources::{DownloadSources, SourceKind, SourceList, TrackerPeer};
+use zung_torrent::testing::TorrentBuilder;
+use zung_torrent::{Client, Progress};
+
+// The progress of a client which has nothing of the torrent yet.
+fn nothing(client: &Client) -> Progress {
+    client.progress(&Bitfield::new(client.meta_info().number_of_pieces()))
+}
 
 #[test]
 fn source_types() {
@@ -46,19 +56,84 @@ fn mit_source() {
     }
 }
 
+#[test]
+fn source_lists() {
+    let mit = CLIENT.mit.sources();
+
+    let trackers = mit.trackers().expect("This should be some");
+    assert_eq!(trackers.kind(), SourceKind::Trackers);
+    assert_eq!(trackers.display_rows().len(), SourceList::len(trackers));
+
+    let http_seeders = mit.http_seeders().expect("This should be some");
+    assert_eq!(http_seeders.kind(), SourceKind::HttpSeeders);
+    for (row, (url, seeder)) in http_seeders.display_rows().iter().zip(http_seeders.iter()) {
+        assert_eq!(row.source, url.as_ref());
+        assert_eq!(row.details, seeder.urls());
+    }
+}
+
+#[tokio::test]
+async fn sources_outlive_the_meta_info() {
+    let sources = {
+        let bytes = TorrentBuilder::multi_file("seeded")
+            .file("a.txt", 10)
+            .url_list(&["http://localhost/files/"])
+            .build();
+        let meta_info = MetaInfo::from_bytes(&bytes).unwrap();
+        DownloadSources::new(&meta_info)
+    };
+
+    // The sources do not borrow the meta info and can be moved into a task.
+    let urls = tokio::spawn(async move {
+        let http_seeders = sources.http_seeders().expect("This should be some");
+        http_seeders[0].1.urls().to_vec()
+    })
+    .await
+    .unwrap();
+    assert_eq!(urls, ["http://localhost/files/seeded/a.txt"]);
+}
+
+#[test]
+fn blocked_peers_are_filtered() {
+    let path = TorrentBuilder::single_file("filtered.bin", 10)
+        .write_to(std::env::temp_dir())
+        .unwrap();
+    let mut client = zung_torrent::Client::new(&path).unwrap();
+    std::fs::remove_file(path).unwrap();
+
+    let peer = |addr: &str| TrackerPeer {
+        addr: addr.parse().unwrap(),
+        peer_id: None,
+    };
+    let mut peers = vec![peer("10.0.0.1:6881"), peer("8.8.8.8:6881")];
+
+    // Nothing is filtered without a filter.
+    assert_eq!(client.filter_peers(&mut peers), 0);
+
+    client.set_ip_filter(Some(
+        IpFilter::parse("Private:10.0.0.0-10.255.255.255").into(),
+    ));
+    assert_eq!(client.filter_peers(&mut peers), 1);
+    assert_eq!(peers, [peer("8.8.8.8:6881")]);
+
+    assert!(!client.admits("10.1.2.3:51413".parse().unwrap()));
+    assert!(client.admits("1.1.1.1:51413".parse().unwrap()));
+    assert_eq!(client.stats().filtered_peers(), 2);
+}
+
 #[tokio::test]
 async fn kali_source() {
     let kali = &CLIENT.kali;
 
     let mut list = kali
         .sources()
-        .tracker_requests(kali.info_hash().as_encoded(), kali.peer_id())
+        .tracker_requests(kali.info_hash().as_encoded(), kali.peer_id(), nothing(kali))
         .unwrap();
 
     // Waits for ALL futures to complete
     while let Some(result) = list.next().await {
-        let Ok(a) = result else { continue };
-        if let Ok(a) = a {
+        let Ok(outcome) = result else { continue };
+        if let Ok(a) = outcome.result {
             if a.is_http() {
                 assert!(a
                     .to_url()
@@ -70,3 +145,25 @@ async fn kali_source() {
         }
     }
 }
+
+#[tokio::test]
+async fn udp_tracker_request_against_mock() {
+    use zung_torrent::sources::Tracker;
+    use zung_torrent::testing::{MockUdpTracker, UdpBehaviour};
+
+    let kali = &CLIENT.kali;
+    let mock = MockUdpTracker::start(UdpBehaviour::Connect {
+        connection_id: 0x1234,
+    })
+    .await
+    .unwrap();
+
+    let request = Tracker::new(&mock.url())
+        .generate_request(kali.info_hash().as_encoded(), kali.peer_id(), nothing(kali))
+        .await
+        .unwrap();
+
+    assert!(request.is_udp());
+    assert_eq!(request.connection_id(), Some(0x1234));
+    assert_eq!(mock.requests(), 1);
+}